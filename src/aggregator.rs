@@ -1,19 +1,517 @@
-/// Aggregates a list of optional price points into a single optional average price.
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use serde::Serialize;
+
+use crate::config::{AggregationMethod, AggregationSettings, OutlierFilter};
+
+/// `price` as an `f64`, for the spreads, deviations and metrics that compare prices rather
+/// than publish them.
+pub fn as_f64(price: Decimal) -> f64 {
+    // Every `Decimal` is within `f64`'s range.
+    price.to_f64().unwrap_or(f64::NAN)
+}
+
+/// One source's contribution to an aggregate.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregationInput {
+    pub source: String,
+    /// Exchange ticker that was matched for the symbol, if any.
+    pub exchange_symbol: Option<String>,
+    pub price: Option<Decimal>,
+    /// Why the source's price was left out of the aggregate, if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded: Option<String>,
+    /// Relative weight of the price: the source's weight (`1` unless registered with
+    /// another), reduced while it recovers from an outage, times the 24h volume for
+    /// volume-weighted feeds.
+    pub weight: f64,
+    /// Trailing 24h volume in base currency, fetched for volume-weighted feeds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    /// Whether the price counted towards the aggregate, i.e. was valid and not excluded.
+    pub included: bool,
+    /// The venue adjustment `price` includes, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adjustment: Option<PriceAdjustment>,
+    /// Best bid and ask, fetched for feeds publishing them; adjusted like `price`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<Quote>,
+    /// When the source set the price (e.g. its last trade), by the source's own clock, if
+    /// it reported it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_at_ms: Option<i64>,
+}
+
+/// A venue's best bid and ask, or a feed's aggregate of them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Quote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+/// A configured venue premium or fee correction applied to a source price.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PriceAdjustment {
+    pub bps: f64,
+    /// The price as the source reported it.
+    pub raw_price: Decimal,
+}
+
+impl AggregationInput {
+    /// An input of full weight, not yet aggregated.
+    pub fn new(source: &str, exchange_symbol: Option<String>, price: Option<Decimal>) -> Self {
+        Self {
+            source: source.to_string(),
+            exchange_symbol,
+            price,
+            excluded: None,
+            weight: 1.0,
+            volume: None,
+            included: false,
+            adjustment: None,
+            quote: None,
+            observed_at_ms: None,
+        }
+    }
+
+    /// Shifts the price and quote by `bps` basis points, recording the raw price. A zero
+    /// adjustment, one that is not a finite number or a missing price is left as is.
+    pub fn adjust(&mut self, bps: f64) {
+        let factor = Decimal::from_f64(bps).map(|bps| Decimal::ONE + bps / Decimal::from(10_000));
+        if let (Some(raw_price), Some(factor)) = (self.price.filter(|_| bps != 0.0), factor) {
+            self.price = Some(raw_price * factor);
+            self.adjustment = Some(PriceAdjustment { bps, raw_price });
+            if let Some(quote) = &mut self.quote {
+                quote.bid *= factor;
+                quote.ask *= factor;
+            }
+        }
+    }
+
+    /// The price, if it may go into the aggregate.
+    pub fn usable_price(&self) -> Option<Decimal> {
+        self.price.filter(|_| self.excluded.is_none())
+    }
+}
+
+/// How an aggregate was (or was not) produced from the source prices. Consumed by the
+/// publish gates, metrics and debug bundles.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregateResult {
+    pub method: String,
+    pub inputs: Vec<AggregationInput>,
+    /// `(max - min) / min` across the included inputs, in percent.
+    pub spread_pct: Option<f64>,
+    /// `None` when too few sources contributed.
+    pub result: Option<Decimal>,
+    /// How far the included prices lie from `result`: their weighted standard deviation
+    /// around it, in the feed's currency. `None` without a result or with a single price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl AggregateResult {
+    /// Inputs that counted towards the aggregate.
+    pub fn contributing(&self) -> usize {
+        self.inputs.iter().filter(|i| i.included).count()
+    }
+}
+
+/// One valid source price and the weight it carries in the aggregate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedSample {
+    pub price: Decimal,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AggregatedPrice {
+    pub price: Decimal,
+    /// Samples the price was computed from.
+    pub samples: usize,
+}
+
+/// A strategy combining a feed's valid source prices into one.
+pub trait Aggregator: Send + Sync {
+    /// Name recorded as the aggregation method in debug bundles, e.g. `median`.
+    fn name(&self) -> &'static str;
+
+    /// `None` when there are no samples or they carry no weight.
+    fn aggregate(&self, samples: &[WeightedSample]) -> Option<AggregatedPrice>;
+}
+
+/// Weighted arithmetic mean; with equal weights, the plain mean.
+pub struct Mean;
+
+/// Weighted median: the price at which half of the total weight lies on either side.
+pub struct Median;
+
+/// Mean of the samples left after dropping the `trim_pct` percent lowest and highest prices.
+/// The median sample always remains.
+pub struct TrimmedMean {
+    pub trim_pct: f64,
+}
+
+/// The mean in decimal, so prices keep their digits. `None` without weight, or if the
+/// weighted sum overflows.
+fn weighted_mean(samples: &[WeightedSample]) -> Option<Decimal> {
+    let mut sum = Decimal::ZERO;
+    let mut total_weight = Decimal::ZERO;
+    for sample in samples {
+        // A weight that is not a finite number has no decimal value, and counts as none.
+        let Some(weight) = Decimal::from_f64(sample.weight) else {
+            continue;
+        };
+        sum = sum.checked_add(sample.price.checked_mul(weight)?)?;
+        total_weight = total_weight.checked_add(weight)?;
+    }
+    if total_weight <= Decimal::ZERO {
+        return None;
+    }
+    sum.checked_div(total_weight)
+}
+
+fn by_price(samples: &[WeightedSample]) -> Vec<WeightedSample> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by_key(|s| s.price);
+    sorted
+}
+
+impl Aggregator for Mean {
+    fn name(&self) -> &'static str {
+        "mean"
+    }
+
+    fn aggregate(&self, samples: &[WeightedSample]) -> Option<AggregatedPrice> {
+        Some(AggregatedPrice {
+            price: weighted_mean(samples)?,
+            samples: samples.len(),
+        })
+    }
+}
+
+impl Aggregator for Median {
+    fn name(&self) -> &'static str {
+        "median"
+    }
+
+    fn aggregate(&self, samples: &[WeightedSample]) -> Option<AggregatedPrice> {
+        let sorted = by_price(samples);
+        let total_weight: f64 = sorted.iter().map(|s| s.weight).sum();
+        if total_weight <= 0.0 {
+            return None;
+        }
+        let half = total_weight / 2.0;
+        let tolerance = total_weight * 1e-12;
+        let mut cumulative = 0.0;
+        for (i, sample) in sorted.iter().enumerate() {
+            cumulative += sample.weight;
+            if (cumulative - half).abs() <= tolerance {
+                // Exactly half the weight at or below this price: split the difference with
+                // the next weighted price, as the plain median of an even count does.
+                let next = sorted[i + 1..].iter().find(|s| s.weight > 0.0);
+                let price = next.map_or(sample.price, |next| {
+                    (sample.price + next.price) / Decimal::TWO
+                });
+                return Some(AggregatedPrice {
+                    price,
+                    samples: samples.len(),
+                });
+            }
+            if cumulative > half {
+                return Some(AggregatedPrice {
+                    price: sample.price,
+                    samples: samples.len(),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl Aggregator for TrimmedMean {
+    fn name(&self) -> &'static str {
+        "trimmed_mean"
+    }
+
+    fn aggregate(&self, samples: &[WeightedSample]) -> Option<AggregatedPrice> {
+        let sorted = by_price(samples);
+        let max_trim = sorted.len().saturating_sub(1) / 2;
+        let trim = ((sorted.len() as f64 * self.trim_pct / 100.0).floor() as usize).min(max_trim);
+        let kept = &sorted[trim..sorted.len() - trim];
+        Some(AggregatedPrice {
+            price: weighted_mean(kept)?,
+            samples: kept.len(),
+        })
+    }
+}
+
+/// The aggregator `method` selects. Volume weighting happens in the weights handed to it,
+/// so `Vwap` combines them with the mean.
+pub fn for_method(
+    method: AggregationMethod,
+    settings: &AggregationSettings,
+) -> Box<dyn Aggregator> {
+    match method {
+        AggregationMethod::Mean | AggregationMethod::Vwap => Box::new(Mean),
+        AggregationMethod::Median => Box::new(Median),
+        AggregationMethod::TrimmedMean => Box::new(TrimmedMean {
+            trim_pct: settings.trim_pct,
+        }),
+    }
+}
+
+/// Aggregates the sources' prices into a single optional price.
 ///
-/// - Filters out `None` values (representing failures from a source).
-/// - If no valid prices remain, returns `None`.
-/// - Otherwise, calculates the arithmetic mean of the valid prices.
-pub fn aggregate_prices(price_options: &[Option<f64>]) -> Option<f64> {
-    let valid_prices: Vec<f64> = price_options
+/// - Leaves out inputs without a price (representing failures from a source) and excluded
+///   ones; the rest are marked `included`.
+/// - If fewer than `min_sources` (and at least one) inputs are included, there is no result.
+/// - Otherwise, combines the included prices with `aggregator`, each carrying its weight.
+///   Prices with a reduced weight still count towards `min_sources`.
+pub fn aggregate_prices(
+    aggregator: &dyn Aggregator,
+    method: String,
+    mut inputs: Vec<AggregationInput>,
+    min_sources: usize,
+) -> AggregateResult {
+    for input in &mut inputs {
+        input.included = input.usable_price().is_some();
+    }
+    let samples: Vec<WeightedSample> = inputs
         .iter()
-        .filter_map(|&opt_price| opt_price)
+        .filter_map(|i| {
+            i.usable_price().map(|price| WeightedSample {
+                price,
+                weight: i.weight,
+            })
+        })
         .collect();
-
-    if valid_prices.is_empty() {
+    let prices: Vec<Option<Decimal>> = samples.iter().map(|s| Some(s.price)).collect();
+    let result = if samples.is_empty() || samples.len() < min_sources {
         None
     } else {
-        let sum: f64 = valid_prices.iter().sum();
-        Some(sum / valid_prices.len() as f64)
+        aggregator
+            .aggregate(&samples)
+            .map(|aggregated| aggregated.price)
+    };
+    AggregateResult {
+        method,
+        spread_pct: source_spread_pct(&prices),
+        confidence: result.and_then(|result| weighted_std_dev(&samples, result)),
+        result,
+        inputs,
+    }
+}
+
+/// Weighted standard deviation of `samples` around `center`. `None` for fewer than two
+/// samples, which leave nothing to disagree, or samples without weight.
+fn weighted_std_dev(samples: &[WeightedSample], center: Decimal) -> Option<f64> {
+    let total: f64 = samples.iter().map(|s| s.weight).sum();
+    if samples.len() < 2 || total <= 0.0 {
+        return None;
+    }
+    let variance = samples
+        .iter()
+        .map(|s| s.weight * as_f64(s.price - center).powi(2))
+        .sum::<f64>()
+        / total;
+    Some(variance.sqrt())
+}
+
+/// Each price's reported 24h volume as its weight, for a volume-weighted mean. `None` when
+/// a valid price has no usable volume or the volumes sum to zero: weighting by partial
+/// volumes would skew the mean towards the venues that happened to report.
+pub fn volume_weights(
+    price_options: &[Option<Decimal>],
+    volumes: &[Option<f64>],
+) -> Option<Vec<f64>> {
+    let mut total = 0.0;
+    let weights = price_options
+        .iter()
+        .zip(volumes)
+        .map(|(price, volume)| match (price, volume) {
+            (None, _) => Some(0.0),
+            (Some(_), Some(v)) if v.is_finite() && *v >= 0.0 => {
+                total += v;
+                Some(*v)
+            }
+            (Some(_), _) => None,
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    (total > 0.0).then_some(weights)
+}
+
+/// Aggregates the bids and the asks of the inputs that counted towards `aggregate`, each
+/// side on its own with `aggregator` and the inputs' weights. Crossed venue quotes are left
+/// out. Fails when fewer than `min_sources` quotes remain, or when the aggregate bid and
+/// ask do not bracket the aggregate price.
+pub fn aggregate_quotes(
+    aggregator: &dyn Aggregator,
+    aggregate: &AggregateResult,
+    min_sources: usize,
+) -> Result<Quote, String> {
+    let mid = aggregate
+        .result
+        .ok_or_else(|| "no aggregate price".to_string())?;
+    let quoted: Vec<(Quote, f64)> = aggregate
+        .inputs
+        .iter()
+        .filter(|input| input.included)
+        .filter_map(|input| Some((input.quote?, input.weight)))
+        .filter(|(quote, _)| quote.bid > Decimal::ZERO && quote.bid <= quote.ask)
+        .collect();
+    if quoted.is_empty() || quoted.len() < min_sources {
+        return Err(format!(
+            "not enough quotes ({} of {} required sources)",
+            quoted.len(),
+            min_sources
+        ));
+    }
+    let side = |price: fn(&Quote) -> Decimal| {
+        let samples: Vec<WeightedSample> = quoted
+            .iter()
+            .map(|(quote, weight)| WeightedSample {
+                price: price(quote),
+                weight: *weight,
+            })
+            .collect();
+        aggregator.aggregate(&samples).map(|a| a.price)
+    };
+    let (Some(bid), Some(ask)) = (side(|q| q.bid), side(|q| q.ask)) else {
+        return Err("quotes carry no weight".to_string());
+    };
+    if bid > mid || mid > ask {
+        return Err(format!(
+            "aggregate bid {} and ask {} do not bracket {}",
+            bid, ask, mid
+        ));
+    }
+    Ok(Quote { bid, ask })
+}
+
+/// Spread between the highest and lowest valid price, as a percentage of the lowest.
+///
+/// Returns `None` when fewer than two valid prices are available, since there is nothing
+/// to compare. A lowest price of zero or less is an infinite spread: a venue quoting it is
+/// the outage the spread check is for.
+pub fn source_spread_pct(price_options: &[Option<Decimal>]) -> Option<f64> {
+    let mut valid_prices = price_options.iter().filter_map(|&opt_price| opt_price);
+    let first = valid_prices.next()?;
+    let (min, max, count) = valid_prices.fold((first, first, 1), |(min, max, n), p| {
+        (min.min(p), max.max(p), n + 1)
+    });
+    if count < 2 {
+        return None;
+    }
+    if min <= Decimal::ZERO {
+        return Some(f64::INFINITY);
+    }
+    Some(as_f64((max - min) / min * Decimal::ONE_HUNDRED))
+}
+
+/// Fewest valid prices outlier rejection works on: of two, neither can be singled out.
+pub const MIN_OUTLIER_SAMPLES: usize = 3;
+
+fn median(sorted: &[Decimal]) -> Decimal {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / Decimal::TWO
+    } else {
+        sorted[mid]
+    }
+}
+
+fn sorted(values: impl Iterator<Item = Decimal>) -> Vec<Decimal> {
+    let mut values: Vec<Decimal> = values.collect();
+    values.sort();
+    values
+}
+
+/// Why each price must be left out of the aggregate as an outlier, by position in
+/// `price_options`. Nothing is rejected with fewer than `MIN_OUTLIER_SAMPLES` valid prices.
+pub fn outlier_rejections(
+    price_options: &[Option<Decimal>],
+    filter: OutlierFilter,
+) -> Vec<Option<String>> {
+    let valid = sorted(price_options.iter().filter_map(|&p| p));
+    if valid.len() < MIN_OUTLIER_SAMPLES {
+        return vec![None; price_options.len()];
+    }
+    let median = median(&valid);
+    let mad = median_abs_deviation(&valid, median);
+    price_options
+        .iter()
+        .map(|&price| {
+            let price = price?;
+            let deviation = as_f64((price - median).abs());
+            let mad = as_f64(mad);
+            match filter {
+                OutlierFilter::Mad { max_deviations } if deviation > max_deviations * mad => {
+                    Some(if mad > 0.0 {
+                        format!(
+                            "outlier: {} is {:.1} MADs from median {}",
+                            price,
+                            deviation / mad,
+                            median
+                        )
+                    } else {
+                        format!(
+                            "outlier: {} while the other sources agree on {}",
+                            price, median
+                        )
+                    })
+                }
+                OutlierFilter::Percent { max_deviation_pct }
+                    if median > Decimal::ZERO
+                        && deviation / as_f64(median) * 100.0 > max_deviation_pct =>
+                {
+                    Some(format!(
+                        "outlier: {} is {:.2}% from median {}",
+                        price,
+                        deviation / as_f64(median) * 100.0,
+                        median
+                    ))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn median_abs_deviation(sorted_prices: &[Decimal], median_price: Decimal) -> Decimal {
+    median(&sorted(
+        sorted_prices.iter().map(|p| (p - median_price).abs()),
+    ))
+}
+
+/// Displayed liquidity at the top of the book, in quote currency: the thinner of the best
+/// bid and the best ask, since either side alone lets a small order move the price.
+pub fn top_of_book_notional(bid_price: f64, bid_qty: f64, ask_price: f64, ask_qty: f64) -> f64 {
+    (bid_price * bid_qty).min(ask_price * ask_qty)
+}
+
+/// Why a source must be left out of the aggregate for lack of liquidity, if it must.
+/// An unknown depth excludes the source too, since the requirement cannot be checked.
+pub fn liquidity_exclusion(notional: Option<f64>, min_notional: f64) -> Option<String> {
+    match notional {
+        Some(notional) if notional >= min_notional => None,
+        Some(notional) => Some(format!(
+            "top-of-book liquidity {:.0} below minimum {:.0}",
+            notional, min_notional
+        )),
+        None => Some("top-of-book liquidity unknown".to_string()),
+    }
+}
+
+/// Why a source must be left out of the aggregate for a sample `age_ms` old, if it must.
+/// A sample without a timestamp is kept, since its age cannot be checked.
+pub fn staleness_exclusion(age_ms: Option<u64>, max_age_ms: u64) -> Option<String> {
+    match age_ms {
+        Some(age_ms) if age_ms > max_age_ms => Some(format!(
+            "sample {} ms old, older than max {} ms",
+            age_ms, max_age_ms
+        )),
+        _ => None,
     }
 }
 
@@ -23,58 +521,402 @@ mod tests {
 
     const DELTA: f64 = 1e-9; // For floating point comparisons
 
+    fn dec(value: f64) -> Decimal {
+        Decimal::from_f64(value).unwrap()
+    }
+
+    fn decs(prices: &[Option<f64>]) -> Vec<Option<Decimal>> {
+        prices.iter().map(|price| price.map(dec)).collect()
+    }
+
+    fn weighted(prices: &[Option<f64>], weights: &[f64], min_sources: usize) -> Option<f64> {
+        let inputs = decs(prices)
+            .into_iter()
+            .zip(weights)
+            .map(|(price, &weight)| AggregationInput {
+                weight,
+                ..AggregationInput::new("source", None, price)
+            })
+            .collect();
+        aggregate_prices(&Mean, "mean".to_string(), inputs, min_sources)
+            .result
+            .map(as_f64)
+    }
+
+    fn mean(prices: &[Option<f64>], min_sources: usize) -> Option<f64> {
+        weighted(prices, &vec![1.0; prices.len()], min_sources)
+    }
+
     #[test]
     fn test_aggregate_two_valid_prices() {
         let prices = [Some(100.0), Some(102.0)];
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - 101.0).abs() < DELTA);
     }
 
     #[test]
     fn test_aggregate_one_valid_one_none() {
         let prices = [Some(100.0), None];
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - 100.0).abs() < DELTA);
     }
 
     #[test]
     fn test_aggregate_one_none_one_valid() {
         let prices = [None, Some(102.0)];
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - 102.0).abs() < DELTA);
     }
 
     #[test]
     fn test_aggregate_two_none_prices() {
         let prices = [None, None];
-        assert_eq!(aggregate_prices(&prices), None);
+        assert_eq!(mean(&prices, 1), None);
     }
 
     #[test]
     fn test_aggregate_empty_input() {
         let prices: [Option<f64>; 0] = [];
-        assert_eq!(aggregate_prices(&prices), None);
+        assert_eq!(mean(&prices, 1), None);
     }
 
     #[test]
     fn test_aggregate_multiple_valid_prices() {
         let prices = [Some(10.0), Some(20.0), Some(30.0)];
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - 20.0).abs() < DELTA);
     }
 
     #[test]
     fn test_aggregate_single_valid_price() {
         let prices = [Some(123.45)];
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - 123.45).abs() < DELTA);
     }
 
+    #[test]
+    fn test_aggregate_requires_quorum() {
+        let prices = [Some(100.0), None, Some(102.0)];
+        assert_eq!(mean(&prices, 3), None);
+        let aggregated = mean(&prices, 2).unwrap();
+        assert!((aggregated - 101.0).abs() < DELTA);
+        assert_eq!(mean(&[None, None], 0), None);
+    }
+
+    #[test]
+    fn test_aggregate_weighted() {
+        let prices = [Some(100.0), Some(110.0), None];
+        let aggregated = weighted(&prices, &[1.0, 0.25, 1.0], 2).unwrap();
+        assert!((aggregated - 102.0).abs() < DELTA);
+        assert_eq!(weighted(&prices, &[0.0, 0.0, 1.0], 1), None);
+    }
+
+    #[test]
+    fn test_vwap() {
+        let prices = [Some(100.0), Some(104.0), None];
+        let weights = volume_weights(&decs(&prices), &[Some(300.0), Some(100.0), None]).unwrap();
+        assert_eq!(weights, [300.0, 100.0, 0.0]);
+        let aggregated = weighted(&prices, &weights, 2).unwrap();
+        assert!((aggregated - 101.0).abs() < DELTA);
+
+        assert_eq!(
+            volume_weights(&decs(&prices), &[Some(300.0), None, None]),
+            None
+        );
+        assert_eq!(
+            volume_weights(&decs(&prices), &[Some(0.0), Some(0.0), None]),
+            None
+        );
+    }
+
+    fn samples(prices: &[f64]) -> Vec<WeightedSample> {
+        prices
+            .iter()
+            .map(|&price| WeightedSample {
+                price: dec(price),
+                weight: 1.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mean_keeps_decimal_digits() {
+        let mean = |prices: &[f64]| Mean.aggregate(&samples(prices)).unwrap().price;
+        // In f64, 0.1 + 0.2 is 0.30000000000000004.
+        assert_eq!(mean(&[0.1, 0.2]), Decimal::new(15, 2));
+        assert_eq!(mean(&[1.0000005, 1.0000005]), Decimal::new(10_000_005, 7));
+        let weighted = [
+            WeightedSample {
+                price: dec(100.0),
+                weight: 1.0,
+            },
+            WeightedSample {
+                price: dec(110.0),
+                weight: 0.25,
+            },
+        ];
+        assert_eq!(Mean.aggregate(&weighted).unwrap().price, dec(102.0));
+    }
+
+    #[test]
+    fn test_aggregate_result_explains_contributions() {
+        let mut outlier =
+            AggregationInput::new("kraken", Some("XBTUSD".to_string()), Some(dec(1.0)));
+        outlier.excluded = Some("outlier".to_string());
+        let inputs = vec![
+            AggregationInput::new("binance", Some("BTCUSDT".to_string()), Some(dec(100.0))),
+            AggregationInput::new("coinbase", Some("BTC-USD".to_string()), Some(dec(102.0))),
+            AggregationInput::new("okx", None, None),
+            outlier,
+        ];
+        let aggregate = aggregate_prices(&Mean, "mean".to_string(), inputs, 2);
+        assert_eq!(aggregate.result, Some(dec(101.0)));
+        assert!((aggregate.spread_pct.unwrap() - 2.0).abs() < DELTA);
+        assert_eq!(aggregate.contributing(), 2);
+        assert!((aggregate.confidence.unwrap() - 1.0).abs() < DELTA);
+        let included: Vec<bool> = aggregate.inputs.iter().map(|i| i.included).collect();
+        assert_eq!(included, [true, true, false, false]);
+
+        let short = aggregate_prices(&Mean, "mean".to_string(), aggregate.inputs, 3);
+        assert_eq!(short.result, None);
+        assert_eq!(short.confidence, None);
+        assert_eq!(short.contributing(), 2);
+    }
+
+    #[test]
+    fn test_weighted_std_dev() {
+        let std_dev = |prices: &[f64], center| weighted_std_dev(&samples(prices), dec(center));
+        assert_eq!(std_dev(&[100.0], 100.0), None);
+        assert_eq!(std_dev(&[100.0, 100.0], 100.0), Some(0.0));
+        assert!(
+            (std_dev(&[98.0, 100.0, 102.0, 100.0], 100.0).unwrap() - 2f64.sqrt()).abs() < DELTA
+        );
+        // Around a median, the deviation of every price counts.
+        assert!((std_dev(&[100.0, 100.0, 103.0], 100.0).unwrap() - 3f64.sqrt()).abs() < DELTA);
+        let weighted = [
+            WeightedSample {
+                price: dec(99.0),
+                weight: 3.0,
+            },
+            WeightedSample {
+                price: dec(103.0),
+                weight: 1.0,
+            },
+        ];
+        assert!((weighted_std_dev(&weighted, dec(100.0)).unwrap() - 3f64.sqrt()).abs() < DELTA);
+        assert_eq!(
+            weighted_std_dev(
+                &weighted.map(|s| WeightedSample { weight: 0.0, ..s }),
+                dec(100.0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_median() {
+        let median = |prices: &[f64]| as_f64(Median.aggregate(&samples(prices)).unwrap().price);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(&[7.0]), 7.0);
+
+        assert_eq!(median(&[100.0, 150.0]), 125.0);
+        let mut weighted = samples(&[100.0, 150.0]);
+        weighted[1].weight = 0.25;
+        assert_eq!(Median.aggregate(&weighted).unwrap().price, dec(100.0));
+        assert_eq!(Median.aggregate(&[]), None);
+    }
+
+    #[test]
+    fn test_trimmed_mean() {
+        let trimmed = TrimmedMean { trim_pct: 20.0 };
+        let aggregated = trimmed
+            .aggregate(&samples(&[1.0, 100.0, 101.0, 102.0, 1_000.0]))
+            .unwrap();
+        assert_eq!(aggregated.price, dec(101.0));
+        assert_eq!(aggregated.samples, 3);
+        // Too few samples to trim 20% of: nothing is dropped.
+        let aggregated = trimmed.aggregate(&samples(&[100.0, 102.0])).unwrap();
+        assert_eq!(aggregated.price, dec(101.0));
+        // Trimming never drops the median.
+        let all = TrimmedMean { trim_pct: 49.0 };
+        assert_eq!(
+            all.aggregate(&samples(&[1.0, 2.0, 9.0])).unwrap().price,
+            dec(2.0)
+        );
+    }
+
+    #[test]
+    fn test_for_method() {
+        let settings = AggregationSettings::default();
+        let name = |method| for_method(method, &settings).name();
+        assert_eq!(name(AggregationMethod::Mean), "mean");
+        assert_eq!(name(AggregationMethod::Median), "median");
+        assert_eq!(name(AggregationMethod::TrimmedMean), "trimmed_mean");
+        assert_eq!(name(AggregationMethod::Vwap), "mean");
+    }
+
     #[test]
     fn test_with_real_world_like_values() {
         let prices = [Some(60100.50), Some(60102.30), None, Some(60098.10)];
         let expected_avg = (60100.50 + 60102.30 + 60098.10) / 3.0;
-        let aggregated = aggregate_prices(&prices).unwrap();
+        let aggregated = mean(&prices, 1).unwrap();
         assert!((aggregated - expected_avg).abs() < DELTA);
     }
+
+    #[test]
+    fn test_source_spread_pct() {
+        let spread =
+            source_spread_pct(&decs(&[Some(100.0), None, Some(102.0), Some(101.0)])).unwrap();
+        assert!((spread - 2.0).abs() < DELTA);
+        assert_eq!(source_spread_pct(&decs(&[Some(100.0), None])), None);
+        assert_eq!(source_spread_pct(&[]), None);
+        // A venue quoting zero or less exceeds any tolerance.
+        assert_eq!(
+            source_spread_pct(&decs(&[Some(100.0), Some(0.0)])),
+            Some(f64::INFINITY)
+        );
+        assert_eq!(
+            source_spread_pct(&decs(&[Some(-1.0), Some(100.0)])),
+            Some(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_mad_rejects_glitched_source() {
+        let mad = OutlierFilter::Mad {
+            max_deviations: 5.0,
+        };
+        let rejections = outlier_rejections(
+            &decs(&[Some(6.0), Some(60_000.0), None, Some(60_001.0)]),
+            mad,
+        );
+        assert_eq!(
+            rejections[0].as_deref(),
+            Some("outlier: 6 is 59994.0 MADs from median 60000")
+        );
+        assert_eq!(&rejections[1..], &[None, None, None]);
+
+        // Sources that agree exactly give a zero MAD; any other price is an outlier.
+        let rejections = outlier_rejections(&decs(&[Some(100.0), Some(100.0), Some(100.5)]), mad);
+        assert_eq!(rejections.iter().flatten().count(), 1);
+        assert!(rejections[2].is_some());
+
+        // Normal dispersion stays in.
+        let prices = [
+            Some(60_000.0),
+            Some(60_010.0),
+            Some(60_004.0),
+            Some(59_995.0),
+        ];
+        assert!(
+            outlier_rejections(&decs(&prices), mad)
+                .iter()
+                .all(Option::is_none)
+        );
+        // Two prices cannot be told apart.
+        assert!(
+            outlier_rejections(&decs(&[Some(6.0), Some(60_000.0)]), mad)
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn test_percent_rejection() {
+        let pct = OutlierFilter::Percent {
+            max_deviation_pct: 1.0,
+        };
+        let rejections = outlier_rejections(&decs(&[Some(100.0), Some(100.5), Some(98.0)]), pct);
+        assert_eq!(rejections[0], None);
+        assert_eq!(rejections[1], None);
+        assert_eq!(
+            rejections[2].as_deref(),
+            Some("outlier: 98 is 2.00% from median 100")
+        );
+    }
+
+    #[test]
+    fn test_top_of_book_notional_uses_thinner_side() {
+        let notional = top_of_book_notional(100.0, 5.0, 101.0, 2.0);
+        assert!((notional - 202.0).abs() < DELTA);
+    }
+
+    #[test]
+    fn test_liquidity_exclusion() {
+        assert_eq!(liquidity_exclusion(Some(50_000.0), 50_000.0), None);
+        assert_eq!(
+            liquidity_exclusion(Some(1_200.4), 50_000.0).as_deref(),
+            Some("top-of-book liquidity 1200 below minimum 50000")
+        );
+        assert!(liquidity_exclusion(None, 50_000.0).is_some());
+    }
+
+    #[test]
+    fn test_staleness_exclusion() {
+        assert_eq!(staleness_exclusion(Some(60_000), 60_000), None);
+        assert_eq!(
+            staleness_exclusion(Some(600_000), 60_000).as_deref(),
+            Some("sample 600000 ms old, older than max 60000 ms")
+        );
+        assert_eq!(staleness_exclusion(None, 60_000), None);
+    }
+
+    #[test]
+    fn test_venue_adjustment_keeps_raw_price() {
+        let mut input = AggregationInput::new("upbit", None, Some(dec(103.0)));
+        input.adjust(-150.0);
+        assert_eq!(input.price, Some(dec(101.455)));
+        assert_eq!(
+            input.adjustment,
+            Some(PriceAdjustment {
+                bps: -150.0,
+                raw_price: dec(103.0)
+            })
+        );
+
+        let mut unadjusted = AggregationInput::new("binance", None, Some(dec(100.0)));
+        unadjusted.adjust(0.0);
+        assert_eq!(unadjusted.price, Some(dec(100.0)));
+        assert!(unadjusted.adjustment.is_none());
+    }
+
+    #[test]
+    fn test_bid_and_ask_aggregate_separately() {
+        let quoted = |price, bid, ask| AggregationInput {
+            quote: Some(Quote {
+                bid: dec(bid),
+                ask: dec(ask),
+            }),
+            ..AggregationInput::new("source", None, Some(dec(price)))
+        };
+        let inputs = vec![
+            quoted(100.0, 99.0, 101.0),
+            quoted(102.0, 101.0, 103.0),
+            // Crossed book: left out.
+            quoted(101.0, 105.0, 95.0),
+            AggregationInput::new("source", None, Some(dec(101.0))),
+        ];
+        let aggregate = aggregate_prices(&Mean, "mean".to_string(), inputs, 1);
+        let quote = aggregate_quotes(&Mean, &aggregate, 2).unwrap();
+        assert_eq!(quote.bid, dec(100.0));
+        assert_eq!(quote.ask, dec(102.0));
+        assert_eq!(
+            aggregate_quotes(&Mean, &aggregate, 3).unwrap_err(),
+            "not enough quotes (2 of 3 required sources)"
+        );
+
+        // Last trades above every venue's ask.
+        let inputs = vec![quoted(110.0, 99.0, 101.0), quoted(112.0, 101.0, 103.0)];
+        let aggregate = aggregate_prices(&Mean, "mean".to_string(), inputs, 1);
+        assert!(
+            aggregate_quotes(&Mean, &aggregate, 1)
+                .unwrap_err()
+                .contains("do not bracket")
+        );
+
+        let mut adjusted = quoted(100.0, 99.0, 101.0);
+        adjusted.adjust(100.0);
+        assert_eq!(adjusted.quote.unwrap().ask, dec(102.01));
+    }
 }
@@ -0,0 +1,98 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::config::AlertSettings;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub severity: Severity,
+    /// Stable machine-readable identifier, e.g. `sla_breach`.
+    pub kind: &'static str,
+    pub symbol: String,
+    pub message: String,
+    pub timestamp_ms: u64,
+}
+
+/// Delivers alerts to the log and, when configured, to a webhook. Webhook deliveries run in
+/// the background, bounded by `max_pending`; when that many are in flight further alerts are
+/// only logged. Delivery failures never propagate into the publishing loop.
+pub struct Alerter {
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+    pending: Arc<Semaphore>,
+    max_pending: usize,
+    /// Critical alerts per symbol and kind since the last `take_incidents`.
+    incidents: Mutex<BTreeMap<(String, &'static str), u32>>,
+}
+
+impl Alerter {
+    pub fn new(settings: &AlertSettings, max_pending: usize) -> Self {
+        Self {
+            webhook_url: settings.webhook_url.clone(),
+            client: reqwest::Client::new(),
+            pending: Arc::new(Semaphore::new(max_pending)),
+            max_pending,
+            incidents: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Counts of the critical alerts sent since the last call, for the daily digest.
+    pub fn take_incidents(&self) -> BTreeMap<(String, &'static str), u32> {
+        std::mem::take(&mut *self.incidents.lock().unwrap())
+    }
+
+    /// Waits for webhook deliveries in flight, for commands that exit right after alerting.
+    pub async fn flush(&self) {
+        let all = u32::try_from(self.max_pending).unwrap_or(u32::MAX);
+        let _ = tokio::time::timeout(WEBHOOK_TIMEOUT, self.pending.acquire_many(all)).await;
+    }
+
+    pub fn send(&self, alert: &Alert) {
+        match alert.severity {
+            Severity::Critical => {
+                log::error!("ALERT [{}] {}: {}", alert.kind, alert.symbol, alert.message);
+                *self
+                    .incidents
+                    .lock()
+                    .unwrap()
+                    .entry((alert.symbol.clone(), alert.kind))
+                    .or_default() += 1;
+            }
+            Severity::Info => {
+                log::info!("ALERT [{}] {}: {}", alert.kind, alert.symbol, alert.message)
+            }
+        }
+
+        let Some(url) = self.webhook_url.clone() else {
+            return;
+        };
+        let Ok(permit) = self.pending.clone().try_acquire_owned() else {
+            log::warn!(
+                "Too many pending alert deliveries; not sending [{}] {} to webhook",
+                alert.kind,
+                alert.symbol
+            );
+            return;
+        };
+        let request = self.client.post(url).timeout(WEBHOOK_TIMEOUT).json(alert);
+        tokio::spawn(async move {
+            let result = request.send().await.and_then(|r| r.error_for_status());
+            if let Err(e) = result {
+                log::warn!("Failed to deliver alert to webhook: {}", e);
+            }
+            drop(permit);
+        });
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+
+use sui_sdk::types::base_types::ObjectID;
+
+use crate::config::{CallTemplate, Settings};
+use crate::schema::AssetInfo;
+use crate::sui_publisher::{self, MoveArg};
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits `s` at the commas outside angle brackets.
+fn split_type_args(s: &str) -> Result<Vec<&str>> {
+    let mut args = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow!("unbalanced '>'"))?
+            }
+            ',' if depth == 0 => {
+                args.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&s[start..]);
+    Ok(args)
+}
+
+/// `raw` as Sui renders type names: a full-length lowercase address, e.g.
+/// `0x0000…0002::sui::SUI` for `0x2::sui::SUI`, and type arguments normalized the same way.
+pub fn canonical_coin_type(raw: &str) -> Result<String> {
+    let invalid = |why: &str| anyhow!("'{}' is not a Move type ({})", raw, why);
+    let raw = raw.trim();
+    let (path, type_args) = match raw.split_once('<') {
+        Some((path, rest)) => {
+            let args = rest
+                .strip_suffix('>')
+                .ok_or_else(|| invalid("unclosed '<'"))?;
+            (path, Some(args))
+        }
+        None => (raw, None),
+    };
+    let mut parts = path.split("::");
+    let (Some(address), Some(module), Some(name), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("expected address::module::Name"));
+    };
+    let hex = address
+        .strip_prefix("0x")
+        .ok_or_else(|| invalid("the address needs a 0x prefix"))?;
+    if hex.is_empty() || hex.len() > 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("invalid address"));
+    }
+    if !is_identifier(module) || !is_identifier(name) {
+        return Err(invalid("invalid module or type name"));
+    }
+    let mut canonical = format!("0x{:0>64}::{}::{}", hex.to_ascii_lowercase(), module, name);
+    if let Some(type_args) = type_args {
+        let args = split_type_args(type_args)
+            .map_err(|e| invalid(&e.to_string()))?
+            .into_iter()
+            .map(canonical_coin_type)
+            .collect::<Result<Vec<_>>>()?;
+        canonical.push('<');
+        canonical.push_str(&args.join(", "));
+        canonical.push('>');
+    }
+    Ok(canonical)
+}
+
+/// The `[assets]` coin types, with the feed pricing each and that feed's PriceObject from
+/// `sui.known_objects_file`, read afresh so objects created since startup are included.
+pub fn list(settings: &Settings) -> Result<Vec<AssetInfo>> {
+    let objects = sui_publisher::load_known_objects(&settings.sui.known_objects_file)?;
+    Ok(settings
+        .assets
+        .iter()
+        .map(|(coin_type, asset)| AssetInfo {
+            coin_type: coin_type.clone(),
+            feed: asset.feed.clone(),
+            decimals: asset.decimals,
+            object_id: objects.get(&asset.feed).map(ObjectID::to_string),
+        })
+        .collect())
+}
+
+/// The asset of `coin_type`, written in any form `canonical_coin_type` accepts.
+#[cfg(feature = "http-api")]
+pub fn lookup(settings: &Settings, coin_type: &str) -> Result<Option<AssetInfo>> {
+    let coin_type = canonical_coin_type(coin_type)?;
+    Ok(list(settings)?
+        .into_iter()
+        .find(|a| a.coin_type == coin_type))
+}
+
+/// Expands `template.args` for registering `asset` on-chain. `{coin_type}` and `{feed}`
+/// are passed as strings, `{decimals}` as a `u8`, `{price_object}` as the feed's
+/// PriceObject, and `0x...` values as object IDs.
+pub fn resolve_args(template: &CallTemplate, asset: &AssetInfo) -> Result<Vec<MoveArg>> {
+    template
+        .args
+        .iter()
+        .map(|arg| match arg.as_str() {
+            "{coin_type}" => Ok(MoveArg::String(asset.coin_type.clone())),
+            "{feed}" => Ok(MoveArg::String(asset.feed.clone())),
+            "{decimals}" => asset.decimals.map(MoveArg::U8).ok_or_else(|| {
+                anyhow!(
+                    "{} needs the decimals of {}",
+                    template.function,
+                    asset.coin_type
+                )
+            }),
+            "{price_object}" => {
+                let object_id = asset.object_id.as_deref().ok_or_else(|| {
+                    anyhow!(
+                        "{} has no PriceObject yet; run `bootstrap` first",
+                        asset.feed
+                    )
+                })?;
+                ObjectID::from_str(object_id)
+                    .map(MoveArg::Object)
+                    .map_err(|e| anyhow!("Invalid object ID '{}': {}", object_id, e))
+            }
+            literal if literal.starts_with("0x") => ObjectID::from_str(literal)
+                .map(MoveArg::Object)
+                .map_err(|e| anyhow!("Invalid object ID '{}': {}", literal, e)),
+            other => Err(anyhow!(
+                "Unsupported argument '{}' in {}; expected {{coin_type}}, {{feed}}, \
+                 {{decimals}}, {{price_object}} or an object ID",
+                other,
+                template.function
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUI: &str =
+        "0x0000000000000000000000000000000000000000000000000000000000000002::sui::SUI";
+
+    #[test]
+    fn test_canonical_coin_types() {
+        assert_eq!(canonical_coin_type("0x2::sui::SUI").unwrap(), SUI);
+        assert_eq!(canonical_coin_type(&format!(" {} ", SUI)).unwrap(), SUI);
+        assert_eq!(
+            canonical_coin_type("0xA1::pool::LP<0x2::sui::SUI,0xb::usdc::USDC>").unwrap(),
+            format!(
+                "0x{:0>64}::pool::LP<{}, 0x{:0>64}::usdc::USDC>",
+                "a1", SUI, "b"
+            )
+        );
+        for raw in [
+            "SUI",
+            "0x2::sui",
+            "2::sui::SUI",
+            "0xzz::sui::SUI",
+            "0x2::sui::SUI<",
+        ] {
+            assert!(canonical_coin_type(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_resolve_args() {
+        let template = CallTemplate {
+            module: "asset_registry".to_string(),
+            function: "register".to_string(),
+            args: [
+                "0x5",
+                "{coin_type}",
+                "{feed}",
+                "{decimals}",
+                "{price_object}",
+            ]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+        };
+        let mut asset = AssetInfo {
+            coin_type: SUI.to_string(),
+            feed: "SUI/USD".to_string(),
+            decimals: Some(9),
+            object_id: Some("0x11".to_string()),
+        };
+        let args = resolve_args(&template, &asset).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                MoveArg::Object(ObjectID::from_str("0x5").unwrap()),
+                MoveArg::String(SUI.to_string()),
+                MoveArg::String("SUI/USD".to_string()),
+                MoveArg::U8(9),
+                MoveArg::Object(ObjectID::from_str("0x11").unwrap()),
+            ]
+        );
+        asset.object_id = None;
+        let err = resolve_args(&template, &asset).unwrap_err();
+        assert!(err.to_string().contains("run `bootstrap` first"));
+    }
+}
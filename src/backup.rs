@@ -0,0 +1,336 @@
+//! Encrypted archives of a deployment's state for `state backup` and `state restore`, so a
+//! failed host can be rebuilt with the same PriceObjects, quarantines, unsent updates and
+//! configuration. The archive is sealed with XChaCha20-Poly1305 under a key derived from a
+//! passphrase with Argon2id.
+
+use anyhow::{Context, Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::config::Settings;
+use crate::keystore;
+use crate::sui_publisher;
+
+const FORMAT: &str = "neo-oracle-state-backup";
+const FORMAT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+/// How far above Argon2's defaults an archive's cost parameters may go. Archives are sealed
+/// with the defaults, so a costlier one was not sealed by `seal` and could exhaust memory.
+const MAX_COST_FACTOR: u32 = 8;
+
+/// Environment variable holding the passphrase, unless `--passphrase-env` names another.
+pub const PASSPHRASE_ENV: &str = "ORACLE_BACKUP_PASSPHRASE";
+
+/// The configuration files `Settings::load` reads.
+const CONFIG_FILES: [&str; 2] = ["config/default.toml", "config/local.toml"];
+
+/// What an archived file is, so `state restore` can say what it brings back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileRole {
+    Config,
+    ConfigHistory,
+    Objects,
+    RegisteredFeeds,
+    Quarantine,
+    Outbox,
+    Keys,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub role: FileRole,
+    /// Where the file was read from, and is restored to.
+    pub path: String,
+    pub content: String,
+}
+
+/// The state of one deployment, as sealed into a backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateArchive {
+    pub created_at_ms: u64,
+    /// The oracle version that wrote the archive.
+    pub version: String,
+    pub files: Vec<ArchivedFile>,
+}
+
+/// A sealed archive as written to disk. Only the key derivation and nonce are readable
+/// without the passphrase.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    format_version: u32,
+    /// Argon2id memory cost in KiB, iterations and parallelism.
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// The state files of `settings` worth carrying to a new host, with their roles. The
+/// keystore and its aliases are only listed with `include_keys`; the fee ledger, price
+/// history and debug bundles are records rather than state and are left out.
+pub fn state_files(settings: &Settings, include_keys: bool) -> Vec<(FileRole, String)> {
+    let mut files: Vec<(FileRole, String)> = CONFIG_FILES
+        .iter()
+        .map(|path| (FileRole::Config, path.to_string()))
+        .collect();
+    files.push((
+        FileRole::ConfigHistory,
+        settings.general.config_history_file.clone(),
+    ));
+    let mut known_objects_files = vec![settings.sui.known_objects_file.clone()];
+    if let Some(migration) = &settings.sui.migration {
+        known_objects_files.push(migration.previous_known_objects_file.clone());
+    }
+    for path in known_objects_files {
+        files.push((
+            FileRole::Objects,
+            sui_publisher::pending_creations_path(&path),
+        ));
+        files.push((FileRole::Objects, path));
+    }
+    files.push((
+        FileRole::RegisteredFeeds,
+        settings.admin.registered_symbols_file.clone(),
+    ));
+    files.push((
+        FileRole::RegisteredFeeds,
+        settings.admin.registered_sources_file.clone(),
+    ));
+    files.push((FileRole::Quarantine, settings.quarantine.file.clone()));
+    files.push((FileRole::Outbox, settings.outbox.file.clone()));
+    if include_keys && let Some(keystore_file) = &settings.sui.keystore_file {
+        let aliases = keystore::aliases_path(Path::new(keystore_file));
+        files.push((FileRole::Keys, keystore_file.clone()));
+        files.push((FileRole::Keys, aliases.to_string_lossy().into_owned()));
+    }
+    files
+}
+
+/// Reads `files` into an archive; files that don't exist are left out.
+pub fn collect(files: &[(FileRole, String)], created_at_ms: u64) -> Result<StateArchive> {
+    let mut archived = Vec::new();
+    for (role, path) in files {
+        if !Path::new(path).exists() {
+            continue;
+        }
+        let content = fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+        archived.push(ArchivedFile {
+            role: *role,
+            path: path.clone(),
+            content,
+        });
+    }
+    Ok(StateArchive {
+        created_at_ms,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        files: archived,
+    })
+}
+
+fn cipher(passphrase: &str, salt: &[u8], params: Params) -> Result<XChaCha20Poly1305> {
+    if passphrase.is_empty() {
+        return Err(anyhow!("The backup passphrase is empty"));
+    }
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the backup key: {}", e))?;
+    Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Encrypts `archive` under `passphrase`.
+pub fn seal(archive: &StateArchive, passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let params = Params::default();
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(archive)?;
+    let ciphertext = cipher(passphrase, &salt, params.clone())?
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| anyhow!("Failed to encrypt the backup"))?;
+    let envelope = Envelope {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        m_cost: params.m_cost(),
+        t_cost: params.t_cost(),
+        p_cost: params.p_cost(),
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    };
+    Ok(serde_json::to_vec_pretty(&envelope)?)
+}
+
+/// Decrypts an archive sealed by `seal`. A wrong passphrase and a tampered archive fail
+/// alike.
+pub fn open(sealed: &[u8], passphrase: &str) -> Result<StateArchive> {
+    let envelope: Envelope = serde_json::from_slice(sealed).context("Not a state backup")?;
+    if envelope.format != FORMAT {
+        return Err(anyhow!("Not a state backup: format {}", envelope.format));
+    }
+    if envelope.format_version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "State backup format {} is newer than this build reads ({})",
+            envelope.format_version,
+            FORMAT_VERSION
+        ));
+    }
+    let limits = [
+        ("m_cost", envelope.m_cost, Params::DEFAULT_M_COST),
+        ("t_cost", envelope.t_cost, Params::DEFAULT_T_COST),
+        ("p_cost", envelope.p_cost, Params::DEFAULT_P_COST),
+    ];
+    for (name, cost, default) in limits {
+        if cost > default * MAX_COST_FACTOR {
+            return Err(anyhow!(
+                "Invalid key derivation parameters: {} {} is above {}",
+                name,
+                cost,
+                default * MAX_COST_FACTOR
+            ));
+        }
+    }
+    let params = Params::new(envelope.m_cost, envelope.t_cost, envelope.p_cost, None)
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?;
+    let salt = hex::decode(&envelope.salt).context("Invalid salt")?;
+    let nonce = hex::decode(&envelope.nonce).context("Invalid nonce")?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("Invalid nonce: {} bytes", nonce.len()));
+    }
+    let ciphertext = hex::decode(&envelope.ciphertext).context("Invalid ciphertext")?;
+    let plaintext = cipher(passphrase, &salt, params)?
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("Failed to decrypt the backup: wrong passphrase or a damaged file"))?;
+    serde_json::from_slice(&plaintext).context("Invalid state archive")
+}
+
+/// Writes the archived files back to their paths. Fails before writing anything if one
+/// already exists, unless `force`. Returns what was restored.
+pub fn restore(archive: &StateArchive, force: bool) -> Result<Vec<String>> {
+    if !force {
+        let existing: Vec<&str> = archive
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .filter(|path| Path::new(path).exists())
+            .collect();
+        if !existing.is_empty() {
+            return Err(anyhow!(
+                "Refusing to overwrite {} (use --force)",
+                existing.join(", ")
+            ));
+        }
+    }
+    let mut restored = Vec::new();
+    for file in &archive.files {
+        let path = Path::new(&file.path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+        }
+        if file.role == FileRole::Keys {
+            write_private(path, file.content.as_bytes())?;
+        } else {
+            fs::write(path, &file.content).context(format!("Failed to write {}", file.path))?;
+        }
+        restored.push(format!("{:?}: {}", file.role, file.path));
+    }
+    Ok(restored)
+}
+
+/// Writes `content` to a file only the owner can read, restricting it before anything is
+/// written, so a key is never readable by others, even for a moment.
+pub fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    // `mode` only applies to newly created files.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .context(format!(
+                "Failed to restrict permissions of {}",
+                path.display()
+            ))?;
+    }
+    file.write_all(content)
+        .context(format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_round_trip() -> Result<()> {
+        let dir = "./test_state_backup";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir)?;
+        let objects = format!("{}/known_price_objects.json", dir);
+        let keystore = format!("{}/keys/sui.keystore", dir);
+        fs::write(&objects, r#"{"schema_version": 1, "objects": {}}"#)?;
+        let files = [
+            (FileRole::Objects, objects.clone()),
+            (FileRole::Outbox, format!("{}/outbox.json", dir)),
+        ];
+        let archive = collect(&files, 1_000)?;
+        assert_eq!(archive.files.len(), 1);
+        let archive = StateArchive {
+            files: [
+                archive.files,
+                vec![ArchivedFile {
+                    role: FileRole::Keys,
+                    path: keystore.clone(),
+                    content: "[]".to_string(),
+                }],
+            ]
+            .concat(),
+            ..archive
+        };
+
+        let sealed = seal(&archive, "correct horse")?;
+        assert!(!String::from_utf8_lossy(&sealed).contains("schema_version"));
+        assert_eq!(open(&sealed, "correct horse")?, archive);
+        assert!(open(&sealed, "wrong horse").is_err());
+        assert!(seal(&archive, "").is_err());
+        // Costs no archive is sealed with are refused before deriving anything.
+        let mut envelope: serde_json::Value = serde_json::from_slice(&sealed)?;
+        envelope["m_cost"] = serde_json::json!(u32::MAX);
+        let err = open(&serde_json::to_vec(&envelope)?, "correct horse").unwrap_err();
+        assert!(err.to_string().contains("m_cost"), "{}", err);
+
+        let err = restore(&archive, false).unwrap_err();
+        assert!(
+            err.to_string().contains("known_price_objects.json"),
+            "{}",
+            err
+        );
+        assert!(!Path::new(&keystore).exists());
+        fs::remove_file(&objects)?;
+        assert_eq!(restore(&archive, false)?.len(), 2);
+        assert_eq!(fs::read_to_string(&keystore)?, "[]");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&keystore)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(restore(&archive, true).is_ok());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
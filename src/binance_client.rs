@@ -1,50 +1,471 @@
-use crate::config::ExchangeConfig;
-use anyhow::Result;
+use crate::aggregator::{self, Quote};
+use crate::clock_drift::{self, ClockDriftTracker};
+use crate::config::{ExchangeConfig, RateLimitSettings, RequestRetrySettings};
+use crate::endpoints::{self, EndpointSelector};
+use crate::rate_limit;
+use crate::sources::{self, PriceSample, PriceSource};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
 use reqwest::Client;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "ws-sources")]
+use crate::sources::PriceStream;
+#[cfg(feature = "ws-sources")]
+use crate::ws_stream::{self, StreamCache, StreamTrade};
 
 #[derive(Deserialize, Debug)]
 pub struct BinanceTickerResponse {
     pub symbol: String,
-    pub price: String,
+    pub price: Decimal,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceBookTickerResponse {
+    pub symbol: String,
+    pub bid_price: Decimal,
+    pub bid_qty: String,
+    pub ask_price: Decimal,
+    pub ask_qty: String,
+}
+
+/// Rolling 24h statistics; `volume` is in base currency.
+#[derive(Deserialize, Debug)]
+pub struct BinanceTicker24hrResponse {
+    pub volume: String,
+}
+
+impl BinanceBookTickerResponse {
+    fn parse(&self, field: &str, value: &str) -> Result<f64> {
+        value
+            .parse::<f64>()
+            .context(format!("Invalid {} '{}' for {}", field, value, self.symbol))
+    }
+
+    fn notional(&self) -> Result<f64> {
+        Ok(aggregator::top_of_book_notional(
+            aggregator::as_f64(self.bid_price),
+            self.parse("bidQty", &self.bid_qty)?,
+            aggregator::as_f64(self.ask_price),
+            self.parse("askQty", &self.ask_qty)?,
+        ))
+    }
+
+    fn quote(&self) -> Quote {
+        Quote {
+            bid: self.bid_price,
+            ask: self.ask_price,
+        }
+    }
+}
+
+/// A message of the combined trade stream (`/stream?streams=<ticker>@trade/...`).
+#[cfg(feature = "ws-sources")]
+#[derive(Deserialize, Debug)]
+struct BinanceStreamMessage {
+    data: BinanceTradeEvent,
+}
+
+#[cfg(feature = "ws-sources")]
+#[derive(Deserialize, Debug)]
+struct BinanceTradeEvent {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    price: Decimal,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "T")]
+    trade_time_ms: i64,
+}
+
+/// The combined trade stream of `symbols` on `stream_url`.
+#[cfg(feature = "ws-sources")]
+fn trade_stream_url(stream_url: &str, symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols
+        .iter()
+        .map(|s| format!("{}@trade", s.to_lowercase()))
+        .collect();
+    format!(
+        "{}/stream?streams={}",
+        stream_url.trim_end_matches('/'),
+        streams.join("/")
+    )
+}
+
+#[cfg(feature = "ws-sources")]
+fn parse_trade_message(text: &str) -> Result<Vec<StreamTrade>> {
+    let trade = serde_json::from_str::<BinanceStreamMessage>(text)?.data;
+    Ok(vec![StreamTrade {
+        size: trade.quantity.parse().ok(),
+        ticker: trade.symbol,
+        sample: PriceSample {
+            price: trade.price,
+            timestamp_ms: Some(trade.trade_time_ms),
+        },
+    }])
+}
+
+const EXCHANGE_NAME: &str = "binance";
+
+/// Binance meters 6000 request weight per minute per IP. A price ticker, book ticker or
+/// 24h statistics request for one symbol weighs 2.
+pub const RATE_LIMIT: RateLimitSettings = RateLimitSettings {
+    weight_per_minute: 6000,
+    burst: None,
+    request_weight: 2,
+};
+
+/// The book-ticker endpoint next to a configured price-ticker endpoint.
+fn book_ticker_url(base_url: &str) -> String {
+    let base = base_url.strip_suffix("/price").unwrap_or(base_url);
+    format!("{}/bookTicker", base)
+}
+
+/// The 24h statistics endpoint next to a configured price-ticker endpoint.
+fn ticker_24hr_url(base_url: &str) -> String {
+    let base = base_url.strip_suffix("/price").unwrap_or(base_url);
+    format!("{}/24hr", base)
 }
 
 async fn get_binance_ticker_price(
     client: &Client,
+    name: &str,
     base_url: &str,
     symbol: &str,
+    timeout: Duration,
+    clock_drift: &ClockDriftTracker,
 ) -> Result<BinanceTickerResponse> {
     let url = format!("{}?symbol={}", base_url, symbol);
     log::debug!("Fetching price for {} from Binance: {}", symbol, url);
-    let response = client.get(&url).send().await?;
-    response.error_for_status_ref()?;
+    let request_sent_ms = clock_drift::local_now_ms();
+    let response = client.get(&url).timeout(timeout).send().await?;
+    let response_received_ms = clock_drift::local_now_ms();
+    let response = rate_limit::check(response)?;
+    // The ticker payload carries no timestamp, so the Date header is our only view of Binance's clock.
+    if let Some(date) = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+    {
+        clock_drift.observe_http_date(name, date, request_sent_ms, response_received_ms);
+    }
     let ticker_response = response.json::<BinanceTickerResponse>().await?;
     log::info!("Fetched price for {}: {}", symbol, ticker_response.price);
     Ok(ticker_response)
 }
 
-pub async fn get_binance_prices(config: &ExchangeConfig) -> Result<HashMap<String, String>> {
-    let client = Client::new();
+async fn get_binance_book_ticker(
+    client: &Client,
+    base_url: &str,
+    symbol: &str,
+) -> Result<BinanceBookTickerResponse> {
+    let url = format!("{}?symbol={}", book_ticker_url(base_url), symbol);
+    log::debug!("Fetching book ticker for {} from Binance: {}", symbol, url);
+    let response = rate_limit::check(client.get(&url).send().await?)?;
+    Ok(response.json::<BinanceBookTickerResponse>().await?)
+}
+
+/// Displayed top-of-book notional for each of `symbols`, in quote currency. Symbols whose
+/// book could not be fetched are missing from the map.
+async fn get_binance_depth(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    symbols: &[String],
+) -> Result<HashMap<String, f64>> {
+    let mut depth = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for symbol in symbols {
+        let request = get_binance_book_ticker(client, &endpoint.base_url, symbol);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|book| book.notional())
+        {
+            Ok(notional) => {
+                depth.insert(symbol.clone(), notional);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch book ticker for {} from Binance: {}",
+                    symbol,
+                    e
+                );
+            }
+        }
+    }
+    Ok(depth)
+}
+
+/// Best bid and ask of each of `symbols`. Symbols whose book could not be fetched are
+/// missing from the map.
+async fn get_binance_quotes(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    symbols: &[String],
+) -> Result<HashMap<String, Quote>> {
+    let mut quotes = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for symbol in symbols {
+        let request = get_binance_book_ticker(client, &endpoint.base_url, symbol);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .map(|book| book.quote())
+        {
+            Ok(quote) => {
+                quotes.insert(symbol.clone(), quote);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch book ticker for {} from Binance: {}",
+                    symbol,
+                    e
+                );
+            }
+        }
+    }
+    Ok(quotes)
+}
+
+async fn get_binance_ticker_24hr(
+    client: &Client,
+    base_url: &str,
+    symbol: &str,
+) -> Result<BinanceTicker24hrResponse> {
+    let url = format!("{}?symbol={}", ticker_24hr_url(base_url), symbol);
+    log::debug!("Fetching 24h volume for {} from Binance: {}", symbol, url);
+    let response = rate_limit::check(client.get(&url).send().await?)?;
+    Ok(response.json::<BinanceTicker24hrResponse>().await?)
+}
+
+/// Trailing 24h volume of each of `symbols`, in base currency. Symbols whose statistics
+/// could not be fetched are missing from the map.
+async fn get_binance_volume(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    symbols: &[String],
+) -> Result<HashMap<String, f64>> {
+    let mut volume = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for symbol in symbols {
+        let request = get_binance_ticker_24hr(client, &endpoint.base_url, symbol);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|stats| {
+                stats
+                    .volume
+                    .parse::<f64>()
+                    .context(format!("Invalid volume '{}' for {}", stats.volume, symbol))
+            }) {
+            Ok(base_volume) => {
+                volume.insert(symbol.clone(), base_volume);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch 24h volume for {} from Binance: {}",
+                    symbol,
+                    e
+                );
+            }
+        }
+    }
+    Ok(volume)
+}
+
+async fn get_binance_prices(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    retry: &RequestRetrySettings,
+    symbols: &[String],
+    clock_drift: &ClockDriftTracker,
+    selector: &EndpointSelector,
+) -> Result<HashMap<String, PriceSample>> {
     let mut prices = HashMap::new();
+    let endpoints = endpoints::endpoints(config);
+    let endpoint = selector.select(name, &endpoints);
+    let timeout = Duration::from_millis(retry.attempt_timeout_ms);
 
-    for symbol in &config.symbols {
-        match get_binance_ticker_price(&client, &config.base_url, symbol).await {
+    for symbol in symbols {
+        let what = format!("{} price of {}", name, symbol);
+        let response = sources::with_retry(retry, &what, || {
+            let request = get_binance_ticker_price(
+                client,
+                name,
+                &endpoint.base_url,
+                symbol,
+                timeout,
+                clock_drift,
+            );
+            selector.timed(name, &endpoint, request)
+        });
+        match response.await {
             Ok(response) => {
-                prices.insert(response.symbol.clone(), response.price);
+                // The ticker carries no time of its own.
+                let sample = PriceSample {
+                    price: response.price,
+                    timestamp_ms: None,
+                };
+                prices.insert(response.symbol, sample);
             }
             Err(e) => {
                 log::error!("Failed to fetch price for {} from Binance: {}", symbol, e);
             }
         }
     }
+
+    // Keep the other endpoints' latency current with one request per cycle.
+    if let (Some(probe), Some(symbol)) = (
+        selector.probe_candidate(name, &endpoints, &endpoint),
+        symbols.first(),
+    ) {
+        let request =
+            get_binance_ticker_price(client, name, &probe.base_url, symbol, timeout, clock_drift);
+        if let Err(e) = selector.timed(name, &probe, request).await {
+            log::debug!("Binance endpoint probe of {} failed: {}", probe.base_url, e);
+        }
+    }
     Ok(prices)
 }
 
+/// Binance spot tickers (`/api/v3/ticker/price`), with depth and quotes from the book
+/// ticker and volume from the 24h statistics. With a `stream_url`, prices also come from
+/// the trade streams of its tickers.
+pub struct BinanceSource {
+    name: &'static str,
+    config: ExchangeConfig,
+    client: Client,
+    retry: RequestRetrySettings,
+    clock_drift: Arc<ClockDriftTracker>,
+    endpoints: Arc<EndpointSelector>,
+    #[cfg(feature = "ws-sources")]
+    stream: Option<Arc<StreamCache>>,
+}
+
+impl BinanceSource {
+    pub fn new(
+        config: ExchangeConfig,
+        client: Client,
+        retry: RequestRetrySettings,
+        clock_drift: Arc<ClockDriftTracker>,
+        endpoints: Arc<EndpointSelector>,
+    ) -> Self {
+        #[cfg(feature = "ws-sources")]
+        let stream = config
+            .stream_url
+            .as_ref()
+            .filter(|_| !config.symbols.is_empty())
+            .map(|url| {
+                ws_stream::spawn(
+                    EXCHANGE_NAME,
+                    trade_stream_url(url, &config.symbols),
+                    None,
+                    config.min_trade_notional,
+                    parse_trade_message,
+                )
+            });
+        Self {
+            name: EXCHANGE_NAME,
+            config,
+            client,
+            retry,
+            clock_drift,
+            endpoints,
+            #[cfg(feature = "ws-sources")]
+            stream,
+        }
+    }
+
+    /// The source under another name, for a venue that speaks Binance's API. Clock drift
+    /// and endpoint latency are tracked by name, so they are kept apart from Binance's.
+    pub fn with_name(self, name: &'static str) -> Self {
+        Self { name, ..self }
+    }
+}
+
+#[async_trait]
+impl PriceSource for BinanceSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.config.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        get_binance_prices(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.retry,
+            symbols,
+            &self.clock_drift,
+            &self.endpoints,
+        )
+        .await
+    }
+
+    /// One request per ticker.
+    fn requests_per_fetch(&self, symbols: &[String]) -> u32 {
+        symbols.len() as u32
+    }
+
+    async fn fetch_depth(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_binance_depth(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    async fn fetch_volume(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_binance_volume(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    async fn fetch_quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        get_binance_quotes(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    #[cfg(feature = "ws-sources")]
+    fn stream(&self) -> Option<&dyn PriceStream> {
+        self.stream.as_deref().map(|s| s as &dyn PriceStream)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json;
 
     #[test]
     fn test_parse_binance_response() {
@@ -53,7 +474,59 @@ mod tests {
         assert!(parsed.is_ok());
         let response = parsed.unwrap();
         assert_eq!(response.symbol, "BTCUSDT");
-        assert_eq!(response.price, "60000.00");
+        assert_eq!(response.price, Decimal::from(60000));
+    }
+
+    #[test]
+    fn test_parse_book_ticker() {
+        let json_data = r#"{"symbol":"BTCUSDT","bidPrice":"60000.00","bidQty":"0.50","askPrice":"60001.00","askQty":"2.00"}"#;
+        let book: BinanceBookTickerResponse = serde_json::from_str(json_data).unwrap();
+        assert_eq!(book.notional().unwrap(), 30_000.0);
+        assert_eq!(
+            book.quote(),
+            Quote {
+                bid: Decimal::from(60000),
+                ask: Decimal::from(60001)
+            }
+        );
+        assert_eq!(
+            book_ticker_url("https://api.binance.com/api/v3/ticker/price"),
+            "https://api.binance.com/api/v3/ticker/bookTicker"
+        );
+    }
+
+    #[test]
+    fn test_parse_ticker_24hr() {
+        let json_data = r#"{"symbol":"BTCUSDT","priceChange":"-94.99","volume":"18151.52","quoteVolume":"1090604722.87"}"#;
+        let stats: BinanceTicker24hrResponse = serde_json::from_str(json_data).unwrap();
+        assert_eq!(stats.volume, "18151.52");
+        assert_eq!(
+            ticker_24hr_url("https://api.binance.com/api/v3/ticker/price"),
+            "https://api.binance.com/api/v3/ticker/24hr"
+        );
+    }
+
+    #[cfg(feature = "ws-sources")]
+    #[test]
+    fn test_parse_trade_stream() {
+        let symbols = ["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        assert_eq!(
+            trade_stream_url("wss://stream.binance.com:9443/", &symbols),
+            "wss://stream.binance.com:9443/stream?streams=btcusdt@trade/ethusdt@trade"
+        );
+        let message = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1672515782136,"s":"BTCUSDT","t":12345,"p":"65000.10","q":"0.0015","T":1672515782134,"m":true,"M":true}}"#;
+        assert_eq!(
+            parse_trade_message(message).unwrap(),
+            vec![StreamTrade {
+                ticker: "BTCUSDT".to_string(),
+                sample: PriceSample {
+                    price: Decimal::new(6500010, 2),
+                    timestamp_ms: Some(1672515782134),
+                },
+                size: Some(0.0015),
+            }]
+        );
+        assert!(parse_trade_message(r#"{"result":null,"id":1}"#).is_err());
     }
 
     #[test]
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+/// Language of the generated read helpers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Language {
+    /// A module for `@mysten/sui`.
+    Typescript,
+    /// A module for `sui-sdk`.
+    Rust,
+}
+
+/// One published feed of the deployment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub object_id: String,
+    pub decimals: u8,
+}
+
+/// What integrators need to read this deployment's feeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deployment {
+    pub package_id: String,
+    pub module: String,
+    pub rpc_url: String,
+    pub feeds: BTreeMap<String, Feed>,
+}
+
+/// A string literal valid in both TypeScript and Rust.
+fn literal(value: &str) -> String {
+    serde_json::to_string(value).expect("strings always serialize")
+}
+
+pub fn render(deployment: &Deployment, language: Language) -> String {
+    match language {
+        Language::Typescript => render_typescript(deployment),
+        Language::Rust => render_rust(deployment),
+    }
+}
+
+fn render_typescript(deployment: &Deployment) -> String {
+    let feeds: String = deployment
+        .feeds
+        .iter()
+        .map(|(symbol, feed)| {
+            format!(
+                "  {}: {{ objectId: {}, decimals: {} }},\n",
+                literal(symbol),
+                literal(&feed.object_id),
+                feed.decimals
+            )
+        })
+        .collect();
+    format!(
+        r#"// Generated by `neo_oracle_mvp gen-bindings` for package {package_id}. Do not edit;
+// regenerate after creating or migrating feeds.
+import {{ SuiClient }} from "@mysten/sui/client";
+
+export const PACKAGE_ID = {package_literal};
+export const RPC_URL = {rpc_literal};
+/** Every feed is one shared object of this type. */
+export const PRICE_OBJECT_TYPE = `${{PACKAGE_ID}}::{module}::PriceObject`;
+
+export const FEEDS = {{
+{feeds}}} as const;
+
+export type FeedSymbol = keyof typeof FEEDS;
+
+export interface OraclePrice {{
+  symbol: string;
+  /** `scaledPrice / 10^decimals`; use `scaledPrice` where precision matters. */
+  price: number;
+  scaledPrice: bigint;
+  decimals: number;
+  timestampMs: bigint;
+}}
+
+/**
+ * Decodes PriceObject fields as Sui JSON-RPC renders them: `u64`s as strings and
+ * `symbol: vector<u8>` as an array of bytes.
+ */
+export function decodePriceObject(fields: Record<string, unknown>): OraclePrice {{
+  const scaledPrice = BigInt(fields.price as string);
+  const decimals = Number(fields.decimals);
+  const symbol = Array.isArray(fields.symbol)
+    ? new TextDecoder().decode(Uint8Array.from(fields.symbol as number[]))
+    : String(fields.symbol);
+  return {{
+    symbol,
+    price: Number(scaledPrice) / 10 ** decimals,
+    scaledPrice,
+    decimals,
+    timestampMs: BigInt(fields.timestamp_ms as string),
+  }};
+}}
+
+/** Reads the latest price of `symbol` from its PriceObject. */
+export async function readPrice(client: SuiClient, symbol: FeedSymbol): Promise<OraclePrice> {{
+  const {{ data, error }} = await client.getObject({{
+    id: FEEDS[symbol].objectId,
+    options: {{ showContent: true }},
+  }});
+  if (error || data?.content?.dataType !== "moveObject") {{
+    throw new Error(`PriceObject of ${{symbol}} is unreadable: ${{JSON.stringify(error)}}`);
+  }}
+  return decodePriceObject(data.content.fields as Record<string, unknown>);
+}}
+
+/** Reads every feed in one batched request. */
+export async function readAllPrices(client: SuiClient): Promise<Partial<Record<FeedSymbol, OraclePrice>>> {{
+  const symbols = Object.keys(FEEDS) as FeedSymbol[];
+  const objects = await client.multiGetObjects({{
+    ids: symbols.map((symbol) => FEEDS[symbol].objectId),
+    options: {{ showContent: true }},
+  }});
+  const prices: Partial<Record<FeedSymbol, OraclePrice>> = {{}};
+  objects.forEach((object, i) => {{
+    const content = object.data?.content;
+    if (content?.dataType === "moveObject") {{
+      prices[symbols[i]] = decodePriceObject(content.fields as Record<string, unknown>);
+    }}
+  }});
+  return prices;
+}}
+"#,
+        package_id = deployment.package_id,
+        package_literal = literal(&deployment.package_id),
+        rpc_literal = literal(&deployment.rpc_url),
+        module = deployment.module,
+        feeds = feeds,
+    )
+}
+
+fn render_rust(deployment: &Deployment) -> String {
+    let feeds: String = deployment
+        .feeds
+        .iter()
+        .map(|(symbol, feed)| {
+            format!(
+                "    Feed {{\n        symbol: {},\n        object_id: {},\n        decimals: {},\n    }},\n",
+                literal(symbol),
+                literal(&feed.object_id),
+                feed.decimals
+            )
+        })
+        .collect();
+    format!(
+        r#"//! Generated by `neo_oracle_mvp gen-bindings` for package {package_id}. Do not edit;
+//! regenerate after creating or migrating feeds. Needs `anyhow`, `serde_json` and `sui-sdk`.
+use anyhow::{{Result, anyhow}};
+use std::str::FromStr;
+use sui_sdk::SuiClient;
+use sui_sdk::rpc_types::{{SuiObjectDataOptions, SuiParsedData}};
+use sui_sdk::types::base_types::ObjectID;
+
+pub const PACKAGE_ID: &str = {package_literal};
+pub const RPC_URL: &str = {rpc_literal};
+/// Every feed is one shared object of this type.
+pub const PRICE_OBJECT_TYPE: &str = {type_literal};
+
+pub struct Feed {{
+    pub symbol: &'static str,
+    pub object_id: &'static str,
+    pub decimals: u8,
+}}
+
+pub const FEEDS: &[Feed] = &[
+{feeds}];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OraclePrice {{
+    pub symbol: String,
+    pub scaled_price: u64,
+    pub decimals: u8,
+    pub timestamp_ms: u64,
+}}
+
+impl OraclePrice {{
+    /// `scaled_price / 10^decimals`; use `scaled_price` where precision matters.
+    pub fn price(&self) -> f64 {{
+        self.scaled_price as f64 / 10f64.powi(self.decimals as i32)
+    }}
+}}
+
+/// Move `u64`s are rendered as JSON strings, smaller integers as JSON numbers.
+fn json_u64(fields: &serde_json::Value, name: &str) -> Result<u64> {{
+    match fields.get(name) {{
+        Some(serde_json::Value::String(s)) => Ok(s.parse()?),
+        Some(serde_json::Value::Number(n)) => n.as_u64().ok_or_else(|| anyhow!("{{}} is not a u64", name)),
+        _ => Err(anyhow!("PriceObject has no {{}} field", name)),
+    }}
+}}
+
+/// Decodes PriceObject fields as Sui JSON-RPC renders them.
+pub fn decode_price_object(fields: &serde_json::Value) -> Result<OraclePrice> {{
+    let symbol = match fields.get("symbol") {{
+        Some(serde_json::Value::Array(bytes)) => String::from_utf8(
+            bytes
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| anyhow!("symbol is not a byte array"))?,
+        )?,
+        Some(serde_json::Value::String(s)) => s.clone(),
+        _ => return Err(anyhow!("PriceObject has no symbol field")),
+    }};
+    Ok(OraclePrice {{
+        symbol,
+        scaled_price: json_u64(fields, "price")?,
+        decimals: u8::try_from(json_u64(fields, "decimals")?)?,
+        timestamp_ms: json_u64(fields, "timestamp_ms")?,
+    }})
+}}
+
+/// Reads the latest price of `symbol` from its PriceObject.
+pub async fn read_price(client: &SuiClient, symbol: &str) -> Result<OraclePrice> {{
+    let feed = FEEDS
+        .iter()
+        .find(|feed| feed.symbol == symbol)
+        .ok_or_else(|| anyhow!("{{}} is not a feed of this deployment", symbol))?;
+    let response = client
+        .read_api()
+        .get_object_with_options(
+            ObjectID::from_str(feed.object_id)?,
+            SuiObjectDataOptions::new().with_content(),
+        )
+        .await?;
+    match response.data.and_then(|data| data.content) {{
+        Some(SuiParsedData::MoveObject(object)) => decode_price_object(&object.fields.to_json_value()),
+        _ => Err(anyhow!("PriceObject of {{}} is unreadable", symbol)),
+    }}
+}}
+"#,
+        package_id = deployment.package_id,
+        package_literal = literal(&deployment.package_id),
+        rpc_literal = literal(&deployment.rpc_url),
+        type_literal = literal(&format!(
+            "{}::{}::PriceObject",
+            deployment.package_id, deployment.module
+        )),
+        feeds = feeds,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment() -> Deployment {
+        Deployment {
+            package_id: "0xabc".to_string(),
+            module: "price_oracle".to_string(),
+            rpc_url: "https://fullnode.testnet.sui.io:443".to_string(),
+            feeds: BTreeMap::from([
+                (
+                    "BTC/USD".to_string(),
+                    Feed {
+                        object_id: "0x11".to_string(),
+                        decimals: 6,
+                    },
+                ),
+                (
+                    "ODD \"NAME\"".to_string(),
+                    Feed {
+                        object_id: "0x22".to_string(),
+                        decimals: 8,
+                    },
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_typescript_bindings_carry_the_deployment() {
+        let ts = render(&deployment(), Language::Typescript);
+        assert!(ts.contains(r#"export const PACKAGE_ID = "0xabc";"#));
+        assert!(ts.contains(r#"  "BTC/USD": { objectId: "0x11", decimals: 6 },"#));
+        assert!(ts.contains(r#"  "ODD \"NAME\"": { objectId: "0x22", decimals: 8 },"#));
+        assert!(ts.contains("`${PACKAGE_ID}::price_oracle::PriceObject`"));
+    }
+
+    #[test]
+    fn test_rust_bindings_carry_the_deployment() {
+        let rust = render(&deployment(), Language::Rust);
+        assert!(rust.contains(
+            r#"pub const PRICE_OBJECT_TYPE: &str = "0xabc::price_oracle::PriceObject";"#
+        ));
+        assert!(rust.contains(
+            "    Feed {\n        symbol: \"BTC/USD\",\n        object_id: \"0x11\",\n        decimals: 6,\n    },\n"
+        ));
+        assert!(rust.contains("pub async fn read_price("));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::aggregator::{self, Quote};
+use crate::clock_drift::{self, ClockDriftTracker};
+use crate::config::{ExchangeConfig, RateLimitSettings};
+use crate::endpoints::{self, EndpointSelector};
+use crate::rate_limit;
+use crate::sources::{PriceSample, PriceSource};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The envelope of every Bybit v5 response. A `retCode` other than `0` is an error, even
+/// with a `200` status.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitResponse<T> {
+    pub ret_code: i64,
+    #[serde(default)]
+    pub ret_msg: String,
+    pub result: Option<T>,
+    /// Bybit's clock when it answered, in milliseconds since the epoch.
+    #[serde(default)]
+    pub time: Option<i64>,
+}
+
+impl<T> BybitResponse<T> {
+    fn into_result(self) -> Result<T> {
+        if self.ret_code != 0 {
+            return Err(anyhow!("Bybit error {}: {}", self.ret_code, self.ret_msg));
+        }
+        self.result
+            .ok_or_else(|| anyhow!("Bybit response without a result"))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BybitTickers {
+    pub list: Vec<BybitTicker>,
+}
+
+/// One spot ticker. Sizes and `volume24h` are in base currency. Bybit reports no size for
+/// the last trade.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitTicker {
+    pub symbol: String,
+    pub last_price: String,
+    #[serde(default, rename = "bid1Price")]
+    pub bid_price: String,
+    #[serde(default, rename = "bid1Size")]
+    pub bid_size: String,
+    #[serde(default, rename = "ask1Price")]
+    pub ask_price: String,
+    #[serde(default, rename = "ask1Size")]
+    pub ask_size: String,
+    #[serde(default)]
+    pub volume24h: String,
+}
+
+impl BybitTicker {
+    fn parse(&self, field: &str, value: &str) -> Result<f64> {
+        value
+            .parse()
+            .context(format!("Invalid {} '{}' for {}", field, value, self.symbol))
+    }
+
+    fn parse_price(&self, field: &str, value: &str) -> Result<Decimal> {
+        value
+            .parse()
+            .context(format!("Invalid {} '{}' for {}", field, value, self.symbol))
+    }
+
+    fn price(&self) -> Result<Decimal> {
+        self.parse_price("lastPrice", &self.last_price)
+    }
+
+    fn notional(&self) -> Result<f64> {
+        Ok(aggregator::top_of_book_notional(
+            self.parse("bid1Price", &self.bid_price)?,
+            self.parse("bid1Size", &self.bid_size)?,
+            self.parse("ask1Price", &self.ask_price)?,
+            self.parse("ask1Size", &self.ask_size)?,
+        ))
+    }
+
+    fn quote(&self) -> Result<Quote> {
+        Ok(Quote {
+            bid: self.parse_price("bid1Price", &self.bid_price)?,
+            ask: self.parse_price("ask1Price", &self.ask_price)?,
+        })
+    }
+
+    fn volume(&self) -> Result<f64> {
+        self.parse("volume24h", &self.volume24h)
+    }
+}
+
+const EXCHANGE_NAME: &str = "bybit";
+
+/// Bybit allows 600 requests per 5 seconds per IP.
+pub const RATE_LIMIT: RateLimitSettings = RateLimitSettings {
+    weight_per_minute: 7200,
+    burst: Some(600),
+    request_weight: 1,
+};
+
+fn tickers_url(base_url: &str, symbol: &str) -> String {
+    format!(
+        "{}/market/tickers?category=spot&symbol={}",
+        base_url.trim_end_matches('/'),
+        symbol
+    )
+}
+
+/// `symbol`'s ticker, with the time Bybit answered.
+async fn get_bybit_ticker(
+    client: &Client,
+    name: &str,
+    base_url: &str,
+    symbol: &str,
+    clock_drift: &ClockDriftTracker,
+) -> Result<(BybitTicker, Option<i64>)> {
+    let url = tickers_url(base_url, symbol);
+    log::debug!("Fetching ticker for {} from Bybit: {}", symbol, url);
+    let request_sent_ms = clock_drift::local_now_ms();
+    let response = rate_limit::check(client.get(&url).send().await?)?;
+    let response_received_ms = clock_drift::local_now_ms();
+    let response = response.json::<BybitResponse<BybitTickers>>().await?;
+    let server_time_ms = response.time;
+    if let Some(time) = server_time_ms {
+        clock_drift.observe(name, time, request_sent_ms, response_received_ms);
+    }
+    let ticker = response
+        .into_result()?
+        .list
+        .into_iter()
+        .find(|t| t.symbol == symbol)
+        .ok_or_else(|| anyhow!("Bybit returned no ticker for {}", symbol))?;
+    Ok((ticker, server_time_ms))
+}
+
+async fn get_bybit_prices(
+    source: &BybitSource,
+    symbols: &[String],
+) -> Result<HashMap<String, PriceSample>> {
+    let BybitSource {
+        name,
+        config,
+        client,
+        clock_drift,
+        endpoints: selector,
+    } = source;
+    let mut prices = HashMap::new();
+    let endpoints = endpoints::endpoints(config);
+    let endpoint = selector.select(name, &endpoints);
+
+    for symbol in symbols {
+        let request = get_bybit_ticker(client, name, &endpoint.base_url, symbol, clock_drift);
+        let ticker = selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|(ticker, server_time_ms)| Ok((ticker.price()?, server_time_ms)));
+        match ticker {
+            Ok((price, server_time_ms)) => {
+                log::info!("Fetched price for {}: {}", symbol, price);
+                let sample = PriceSample {
+                    price,
+                    timestamp_ms: server_time_ms,
+                };
+                prices.insert(symbol.clone(), sample);
+            }
+            Err(e) => log::error!("Failed to fetch price for {} from Bybit: {}", symbol, e),
+        }
+    }
+
+    // Keep the other endpoints' latency current with one request per cycle.
+    if let (Some(probe), Some(symbol)) = (
+        selector.probe_candidate(name, &endpoints, &endpoint),
+        symbols.first(),
+    ) {
+        let request = get_bybit_ticker(client, name, &probe.base_url, symbol, clock_drift);
+        if let Err(e) = selector.timed(name, &probe, request).await {
+            log::debug!("Bybit endpoint probe of {} failed: {}", probe.base_url, e);
+        }
+    }
+    Ok(prices)
+}
+
+/// `what` of each of `symbols`, read from its ticker by `extract`. Symbols whose ticker
+/// could not be fetched or lacks the value are missing from the map.
+async fn get_bybit_ticker_values<T>(
+    source: &BybitSource,
+    symbols: &[String],
+    what: &str,
+    extract: fn(&BybitTicker) -> Result<T>,
+) -> Result<HashMap<String, T>> {
+    let mut values = HashMap::new();
+    let endpoint = source
+        .endpoints
+        .select(source.name, &endpoints::endpoints(&source.config));
+    for symbol in symbols {
+        let request = get_bybit_ticker(
+            &source.client,
+            source.name,
+            &endpoint.base_url,
+            symbol,
+            &source.clock_drift,
+        );
+        match source
+            .endpoints
+            .timed(source.name, &endpoint, request)
+            .await
+            .and_then(|(ticker, _)| extract(&ticker))
+        {
+            Ok(value) => {
+                values.insert(symbol.clone(), value);
+            }
+            Err(e) => log::error!("Failed to fetch {} of {} from Bybit: {}", what, symbol, e),
+        }
+    }
+    Ok(values)
+}
+
+/// Bybit spot tickers (`/v5/market/tickers?category=spot`), keyed by symbol such as
+/// `BTCUSDT`. Depth, quotes and 24h volume come from the same ticker.
+pub struct BybitSource {
+    name: &'static str,
+    config: ExchangeConfig,
+    client: Client,
+    clock_drift: Arc<ClockDriftTracker>,
+    endpoints: Arc<EndpointSelector>,
+}
+
+impl BybitSource {
+    pub fn new(
+        config: ExchangeConfig,
+        client: Client,
+        clock_drift: Arc<ClockDriftTracker>,
+        endpoints: Arc<EndpointSelector>,
+    ) -> Self {
+        if config.stream_url.is_some() {
+            log::warn!("Bybit sources have no WebSocket stream yet; stream_url is ignored");
+        }
+        Self {
+            name: EXCHANGE_NAME,
+            config,
+            client,
+            clock_drift,
+            endpoints,
+        }
+    }
+
+    /// The source under another name, for a venue that speaks Bybit's API. Clock drift and
+    /// endpoint latency are tracked by name, so they are kept apart from Bybit's.
+    pub fn with_name(self, name: &'static str) -> Self {
+        Self { name, ..self }
+    }
+}
+
+#[async_trait]
+impl PriceSource for BybitSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.config.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        get_bybit_prices(self, symbols).await
+    }
+
+    async fn fetch_depth(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_bybit_ticker_values(self, symbols, "order-book depth", BybitTicker::notional).await
+    }
+
+    async fn fetch_volume(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_bybit_ticker_values(self, symbols, "24h volume", BybitTicker::volume).await
+    }
+
+    async fn fetch_quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        get_bybit_ticker_values(self, symbols, "bid and ask", BybitTicker::quote).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bybit_tickers() {
+        let json_data = r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[{"symbol":"BTCUSDT","bid1Price":"65000.0","bid1Size":"0.5","ask1Price":"65000.2","ask1Size":"1.5","lastPrice":"65000.1","prevPrice24h":"64000","price24hPcnt":"0.0156","highPrice24h":"65500","lowPrice24h":"63800","turnover24h":"1173557484.5","volume24h":"18151.52","usdIndexPrice":"65001.3"}]},"retExtInfo":{},"time":1672515782136}"#;
+        let response: BybitResponse<BybitTickers> = serde_json::from_str(json_data).unwrap();
+        assert_eq!(response.time, Some(1672515782136));
+        let ticker = response.into_result().unwrap().list.pop().unwrap();
+        assert_eq!(ticker.symbol, "BTCUSDT");
+        assert_eq!(ticker.price().unwrap(), Decimal::new(650001, 1));
+        assert_eq!(ticker.notional().unwrap(), 32_500.0);
+        assert_eq!(
+            ticker.quote().unwrap(),
+            Quote {
+                bid: Decimal::from(65000),
+                ask: Decimal::new(650002, 1)
+            }
+        );
+        assert_eq!(ticker.volume().unwrap(), 18151.52);
+        assert_eq!(
+            tickers_url("https://api.bybit.com/v5/", "BTCUSDT"),
+            "https://api.bybit.com/v5/market/tickers?category=spot&symbol=BTCUSDT"
+        );
+    }
+
+    #[test]
+    fn test_bybit_error_envelope() {
+        let json_data =
+            r#"{"retCode":10001,"retMsg":"Not supported symbols","result":{},"time":1}"#;
+        let response: BybitResponse<serde_json::Value> = serde_json::from_str(json_data).unwrap();
+        let err = response.into_result().unwrap_err().to_string();
+        assert_eq!(err, "Bybit error 10001: Not supported symbols");
+    }
+}
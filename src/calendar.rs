@@ -0,0 +1,334 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::config::{CalendarSettings, SymbolSettings};
+
+pub const DEFAULT_CALENDAR: &str = "crypto";
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Trading sessions of a market, expressed in the market's local time zone.
+#[derive(Debug, Clone)]
+pub struct MarketCalendar {
+    tz: Tz,
+    /// Open intervals per weekday (Monday first), in minutes since local midnight.
+    sessions: [Vec<(u32, u32)>; 7],
+    holidays: HashSet<NaiveDate>,
+}
+
+pub fn parse_hhmm(value: &str) -> Result<u32> {
+    let (h, m) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected HH:MM, got '{}'", value))?;
+    let (h, m): (u32, u32) = (h.parse()?, m.parse()?);
+    if m >= 60 || h * 60 + m > MINUTES_PER_DAY {
+        return Err(anyhow!("Time of day out of range: '{}'", value));
+    }
+    Ok(h * 60 + m)
+}
+
+fn parse_weekday(value: &str) -> Result<usize> {
+    const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    DAYS.iter()
+        .position(|d| value.eq_ignore_ascii_case(d))
+        .ok_or_else(|| anyhow!("Unknown weekday '{}'", value))
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse()
+        .map_err(|e| anyhow!("Unknown time zone '{}': {}", name, e))
+}
+
+/// Resolves a local wall-clock time, moving forward past a DST gap if necessary.
+pub fn local_to_utc(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(t) => t.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => local_to_utc(tz, local + Duration::hours(1)),
+    }
+}
+
+impl MarketCalendar {
+    /// Markets that never close.
+    pub fn always_open() -> Self {
+        Self {
+            tz: Tz::UTC,
+            sessions: std::array::from_fn(|_| vec![(0, MINUTES_PER_DAY)]),
+            holidays: HashSet::new(),
+        }
+    }
+
+    /// Spot FX: Sunday 17:00 to Friday 17:00 New York time.
+    pub fn fx() -> Self {
+        let mut sessions: [Vec<(u32, u32)>; 7] = Default::default();
+        for day in sessions.iter_mut().take(4) {
+            day.push((0, MINUTES_PER_DAY));
+        }
+        sessions[4].push((0, 17 * 60));
+        sessions[6].push((17 * 60, MINUTES_PER_DAY));
+        Self {
+            tz: chrono_tz::America::New_York,
+            sessions,
+            holidays: HashSet::new(),
+        }
+    }
+
+    /// US cash equities regular session, 09:30-16:00 New York time on weekdays. Exchange
+    /// holidays are not built in; define a `[calendars.<name>]` entry with `holidays` instead.
+    pub fn us_equities() -> Self {
+        let mut sessions: [Vec<(u32, u32)>; 7] = Default::default();
+        for day in sessions.iter_mut().take(5) {
+            day.push((9 * 60 + 30, 16 * 60));
+        }
+        Self {
+            tz: chrono_tz::America::New_York,
+            sessions,
+            holidays: HashSet::new(),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "crypto" => Some(Self::always_open()),
+            "fx" => Some(Self::fx()),
+            "us_equities" => Some(Self::us_equities()),
+            _ => None,
+        }
+    }
+
+    /// The calendar of `settings`, in `default_tz` unless it names its own zone.
+    pub fn from_settings(settings: &CalendarSettings, default_tz: Tz) -> Result<Self> {
+        let tz = match &settings.timezone {
+            Some(name) => parse_timezone(name)?,
+            None => default_tz,
+        };
+        let open = parse_hhmm(&settings.open)?;
+        let close = parse_hhmm(&settings.close)?;
+        if open >= close {
+            return Err(anyhow!(
+                "Session must open before it closes ({} >= {})",
+                settings.open,
+                settings.close
+            ));
+        }
+        let mut sessions: [Vec<(u32, u32)>; 7] = Default::default();
+        for day in &settings.days {
+            sessions[parse_weekday(day)?].push((open, close));
+        }
+        Ok(Self {
+            tz,
+            sessions,
+            holidays: settings.holidays.iter().copied().collect(),
+        })
+    }
+
+    /// Open intervals of one local date, in UTC.
+    fn sessions_on(&self, date: NaiveDate) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> {
+        let closed = self.holidays.contains(&date);
+        let day = &self.sessions[date.weekday().num_days_from_monday() as usize];
+        day.iter()
+            .filter(move |_| !closed)
+            .map(move |&(open, close)| {
+                let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is valid");
+                (
+                    local_to_utc(self.tz, midnight + Duration::minutes(open as i64)),
+                    local_to_utc(self.tz, midnight + Duration::minutes(close as i64)),
+                )
+            })
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        let local_date = at.with_timezone(&self.tz).date_naive();
+        // A session ending at 24:00 local can be reported against the previous date.
+        [local_date.pred_opt(), Some(local_date)]
+            .into_iter()
+            .flatten()
+            .flat_map(|d| self.sessions_on(d))
+            .any(|(open, close)| open <= at && at < close)
+    }
+
+    /// Milliseconds the market was open in `[from_ms, to_ms)`.
+    pub fn open_ms_between(&self, from_ms: u64, to_ms: u64) -> u64 {
+        if to_ms <= from_ms {
+            return 0;
+        }
+        let (Some(from), Some(to)) = (
+            DateTime::<Utc>::from_timestamp_millis(from_ms as i64),
+            DateTime::<Utc>::from_timestamp_millis(to_ms as i64),
+        ) else {
+            return 0;
+        };
+
+        let mut total = 0;
+        let mut date = from
+            .with_timezone(&self.tz)
+            .date_naive()
+            .pred_opt()
+            .unwrap_or_default();
+        let last = to.with_timezone(&self.tz).date_naive();
+        while date <= last {
+            for (open, close) in self.sessions_on(date) {
+                let start = open.max(from);
+                let end = close.min(to);
+                if start < end {
+                    total += (end - start).num_milliseconds() as u64;
+                }
+            }
+            date = match date.succ_opt() {
+                Some(d) => d,
+                None => break,
+            };
+        }
+        total
+    }
+}
+
+/// Calendars by name, combining the built-ins with `[calendars.*]` from config.
+pub struct CalendarRegistry {
+    by_symbol: HashMap<String, MarketCalendar>,
+    default: MarketCalendar,
+}
+
+impl CalendarRegistry {
+    pub fn from_settings(
+        calendars: &BTreeMap<String, CalendarSettings>,
+        symbols: &BTreeMap<String, SymbolSettings>,
+        default_tz: Tz,
+    ) -> Result<Self> {
+        let mut by_symbol = HashMap::new();
+        for (symbol, settings) in symbols {
+            let name = settings.calendar.as_deref().unwrap_or(DEFAULT_CALENDAR);
+            let calendar = match calendars.get(name) {
+                Some(custom) => MarketCalendar::from_settings(custom, default_tz)
+                    .context(format!("Invalid calendar '{}'", name))?,
+                None => MarketCalendar::builtin(name).ok_or_else(|| {
+                    anyhow!(
+                        "symbols.\"{}\".calendar: unknown calendar '{}'",
+                        symbol,
+                        name
+                    )
+                })?,
+            };
+            by_symbol.insert(symbol.clone(), calendar);
+        }
+        Ok(Self {
+            by_symbol,
+            default: MarketCalendar::always_open(),
+        })
+    }
+
+    /// Symbols without an explicit calendar trade around the clock.
+    pub fn for_symbol(&self, symbol: &str) -> &MarketCalendar {
+        self.by_symbol.get(symbol).unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn ms(s: &str) -> u64 {
+        utc(s).timestamp_millis() as u64
+    }
+
+    #[test]
+    fn test_crypto_is_always_open() {
+        let cal = MarketCalendar::always_open();
+        assert!(cal.is_open(utc("2026-10-17T12:00:00Z"))); // Saturday
+        assert_eq!(
+            cal.open_ms_between(ms("2026-10-17T00:00:00Z"), ms("2026-10-18T00:00:00Z")),
+            24 * 3_600_000
+        );
+    }
+
+    #[test]
+    fn test_fx_weekend_closure() {
+        let cal = MarketCalendar::fx();
+        // Friday 16:59 and 17:01 New York (EDT, UTC-4).
+        assert!(cal.is_open(utc("2026-10-16T20:59:00Z")));
+        assert!(!cal.is_open(utc("2026-10-16T21:01:00Z")));
+        assert!(!cal.is_open(utc("2026-10-17T12:00:00Z")));
+        // Sunday 17:00 New York reopens.
+        assert!(cal.is_open(utc("2026-10-18T21:00:00Z")));
+        // Late Sunday local time is still open across local midnight.
+        assert!(cal.is_open(utc("2026-10-19T03:00:00Z")));
+        // Friday close to Sunday reopen: no open time elapses.
+        assert_eq!(
+            cal.open_ms_between(ms("2026-10-16T21:00:00Z"), ms("2026-10-18T21:00:00Z")),
+            0
+        );
+    }
+
+    #[test]
+    fn test_us_equities_session_follows_dst() {
+        let cal = MarketCalendar::us_equities();
+        // 09:30 New York is 13:30 UTC in summer and 14:30 UTC in winter.
+        assert!(cal.is_open(utc("2026-07-01T13:30:00Z")));
+        assert!(!cal.is_open(utc("2026-12-01T13:30:00Z")));
+        assert!(cal.is_open(utc("2026-12-01T14:30:00Z")));
+        assert_eq!(
+            cal.open_ms_between(ms("2026-12-01T00:00:00Z"), ms("2026-12-02T00:00:00Z")),
+            390 * 60_000
+        );
+    }
+
+    #[test]
+    fn test_custom_calendar_with_holiday() {
+        let mut settings = CalendarSettings {
+            timezone: Some("Europe/London".to_string()),
+            days: vec![
+                "mon".into(),
+                "tue".into(),
+                "wed".into(),
+                "thu".into(),
+                "fri".into(),
+            ],
+            open: "08:00".to_string(),
+            close: "16:30".to_string(),
+            holidays: vec![NaiveDate::from_ymd_opt(2026, 12, 25).unwrap()],
+        };
+        let cal = MarketCalendar::from_settings(&settings, Tz::UTC).unwrap();
+        assert!(cal.is_open(utc("2026-12-24T09:00:00Z")));
+        assert!(!cal.is_open(utc("2026-12-25T09:00:00Z")));
+        assert!(!cal.is_open(utc("2026-12-26T09:00:00Z"))); // Saturday
+
+        // Without a zone of its own, the sessions are in the operator's.
+        settings.timezone = None;
+        let cal = MarketCalendar::from_settings(&settings, chrono_tz::Asia::Tokyo).unwrap();
+        assert!(cal.is_open(utc("2026-12-24T00:00:00Z")));
+        assert!(!cal.is_open(utc("2026-12-24T09:00:00Z")));
+    }
+
+    #[test]
+    fn test_registry_rejects_unknown_calendar() {
+        let symbols = BTreeMap::from([(
+            "SPY/USD".to_string(),
+            SymbolSettings {
+                calendar: Some("moon".to_string()),
+                ..Default::default()
+            },
+        )]);
+        let err = CalendarRegistry::from_settings(&BTreeMap::new(), &symbols, Tz::UTC)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("unknown calendar 'moon'"));
+
+        let ok =
+            CalendarRegistry::from_settings(&BTreeMap::new(), &BTreeMap::new(), Tz::UTC).unwrap();
+        assert!(ok.for_symbol("BTC/USD").is_open(Utc::now()));
+    }
+
+    #[test]
+    fn test_parse_hhmm() {
+        assert_eq!(parse_hhmm("09:30").unwrap(), 570);
+        assert_eq!(parse_hhmm("24:00").unwrap(), MINUTES_PER_DAY);
+        assert!(parse_hhmm("24:01").is_err());
+        assert!(parse_hhmm("9").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! What the chain being published to can do. Each backend reports its `Capabilities` once
+//! connected, probed from the deployed contract where the contract decides, and the
+//! pipeline adapts to them instead of assuming Sui: how many updates go into one
+//! transaction, whether updates carry a confidence, and whether stale feeds are flagged.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::config::PriceRounding;
+use crate::cycle::CycleRng;
+use crate::metrics::Metrics;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceInfo {
+    pub symbol: String,
+    pub price: Decimal,
+    pub timestamp_ms: u64,
+    /// Operator-set price; published through the target's manual update call, e.g.
+    /// `sui.manual_update_function`.
+    #[serde(default)]
+    pub manual_override: bool,
+    /// The aggregate's confidence, published when the target supports it. `None` for prices
+    /// not aggregated from several sources.
+    #[serde(default)]
+    pub confidence: Option<f64>,
+}
+
+/// Result of publishing one update through `ChainBackend::publish`.
+#[derive(Debug, Clone)]
+pub enum Submission {
+    Published(PublishReceipt),
+    /// Identical to the last payload submitted to the object; nothing was sent.
+    Duplicate,
+    /// `age_ms` old before an attempt, over the target's maximum payload age (e.g.
+    /// `sui.retry.max_payload_age_ms`); no further attempt was made.
+    Expired {
+        age_ms: u64,
+    },
+}
+
+/// Outcome of a successful on-chain price update.
+#[derive(Debug, Clone)]
+pub struct PublishReceipt {
+    pub digest: String,
+    /// Net gas charged, in the chain's smallest unit (MIST on Sui).
+    pub gas_used_mist: u64,
+}
+
+/// Transaction bytes and RPC responses of one publish attempt, kept for debug bundles.
+#[derive(Debug, Default, Serialize)]
+pub struct PublishTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraceStep {
+    pub label: String,
+    /// The transaction as the chain encodes it for signing, hex-encoded.
+    pub tx_bytes_hex: String,
+    pub rpc_response: Option<serde_json::Value>,
+}
+
+impl PublishTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_tx(&mut self, label: &str, tx_bytes: &[u8]) {
+        self.steps.push(TraceStep {
+            label: label.to_string(),
+            tx_bytes_hex: hex::encode(tx_bytes),
+            rpc_response: None,
+        });
+    }
+
+    pub fn record_response<T: Serialize>(&mut self, response: &T) {
+        if let Some(step) = self.steps.last_mut() {
+            step.rpc_response = serde_json::to_value(response).ok();
+        }
+    }
+}
+
+/// The chain family a backend publishes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainKind {
+    Sui,
+}
+
+impl fmt::Display for ChainKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChainKind::Sui => "sui",
+        })
+    }
+}
+
+/// What a publishing target supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub chain: ChainKind,
+    /// Most updates one transaction carries; `1` without batching.
+    pub max_batch_size: usize,
+    /// Whether updates can carry the aggregate's confidence.
+    pub confidence: bool,
+    /// Whether a feed can be flagged stale on-chain.
+    pub stale_marking: bool,
+}
+
+impl Capabilities {
+    /// Drops what `update` carries that the target cannot publish, so gates, the outbox
+    /// and the price history see the update as it will land.
+    pub fn adapt(&self, update: &mut PriceInfo) {
+        if !self.confidence {
+            update.confidence = None;
+        }
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |supported: bool| if supported { "yes" } else { "no" };
+        write!(
+            f,
+            "{}: batches of up to {}, confidence {}, stale marking {}",
+            self.chain,
+            self.max_batch_size,
+            flag(self.confidence),
+            flag(self.stale_marking)
+        )
+    }
+}
+
+/// Where feeds get the object they are published to. A trait so that feed registration can
+/// be tested without a chain.
+#[async_trait]
+pub trait PriceObjects: Send + Sync {
+    /// Finds or creates the object of `symbol` without publishing a price, recovering an
+    /// unfinished earlier creation instead of repeating it. Returns the object's ID as the
+    /// chain writes it.
+    async fn ensure_price_object(&self, symbol: &str) -> Result<String>;
+}
+
+/// A publishing target, e.g. `SuiPublisher`. The pipeline publishes, flags stale feeds and,
+/// through `PriceObjects`, creates feeds' objects only through this trait.
+#[async_trait]
+pub trait ChainBackend: PriceObjects {
+    /// What the target supports, as probed when it connected.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Decimals `symbol` is published at.
+    fn decimals(&self, symbol: &str) -> u8;
+
+    /// How prices are rounded to those decimals.
+    fn price_rounding(&self) -> PriceRounding;
+
+    /// The ID of the object `symbol` is published to, as the chain writes it, if it has one
+    /// yet.
+    fn known_object(&self, symbol: &str) -> Result<Option<String>>;
+
+    /// Starts a round of transactions that must not contend with each other, e.g. one
+    /// cycle's. On Sui each transaction of a round pays from a gas coin of its own.
+    fn begin_round(&self);
+
+    /// Publishes `updates` in transactions of up to `Capabilities::max_batch_size` updates,
+    /// creating the objects of feeds that have none. Retry jitter is drawn from `rng`, so a
+    /// cycle's retries follow from its logged seed. Results are in the order of `updates`.
+    async fn publish(
+        &self,
+        updates: Vec<PriceInfo>,
+        rng: &mut CycleRng,
+        trace: &mut PublishTrace,
+    ) -> Vec<Result<Submission>>;
+
+    /// Flags `symbol` stale on-chain, for targets with `Capabilities::stale_marking`. `None`
+    /// if the feed has no object yet.
+    async fn mark_stale(
+        &self,
+        symbol: &str,
+        trace: &mut PublishTrace,
+    ) -> Result<Option<PublishReceipt>>;
+
+    /// The publisher's balance for gas, in the chain's smallest unit.
+    async fn gas_balance(&self) -> Result<u128>;
+
+    /// Exports the target's own metrics, e.g. the health of its RPC endpoints.
+    fn export_metrics(&self, metrics: &Metrics);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapt_to_capabilities() {
+        let capabilities = Capabilities {
+            chain: ChainKind::Sui,
+            max_batch_size: 50,
+            confidence: false,
+            stale_marking: true,
+        };
+        let mut update = PriceInfo {
+            symbol: "BTC/USD".to_string(),
+            price: Decimal::from(65_000),
+            timestamp_ms: 1,
+            manual_override: false,
+            confidence: Some(12.5),
+        };
+        Capabilities {
+            confidence: true,
+            ..capabilities
+        }
+        .adapt(&mut update);
+        assert_eq!(update.confidence, Some(12.5));
+        capabilities.adapt(&mut update);
+        assert_eq!(update.confidence, None);
+        assert_eq!(
+            capabilities.to_string(),
+            "sui: batches of up to 50, confidence no, stale marking yes"
+        );
+    }
+}
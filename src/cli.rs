@@ -0,0 +1,1107 @@
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use sui_sdk::types::base_types::SuiAddress;
+
+use crate::assets;
+use crate::backup;
+use crate::bindings::{self, Language};
+use crate::chain::PriceObjects;
+use crate::config::Settings;
+use crate::config_history;
+use crate::consumers::{self, TemplateValues};
+use crate::keystore;
+use crate::loadtest::Target;
+use crate::object_map::{self, ObjectMapFormat};
+use crate::quarantine::{QuarantineState, QuarantineStore, Subject};
+use crate::sui_publisher::SuiPublisher;
+use crate::{alerts, schema, service, sui_publisher, symbol_registry};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "neo_oracle_mvp",
+    version,
+    about = "Neo Oracle MVP price publisher"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the oracle: fetch, aggregate and publish prices continuously (default).
+    Run,
+    /// Read all known PriceObjects in one batched RPC call and report their on-chain state.
+    ChainStatus {
+        /// Print a machine-readable `FeedStatusList` instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export or import the feed -> PriceObject ID map (`sui.known_objects_file`).
+    Objects {
+        #[command(subcommand)]
+        command: ObjectsCommand,
+    },
+    /// Show every recorded configuration change of one feed.
+    ConfigHistory {
+        symbol: String,
+        /// Print the raw history entries as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Manage the package's consumer allowlist and fees through the `[consumers]` templates.
+    Consumers {
+        #[command(subcommand)]
+        command: ConsumersCommand,
+    },
+    /// List the `[assets]` coin types with their feeds, or register them on-chain through
+    /// `asset_registry.register`.
+    Assets {
+        #[command(subcommand)]
+        command: AssetsCommand,
+    },
+    /// Create the PriceObjects of every configured feed and index that has none yet.
+    Bootstrap {
+        /// Keep creating the remaining objects after one fails.
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// List the keystore's accounts with their `sui.aliases` aliases and SUI balances.
+    Accounts {
+        /// Print the accounts as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Publish synthetic feeds in a loop and report cycle times, RPC volume and memory use.
+    Loadtest {
+        /// Number of synthetic symbols.
+        #[arg(long, default_value_t = 100)]
+        symbols: usize,
+        #[arg(long, default_value_t = 10)]
+        cycles: u64,
+        #[arg(long, value_enum, default_value_t = Target::Mock)]
+        target: Target,
+        /// Simulated latency of each RPC request with `--target mock`.
+        #[arg(long, default_value_t = 50)]
+        rpc_latency_ms: u64,
+        /// Seed of the synthetic prices; defaults to `general.random_seed`, else random.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Register the oracle as a systemd (Linux) or launchd (macOS) service, run from the
+    /// current directory, and start it.
+    InstallService {
+        #[arg(long, default_value = service::DEFAULT_NAME)]
+        name: String,
+        /// Account the service runs as; defaults to root.
+        #[arg(long)]
+        user: Option<String>,
+        /// Environment variable for the service, repeatable.
+        #[arg(long = "env", value_name = "KEY=VALUE")]
+        env: Vec<String>,
+        /// Replace an existing unit of the same name.
+        #[arg(long)]
+        force: bool,
+        /// Print the unit instead of installing it.
+        #[arg(long)]
+        print: bool,
+    },
+    /// Stop the service and remove its unit.
+    UninstallService {
+        #[arg(long, default_value = service::DEFAULT_NAME)]
+        name: String,
+    },
+    /// Inspect or clear quarantined sources and tripped feed circuit breakers.
+    Quarantine {
+        #[command(subcommand)]
+        command: QuarantineCommand,
+    },
+    /// Digest the deployed package's bytecode and check it against
+    /// `sui.expected_package_digest`, alerting on a mismatch.
+    VerifyPackage {
+        /// Print the module and package digests as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Back up the deployment's state to one encrypted archive, or restore it on a new host.
+    State {
+        #[command(subcommand)]
+        command: StateCommand,
+    },
+    /// Write TypeScript or Rust helpers that read this deployment's PriceObjects.
+    GenBindings {
+        #[arg(long, value_enum, default_value_t = Language::Typescript)]
+        lang: Language,
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConsumersCommand {
+    /// Allow an address to read the feeds.
+    Add { address: String },
+    /// Revoke an address's access.
+    Remove { address: String },
+    /// Withdraw collected fees.
+    WithdrawFees {
+        /// Address receiving the fees; defaults to the publisher address.
+        #[arg(long)]
+        recipient: Option<String>,
+        /// Amount in MIST, for templates that take `{amount}`.
+        #[arg(long)]
+        amount: Option<u64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AssetsCommand {
+    /// Show each coin type, its feed and the feed's PriceObject.
+    List {
+        /// Print an `AssetList` as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Register every asset with the package's on-chain registry.
+    Publish,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QuarantineCommand {
+    /// Show quarantined sources and feeds, and those failing towards quarantine.
+    List {
+        /// Print the quarantine file's state as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-admit sources or feeds, e.g. `source:binance` or `feed:BTC/USD`.
+    Clear {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        subjects: Vec<Subject>,
+        /// Clear every entry and failure count.
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StateCommand {
+    /// Write the PriceObject maps, registered feeds, quarantines, outbox, configuration and
+    /// its history to an archive encrypted with the passphrase in `--passphrase-env`.
+    Backup {
+        output: PathBuf,
+        /// Also archive `sui.keystore_file` and its aliases.
+        #[arg(long)]
+        include_keys: bool,
+        #[arg(long, default_value = backup::PASSPHRASE_ENV)]
+        passphrase_env: String,
+    },
+    /// Write an archive's files back to where they were backed up from.
+    Restore {
+        file: PathBuf,
+        /// Overwrite files that already exist.
+        #[arg(long)]
+        force: bool,
+        #[arg(long, default_value = backup::PASSPHRASE_ENV)]
+        passphrase_env: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ObjectsCommand {
+    /// Write the known-objects map to stdout or a file.
+    Export {
+        #[arg(long, value_enum, default_value_t = ObjectMapFormat::Csv)]
+        format: ObjectMapFormat,
+        /// Write to this file instead of stdout.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Merge an exported map into the known-objects file after checking every object on chain.
+    Import {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ObjectMapFormat::Csv)]
+        format: ObjectMapFormat,
+        /// Allow remapping symbols that already point at a different object.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn format_staleness(staleness_ms: u64) -> String {
+    if staleness_ms < 1_000 {
+        format!("{}ms", staleness_ms)
+    } else if staleness_ms < 120_000 {
+        format!("{:.1}s", staleness_ms as f64 / 1_000.0)
+    } else {
+        format!("{:.1}m", staleness_ms as f64 / 60_000.0)
+    }
+}
+
+pub async fn chain_status(json: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let states = sui_publisher::chain_status(&settings.sui).await?;
+    if json {
+        let list = schema::FeedStatusList {
+            schema_version: schema::SCHEMA_VERSION.to_string(),
+            feeds: states.into_iter().map(Into::into).collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&list)?);
+        return Ok(());
+    }
+    if states.is_empty() {
+        println!("No known PriceObjects. Run the oracle once to create them.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<68} {:>10} {:>18} {:>15} {:>10}",
+        "SYMBOL", "OBJECT ID", "VERSION", "PRICE", "TIMESTAMP (ms)", "STALENESS"
+    );
+    for state in states {
+        if let Some(error) = &state.error {
+            println!(
+                "{:<12} {:<68} error: {}",
+                state.symbol, state.object_id, error
+            );
+            continue;
+        }
+        println!(
+            "{:<12} {:<68} {:>10} {:>18} {:>15} {:>10}",
+            state.symbol,
+            state.object_id.to_string(),
+            state.version.map_or("-".to_string(), |v| v.to_string()),
+            state.price.map_or("-".to_string(), |p| format!(
+                "{:.*}",
+                state.decimals.unwrap_or(2) as usize,
+                p
+            )),
+            state
+                .timestamp_ms
+                .map_or("-".to_string(), |t| t.to_string()),
+            state.staleness_ms.map_or("-".to_string(), format_staleness),
+        );
+    }
+    Ok(())
+}
+
+pub async fn objects(command: ObjectsCommand) -> Result<()> {
+    let settings = Settings::load()?;
+    let known_objects_file = &settings.sui.known_objects_file;
+    match command {
+        ObjectsCommand::Export { format, output } => {
+            let objects: BTreeMap<String, _> =
+                sui_publisher::load_known_objects(known_objects_file)?
+                    .into_iter()
+                    .collect();
+            let rendered = object_map::render(&objects, format)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, rendered)
+                        .context(format!("Failed to write {}", path.display()))?;
+                    eprintln!("Exported {} objects to {}", objects.len(), path.display());
+                }
+                None => print!("{}", rendered),
+            }
+        }
+        ObjectsCommand::Import {
+            file,
+            format,
+            force,
+        } => {
+            let content =
+                fs::read_to_string(&file).context(format!("Failed to read {}", file.display()))?;
+            let imported = object_map::parse(&content, format)
+                .context(format!("Invalid object map {}", file.display()))?;
+
+            // Every imported ID must be a readable PriceObject on the configured network.
+            let states = sui_publisher::read_feed_states(
+                &settings.sui,
+                &imported.clone().into_iter().collect(),
+            )
+            .await?;
+            let invalid: Vec<String> = states
+                .iter()
+                .filter_map(|s| {
+                    let error = s.error.as_ref()?;
+                    Some(format!("{} ({}): {}", s.symbol, s.object_id, error))
+                })
+                .collect();
+            if !invalid.is_empty() {
+                return Err(anyhow!(
+                    "Not valid PriceObjects on {}:\n  {}",
+                    settings.sui.rpc_url,
+                    invalid.join("\n  ")
+                ));
+            }
+
+            let mut known = sui_publisher::load_known_objects(known_objects_file)?;
+            let changed = object_map::merge(&mut known, &imported, force)?;
+            sui_publisher::save_known_objects(known_objects_file, &known)?;
+            println!(
+                "Imported {} objects ({} added or changed) into {}",
+                imported.len(),
+                changed,
+                known_objects_file
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn config_history(symbol: &str, json: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let entries = config_history::history(&settings.general.config_history_file, symbol)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("No recorded configuration for {}.", symbol);
+        return Ok(());
+    }
+    let mut previous: Option<&config_history::FeedConfig> = None;
+    for entry in &entries {
+        let at =
+            chrono::DateTime::<chrono::Utc>::from_timestamp_millis(entry.recorded_at_ms as i64)
+                .map_or_else(|| entry.recorded_at_ms.to_string(), |t| t.to_rfc3339());
+        match previous {
+            None => println!("{}  initial: {}", at, serde_json::to_string(&entry.config)?),
+            Some(old) => {
+                for change in config_history::changed_fields(old, &entry.config) {
+                    println!("{}  {}", at, change);
+                }
+            }
+        }
+        previous = Some(&entry.config);
+    }
+    Ok(())
+}
+
+pub fn quarantine(command: QuarantineCommand) -> Result<()> {
+    let settings = Settings::load()?;
+    let store = QuarantineStore::new(&settings.quarantine.file);
+    match command {
+        QuarantineCommand::List { json } => {
+            let state = store.load()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&state)?);
+                return Ok(());
+            }
+            let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+            if state.entries.is_empty() && state.failures.is_empty() {
+                println!("Nothing is quarantined.");
+            }
+            for entry in state.entries.values() {
+                let status = if entry.until_ms > now_ms {
+                    format!("quarantined until {}", entry.until())
+                } else {
+                    "expired, re-admitted next cycle".to_string()
+                };
+                println!("{}  {}  {}", entry.subject(), status, entry.reason);
+            }
+            for (subject, failures) in &state.failures {
+                if !state.entries.contains_key(subject) {
+                    println!("{}  failing: {} consecutive failures", subject, failures);
+                }
+            }
+        }
+        QuarantineCommand::Clear { subjects, all } => {
+            let cleared = store.update(|state| {
+                if all {
+                    let cleared = state.entries.len();
+                    *state = QuarantineState::default();
+                    return cleared;
+                }
+                subjects.iter().filter(|s| state.clear(s)).count()
+            })?;
+            println!(
+                "Cleared {} quarantined entries; a running oracle re-admits them from its next cycle.",
+                cleared
+            );
+        }
+    }
+    Ok(())
+}
+
+fn parse_address(value: &str) -> Result<SuiAddress> {
+    SuiAddress::from_str(value).map_err(|e| anyhow!("Invalid address '{}': {}", value, e))
+}
+
+pub async fn consumers(command: ConsumersCommand) -> Result<()> {
+    let settings = Settings::load()?;
+    let (name, template, values) = match command {
+        ConsumersCommand::Add { address } => (
+            "add",
+            &settings.consumers.add,
+            TemplateValues {
+                consumer: Some(parse_address(&address)?),
+                ..Default::default()
+            },
+        ),
+        ConsumersCommand::Remove { address } => (
+            "remove",
+            &settings.consumers.remove,
+            TemplateValues {
+                consumer: Some(parse_address(&address)?),
+                ..Default::default()
+            },
+        ),
+        ConsumersCommand::WithdrawFees { recipient, amount } => {
+            let recipient = match recipient {
+                Some(r) => parse_address(&r)?,
+                None => sui_publisher::publisher_address(&settings.sui)?,
+            };
+            (
+                "withdraw_fees",
+                &settings.consumers.withdraw_fees,
+                TemplateValues {
+                    recipient: Some(recipient),
+                    amount,
+                    ..Default::default()
+                },
+            )
+        }
+    };
+    let template = template.as_ref().ok_or_else(|| {
+        anyhow!(
+            "consumers.{} is not configured; the deployed package must support it",
+            name
+        )
+    })?;
+
+    let args = consumers::resolve_args(template, &values)?;
+    let digest = sui_publisher::execute_move_call(
+        &settings.sui,
+        &template.module,
+        &template.function,
+        &args,
+    )
+    .await?;
+    println!(
+        "{}::{} succeeded. Digest: {}",
+        template.module, template.function, digest
+    );
+    Ok(())
+}
+
+pub async fn assets(command: AssetsCommand) -> Result<()> {
+    let settings = Settings::load()?;
+    let assets = assets::list(&settings)?;
+    match command {
+        AssetsCommand::List { json } => {
+            if json {
+                let list = schema::AssetList {
+                    schema_version: schema::SCHEMA_VERSION.to_string(),
+                    assets,
+                };
+                println!("{}", serde_json::to_string_pretty(&list)?);
+                return Ok(());
+            }
+            if assets.is_empty() {
+                println!("No assets configured.");
+            }
+            for asset in &assets {
+                println!(
+                    "{}  {}  decimals {}  {}",
+                    asset.coin_type,
+                    asset.feed,
+                    asset.decimals.map_or("-".to_string(), |d| d.to_string()),
+                    asset.object_id.as_deref().unwrap_or("no PriceObject")
+                );
+            }
+        }
+        AssetsCommand::Publish => {
+            let template = settings.asset_registry.register.as_ref().ok_or_else(|| {
+                anyhow!("asset_registry.register is not configured; the deployed package must support it")
+            })?;
+            // Resolve every asset before sending anything, so a bad entry registers none.
+            let calls = assets
+                .iter()
+                .map(|asset| Ok((asset, assets::resolve_args(template, asset)?)))
+                .collect::<Result<Vec<_>>>()?;
+            for (asset, args) in calls {
+                let digest = sui_publisher::execute_move_call(
+                    &settings.sui,
+                    &template.module,
+                    &template.function,
+                    &args,
+                )
+                .await
+                .context(format!("Failed to register {}", asset.coin_type))?;
+                println!(
+                    "Registered {} as {}. Digest: {}",
+                    asset.coin_type, asset.feed, digest
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum BootstrapStatus {
+    /// Already in the known-objects file.
+    Existing(String),
+    Created(String),
+    Failed(String),
+    /// Not tried, because an earlier creation failed.
+    NotAttempted,
+}
+
+impl BootstrapStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            BootstrapStatus::Existing(_) => "existing",
+            BootstrapStatus::Created(_) => "created",
+            BootstrapStatus::Failed(_) => "failed",
+            BootstrapStatus::NotAttempted => "not attempted",
+        }
+    }
+}
+
+/// The error to exit with, if any creation failed.
+fn bootstrap_error(report: &[(&str, BootstrapStatus)]) -> Option<anyhow::Error> {
+    let failed = report
+        .iter()
+        .filter(|(_, s)| matches!(s, BootstrapStatus::Failed(_)))
+        .count();
+    if failed == 0 {
+        return None;
+    }
+    let not_attempted = report
+        .iter()
+        .filter(|(_, s)| *s == BootstrapStatus::NotAttempted)
+        .count();
+    let mut message = format!(
+        "{} of {} PriceObjects could not be created",
+        failed,
+        report.len()
+    );
+    if not_attempted > 0 {
+        message.push_str(&format!(
+            " and {} were not attempted; rerun to resume, or add --continue-on-error",
+            not_attempted
+        ));
+    }
+    Some(anyhow!(message))
+}
+
+pub async fn bootstrap(continue_on_error: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let publisher = SuiPublisher::connect(settings.sui.clone())
+        .await?
+        .with_decimals(settings.feed_decimals());
+    let known = sui_publisher::load_known_objects(&settings.sui.known_objects_file)?;
+    let derived_feeds = settings.derived_feeds();
+    let symbols = settings
+        .feed_symbols()
+        .into_iter()
+        .chain(derived_feeds.iter().map(String::as_str))
+        .chain(settings.computed_feeds());
+
+    let mut report = Vec::new();
+    let mut stopped = false;
+    for symbol in symbols {
+        let status = if let Some(object_id) = known.get(symbol) {
+            BootstrapStatus::Existing(object_id.to_string())
+        } else if stopped {
+            BootstrapStatus::NotAttempted
+        } else {
+            match publisher.ensure_price_object(symbol).await {
+                Ok(object_id) => BootstrapStatus::Created(object_id),
+                Err(e) => {
+                    log::error!("Failed to create the PriceObject of {}: {:?}", symbol, e);
+                    stopped = !continue_on_error;
+                    BootstrapStatus::Failed(format!("{:#}", e))
+                }
+            }
+        };
+        report.push((symbol, status));
+    }
+
+    println!("{:<12} {:<14} OBJECT ID / ERROR", "SYMBOL", "STATUS");
+    for (symbol, status) in &report {
+        let detail = match status {
+            BootstrapStatus::Existing(id) | BootstrapStatus::Created(id) => id.clone(),
+            BootstrapStatus::Failed(error) => error.clone(),
+            BootstrapStatus::NotAttempted => "-".to_string(),
+        };
+        println!("{:<12} {:<14} {}", symbol, status.label(), detail);
+    }
+    match bootstrap_error(&report) {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+pub async fn verify_package(json: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let digests = sui_publisher::package_digests(&settings.sui).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&digests)?);
+    } else {
+        println!(
+            "Package {} (version {})",
+            digests.package_id, digests.version
+        );
+        for (module, digest) in &digests.modules {
+            println!("  {:<24} {}", module, digest);
+        }
+        println!("Package digest: {}", digests.digest);
+    }
+    let Some(expected) = &settings.sui.expected_package_digest else {
+        eprintln!(
+            "sui.expected_package_digest is not set; pin {} to detect bytecode changes",
+            digests.digest
+        );
+        return Ok(());
+    };
+    if let Err(e) = digests.verify(expected) {
+        let alerter = alerts::Alerter::new(&settings.alerts, settings.limits.max_pending_alerts);
+        alerter.send(&alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind: "package_mismatch",
+            symbol: digests.package_id.clone(),
+            message: format!("{:#}", e),
+            timestamp_ms: chrono::Utc::now().timestamp_millis() as u64,
+        });
+        alerter.flush().await;
+        return Err(e);
+    }
+    eprintln!("Package matches sui.expected_package_digest");
+    Ok(())
+}
+
+fn backup_passphrase(env: &str) -> Result<String> {
+    std::env::var(env)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow!("Set ${} to the backup passphrase", env))
+}
+
+pub fn state(command: StateCommand) -> Result<()> {
+    match command {
+        StateCommand::Backup {
+            output,
+            include_keys,
+            passphrase_env,
+        } => {
+            let settings = Settings::load()?;
+            let passphrase = backup_passphrase(&passphrase_env)?;
+            let files = backup::state_files(&settings, include_keys);
+            let archive = backup::collect(&files, chrono::Utc::now().timestamp_millis() as u64)?;
+            backup::write_private(&output, &backup::seal(&archive, &passphrase)?)?;
+            for file in &archive.files {
+                eprintln!("{:?}: {}", file.role, file.path);
+            }
+            eprintln!(
+                "Backed up {} files to {}",
+                archive.files.len(),
+                output.display()
+            );
+            if include_keys && settings.sui.keystore_file.is_none() {
+                eprintln!(
+                    "The publisher key is read from ${} and is not in the backup",
+                    settings.sui.private_key_env
+                );
+            }
+        }
+        // The configuration may be among what is restored, so it is not loaded.
+        StateCommand::Restore {
+            file,
+            force,
+            passphrase_env,
+        } => {
+            let passphrase = backup_passphrase(&passphrase_env)?;
+            let sealed = fs::read(&file).context(format!("Failed to read {}", file.display()))?;
+            let archive = backup::open(&sealed, &passphrase)?;
+            let taken_at = chrono::DateTime::from_timestamp_millis(archive.created_at_ms as i64)
+                .map_or("-".to_string(), |t| t.to_rfc3339());
+            eprintln!(
+                "Restoring a backup of version {} taken at {}",
+                archive.version, taken_at
+            );
+            for restored in backup::restore(&archive, force)? {
+                eprintln!("{}", restored);
+            }
+            eprintln!("Restored {} files", archive.files.len());
+        }
+    }
+    Ok(())
+}
+
+pub fn gen_bindings(lang: Language, output: Option<PathBuf>) -> Result<()> {
+    // Feeds registered at runtime are part of the deployment too.
+    let settings = symbol_registry::with_registered(Settings::load()?)?;
+    let known = sui_publisher::load_known_objects(&settings.sui.known_objects_file)?;
+    let mut feeds = BTreeMap::new();
+    let derived_feeds = settings.derived_feeds();
+    for symbol in settings
+        .feed_symbols()
+        .into_iter()
+        .chain(derived_feeds.iter().map(String::as_str))
+        .chain(settings.computed_feeds())
+    {
+        match known.get(symbol) {
+            Some(object_id) => {
+                let feed = bindings::Feed {
+                    object_id: object_id.to_string(),
+                    decimals: settings.decimals(symbol),
+                };
+                feeds.insert(symbol.to_string(), feed);
+            }
+            None => eprintln!("Skipping {}: no PriceObject yet (see `bootstrap`)", symbol),
+        }
+    }
+    if feeds.is_empty() {
+        return Err(anyhow!(
+            "No configured feed has a PriceObject in {}",
+            settings.sui.known_objects_file
+        ));
+    }
+    let deployment = bindings::Deployment {
+        package_id: settings.sui.package_id.clone(),
+        module: sui_publisher::MODULE_NAME.to_string(),
+        rpc_url: settings.sui.rpc_url.clone(),
+        feeds,
+    };
+    let rendered = bindings::render(&deployment, lang);
+    match output {
+        Some(path) => {
+            fs::write(&path, rendered).context(format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "Wrote {:?} bindings for {} feeds to {}",
+                lang,
+                deployment.feeds.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// MIST per SUI.
+const MIST_PER_SUI: u128 = 1_000_000_000;
+
+fn format_sui(mist: u128) -> String {
+    format!("{}.{:09}", mist / MIST_PER_SUI, mist % MIST_PER_SUI)
+}
+
+#[derive(Debug, Serialize)]
+struct AccountRow {
+    alias: Option<String>,
+    address: String,
+    balance_mist: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance_error: Option<String>,
+    publisher: bool,
+}
+
+pub async fn accounts(json: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let publisher = sui_publisher::publisher_address(&settings.sui)?;
+    let accounts = match &settings.sui.keystore_file {
+        Some(path) => keystore::accounts(path, settings.sui.fix_keystore_permissions)?,
+        None => vec![keystore::Account {
+            alias: None,
+            address: publisher,
+        }],
+    };
+    let addresses: Vec<SuiAddress> = accounts.iter().map(|a| a.address).collect();
+    let balances = sui_publisher::sui_balances(&settings.sui, &addresses).await?;
+    let rows: Vec<AccountRow> = accounts
+        .into_iter()
+        .zip(balances)
+        .map(|(account, balance)| AccountRow {
+            publisher: account.address == publisher,
+            alias: account.alias,
+            address: account.address.to_string(),
+            balance_error: balance.as_ref().err().map(|e| e.to_string()),
+            balance_mist: balance.ok(),
+        })
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<68} {:>22}  PUBLISHER",
+        "ALIAS", "ADDRESS", "BALANCE (SUI)"
+    );
+    for row in rows {
+        println!(
+            "{:<16} {:<68} {:>22}  {}",
+            row.alias.as_deref().unwrap_or("-"),
+            row.address,
+            match (row.balance_mist, &row.balance_error) {
+                (Some(mist), _) => format_sui(mist),
+                (None, Some(error)) => format!("error: {}", error),
+                (None, None) => "-".to_string(),
+            },
+            if row.publisher { "*" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+pub fn install_service(
+    name: String,
+    user: Option<String>,
+    env: &[String],
+    force: bool,
+    print: bool,
+) -> Result<()> {
+    let settings = Settings::load()?;
+    let working_dir = std::env::current_dir()?.canonicalize()?;
+    let mut env = env
+        .iter()
+        .map(|arg| service::parse_env(arg))
+        .collect::<Result<Vec<_>>>()?;
+    if !env.iter().any(|(key, _)| key == "RUST_LOG") {
+        env.insert(0, ("RUST_LOG".to_string(), "info".to_string()));
+    }
+    // State written outside the working directory must stay writable under the hardening.
+    let mut writable_paths: Vec<PathBuf> = Vec::new();
+    let dirs = [
+        Some(settings.general.state_dir.as_str()),
+        Some(settings.debug_bundles.dir.as_str()),
+        std::path::Path::new(&settings.general.config_history_file)
+            .parent()
+            .and_then(|p| p.to_str()),
+        std::path::Path::new(&settings.admin.audit_log_file)
+            .parent()
+            .and_then(|p| p.to_str()),
+    ];
+    for dir in dirs.into_iter().flatten().map(PathBuf::from) {
+        if dir.is_absolute() && !dir.starts_with(&working_dir) && !writable_paths.contains(&dir) {
+            writable_paths.push(dir);
+        }
+    }
+    let spec = service::ServiceSpec {
+        name,
+        executable: std::env::current_exe()?.canonicalize()?,
+        working_dir,
+        user,
+        env,
+        writable_paths,
+    };
+
+    let platform = service::Platform::current()?;
+    if print {
+        print!("{}", platform.render(&spec));
+        return Ok(());
+    }
+    let path = service::install(platform, &spec, force)?;
+    println!("Installed and started {} ({})", spec.name, path.display());
+    if platform == service::Platform::Systemd {
+        println!(
+            "Put secrets such as ${} in /etc/{}/env (mode 0600), not in --env.",
+            settings.admin.token_env, spec.name
+        );
+    }
+    Ok(())
+}
+
+pub fn uninstall_service(name: &str) -> Result<()> {
+    let path = service::uninstall(service::Platform::current()?, name)?;
+    println!("Stopped {} and removed {}", name, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sui_sdk::types::base_types::ObjectID;
+
+    #[test]
+    fn test_parse_defaults_to_no_subcommand() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp"]).unwrap();
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_parse_chain_status() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "chain-status"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::ChainStatus { json: false })
+        ));
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "chain-status", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::ChainStatus { json: true })
+        ));
+    }
+
+    #[test]
+    fn test_parse_objects_commands() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "objects", "export", "--format", "json"])
+            .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Objects {
+                command: ObjectsCommand::Export {
+                    format: ObjectMapFormat::Json,
+                    output: None
+                }
+            })
+        ));
+        let cli =
+            Cli::try_parse_from(["neo_oracle_mvp", "objects", "import", "map.csv", "--force"])
+                .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Objects {
+                command: ObjectsCommand::Import {
+                    format: ObjectMapFormat::Csv,
+                    force: true,
+                    ..
+                }
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_consumers_commands() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "consumers", "add", "0x2"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Consumers {
+                command: ConsumersCommand::Add { .. }
+            })
+        ));
+        let cli = Cli::try_parse_from([
+            "neo_oracle_mvp",
+            "consumers",
+            "withdraw-fees",
+            "--amount",
+            "1000",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Consumers {
+                command: ConsumersCommand::WithdrawFees {
+                    recipient: None,
+                    amount: Some(1000)
+                }
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_assets_commands() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "assets", "list", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Assets {
+                command: AssetsCommand::List { json: true }
+            })
+        ));
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "assets", "publish"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Assets {
+                command: AssetsCommand::Publish
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_config_history() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "config-history", "BTC/USD"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::ConfigHistory { symbol, json: false }) if symbol == "BTC/USD"
+        ));
+    }
+
+    #[test]
+    fn test_bootstrap_error() {
+        let id = ObjectID::from_single_byte(1).to_string();
+        let mut report = vec![
+            ("BTC/USD", BootstrapStatus::Existing(id.clone())),
+            ("ETH/USD", BootstrapStatus::Created(id)),
+        ];
+        assert!(bootstrap_error(&report).is_none());
+        report.push(("SOL/USD", BootstrapStatus::Failed("rpc".to_string())));
+        report.push(("TOP2", BootstrapStatus::NotAttempted));
+        assert_eq!(
+            bootstrap_error(&report).unwrap().to_string(),
+            "1 of 4 PriceObjects could not be created and 1 were not attempted; \
+             rerun to resume, or add --continue-on-error"
+        );
+
+        let cli =
+            Cli::try_parse_from(["neo_oracle_mvp", "bootstrap", "--continue-on-error"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Bootstrap {
+                continue_on_error: true
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_accounts() {
+        let cli = Cli::try_parse_from(["neo_oracle_mvp", "accounts", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Accounts { json: true })
+        ));
+        assert_eq!(format_sui(1_500_000_000), "1.500000000");
+        assert_eq!(format_sui(42), "0.000000042");
+    }
+
+    #[test]
+    fn test_parse_state_commands() {
+        let cli =
+            Cli::try_parse_from(["neo_oracle_mvp", "state", "backup", "oracle.backup"]).unwrap();
+        match cli.command {
+            Some(Command::State {
+                command:
+                    StateCommand::Backup {
+                        include_keys,
+                        passphrase_env,
+                        ..
+                    },
+            }) => {
+                assert!(!include_keys);
+                assert_eq!(passphrase_env, backup::PASSPHRASE_ENV);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let cli = Cli::try_parse_from([
+            "neo_oracle_mvp",
+            "state",
+            "restore",
+            "oracle.backup",
+            "--force",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::State {
+                command: StateCommand::Restore { force: true, .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn test_format_staleness() {
+        assert_eq!(format_staleness(250), "250ms");
+        assert_eq!(format_staleness(4_500), "4.5s");
+        assert_eq!(format_staleness(180_000), "3.0m");
+    }
+}
@@ -0,0 +1,246 @@
+use chrono::DateTime;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Weight given to each new offset observation in the moving average.
+/// HTTP `Date` headers only carry whole seconds, so individual observations are
+/// noisy and need smoothing before they are trusted.
+const SMOOTHING_ALPHA: f64 = 0.2;
+
+/// Returns the local wall-clock time in milliseconds since the Unix epoch.
+pub fn local_now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// `timestamp_ms` rounded to the nearest multiple of `granularity_ms` (halves round up);
+/// unchanged when `granularity_ms` is `0`.
+pub fn round_to_granularity(timestamp_ms: u64, granularity_ms: u64) -> u64 {
+    if granularity_ms == 0 {
+        return timestamp_ms;
+    }
+    (timestamp_ms + granularity_ms / 2) / granularity_ms * granularity_ms
+}
+
+/// The timestamp to publish an update computed now with.
+pub fn publish_timestamp_ms(granularity_ms: u64) -> u64 {
+    round_to_granularity(local_now_ms() as u64, granularity_ms)
+}
+
+/// Parses an HTTP `Date` header (RFC 7231 IMF-fixdate, e.g. `Tue, 15 Nov 1994 08:12:31 GMT`)
+/// into milliseconds since the Unix epoch.
+pub fn parse_http_date_ms(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+/// Parses an RFC 3339 timestamp as returned by exchange APIs (e.g. Coinbase `time`).
+pub fn parse_rfc3339_ms(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DriftEstimate {
+    offset_ms: f64,
+    samples: u64,
+}
+
+/// Tracks the estimated clock offset (server time minus local time) of each exchange.
+///
+/// - Offsets are learned from server timestamps observed on responses.
+/// - Source timestamps are shifted by the learned offset before their age is evaluated,
+///   so a venue whose clock runs ahead or behind isn't mistaken for serving stale data.
+#[derive(Debug, Default)]
+pub struct ClockDriftTracker {
+    estimates: Mutex<HashMap<String, DriftEstimate>>,
+}
+
+impl ClockDriftTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a server timestamp observed on a response.
+    ///
+    /// The server is assumed to have stamped the response halfway through the round trip.
+    /// Timestamps with whole-second resolution should go through `observe_http_date`.
+    pub fn observe(
+        &self,
+        exchange: &str,
+        server_time_ms: i64,
+        request_sent_ms: i64,
+        response_received_ms: i64,
+    ) {
+        let local_midpoint_ms = (request_sent_ms + response_received_ms) as f64 / 2.0;
+        let observed_offset_ms = server_time_ms as f64 - local_midpoint_ms;
+
+        let mut estimates = self.estimates.lock().unwrap();
+        let estimate = estimates
+            .entry(exchange.to_string())
+            .and_modify(|e| {
+                e.offset_ms += SMOOTHING_ALPHA * (observed_offset_ms - e.offset_ms);
+                e.samples += 1;
+            })
+            .or_insert(DriftEstimate {
+                offset_ms: observed_offset_ms,
+                samples: 1,
+            });
+        log::debug!(
+            "Clock offset for {}: observed {:.0} ms, estimated {:.0} ms ({} samples)",
+            exchange,
+            observed_offset_ms,
+            estimate.offset_ms,
+            estimate.samples
+        );
+    }
+
+    /// Records the `Date` header of a response. The header is truncated to whole seconds,
+    /// so the middle of that second is used as the server time.
+    pub fn observe_http_date(
+        &self,
+        exchange: &str,
+        date_header: &str,
+        request_sent_ms: i64,
+        response_received_ms: i64,
+    ) {
+        match parse_http_date_ms(date_header) {
+            Some(server_time_ms) => self.observe(
+                exchange,
+                server_time_ms + 500,
+                request_sent_ms,
+                response_received_ms,
+            ),
+            None => log::debug!(
+                "Ignoring unparsable Date header from {}: {}",
+                exchange,
+                date_header
+            ),
+        }
+    }
+
+    /// Estimated offset of the exchange clock relative to ours, in milliseconds.
+    /// Positive values mean the exchange clock runs ahead. Unknown exchanges report 0.
+    pub fn offset_ms(&self, exchange: &str) -> i64 {
+        self.estimates
+            .lock()
+            .unwrap()
+            .get(exchange)
+            .map_or(0, |e| e.offset_ms.round() as i64)
+    }
+
+    /// Age of a source timestamp expressed on our local clock, after removing the
+    /// exchange's estimated offset. Never negative.
+    pub fn corrected_age_ms(
+        &self,
+        exchange: &str,
+        source_timestamp_ms: i64,
+        local_now_ms: i64,
+    ) -> u64 {
+        let local_equivalent_ms = source_timestamp_ms - self.offset_ms(exchange);
+        (local_now_ms - local_equivalent_ms).max(0) as u64
+    }
+
+    /// Whether a sample stamped by the exchange at `source_timestamp_ms` is no older than
+    /// `max_age_ms` once clock drift is accounted for.
+    pub fn is_fresh(&self, exchange: &str, source_timestamp_ms: i64, max_age_ms: u64) -> bool {
+        self.corrected_age_ms(exchange, source_timestamp_ms, local_now_ms()) <= max_age_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_to_granularity() {
+        assert_eq!(
+            round_to_granularity(1_700_000_000_499, 1_000),
+            1_700_000_000_000
+        );
+        assert_eq!(
+            round_to_granularity(1_700_000_000_500, 1_000),
+            1_700_000_001_000
+        );
+        assert_eq!(
+            round_to_granularity(1_700_000_004_999, 5_000),
+            1_700_000_005_000
+        );
+        assert_eq!(
+            round_to_granularity(1_700_000_000_123, 0),
+            1_700_000_000_123
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date_ms("Thu, 01 Jan 1970 00:00:10 GMT"),
+            Some(10_000)
+        );
+        assert_eq!(parse_http_date_ms("not a date"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339_ms("1970-01-01T00:00:01.250Z"), Some(1_250));
+        assert_eq!(parse_rfc3339_ms(""), None);
+    }
+
+    #[test]
+    fn test_unknown_exchange_has_zero_offset() {
+        let tracker = ClockDriftTracker::new();
+        assert_eq!(tracker.offset_ms("binance"), 0);
+    }
+
+    #[test]
+    fn test_first_observation_uses_round_trip_midpoint() {
+        let tracker = ClockDriftTracker::new();
+        // Request sent at 1_000, response at 1_200 -> midpoint 1_100; server says 4_100.
+        tracker.observe("coinbase", 4_100, 1_000, 1_200);
+        assert_eq!(tracker.offset_ms("coinbase"), 3_000);
+    }
+
+    #[test]
+    fn test_observations_are_smoothed() {
+        let tracker = ClockDriftTracker::new();
+        tracker.observe("binance", 1_000, 0, 0);
+        tracker.observe("binance", 2_000, 0, 0);
+        // 1000 + 0.2 * (2000 - 1000)
+        assert_eq!(tracker.offset_ms("binance"), 1_200);
+    }
+
+    #[test]
+    fn test_http_date_observation_uses_middle_of_second() {
+        let tracker = ClockDriftTracker::new();
+        tracker.observe_http_date("binance", "Thu, 01 Jan 1970 00:00:10 GMT", 10_000, 10_000);
+        assert_eq!(tracker.offset_ms("binance"), 500);
+    }
+
+    #[test]
+    fn test_skewed_exchange_is_not_stale_after_correction() {
+        let tracker = ClockDriftTracker::new();
+        // Exchange clock lags ours by 90 seconds.
+        tracker.observe("coinbase", 10_000, 100_000, 100_000);
+        let now = 200_000;
+        // A trade the exchange stamped 1 second ago on its own clock.
+        let source_ts = now - 90_000 - 1_000;
+        assert_eq!(tracker.corrected_age_ms("coinbase", source_ts, now), 1_000);
+
+        let uncorrected = ClockDriftTracker::new();
+        assert_eq!(
+            uncorrected.corrected_age_ms("coinbase", source_ts, now),
+            91_000
+        );
+    }
+
+    #[test]
+    fn test_future_timestamps_have_zero_age() {
+        let tracker = ClockDriftTracker::new();
+        assert_eq!(tracker.corrected_age_ms("okx", 5_000, 1_000), 0);
+    }
+}
@@ -1,33 +1,175 @@
-use crate::config::ExchangeConfig;
-use anyhow::Result;
+use crate::aggregator::{self, Quote};
+use crate::clock_drift::{self, ClockDriftTracker};
+use crate::config::{ExchangeConfig, RateLimitSettings, RequestRetrySettings};
+use crate::endpoints::{self, EndpointSelector};
+use crate::rate_limit;
+use crate::sources::{self, PriceSample, PriceSource, TradeFilter};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
 use reqwest::Client;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "ws-sources")]
+use crate::sources::PriceStream;
+#[cfg(feature = "ws-sources")]
+use crate::ws_stream::{self, StreamCache, StreamTrade};
 
 #[derive(Deserialize, Debug)]
 pub struct CoinbaseTickerResponse {
-    pub price: String,
-    // Coinbase API might return other fields like "trade_id", "size", "time", "bid", "ask", "volume"
-    // We only care about the price for now.
+    /// Coinbase sends prices as strings; a bare number means the response is not a ticker.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    /// Time of the last trade, as stamped by Coinbase's clock (RFC 3339).
+    #[serde(default)]
+    pub time: Option<String>,
+    /// Size of the last trade, in base currency.
+    #[serde(default)]
+    pub size: Option<String>,
+    // Coinbase API might return other fields like "trade_id", "bid", "ask", "volume"
+    // We only care about the last trade for now.
+}
+
+/// Level-1 order book: the best bid and ask, each as `[price, size, num_orders]`.
+#[derive(Deserialize, Debug)]
+pub struct CoinbaseBookResponse {
+    pub bids: Vec<(Decimal, String, serde_json::Value)>,
+    pub asks: Vec<(Decimal, String, serde_json::Value)>,
+}
+
+/// 24h product statistics; `volume` is in base currency.
+#[derive(Deserialize, Debug)]
+pub struct CoinbaseStatsResponse {
+    pub volume: String,
+}
+
+impl CoinbaseBookResponse {
+    /// Price and size of the best level of `side`.
+    fn best(side: &[(Decimal, String, serde_json::Value)], name: &str) -> Result<(Decimal, f64)> {
+        let (price, size, _) = side.first().ok_or_else(|| anyhow!("Empty {} side", name))?;
+        Ok((
+            *price,
+            size.parse()
+                .context(format!("Invalid {} size '{}'", name, size))?,
+        ))
+    }
+
+    fn notional(&self) -> Result<f64> {
+        let (bid_price, bid_size) = Self::best(&self.bids, "bid")?;
+        let (ask_price, ask_size) = Self::best(&self.asks, "ask")?;
+        Ok(aggregator::top_of_book_notional(
+            aggregator::as_f64(bid_price),
+            bid_size,
+            aggregator::as_f64(ask_price),
+            ask_size,
+        ))
+    }
+
+    fn quote(&self) -> Result<Quote> {
+        Ok(Quote {
+            bid: Self::best(&self.bids, "bid")?.0,
+            ask: Self::best(&self.asks, "ask")?.0,
+        })
+    }
 }
 
+/// A message of the WebSocket feed. Only `ticker` messages carry prices; `error` ones
+/// report a rejected subscription.
+#[cfg(feature = "ws-sources")]
+#[derive(Deserialize, Debug)]
+struct CoinbaseStreamMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    product_id: Option<String>,
+    #[serde(default)]
+    price: Option<Decimal>,
+    /// Size of the trade that set `price`, in base currency.
+    #[serde(default)]
+    last_size: Option<String>,
+    #[serde(default)]
+    time: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// The subscription to the `ticker` channel of `product_ids`.
+#[cfg(feature = "ws-sources")]
+fn ticker_subscription(product_ids: &[String]) -> String {
+    serde_json::json!({
+        "type": "subscribe",
+        "product_ids": product_ids,
+        "channels": ["ticker"],
+    })
+    .to_string()
+}
+
+#[cfg(feature = "ws-sources")]
+fn parse_ticker_message(text: &str) -> Result<Vec<StreamTrade>> {
+    let message: CoinbaseStreamMessage = serde_json::from_str(text)?;
+    match message.kind.as_str() {
+        "ticker" => {}
+        "error" => return Err(anyhow!("error: {}", message.message.unwrap_or_default())),
+        _ => return Ok(Vec::new()),
+    }
+    let (Some(ticker), Some(price)) = (message.product_id, message.price) else {
+        return Err(anyhow!("ticker message without product_id or price"));
+    };
+    Ok(vec![StreamTrade {
+        ticker,
+        sample: PriceSample {
+            price,
+            timestamp_ms: message
+                .time
+                .as_deref()
+                .and_then(clock_drift::parse_rfc3339_ms),
+        },
+        size: message.last_size.and_then(|s| s.parse().ok()),
+    }])
+}
+
+const EXCHANGE_NAME: &str = "coinbase";
+
+/// Coinbase Exchange allows 10 public requests a second per IP, in bursts of up to 15.
+pub const RATE_LIMIT: RateLimitSettings = RateLimitSettings {
+    weight_per_minute: 600,
+    burst: Some(15),
+    request_weight: 1,
+};
+
 async fn get_coinbase_ticker_price(
     client: &Client,
+    name: &str,
     base_url: &str,
     product_id: &str,
+    timeout: Duration,
+    clock_drift: &ClockDriftTracker,
 ) -> Result<CoinbaseTickerResponse> {
     // Construct URL from base_url and product_id
     let url = format!("{}/{}/ticker", base_url, product_id);
     log::debug!("Fetching price for {} from Coinbase: {}", product_id, url);
 
     // Coinbase API often requires a User-Agent header
+    let request_sent_ms = clock_drift::local_now_ms();
     let response = client
         .get(&url)
         .header("User-Agent", "neo-oracle-mvp") // Simple User-Agent
+        .timeout(timeout)
         .send()
         .await?;
+    let response_received_ms = clock_drift::local_now_ms();
 
-    response.error_for_status_ref()?; // Ensure we have a success status
+    let response = rate_limit::check(response)?;
+    if let Some(date) = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+    {
+        clock_drift.observe_http_date(name, date, request_sent_ms, response_received_ms);
+    }
     let ticker_response = response.json::<CoinbaseTickerResponse>().await?;
     log::info!(
         "Fetched price for {}: {}",
@@ -37,15 +179,243 @@ async fn get_coinbase_ticker_price(
     Ok(ticker_response)
 }
 
-pub async fn get_coinbase_prices(config: &ExchangeConfig) -> Result<HashMap<String, String>> {
-    let client = Client::new();
+async fn get_coinbase_book(
+    client: &Client,
+    base_url: &str,
+    product_id: &str,
+) -> Result<CoinbaseBookResponse> {
+    let url = format!("{}/{}/book?level=1", base_url, product_id);
+    log::debug!(
+        "Fetching order book for {} from Coinbase: {}",
+        product_id,
+        url
+    );
+    let response = client
+        .get(&url)
+        .header("User-Agent", "neo-oracle-mvp")
+        .send()
+        .await?;
+    let response = rate_limit::check(response)?;
+    Ok(response.json::<CoinbaseBookResponse>().await?)
+}
+
+/// Displayed top-of-book notional for each of `product_ids`, in quote currency. Products
+/// whose book could not be fetched are missing from the map.
+async fn get_coinbase_depth(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    product_ids: &[String],
+) -> Result<HashMap<String, f64>> {
+    let mut depth = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for product_id in product_ids {
+        let request = get_coinbase_book(client, &endpoint.base_url, product_id);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|book| book.notional())
+        {
+            Ok(notional) => {
+                depth.insert(product_id.clone(), notional);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch order book for {} from Coinbase: {}",
+                    product_id,
+                    e
+                );
+            }
+        }
+    }
+    Ok(depth)
+}
+
+/// Best bid and ask of each of `product_ids`. Products whose book could not be fetched are
+/// missing from the map.
+async fn get_coinbase_quotes(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    product_ids: &[String],
+) -> Result<HashMap<String, Quote>> {
+    let mut quotes = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for product_id in product_ids {
+        let request = get_coinbase_book(client, &endpoint.base_url, product_id);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|book| book.quote())
+        {
+            Ok(quote) => {
+                quotes.insert(product_id.clone(), quote);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch order book for {} from Coinbase: {}",
+                    product_id,
+                    e
+                );
+            }
+        }
+    }
+    Ok(quotes)
+}
+
+async fn get_coinbase_stats(
+    client: &Client,
+    base_url: &str,
+    product_id: &str,
+) -> Result<CoinbaseStatsResponse> {
+    let url = format!("{}/{}/stats", base_url, product_id);
+    log::debug!(
+        "Fetching 24h stats for {} from Coinbase: {}",
+        product_id,
+        url
+    );
+    let response = client
+        .get(&url)
+        .header("User-Agent", "neo-oracle-mvp")
+        .send()
+        .await?;
+    let response = rate_limit::check(response)?;
+    Ok(response.json::<CoinbaseStatsResponse>().await?)
+}
+
+/// Trailing 24h volume of each of `product_ids`, in base currency. Products whose stats
+/// could not be fetched are missing from the map.
+async fn get_coinbase_volume(
+    client: &Client,
+    name: &str,
+    config: &ExchangeConfig,
+    selector: &EndpointSelector,
+    product_ids: &[String],
+) -> Result<HashMap<String, f64>> {
+    let mut volume = HashMap::new();
+    let endpoint = selector.select(name, &endpoints::endpoints(config));
+    for product_id in product_ids {
+        let request = get_coinbase_stats(client, &endpoint.base_url, product_id);
+        match selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|stats| {
+                stats.volume.parse::<f64>().context(format!(
+                    "Invalid volume '{}' for {}",
+                    stats.volume, product_id
+                ))
+            }) {
+            Ok(base_volume) => {
+                volume.insert(product_id.clone(), base_volume);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to fetch 24h stats for {} from Coinbase: {}",
+                    product_id,
+                    e
+                );
+            }
+        }
+    }
+    Ok(volume)
+}
+
+/// Whether a ticker's last trade is recent enough to be used, after correcting
+/// for Coinbase's estimated clock offset. Tickers without a parsable time are kept.
+fn is_ticker_fresh(
+    response: &CoinbaseTickerResponse,
+    name: &str,
+    clock_drift: &ClockDriftTracker,
+    max_age_ms: u64,
+) -> bool {
+    match response
+        .time
+        .as_deref()
+        .and_then(clock_drift::parse_rfc3339_ms)
+    {
+        Some(trade_time_ms) => clock_drift.is_fresh(name, trade_time_ms, max_age_ms),
+        None => true,
+    }
+}
+
+async fn get_coinbase_prices(
+    source: &CoinbaseSource,
+    product_ids: &[String],
+) -> Result<HashMap<String, PriceSample>> {
+    let CoinbaseSource {
+        name,
+        config,
+        max_source_age_ms,
+        client,
+        retry,
+        clock_drift,
+        endpoints: selector,
+        trades,
+        ..
+    } = source;
+    let max_source_age_ms = *max_source_age_ms;
     let mut prices = HashMap::new();
+    let endpoints = endpoints::endpoints(config);
+    let endpoint = selector.select(name, &endpoints);
+    let timeout = Duration::from_millis(retry.attempt_timeout_ms);
 
-    // Use product_ids from config.symbols
-    for product_id in &config.symbols {
-        match get_coinbase_ticker_price(&client, &config.base_url, product_id).await {
+    for product_id in product_ids {
+        let what = format!("{} price of {}", name, product_id);
+        let response = sources::with_retry(retry, &what, || {
+            selector.timed(
+                name,
+                &endpoint,
+                get_coinbase_ticker_price(
+                    client,
+                    name,
+                    &endpoint.base_url,
+                    product_id,
+                    timeout,
+                    clock_drift,
+                ),
+            )
+        });
+        match response.await {
             Ok(response) => {
-                prices.insert(product_id.to_string(), response.price);
+                if !is_ticker_fresh(&response, name, clock_drift, max_source_age_ms) {
+                    log::warn!(
+                        "Quarantining stale Coinbase price for {}: last trade at {:?} is older than {} ms (clock offset {} ms)",
+                        product_id,
+                        response.time,
+                        max_source_age_ms,
+                        clock_drift.offset_ms(name)
+                    );
+                    continue;
+                }
+                let size = response.size.as_deref().and_then(|s| s.parse().ok());
+                let trade = PriceSample {
+                    timestamp_ms: response
+                        .time
+                        .as_deref()
+                        .and_then(clock_drift::parse_rfc3339_ms),
+                    price: response.price,
+                };
+                let Some(sample) = trades.observe(product_id, trade, size) else {
+                    log::warn!(
+                        "No Coinbase trade of {} above the minimum notional yet",
+                        product_id
+                    );
+                    continue;
+                };
+                // The last trade above the minimum may be older than the one just seen.
+                if let Some(trade_time_ms) = sample.timestamp_ms
+                    && !clock_drift.is_fresh(name, trade_time_ms, max_source_age_ms)
+                {
+                    log::warn!(
+                        "Quarantining stale Coinbase price for {}: no trade above the minimum notional within {} ms",
+                        product_id,
+                        max_source_age_ms
+                    );
+                    continue;
+                }
+                prices.insert(product_id.to_string(), sample);
             }
             Err(e) => {
                 log::error!(
@@ -60,13 +430,155 @@ pub async fn get_coinbase_prices(config: &ExchangeConfig) -> Result<HashMap<Stri
             }
         }
     }
+
+    // Keep the other endpoints' latency current with one request per cycle.
+    if let (Some(probe), Some(product_id)) = (
+        selector.probe_candidate(name, &endpoints, &endpoint),
+        product_ids.first(),
+    ) {
+        let request = get_coinbase_ticker_price(
+            client,
+            name,
+            &probe.base_url,
+            product_id,
+            timeout,
+            clock_drift,
+        );
+        if let Err(e) = selector.timed(name, &probe, request).await {
+            log::debug!(
+                "Coinbase endpoint probe of {} failed: {}",
+                probe.base_url,
+                e
+            );
+        }
+    }
     Ok(prices)
 }
 
+/// Coinbase Exchange product tickers, with depth and quotes from the level-1 book and
+/// volume from the product stats. Tickers whose last
+/// trade is older than `max_source_age_ms` are dropped, and trades below
+/// `min_trade_notional` leave the price at the previous trade. With a `stream_url`,
+/// prices also come from the `ticker` channel of its products.
+pub struct CoinbaseSource {
+    name: &'static str,
+    config: ExchangeConfig,
+    max_source_age_ms: u64,
+    client: Client,
+    retry: RequestRetrySettings,
+    clock_drift: Arc<ClockDriftTracker>,
+    endpoints: Arc<EndpointSelector>,
+    trades: TradeFilter,
+    #[cfg(feature = "ws-sources")]
+    stream: Option<Arc<StreamCache>>,
+}
+
+impl CoinbaseSource {
+    pub fn new(
+        config: ExchangeConfig,
+        max_source_age_ms: u64,
+        client: Client,
+        retry: RequestRetrySettings,
+        clock_drift: Arc<ClockDriftTracker>,
+        endpoints: Arc<EndpointSelector>,
+    ) -> Self {
+        #[cfg(feature = "ws-sources")]
+        let stream = config
+            .stream_url
+            .as_ref()
+            .filter(|_| !config.symbols.is_empty())
+            .map(|url| {
+                ws_stream::spawn(
+                    EXCHANGE_NAME,
+                    url.clone(),
+                    Some(ticker_subscription(&config.symbols)),
+                    config.min_trade_notional,
+                    parse_ticker_message,
+                )
+            });
+        Self {
+            name: EXCHANGE_NAME,
+            trades: TradeFilter::new(config.min_trade_notional),
+            config,
+            max_source_age_ms,
+            client,
+            retry,
+            clock_drift,
+            endpoints,
+            #[cfg(feature = "ws-sources")]
+            stream,
+        }
+    }
+
+    /// The source under another name, for a venue that speaks Coinbase Exchange's API.
+    /// Clock drift and endpoint latency are tracked by name, so they are kept apart from
+    /// Coinbase's.
+    pub fn with_name(self, name: &'static str) -> Self {
+        Self { name, ..self }
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinbaseSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.config.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        get_coinbase_prices(self, symbols).await
+    }
+
+    /// One request per product.
+    fn requests_per_fetch(&self, symbols: &[String]) -> u32 {
+        symbols.len() as u32
+    }
+
+    async fn fetch_depth(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_coinbase_depth(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    async fn fetch_volume(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_coinbase_volume(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    async fn fetch_quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        get_coinbase_quotes(
+            &self.client,
+            self.name,
+            &self.config,
+            &self.endpoints,
+            symbols,
+        )
+        .await
+    }
+
+    #[cfg(feature = "ws-sources")]
+    fn stream(&self) -> Option<&dyn PriceStream> {
+        self.stream.as_deref().map(|s| s as &dyn PriceStream)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json;
 
     #[test]
     fn test_parse_coinbase_response() {
@@ -83,7 +595,102 @@ mod tests {
         "#;
         let parsed: Result<CoinbaseTickerResponse, _> = serde_json::from_str(json_data);
         assert!(parsed.is_ok());
-        assert_eq!(parsed.unwrap().price, "30000.00");
+        let response = parsed.unwrap();
+        assert_eq!(response.price, Decimal::from(30000));
+        assert_eq!(response.size.as_deref(), Some("0.001"));
+        assert_eq!(response.time.as_deref(), Some("2023-10-27T10:00:00Z"));
+    }
+
+    #[test]
+    fn test_parse_coinbase_book() {
+        let json_data =
+            r#"{"sequence":3,"bids":[["29999.00","0.10",2]],"asks":[["30001.00","1.5",1]]}"#;
+        let book: CoinbaseBookResponse = serde_json::from_str(json_data).unwrap();
+        assert!((book.notional().unwrap() - 2_999.9).abs() < 1e-6);
+        assert_eq!(
+            book.quote().unwrap(),
+            Quote {
+                bid: Decimal::from(29999),
+                ask: Decimal::from(30001)
+            }
+        );
+
+        let empty: CoinbaseBookResponse =
+            serde_json::from_str(r#"{"bids":[],"asks":[["30001.00","1.5",1]]}"#).unwrap();
+        assert!(empty.notional().is_err());
+    }
+
+    #[test]
+    fn test_parse_coinbase_stats() {
+        let json_data = r#"{"open":"29000.00","high":"30500.00","low":"28800.00","last":"30000.00","volume":"12034.5","volume_30day":"401234.7"}"#;
+        let stats: CoinbaseStatsResponse = serde_json::from_str(json_data).unwrap();
+        assert_eq!(stats.volume, "12034.5");
+    }
+
+    #[test]
+    fn test_ticker_freshness_accounts_for_clock_drift() {
+        let now = clock_drift::local_now_ms();
+        let tracker = ClockDriftTracker::new();
+        // Coinbase clock observed to run two minutes behind ours.
+        tracker.observe(EXCHANGE_NAME, now - 120_000, now, now);
+
+        let trade_time = chrono::DateTime::from_timestamp_millis(now - 121_000)
+            .unwrap()
+            .to_rfc3339();
+        let response = CoinbaseTickerResponse {
+            price: Decimal::new(3000000, 2),
+            time: Some(trade_time),
+            size: None,
+        };
+        assert!(is_ticker_fresh(&response, EXCHANGE_NAME, &tracker, 10_000));
+        assert!(!is_ticker_fresh(
+            &response,
+            EXCHANGE_NAME,
+            &ClockDriftTracker::new(),
+            10_000
+        ));
+    }
+
+    #[test]
+    fn test_ticker_without_time_is_fresh() {
+        let response = CoinbaseTickerResponse {
+            price: Decimal::new(3000000, 2),
+            time: None,
+            size: None,
+        };
+        assert!(is_ticker_fresh(
+            &response,
+            EXCHANGE_NAME,
+            &ClockDriftTracker::new(),
+            0
+        ));
+    }
+
+    #[cfg(feature = "ws-sources")]
+    #[test]
+    fn test_parse_ticker_stream() {
+        let products = ["BTC-USD".to_string()];
+        assert_eq!(
+            ticker_subscription(&products),
+            r#"{"channels":["ticker"],"product_ids":["BTC-USD"],"type":"subscribe"}"#
+        );
+        let message = r#"{"type":"ticker","sequence":37475248783,"product_id":"BTC-USD","price":"65000.10","best_bid":"65000.00","best_ask":"65000.20","side":"buy","time":"2026-10-14T12:00:00.000000Z","trade_id":370843401,"last_size":"0.002"}"#;
+        assert_eq!(
+            parse_ticker_message(message).unwrap(),
+            vec![StreamTrade {
+                ticker: "BTC-USD".to_string(),
+                sample: PriceSample {
+                    price: Decimal::new(6500010, 2),
+                    timestamp_ms: Some(1791979200000),
+                },
+                size: Some(0.002),
+            }]
+        );
+        let subscribed =
+            r#"{"type":"subscriptions","channels":[{"name":"ticker","product_ids":["BTC-USD"]}]}"#;
+        assert!(parse_ticker_message(subscribed).unwrap().is_empty());
+        let error = r#"{"type":"error","message":"Failed to subscribe","reason":"BTC-XYZ is not a valid product"}"#;
+        assert!(parse_ticker_message(error).is_err());
     }
 
     #[test]
@@ -0,0 +1,192 @@
+use crate::config::{CoinGeckoConfig, RateLimitSettings};
+use crate::endpoints::{self, EndpointSelector};
+use crate::rate_limit;
+use crate::sources::{PriceSample, PriceSource};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SOURCE_NAME: &str = "coingecko";
+
+/// CoinGecko's public API allows about 30 requests a minute.
+pub const RATE_LIMIT: RateLimitSettings = RateLimitSettings {
+    weight_per_minute: 30,
+    burst: Some(5),
+    request_weight: 1,
+};
+
+/// `/simple/price` answers with each coin's price per currency, keyed by coin ID, plus
+/// `last_updated_at` in seconds: `{"bitcoin": {"usd": 65000.1, "last_updated_at": 1672515782}}`.
+/// Prices are read from the JSON numbers' digits, not through `f64`.
+type SimplePrices = HashMap<String, HashMap<String, Decimal>>;
+
+/// A ticker `bitcoin/usd` as the coin ID and the currency it is priced in.
+fn split_ticker(ticker: &str) -> Result<(&str, &str)> {
+    ticker
+        .split_once('/')
+        .filter(|(id, currency)| !id.is_empty() && !currency.is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid CoinGecko ticker '{}'; expected <coin id>/<currency>, e.g. bitcoin/usd",
+                ticker
+            )
+        })
+}
+
+fn simple_price_url(base_url: &str, tickers: &[String]) -> Result<String> {
+    let mut ids = BTreeSet::new();
+    let mut currencies = BTreeSet::new();
+    for ticker in tickers {
+        let (id, currency) = split_ticker(ticker)?;
+        ids.insert(id);
+        currencies.insert(currency);
+    }
+    Ok(format!(
+        "{}/simple/price?ids={}&vs_currencies={}&include_last_updated_at=true",
+        base_url.trim_end_matches('/'),
+        ids.into_iter().collect::<Vec<_>>().join(","),
+        currencies.into_iter().collect::<Vec<_>>().join(",")
+    ))
+}
+
+/// The samples of `tickers` in `prices`; tickers CoinGecko has no price for are missing.
+fn samples(prices: &SimplePrices, tickers: &[String]) -> HashMap<String, PriceSample> {
+    tickers
+        .iter()
+        .filter_map(|ticker| {
+            let (id, currency) = split_ticker(ticker).ok()?;
+            let coin = prices.get(id)?;
+            let price = coin.get(currency)?;
+            let sample = PriceSample {
+                price: *price,
+                timestamp_ms: coin
+                    .get("last_updated_at")
+                    .and_then(|seconds| seconds.to_i64())
+                    .map(|seconds| seconds * 1000),
+            };
+            Some((ticker.clone(), sample))
+        })
+        .collect()
+}
+
+/// CoinGecko's aggregated prices (`/simple/price`), keyed by `<coin id>/<currency>` such as
+/// `bitcoin/usd`. A reference source: its prices are refreshed at most every
+/// `refresh_interval_ms` and only ever check the venues' aggregate, never join it.
+pub struct CoinGeckoSource {
+    config: CoinGeckoConfig,
+    client: Client,
+    endpoints: Arc<EndpointSelector>,
+    /// The last answer, and when it was fetched.
+    cached: Mutex<Option<(Instant, SimplePrices)>>,
+}
+
+impl CoinGeckoSource {
+    pub fn new(config: CoinGeckoConfig, client: Client, endpoints: Arc<EndpointSelector>) -> Self {
+        if config.exchange.stream_url.is_some() {
+            log::warn!("CoinGecko has no WebSocket stream; stream_url is ignored");
+        }
+        Self {
+            config,
+            client,
+            endpoints,
+            cached: Mutex::new(None),
+        }
+    }
+
+    async fn fetch_simple_prices(&self, tickers: &[String]) -> Result<SimplePrices> {
+        // One request per refresh covers every ticker: the public API allows a few
+        // requests a minute, so no other endpoints are probed.
+        let endpoint = self
+            .endpoints
+            .select(SOURCE_NAME, &endpoints::endpoints(&self.config.exchange));
+        let url = simple_price_url(&endpoint.base_url, tickers)?;
+        log::debug!("Fetching reference prices from CoinGecko: {}", url);
+        let mut request = self.client.get(&url);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header("x-cg-demo-api-key", api_key);
+        }
+        let request = async {
+            Ok(rate_limit::check(request.send().await?)?
+                .json::<SimplePrices>()
+                .await?)
+        };
+        self.endpoints.timed(SOURCE_NAME, &endpoint, request).await
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinGeckoSource {
+    fn name(&self) -> &'static str {
+        SOURCE_NAME
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.config.exchange.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        let refresh = Duration::from_millis(self.config.refresh_interval_ms);
+        let cached = self.cached.lock().unwrap().clone();
+        if let Some((fetched_at, prices)) = &cached
+            && fetched_at.elapsed() < refresh
+        {
+            return Ok(samples(prices, symbols));
+        }
+        match self.fetch_simple_prices(symbols).await {
+            Ok(prices) => {
+                let fetched = samples(&prices, symbols);
+                *self.cached.lock().unwrap() = Some((Instant::now(), prices));
+                Ok(fetched)
+            }
+            // The previous prices keep their timestamps, so they age out as usual.
+            Err(e) => match cached {
+                Some((_, prices)) => {
+                    log::warn!("Failed to refresh CoinGecko prices: {:#}", e);
+                    Ok(samples(&prices, symbols))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn reference_max_age(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.config.max_age_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple_prices() {
+        let json_data = r#"{"bitcoin":{"usd":65000.1,"eur":60100.5,"last_updated_at":1672515782},"sui":{"usd":1.52,"last_updated_at":1672515790}}"#;
+        let prices: SimplePrices = serde_json::from_str(json_data).unwrap();
+        let tickers = [
+            "bitcoin/usd".to_string(),
+            "sui/usd".to_string(),
+            "sui/eur".to_string(),
+        ];
+        let samples = samples(&prices, &tickers);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(
+            samples["bitcoin/usd"],
+            PriceSample {
+                price: Decimal::new(650001, 1),
+                timestamp_ms: Some(1_672_515_782_000),
+            }
+        );
+        assert_eq!(samples["sui/usd"].price, Decimal::new(152, 2));
+        assert_eq!(
+            simple_price_url("https://api.coingecko.com/api/v3/", &tickers).unwrap(),
+            "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin,sui&vs_currencies=eur,usd\
+             &include_last_updated_at=true"
+        );
+        assert!(simple_price_url("https://api.coingecko.com/api/v3", &["BTCUSDT".into()]).is_err());
+    }
+}
@@ -1,157 +1,3219 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use config::{Config, ConfigError, File};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::assets;
+use crate::calendar;
+use crate::symbol::{AUTO_TICKER, Symbol};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExchangeConfig {
     pub base_url: String,
+    /// Tickers fetched besides those mapped to this exchange in `[symbols]`.
+    #[serde(default)]
     pub symbols: Vec<String>,
+    /// Testnet/sandbox equivalent of `base_url`, used instead of it in paper mode.
+    #[serde(default)]
+    pub sandbox_base_url: Option<String>,
+    /// Region `base_url` is served from, e.g. `eu`.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Alternative endpoints of the same API; the fastest healthy endpoint is used.
+    #[serde(default)]
+    pub mirrors: Vec<EndpointMirror>,
+    /// Trades smaller than this (quote currency) don't move the price of a trade-based
+    /// source. Ignored by sources that don't report trade sizes.
+    #[serde(default)]
+    pub min_trade_notional: Option<f64>,
+    /// Applied to every price of this venue before aggregation, in basis points, e.g. `-150`
+    /// for a venue that trades at a 1.5% premium. Overridden per symbol by
+    /// `symbols.<symbol>.price_adjustments_bps`.
+    #[serde(default)]
+    pub price_adjustment_bps: f64,
+    /// WebSocket endpoint pushing the venue's trades. When set, and built with the
+    /// `ws-sources` feature, the source is read from its stream while it is healthy.
+    #[serde(default)]
+    pub stream_url: Option<String>,
+    /// Sandbox equivalent of `stream_url`; in paper mode the source only streams if set.
+    #[serde(default)]
+    pub sandbox_stream_url: Option<String>,
+    /// What the exchange allows one IP; the source's documented limit when unset.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitSettings>,
+}
+
+/// A request budget, as exchanges meter it: weight per minute, spent by every request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RateLimitSettings {
+    pub weight_per_minute: u32,
+    /// Most weight spent at once before requests are spaced out; a second's worth when
+    /// unset.
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Weight of one request.
+    #[serde(default = "default_request_weight")]
+    pub request_weight: u32,
+}
+
+fn default_request_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EndpointMirror {
+    pub region: String,
+    pub base_url: String,
+}
+
+/// CoinGecko, a reference source: its prices check the venues' aggregates rather than
+/// join them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CoinGeckoConfig {
+    #[serde(flatten)]
+    pub exchange: ExchangeConfig,
+    /// Demo API key, sent as `x-cg-demo-api-key`; the public API works without one.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Prices are fetched at most this often, whatever `general.fetch_interval_seconds`.
+    #[serde(default = "default_reference_refresh_interval_ms")]
+    pub refresh_interval_ms: u64,
+    /// Prices CoinGecko last updated longer ago than this are not checked against.
+    #[serde(default = "default_reference_max_age_ms")]
+    pub max_age_ms: u64,
+}
+
+fn default_reference_refresh_interval_ms() -> u64 {
+    60_000
+}
+
+fn default_reference_max_age_ms() -> u64 {
+    300_000
+}
+
+/// The API a registered source speaks.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Binance,
+    Coinbase,
+    Okx,
+    Bybit,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// A venue added through `POST /admin/sources` that speaks the API of a built-in source,
+/// e.g. a regional Binance deployment.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegisteredSourceSettings {
+    pub kind: SourceKind,
+    /// Relative weight of its prices in aggregates; configured sources weigh `1`.
+    pub weight: f64,
+    pub exchange: ExchangeConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiConfigs {
     pub binance: ExchangeConfig,
     pub coinbase: ExchangeConfig,
+    /// OKX, a source only when configured, e.g. `base_url = "https://www.okx.com/api/v5"`.
+    #[serde(default)]
+    pub okx: Option<ExchangeConfig>,
+    /// Bybit, a source only when configured, e.g. `base_url = "https://api.bybit.com/v5"`.
+    #[serde(default)]
+    pub bybit: Option<ExchangeConfig>,
+    /// CoinGecko, a reference source only when configured, e.g.
+    /// `base_url = "https://api.coingecko.com/api/v3"`.
+    #[serde(default)]
+    pub coingecko: Option<CoinGeckoConfig>,
+    /// Sources registered at runtime, by name; never read from the config file.
+    #[serde(default, skip_deserializing)]
+    pub registered: BTreeMap<String, RegisteredSourceSettings>,
+}
+
+impl ApiConfigs {
+    /// The API `name` speaks, if it is a configured or registered source.
+    fn source_kind(&self, name: &str) -> Option<SourceKind> {
+        match name {
+            "binance" => Some(SourceKind::Binance),
+            "coinbase" => Some(SourceKind::Coinbase),
+            "okx" if self.okx.is_some() => Some(SourceKind::Okx),
+            "bybit" if self.bybit.is_some() => Some(SourceKind::Bybit),
+            _ => self.registered.get(name).map(|source| source.kind),
+        }
+    }
+
+    pub fn exchanges(&self) -> Vec<(&str, &ExchangeConfig)> {
+        let registered = self
+            .registered
+            .iter()
+            .map(|(name, source)| (name.as_str(), &source.exchange));
+        [("binance", &self.binance), ("coinbase", &self.coinbase)]
+            .into_iter()
+            .chain(self.okx.as_ref().map(|okx| ("okx", okx)))
+            .chain(self.bybit.as_ref().map(|bybit| ("bybit", bybit)))
+            .chain(self.coingecko.as_ref().map(|c| ("coingecko", &c.exchange)))
+            .chain(registered)
+            .collect()
+    }
+
+    /// Every exchange, by the source name used in `symbols.<symbol>.tickers`.
+    fn exchanges_mut(&mut self) -> Vec<(&str, &mut ExchangeConfig)> {
+        let registered = self
+            .registered
+            .iter_mut()
+            .map(|(name, source)| (name.as_str(), &mut source.exchange));
+        [
+            ("binance", &mut self.binance),
+            ("coinbase", &mut self.coinbase),
+        ]
+        .into_iter()
+        .chain(self.okx.as_mut().map(|okx| ("okx", okx)))
+        .chain(self.bybit.as_mut().map(|bybit| ("bybit", bybit)))
+        .chain(
+            self.coingecko
+                .as_mut()
+                .map(|c| ("coingecko", &mut c.exchange)),
+        )
+        .chain(registered)
+        .collect()
+    }
+
+    /// Whether `name` is a configured or registered source.
+    pub fn has_source(&self, name: &str) -> bool {
+        self.exchanges().iter().any(|(source, _)| *source == name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeneralSettings {
+    pub fetch_interval_seconds: u64,
+    /// Source samples whose exchange timestamp (corrected for clock drift) is older than
+    /// this are treated as stale and left out of aggregation.
+    #[serde(default = "default_max_source_age_ms")]
+    pub max_source_age_ms: u64,
+    /// Longest each source's prices, depth or volume may take to fetch per cycle. Sources
+    /// are fetched concurrently, so this bounds how long the slowest one delays a cycle.
+    #[serde(default = "default_source_timeout_ms")]
+    pub source_timeout_ms: u64,
+    /// Rounds of back-to-back fetches at startup, before the first publish, that seed the
+    /// sources' clock drift and latency estimates; `0` publishes from the first fetch.
+    #[serde(default = "default_startup_burst_fetches")]
+    pub startup_burst_fetches: u32,
+    /// Pause after each startup burst fetch.
+    #[serde(default = "default_startup_burst_spacing_ms")]
+    pub startup_burst_spacing_ms: u64,
+    /// Published timestamps are rounded to the nearest multiple of this many milliseconds
+    /// since the Unix epoch, identically for every symbol and publish target; `0` publishes
+    /// them unrounded.
+    #[serde(default)]
+    pub timestamp_granularity_ms: u64,
+    /// Rehearsal mode: every exchange uses its sandbox endpoint and the publisher targets
+    /// the `[sui.paper]` network, so nothing touches production systems.
+    #[serde(default)]
+    pub paper_mode: bool,
+    /// JSON-lines record of every effective per-feed configuration change.
+    #[serde(default = "default_config_history_file")]
+    pub config_history_file: String,
+    /// Directory for the known-objects and keystore files; relative `sui.*_file` paths are
+    /// resolved against it.
+    #[serde(default = "default_state_dir")]
+    pub state_dir: String,
+    /// Base seed for all randomized behaviour; a fresh one is drawn (and logged) when unset.
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+    /// Up to this many milliseconds of random delay are added to each fetch interval, so
+    /// several oracles don't hit the exchanges in lockstep.
+    #[serde(default)]
+    pub fetch_jitter_ms: u64,
+    /// Outlier rejection for feeds without their own `symbols.<symbol>.outlier_filter`.
+    #[serde(default)]
+    pub outlier_filter: Option<OutlierFilter>,
+    /// The operator's time zone (IANA name) for what the oracle reports and schedules: log
+    /// timestamps, the digest, and calendars without a `timezone`. Internal times, state
+    /// files and on-chain timestamps stay UTC.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+/// Drops source prices too far from the median of all sources before they are averaged,
+/// e.g. `{ method = "mad", max_deviations = 5.0 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum OutlierFilter {
+    /// More than `max_deviations` median absolute deviations from the median.
+    Mad { max_deviations: f64 },
+    /// More than `max_deviation_pct` percent of the median away from it.
+    Percent { max_deviation_pct: f64 },
+}
+
+/// How a price is rounded to the decimals it is published at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceRounding {
+    /// To the nearest, ties away from zero.
+    #[default]
+    HalfUp,
+    /// To the nearest, ties to the even neighbour.
+    HalfEven,
+    /// Toward zero.
+    Down,
+    /// Away from zero.
+    Up,
+}
+
+/// How a feed's source prices are combined into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationMethod {
+    /// Arithmetic mean of the source prices.
+    #[default]
+    Mean,
+    /// Median of the source prices.
+    Median,
+    /// Mean after dropping the `aggregation.trim_pct` percent highest and lowest prices.
+    TrimmedMean,
+    /// Each venue's price weighted by its reported 24h volume.
+    Vwap,
+}
+
+fn default_max_source_age_ms() -> u64 {
+    60_000
+}
+
+fn default_source_timeout_ms() -> u64 {
+    4_000
+}
+
+fn default_startup_burst_fetches() -> u32 {
+    3
+}
+
+fn default_startup_burst_spacing_ms() -> u64 {
+    250
+}
+
+fn default_config_history_file() -> String {
+    "config_history.jsonl".to_string()
+}
+
+fn default_state_dir() -> String {
+    "state".to_string()
+}
+
+/// `path` inside `state_dir`, unless it is absolute.
+pub fn state_path(state_dir: &str, path: &str) -> String {
+    if Path::new(path).is_absolute() {
+        return path.to_string();
+    }
+    Path::new(state_dir)
+        .join(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Retries of a failed price update transaction. The `n`th retry waits `base_delay_ms * 2^(n-1)`
+/// plus up to `jitter_ms`, so publishers that failed together don't retry together.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetrySettings {
+    /// Attempts per transaction, the first included; `1` never retries.
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_retry_jitter_ms")]
+    pub jitter_ms: u64,
+    /// How old an update may get before an attempt to publish it, counted from its
+    /// timestamp. An older one is abandoned rather than published outdated, and prices are
+    /// fetched and aggregated again at once. `None` publishes at any age.
+    #[serde(default)]
+    pub max_payload_age_ms: Option<u64>,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            base_delay_ms: default_retry_base_delay_ms(),
+            jitter_ms: default_retry_jitter_ms(),
+            max_payload_age_ms: None,
+        }
+    }
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_retry_jitter_ms() -> u64 {
+    250
+}
+
+/// Network the publisher writes to while in paper mode (normally a Sui localnet).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SuiPaperProfile {
+    #[serde(default = "default_localnet_rpc_url")]
+    pub rpc_url: String,
+    /// Package published to the paper network. Required when paper mode is enabled.
+    #[serde(default)]
+    pub package_id: Option<String>,
+    #[serde(default = "default_paper_known_objects_file")]
+    pub known_objects_file: String,
+}
+
+impl Default for SuiPaperProfile {
+    fn default() -> Self {
+        Self {
+            rpc_url: default_localnet_rpc_url(),
+            package_id: None,
+            known_objects_file: default_paper_known_objects_file(),
+        }
+    }
+}
+
+/// A well-known Sui network, selected with `sui.network`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuiNetwork {
+    Mainnet,
+    Testnet,
+    Devnet,
+    Localnet,
+}
+
+impl SuiNetwork {
+    pub fn name(self) -> &'static str {
+        match self {
+            SuiNetwork::Mainnet => "mainnet",
+            SuiNetwork::Testnet => "testnet",
+            SuiNetwork::Devnet => "devnet",
+            SuiNetwork::Localnet => "localnet",
+        }
+    }
+
+    /// The network's public fullnode, or the default localnet address.
+    pub fn rpc_url(self) -> String {
+        match self {
+            SuiNetwork::Localnet => default_localnet_rpc_url(),
+            network => format!("https://fullnode.{}.sui.io:443", network.name()),
+        }
+    }
+}
+
+/// What the oracle publishes to on one network. Applied over the `[sui]` settings when
+/// `sui.network` selects it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SuiNetworkProfile {
+    /// Defaults to the network's public fullnode.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
+    /// The oracle package deployed to the network. Required when it is selected.
+    #[serde(default)]
+    pub package_id: Option<String>,
+    /// Defaults to `known_price_objects_<network>.json`.
+    #[serde(default)]
+    pub known_objects_file: Option<String>,
+    #[serde(default)]
+    pub expected_package_digest: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SuiSettings {
+    /// Publish to the `sui.networks.<network>` profile instead of the settings below.
+    #[serde(default)]
+    pub network: Option<SuiNetwork>,
+    #[serde(default)]
+    pub networks: BTreeMap<SuiNetwork, SuiNetworkProfile>,
+    #[serde(default = "default_sui_rpc_url")]
+    pub rpc_url: String,
+    /// Fullnodes to fail over to, in order, while `rpc_url` times out or errors. Not
+    /// applied in paper mode.
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
+    /// How long a failed endpoint is skipped before it is probed again.
+    #[serde(default = "default_rpc_cooldown_seconds")]
+    pub rpc_cooldown_seconds: u64,
+    #[serde(default = "default_package_id")]
+    pub package_id: String,
+    /// `verify-package` digest of the package's bytecode. When set, the oracle refuses to
+    /// start against a package whose modules differ. Not applied in paper mode.
+    #[serde(default)]
+    pub expected_package_digest: Option<String>,
+    /// JSON file mapping feed symbols to their PriceObject IDs on this network.
+    #[serde(default = "default_known_objects_file")]
+    pub known_objects_file: String,
+    /// Entry function used for operator overrides; same arguments as `update_price`, but
+    /// marks the PriceObject update as manually set.
+    #[serde(default = "default_manual_update_function")]
+    pub manual_update_function: String,
+    /// Entry function taking the PriceObject and the `Clock` that flags the feed stale
+    /// on-chain. When set, a feed going past its SLA's `max_staleness_ms` is flagged once
+    /// until it updates again; unset, staleness is only visible from the timestamp.
+    #[serde(default)]
+    pub stale_function: Option<String>,
+    /// Entry function taking the PriceObject, price, timestamp and confidence, used instead
+    /// of `update_price` for updates that have a confidence. Unset, none is published.
+    #[serde(default)]
+    pub confidence_function: Option<String>,
+    /// Environment variable that may hold the publisher key instead of a keystore, as base64
+    /// `flag || private key` like the entries of a Sui keystore.
+    #[serde(default = "default_private_key_env")]
+    pub private_key_env: String,
+    /// Sui CLI keystore holding the publisher key; the first key is used unless
+    /// `key_alias` is set.
+    #[serde(default)]
+    pub keystore_file: Option<String>,
+    /// Selects the publisher key by its alias in the `sui.aliases` file next to the
+    /// keystore, as managed by `sui keytool`.
+    #[serde(default)]
+    pub key_alias: Option<String>,
+    /// Tighten a keystore that other users can read to `0600` instead of refusing to start.
+    #[serde(default)]
+    pub fix_keystore_permissions: bool,
+    /// Minimum spacing between on-chain updates of each PriceObject, for consumer contracts
+    /// that reject more frequent updates; `0` disables it. Early updates are queued and the
+    /// latest one is published when the spacing elapses.
+    #[serde(default)]
+    pub min_update_interval_ms: u64,
+    /// An update whose scaled price and timestamp bucket equal the last one submitted to
+    /// the object is skipped. `0` only skips byte-identical payloads.
+    #[serde(default)]
+    pub dedup_timestamp_bucket_ms: u64,
+    /// How prices are rounded to the published decimals.
+    #[serde(default)]
+    pub price_rounding: PriceRounding,
+    /// An update is only submitted if its price moved at least this many basis points from
+    /// the last published one, or the last publish is `heartbeat_seconds` old. `0` submits
+    /// every update.
+    #[serde(default)]
+    pub deviation_threshold_bps: f64,
+    #[serde(default = "default_heartbeat_seconds")]
+    pub heartbeat_seconds: u64,
+    /// Updates of a cycle are packed into programmable transaction blocks of at most this
+    /// many Move calls; `1` submits each update in its own transaction.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Each transaction is dry-run first and budgeted at the gas it used plus
+    /// `gas_budget_margin_pct`; otherwise every budget is the maximum.
+    #[serde(default = "default_true")]
+    pub dry_run_gas_budget: bool,
+    #[serde(default = "default_gas_budget_margin_pct")]
+    pub gas_budget_margin_pct: f64,
+    #[serde(default)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub paper: SuiPaperProfile,
+    #[serde(default)]
+    pub migration: Option<SuiMigration>,
+}
+
+/// Dual-write window after a package upgrade: until `ends_at` every update is also published
+/// to the previous package's objects, so consumers can migrate without a freshness gap.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SuiMigration {
+    pub previous_package_id: String,
+    /// Known-objects file of the previous package; only symbols listed there are dual-written.
+    pub previous_known_objects_file: String,
+    /// RFC 3339 cut-over time, e.g. `2026-11-01T00:00:00Z`.
+    pub ends_at: DateTime<Utc>,
+}
+
+impl SuiSettings {
+    /// Publishing target for the previous package while the dual-write window is open.
+    pub fn previous_package(&self, now: DateTime<Utc>) -> Option<SuiSettings> {
+        let migration = self.migration.as_ref().filter(|m| now < m.ends_at)?;
+        Some(SuiSettings {
+            package_id: migration.previous_package_id.clone(),
+            known_objects_file: migration.previous_known_objects_file.clone(),
+            expected_package_digest: None,
+            migration: None,
+            ..self.clone()
+        })
+    }
+}
+
+impl Default for SuiSettings {
+    fn default() -> Self {
+        Self {
+            network: None,
+            networks: BTreeMap::new(),
+            rpc_url: default_sui_rpc_url(),
+            fallback_rpc_urls: Vec::new(),
+            rpc_cooldown_seconds: default_rpc_cooldown_seconds(),
+            package_id: default_package_id(),
+            expected_package_digest: None,
+            known_objects_file: default_known_objects_file(),
+            manual_update_function: default_manual_update_function(),
+            stale_function: None,
+            confidence_function: None,
+            private_key_env: default_private_key_env(),
+            keystore_file: None,
+            key_alias: None,
+            fix_keystore_permissions: false,
+            min_update_interval_ms: 0,
+            dedup_timestamp_bucket_ms: 0,
+            price_rounding: PriceRounding::default(),
+            deviation_threshold_bps: 0.0,
+            heartbeat_seconds: default_heartbeat_seconds(),
+            max_batch_size: default_max_batch_size(),
+            dry_run_gas_budget: true,
+            gas_budget_margin_pct: default_gas_budget_margin_pct(),
+            retry: RetrySettings::default(),
+            paper: SuiPaperProfile::default(),
+            migration: None,
+        }
+    }
+}
+
+fn default_private_key_env() -> String {
+    "SUI_PRIVATE_KEY".to_string()
+}
+
+fn default_manual_update_function() -> String {
+    "update_price_manual".to_string()
+}
+
+fn default_heartbeat_seconds() -> u64 {
+    3600
+}
+
+fn default_max_batch_size() -> usize {
+    50
+}
+
+fn default_gas_budget_margin_pct() -> f64 {
+    20.0
+}
+
+fn default_rpc_cooldown_seconds() -> u64 {
+    60
+}
+
+fn default_sui_rpc_url() -> String {
+    "https://fullnode.testnet.sui.io:443".to_string()
+}
+
+fn default_localnet_rpc_url() -> String {
+    "http://127.0.0.1:9000".to_string()
+}
+
+fn default_package_id() -> String {
+    "0xe99f0a2f17480d0859a5eb3c565a9f6ea3cbe4a7dec819dbacdb37f5ee33f482".to_string()
+}
+
+fn default_known_objects_file() -> String {
+    "known_price_objects.json".to_string()
+}
+
+fn default_paper_known_objects_file() -> String {
+    "known_price_objects.paper.json".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpSettings {
+    #[serde(default = "default_http_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_http_bind_addr")]
+    pub bind_addr: String,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_http_enabled(),
+            bind_addr: default_http_bind_addr(),
+        }
+    }
+}
+
+fn default_http_enabled() -> bool {
+    true
+}
+
+fn default_http_bind_addr() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+/// The HTTP client shared by every exchange source, built once at startup so connections
+/// and TLS sessions are reused across cycles.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpClientSettings {
+    /// Longest a TCP and TLS connection may take to establish.
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Longest one request may take, from sending it to reading the whole response.
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// Idle pooled connections are closed after this long.
+    #[serde(default = "default_pool_idle_timeout_ms")]
+    pub pool_idle_timeout_ms: u64,
+    /// Idle connections kept per host.
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// Interval of TCP keep-alive probes on open connections; `0` disables them.
+    #[serde(default = "default_tcp_keepalive_ms")]
+    pub tcp_keepalive_ms: u64,
+    /// Proxy for all exchange requests, e.g. `http://proxy.internal:3128`. When unset, the
+    /// `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` environment variables apply.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub retry: RequestRetrySettings,
+}
+
+impl Default for HttpClientSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: default_connect_timeout_ms(),
+            request_timeout_ms: default_request_timeout_ms(),
+            pool_idle_timeout_ms: default_pool_idle_timeout_ms(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            tcp_keepalive_ms: default_tcp_keepalive_ms(),
+            proxy: None,
+            retry: RequestRetrySettings::default(),
+        }
+    }
+}
+
+/// Retries of a failed exchange price request. Only server errors (`5xx`) and timeouts are
+/// retried; the `n`th retry waits `base_delay_ms * 2^(n-1)`, at most `max_delay_ms`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RequestRetrySettings {
+    /// Attempts per request, the first included; `1` never retries.
+    #[serde(default = "default_request_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Longest one attempt may take, in place of `http_client.request_timeout_ms`, so that
+    /// the attempts fit `general.source_timeout_ms`.
+    #[serde(default = "default_request_retry_attempt_timeout_ms")]
+    pub attempt_timeout_ms: u64,
+    #[serde(default = "default_request_retry_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_request_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl RequestRetrySettings {
+    /// How long retry `retry` (from `1`) of a request waits, in milliseconds.
+    pub fn delay_ms(&self, retry: u32) -> u64 {
+        self.base_delay_ms
+            .saturating_mul(1u64.checked_shl(retry - 1).unwrap_or(u64::MAX))
+            .min(self.max_delay_ms)
+    }
+
+    /// Longest a retried request can take: every attempt timing out, plus the waits between
+    /// them.
+    pub fn max_request_ms(&self) -> u64 {
+        let attempts = self.max_attempts.max(1);
+        let waits: u64 = (1..attempts).map(|retry| self.delay_ms(retry)).sum();
+        self.attempt_timeout_ms
+            .saturating_mul(attempts.into())
+            .saturating_add(waits)
+    }
+}
+
+impl Default for RequestRetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_request_retry_max_attempts(),
+            attempt_timeout_ms: default_request_retry_attempt_timeout_ms(),
+            base_delay_ms: default_request_retry_base_delay_ms(),
+            max_delay_ms: default_request_retry_max_delay_ms(),
+        }
+    }
+}
+
+fn default_request_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_request_retry_attempt_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_request_retry_base_delay_ms() -> u64 {
+    100
+}
+
+fn default_request_retry_max_delay_ms() -> u64 {
+    1000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_request_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_pool_idle_timeout_ms() -> u64 {
+    90_000
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    4
+}
+
+fn default_tcp_keepalive_ms() -> u64 {
+    30_000
+}
+
+/// Service-level objective a feed promises its downstream consumers.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SlaSettings {
+    /// Longest acceptable gap between successful on-chain updates.
+    pub max_staleness_ms: u64,
+    /// Minimum number of successful updates in any rolling hour.
+    #[serde(default)]
+    pub min_updates_per_hour: u32,
+}
+
+/// Per-symbol overrides, keyed by on-chain symbol (e.g. `[symbols."BTC/USD"]`).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SymbolSettings {
+    /// Exchange ticker of this symbol per source, e.g. `{ binance = "BTCUSDT" }`. Symbols
+    /// with tickers are fetched and published each cycle.
+    #[serde(default)]
+    pub tickers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub sla: Option<SlaSettings>,
+    /// Withhold publication when `(max - min) / min` across sources exceeds this percentage.
+    #[serde(default)]
+    pub max_source_spread_pct: Option<f64>,
+    /// Withhold publication when the aggregate differs from a reference source's price, e.g.
+    /// CoinGecko's, by more than this percentage.
+    #[serde(default)]
+    pub max_reference_deviation_pct: Option<f64>,
+    /// Leave a venue out of the aggregate when its displayed top-of-book liquidity (the
+    /// thinner of best bid and best ask, in quote currency) is below this notional.
+    #[serde(default)]
+    pub min_liquidity_notional: Option<f64>,
+    /// Overrides `general.outlier_filter`.
+    #[serde(default)]
+    pub outlier_filter: Option<OutlierFilter>,
+    /// Overrides `aggregation.method`.
+    #[serde(default)]
+    pub aggregation_method: Option<AggregationMethod>,
+    /// Overrides `apis.<source>.price_adjustment_bps` for this symbol, per source, e.g.
+    /// `{ binance = -5.0 }`.
+    #[serde(default)]
+    pub price_adjustments_bps: BTreeMap<String, f64>,
+    /// Also publish the aggregate best bid and ask, as the feeds `<symbol>:bid` and
+    /// `<symbol>:ask`, for protocols that price both directions.
+    #[serde(default)]
+    pub publish_bid_ask: bool,
+    /// Also publish the inverse pair, e.g. `USD/BTC` for `BTC/USD`, priced `1 / price` from
+    /// the same aggregate.
+    #[serde(default)]
+    pub publish_inverse: bool,
+    /// Minimum spacing between on-chain updates of this symbol, overriding
+    /// `sui.min_update_interval_ms`.
+    #[serde(default)]
+    pub min_update_interval_ms: Option<u64>,
+    /// Override `sui.deviation_threshold_bps` and `sui.heartbeat_seconds` for this symbol.
+    #[serde(default)]
+    pub deviation_threshold_bps: Option<f64>,
+    #[serde(default)]
+    pub heartbeat_seconds: Option<u64>,
+    /// Publishing priority; when `limits.max_symbols_per_cycle` is hit, the lowest
+    /// priorities are paused first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Market-hours calendar: a built-in (`crypto`, `fx`, `us_equities`) or a
+    /// `[calendars.<name>]` entry. Defaults to `crypto` (always open).
+    #[serde(default)]
+    pub calendar: Option<String>,
+    /// Decimals of the feed's PriceObject, e.g. `12` for SHIB/USD or `4` for an FX pair,
+    /// instead of `6`. Also used by its bid and ask feeds. Set when the object is created,
+    /// so changing it needs a new PriceObject.
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    /// Withhold publication of an aggregate below `min_price` or above `max_price`.
+    #[serde(default)]
+    pub min_price: Option<f64>,
+    #[serde(default)]
+    pub max_price: Option<f64>,
+    /// Withhold publication of an aggregate more than this percentage from a price
+    /// published less than `max_change_interval_seconds` (default 300) ago.
+    #[serde(default)]
+    pub max_change_pct: Option<f64>,
+    #[serde(default)]
+    pub max_change_interval_seconds: Option<u64>,
+}
+
+/// A single daily trading session on the listed weekdays, in `timezone` local time.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarSettings {
+    /// IANA time zone name, e.g. `America/New_York`; defaults to `general.timezone`.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Lowercase three-letter weekday names, e.g. `["mon", "tue"]`.
+    pub days: Vec<String>,
+    /// Local opening time, `HH:MM`.
+    pub open: String,
+    /// Local closing time, `HH:MM` (`24:00` for midnight).
+    pub close: String,
+    /// Full-day closures, as `"YYYY-MM-DD"` strings.
+    #[serde(default)]
+    pub holidays: Vec<chrono::NaiveDate>,
+}
+
+/// An index feed: a weighted basket of other feeds, published as its own symbol
+/// (e.g. `[indices."TOP3"]`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "IndexDefinition")]
+pub struct IndexSettings {
+    /// Weight of each component feed, keyed by on-chain symbol.
+    pub components: BTreeMap<String, f64>,
+    /// The index level is `sum(weight * price) / divisor`.
+    #[serde(default = "default_index_divisor")]
+    pub divisor: f64,
+}
+
+fn default_index_divisor() -> f64 {
+    1.0
+}
+
+/// An index as written: a table of components, or an expression such as
+/// `"TOP2" = "0.6*BTC/USD + 0.4*ETH/USD"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexDefinition {
+    Expression(String),
+    Table {
+        components: BTreeMap<String, f64>,
+        #[serde(default = "default_index_divisor")]
+        divisor: f64,
+    },
+}
+
+impl TryFrom<IndexDefinition> for IndexSettings {
+    type Error = anyhow::Error;
+
+    fn try_from(definition: IndexDefinition) -> Result<Self> {
+        match definition {
+            IndexDefinition::Expression(expression) => crate::index::parse_expression(&expression),
+            IndexDefinition::Table {
+                components,
+                divisor,
+            } => Ok(Self {
+                components,
+                divisor,
+            }),
+        }
+    }
+}
+
+/// A cross-rate feed: one feed's price over another's, published as its own symbol
+/// (e.g. `[crosses."ETH/BTC"]` from `ETH/USD` and `BTC/USD`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrossSettings {
+    /// The feed divided, keyed by on-chain symbol.
+    pub base: String,
+    /// The feed divided by.
+    pub quote: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AlertSettings {
+    /// Optional URL that receives every alert as a JSON POST; alerts are always logged.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+/// A daily health report sent through the alerting channel, for operators without
+/// dashboards.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// When the report goes out, `HH:MM` in UTC.
+    #[serde(default = "default_digest_send_at_utc")]
+    pub send_at_utc: String,
+    /// When the report goes out, `HH:MM` in `general.timezone`; replaces `send_at_utc`.
+    #[serde(default)]
+    pub send_at: Option<String>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            send_at_utc: default_digest_send_at_utc(),
+            send_at: None,
+        }
+    }
+}
+
+fn default_digest_send_at_utc() -> String {
+    "09:00".to_string()
+}
+
+/// How gas spend is reported in USD and where every publish's gas is recorded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeeSettings {
+    /// The feed whose published price converts SUI to USD; empty reports MIST only.
+    #[serde(default = "default_fees_usd_feed")]
+    pub usd_feed: String,
+    /// Append-only JSON lines, one per publish. Resolved against `general.state_dir`.
+    #[serde(default = "default_gas_ledger_file")]
+    pub ledger_file: String,
+}
+
+impl Default for FeeSettings {
+    fn default() -> Self {
+        Self {
+            usd_feed: default_fees_usd_feed(),
+            ledger_file: default_gas_ledger_file(),
+        }
+    }
+}
+
+fn default_fees_usd_feed() -> String {
+    "SUI/USD".to_string()
+}
+
+fn default_gas_ledger_file() -> String {
+    "gas_ledger.jsonl".to_string()
+}
+
+/// SQLite history of every fetched sample and every aggregated price with its outcome, for
+/// auditing what the oracle signed and published. Needs the `storage-sql` feature.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Resolved against `general.state_dir`.
+    #[serde(default = "default_storage_file")]
+    pub file: String,
+    /// Rows older than this are deleted; `0` keeps everything.
+    #[serde(default = "default_storage_retention_days")]
+    pub retention_days: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: default_storage_file(),
+            retention_days: default_storage_retention_days(),
+        }
+    }
+}
+
+fn default_storage_file() -> String {
+    "price_history.sqlite".to_string()
+}
+
+fn default_storage_retention_days() -> u64 {
+    30
+}
+
+/// What `/healthz` and `/readyz` require.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthSettings {
+    /// Liveness fails once no cycle has completed for this long.
+    #[serde(default = "default_max_cycle_age_seconds")]
+    pub max_cycle_age_seconds: u64,
+    /// How often the cycle loop reads the publisher's balance, which also checks the RPC.
+    #[serde(default = "default_health_check_interval_seconds")]
+    pub check_interval_seconds: u64,
+    /// Readiness fails below this balance.
+    #[serde(default = "default_min_gas_balance_sui")]
+    pub min_gas_balance_sui: f64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            max_cycle_age_seconds: default_max_cycle_age_seconds(),
+            check_interval_seconds: default_health_check_interval_seconds(),
+            min_gas_balance_sui: default_min_gas_balance_sui(),
+        }
+    }
+}
+
+fn default_max_cycle_age_seconds() -> u64 {
+    300
+}
+
+fn default_health_check_interval_seconds() -> u64 {
+    60
+}
+
+fn default_min_gas_balance_sui() -> f64 {
+    1.0
+}
+
+/// Where post-mortem bundles are written when a publish fails, and how many are kept.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DebugBundleSettings {
+    #[serde(default = "default_debug_bundles_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_debug_bundles_dir")]
+    pub dir: String,
+    /// Oldest bundles beyond this count are deleted.
+    #[serde(default = "default_debug_bundles_max_count")]
+    pub max_bundles: usize,
+    /// Bundles older than this are deleted regardless of count.
+    #[serde(default = "default_debug_bundles_max_age_hours")]
+    pub max_age_hours: u64,
+}
+
+impl Default for DebugBundleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_debug_bundles_enabled(),
+            dir: default_debug_bundles_dir(),
+            max_bundles: default_debug_bundles_max_count(),
+            max_age_hours: default_debug_bundles_max_age_hours(),
+        }
+    }
+}
+
+fn default_debug_bundles_enabled() -> bool {
+    true
+}
+
+fn default_debug_bundles_dir() -> String {
+    "debug_bundles".to_string()
+}
+
+fn default_debug_bundles_max_count() -> usize {
+    50
+}
+
+fn default_debug_bundles_max_age_hours() -> u64 {
+    7 * 24
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AggregationSettings {
+    /// Method of feeds without their own `symbols.<symbol>.aggregation_method`.
+    #[serde(default)]
+    pub method: AggregationMethod,
+    /// Percentage of prices dropped from each end by `trimmed_mean`.
+    #[serde(default = "default_trim_pct")]
+    pub trim_pct: f64,
+    /// Sources that must contribute a price before a feed is aggregated and published.
+    /// `1` lets a single venue set the price, which has to be chosen explicitly.
+    #[serde(default = "default_min_sources")]
+    pub min_sources: usize,
+    /// Cycles a source recovering from an outage takes to ramp back to full weight;
+    /// `0` re-admits it at full weight straight away.
+    #[serde(default = "default_recovery_ramp_cycles")]
+    pub recovery_ramp_cycles: u32,
+    /// Weights of the listed sources' prices, relative to the unlisted ones at `1`. Reference
+    /// sources only check aggregates, so they cannot be weighted.
+    #[serde(default)]
+    pub weights: BTreeMap<String, f64>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct GeneralSettings {
-    pub fetch_interval_seconds: u64,
-}
+impl Default for AggregationSettings {
+    fn default() -> Self {
+        Self {
+            method: AggregationMethod::default(),
+            trim_pct: default_trim_pct(),
+            min_sources: default_min_sources(),
+            recovery_ramp_cycles: default_recovery_ramp_cycles(),
+            weights: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_trim_pct() -> f64 {
+    20.0
+}
+
+fn default_min_sources() -> usize {
+    2
+}
+
+fn default_recovery_ramp_cycles() -> u32 {
+    3
+}
+
+/// A second aggregation configuration run beside the live one on the same source prices.
+/// Its prices are only compared with the live ones, never published, so a change of method
+/// or weights can be tried before it is made.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShadowSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Method of every feed, including those with their own `aggregation_method`; unset
+    /// keeps the live ones.
+    #[serde(default)]
+    pub method: Option<AggregationMethod>,
+    #[serde(default)]
+    pub trim_pct: Option<f64>,
+    #[serde(default)]
+    pub min_sources: Option<usize>,
+    /// Weights of the listed sources; the others keep their live weight.
+    #[serde(default)]
+    pub weights: BTreeMap<String, f64>,
+    /// A shadow price this many basis points from the live one is logged as a warning.
+    #[serde(default = "default_shadow_warn_bps")]
+    pub warn_bps: f64,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            method: None,
+            trim_pct: None,
+            min_sources: None,
+            weights: BTreeMap::new(),
+            warn_bps: default_shadow_warn_bps(),
+        }
+    }
+}
+
+fn default_shadow_warn_bps() -> f64 {
+    50.0
+}
+
+/// Process-level resource limits. When one is hit the oracle sheds load (drops the oldest
+/// history, pauses low-priority symbols, rejects requests) instead of growing unbounded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LimitSettings {
+    /// Update timestamps kept per symbol for SLA rate tracking.
+    #[serde(default = "default_max_history_per_symbol")]
+    pub max_history_per_symbol: usize,
+    /// Symbols published per cycle; `0` means unlimited.
+    #[serde(default)]
+    pub max_symbols_per_cycle: usize,
+    /// Alert webhook deliveries in flight at once.
+    #[serde(default = "default_max_pending_alerts")]
+    pub max_pending_alerts: usize,
+    /// Concurrent HTTP API requests; excess requests get `503 Service Unavailable`.
+    #[serde(default = "default_max_http_connections")]
+    pub max_http_connections: usize,
+    /// Messages queued per `/ws` subscriber; one further behind loses the oldest.
+    #[serde(default = "default_max_ws_queue")]
+    pub max_ws_queue: usize,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self {
+            max_history_per_symbol: default_max_history_per_symbol(),
+            max_symbols_per_cycle: 0,
+            max_pending_alerts: default_max_pending_alerts(),
+            max_http_connections: default_max_http_connections(),
+            max_ws_queue: default_max_ws_queue(),
+        }
+    }
+}
+
+fn default_max_history_per_symbol() -> usize {
+    3600
+}
+
+fn default_max_pending_alerts() -> usize {
+    32
+}
+
+fn default_max_http_connections() -> usize {
+    64
+}
+
+fn default_max_ws_queue() -> usize {
+    256
+}
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `env_logger`'s text lines.
+    #[default]
+    Text,
+    /// One JSON object per line, with the cycle, symbol, source and digest a line concerns
+    /// as fields, for log pipelines such as Loki or ELK.
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoggingSettings {
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default)]
+    pub redaction: RedactionSettings,
+}
+
+/// Masking of deployment details in log lines, so logs can be shared safely. Each rule
+/// takes effect only while `enabled` is set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedactionSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sui addresses and object IDs (`0x` + 64 hex digits).
+    #[serde(default = "default_true")]
+    pub addresses: bool,
+    /// Base58 transaction digests, of which the first `digest_prefix_len` characters are kept.
+    #[serde(default = "default_true")]
+    pub digests: bool,
+    #[serde(default = "default_digest_prefix_len")]
+    pub digest_prefix_len: usize,
+    /// Values of `api_key=`, `token=`, `secret=`, `password=` and bearer credentials.
+    #[serde(default = "default_true")]
+    pub api_keys: bool,
+    /// Absolute file paths, of which only the file name is kept.
+    #[serde(default = "default_true")]
+    pub paths: bool,
+    /// Further regular expressions whose matches are masked.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            addresses: true,
+            digests: true,
+            digest_prefix_len: default_digest_prefix_len(),
+            api_keys: true,
+            paths: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_digest_prefix_len() -> usize {
+    6
+}
+
+/// A Move call made by the `consumers` commands. `args` are passed in order: `{consumer}`,
+/// `{recipient}` and `{amount}` are filled in from the command line, and `0x...` values are
+/// object IDs (shared objects are passed mutably).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CallTemplate {
+    #[serde(default = "default_call_module")]
+    pub module: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+fn default_call_module() -> String {
+    "price_oracle".to_string()
+}
+
+/// A coin type priced by one of the feeds (`[assets."0x2::sui::SUI"]`), served on
+/// `GET /assets` so consumer protocols can look up the feed of a coin.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AssetSettings {
+    /// The configured feed, index, or bid, ask or inverse feed pricing the coin.
+    pub feed: String,
+    /// Decimals of the coin's on-chain amounts, e.g. `9` for SUI.
+    #[serde(default)]
+    pub decimals: Option<u8>,
+}
+
+/// Publishing `[assets]` to an on-chain registry with `assets publish`, for packages that
+/// have one. `register.args` take `{coin_type}`, `{feed}`, `{decimals}` and
+/// `{price_object}` besides object IDs.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AssetRegistrySettings {
+    pub register: Option<CallTemplate>,
+}
+
+/// Consumer allowlist and fee management, for packages that support it. A command whose
+/// template is not configured is unavailable.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConsumerSettings {
+    pub add: Option<CallTemplate>,
+    pub remove: Option<CallTemplate>,
+    pub withdraw_fees: Option<CallTemplate>,
+}
+
+/// What an admin token may do. Each role may do everything the roles before it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Read overrides and registered feeds and sources.
+    ReadOnly,
+    /// Set and clear overrides, register feeds and sources, and reconcile with the chain.
+    FeedOperator,
+    /// Everything, including key management.
+    KeyAdmin,
+}
+
+/// An admin token besides `admin.token_env`'s, with its own role.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdminTokenSettings {
+    /// Environment variable holding the bearer token; the token is disabled when unset.
+    pub token_env: String,
+    pub role: AdminRole,
+}
+
+/// Admin API access and manual-override policy.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdminSettings {
+    /// Environment variable holding the `key_admin` bearer token.
+    #[serde(default = "default_admin_token_env")]
+    pub token_env: String,
+    /// Tokens with narrower roles, keyed by a name for logs, e.g. `[admin.tokens.dashboards]`.
+    /// Admin routes are disabled when no token is set.
+    #[serde(default)]
+    pub tokens: BTreeMap<String, AdminTokenSettings>,
+    #[serde(default = "default_max_override_ttl_seconds")]
+    pub max_override_ttl_seconds: u64,
+    #[serde(default = "default_audit_log_file")]
+    pub audit_log_file: String,
+    /// Feeds registered through `POST /admin/symbols`, loaded at startup alongside
+    /// `[symbols]`. Resolved against `general.state_dir`.
+    #[serde(default = "default_registered_symbols_file")]
+    pub registered_symbols_file: String,
+    /// Sources registered through `POST /admin/sources`, loaded at startup. Resolved
+    /// against `general.state_dir`.
+    #[serde(default = "default_registered_sources_file")]
+    pub registered_sources_file: String,
+}
+
+impl Default for AdminSettings {
+    fn default() -> Self {
+        Self {
+            token_env: default_admin_token_env(),
+            tokens: BTreeMap::new(),
+            max_override_ttl_seconds: default_max_override_ttl_seconds(),
+            audit_log_file: default_audit_log_file(),
+            registered_symbols_file: default_registered_symbols_file(),
+            registered_sources_file: default_registered_sources_file(),
+        }
+    }
+}
+
+impl AdminSettings {
+    /// Rejects tokens read from the same environment variable as another.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut envs = vec![self.token_env.as_str()];
+        for (name, token) in &self.tokens {
+            if envs.contains(&token.token_env.as_str()) {
+                return Err(ConfigError::Message(format!(
+                    "admin.tokens.{}: {} is already the environment variable of another token",
+                    name, token.token_env
+                )));
+            }
+            envs.push(&token.token_env);
+        }
+        Ok(())
+    }
+}
+
+fn default_admin_token_env() -> String {
+    "ORACLE_ADMIN_TOKEN".to_string()
+}
+
+fn default_max_override_ttl_seconds() -> u64 {
+    3600
+}
+
+fn default_audit_log_file() -> String {
+    "admin_audit.jsonl".to_string()
+}
+
+fn default_registered_symbols_file() -> String {
+    "registered_symbols.json".to_string()
+}
+
+fn default_registered_sources_file() -> String {
+    "registered_sources.json".to_string()
+}
+
+/// Sources and feeds that keep failing are taken out of the cycle for a while. The state is
+/// kept in `file`, so restarts don't re-admit them early.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuarantineSettings {
+    /// Resolved against `general.state_dir`.
+    #[serde(default = "default_quarantine_file")]
+    pub file: String,
+    /// Consecutive failed fetches after which a source is no longer fetched; `0` never
+    /// quarantines sources.
+    #[serde(default = "default_quarantine_threshold")]
+    pub source_failure_threshold: u32,
+    /// Consecutive failed publishes after which a feed's circuit breaker trips and it is no
+    /// longer published; `0` never trips.
+    #[serde(default = "default_quarantine_threshold")]
+    pub feed_failure_threshold: u32,
+    /// How long a quarantine lasts. Afterwards the subject is re-admitted on probation: its
+    /// next failure quarantines it again.
+    #[serde(default = "default_quarantine_ttl_seconds")]
+    pub ttl_seconds: u64,
+    /// Each quarantine of a subject that failed again on probation lasts twice as long as
+    /// the last, up to this; its next success resets it to `ttl_seconds`.
+    #[serde(default = "default_quarantine_max_ttl_seconds")]
+    pub max_ttl_seconds: u64,
+}
+
+impl Default for QuarantineSettings {
+    fn default() -> Self {
+        Self {
+            file: default_quarantine_file(),
+            source_failure_threshold: default_quarantine_threshold(),
+            feed_failure_threshold: default_quarantine_threshold(),
+            ttl_seconds: default_quarantine_ttl_seconds(),
+            max_ttl_seconds: default_quarantine_max_ttl_seconds(),
+        }
+    }
+}
+
+/// When a source that streams its prices is polled over REST instead.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamSettings {
+    /// A stream without a message for this long is degraded.
+    #[serde(default = "default_stream_max_staleness_ms")]
+    pub max_staleness_ms: u64,
+    /// A stream reconnecting more often than this within `reconnect_window_seconds` is
+    /// degraded.
+    #[serde(default = "default_stream_max_reconnects")]
+    pub max_reconnects: u32,
+    #[serde(default = "default_stream_reconnect_window_seconds")]
+    pub reconnect_window_seconds: u64,
+    /// How long a stream must stay healthy before the source is read from it again.
+    #[serde(default = "default_stream_stable_seconds")]
+    pub stable_seconds: u64,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            max_staleness_ms: default_stream_max_staleness_ms(),
+            max_reconnects: default_stream_max_reconnects(),
+            reconnect_window_seconds: default_stream_reconnect_window_seconds(),
+            stable_seconds: default_stream_stable_seconds(),
+        }
+    }
+}
+
+fn default_stream_max_staleness_ms() -> u64 {
+    5_000
+}
+
+fn default_stream_max_reconnects() -> u32 {
+    3
+}
+
+fn default_stream_reconnect_window_seconds() -> u64 {
+    300
+}
+
+fn default_stream_stable_seconds() -> u64 {
+    60
+}
+
+/// Labels identifying this instance, e.g. `{ instance = "oracle-a", operator = "acme" }`,
+/// added to every metric series and log line and to `/info`, so several instances (HA pairs,
+/// shadow instances, other operators) can report to the same observability stack.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InstanceSettings {
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl InstanceSettings {
+    /// Rejects label names Prometheus would not accept.
+    fn validate(&self) -> Result<(), ConfigError> {
+        for name in self.labels.keys() {
+            let mut chars = name.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.starts_with("__");
+            if !valid {
+                return Err(ConfigError::Message(format!(
+                    "instance.labels: '{}' is not a valid label name",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Caps the label values each metric family keeps, so exporters stay healthy with hundreds
+/// of symbols. Values beyond the cap are rolled up into `other`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricsSettings {
+    /// The labels that are capped, e.g. `symbol`.
+    #[serde(default = "default_rollup_labels")]
+    pub rollup_labels: Vec<String>,
+    /// Values of those labels that always keep a series of their own, e.g. the majors.
+    /// When set, every other value is rolled up.
+    #[serde(default)]
+    pub labelled_values: Vec<String>,
+    /// Without `labelled_values`, the first this many values of a label seen per family
+    /// keep their own series; `0` keeps them all.
+    #[serde(default = "default_max_label_values")]
+    pub max_label_values: usize,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            rollup_labels: default_rollup_labels(),
+            labelled_values: Vec::new(),
+            max_label_values: default_max_label_values(),
+        }
+    }
+}
+
+fn default_rollup_labels() -> Vec<String> {
+    vec!["symbol".to_string()]
+}
+
+fn default_max_label_values() -> usize {
+    200
+}
+
+/// Updates are kept in `file` from aggregation until they land, so those still pending when
+/// the process dies are published at the next startup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboxSettings {
+    /// Resolved against `general.state_dir`.
+    #[serde(default = "default_outbox_file")]
+    pub file: String,
+    /// A pending update older than this at startup is dropped instead of published; `0`
+    /// disables the outbox.
+    #[serde(default = "default_outbox_max_age_seconds")]
+    pub max_age_seconds: u64,
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        Self {
+            file: default_outbox_file(),
+            max_age_seconds: default_outbox_max_age_seconds(),
+        }
+    }
+}
+
+fn default_outbox_file() -> String {
+    "outbox.json".to_string()
+}
+
+fn default_outbox_max_age_seconds() -> u64 {
+    60
+}
+
+fn default_quarantine_file() -> String {
+    "quarantine.json".to_string()
+}
+
+fn default_quarantine_threshold() -> u32 {
+    5
+}
+
+fn default_quarantine_ttl_seconds() -> u64 {
+    900
+}
+
+fn default_quarantine_max_ttl_seconds() -> u64 {
+    14_400
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Settings {
+    pub apis: ApiConfigs,
+    pub general: GeneralSettings,
+    #[serde(default)]
+    pub sui: SuiSettings,
+    #[serde(default)]
+    pub http: HttpSettings,
+    #[serde(default)]
+    pub http_client: HttpClientSettings,
+    #[serde(default)]
+    pub aggregation: AggregationSettings,
+    #[serde(default)]
+    pub quarantine: QuarantineSettings,
+    #[serde(default)]
+    pub outbox: OutboxSettings,
+    #[serde(default)]
+    pub streams: StreamSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub instance: InstanceSettings,
+    #[serde(default)]
+    pub symbols: BTreeMap<String, SymbolSettings>,
+    #[serde(default)]
+    pub calendars: BTreeMap<String, CalendarSettings>,
+    #[serde(default)]
+    pub indices: BTreeMap<String, IndexSettings>,
+    #[serde(default)]
+    pub crosses: BTreeMap<String, CrossSettings>,
+    #[serde(default)]
+    pub alerts: AlertSettings,
+    #[serde(default)]
+    pub digest: DigestSettings,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub fees: FeeSettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub shadow: ShadowSettings,
+    #[serde(default)]
+    pub debug_bundles: DebugBundleSettings,
+    #[serde(default)]
+    pub admin: AdminSettings,
+    #[serde(default)]
+    pub limits: LimitSettings,
+    #[serde(default)]
+    pub consumers: ConsumerSettings,
+    /// Coin types by canonical type name, e.g. `0x000…0002::sui::SUI`, however written in
+    /// the config.
+    #[serde(default)]
+    pub assets: BTreeMap<String, AssetSettings>,
+    #[serde(default)]
+    pub asset_registry: AssetRegistrySettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    // We could add other general settings here later, e.g., logging level, aggregation strategy, etc.
+}
+
+/// Most decimals a feed may be published at.
+pub const MAX_DECIMALS: u8 = 18;
+
+/// `symbols.<symbol>.max_change_interval_seconds` when not set.
+pub const DEFAULT_MAX_CHANGE_INTERVAL_SECONDS: u64 = 300;
+
+/// Sides a feed with `publish_bid_ask` publishes besides its price.
+pub const QUOTE_SIDES: [&str; 2] = ["bid", "ask"];
+
+/// The feed `side` of `symbol` is published as, e.g. `BTC/USD:bid`.
+pub fn quote_feed(symbol: &str, side: &str) -> String {
+    format!("{}:{}", symbol, side)
+}
+
+/// The feed whose bid or ask `symbol` is, if it is one.
+pub fn quote_feed_parent(symbol: &str) -> Option<&str> {
+    let (parent, side) = symbol.rsplit_once(':')?;
+    QUOTE_SIDES.contains(&side).then_some(parent)
+}
+
+/// The inverse pair of `symbol`, e.g. `USD/BTC` for `BTC/USD`, if it is a `BASE/QUOTE` pair.
+pub fn inverse_symbol(symbol: &str) -> Option<String> {
+    Symbol::parse(symbol)
+        .ok()
+        .map(|pair| pair.inverse().to_string())
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, ConfigError> {
+        let builder = Config::builder()
+            // Start with `./config/default.toml`
+            .add_source(File::with_name("config/default").required(true))
+            // Add in `./config/local.toml` to override defaults
+            .add_source(File::with_name("config/local").required(false));
+
+        let mut settings: Settings = builder.build()?.try_deserialize()?;
+        if settings.aggregation.min_sources == 0 {
+            return Err(ConfigError::Message(
+                "aggregation.min_sources must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..50.0).contains(&settings.aggregation.trim_pct) {
+            return Err(ConfigError::Message(
+                "aggregation.trim_pct must be at least 0 and below 50".to_string(),
+            ));
+        }
+        calendar::parse_timezone(&settings.general.timezone)
+            .map_err(|e| ConfigError::Message(format!("general.timezone: {}", e)))?;
+        settings.normalize_symbols()?;
+        settings.resolve_tickers()?;
+        settings.validate_price_adjustments()?;
+        settings.validate_source_weights()?;
+        settings.validate_inverse_feeds()?;
+        settings.validate_decimals()?;
+        settings.validate_sanity_bounds()?;
+        settings.validate_rate_limits()?;
+        settings.validate_request_budget()?;
+        settings.instance.validate()?;
+        settings.admin.validate()?;
+        settings.normalize_assets()?;
+        settings.apply_network()?;
+        if settings.general.paper_mode {
+            settings.apply_paper_mode()?;
+        }
+        settings.resolve_state_paths();
+        Ok(settings)
+    }
+
+    /// The feeds fetched from the exchanges and published each cycle.
+    pub fn feed_symbols(&self) -> Vec<&str> {
+        self.symbols
+            .iter()
+            .filter(|(_, s)| !s.tickers.is_empty())
+            .map(|(symbol, _)| symbol.as_str())
+            .collect()
+    }
+
+    /// The bid and ask feeds of the feeds with `publish_bid_ask`.
+    pub fn quote_feeds(&self) -> Vec<String> {
+        self.feed_symbols()
+            .into_iter()
+            .filter(|symbol| self.symbols[*symbol].publish_bid_ask)
+            .flat_map(|symbol| QUOTE_SIDES.map(|side| quote_feed(symbol, side)))
+            .collect()
+    }
+
+    /// The inverse feed `symbol` publishes, if it has `publish_inverse`.
+    pub fn inverse_feed(&self, symbol: &str) -> Option<String> {
+        self.symbols
+            .get(symbol)
+            .filter(|s| s.publish_inverse)
+            .and_then(|_| inverse_symbol(symbol))
+    }
+
+    /// The feeds computed from other feeds' prices each cycle: indices and cross rates.
+    pub fn computed_feeds(&self) -> impl Iterator<Item = &str> {
+        self.indices
+            .keys()
+            .chain(self.crosses.keys())
+            .map(String::as_str)
+    }
+
+    /// The feeds published from another feed's aggregate: bid, ask and inverse feeds.
+    pub fn derived_feeds(&self) -> Vec<String> {
+        let inverse_feeds = self
+            .feed_symbols()
+            .into_iter()
+            .filter_map(|symbol| self.inverse_feed(symbol));
+        self.quote_feeds()
+            .into_iter()
+            .chain(inverse_feeds)
+            .collect()
+    }
+
+    /// The feed whose bid, ask or inverse `symbol` is, if it is one.
+    pub fn derived_feed_parent<'a>(&'a self, symbol: &'a str) -> Option<&'a str> {
+        quote_feed_parent(symbol).or_else(|| {
+            self.feed_symbols()
+                .into_iter()
+                .find(|parent| self.inverse_feed(parent).as_deref() == Some(symbol))
+        })
+    }
+
+    /// Decimals `feed` is published at: its own `decimals`, those of the feed it is the bid
+    /// or ask of, else `sui_publisher::DECIMALS`.
+    pub fn decimals(&self, feed: &str) -> u8 {
+        let feed = quote_feed_parent(feed).unwrap_or(feed);
+        self.symbols
+            .get(feed)
+            .and_then(|s| s.decimals)
+            .unwrap_or(crate::sui_publisher::DECIMALS)
+    }
+
+    /// Decimals of every published feed with its own, for the publisher.
+    pub fn feed_decimals(&self) -> HashMap<String, u8> {
+        let derived = self.derived_feeds();
+        self.feed_symbols()
+            .into_iter()
+            .chain(derived.iter().map(String::as_str))
+            .chain(self.computed_feeds())
+            .map(|feed| (feed.to_string(), self.decimals(feed)))
+            .filter(|(_, decimals)| *decimals != crate::sui_publisher::DECIMALS)
+            .collect()
+    }
+
+    /// The outlier rejection applied to `symbol`, if any.
+    pub fn outlier_filter(&self, symbol: &str) -> Option<OutlierFilter> {
+        self.symbols
+            .get(symbol)
+            .and_then(|s| s.outlier_filter)
+            .or(self.general.outlier_filter)
+    }
+
+    /// How the source prices of `symbol` are combined.
+    pub fn aggregation_method(&self, symbol: &str) -> AggregationMethod {
+        self.symbols
+            .get(symbol)
+            .and_then(|s| s.aggregation_method)
+            .unwrap_or(self.aggregation.method)
+    }
+
+    /// Relative weight of `source`'s prices in aggregates: its registration's weight for a
+    /// source registered at runtime, else `aggregation.weights`, else `1`.
+    pub fn source_weight(&self, source: &str) -> f64 {
+        match self.apis.registered.get(source) {
+            Some(registered) => registered.weight,
+            None => self.aggregation.weights.get(source).copied().unwrap_or(1.0),
+        }
+    }
+
+    /// The method and aggregation settings of `symbol` under `[shadow]`.
+    pub fn shadow_aggregation(&self, symbol: &str) -> (AggregationMethod, AggregationSettings) {
+        let shadow = &self.shadow;
+        let method = shadow
+            .method
+            .unwrap_or_else(|| self.aggregation_method(symbol));
+        let aggregation = AggregationSettings {
+            method,
+            trim_pct: shadow.trim_pct.unwrap_or(self.aggregation.trim_pct),
+            min_sources: shadow.min_sources.unwrap_or(self.aggregation.min_sources),
+            ..self.aggregation.clone()
+        };
+        (method, aggregation)
+    }
+
+    /// Relative weight of `source`'s prices in shadow aggregates.
+    pub fn shadow_source_weight(&self, source: &str) -> f64 {
+        self.shadow
+            .weights
+            .get(source)
+            .copied()
+            .unwrap_or_else(|| self.source_weight(source))
+    }
+
+    /// Basis points added to `source`'s price of `symbol` before aggregation.
+    pub fn price_adjustment_bps(&self, symbol: &str, source: &str) -> f64 {
+        self.symbols
+            .get(symbol)
+            .and_then(|s| s.price_adjustments_bps.get(source))
+            .copied()
+            .or_else(|| {
+                let exchanges = self.apis.exchanges();
+                let (_, exchange) = exchanges.iter().find(|(name, _)| *name == source)?;
+                Some(exchange.price_adjustment_bps)
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Rejects weights of unknown sources, of reference sources, which would then check an
+    /// aggregate they are part of, and weights that are not positive.
+    fn validate_source_weights(&self) -> Result<(), ConfigError> {
+        let exchanges = self.apis.exchanges();
+        for (source, weight) in &self.aggregation.weights {
+            if !exchanges.iter().any(|(name, _)| name == source) {
+                return Err(ConfigError::Message(format!(
+                    "aggregation.weights names unknown source '{}'",
+                    source
+                )));
+            }
+            if source == "coingecko" {
+                return Err(ConfigError::Message(
+                    "aggregation.weights cannot weight coingecko, a reference source that \
+                     checks the aggregate"
+                        .to_string(),
+                ));
+            }
+            if !(*weight > 0.0 && weight.is_finite()) {
+                return Err(ConfigError::Message(format!(
+                    "aggregation.weights.{} must be positive, not {}",
+                    source, weight
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects adjustments of unknown sources and adjustments that would make a price
+    /// zero or negative.
+    fn validate_price_adjustments(&self) -> Result<(), ConfigError> {
+        let exchanges = self.apis.exchanges();
+        let venue = exchanges
+            .iter()
+            .map(|(name, exchange)| (format!("apis.{}", name), exchange.price_adjustment_bps));
+        let per_symbol = self.symbols.iter().flat_map(|(symbol, s)| {
+            s.price_adjustments_bps.iter().map(move |(source, bps)| {
+                (
+                    format!("symbols.\"{}\".price_adjustments_bps.{}", symbol, source),
+                    *bps,
+                )
+            })
+        });
+        for (field, bps) in venue.chain(per_symbol) {
+            if !(bps > -10_000.0 && bps.is_finite()) {
+                return Err(ConfigError::Message(format!(
+                    "{} adjustment of {} bps must be above -10000",
+                    field, bps
+                )));
+            }
+        }
+        for (symbol, s) in &self.symbols {
+            if let Some(source) = s
+                .price_adjustments_bps
+                .keys()
+                .find(|source| !exchanges.iter().any(|(name, _)| name == source))
+            {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".price_adjustments_bps names unknown source '{}'",
+                    symbol, source
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Inverse feeds need a `BASE/QUOTE` symbol, and must not clash with another feed.
+    fn validate_inverse_feeds(&self) -> Result<(), ConfigError> {
+        for (symbol, s) in self.symbols.iter().filter(|(_, s)| s.publish_inverse) {
+            let inverse = inverse_symbol(symbol).ok_or_else(|| {
+                ConfigError::Message(format!(
+                    "symbols.\"{}\".publish_inverse needs a BASE/QUOTE symbol",
+                    symbol
+                ))
+            })?;
+            if s.tickers.is_empty() {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".publish_inverse is set but the symbol has no tickers",
+                    symbol
+                )));
+            }
+            if self.symbols.contains_key(&inverse) || self.computed_feeds().any(|c| c == inverse) {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".publish_inverse would publish {}, which is already configured",
+                    symbol, inverse
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_sanity_bounds(&self) -> Result<(), ConfigError> {
+        for (symbol, s) in &self.symbols {
+            let bounds = [
+                ("min_price", s.min_price),
+                ("max_price", s.max_price),
+                ("max_change_pct", s.max_change_pct),
+            ];
+            for (name, value) in bounds {
+                if let Some(value) = value.filter(|v| !(*v > 0.0 && v.is_finite())) {
+                    return Err(ConfigError::Message(format!(
+                        "symbols.\"{}\".{} must be positive, not {}",
+                        symbol, name, value
+                    )));
+                }
+            }
+            if let (Some(min), Some(max)) = (s.min_price, s.max_price)
+                && min >= max
+            {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".min_price {} must be below max_price {}",
+                    symbol, min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_rate_limits(&self) -> Result<(), ConfigError> {
+        for (name, exchange) in self.apis.exchanges() {
+            let Some(limit) = &exchange.rate_limit else {
+                continue;
+            };
+            let fields = [
+                ("weight_per_minute", Some(limit.weight_per_minute)),
+                ("burst", limit.burst),
+                ("request_weight", Some(limit.request_weight)),
+            ];
+            if let Some((field, _)) = fields.iter().find(|(_, value)| *value == Some(0)) {
+                return Err(ConfigError::Message(format!(
+                    "apis.{}.rate_limit.{} must be positive",
+                    name, field
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// A retried request that could outlast its share of `general.source_timeout_ms` would
+    /// be abandoned mid-retry, so the retries could never help.
+    fn validate_request_budget(&self) -> Result<(), ConfigError> {
+        let retry = &self.http_client.retry;
+        if retry.max_request_ms() > self.general.source_timeout_ms {
+            return Err(ConfigError::Message(format!(
+                "http_client.retry.attempt_timeout_ms {} over {} attempts, with the retries' \
+                 backoff, takes up to {} ms, more than general.source_timeout_ms {}",
+                retry.attempt_timeout_ms,
+                retry.max_attempts,
+                retry.max_request_ms(),
+                self.general.source_timeout_ms
+            )));
+        }
+        Ok(())
+    }
+
+    /// Scaled prices are `u64`s, so beyond 18 decimals not even a price of 1 fits.
+    fn validate_decimals(&self) -> Result<(), ConfigError> {
+        for (symbol, s) in &self.symbols {
+            if let Some(decimals) = s.decimals.filter(|d| *d > MAX_DECIMALS) {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".decimals must be at most {}, not {}",
+                    symbol, MAX_DECIMALS, decimals
+                )));
+            }
+            let decimals = self.decimals(symbol);
+            if let Some(max_price) = s.max_price
+                && max_price * 10f64.powi(decimals.into()) > u64::MAX as f64
+            {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\".max_price {} does not fit a u64 at {} decimals",
+                    symbol, max_price, decimals
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// `general.timezone`, checked when the settings are loaded.
+    pub fn reporting_timezone(&self) -> chrono_tz::Tz {
+        calendar::parse_timezone(&self.general.timezone).unwrap_or(chrono_tz::Tz::UTC)
+    }
+
+    /// Keys `[assets]` by canonical coin type and checks that each names a published feed.
+    fn normalize_assets(&mut self) -> Result<(), ConfigError> {
+        let derived = self.derived_feeds();
+        let published = |feed: &str| {
+            self.feed_symbols().contains(&feed)
+                || self.computed_feeds().any(|c| c == feed)
+                || derived.iter().any(|d| d == feed)
+        };
+        let mut assets = BTreeMap::new();
+        for (coin_type, asset) in &self.assets {
+            let canonical = assets::canonical_coin_type(coin_type)
+                .map_err(|e| ConfigError::Message(format!("assets.\"{}\": {}", coin_type, e)))?;
+            if !published(&asset.feed) {
+                return Err(ConfigError::Message(format!(
+                    "assets.\"{}\".feed names unknown feed '{}'",
+                    coin_type, asset.feed
+                )));
+            }
+            if assets.insert(canonical.clone(), asset.clone()).is_some() {
+                return Err(ConfigError::Message(format!(
+                    "assets.\"{}\" is configured twice, both written as {}",
+                    coin_type, canonical
+                )));
+            }
+        }
+        self.assets = assets;
+        Ok(())
+    }
+
+    /// The ticker of `symbol` on `source`, if that source quotes it.
+    pub fn ticker(&self, symbol: &str, source: &str) -> Option<&String> {
+        self.symbols.get(symbol)?.tickers.get(source)
+    }
+
+    /// Keys `[symbols]` by canonical pair, e.g. `btc-usd` as `BTC/USD`, and rewrites
+    /// `fees.usd_feed`, index components and cross rates the same way. A feed with tickers
+    /// must be a pair; other entries, e.g. of indices, are kept as written.
+    fn normalize_symbols(&mut self) -> Result<(), ConfigError> {
+        let canonical = |name: &str| Symbol::parse(name).map(|pair| pair.to_string());
+        let mut symbols = BTreeMap::new();
+        for (name, symbol_settings) in std::mem::take(&mut self.symbols) {
+            let key = match canonical(&name) {
+                Ok(key) => key,
+                Err(e) if !symbol_settings.tickers.is_empty() => {
+                    return Err(ConfigError::Message(format!("symbols.\"{}\": {}", name, e)));
+                }
+                Err(_) => name.clone(),
+            };
+            if symbols.insert(key.clone(), symbol_settings).is_some() {
+                return Err(ConfigError::Message(format!(
+                    "symbols.\"{}\" is configured twice, both written as {}",
+                    name, key
+                )));
+            }
+        }
+        self.symbols = symbols;
+        for index in self.indices.values_mut() {
+            index.components = std::mem::take(&mut index.components)
+                .into_iter()
+                .map(|(name, weight)| (canonical(&name).unwrap_or(name), weight))
+                .collect();
+        }
+        self.crosses = std::mem::take(&mut self.crosses)
+            .into_iter()
+            .map(|(name, cross)| {
+                let cross = CrossSettings {
+                    base: canonical(&cross.base).unwrap_or(cross.base),
+                    quote: canonical(&cross.quote).unwrap_or(cross.quote),
+                };
+                (canonical(&name).unwrap_or(name), cross)
+            })
+            .collect();
+        if let Ok(usd_feed) = canonical(&self.fees.usd_feed) {
+            self.fees.usd_feed = usd_feed;
+        }
+        Ok(())
+    }
+
+    /// Adds every `[symbols]` ticker to its exchange's fetch list, rejecting tickers of
+    /// unknown sources. An `"auto"` ticker is the symbol in the source's format.
+    pub fn resolve_tickers(&mut self) -> Result<(), ConfigError> {
+        for (symbol, symbol_settings) in &mut self.symbols {
+            for (source, ticker) in &mut symbol_settings.tickers {
+                if ticker != AUTO_TICKER {
+                    continue;
+                }
+                if source == "coingecko" && self.apis.coingecko.is_some() {
+                    return Err(ConfigError::Message(format!(
+                        "symbols.\"{}\".tickers.coingecko can't be \"auto\"; use the coin ID \
+                         and currency, e.g. \"bitcoin/usd\"",
+                        symbol
+                    )));
+                }
+                let kind = self.apis.source_kind(source).ok_or_else(|| {
+                    ConfigError::Message(format!(
+                        "symbols.\"{}\".tickers names unknown source '{}'",
+                        symbol, source
+                    ))
+                })?;
+                let pair = Symbol::parse(symbol).map_err(|e| {
+                    ConfigError::Message(format!(
+                        "symbols.\"{}\".tickers.{} is \"auto\": {}",
+                        symbol, source, e
+                    ))
+                })?;
+                *ticker = pair.ticker(kind);
+            }
+        }
+        let mut exchanges = self.apis.exchanges_mut();
+        for (symbol, symbol_settings) in &self.symbols {
+            for (source, ticker) in &symbol_settings.tickers {
+                let (_, exchange) = exchanges
+                    .iter_mut()
+                    .find(|(name, _)| name == source)
+                    .ok_or_else(|| {
+                        ConfigError::Message(format!(
+                            "symbols.\"{}\".tickers names unknown source '{}'",
+                            symbol, source
+                        ))
+                    })?;
+                if !exchange.symbols.contains(ticker) {
+                    exchange.symbols.push(ticker.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_state_paths(&mut self) {
+        let state_dir = self.general.state_dir.clone();
+        self.admin.registered_symbols_file =
+            state_path(&state_dir, &self.admin.registered_symbols_file);
+        self.admin.registered_sources_file =
+            state_path(&state_dir, &self.admin.registered_sources_file);
+        self.quarantine.file = state_path(&state_dir, &self.quarantine.file);
+        self.outbox.file = state_path(&state_dir, &self.outbox.file);
+        self.fees.ledger_file = state_path(&state_dir, &self.fees.ledger_file);
+        self.storage.file = state_path(&state_dir, &self.storage.file);
+        let sui = &mut self.sui;
+        sui.known_objects_file = state_path(&state_dir, &sui.known_objects_file);
+        if let Some(keystore_file) = &mut sui.keystore_file {
+            *keystore_file = state_path(&state_dir, keystore_file);
+        }
+        if let Some(migration) = &mut sui.migration {
+            migration.previous_known_objects_file =
+                state_path(&state_dir, &migration.previous_known_objects_file);
+        }
+    }
+
+    /// Points the publisher at the `sui.network` profile, if one is selected. Fails without
+    /// a package ID for the network rather than publishing to another network's package.
+    fn apply_network(&mut self) -> Result<(), ConfigError> {
+        let Some(network) = self.sui.network else {
+            return Ok(());
+        };
+        let profile = self.sui.networks.get(&network).cloned().unwrap_or_default();
+        self.sui.package_id = profile.package_id.ok_or_else(|| {
+            ConfigError::Message(format!(
+                "sui.network is {} but sui.networks.{}.package_id is not set",
+                network.name(),
+                network.name()
+            ))
+        })?;
+        self.sui.rpc_url = profile.rpc_url.unwrap_or_else(|| network.rpc_url());
+        self.sui.fallback_rpc_urls = profile.fallback_rpc_urls;
+        self.sui.known_objects_file = profile
+            .known_objects_file
+            .unwrap_or_else(|| format!("known_price_objects_{}.json", network.name()));
+        self.sui.expected_package_digest = profile.expected_package_digest;
+        Ok(())
+    }
+
+    /// Points every source at its sandbox endpoint and the publisher at the paper network.
+    /// Fails rather than silently falling back to a production endpoint.
+    fn apply_paper_mode(&mut self) -> Result<(), ConfigError> {
+        for (name, exchange) in self.apis.exchanges_mut() {
+            let sandbox_url = exchange.sandbox_base_url.clone().ok_or_else(|| {
+                ConfigError::Message(format!(
+                    "paper_mode is enabled but apis.{}.sandbox_base_url is not set",
+                    name
+                ))
+            })?;
+            exchange.base_url = sandbox_url;
+            exchange.mirrors.clear();
+            exchange.stream_url = exchange.sandbox_stream_url.clone();
+        }
+
+        let paper = self.sui.paper.clone();
+        self.sui.package_id = paper.package_id.ok_or_else(|| {
+            ConfigError::Message(
+                "paper_mode is enabled but sui.paper.package_id is not set".to_string(),
+            )
+        })?;
+        self.sui.rpc_url = paper.rpc_url;
+        self.sui.fallback_rpc_urls.clear();
+        self.sui.known_objects_file = paper.known_objects_file;
+        self.sui.expected_package_digest = None;
+        self.sui.network = None;
+        // The previous package lives on the production network, not the paper one.
+        self.sui.migration = None;
+        log::warn!(
+            "Paper mode enabled: using sandbox exchange endpoints and Sui RPC {}",
+            self.sui.rpc_url
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    // Helper to create a temporary config file for testing
+    fn create_temp_config_file(dir: &str, name: &str, content: &str) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = format!("{}/{}.toml", dir, name);
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "{}", content)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_defaults_only() -> Result<()> {
+        // Create a dummy default.toml
+        let config_dir = "./test_config_load_defaults";
+        create_temp_config_file(
+            config_dir,
+            "default",
+            r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+symbols = ["BTCUSDT", "ETHUSDT"]
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+symbols = ["BTC-USD", "ETH-USD"]
+
+[general]
+fetch_interval_seconds = 5
+        "#,
+        )?;
+
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
+        let settings: Settings = s.try_deserialize()?;
+
+        assert_eq!(
+            settings.apis.binance.base_url,
+            "https://api.binance.com/api/v3"
+        );
+        assert_eq!(settings.apis.binance.symbols, vec!["BTCUSDT", "ETHUSDT"]);
+        assert_eq!(
+            settings.apis.coinbase.base_url,
+            "https://api.exchange.coinbase.com"
+        );
+        assert_eq!(settings.apis.coinbase.symbols, vec!["BTC-USD", "ETH-USD"]);
+        assert_eq!(settings.general.fetch_interval_seconds, 5);
+        assert_eq!(settings.general.max_source_age_ms, 60_000);
+        assert!(!settings.general.paper_mode);
+        assert_eq!(settings.sui.rpc_url, "https://fullnode.testnet.sui.io:443");
+        assert_eq!(settings.sui.known_objects_file, "known_price_objects.json");
+
+        // Clean up
+        fs::remove_dir_all(config_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_with_local_override() -> Result<()> {
+        let config_dir = "./test_config_load_local";
+        // Default config
+        create_temp_config_file(
+            config_dir,
+            "default",
+            r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+symbols = ["BTCUSDT", "ETHUSDT"]
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+symbols = ["BTC-USD", "ETH-USD"]
+
+[general]
+fetch_interval_seconds = 10 # Default interval
+        "#,
+        )?;
+
+        // Local override for binance url and one symbol, and fetch interval
+        create_temp_config_file(
+            config_dir,
+            "local",
+            r#"
+[apis.binance]
+base_url = "http://localhost:8080/binance"
+symbols = ["DOGEUSDT"]
+
+[general]
+fetch_interval_seconds = 3 # Override interval
+        "#,
+        )?;
+
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .add_source(File::with_name(&format!("{}/local", config_dir)).required(false))
+            .build()?;
+        let settings: Settings = s.try_deserialize()?;
+
+        // Binance should be overridden
+        assert_eq!(
+            settings.apis.binance.base_url,
+            "http://localhost:8080/binance"
+        );
+        assert_eq!(settings.apis.binance.symbols, vec!["DOGEUSDT"]);
+        // Coinbase should remain default
+        assert_eq!(
+            settings.apis.coinbase.base_url,
+            "https://api.exchange.coinbase.com"
+        );
+        assert_eq!(settings.apis.coinbase.symbols, vec!["BTC-USD", "ETH-USD"]);
+        assert_eq!(settings.general.fetch_interval_seconds, 3);
+
+        fs::remove_dir_all(config_dir)?;
+        Ok(())
+    }
+
+    fn paper_mode_settings(config_dir: &str, sui_section: &str) -> Result<Settings> {
+        create_temp_config_file(
+            config_dir,
+            "default",
+            &format!(
+                r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3/ticker/price"
+sandbox_base_url = "https://testnet.binance.vision/api/v3/ticker/price"
+symbols = ["BTCUSDT"]
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com/products"
+sandbox_base_url = "https://api-public.sandbox.exchange.coinbase.com/products"
+symbols = ["BTC-USD"]
+
+[general]
+fetch_interval_seconds = 5
+paper_mode = true
+
+{}
+        "#,
+                sui_section
+            ),
+        )?;
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
+        let settings: Settings = s.try_deserialize()?;
+        fs::remove_dir_all(config_dir)?;
+        Ok(settings)
+    }
+
+    #[test]
+    fn test_paper_mode_switches_to_test_endpoints() -> Result<()> {
+        let mut settings = paper_mode_settings(
+            "./test_config_paper_mode",
+            r#"
+[sui.paper]
+package_id = "0x42"
+
+[sui.migration]
+previous_package_id = "0x41"
+previous_known_objects_file = "known_price_objects.v1.json"
+ends_at = "2026-11-01T00:00:00Z"
+        "#,
+        )?;
+        settings.apply_paper_mode()?;
+
+        assert_eq!(
+            settings.apis.binance.base_url,
+            "https://testnet.binance.vision/api/v3/ticker/price"
+        );
+        assert_eq!(
+            settings.apis.coinbase.base_url,
+            "https://api-public.sandbox.exchange.coinbase.com/products"
+        );
+        assert_eq!(settings.sui.rpc_url, "http://127.0.0.1:9000");
+        assert_eq!(settings.sui.package_id, "0x42");
+        assert_eq!(
+            settings.sui.known_objects_file,
+            "known_price_objects.paper.json"
+        );
+        assert!(settings.sui.migration.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_profile_selects_rpc_package_and_objects() -> Result<()> {
+        let mut settings = paper_mode_settings(
+            "./test_config_network",
+            r#"
+[sui]
+network = "mainnet"
+
+[sui.networks.mainnet]
+package_id = "0x43"
+
+[sui.networks.localnet]
+package_id = "0x44"
+known_objects_file = "local.json"
+        "#,
+        )?;
+        settings.apply_network()?;
+        assert_eq!(settings.sui.rpc_url, "https://fullnode.mainnet.sui.io:443");
+        assert_eq!(settings.sui.package_id, "0x43");
+        assert_eq!(
+            settings.sui.known_objects_file,
+            "known_price_objects_mainnet.json"
+        );
+
+        settings.sui.network = Some(SuiNetwork::Localnet);
+        settings.apply_network()?;
+        assert_eq!(settings.sui.rpc_url, "http://127.0.0.1:9000");
+        assert_eq!(settings.sui.package_id, "0x44");
+        assert_eq!(settings.sui.known_objects_file, "local.json");
+
+        settings.sui.network = Some(SuiNetwork::Devnet);
+        let err = settings.apply_network().unwrap_err().to_string();
+        assert!(err.contains("sui.networks.devnet.package_id"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_price_adjustments_per_venue_and_symbol() -> Result<()> {
+        let config_dir = "./test_config_adjustments";
+        create_temp_config_file(
+            config_dir,
+            "default",
+            r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+price_adjustment_bps = -10.0
+rate_limit = { weight_per_minute = 1200 }
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+
+[general]
+fetch_interval_seconds = 5
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct Settings {
-    pub apis: ApiConfigs,
-    pub general: GeneralSettings,
-    // We could add other general settings here later, e.g., logging level, aggregation strategy, etc.
-}
+[symbols."BTC/USD"]
+tickers = { binance = "BTCUSDT", coinbase = "BTC-USD" }
+price_adjustments_bps = { binance = -2.5 }
 
-impl Settings {
-    pub fn load() -> Result<Self, ConfigError> {
-        let builder = Config::builder()
-            // Start with `./config/default.toml`
-            .add_source(File::with_name("config/default").required(true))
-            // Add in `./config/local.toml` to override defaults
-            .add_source(File::with_name("config/local").required(false));
+[symbols."ETH/USD"]
+tickers = { binance = "ETHUSDT" }
+        "#,
+        )?;
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
+        fs::remove_dir_all(config_dir)?;
+        let mut settings: Settings = s.try_deserialize()?;
+        settings.validate_price_adjustments()?;
 
-        builder.build()?.try_deserialize()
-    }
-}
+        assert_eq!(settings.price_adjustment_bps("BTC/USD", "binance"), -2.5);
+        assert_eq!(settings.price_adjustment_bps("ETH/USD", "binance"), -10.0);
+        assert_eq!(settings.price_adjustment_bps("BTC/USD", "coinbase"), 0.0);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::io::Write;
+        settings.apis.coinbase.price_adjustment_bps = -10_000.0;
+        assert!(settings.validate_price_adjustments().is_err());
+        settings.apis.coinbase.price_adjustment_bps = 0.0;
+        let eth = settings.symbols.get_mut("ETH/USD").unwrap();
+        eth.price_adjustments_bps.insert("kraken".to_string(), 5.0);
+        let err = settings
+            .validate_price_adjustments()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown source 'kraken'"), "{}", err);
 
-    // Helper to create a temporary config file for testing
-    fn create_temp_config_file(dir: &str, name: &str, content: &str) -> Result<()> {
-        fs::create_dir_all(dir)?;
-        let path = format!("{}/{}.toml", dir, name);
-        let mut file = fs::File::create(path)?;
-        writeln!(file, "{}", content)?;
+        let limit = settings.apis.binance.rate_limit.as_ref().unwrap();
+        assert_eq!((limit.burst, limit.request_weight), (None, 1));
+        assert!(settings.validate_rate_limits().is_ok());
+        settings.apis.coinbase.rate_limit = Some(RateLimitSettings {
+            weight_per_minute: 600,
+            burst: Some(0),
+            request_weight: 1,
+        });
+        let err = settings.validate_rate_limits().unwrap_err().to_string();
+        assert!(err.contains("apis.coinbase.rate_limit.burst"), "{}", err);
+
+        // 3 attempts of 1000 ms, waiting 100 and 200 ms between them.
+        assert_eq!(settings.http_client.retry.max_request_ms(), 3300);
+        assert!(settings.validate_request_budget().is_ok());
+        settings.http_client.retry.attempt_timeout_ms = 3000;
+        let err = settings.validate_request_budget().unwrap_err().to_string();
+        assert!(err.contains("up to 9300 ms"), "{}", err);
+        settings.http_client.retry.max_attempts = 1;
+        assert!(settings.validate_request_budget().is_ok());
         Ok(())
     }
 
     #[test]
-    fn test_load_config_defaults_only() -> Result<()> {
-        // Create a dummy default.toml
-        let config_dir = "./test_config_load_defaults";
+    fn test_shadow_aggregation_overrides_live_settings() -> Result<()> {
+        let config_dir = "./test_config_shadow";
         create_temp_config_file(
             config_dir,
             "default",
             r#"
 [apis.binance]
 base_url = "https://api.binance.com/api/v3"
-symbols = ["BTCUSDT", "ETHUSDT"]
 
 [apis.coinbase]
 base_url = "https://api.exchange.coinbase.com"
-symbols = ["BTC-USD", "ETH-USD"]
 
 [general]
 fetch_interval_seconds = 5
+
+[aggregation]
+method = "mean"
+
+[symbols."BTC/USD"]
+aggregation_method = "vwap"
+
+[shadow]
+enabled = true
+min_sources = 3
+weights = { binance = 2.0 }
         "#,
         )?;
-
         let s = Config::builder()
             .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
             .build()?;
-        let settings: Settings = s.try_deserialize()?;
+        fs::remove_dir_all(config_dir)?;
+        let mut settings: Settings = s.try_deserialize()?;
+
+        let (method, aggregation) = settings.shadow_aggregation("BTC/USD");
+        assert_eq!(method, AggregationMethod::Vwap);
+        assert_eq!((aggregation.min_sources, aggregation.trim_pct), (3, 20.0));
+        assert_eq!(settings.shadow_source_weight("binance"), 2.0);
+        assert_eq!(settings.shadow_source_weight("coinbase"), 1.0);
 
+        settings.shadow.method = Some(AggregationMethod::Median);
         assert_eq!(
-            settings.apis.binance.base_url,
-            "https://api.binance.com/api/v3"
+            settings.shadow_aggregation("BTC/USD").0,
+            AggregationMethod::Median
         );
-        assert_eq!(settings.apis.binance.symbols, vec!["BTCUSDT", "ETHUSDT"]);
         assert_eq!(
-            settings.apis.coinbase.base_url,
-            "https://api.exchange.coinbase.com"
+            settings.aggregation_method("BTC/USD"),
+            AggregationMethod::Vwap
         );
-        assert_eq!(settings.apis.coinbase.symbols, vec!["BTC-USD", "ETH-USD"]);
-        assert_eq!(settings.general.fetch_interval_seconds, 5);
+        Ok(())
+    }
 
-        // Clean up
+    #[test]
+    fn test_source_weights() -> Result<()> {
+        let config_dir = "./test_config_source_weights";
+        create_temp_config_file(
+            config_dir,
+            "default",
+            r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+
+[apis.coingecko]
+base_url = "https://api.coingecko.com/api/v3"
+
+[general]
+fetch_interval_seconds = 5
+
+[aggregation]
+weights = { coinbase = 0.5 }
+
+[shadow]
+weights = { binance = 2.0 }
+        "#,
+        )?;
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
         fs::remove_dir_all(config_dir)?;
+        let mut settings: Settings = s.try_deserialize()?;
+
+        assert_eq!(settings.source_weight("binance"), 1.0);
+        assert_eq!(settings.source_weight("coinbase"), 0.5);
+        assert_eq!(settings.shadow_source_weight("binance"), 2.0);
+        assert_eq!(settings.shadow_source_weight("coinbase"), 0.5);
+        assert!(settings.validate_source_weights().is_ok());
+        settings
+            .aggregation
+            .weights
+            .insert("kraken".to_string(), 1.0);
+        assert!(settings.validate_source_weights().is_err());
+        settings.aggregation.weights = BTreeMap::from([("binance".to_string(), 0.0)]);
+        assert!(settings.validate_source_weights().is_err());
+        settings.aggregation.weights = BTreeMap::from([("coingecko".to_string(), 0.25)]);
+        assert!(settings.validate_source_weights().is_err());
         Ok(())
     }
 
     #[test]
-    fn test_load_config_with_local_override() -> Result<()> {
-        let config_dir = "./test_config_load_local";
-        // Default config
+    fn test_symbol_tickers_drive_fetch_lists() -> Result<()> {
+        let config_dir = "./test_config_tickers";
         create_temp_config_file(
             config_dir,
             "default",
             r#"
 [apis.binance]
 base_url = "https://api.binance.com/api/v3"
-symbols = ["BTCUSDT", "ETHUSDT"]
 
 [apis.coinbase]
 base_url = "https://api.exchange.coinbase.com"
-symbols = ["BTC-USD", "ETH-USD"]
+symbols = ["BTC-USD"]
 
 [general]
-fetch_interval_seconds = 10 # Default interval
+fetch_interval_seconds = 5
+
+[symbols."BTC/USD"]
+tickers = { binance = "BTCUSDT", coinbase = "BTC-USD" }
+
+[symbols."SUI/USD"]
+tickers = { binance = "SUIUSDT" }
+publish_bid_ask = true
+publish_inverse = true
+decimals = 9
+
+[symbols."TOP2"]
+priority = 5
         "#,
         )?;
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
+        fs::remove_dir_all(config_dir)?;
+        let mut settings: Settings = s.try_deserialize()?;
+        settings.resolve_tickers()?;
 
-        // Local override for binance url and one symbol, and fetch interval
+        assert_eq!(settings.feed_symbols(), vec!["BTC/USD", "SUI/USD"]);
+        assert_eq!(settings.apis.binance.symbols, vec!["BTCUSDT", "SUIUSDT"]);
+        assert_eq!(settings.apis.coinbase.symbols, vec!["BTC-USD"]);
+        assert_eq!(
+            settings.ticker("SUI/USD", "binance").map(String::as_str),
+            Some("SUIUSDT")
+        );
+        assert_eq!(settings.ticker("SUI/USD", "coinbase"), None);
+        assert_eq!(settings.quote_feeds(), vec!["SUI/USD:bid", "SUI/USD:ask"]);
+        assert_eq!(quote_feed_parent("SUI/USD:ask"), Some("SUI/USD"));
+        assert_eq!(quote_feed_parent("SUI/USD"), None);
+        assert_eq!(
+            settings.derived_feeds(),
+            vec!["SUI/USD:bid", "SUI/USD:ask", "USD/SUI"]
+        );
+        assert_eq!(settings.derived_feed_parent("USD/SUI"), Some("SUI/USD"));
+        assert_eq!(settings.derived_feed_parent("SUI/USD:bid"), Some("SUI/USD"));
+        assert_eq!(settings.derived_feed_parent("USD/BTC"), None);
+        assert_eq!(inverse_symbol("TOP2"), None);
+        assert!(settings.validate_inverse_feeds().is_ok());
+        settings
+            .symbols
+            .insert("USD/SUI".to_string(), SymbolSettings::default());
+        assert!(settings.validate_inverse_feeds().is_err());
+        settings.symbols.remove("USD/SUI");
+
+        assert_eq!(settings.decimals("SUI/USD:ask"), 9);
+        assert_eq!(settings.decimals("USD/SUI"), 6);
+        assert_eq!(settings.decimals("BTC/USD"), 6);
+        let mut feed_decimals: Vec<(String, u8)> = settings.feed_decimals().into_iter().collect();
+        feed_decimals.sort();
+        assert_eq!(
+            feed_decimals,
+            [("SUI/USD", 9), ("SUI/USD:ask", 9), ("SUI/USD:bid", 9)].map(|(f, d)| (f.into(), d))
+        );
+        assert!(settings.validate_decimals().is_ok());
+        settings.symbols.get_mut("SUI/USD").unwrap().decimals = Some(19);
+        assert!(settings.validate_decimals().is_err());
+        // Up to about 18.4 at 18 decimals, about 18.4 billion at 9.
+        let sui = settings.symbols.get_mut("SUI/USD").unwrap();
+        sui.decimals = Some(18);
+        sui.max_price = Some(18.0);
+        assert!(settings.validate_decimals().is_ok());
+        settings.symbols.get_mut("SUI/USD").unwrap().max_price = Some(19.0);
+        assert!(settings.validate_decimals().is_err());
+        settings.symbols.get_mut("SUI/USD").unwrap().decimals = Some(9);
+        assert!(settings.validate_decimals().is_ok());
+        settings.symbols.get_mut("SUI/USD").unwrap().max_price = None;
+
+        assert!(settings.validate_sanity_bounds().is_ok());
+        let sui = settings.symbols.get_mut("SUI/USD").unwrap();
+        sui.min_price = Some(5.0);
+        sui.max_price = Some(1.0);
+        assert!(settings.validate_sanity_bounds().is_err());
+        let sui = settings.symbols.get_mut("SUI/USD").unwrap();
+        sui.max_price = None;
+        sui.max_change_pct = Some(0.0);
+        assert!(settings.validate_sanity_bounds().is_err());
+        let sui = settings.symbols.get_mut("SUI/USD").unwrap();
+        sui.min_price = None;
+        sui.max_change_pct = None;
+
+        settings
+            .symbols
+            .get_mut("SUI/USD")
+            .unwrap()
+            .tickers
+            .insert("kraken".to_string(), "SUIUSD".to_string());
+        assert!(
+            settings
+                .resolve_tickers()
+                .unwrap_err()
+                .to_string()
+                .contains("unknown source 'kraken'")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbols_are_normalized() {
+        let parse = |toml: &str| -> Settings {
+            Config::builder()
+                .add_source(File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        let base = r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+
+[general]
+fetch_interval_seconds = 5
+
+[fees]
+usd_feed = "sui-usd"
+"#;
+        let mut settings = parse(&format!(
+            r#"{}
+[symbols."btc-usd"]
+tickers = {{ binance = "BTCUSDT", coinbase = "auto" }}
+
+[symbols."sui/usd"]
+tickers = {{ binance = "auto" }}
+
+[symbols."TOP2"]
+priority = 5
+
+[indices."TOP2"]
+components = {{ "btc_usd" = 0.5, "SUI/USD" = 0.5 }}
+
+[crosses."sui-btc"]
+base = "sui_usd"
+quote = "BTC/USD"
+"#,
+            base
+        ));
+        settings.normalize_symbols().unwrap();
+        settings.resolve_tickers().unwrap();
+        assert_eq!(settings.feed_symbols(), vec!["BTC/USD", "SUI/USD"]);
+        assert!(settings.symbols.contains_key("TOP2"));
+        assert_eq!(settings.fees.usd_feed, "SUI/USD");
+        assert_eq!(
+            settings.indices["TOP2"]
+                .components
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["BTC/USD", "SUI/USD"]
+        );
+        assert_eq!(settings.crosses["SUI/BTC"].base, "SUI/USD");
+        assert_eq!(
+            settings.computed_feeds().collect::<Vec<_>>(),
+            vec!["TOP2", "SUI/BTC"]
+        );
+        assert_eq!(settings.apis.binance.symbols, vec!["BTCUSDT", "SUIUSD"]);
+        assert_eq!(
+            settings.ticker("BTC/USD", "coinbase").map(String::as_str),
+            Some("BTC-USD")
+        );
+
+        let mut twice = parse(&format!(
+            r#"{}
+[symbols."BTC/USD"]
+tickers = {{ binance = "BTCUSDT" }}
+
+[symbols."btc-usd"]
+tickers = {{ coinbase = "BTC-USD" }}
+"#,
+            base
+        ));
+        let err = twice.normalize_symbols().unwrap_err().to_string();
+        assert!(
+            err.contains("configured twice, both written as BTC/USD"),
+            "{}",
+            err
+        );
+        let mut not_a_pair = parse(&format!(
+            r#"{}
+[symbols."BTCUSD"]
+tickers = {{ binance = "BTCUSD" }}
+"#,
+            base
+        ));
+        let err = not_a_pair.normalize_symbols().unwrap_err().to_string();
+        assert!(err.contains("is not a BASE/QUOTE pair"), "{}", err);
+    }
+
+    #[test]
+    fn test_optional_exchanges_are_sources_once_configured() {
+        let parse = |optional: &str| -> Settings {
+            Config::builder()
+                .add_source(File::from_str(
+                    &format!(
+                        r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+{}
+[general]
+fetch_interval_seconds = 5
+
+[symbols."BTC/USDT"]
+tickers = {{ binance = "BTCUSDT", okx = "auto", bybit = "auto" }}
+"#,
+                        optional
+                    ),
+                    config::FileFormat::Toml,
+                ))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        let mut without = parse(
+            r#"
+[apis.okx]
+base_url = "https://www.okx.com/api/v5"
+"#,
+        );
+        assert!(!without.apis.has_source("bybit"));
+        let err = without.resolve_tickers().unwrap_err().to_string();
+        assert!(err.contains("unknown source 'bybit'"), "{}", err);
+
+        let mut settings = parse(
+            r#"
+[apis.okx]
+base_url = "https://www.okx.com/api/v5"
+
+[apis.bybit]
+base_url = "https://api.bybit.com/v5"
+
+[apis.coingecko]
+base_url = "https://api.coingecko.com/api/v3"
+max_age_ms = 120000
+"#,
+        );
+        settings.resolve_tickers().unwrap();
+        assert!(settings.apis.has_source("okx"));
+        assert_eq!(
+            settings.ticker("BTC/USDT", "okx").map(String::as_str),
+            Some("BTC-USDT")
+        );
+        assert_eq!(settings.apis.okx.unwrap().symbols, vec!["BTC-USDT"]);
+        assert_eq!(settings.apis.bybit.unwrap().symbols, vec!["BTCUSDT"]);
+        let coingecko = settings.apis.coingecko.unwrap();
+        assert_eq!(
+            coingecko.exchange.base_url,
+            "https://api.coingecko.com/api/v3"
+        );
+        assert_eq!(
+            (coingecko.refresh_interval_ms, coingecko.max_age_ms),
+            (60_000, 120_000)
+        );
+    }
+
+    #[test]
+    fn test_assets_are_keyed_by_canonical_type() {
+        let parse = |assets: &str| -> Settings {
+            Config::builder()
+                .add_source(File::from_str(
+                    &format!(
+                        r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+
+[general]
+fetch_interval_seconds = 5
+
+[symbols."SUI/USD"]
+tickers = {{ binance = "SUIUSDT" }}
+{}"#,
+                        assets
+                    ),
+                    config::FileFormat::Toml,
+                ))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        let mut settings = parse(
+            r#"
+[assets."0x2::sui::SUI"]
+feed = "SUI/USD"
+decimals = 9
+"#,
+        );
+        settings.normalize_assets().unwrap();
+        let sui = "0x0000000000000000000000000000000000000000000000000000000000000002::sui::SUI";
+        assert_eq!(settings.assets[sui].decimals, Some(9));
+
+        let mut twice = parse(&format!(
+            r#"
+[assets."0x2::sui::SUI"]
+feed = "SUI/USD"
+
+[assets."{}"]
+feed = "SUI/USD"
+"#,
+            sui
+        ));
+        let err = twice.normalize_assets().unwrap_err().to_string();
+        assert!(err.contains("configured twice"), "{}", err);
+        let mut unknown = parse(
+            r#"
+[assets."0x2::sui::SUI"]
+feed = "ETH/USD"
+"#,
+        );
+        let err = unknown.normalize_assets().unwrap_err().to_string();
+        assert!(err.contains("unknown feed 'ETH/USD'"), "{}", err);
+    }
+
+    #[test]
+    fn test_migration_window() {
+        let ends_at: DateTime<Utc> = "2026-11-01T00:00:00Z".parse().unwrap();
+        let sui = SuiSettings {
+            package_id: "0x42".to_string(),
+            migration: Some(SuiMigration {
+                previous_package_id: "0x41".to_string(),
+                previous_known_objects_file: "known_price_objects.v1.json".to_string(),
+                ends_at,
+            }),
+            ..Default::default()
+        };
+        let previous = sui
+            .previous_package(ends_at - chrono::Duration::seconds(1))
+            .unwrap();
+        assert_eq!(previous.package_id, "0x41");
+        assert_eq!(previous.known_objects_file, "known_price_objects.v1.json");
+        assert_eq!(previous.rpc_url, sui.rpc_url);
+        assert!(sui.previous_package(ends_at).is_none());
+        assert!(SuiSettings::default().previous_package(ends_at).is_none());
+    }
+
+    #[test]
+    fn test_state_paths() {
+        assert_eq!(
+            state_path("state", "known_price_objects.json"),
+            "state/known_price_objects.json"
+        );
+        assert_eq!(
+            state_path("state", "/etc/neo-oracle/sui.keystore"),
+            "/etc/neo-oracle/sui.keystore"
+        );
+    }
+
+    #[test]
+    fn test_instance_label_names() {
+        let instance = |name: &str| InstanceSettings {
+            labels: BTreeMap::from([(name.to_string(), "oracle-a".to_string())]),
+        };
+        assert!(instance("instance").validate().is_ok());
+        assert!(instance("_role2").validate().is_ok());
+        for invalid in ["", "2nd", "operator-id", "__name__"] {
+            assert!(instance(invalid).validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_admin_tokens_have_their_own_env() {
+        let token = |token_env: &str| AdminTokenSettings {
+            token_env: token_env.to_string(),
+            role: AdminRole::ReadOnly,
+        };
+        let mut admin = AdminSettings {
+            tokens: BTreeMap::from([("dashboards".to_string(), token("ORACLE_DASHBOARD_TOKEN"))]),
+            ..Default::default()
+        };
+        assert!(admin.validate().is_ok());
+        admin
+            .tokens
+            .insert("grafana".to_string(), token("ORACLE_ADMIN_TOKEN"));
+        assert!(admin.validate().is_err());
+        assert!(AdminRole::ReadOnly < AdminRole::FeedOperator);
+        assert!(AdminRole::FeedOperator < AdminRole::KeyAdmin);
+    }
+
+    #[test]
+    fn test_paper_mode_requires_paper_package() -> Result<()> {
+        let mut settings = paper_mode_settings("./test_config_paper_mode_no_package", "")?;
+        let err = settings.apply_paper_mode().unwrap_err();
+        assert!(err.to_string().contains("sui.paper.package_id"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_symbol_sla() -> Result<()> {
+        let config_dir = "./test_config_symbol_sla";
         create_temp_config_file(
             config_dir,
-            "local",
+            "default",
             r#"
 [apis.binance]
-base_url = "http://localhost:8080/binance"
-symbols = ["DOGEUSDT"]
+base_url = "https://api.binance.com/api/v3"
+symbols = ["BTCUSDT"]
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+symbols = ["BTC-USD"]
 
 [general]
-fetch_interval_seconds = 3 # Override interval
+fetch_interval_seconds = 5
+
+[symbols."BTC/USD"]
+max_source_spread_pct = 1.5
+min_liquidity_notional = 50000.0
+
+[symbols."BTC/USD".sla]
+max_staleness_ms = 30000
+min_updates_per_hour = 100
+
+[symbols."ETH/USD"]
+
+[indices."TOP2".components]
+"BTC/USD" = 0.6
+"ETH/USD" = 0.4
+
+[indices]
+"MAJORS" = "0.25 * BTC/USD + ETH/USD"
+
+[consumers.add]
+function = "add_consumer"
+args = ["0x5", "{consumer}"]
         "#,
         )?;
-
         let s = Config::builder()
             .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
-            .add_source(File::with_name(&format!("{}/local", config_dir)).required(false))
             .build()?;
         let settings: Settings = s.try_deserialize()?;
+        fs::remove_dir_all(config_dir)?;
 
-        // Binance should be overridden
         assert_eq!(
-            settings.apis.binance.base_url,
-            "http://localhost:8080/binance"
+            settings.symbols["BTC/USD"].sla,
+            Some(SlaSettings {
+                max_staleness_ms: 30_000,
+                min_updates_per_hour: 100,
+            })
         );
-        assert_eq!(settings.apis.binance.symbols, vec!["DOGEUSDT"]);
-        // Coinbase should remain default
+        assert_eq!(settings.symbols["BTC/USD"].max_source_spread_pct, Some(1.5));
         assert_eq!(
-            settings.apis.coinbase.base_url,
-            "https://api.exchange.coinbase.com"
+            settings.symbols["BTC/USD"].min_liquidity_notional,
+            Some(50_000.0)
         );
-        assert_eq!(settings.apis.coinbase.symbols, vec!["BTC-USD", "ETH-USD"]);
-        assert_eq!(settings.general.fetch_interval_seconds, 3);
+        assert_eq!(settings.symbols["ETH/USD"].sla, None);
+        assert_eq!(settings.symbols["ETH/USD"].max_source_spread_pct, None);
+        assert_eq!(settings.indices["TOP2"].components["BTC/USD"], 0.6);
+        assert_eq!(settings.indices["TOP2"].divisor, 1.0);
+        assert_eq!(settings.indices["MAJORS"].components["BTC/USD"], 0.25);
+        assert_eq!(settings.indices["MAJORS"].components["ETH/USD"], 1.0);
+        assert_eq!(settings.alerts.webhook_url, None);
+        assert_eq!(
+            settings.consumers.add,
+            Some(CallTemplate {
+                module: "price_oracle".to_string(),
+                function: "add_consumer".to_string(),
+                args: vec!["0x5".to_string(), "{consumer}".to_string()],
+            })
+        );
+        assert!(settings.consumers.withdraw_fees.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbol_calendars() -> Result<()> {
+        let config_dir = "./test_config_calendars";
+        create_temp_config_file(
+            config_dir,
+            "default",
+            r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3"
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com"
+
+[general]
+fetch_interval_seconds = 5
+
+[symbols."BTC/USD"]
 
+[symbols."SPY/USD"]
+calendar = "nyse"
+
+[calendars.nyse]
+timezone = "America/New_York"
+days = ["mon", "tue", "wed", "thu", "fri"]
+open = "09:30"
+close = "16:00"
+holidays = ["2026-11-26", "2026-12-25"]
+        "#,
+        )?;
+        let s = Config::builder()
+            .add_source(File::with_name(&format!("{}/default", config_dir)).required(true))
+            .build()?;
+        let settings: Settings = s.try_deserialize()?;
         fs::remove_dir_all(config_dir)?;
+
+        assert_eq!(
+            settings.symbols["SPY/USD"].calendar.as_deref(),
+            Some("nyse")
+        );
+        assert_eq!(settings.symbols["BTC/USD"].calendar, None);
+        assert_eq!(settings.calendars["nyse"].holidays.len(), 2);
         Ok(())
     }
 }
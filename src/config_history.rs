@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::config::{AggregationMethod, OutlierFilter, SlaSettings};
+
+/// The settings that shape one feed's output, as in effect when the oracle started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedConfig {
+    /// Exchange ticker used from each source; `None` when the source doesn't list the feed.
+    pub sources: BTreeMap<String, Option<String>>,
+    pub max_source_age_ms: u64,
+    pub max_source_spread_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_reference_deviation_pct: Option<f64>,
+    #[serde(default)]
+    pub min_liquidity_notional: Option<f64>,
+    #[serde(default)]
+    pub outlier_filter: Option<OutlierFilter>,
+    #[serde(default)]
+    pub aggregation_method: AggregationMethod,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub publish_bid_ask: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub publish_inverse: bool,
+    /// Non-zero venue adjustments in basis points, per source.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub price_adjustments_bps: BTreeMap<String, f64>,
+    pub sla: Option<SlaSettings>,
+    pub calendar: String,
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub recorded_at_ms: u64,
+    pub symbol: String,
+    pub config: FeedConfig,
+}
+
+/// Reads all entries; a missing file is an empty history.
+fn read_all(path: &str) -> Result<Vec<HistoryEntry>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).context(format!("{}: invalid entry on line {}", path, i + 1))
+        })
+        .collect()
+}
+
+/// Appends an entry for every feed whose config differs from its latest recorded one.
+/// Returns the symbols that changed.
+pub fn record(path: &str, now_ms: u64, feeds: &[(String, FeedConfig)]) -> Result<Vec<String>> {
+    let mut latest: HashMap<String, FeedConfig> = HashMap::new();
+    for entry in read_all(path)? {
+        latest.insert(entry.symbol, entry.config);
+    }
+
+    let mut changed = Vec::new();
+    let mut lines = String::new();
+    for (symbol, config) in feeds {
+        if latest.get(symbol) == Some(config) {
+            continue;
+        }
+        let entry = HistoryEntry {
+            recorded_at_ms: now_ms,
+            symbol: symbol.clone(),
+            config: config.clone(),
+        };
+        lines.push_str(&serde_json::to_string(&entry)?);
+        lines.push('\n');
+        changed.push(symbol.clone());
+    }
+    if !lines.is_empty() {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open {}", path))?;
+        file.write_all(lines.as_bytes())
+            .context(format!("Failed to write {}", path))?;
+    }
+    Ok(changed)
+}
+
+/// All recorded configs of `symbol`, oldest first.
+pub fn history(path: &str, symbol: &str) -> Result<Vec<HistoryEntry>> {
+    Ok(read_all(path)?
+        .into_iter()
+        .filter(|e| e.symbol == symbol)
+        .collect())
+}
+
+/// `field: old -> new` for every top-level field that differs between two configs.
+pub fn changed_fields(old: &FeedConfig, new: &FeedConfig) -> Vec<String> {
+    let (serde_json::Value::Object(old), serde_json::Value::Object(new)) = (
+        serde_json::to_value(old).unwrap_or_default(),
+        serde_json::to_value(new).unwrap_or_default(),
+    ) else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(field, value)| old.get(*field) != Some(value))
+        .map(|(field, value)| {
+            let before = old.get(field).cloned().unwrap_or_default();
+            format!("{}: {} -> {}", field, before, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(spread: Option<f64>) -> FeedConfig {
+        FeedConfig {
+            sources: BTreeMap::from([("binance".to_string(), Some("BTCUSDT".to_string()))]),
+            max_source_age_ms: 60_000,
+            max_source_spread_pct: spread,
+            max_reference_deviation_pct: None,
+            min_liquidity_notional: None,
+            outlier_filter: None,
+            aggregation_method: AggregationMethod::Mean,
+            publish_bid_ask: false,
+            publish_inverse: false,
+            price_adjustments_bps: BTreeMap::new(),
+            sla: None,
+            calendar: "crypto".to_string(),
+            priority: 0,
+        }
+    }
+
+    #[test]
+    fn test_only_changes_are_recorded() {
+        let path = "./test_config_history.jsonl";
+        let _ = fs::remove_file(path);
+        let btc = |spread| vec![("BTC/USD".to_string(), feed(spread))];
+
+        assert_eq!(record(path, 1, &btc(Some(1.0))).unwrap(), vec!["BTC/USD"]);
+        assert!(record(path, 2, &btc(Some(1.0))).unwrap().is_empty());
+        assert_eq!(record(path, 3, &btc(Some(2.0))).unwrap(), vec!["BTC/USD"]);
+        record(path, 4, &[("ETH/USD".to_string(), feed(None))]).unwrap();
+
+        let entries = history(path, "BTC/USD").unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.recorded_at_ms).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(history(path, "ETH/USD").unwrap().len(), 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        assert_eq!(
+            changed_fields(&feed(Some(1.0)), &feed(None)),
+            vec!["max_source_spread_pct: 1.0 -> null"]
+        );
+        assert!(changed_fields(&feed(None), &feed(None)).is_empty());
+    }
+}
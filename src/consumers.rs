@@ -0,0 +1,81 @@
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+
+use crate::config::CallTemplate;
+use crate::sui_publisher::MoveArg;
+
+/// Values the command line can supply to a `[consumers.*]` template.
+#[derive(Debug, Default)]
+pub struct TemplateValues {
+    pub consumer: Option<SuiAddress>,
+    pub recipient: Option<SuiAddress>,
+    pub amount: Option<u64>,
+}
+
+/// Expands `template.args` into Move call arguments.
+pub fn resolve_args(template: &CallTemplate, values: &TemplateValues) -> Result<Vec<MoveArg>> {
+    template
+        .args
+        .iter()
+        .map(|arg| {
+            let missing = || anyhow!("{} needs a value for {}", template.function, arg);
+            match arg.as_str() {
+                "{consumer}" => values.consumer.map(MoveArg::Address).ok_or_else(missing),
+                "{recipient}" => values.recipient.map(MoveArg::Address).ok_or_else(missing),
+                "{amount}" => values.amount.map(MoveArg::U64).ok_or_else(missing),
+                literal if literal.starts_with("0x") => ObjectID::from_str(literal)
+                    .map(MoveArg::Object)
+                    .map_err(|e| anyhow!("Invalid object ID '{}': {}", literal, e)),
+                other => Err(anyhow!(
+                    "Unsupported argument '{}' in {}; expected {{consumer}}, {{recipient}}, \
+                     {{amount}} or an object ID",
+                    other,
+                    template.function
+                )),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(args: &[&str]) -> CallTemplate {
+        CallTemplate {
+            module: "price_oracle".to_string(),
+            function: "withdraw_fees".to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_args_in_order() {
+        let recipient = SuiAddress::from_str("0x2").unwrap();
+        let values = TemplateValues {
+            recipient: Some(recipient),
+            amount: Some(500),
+            ..Default::default()
+        };
+        let args = resolve_args(&template(&["0x5", "{recipient}", "{amount}"]), &values).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                MoveArg::Object(ObjectID::from_single_byte(5)),
+                MoveArg::Address(recipient),
+                MoveArg::U64(500),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_args_errors() {
+        let values = TemplateValues::default();
+        let err = resolve_args(&template(&["{amount}"]), &values).unwrap_err();
+        assert!(err.to_string().contains("needs a value for {amount}"));
+        assert!(resolve_args(&template(&["{fee}"]), &values).is_err());
+        assert!(resolve_args(&template(&["0xzz"]), &values).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Result, anyhow};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::aggregator::AggregationInput;
+use crate::config::CrossSettings;
+
+/// Checks that every cross rate divides two different configured feeds and doesn't shadow a
+/// feed or an index.
+pub fn validate(
+    crosses: &BTreeMap<String, CrossSettings>,
+    feeds: &[&str],
+    indices: &[&str],
+) -> Result<()> {
+    for (name, cross) in crosses {
+        if feeds.contains(&name.as_str()) || indices.contains(&name.as_str()) {
+            return Err(anyhow!("crosses.\"{}\": name is already a feed", name));
+        }
+        for leg in [&cross.base, &cross.quote] {
+            if !feeds.contains(&leg.as_str()) {
+                return Err(anyhow!(
+                    "crosses.\"{}\": {} is not a configured feed",
+                    name,
+                    leg
+                ));
+            }
+        }
+        if cross.base == cross.quote {
+            return Err(anyhow!(
+                "crosses.\"{}\": base and quote are both {}",
+                name,
+                cross.base
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `base / quote` over this cycle's feed prices, e.g. ETH/BTC as ETH/USD over BTC/USD. Both
+/// legs must have a price from this cycle, so the rate never mixes a fresh price with an
+/// old one.
+pub fn compute(
+    cross: &CrossSettings,
+    prices: &HashMap<String, Decimal>,
+) -> Result<(Decimal, Vec<AggregationInput>)> {
+    let missing: Vec<&str> = [&cross.base, &cross.quote]
+        .into_iter()
+        .filter(|leg| !prices.contains_key(*leg))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!("missing leg price: {}", missing.join(", ")));
+    }
+    let (base, quote) = (prices[&cross.base], prices[&cross.quote]);
+    if quote <= Decimal::ZERO {
+        return Err(anyhow!("{} price {} is not positive", cross.quote, quote));
+    }
+    let inputs = [&cross.base, &cross.quote]
+        .into_iter()
+        .map(|leg| AggregationInput {
+            included: true,
+            ..AggregationInput::new(leg, None, prices.get(leg).copied())
+        })
+        .collect();
+    let rate = base
+        .checked_div(quote)
+        .ok_or_else(|| anyhow!("{} / {} overflows", cross.base, cross.quote))?;
+    Ok((rate, inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth_btc() -> CrossSettings {
+        CrossSettings {
+            base: "ETH/USD".to_string(),
+            quote: "BTC/USD".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compute_cross_rate() {
+        let prices = HashMap::from([
+            ("BTC/USD".to_string(), Decimal::from(60_000)),
+            ("ETH/USD".to_string(), Decimal::from(3_000)),
+        ]);
+        let (rate, inputs) = compute(&eth_btc(), &prices).unwrap();
+        assert_eq!(rate, Decimal::new(5, 2));
+        assert_eq!(inputs.len(), 2);
+
+        let partial = HashMap::from([("ETH/USD".to_string(), Decimal::from(3_000))]);
+        let err = compute(&eth_btc(), &partial).unwrap_err();
+        assert_eq!(err.to_string(), "missing leg price: BTC/USD");
+    }
+
+    #[test]
+    fn test_validate() {
+        let feeds = ["BTC/USD", "ETH/USD"];
+        let crosses = |name: &str, cross| BTreeMap::from([(name.to_string(), cross)]);
+        assert!(validate(&crosses("ETH/BTC", eth_btc()), &feeds, &[]).is_ok());
+        assert!(validate(&crosses("BTC/USD", eth_btc()), &feeds, &[]).is_err());
+        assert!(validate(&crosses("TOP2", eth_btc()), &feeds, &["TOP2"]).is_err());
+        assert!(validate(&crosses("ETH/BTC", eth_btc()), &feeds[..1], &[]).is_err());
+
+        let same_legs = CrossSettings {
+            quote: "ETH/USD".to_string(),
+            ..eth_btc()
+        };
+        assert!(validate(&crosses("ETH/ETH", same_legs), &feeds, &[]).is_err());
+    }
+}
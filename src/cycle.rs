@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::config::SymbolSettings;
+
+/// What happened to one symbol during a publishing cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolOutcome {
+    Published {
+        digest: String,
+        gas_used_mist: u64,
+        /// The gas in USD, once the USD feed has a price.
+        gas_usd: Option<f64>,
+    },
+    /// Nothing was submitted, e.g. because no source delivered a usable price.
+    Skipped {
+        reason: String,
+    },
+    /// A price was available but deliberately not published, e.g. sources disagreed.
+    Withheld {
+        reason: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl SymbolOutcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymbolOutcome::Published { .. } => "published",
+            SymbolOutcome::Skipped { .. } => "skipped",
+            SymbolOutcome::Withheld { .. } => "withheld",
+            SymbolOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+/// Symbols to pause this cycle when more than `max_symbols` are configured (`0` means no
+/// limit). Higher `priority` wins; ties keep publishing order.
+pub fn paused_symbols<'a>(
+    symbols: &[&'a str],
+    settings: &BTreeMap<String, SymbolSettings>,
+    max_symbols: usize,
+) -> HashSet<&'a str> {
+    if max_symbols == 0 || symbols.len() <= max_symbols {
+        return HashSet::new();
+    }
+    let priority = |s: &str| settings.get(s).map_or(0, |c| c.priority);
+    let mut ranked = symbols.to_vec();
+    ranked.sort_by_key(|s| std::cmp::Reverse(priority(s)));
+    ranked.split_off(max_symbols).into_iter().collect()
+}
+
+/// SplitMix64 step: a fast, well-mixed 64-bit generator that is trivial to reproduce.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A base seed for runs without `general.random_seed`.
+pub fn random_base_seed() -> u64 {
+    let mut state = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+        ^ u64::from(std::process::id()).rotate_left(32);
+    splitmix64(&mut state)
+}
+
+/// The only source of randomness within a cycle. Seeds are derived from the run's base
+/// seed and the cycle number, and logged, so a cycle's random choices can be reproduced
+/// by restarting with the same `general.random_seed`.
+#[derive(Debug, Clone)]
+pub struct CycleRng {
+    seed: u64,
+    state: u64,
+}
+
+impl CycleRng {
+    pub fn for_cycle(base_seed: u64, cycle: u64) -> Self {
+        let mut state = base_seed ^ cycle.wrapping_mul(0xA24B_AED4_963E_E407);
+        Self::from_seed(splitmix64(&mut state))
+    }
+
+    /// For publishing between the cycles: the outbox before the first one, and queued
+    /// updates. Derived from the base seed like a cycle's, but from a stream of its own.
+    pub fn for_background(base_seed: u64) -> Self {
+        let mut state = base_seed ^ 0xD1B5_4A32_D192_ED03;
+        Self::from_seed(splitmix64(&mut state))
+    }
+
+    pub fn from_seed(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        splitmix64(&mut self.state)
+    }
+
+    /// Uniform in `0..=max_ms`.
+    pub fn jitter_ms(&mut self, max_ms: u64) -> u64 {
+        if max_ms == 0 {
+            return 0;
+        }
+        self.next_u64() % (max_ms + 1)
+    }
+}
+
+/// Per-symbol outcomes of one cycle, in publishing order.
+#[derive(Debug, Default)]
+pub struct CycleSummary {
+    outcomes: Vec<(String, SymbolOutcome)>,
+}
+
+impl CycleSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, symbol: &str, outcome: SymbolOutcome) {
+        self.outcomes.push((symbol.to_string(), outcome));
+    }
+
+    fn count(&self, label: &str) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, o)| o.label() == label)
+            .count()
+    }
+
+    /// Why the cycle did not succeed: if any of its updates failed.
+    pub fn failure(&self) -> Option<String> {
+        let failed = self.count("failed");
+        (failed > 0).then(|| format!("{} of {} updates failed", failed, self.outcomes.len()))
+    }
+
+    pub fn log(&self) {
+        if self.count("failed") > 0 {
+            log::warn!("{}", self);
+        } else {
+            log::info!("{}", self);
+        }
+    }
+}
+
+impl fmt::Display for CycleSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cycle summary: {} published, {} skipped, {} withheld, {} failed",
+            self.count("published"),
+            self.count("skipped"),
+            self.count("withheld"),
+            self.count("failed")
+        )?;
+        for (symbol, outcome) in &self.outcomes {
+            match outcome {
+                SymbolOutcome::Published {
+                    digest,
+                    gas_used_mist,
+                    gas_usd,
+                } => {
+                    write!(
+                        f,
+                        "; {}: published {} ({} MIST",
+                        symbol, digest, gas_used_mist
+                    )?;
+                    if let Some(usd) = gas_usd {
+                        write!(f, ", ${:.6}", usd)?;
+                    }
+                    write!(f, ")")?
+                }
+                SymbolOutcome::Skipped { reason } => {
+                    write!(f, "; {}: skipped ({})", symbol, reason)?
+                }
+                SymbolOutcome::Withheld { reason } => {
+                    write!(f, "; {}: withheld ({})", symbol, reason)?
+                }
+                SymbolOutcome::Failed { error } => write!(f, "; {}: failed ({})", symbol, error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_distinguishes_outcomes() {
+        let mut summary = CycleSummary::new();
+        summary.record(
+            "BTC/USD",
+            SymbolOutcome::Published {
+                digest: "D1".to_string(),
+                gas_used_mist: 1000,
+                gas_usd: None,
+            },
+        );
+        summary.record(
+            "ETH/USD",
+            SymbolOutcome::Failed {
+                error: "no gas".to_string(),
+            },
+        );
+        summary.record(
+            "SOL/USD",
+            SymbolOutcome::Skipped {
+                reason: "not enough data".to_string(),
+            },
+        );
+
+        assert_eq!(
+            summary.to_string(),
+            "Cycle summary: 1 published, 1 skipped, 0 withheld, 1 failed; \
+             BTC/USD: published D1 (1000 MIST); ETH/USD: failed (no gas); \
+             SOL/USD: skipped (not enough data)"
+        );
+        assert_eq!(summary.outcomes[1].1.label(), "failed");
+    }
+
+    #[test]
+    fn test_lowest_priority_symbols_are_paused() {
+        let settings = BTreeMap::from([
+            (
+                "BTC/USD".to_string(),
+                SymbolSettings {
+                    priority: 10,
+                    ..Default::default()
+                },
+            ),
+            (
+                "DOGE/USD".to_string(),
+                SymbolSettings {
+                    priority: -1,
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let symbols = ["DOGE/USD", "ETH/USD", "SOL/USD", "BTC/USD"];
+        assert_eq!(
+            paused_symbols(&symbols, &settings, 2),
+            HashSet::from(["DOGE/USD", "SOL/USD"])
+        );
+        assert!(paused_symbols(&symbols, &settings, 0).is_empty());
+        assert!(paused_symbols(&symbols, &settings, 4).is_empty());
+    }
+
+    #[test]
+    fn test_cycle_rng_is_reproducible() {
+        let mut a = CycleRng::for_cycle(42, 7);
+        let mut b = CycleRng::from_seed(a.seed());
+        let draws: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(draws, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+
+        assert_ne!(
+            CycleRng::for_cycle(42, 7).seed(),
+            CycleRng::for_cycle(42, 8).seed()
+        );
+        assert_eq!(
+            CycleRng::for_background(42).seed(),
+            CycleRng::for_background(42).seed()
+        );
+        assert_ne!(
+            CycleRng::for_background(42).seed(),
+            CycleRng::for_cycle(42, 0).seed()
+        );
+        let mut rng = CycleRng::from_seed(1);
+        assert!((0..100).all(|_| rng.jitter_ms(250) <= 250));
+        assert_eq!(rng.jitter_ms(0), 0);
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::aggregator::AggregateResult;
+use crate::chain::PublishTrace;
+use crate::config::DebugBundleSettings;
+use crate::runtime_info;
+use crate::sources::PriceSample;
+
+/// Directory-name prefix; lexical order of names is chronological order.
+const DIR_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Everything needed to replay a failed publish after the fact.
+#[derive(Debug)]
+pub struct DebugBundle<'a> {
+    /// Why the bundle was captured, e.g. `publish_failed`.
+    pub reason: &'static str,
+    pub symbol: &'a str,
+    pub captured_at_ms: u64,
+    pub error: String,
+    /// Raw ticker maps as returned by each exchange; `None` when the fetch failed.
+    pub sources: BTreeMap<&'static str, Option<&'a HashMap<String, PriceSample>>>,
+    pub aggregation: &'a AggregateResult,
+    pub publish_trace: &'a PublishTrace,
+}
+
+fn sanitize(component: &str) -> String {
+    component
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn bundle_dir_name(captured_at_ms: u64, reason: &str, symbol: &str) -> String {
+    let timestamp = DateTime::<Utc>::from_timestamp_millis(captured_at_ms as i64)
+        .unwrap_or_default()
+        .format(DIR_TIMESTAMP_FORMAT);
+    format!("{}-{}-{}", timestamp, sanitize(reason), sanitize(symbol))
+}
+
+fn captured_at_from_dir_name(name: &str) -> Option<u64> {
+    let prefix = name.split('-').next()?;
+    let parsed = NaiveDateTime::parse_from_str(prefix, DIR_TIMESTAMP_FORMAT).ok()?;
+    u64::try_from(parsed.and_utc().timestamp_millis()).ok()
+}
+
+fn write_json<T: Serialize + ?Sized>(dir: &Path, name: &str, value: &T) -> Result<()> {
+    let path = dir.join(name);
+    let json = serde_json::to_string_pretty(value)?;
+    fs::write(&path, json).context(format!("Failed to write {}", path.display()))
+}
+
+/// Writes `bundle` into a new timestamped directory under `settings.dir`, then applies the
+/// retention limits. Returns the bundle directory.
+pub fn capture(settings: &DebugBundleSettings, bundle: &DebugBundle) -> Result<PathBuf> {
+    let root = Path::new(&settings.dir);
+    let dir = root.join(bundle_dir_name(
+        bundle.captured_at_ms,
+        bundle.reason,
+        bundle.symbol,
+    ));
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+
+    write_json(
+        &dir,
+        "manifest.json",
+        &serde_json::json!({
+            "reason": bundle.reason,
+            "symbol": bundle.symbol,
+            "captured_at_ms": bundle.captured_at_ms,
+            "error": bundle.error,
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_hash": runtime_info::GIT_HASH,
+        }),
+    )?;
+    write_json(&dir, "sources.json", &bundle.sources)?;
+    write_json(&dir, "aggregation.json", bundle.aggregation)?;
+    write_json(&dir, "publish_trace.json", bundle.publish_trace)?;
+
+    enforce_retention(root, settings, bundle.captured_at_ms)?;
+    Ok(dir)
+}
+
+/// Deletes bundles older than `max_age_hours`, then the oldest ones beyond `max_bundles`.
+/// Directories that don't look like bundles are left alone.
+pub fn enforce_retention(root: &Path, settings: &DebugBundleSettings, now_ms: u64) -> Result<()> {
+    let mut bundles: Vec<(u64, PathBuf)> = fs::read_dir(root)
+        .context(format!("Failed to list {}", root.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let captured_at = captured_at_from_dir_name(entry.file_name().to_str()?)?;
+            Some((captured_at, entry.path()))
+        })
+        .collect();
+    bundles.sort();
+
+    let max_age_ms = settings.max_age_hours.saturating_mul(3_600_000);
+    let excess = bundles.len().saturating_sub(settings.max_bundles);
+    for (i, (captured_at, path)) in bundles.iter().enumerate() {
+        if i < excess || now_ms.saturating_sub(*captured_at) > max_age_ms {
+            log::info!("Removing expired debug bundle {}", path.display());
+            fs::remove_dir_all(path).context(format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregator::AggregationInput;
+    use rust_decimal::Decimal;
+
+    const HOUR_MS: u64 = 3_600_000;
+    const T0: u64 = 1_700_000_000_000;
+
+    fn settings(dir: &str, max_bundles: usize, max_age_hours: u64) -> DebugBundleSettings {
+        DebugBundleSettings {
+            enabled: true,
+            dir: dir.to_string(),
+            max_bundles,
+            max_age_hours,
+        }
+    }
+
+    fn explanation() -> AggregateResult {
+        AggregateResult {
+            method: "mean".to_string(),
+            inputs: vec![AggregationInput {
+                included: true,
+                ..AggregationInput::new(
+                    "binance",
+                    Some("BTCUSDT".to_string()),
+                    Some(Decimal::from(100)),
+                )
+            }],
+            spread_pct: None,
+            confidence: None,
+            result: Some(Decimal::from(100)),
+        }
+    }
+
+    fn capture_at(settings: &DebugBundleSettings, at_ms: u64) -> PathBuf {
+        let binance = HashMap::from([(
+            "BTCUSDT".to_string(),
+            PriceSample {
+                price: Decimal::new(1000, 1),
+                timestamp_ms: None,
+            },
+        )]);
+        let aggregation = explanation();
+        let trace = PublishTrace::new();
+        let bundle = DebugBundle {
+            reason: "publish_failed",
+            symbol: "BTC/USD",
+            captured_at_ms: at_ms,
+            error: "boom".to_string(),
+            sources: BTreeMap::from([("binance", Some(&binance)), ("coinbase", None)]),
+            aggregation: &aggregation,
+            publish_trace: &trace,
+        };
+        capture(settings, &bundle).unwrap()
+    }
+
+    #[test]
+    fn test_dir_name_round_trip() {
+        let name = bundle_dir_name(T0 + 123, "publish_failed", "BTC/USD");
+        assert_eq!(name, "20231114T221320.123Z-publish_failed-BTC_USD");
+        assert_eq!(captured_at_from_dir_name(&name), Some(T0 + 123));
+        assert_eq!(captured_at_from_dir_name("notes"), None);
+    }
+
+    #[test]
+    fn test_capture_writes_all_sections() {
+        let dir = "./test_debug_bundle_capture";
+        let path = capture_at(&settings(dir, 10, 24), T0);
+        for file in [
+            "manifest.json",
+            "sources.json",
+            "aggregation.json",
+            "publish_trace.json",
+        ] {
+            assert!(path.join(file).exists(), "missing {}", file);
+        }
+        let sources: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path.join("sources.json")).unwrap()).unwrap();
+        assert_eq!(sources["binance"]["BTCUSDT"]["price"], "100.0");
+        assert!(sources["coinbase"].is_null());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_retention_limits() {
+        let dir = "./test_debug_bundle_retention";
+        let _ = fs::remove_dir_all(dir);
+        let keep_two = settings(dir, 2, 24);
+        let oldest = capture_at(&keep_two, T0);
+        let middle = capture_at(&keep_two, T0 + HOUR_MS);
+        fs::create_dir_all(Path::new(dir).join("notes")).unwrap();
+        let newest = capture_at(&keep_two, T0 + 2 * HOUR_MS);
+        assert!(!oldest.exists());
+        assert!(middle.exists() && newest.exists());
+        assert!(Path::new(dir).join("notes").exists());
+
+        // A day later the age limit removes everything that came before.
+        let latest = capture_at(&keep_two, T0 + 27 * HOUR_MS);
+        assert!(!middle.exists());
+        assert!(!newest.exists());
+        assert!(latest.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
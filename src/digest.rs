@@ -0,0 +1,330 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::alerts::{Alert, Severity};
+use crate::calendar;
+use crate::config::DigestSettings;
+use crate::cycle::SymbolOutcome;
+use crate::fees::MIST_PER_SUI;
+use crate::sla::SlaStatus;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FeedTotals {
+    pub published: u64,
+    pub skipped: u64,
+    pub withheld: u64,
+    pub failed: u64,
+    pub gas_used_mist: u64,
+    /// Gas of the updates priced in USD, and how many were published before the USD feed
+    /// had a price.
+    pub gas_used_usd: f64,
+    pub unpriced: u64,
+    sla_evaluations: u64,
+    sla_compliant: u64,
+}
+
+impl FeedTotals {
+    fn add(&mut self, other: &FeedTotals) {
+        self.published += other.published;
+        self.skipped += other.skipped;
+        self.withheld += other.withheld;
+        self.failed += other.failed;
+        self.gas_used_mist += other.gas_used_mist;
+        self.gas_used_usd += other.gas_used_usd;
+        self.unpriced += other.unpriced;
+        self.sla_evaluations += other.sla_evaluations;
+        self.sla_compliant += other.sla_compliant;
+    }
+
+    /// Failed submissions as a share of all submissions.
+    pub fn error_rate(&self) -> f64 {
+        let submitted = self.published + self.failed;
+        if submitted == 0 {
+            0.0
+        } else {
+            self.failed as f64 / submitted as f64
+        }
+    }
+
+    /// Share of this period's SLA evaluations the feed passed, if it has an SLA.
+    pub fn sla_attainment(&self) -> Option<f64> {
+        (self.sla_evaluations > 0).then(|| self.sla_compliant as f64 / self.sla_evaluations as f64)
+    }
+}
+
+impl std::fmt::Display for FeedTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} published, {} skipped, {} withheld, {} failed ({:.2}% errors), {:.4} SUI gas",
+            self.published,
+            self.skipped,
+            self.withheld,
+            self.failed,
+            self.error_rate() * 100.0,
+            self.gas_used_mist as f64 / MIST_PER_SUI
+        )?;
+        match self.unpriced {
+            0 if self.published > 0 => write!(f, " (${:.2})", self.gas_used_usd)?,
+            0 => {}
+            unpriced if unpriced < self.published => write!(
+                f,
+                " (${:.2} excluding {} unpriced)",
+                self.gas_used_usd, unpriced
+            )?,
+            _ => {}
+        }
+        if let Some(attainment) = self.sla_attainment() {
+            write!(f, ", SLA {:.2}%", attainment * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// What happened between `since` and `until`, per feed.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// The zone the report is written in.
+    pub timezone: Tz,
+    pub feeds: BTreeMap<String, FeedTotals>,
+}
+
+impl Report {
+    fn empty(now: DateTime<Utc>, timezone: Tz) -> Self {
+        Self {
+            since: now,
+            until: now,
+            timezone,
+            feeds: BTreeMap::new(),
+        }
+    }
+
+    /// The report as an info alert, with the critical alerts of the period per feed.
+    pub fn alert(&self, deployment: &str, incidents: &BTreeMap<(String, &str), u32>) -> Alert {
+        let mut total = FeedTotals::default();
+        for totals in self.feeds.values() {
+            total.add(totals);
+        }
+        let mut message = format!(
+            "Daily digest {} to {}: {}",
+            self.since
+                .with_timezone(&self.timezone)
+                .format("%Y-%m-%d %H:%M"),
+            self.until
+                .with_timezone(&self.timezone)
+                .format("%Y-%m-%d %H:%M %Z"),
+            total
+        );
+        let incident_count: u32 = incidents.values().sum();
+        let _ = write!(message, ", {} incidents", incident_count);
+        let mut symbols: Vec<&str> = self.feeds.keys().map(String::as_str).collect();
+        symbols.extend(incidents.keys().map(|(symbol, _)| symbol.as_str()));
+        symbols.sort();
+        symbols.dedup();
+        for symbol in symbols {
+            let _ = write!(message, "\n{}: ", symbol);
+            match self.feeds.get(symbol) {
+                Some(totals) => {
+                    let _ = write!(message, "{}", totals);
+                }
+                None => message.push_str("no cycles"),
+            }
+            let of_feed: Vec<String> = incidents
+                .iter()
+                .filter(|((s, _), _)| s == symbol)
+                .map(|((_, kind), count)| format!("{} x{}", kind, count))
+                .collect();
+            if !of_feed.is_empty() {
+                let _ = write!(message, "; incidents: {}", of_feed.join(", "));
+            }
+        }
+        Alert {
+            severity: Severity::Info,
+            kind: "daily_digest",
+            symbol: deployment.to_string(),
+            message,
+            timestamp_ms: self.until.timestamp_millis() as u64,
+        }
+    }
+}
+
+/// Accumulates each feed's outcomes, gas and SLA compliance, and hands them out as a
+/// `Report` in `timezone` once a day at `send_at`, or `send_at_utc` without it.
+pub struct Digest {
+    /// The zone of `send_at_minutes`.
+    send_tz: Tz,
+    /// Minutes after midnight in `send_tz`.
+    send_at_minutes: u32,
+    period: Mutex<Report>,
+}
+
+impl Digest {
+    pub fn new(settings: &DigestSettings, timezone: Tz, now: DateTime<Utc>) -> Result<Self> {
+        let (send_tz, send_at) = match &settings.send_at {
+            Some(send_at) => (timezone, send_at),
+            None => (Tz::UTC, &settings.send_at_utc),
+        };
+        Ok(Self {
+            send_tz,
+            send_at_minutes: calendar::parse_hhmm(send_at)? % (24 * 60),
+            period: Mutex::new(Report::empty(now, timezone)),
+        })
+    }
+
+    pub fn record(&self, symbol: &str, outcome: &SymbolOutcome) {
+        let mut period = self.period.lock().unwrap();
+        let totals = period.feeds.entry(symbol.to_string()).or_default();
+        match outcome {
+            SymbolOutcome::Published {
+                gas_used_mist,
+                gas_usd,
+                ..
+            } => {
+                totals.published += 1;
+                totals.gas_used_mist += gas_used_mist;
+                match gas_usd {
+                    Some(usd) => totals.gas_used_usd += usd,
+                    None => totals.unpriced += 1,
+                }
+            }
+            SymbolOutcome::Skipped { .. } => totals.skipped += 1,
+            SymbolOutcome::Withheld { .. } => totals.withheld += 1,
+            SymbolOutcome::Failed { .. } => totals.failed += 1,
+        }
+    }
+
+    pub fn record_sla(&self, statuses: &[SlaStatus]) {
+        let mut period = self.period.lock().unwrap();
+        for status in statuses {
+            let totals = period.feeds.entry(status.symbol.clone()).or_default();
+            totals.sla_evaluations += 1;
+            totals.sla_compliant += u64::from(status.is_compliant());
+        }
+    }
+
+    /// The first send time after `since`, following `send_tz`'s daylight saving changes.
+    fn due_after(&self, since: DateTime<Utc>) -> DateTime<Utc> {
+        let today = since.with_timezone(&self.send_tz).date_naive();
+        let send_on = |day: chrono::NaiveDate| {
+            let midnight = day.and_hms_opt(0, 0, 0).expect("midnight exists");
+            calendar::local_to_utc(
+                self.send_tz,
+                midnight + Duration::minutes(i64::from(self.send_at_minutes)),
+            )
+        };
+        let due = send_on(today);
+        if due > since {
+            due
+        } else {
+            send_on(today + Duration::days(1))
+        }
+    }
+
+    /// The report of the period so far once its send time has passed at `now`; a new
+    /// period starts then.
+    pub fn take_due(&self, now: DateTime<Utc>) -> Option<Report> {
+        let mut period = self.period.lock().unwrap();
+        if now < self.due_after(period.since) {
+            return None;
+        }
+        let timezone = period.timezone;
+        let mut report = std::mem::replace(&mut *period, Report::empty(now, timezone));
+        report.until = now;
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_daily_report_per_feed() {
+        let settings = DigestSettings {
+            enabled: true,
+            send_at_utc: "09:00".to_string(),
+            send_at: None,
+        };
+        let digest = Digest::new(&settings, Tz::UTC, at("2026-10-13T12:00:00Z")).unwrap();
+        let published = SymbolOutcome::Published {
+            digest: "D".to_string(),
+            gas_used_mist: 2_000_000,
+            gas_usd: Some(0.003),
+        };
+        digest.record("BTC/USD", &published);
+        digest.record("BTC/USD", &published);
+        digest.record(
+            "BTC/USD",
+            &SymbolOutcome::Failed {
+                error: "timeout".to_string(),
+            },
+        );
+        digest.record(
+            "ETH/USD",
+            &SymbolOutcome::Skipped {
+                reason: "within deviation".to_string(),
+            },
+        );
+        assert!(digest.take_due(at("2026-10-14T08:59:00Z")).is_none());
+
+        let report = digest.take_due(at("2026-10-14T09:00:00Z")).unwrap();
+        let btc = &report.feeds["BTC/USD"];
+        assert_eq!((btc.published, btc.failed), (2, 1));
+        assert!((btc.error_rate() - 1.0 / 3.0).abs() < 1e-9);
+        let incidents = BTreeMap::from([(("BTC/USD".to_string(), "sla_breach"), 2)]);
+        let alert = report.alert("0x42", &incidents);
+        assert_eq!(alert.kind, "daily_digest");
+        assert!(alert.message.starts_with(
+            "Daily digest 2026-10-13 12:00 to 2026-10-14 09:00 UTC: 2 published, 1 skipped, \
+             0 withheld, 1 failed (33.33% errors), 0.0040 SUI gas ($0.01), 2 incidents"
+        ));
+        assert!(alert.message.contains("\nBTC/USD: 2 published"));
+        assert!(alert.message.contains("; incidents: sla_breach x2"));
+
+        // The next period starts empty and is due a day later.
+        assert!(digest.take_due(at("2026-10-15T08:00:00Z")).is_none());
+        let next = digest.take_due(at("2026-10-15T09:01:00Z")).unwrap();
+        assert!(next.feeds.is_empty());
+    }
+
+    #[test]
+    fn test_send_time_in_the_reporting_zone() {
+        let settings = DigestSettings {
+            enabled: true,
+            send_at_utc: "09:00".to_string(),
+            send_at: Some("09:00".to_string()),
+        };
+        let new_york = chrono_tz::America::New_York;
+        let digest = Digest::new(&settings, new_york, at("2026-10-30T14:00:00Z")).unwrap();
+        assert!(digest.take_due(at("2026-10-31T12:59:00Z")).is_none());
+        let report = digest.take_due(at("2026-10-31T13:00:00Z")).unwrap();
+        assert!(
+            report
+                .alert("0x42", &BTreeMap::new())
+                .message
+                .starts_with("Daily digest 2026-10-30 10:00 to 2026-10-31 09:00 EDT:")
+        );
+        // 09:00 stays 09:00 in New York after the clocks go back.
+        assert!(digest.take_due(at("2026-11-01T13:30:00Z")).is_none());
+        assert!(digest.take_due(at("2026-11-01T14:00:00Z")).is_some());
+
+        // Without `send_at`, the report is in the operator's zone but goes out at
+        // `send_at_utc`.
+        let settings = DigestSettings {
+            send_at: None,
+            ..settings
+        };
+        let digest = Digest::new(&settings, new_york, at("2026-10-30T14:00:00Z")).unwrap();
+        assert!(digest.take_due(at("2026-10-31T09:00:00Z")).is_some());
+    }
+}
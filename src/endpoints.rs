@@ -0,0 +1,292 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::{ExchangeConfig, RateLimitSettings};
+use crate::metrics::Metrics;
+use crate::rate_limit::{RateLimited, RateLimiter};
+
+/// Weight of the newest sample in the latency moving average.
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+/// An endpoint is unhealthy after this many failed requests in a row, until one succeeds.
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+const DEFAULT_REGION: &str = "default";
+
+/// One base URL of an exchange, tagged with the region it is served from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub region: String,
+    pub base_url: String,
+}
+
+/// The exchange's `base_url` followed by its `mirrors`, in config order.
+pub fn endpoints(config: &ExchangeConfig) -> Vec<Endpoint> {
+    let primary = Endpoint {
+        region: config
+            .region
+            .clone()
+            .unwrap_or_else(|| DEFAULT_REGION.to_string()),
+        base_url: config.base_url.clone(),
+    };
+    std::iter::once(primary)
+        .chain(config.mirrors.iter().map(|m| Endpoint {
+            region: m.region.clone(),
+            base_url: m.base_url.clone(),
+        }))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    region: String,
+    latency_ewma_ms: Option<f64>,
+    consecutive_failures: u32,
+}
+
+#[derive(Debug, Default)]
+struct ExchangeEndpoints {
+    stats: HashMap<String, EndpointStats>,
+    selected: Option<String>,
+    probe_cursor: usize,
+}
+
+/// Tracks request latency and health per endpoint and picks the fastest healthy one for
+/// each exchange. Endpoints that are not selected are probed in turn, so their latency
+/// stays current and failed ones can recover. Requests also spend from the exchange's
+/// rate limit, which its endpoints share.
+#[derive(Debug, Default)]
+pub struct EndpointSelector {
+    exchanges: Mutex<HashMap<String, ExchangeEndpoints>>,
+    rate_limiter: RateLimiter,
+}
+
+impl EndpointSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the requests `timed` sends to `exchange`.
+    pub fn set_rate_limit(&self, exchange: &str, limit: RateLimitSettings) {
+        self.rate_limiter
+            .configure(exchange, limit, tokio::time::Instant::now());
+    }
+
+    /// The endpoint to fetch from this cycle: healthy before unhealthy, then lowest
+    /// latency, with unmeasured endpoints and ties in config order.
+    pub fn select(&self, exchange: &str, endpoints: &[Endpoint]) -> Endpoint {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let state = exchanges.entry(exchange.to_string()).or_default();
+        let best = endpoints
+            .iter()
+            .enumerate()
+            .min_by(|(i, a), (j, b)| {
+                let key = |e: &Endpoint| {
+                    let stats = state.stats.get(&e.base_url);
+                    (
+                        stats.is_some_and(|s| s.consecutive_failures >= MAX_CONSECUTIVE_FAILURES),
+                        stats
+                            .and_then(|s| s.latency_ewma_ms)
+                            .unwrap_or(f64::INFINITY),
+                    )
+                };
+                let ((a_unhealthy, a_latency), (b_unhealthy, b_latency)) = (key(a), key(b));
+                a_unhealthy
+                    .cmp(&b_unhealthy)
+                    .then(a_latency.total_cmp(&b_latency))
+                    .then(i.cmp(j))
+            })
+            .map(|(_, e)| e.clone())
+            .expect("an exchange always has its base_url");
+
+        if state.selected.as_deref() != Some(best.base_url.as_str()) {
+            if state.selected.is_some() {
+                log::info!(
+                    "Switching {} to endpoint {} ({}), latency {:?} ms",
+                    exchange,
+                    best.base_url,
+                    best.region,
+                    state
+                        .stats
+                        .get(&best.base_url)
+                        .and_then(|s| s.latency_ewma_ms.map(|l| l.round()))
+                );
+            }
+            state.selected = Some(best.base_url.clone());
+        }
+        best
+    }
+
+    /// The next endpoint other than `selected` to probe, round-robin.
+    pub fn probe_candidate(
+        &self,
+        exchange: &str,
+        endpoints: &[Endpoint],
+        selected: &Endpoint,
+    ) -> Option<Endpoint> {
+        let others: Vec<&Endpoint> = endpoints.iter().filter(|e| *e != selected).collect();
+        if others.is_empty() {
+            return None;
+        }
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let state = exchanges.entry(exchange.to_string()).or_default();
+        let candidate = others[state.probe_cursor % others.len()].clone();
+        state.probe_cursor = state.probe_cursor.wrapping_add(1);
+        Some(candidate)
+    }
+
+    /// Records one request: `Some(latency)` on success, `None` on failure.
+    pub fn record(&self, exchange: &str, endpoint: &Endpoint, latency_ms: Option<u64>) {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let stats = exchanges
+            .entry(exchange.to_string())
+            .or_default()
+            .stats
+            .entry(endpoint.base_url.clone())
+            .or_default();
+        stats.region = endpoint.region.clone();
+        match latency_ms {
+            Some(latency) => {
+                let latency = latency as f64;
+                stats.latency_ewma_ms = Some(stats.latency_ewma_ms.map_or(latency, |avg| {
+                    LATENCY_EWMA_ALPHA * latency + (1.0 - LATENCY_EWMA_ALPHA) * avg
+                }));
+                stats.consecutive_failures = 0;
+            }
+            None => stats.consecutive_failures += 1,
+        }
+    }
+
+    /// Runs `request` against `endpoint` once the exchange's rate limit allows, and records
+    /// its latency or failure. A request the exchange refused as over the limit holds the
+    /// exchange's further requests instead of counting against the endpoint, since every
+    /// endpoint shares the limit.
+    pub async fn timed<T>(
+        &self,
+        exchange: &str,
+        endpoint: &Endpoint,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.rate_limiter.acquire(exchange).await?;
+        let started = Instant::now();
+        let result = request.await;
+        if let Err(e) = &result
+            && let Some(limited) = e.downcast_ref::<RateLimited>()
+        {
+            log::warn!(
+                "{} refused a request to {}: {}; holding its requests",
+                exchange,
+                endpoint.base_url,
+                limited
+            );
+            self.rate_limiter
+                .hold(exchange, limited.retry_after, tokio::time::Instant::now());
+            return result;
+        }
+        let latency_ms = result
+            .as_ref()
+            .ok()
+            .map(|_| started.elapsed().as_millis() as u64);
+        self.record(exchange, endpoint, latency_ms);
+        result
+    }
+
+    pub fn export_metrics(&self, metrics: &Metrics) {
+        for (exchange, hold) in self.rate_limiter.holds(tokio::time::Instant::now()) {
+            metrics.set_gauge(
+                "oracle_source_rate_limit_hold_seconds",
+                "How much longer requests to the exchange are held after it rate limited them",
+                &[("exchange", exchange.as_str())],
+                hold.as_secs_f64(),
+            );
+        }
+        let exchanges = self.exchanges.lock().unwrap();
+        for (exchange, state) in exchanges.iter() {
+            for (url, stats) in &state.stats {
+                let labels = [
+                    ("exchange", exchange.as_str()),
+                    ("region", stats.region.as_str()),
+                ];
+                if let Some(latency) = stats.latency_ewma_ms {
+                    metrics.set_gauge(
+                        "oracle_source_endpoint_latency_ms",
+                        "Moving average of request latency per exchange endpoint",
+                        &labels,
+                        latency,
+                    );
+                }
+                let selected = state.selected.as_deref() == Some(url.as_str());
+                metrics.set_gauge(
+                    "oracle_source_endpoint_selected",
+                    "Whether the endpoint is the one currently fetched from",
+                    &labels,
+                    if selected { 1.0 } else { 0.0 },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(region: &str) -> Endpoint {
+        Endpoint {
+            region: region.to_string(),
+            base_url: format!("https://{}.example.com", region),
+        }
+    }
+
+    #[test]
+    fn test_prefers_lowest_latency_healthy_endpoint() {
+        let selector = EndpointSelector::new();
+        let all = [endpoint("eu"), endpoint("tokyo")];
+        // Nothing measured yet: config order.
+        assert_eq!(selector.select("binance", &all), all[0]);
+
+        selector.record("binance", &all[0], Some(120));
+        selector.record("binance", &all[1], Some(15));
+        assert_eq!(selector.select("binance", &all), all[1]);
+
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            selector.record("binance", &all[1], None);
+        }
+        assert_eq!(selector.select("binance", &all), all[0]);
+
+        // A successful probe brings it back.
+        selector.record("binance", &all[1], Some(15));
+        assert_eq!(selector.select("binance", &all), all[1]);
+    }
+
+    #[test]
+    fn test_probes_rotate_over_other_endpoints() {
+        let selector = EndpointSelector::new();
+        let all = [endpoint("eu"), endpoint("tokyo"), endpoint("us")];
+        let probes: Vec<String> = (0..4)
+            .filter_map(|_| selector.probe_candidate("binance", &all, &all[0]))
+            .map(|e| e.region)
+            .collect();
+        assert_eq!(probes, vec!["tokyo", "us", "tokyo", "us"]);
+        assert!(
+            selector
+                .probe_candidate("binance", &all[..1], &all[0])
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_latency_is_smoothed() {
+        let selector = EndpointSelector::new();
+        let eu = endpoint("eu");
+        selector.record("coinbase", &eu, Some(100));
+        selector.record("coinbase", &eu, Some(200));
+        let metrics = Metrics::new();
+        selector.export_metrics(&metrics);
+        assert!(metrics.render().contains(
+            "oracle_source_endpoint_latency_ms{exchange=\"coinbase\",region=\"eu\"} 130"
+        ));
+    }
+}
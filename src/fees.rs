@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::config::FeeSettings;
+use crate::metrics::Metrics;
+
+pub const MIST_PER_SUI: f64 = 1e9;
+
+/// One line of the gas ledger.
+#[derive(Serialize)]
+struct LedgerEntry<'a> {
+    timestamp_ms: u64,
+    symbol: &'a str,
+    digest: &'a str,
+    gas_used_mist: u64,
+    /// Absent until the USD feed has been published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sui_usd: Option<f64>,
+}
+
+/// Prices the gas of every publish in USD at the last published price of `fees.usd_feed`,
+/// and appends it to the JSON-lines ledger in `fees.ledger_file`.
+pub struct FeeTracker {
+    settings: FeeSettings,
+    sui_usd: Mutex<Option<f64>>,
+}
+
+impl FeeTracker {
+    pub fn new(settings: FeeSettings) -> Self {
+        Self {
+            settings,
+            sui_usd: Mutex::new(None),
+        }
+    }
+
+    /// Takes `price` as the SUI/USD rate if `symbol` is the USD feed.
+    pub fn observe_published(&self, symbol: &str, price: f64) {
+        if self.settings.usd_feed == symbol && price.is_finite() && price > 0.0 {
+            *self.sui_usd.lock().unwrap() = Some(price);
+        }
+    }
+
+    /// `gas_used_mist` in USD, if the USD feed has a price.
+    pub fn usd(&self, gas_used_mist: u64) -> Option<f64> {
+        self.sui_usd
+            .lock()
+            .unwrap()
+            .map(|rate| gas_used_mist as f64 / MIST_PER_SUI * rate)
+    }
+
+    /// Records the gas of a publish in the ledger and returns it in USD, if priced.
+    pub fn record(
+        &self,
+        symbol: &str,
+        digest: &str,
+        gas_used_mist: u64,
+        timestamp_ms: u64,
+    ) -> Option<f64> {
+        let sui_usd = *self.sui_usd.lock().unwrap();
+        let gas_usd = self.usd(gas_used_mist);
+        let entry = LedgerEntry {
+            timestamp_ms,
+            symbol,
+            digest,
+            gas_used_mist,
+            gas_usd,
+            sui_usd,
+        };
+        if let Err(e) = self.append(&entry) {
+            log::warn!("Failed to record gas of {} in the ledger: {:#}", digest, e);
+        }
+        gas_usd
+    }
+
+    fn append(&self, entry: &LedgerEntry) -> Result<()> {
+        let path = &self.settings.ledger_file;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open gas ledger {}", path))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+            .context(format!("Failed to write gas ledger {}", path))
+    }
+
+    /// Exports the SUI/USD rate gas is priced at.
+    pub fn export_metrics(&self, metrics: &Metrics) {
+        if let Some(rate) = *self.sui_usd.lock().unwrap() {
+            metrics.set_gauge(
+                "oracle_gas_sui_usd_rate",
+                "SUI/USD rate gas spend is priced at, the last published price of fees.usd_feed",
+                &[],
+                rate,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_priced_once_usd_feed_published() {
+        let ledger = "./test_gas_ledger.jsonl";
+        let _ = std::fs::remove_file(ledger);
+        let fees = FeeTracker::new(FeeSettings {
+            usd_feed: "SUI/USD".to_string(),
+            ledger_file: ledger.to_string(),
+        });
+        assert_eq!(fees.record("BTC/USD", "D1", 2_000_000, 1), None);
+        fees.observe_published("BTC/USD", 60_000.0);
+        assert_eq!(fees.usd(2_000_000), None);
+        fees.observe_published("SUI/USD", 1.5);
+        let usd = fees.record("BTC/USD", "D2", 2_000_000, 2).unwrap();
+        assert!((usd - 0.003).abs() < 1e-12);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(ledger)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(ledger).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].get("gas_usd").is_none());
+        assert_eq!(lines[1]["gas_used_mist"], 2_000_000);
+        assert_eq!(lines[1]["sui_usd"], 1.5);
+    }
+}
@@ -0,0 +1,165 @@
+// Probes are served only by the HTTP API.
+#![cfg_attr(not(feature = "http-api"), allow(dead_code))]
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::config::HealthSettings;
+use crate::schema::{HealthCheck, HealthReport};
+
+const MIST_PER_SUI: f64 = 1e9;
+
+struct Observed<T> {
+    at_ms: u64,
+    result: Result<T, String>,
+}
+
+/// What the cycle loop last saw of itself and the chain, for `/healthz` and `/readyz`.
+pub struct Health {
+    settings: HealthSettings,
+    started_at_ms: u64,
+    cycle: Mutex<Option<Observed<()>>>,
+    /// The publisher's SUI balance in MIST, read through the RPC endpoint in use.
+    balance: Mutex<Option<Observed<u128>>>,
+}
+
+impl Health {
+    pub fn new(settings: HealthSettings, started_at_ms: u64) -> Self {
+        Self {
+            settings,
+            started_at_ms,
+            cycle: Mutex::new(None),
+            balance: Mutex::new(None),
+        }
+    }
+
+    /// Records a completed cycle; `Err` says why it did not succeed.
+    pub fn record_cycle(&self, now_ms: u64, result: Result<(), String>) {
+        *self.cycle.lock().unwrap() = Some(Observed {
+            at_ms: now_ms,
+            result,
+        });
+    }
+
+    /// Whether the balance is due to be read again at `now_ms`.
+    pub fn chain_check_due(&self, now_ms: u64) -> bool {
+        self.balance.lock().unwrap().as_ref().is_none_or(|last| {
+            now_ms.saturating_sub(last.at_ms) >= self.settings.check_interval_seconds * 1000
+        })
+    }
+
+    /// Records the balance read at `now_ms`, or why the RPC could not be reached.
+    pub fn record_balance(&self, now_ms: u64, result: Result<u128, String>) {
+        *self.balance.lock().unwrap() = Some(Observed {
+            at_ms: now_ms,
+            result,
+        });
+    }
+
+    /// Alive as long as cycles keep completing: the last one ended within
+    /// `max_cycle_age_seconds`, or the process started less than that ago.
+    pub fn liveness(&self, now_ms: u64) -> HealthReport {
+        let max_age_ms = self.settings.max_cycle_age_seconds * 1000;
+        let cycle = self.cycle.lock().unwrap();
+        let last_ms = cycle.as_ref().map_or(self.started_at_ms, |c| c.at_ms);
+        let age_ms = now_ms.saturating_sub(last_ms);
+        let check = if age_ms <= max_age_ms {
+            HealthCheck::ok(match &*cycle {
+                Some(_) => format!("last cycle completed {} ms ago", age_ms),
+                None => "starting".to_string(),
+            })
+        } else {
+            HealthCheck::failed(format!(
+                "no cycle completed for {} ms (max {} ms)",
+                age_ms, max_age_ms
+            ))
+        };
+        HealthReport::new(BTreeMap::from([("cycle_loop".to_string(), check)]))
+    }
+
+    /// Ready to serve as an oracle: the last cycle succeeded, the Sui RPC answered the last
+    /// check and the publisher can pay for `min_gas_balance_sui` worth of gas.
+    pub fn readiness(&self, now_ms: u64) -> HealthReport {
+        let mut checks = self.liveness(now_ms).checks;
+        let cycle = match &*self.cycle.lock().unwrap() {
+            None => HealthCheck::failed("no cycle completed yet"),
+            Some(Observed { result: Ok(()), .. }) => HealthCheck::ok("last cycle succeeded"),
+            Some(Observed { result: Err(e), .. }) => {
+                HealthCheck::failed(format!("last cycle failed: {}", e))
+            }
+        };
+        checks.insert("last_cycle".to_string(), cycle);
+
+        let min_mist = (self.settings.min_gas_balance_sui * MIST_PER_SUI) as u128;
+        let (rpc, gas) = match &*self.balance.lock().unwrap() {
+            None => (
+                HealthCheck::failed("not checked yet"),
+                HealthCheck::failed("not checked yet"),
+            ),
+            Some(Observed { result: Err(e), .. }) => (
+                HealthCheck::failed(e.clone()),
+                HealthCheck::failed("unknown: the Sui RPC is unreachable"),
+            ),
+            Some(Observed {
+                result: Ok(mist), ..
+            }) => {
+                let detail = format!(
+                    "{:.4} SUI (min {:.4} SUI)",
+                    *mist as f64 / MIST_PER_SUI,
+                    self.settings.min_gas_balance_sui
+                );
+                (
+                    HealthCheck::ok("reachable"),
+                    if *mist >= min_mist {
+                        HealthCheck::ok(detail)
+                    } else {
+                        HealthCheck::failed(detail)
+                    },
+                )
+            }
+        };
+        checks.insert("sui_rpc".to_string(), rpc);
+        checks.insert("gas_balance".to_string(), gas);
+        HealthReport::new(checks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_needs_cycle_rpc_and_gas() {
+        let settings = HealthSettings {
+            max_cycle_age_seconds: 60,
+            check_interval_seconds: 30,
+            min_gas_balance_sui: 1.0,
+        };
+        let health = Health::new(settings, 0);
+        assert!(health.liveness(10_000).healthy);
+        assert!(!health.readiness(10_000).healthy);
+        assert!(health.chain_check_due(10_000));
+
+        health.record_cycle(10_000, Ok(()));
+        health.record_balance(10_000, Ok(2_000_000_000));
+        assert!(!health.chain_check_due(39_999));
+        let ready = health.readiness(20_000);
+        assert!(ready.healthy, "{:?}", ready);
+        assert_eq!(
+            ready.checks["gas_balance"].detail,
+            "2.0000 SUI (min 1.0000 SUI)"
+        );
+
+        health.record_balance(40_000, Ok(500_000_000));
+        assert!(!health.readiness(40_000).checks["gas_balance"].ok);
+        health.record_balance(40_000, Err("connection refused".to_string()));
+        let report = health.readiness(40_000);
+        assert!(!report.checks["sui_rpc"].ok && !report.checks["gas_balance"].ok);
+
+        health.record_cycle(50_000, Err("2 of 3 updates failed".to_string()));
+        assert!(!health.readiness(50_000).checks["last_cycle"].ok);
+        // A stalled loop fails liveness too.
+        assert!(health.liveness(110_000).healthy);
+        assert!(!health.liveness(110_001).healthy);
+    }
+}
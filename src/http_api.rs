@@ -0,0 +1,960 @@
+use anyhow::{Context, Result};
+use axum::BoxError;
+use axum::body::Bytes;
+use axum::error_handling::HandleErrorLayer;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceBuilder;
+use tower::limit::GlobalConcurrencyLimitLayer;
+
+use crate::assets;
+use crate::clock_drift::local_now_ms;
+use crate::config::{AdminRole, AdminSettings, SymbolSettings};
+use crate::health::Health;
+use crate::keystore;
+use crate::metrics::Metrics;
+use crate::overrides::{ManualOverride, OverrideRequest, OverrideStore};
+use crate::push::{PushHub, Subscriber};
+use crate::reconcile::{Reconcile, ReconcileReport};
+use crate::response_cache::ResponseCache;
+use crate::schema;
+use crate::symbol_registry::{
+    RegisterError, RegisteredSource, RegisteredSymbol, SourceRequest, SymbolRegistry, SymbolRequest,
+};
+
+/// Shared state handed to every HTTP handler.
+pub struct ApiState {
+    pub info: schema::RuntimeInfo,
+    pub metrics: Arc<Metrics>,
+    pub overrides: Arc<OverrideStore>,
+    pub symbols: Arc<SymbolRegistry>,
+    pub health: Arc<Health>,
+    /// Published prices for `/ws` subscribers.
+    pub push: Arc<PushHub>,
+    /// `GET /price` responses, each kept for `general.fetch_interval_seconds`.
+    pub prices: ResponseCache,
+    /// Runs `POST /admin/reconcile`.
+    pub reconciler: Arc<dyn Reconcile>,
+    /// Bearer tokens for `/admin/*`; admin routes reject everything when there are none.
+    pub admin_tokens: Vec<AdminToken>,
+    /// `sui.keystore_file` and `sui.fix_keystore_permissions`, for `GET /admin/keys`.
+    pub keystore_file: Option<String>,
+    pub fix_keystore_permissions: bool,
+    /// Requests handled concurrently; beyond that requests are shed with `503`.
+    pub max_connections: usize,
+}
+
+/// A bearer token for `/admin/*` and the role it grants.
+pub struct AdminToken {
+    /// For logs and errors; never the token itself.
+    pub name: String,
+    pub token: String,
+    pub role: AdminRole,
+}
+
+/// The tokens set in the environment: `admin.token_env`'s as `key_admin`, then
+/// `admin.tokens`.
+pub fn admin_tokens(settings: &AdminSettings) -> Vec<AdminToken> {
+    let configured = std::iter::once(("admin", &settings.token_env, AdminRole::KeyAdmin)).chain(
+        settings
+            .tokens
+            .iter()
+            .map(|(name, token)| (name.as_str(), &token.token_env, token.role)),
+    );
+    configured
+        .filter_map(|(name, token_env, role)| {
+            let token = std::env::var(token_env).ok().filter(|t| !t.is_empty())?;
+            Some(AdminToken {
+                name: name.to_string(),
+                token,
+                role,
+            })
+        })
+        .collect()
+}
+
+type ApiError = (StatusCode, Json<schema::ErrorResponse>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(schema::ErrorResponse::new(message)))
+}
+
+async fn handle_overload(error: BoxError) -> ApiError {
+    api_error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        format!("Server overloaded: {}", error),
+    )
+}
+
+/// Sheds requests with `503` instead of queueing them once `max_connections` are in flight.
+fn with_overload_limit<S>(router: Router<S>, max_connections: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_overload))
+            .load_shed()
+            // Global: `Router::layer` wraps each route separately, and the limit must be shared.
+            .layer(GlobalConcurrencyLimitLayer::new(max_connections.max(1))),
+    )
+}
+
+pub fn router(state: Arc<ApiState>) -> Router {
+    let routes = Router::new()
+        .route("/info", get(get_info))
+        .route("/schema", get(get_schema))
+        .route("/schema/oracle.proto", get(get_proto))
+        .route("/metrics", get(get_metrics))
+        .route("/healthz", get(get_healthz))
+        .route("/readyz", get(get_readyz))
+        .route("/assets", get(get_assets))
+        .route("/price", get(get_price))
+        .route("/ws", get(get_ws))
+        .route(
+            "/admin/overrides",
+            get(list_overrides)
+                .post(set_override)
+                .delete(clear_override),
+        )
+        .route(
+            "/admin/symbols",
+            get(list_registered_symbols).post(register_symbol),
+        )
+        .route(
+            "/admin/sources",
+            get(list_registered_sources).post(register_source),
+        )
+        .route("/admin/reconcile", post(reconcile))
+        .route("/admin/keys", get(list_keys));
+    let max_connections = state.max_connections;
+    with_overload_limit(routes, max_connections).with_state(state)
+}
+
+/// Compares in time independent of where the inputs first differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Admits a request whose bearer token grants at least `required`.
+fn authorize(state: &ApiState, headers: &HeaderMap, required: AdminRole) -> Result<(), ApiError> {
+    if state.admin_tokens.is_empty() {
+        return Err(api_error(StatusCode::FORBIDDEN, "admin API is disabled"));
+    }
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let token = provided.and_then(|provided| {
+        state
+            .admin_tokens
+            .iter()
+            .find(|t| constant_time_eq(provided.as_bytes(), t.token.as_bytes()))
+    });
+    match token {
+        Some(token) if token.role >= required => Ok(()),
+        Some(token) => {
+            log::warn!(
+                "Admin token '{}' ({:?}) refused a {:?} route",
+                token.name,
+                token.role,
+                required
+            );
+            Err(api_error(
+                StatusCode::FORBIDDEN,
+                format!("admin token '{}' may not use this route", token.name),
+            ))
+        }
+        None => Err(api_error(StatusCode::UNAUTHORIZED, "invalid admin token")),
+    }
+}
+
+async fn list_overrides(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ManualOverride>>, ApiError> {
+    authorize(&state, &headers, AdminRole::ReadOnly)?;
+    Ok(Json(state.overrides.list(local_now_ms() as u64)))
+}
+
+async fn set_override(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Json(request): Json<OverrideRequest>,
+) -> Result<(StatusCode, Json<ManualOverride>), ApiError> {
+    authorize(&state, &headers, AdminRole::FeedOperator)?;
+    let entry = state
+        .overrides
+        .set(request, local_now_ms() as u64)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+#[derive(Deserialize)]
+struct ClearOverrideParams {
+    symbol: String,
+    operator: String,
+}
+
+async fn clear_override(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Query(params): Query<ClearOverrideParams>,
+) -> Result<Json<ManualOverride>, ApiError> {
+    authorize(&state, &headers, AdminRole::FeedOperator)?;
+    state
+        .overrides
+        .clear(&params.symbol, &params.operator, local_now_ms() as u64)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "no active override"))
+}
+
+async fn list_registered_symbols(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<BTreeMap<String, SymbolSettings>>, ApiError> {
+    authorize(&state, &headers, AdminRole::ReadOnly)?;
+    Ok(Json(state.symbols.registered()))
+}
+
+async fn register_symbol(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Json(request): Json<SymbolRequest>,
+) -> Result<(StatusCode, Json<RegisteredSymbol>), ApiError> {
+    authorize(&state, &headers, AdminRole::FeedOperator)?;
+    match state.symbols.register(request, local_now_ms() as u64).await {
+        Ok(registered) => Ok((StatusCode::CREATED, Json(registered))),
+        Err(e @ RegisterError::Rejected(_)) => {
+            Err(api_error(StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        Err(e @ RegisterError::Failed(_)) => {
+            log::error!("Failed to register symbol: {}", e);
+            Err(api_error(StatusCode::BAD_GATEWAY, e.to_string()))
+        }
+    }
+}
+
+async fn reconcile(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<ReconcileReport>, ApiError> {
+    authorize(&state, &headers, AdminRole::FeedOperator)?;
+    match state.reconciler.reconcile().await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            log::error!("Failed to reconcile with the chain: {:#}", e);
+            Err(api_error(StatusCode::BAD_GATEWAY, format!("{:#}", e)))
+        }
+    }
+}
+
+/// A key of the keystore in `GET /admin/keys`; never the key itself.
+#[derive(Serialize)]
+struct KeyRow {
+    alias: Option<String>,
+    address: String,
+    publisher: bool,
+}
+
+/// The keystore's keys, read on every request so rotations show up without a restart.
+/// With the key in the environment only the publisher is listed.
+async fn list_keys(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<KeyRow>>, ApiError> {
+    authorize(&state, &headers, AdminRole::KeyAdmin)?;
+    let publisher = &state.info.publisher_address;
+    let Some(path) = &state.keystore_file else {
+        return Ok(Json(vec![KeyRow {
+            alias: None,
+            address: publisher.clone(),
+            publisher: true,
+        }]));
+    };
+    let accounts = keystore::accounts(path, state.fix_keystore_permissions).map_err(|e| {
+        log::error!("Failed to read the keystore: {:#}", e);
+        api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to read the keystore",
+        )
+    })?;
+    Ok(Json(
+        accounts
+            .into_iter()
+            .map(|account| {
+                let address = account.address.to_string();
+                KeyRow {
+                    alias: account.alias,
+                    publisher: &address == publisher,
+                    address,
+                }
+            })
+            .collect(),
+    ))
+}
+
+async fn list_registered_sources(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<BTreeMap<String, SourceRequest>>, ApiError> {
+    authorize(&state, &headers, AdminRole::ReadOnly)?;
+    Ok(Json(state.symbols.registered_sources()))
+}
+
+async fn register_source(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Json(request): Json<SourceRequest>,
+) -> Result<(StatusCode, Json<RegisteredSource>), ApiError> {
+    authorize(&state, &headers, AdminRole::FeedOperator)?;
+    match state
+        .symbols
+        .register_source(request, local_now_ms() as u64)
+        .await
+    {
+        Ok(registered) => Ok((StatusCode::CREATED, Json(registered))),
+        Err(e @ RegisterError::Rejected(_)) => {
+            Err(api_error(StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        Err(e @ RegisterError::Failed(_)) => {
+            log::error!("Failed to register source: {}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn get_info(State(state): State<Arc<ApiState>>) -> Json<schema::RuntimeInfo> {
+    Json(state.info.clone())
+}
+
+/// The wire formats of this build.
+async fn get_schema(State(state): State<Arc<ApiState>>) -> Json<schema::SchemaDocument> {
+    Json(schema::SchemaDocument::new(
+        &state.info.version,
+        &state.info.git_hash,
+    ))
+}
+
+/// The gRPC service definition, for `protoc`.
+async fn get_proto() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        schema::PROTO_DEFINITION,
+    )
+}
+
+fn health_response(report: schema::HealthReport) -> (StatusCode, Json<schema::HealthReport>) {
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+async fn get_healthz(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    health_response(state.health.liveness(local_now_ms() as u64))
+}
+
+async fn get_readyz(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    health_response(state.health.readiness(local_now_ms() as u64))
+}
+
+async fn get_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+#[derive(Deserialize)]
+struct AssetQuery {
+    coin_type: Option<String>,
+}
+
+/// The priced assets, or with `?coin_type=` the one asset of that type.
+async fn get_assets(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<AssetQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    let settings = state.symbols.settings();
+    let internal = |e: anyhow::Error| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let Some(coin_type) = query.coin_type else {
+        let assets = assets::list(&settings).map_err(internal)?;
+        return Ok(Json(schema::AssetList {
+            schema_version: schema::SCHEMA_VERSION.to_string(),
+            assets,
+        })
+        .into_response());
+    };
+    assets::canonical_coin_type(&coin_type)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    match assets::lookup(&settings, &coin_type).map_err(internal)? {
+        Some(asset) => Ok(Json(asset).into_response()),
+        None => Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("no feed prices {}", coin_type),
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+struct PriceQuery {
+    symbol: String,
+}
+
+/// The last price published for `?symbol=`, as pushed over `/ws`.
+async fn get_price(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<PriceQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    let body = state.prices.get_or_render(&query.symbol, || {
+        let update = state.push.latest(&query.symbol)?;
+        serde_json::to_vec(&update).ok().map(Bytes::from)
+    });
+    match body {
+        Some(body) => Ok(([(header::CONTENT_TYPE, "application/json")], body).into_response()),
+        None => Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("no price published for {} yet", query.symbol),
+        )),
+    }
+}
+
+/// A send to a `/ws` subscriber taking longer than this disconnects it.
+const WS_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+const WS_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+async fn get_ws(State(state): State<Arc<ApiState>>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    let subscriber = state.push.subscribe();
+    ws.on_upgrade(move |socket| push_to(socket, subscriber))
+}
+
+/// Sends `subscriber`'s messages and a heartbeat every `WS_HEARTBEAT_INTERVAL` until the
+/// client leaves or stalls.
+async fn push_to(mut socket: WebSocket, mut subscriber: Subscriber) {
+    let mut heartbeat = tokio::time::interval(WS_HEARTBEAT_INTERVAL);
+    loop {
+        let message = tokio::select! {
+            message = subscriber.next() => match message {
+                Some(message) => message,
+                None => return,
+            },
+            _ = heartbeat.tick() => schema::WsMessage::Heartbeat {
+                timestamp_ms: local_now_ms() as u64,
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to encode a WebSocket message: {}", e);
+                continue;
+            }
+        };
+        match tokio::time::timeout(WS_SEND_TIMEOUT, socket.send(Message::Text(text))).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => return,
+            Err(_) => {
+                log::warn!(
+                    "Disconnecting a WebSocket subscriber that accepted nothing for {:?}",
+                    WS_SEND_TIMEOUT
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Serves the HTTP API until the process exits.
+pub async fn serve(bind_addr: &str, state: Arc<ApiState>) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind_addr)
+        .await
+        .context(format!("Failed to bind HTTP API to {}", bind_addr))?;
+    log::info!("HTTP API listening on {}", bind_addr);
+    axum::serve(listener, router(state))
+        .await
+        .context("HTTP API server failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use crate::config::AdminSettings;
+
+    fn test_state() -> Arc<ApiState> {
+        test_state_with_admin(None, "./test_http_api_audit.jsonl")
+    }
+
+    fn test_state_with_admin(admin_token: Option<&str>, audit_log_file: &str) -> Arc<ApiState> {
+        Arc::new(ApiState {
+            info: schema::RuntimeInfo {
+                schema_version: schema::SCHEMA_VERSION.to_string(),
+                version: "0.1.0".to_string(),
+                git_hash: "abc".to_string(),
+                config_digest: "00".to_string(),
+                network: "testnet".to_string(),
+                rpc_url: "https://fullnode.testnet.sui.io:443".to_string(),
+                paper_mode: false,
+                publisher_address: "0x1".to_string(),
+                package_id: "0x2".to_string(),
+                sources: vec![],
+                strategies: Default::default(),
+                started_at_ms: 1,
+                labels: Default::default(),
+            },
+            metrics: Arc::new(Metrics::new()),
+            overrides: Arc::new(OverrideStore::new(&AdminSettings {
+                audit_log_file: audit_log_file.to_string(),
+                ..Default::default()
+            })),
+            symbols: Arc::new(crate::symbol_registry::test_registry(audit_log_file)),
+            health: Arc::new(Health::new(Default::default(), local_now_ms() as u64)),
+            push: Arc::new(PushHub::new(8, Arc::new(Metrics::new()))),
+            prices: ResponseCache::new(Duration::from_secs(60)),
+            reconciler: Arc::new(crate::reconcile::NoChain),
+            admin_tokens: admin_token
+                .map(|token| AdminToken {
+                    name: "admin".to_string(),
+                    token: token.to_string(),
+                    role: AdminRole::KeyAdmin,
+                })
+                .into_iter()
+                .collect(),
+            keystore_file: None,
+            fix_keystore_permissions: false,
+            max_connections: 8,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_info_endpoint() {
+        let response = router(test_state())
+            .oneshot(Request::get("/info").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let info: schema::RuntimeInfo = serde_json::from_slice(&body).unwrap();
+        assert_eq!(info.git_hash, "abc");
+    }
+
+    #[tokio::test]
+    async fn test_schema_endpoints() {
+        let app = router(test_state());
+        let response = app
+            .clone()
+            .oneshot(Request::get("/schema").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let document: schema::SchemaDocument = serde_json::from_slice(&body).unwrap();
+        assert_eq!(document.git_hash, "abc");
+        assert!(document.json_schemas.contains_key("PriceUpdate"));
+
+        let response = app
+            .oneshot(
+                Request::get("/schema/oracle.proto")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, schema::PROTO_DEFINITION.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let state = test_state();
+        state
+            .metrics
+            .set_gauge("oracle_up", "Process is running", &[], 1.0);
+        let response = router(state)
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("oracle_up 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_readiness_waits_for_first_cycle() {
+        let state = test_state();
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        let live = router(state.clone())
+            .oneshot(get("/healthz"))
+            .await
+            .unwrap();
+        assert_eq!(live.status(), StatusCode::OK);
+        let ready = router(state).oneshot(get("/readyz")).await.unwrap();
+        assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = to_bytes(ready.into_body(), usize::MAX).await.unwrap();
+        let report: schema::HealthReport = serde_json::from_slice(&body).unwrap();
+        assert!(!report.healthy);
+        assert_eq!(report.checks["last_cycle"].detail, "no cycle completed yet");
+    }
+
+    fn admin_request(method: &str, uri: &str, token: Option<&str>, body: Body) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_require_token() {
+        let disabled = router(test_state())
+            .oneshot(admin_request(
+                "GET",
+                "/admin/overrides",
+                Some("x"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(disabled.status(), StatusCode::FORBIDDEN);
+
+        let state = test_state_with_admin(Some("s3cret"), "./test_http_api_auth.jsonl");
+        let wrong = router(state)
+            .oneshot(admin_request(
+                "GET",
+                "/admin/overrides",
+                Some("nope"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_enforce_token_role() {
+        let mut state = test_state_with_admin(Some("s3cret"), "./test_http_api_roles.jsonl");
+        Arc::get_mut(&mut state)
+            .unwrap()
+            .admin_tokens
+            .push(AdminToken {
+                name: "dashboards".to_string(),
+                token: "view".to_string(),
+                role: AdminRole::ReadOnly,
+            });
+        let request = |method, uri, token| admin_request(method, uri, Some(token), Body::empty());
+
+        let listed = router(state.clone())
+            .oneshot(request("GET", "/admin/overrides", "view"))
+            .await
+            .unwrap();
+        assert_eq!(listed.status(), StatusCode::OK);
+        let refused = router(state.clone())
+            .oneshot(request("POST", "/admin/reconcile", "view"))
+            .await
+            .unwrap();
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+        let body = to_bytes(refused.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("'dashboards' may not"));
+
+        // Past the role check, on to the chain.
+        let admitted = router(state)
+            .oneshot(request("POST", "/admin/reconcile", "s3cret"))
+            .await
+            .unwrap();
+        assert_eq!(admitted.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn test_key_routes_require_key_admin() {
+        let mut state = test_state_with_admin(Some("s3cret"), "./test_http_api_keys.jsonl");
+        Arc::get_mut(&mut state)
+            .unwrap()
+            .admin_tokens
+            .push(AdminToken {
+                name: "operators".to_string(),
+                token: "ops".to_string(),
+                role: AdminRole::FeedOperator,
+            });
+        let request = |token| admin_request("GET", "/admin/keys", Some(token), Body::empty());
+
+        let refused = router(state.clone()).oneshot(request("ops")).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+
+        let listed = router(state).oneshot(request("s3cret")).await.unwrap();
+        assert_eq!(listed.status(), StatusCode::OK);
+        let body = to_bytes(listed.into_body(), usize::MAX).await.unwrap();
+        let keys: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(keys[0]["address"], "0x1");
+        assert_eq!(keys[0]["publisher"], true);
+    }
+
+    #[tokio::test]
+    async fn test_override_set_and_clear() {
+        let audit = "./test_http_api_override.jsonl";
+        let state = test_state_with_admin(Some("s3cret"), audit);
+        let body = serde_json::json!({
+            "symbol": "BTC/USD",
+            "price": 65000.0,
+            "ttl_seconds": 60,
+            "reason": "venues down",
+            "operator": "alice"
+        });
+        let created = router(state.clone())
+            .oneshot(admin_request(
+                "POST",
+                "/admin/overrides",
+                Some("s3cret"),
+                Body::from(body.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(created.status(), StatusCode::CREATED);
+        assert!(
+            state
+                .overrides
+                .active("BTC/USD", local_now_ms() as u64)
+                .is_some()
+        );
+
+        let cleared = router(state.clone())
+            .oneshot(admin_request(
+                "DELETE",
+                "/admin/overrides?symbol=BTC/USD&operator=bob",
+                Some("s3cret"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(cleared.status(), StatusCode::OK);
+        assert!(
+            state
+                .overrides
+                .active("BTC/USD", local_now_ms() as u64)
+                .is_none()
+        );
+        std::fs::remove_file(audit).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_override_without_expiry_is_rejected() {
+        let state = test_state_with_admin(Some("s3cret"), "./test_http_api_invalid.jsonl");
+        let body = serde_json::json!({
+            "symbol": "BTC/USD",
+            "price": 65000.0,
+            "ttl_seconds": 0,
+            "reason": "venues down",
+            "operator": "alice"
+        });
+        let response = router(state)
+            .oneshot(admin_request(
+                "POST",
+                "/admin/overrides",
+                Some("s3cret"),
+                Body::from(body.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_registration_is_validated() {
+        let audit = "./test_http_api_symbols.jsonl";
+        let state = test_state_with_admin(Some("s3cret"), audit);
+        let body = serde_json::json!({
+            "symbol": "BTC/USD",
+            "tickers": { "binance": "BTCUSDT" },
+            "operator": "alice"
+        });
+        let response = router(state.clone())
+            .oneshot(admin_request(
+                "POST",
+                "/admin/symbols",
+                Some("s3cret"),
+                Body::from(body.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("already a configured symbol"));
+
+        let listed = router(state)
+            .oneshot(admin_request(
+                "GET",
+                "/admin/symbols",
+                Some("s3cret"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(listed.status(), StatusCode::OK);
+        let body = to_bytes(listed.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"{}");
+        assert!(!std::path::Path::new(audit).exists());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_requires_token_and_reports_chain_failure() {
+        let state = test_state_with_admin(Some("s3cret"), "./test_http_api_reconcile.jsonl");
+        let response = router(state.clone())
+            .oneshot(admin_request(
+                "POST",
+                "/admin/reconcile",
+                None,
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router(state)
+            .oneshot(admin_request(
+                "POST",
+                "/admin/reconcile",
+                Some("s3cret"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("no Sui node"));
+    }
+
+    #[tokio::test]
+    async fn test_price_is_served_from_cache() {
+        let state = test_state();
+        let update = |price: f64| schema::PriceUpdate {
+            schema_version: schema::SCHEMA_VERSION.to_string(),
+            symbol: "BTC/USD".to_string(),
+            price,
+            scaled_price: (price * 1e6) as u64,
+            decimals: 6,
+            timestamp_ms: 1,
+            sources: vec![],
+            object_id: None,
+            tx_digest: None,
+        };
+        let get = || {
+            router(state.clone()).oneshot(
+                Request::get("/price?symbol=BTC/USD")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        assert_eq!(get().await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        state
+            .push
+            .publish(schema::WsMessage::PriceUpdate(update(65_000.0)));
+        let response = get().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let served: schema::PriceUpdate = serde_json::from_slice(&body).unwrap();
+        assert_eq!(served, update(65_000.0));
+
+        // Within the TTL the rendered response is served, not the newer price.
+        state
+            .push
+            .publish(schema::WsMessage::PriceUpdate(update(66_000.0)));
+        let body = to_bytes(get().await.unwrap().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let served: schema::PriceUpdate = serde_json::from_slice(&body).unwrap();
+        assert_eq!(served.price, 65_000.0);
+    }
+
+    #[tokio::test]
+    async fn test_assets_endpoint() {
+        let get = |uri: &str| {
+            router(test_state()).oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        };
+        let response = get("/assets").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let list: schema::AssetList = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list.assets.len(), 1);
+        assert_eq!(list.assets[0].feed, "BTC/USD");
+
+        // Found however the type is written.
+        let response = get("/assets?coin_type=0xB::wbtc::WBTC").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let asset: schema::AssetInfo = serde_json::from_slice(&body).unwrap();
+        assert_eq!(asset.decimals, Some(8));
+
+        let response = get("/assets?coin_type=0x2::sui::SUI").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = get("/assets?coin_type=SUI").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_requests_beyond_limit_are_shed() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let held = release.clone();
+        let (entered_tx, entered_rx) = tokio::sync::oneshot::channel();
+        let entered_tx = Arc::new(std::sync::Mutex::new(Some(entered_tx)));
+        let app = with_overload_limit(
+            Router::new().route(
+                "/slow",
+                get(move || async move {
+                    if let Some(tx) = entered_tx.lock().unwrap().take() {
+                        let _ = tx.send(());
+                    }
+                    held.notified().await;
+                    "done"
+                }),
+            ),
+            1,
+        );
+
+        let first = tokio::spawn(
+            app.clone()
+                .oneshot(Request::get("/slow").body(Body::empty()).unwrap()),
+        );
+        entered_rx.await.unwrap();
+        let shed = app
+            .clone()
+            .oneshot(Request::get("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        release.notify_one();
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_is_404() {
+        let response = router(test_state())
+            .oneshot(Request::get("/nope").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
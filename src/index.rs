@@ -0,0 +1,183 @@
+use anyhow::{Result, anyhow};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::aggregator::AggregationInput;
+use crate::config::IndexSettings;
+
+/// Checks that every index is built only from configured feeds and doesn't shadow one.
+pub fn validate(indices: &BTreeMap<String, IndexSettings>, feeds: &[&str]) -> Result<()> {
+    for (name, index) in indices {
+        if feeds.contains(&name.as_str()) {
+            return Err(anyhow!("indices.\"{}\": name is already a feed", name));
+        }
+        if index.components.is_empty() {
+            return Err(anyhow!("indices.\"{}\": no components", name));
+        }
+        if !index.divisor.is_finite() || index.divisor <= 0.0 {
+            return Err(anyhow!("indices.\"{}\": divisor must be positive", name));
+        }
+        for (component, weight) in &index.components {
+            if !feeds.contains(&component.as_str()) {
+                return Err(anyhow!(
+                    "indices.\"{}\": component {} is not a configured feed",
+                    name,
+                    component
+                ));
+            }
+            if !weight.is_finite() || *weight <= 0.0 {
+                return Err(anyhow!(
+                    "indices.\"{}\": weight of {} must be positive",
+                    name,
+                    component
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses an index written as an expression, `0.6*BTC/USD + 0.4*ETH/USD`: a sum of feeds,
+/// each optionally multiplied by its weight, with a divisor of 1.
+pub fn parse_expression(expression: &str) -> Result<IndexSettings> {
+    let mut components = BTreeMap::new();
+    for term in expression.split('+').map(str::trim) {
+        let (weight, feed) = match term.split_once('*') {
+            Some((weight, feed)) => {
+                let weight = weight.trim();
+                let weight = weight
+                    .parse()
+                    .map_err(|_| anyhow!("index '{}': '{}' is not a weight", expression, weight))?;
+                (weight, feed.trim())
+            }
+            None => (1.0, term),
+        };
+        if feed.is_empty() || feed.contains(|c: char| c.is_whitespace() || c == '*') {
+            return Err(anyhow!(
+                "index '{}': '{}' is not a weighted feed",
+                expression,
+                term
+            ));
+        }
+        if components.insert(feed.to_string(), weight).is_some() {
+            return Err(anyhow!("index '{}': {} appears twice", expression, feed));
+        }
+    }
+    Ok(IndexSettings {
+        components,
+        divisor: 1.0,
+    })
+}
+
+/// `sum(weight * price) / divisor` over this cycle's component prices, in decimal. Every
+/// component must have a price; a partial basket would silently shift the index level.
+pub fn compute(
+    index: &IndexSettings,
+    prices: &HashMap<String, Decimal>,
+) -> Result<(Decimal, Vec<AggregationInput>)> {
+    let missing: Vec<&str> = index
+        .components
+        .keys()
+        .filter(|c| !prices.contains_key(*c))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!("missing component price: {}", missing.join(", ")));
+    }
+
+    let inputs = index
+        .components
+        .iter()
+        .map(|(component, &weight)| AggregationInput {
+            weight,
+            included: true,
+            ..AggregationInput::new(component, None, prices.get(component).copied())
+        })
+        .collect();
+    let mut sum = Decimal::ZERO;
+    for (component, &weight) in &index.components {
+        let term = Decimal::from_f64(weight)
+            .and_then(|weight| prices[component].checked_mul(weight))
+            .ok_or_else(|| anyhow!("{} x {} has no decimal value", weight, component))?;
+        sum = sum
+            .checked_add(term)
+            .ok_or_else(|| anyhow!("index level overflows"))?;
+    }
+    let level = Decimal::from_f64(index.divisor)
+        .and_then(|divisor| sum.checked_div(divisor))
+        .ok_or_else(|| anyhow!("divisor {} has no decimal value", index.divisor))?;
+    Ok((level, inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top2() -> IndexSettings {
+        IndexSettings {
+            components: BTreeMap::from([
+                ("BTC/USD".to_string(), 0.5),
+                ("ETH/USD".to_string(), 2.0),
+            ]),
+            divisor: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_compute_weighted_basket() {
+        let prices = HashMap::from([
+            ("BTC/USD".to_string(), Decimal::from(60_000)),
+            ("ETH/USD".to_string(), Decimal::from(3_000)),
+        ]);
+        let (value, inputs) = compute(&top2(), &prices).unwrap();
+        assert_eq!(value, Decimal::from(3_600));
+        assert_eq!(inputs.len(), 2);
+
+        let partial = HashMap::from([("BTC/USD".to_string(), Decimal::from(60_000))]);
+        let err = compute(&top2(), &partial).unwrap_err();
+        assert_eq!(err.to_string(), "missing component price: ETH/USD");
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let index = parse_expression("0.6*BTC/USD + 0.4 * ETH/USD+SUI/USD").unwrap();
+        assert_eq!(
+            index.components,
+            BTreeMap::from([
+                ("BTC/USD".to_string(), 0.6),
+                ("ETH/USD".to_string(), 0.4),
+                ("SUI/USD".to_string(), 1.0),
+            ])
+        );
+        assert_eq!(index.divisor, 1.0);
+
+        for invalid in [
+            "",
+            "0.6*BTC/USD +",
+            "x*BTC/USD",
+            "0.6 BTC/USD",
+            "BTC/USD + BTC/USD",
+        ] {
+            assert!(parse_expression(invalid).is_err(), "{}", invalid);
+        }
+        let err = parse_expression("0.6*BTC/USD + 0.4*").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "index '0.6*BTC/USD + 0.4*': '0.4*' is not a weighted feed"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let feeds = ["BTC/USD", "ETH/USD"];
+        let indices = |name: &str, index| BTreeMap::from([(name.to_string(), index)]);
+        assert!(validate(&indices("TOP2", top2()), &feeds).is_ok());
+        assert!(validate(&indices("BTC/USD", top2()), &feeds).is_err());
+        assert!(validate(&indices("TOP2", top2()), &feeds[..1]).is_err());
+
+        let mut zero_weight = top2();
+        zero_weight.components.insert("BTC/USD".to_string(), 0.0);
+        assert!(validate(&indices("TOP2", zero_weight), &feeds).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sui_sdk::types::base_types::SuiAddress;
+use sui_sdk::types::crypto::{EncodeDecodeBase64, SuiKeyPair};
+
+use crate::config::SuiSettings;
+
+/// The Sui CLI keeps key aliases in this file, next to the keystore.
+const ALIASES_FILE_NAME: &str = "sui.aliases";
+
+/// One entry of a Sui CLI `sui.aliases` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyAlias {
+    pub alias: String,
+    /// Base64 `flag || public key`, as printed by `sui keytool list`.
+    pub public_key_base64: String,
+}
+
+/// A key in the keystore, with its alias if it has one.
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub alias: Option<String>,
+    pub address: SuiAddress,
+}
+
+/// Refuses keystores that other users can read, like ssh does for private keys. With
+/// `fix_permissions` the file is tightened to `0600` instead. A keystore owned by another
+/// user is only warned about, since root-managed key files are a legitimate setup.
+#[cfg(unix)]
+pub fn check_permissions(path: &Path, fix_permissions: bool) -> Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata =
+        fs::metadata(path).context(format!("Failed to read keystore {}", path.display()))?;
+    let uid = unsafe { libc::geteuid() };
+    if metadata.uid() != uid {
+        log::warn!(
+            "Keystore {} is owned by uid {}, not by the running user (uid {})",
+            path.display(),
+            metadata.uid(),
+            uid
+        );
+    }
+
+    let mode = metadata.mode() & 0o777;
+    if mode & 0o077 == 0 {
+        return Ok(());
+    }
+    if !fix_permissions {
+        return Err(anyhow!(
+            "Keystore {} is accessible by other users (mode {:o}); run `chmod 600 {}` or set \
+             sui.fix_keystore_permissions = true",
+            path.display(),
+            mode,
+            path.display()
+        ));
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).context(format!(
+        "Failed to restrict permissions of {}",
+        path.display()
+    ))?;
+    log::warn!(
+        "Keystore {} was accessible by other users (mode {:o}); changed to 600",
+        path.display(),
+        mode
+    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn check_permissions(_path: &Path, _fix_permissions: bool) -> Result<()> {
+    Ok(())
+}
+
+/// All keys of a Sui CLI keystore (a JSON array of base64 `flag || key` strings), in file
+/// order, after checking the file's permissions.
+fn load_keys(path: &Path, fix_permissions: bool) -> Result<Vec<SuiKeyPair>> {
+    check_permissions(path, fix_permissions)?;
+    let content =
+        fs::read_to_string(path).context(format!("Failed to read keystore {}", path.display()))?;
+    let keys: Vec<String> = serde_json::from_str(&content)
+        .context(format!("Failed to parse keystore {}", path.display()))?;
+    keys.iter()
+        .map(|key| {
+            SuiKeyPair::decode_base64(key)
+                .map_err(|e| anyhow!("Failed to decode key in {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+pub fn aliases_path(keystore_path: &Path) -> PathBuf {
+    keystore_path.with_file_name(ALIASES_FILE_NAME)
+}
+
+/// The aliases next to a keystore; a keystore without an aliases file has none.
+pub fn load_aliases(keystore_path: &Path) -> Result<Vec<KeyAlias>> {
+    let path = aliases_path(keystore_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).context(format!("Failed to parse {}", path.display()))
+}
+
+/// Loads the key named `alias` in the keystore's `sui.aliases` file, or the first key of
+/// the keystore when no alias is given.
+pub fn load_keypair(path: &str, alias: Option<&str>, fix_permissions: bool) -> Result<SuiKeyPair> {
+    let path = Path::new(path);
+    let keys = load_keys(path, fix_permissions)?;
+    let Some(alias) = alias else {
+        return keys
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Keystore {} contains no keys", path.display()));
+    };
+    let public_key = load_aliases(path)?
+        .into_iter()
+        .find(|a| a.alias == alias)
+        .map(|a| a.public_key_base64)
+        .ok_or_else(|| {
+            anyhow!(
+                "No key alias '{}' in {}",
+                alias,
+                aliases_path(path).display()
+            )
+        })?;
+    keys.into_iter()
+        .find(|k| k.public().encode_base64() == public_key)
+        .ok_or_else(|| {
+            anyhow!(
+                "Key alias '{}' refers to a key that is not in {}",
+                alias,
+                path.display()
+            )
+        })
+}
+
+/// The publisher key: from the `sui.private_key_env` environment variable, or else from
+/// `sui.keystore_file`. Having neither, or both, is an error; there is no built-in key.
+pub fn publisher_keypair(settings: &SuiSettings) -> Result<SuiKeyPair> {
+    let env_key = std::env::var(&settings.private_key_env)
+        .ok()
+        .filter(|key| !key.trim().is_empty());
+    select_publisher_keypair(settings, env_key.as_deref())
+}
+
+fn select_publisher_keypair(settings: &SuiSettings, env_key: Option<&str>) -> Result<SuiKeyPair> {
+    let env = &settings.private_key_env;
+    match (env_key, &settings.keystore_file) {
+        (Some(_), Some(path)) => Err(anyhow!(
+            "Both {} and sui.keystore_file ({}) provide a publisher key; unset one of them",
+            env,
+            path
+        )),
+        (Some(key), None) => {
+            if let Some(alias) = &settings.key_alias {
+                return Err(anyhow!(
+                    "sui.key_alias = \"{}\" selects a keystore key, but the key comes from {}",
+                    alias,
+                    env
+                ));
+            }
+            // The error would otherwise echo part of the key.
+            SuiKeyPair::decode_base64(key.trim()).map_err(|_| {
+                anyhow!(
+                    "{} is not a base64 `flag || private key` Sui key (convert a suiprivkey \
+                     with `sui keytool convert`)",
+                    env
+                )
+            })
+        }
+        (None, Some(path)) => load_keypair(
+            path,
+            settings.key_alias.as_deref(),
+            settings.fix_keystore_permissions,
+        ),
+        (None, None) => Err(anyhow!(
+            "No publisher key: set {} or sui.keystore_file (a Sui CLI keystore, e.g. \
+             ~/.sui/sui_config/sui.keystore)",
+            env
+        )),
+    }
+}
+
+/// Every key in the keystore with its address and alias, in keystore order.
+pub fn accounts(path: &str, fix_permissions: bool) -> Result<Vec<Account>> {
+    let path = Path::new(path);
+    let aliases = load_aliases(path)?;
+    Ok(load_keys(path, fix_permissions)?
+        .iter()
+        .map(|key| {
+            let public_key = key.public();
+            let encoded = public_key.encode_base64();
+            Account {
+                alias: aliases
+                    .iter()
+                    .find(|a| a.public_key_base64 == encoded)
+                    .map(|a| a.alias.clone()),
+                address: SuiAddress::from(&public_key),
+            }
+        })
+        .collect())
+}
+
+/// The key of a pre-keystore key file: a single base64 `flag || key` line instead of a
+/// JSON array.
+pub fn legacy_key_line(content: &str) -> Option<&str> {
+    let line = content.trim();
+    if line.is_empty() || line.starts_with('[') || line.contains('\n') {
+        return None;
+    }
+    Some(line)
+}
+
+/// Writes `keys` as a Sui CLI keystore that only the owner can read.
+pub fn write_keystore(path: &Path, keys: &[String]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .context(format!("Failed to open keystore {}", path.display()))?;
+    std::io::Write::write_all(&mut file, serde_json::to_string_pretty(keys)?.as_bytes())
+        .context(format!("Failed to write keystore {}", path.display()))?;
+    // `mode` only applies to newly created files.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).context(format!(
+            "Failed to restrict permissions of {}",
+            path.display()
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    const TEST_KEY_B64: &str = "ALiJ7ig1JDkCMh4/TL914LABL4HVntuoSXtf414NmW9K";
+
+    fn write_keystore(path: &str, mode: u32) {
+        fs::write(path, format!("[\"{}\"]", TEST_KEY_B64)).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    fn mode_of(path: &str) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn test_readable_keystore_is_rejected() {
+        let path = "./test_keystore_readable.keystore";
+        write_keystore(path, 0o644);
+        let err = load_keypair(path, None, false).err().unwrap();
+        assert!(err.to_string().contains("accessible by other users"));
+        assert_eq!(mode_of(path), 0o644);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_permissions_are_fixed_when_enabled() {
+        let path = "./test_keystore_fix.keystore";
+        write_keystore(path, 0o640);
+        load_keypair(path, None, true).unwrap();
+        assert_eq!(mode_of(path), 0o600);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_key_is_selected_by_alias() {
+        let dir = "./test_keystore_aliases";
+        fs::create_dir_all(dir).unwrap();
+        let path = format!("{}/sui.keystore", dir);
+        let other_key = "AKy5igvY8KNczT1XZ0Z3nS1ozKzHq2sBwpUUYrT4Zm1A";
+        fs::write(&path, format!("[\"{}\", \"{}\"]", TEST_KEY_B64, other_key)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let public_key = |key| {
+            SuiKeyPair::decode_base64(key)
+                .unwrap()
+                .public()
+                .encode_base64()
+        };
+        let aliases = vec![KeyAlias {
+            alias: "oracle".to_string(),
+            public_key_base64: public_key(other_key),
+        }];
+        fs::write(
+            aliases_path(Path::new(&path)),
+            serde_json::to_string(&aliases).unwrap(),
+        )
+        .unwrap();
+
+        let by_alias = load_keypair(&path, Some("oracle"), false).unwrap();
+        assert_eq!(by_alias.public().encode_base64(), public_key(other_key));
+        assert!(load_keypair(&path, Some("missing"), false).is_err());
+
+        let accounts = accounts(&path, false).unwrap();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|a| a.alias.as_deref())
+                .collect::<Vec<_>>(),
+            vec![None, Some("oracle")]
+        );
+        assert_eq!(accounts[1].address, SuiAddress::from(&by_alias.public()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_key_line() {
+        assert_eq!(
+            legacy_key_line(&format!("{}\n", TEST_KEY_B64)),
+            Some(TEST_KEY_B64)
+        );
+        assert_eq!(legacy_key_line(&format!("[\"{}\"]", TEST_KEY_B64)), None);
+        assert_eq!(legacy_key_line("a\nb"), None);
+    }
+
+    #[test]
+    fn test_private_keystore_loads() {
+        let path = "./test_keystore_private.keystore";
+        write_keystore(path, 0o600);
+        assert!(load_keypair(path, None, false).is_ok());
+        fs::write(path, "[]").unwrap();
+        assert!(load_keypair(path, None, false).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_publisher_key_needs_exactly_one_source() {
+        let mut settings = SuiSettings::default();
+        let err = select_publisher_keypair(&settings, None).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("No publisher key: set SUI_PRIVATE_KEY")
+        );
+        assert!(select_publisher_keypair(&settings, Some(TEST_KEY_B64)).is_ok());
+
+        let err = select_publisher_keypair(&settings, Some("not-a-key")).unwrap_err();
+        assert!(!err.to_string().contains("not-a-key"));
+
+        settings.key_alias = Some("oracle".to_string());
+        assert!(select_publisher_keypair(&settings, Some(TEST_KEY_B64)).is_err());
+
+        settings.key_alias = None;
+        settings.keystore_file = Some("sui.keystore".to_string());
+        let err = select_publisher_keypair(&settings, Some(TEST_KEY_B64)).unwrap_err();
+        assert!(err.to_string().contains("unset one of them"));
+    }
+}
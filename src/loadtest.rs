@@ -0,0 +1,310 @@
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::chain::{ChainBackend, PriceInfo, PublishTrace, Submission};
+use crate::clock_drift;
+use crate::config::{self, Settings};
+use crate::cycle::{self, CycleRng};
+use crate::sources;
+use crate::sui_publisher::{self, SuiPublisher};
+use crate::synthetic_source::SyntheticSource;
+use crate::transport::Transport;
+
+/// RPC requests one transaction makes on the real path, however many updates it carries:
+/// PriceObject reads, reference gas price, gas coins, dry run and execution. The client
+/// connects once, before the first cycle.
+const MOCK_RPCS_PER_TRANSACTION: u64 = 5;
+
+/// Known-objects file of the localnet target, kept apart from the paper mode one.
+const LOADTEST_KNOWN_OBJECTS_FILE: &str = "known_price_objects.loadtest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    /// Simulate every RPC request with a fixed latency; nothing leaves the process.
+    Mock,
+    /// Publish to the `[sui.paper]` network, normally a Sui localnet.
+    Localnet,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub symbols: usize,
+    pub cycles: u64,
+    pub target: Target,
+    pub rpc_latency_ms: u64,
+    pub seed: Option<u64>,
+}
+
+enum Publisher {
+    Mock {
+        latency: Duration,
+        rpc_requests: u64,
+    },
+    Localnet(Box<SuiPublisher>),
+}
+
+impl Publisher {
+    async fn localnet(settings: &Settings) -> Result<Self> {
+        let mut sui = settings.sui.clone();
+        sui.rpc_url = sui.paper.rpc_url.clone();
+        sui.package_id = sui.paper.package_id.clone().ok_or_else(|| {
+            anyhow!("sui.paper.package_id must name the package published to localnet")
+        })?;
+        sui.known_objects_file =
+            config::state_path(&settings.general.state_dir, LOADTEST_KNOWN_OBJECTS_FILE);
+        sui.migration = None;
+        Ok(Publisher::Localnet(Box::new(
+            SuiPublisher::connect(sui).await?,
+        )))
+    }
+
+    /// Publishes a cycle's updates in transactions of up to `max_batch_size` updates.
+    async fn publish(
+        &mut self,
+        updates: Vec<PriceInfo>,
+        max_batch_size: usize,
+        rng: &mut CycleRng,
+    ) -> Vec<Result<()>> {
+        match self {
+            Publisher::Mock {
+                latency,
+                rpc_requests,
+            } => {
+                let transactions = updates.len().div_ceil(max_batch_size.max(1)) as u64;
+                tokio::time::sleep(*latency * (transactions * MOCK_RPCS_PER_TRANSACTION) as u32)
+                    .await;
+                *rpc_requests += transactions * MOCK_RPCS_PER_TRANSACTION;
+                updates.iter().map(|_| Ok(())).collect()
+            }
+            Publisher::Localnet(publisher) => {
+                publisher.begin_round();
+                let mut trace = PublishTrace::new();
+                publisher
+                    .publish(updates, rng, &mut trace)
+                    .await
+                    .into_iter()
+                    .map(|result| match result? {
+                        Submission::Published(_) | Submission::Duplicate => Ok(()),
+                        Submission::Expired { age_ms } => {
+                            Err(anyhow!("Update expired after {} ms", age_ms))
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn rpc_requests(&self) -> u64 {
+        match self {
+            Publisher::Mock { rpc_requests, .. } => *rpc_requests,
+            Publisher::Localnet(_) => sui_publisher::rpc_requests(),
+        }
+    }
+}
+
+/// Resident and peak resident memory in kB, from `/proc/self/status` (Linux only).
+fn memory_kb(status: &str) -> (Option<u64>, Option<u64>) {
+    let field = |name: &str| {
+        status.lines().find_map(|line| {
+            line.strip_prefix(name)?
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse()
+                .ok()
+        })
+    };
+    (field("VmRSS:"), field("VmHWM:"))
+}
+
+/// Nearest-rank percentile of sorted, non-empty `values`.
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_kb(kb: Option<u64>) -> String {
+    kb.map(|kb| format!("{:.1} MiB", kb as f64 / 1024.0))
+        .unwrap_or_else(|| "n/a".to_string())
+}
+
+/// Runs `cycles` fetch-and-publish cycles over `symbols` synthetic feeds, one update per
+/// feed per cycle batched as the main loop publishes them, and prints cycle times, RPC volume and
+/// memory use.
+pub async fn run(options: Options) -> Result<()> {
+    if options.symbols == 0 || options.cycles == 0 {
+        return Err(anyhow!("--symbols and --cycles must be at least 1"));
+    }
+    let settings = Settings::load()?;
+    let seed = options
+        .seed
+        .or(settings.general.random_seed)
+        .unwrap_or_else(cycle::random_base_seed);
+    log::info!(
+        "Load test: {} symbols, {} cycles, {:?} target, seed {}",
+        options.symbols,
+        options.cycles,
+        options.target,
+        seed
+    );
+
+    let synthetic = SyntheticSource::new(options.symbols, seed);
+    let mut publisher = match options.target {
+        Target::Mock => Publisher::Mock {
+            latency: Duration::from_millis(options.rpc_latency_ms),
+            rpc_requests: 0,
+        },
+        Target::Localnet => Publisher::localnet(&settings).await?,
+    };
+    let rpc_start = publisher.rpc_requests();
+
+    let mut cycle_times = Vec::new();
+    let (mut published, mut failed) = (0u64, 0u64);
+    for n in 1..=options.cycles {
+        let started = Instant::now();
+        let fetched = sources::fetch_all(
+            &[&synthetic],
+            |_| Transport::Rest,
+            |_| Vec::new(),
+            |_| Vec::new(),
+            |_| Vec::new(),
+            Duration::from_millis(settings.general.source_timeout_ms),
+        )
+        .await;
+        let prices = fetched
+            .into_iter()
+            .filter_map(|data| data.prices)
+            .flatten()
+            .collect::<Vec<_>>();
+        let mut updates = Vec::with_capacity(prices.len());
+        for (ticker, sample) in prices {
+            updates.push(PriceInfo {
+                symbol: format!("{}/USD", ticker.trim_end_matches("USD")),
+                price: sample.price,
+                timestamp_ms: clock_drift::publish_timestamp_ms(
+                    settings.general.timestamp_granularity_ms,
+                ),
+                manual_override: false,
+                confidence: None,
+            });
+        }
+        let symbols: Vec<String> = updates.iter().map(|u| u.symbol.clone()).collect();
+        let results = publisher
+            .publish(
+                updates,
+                settings.sui.max_batch_size,
+                &mut CycleRng::for_cycle(seed, n),
+            )
+            .await;
+        for (symbol, result) in symbols.iter().zip(results) {
+            match result {
+                Ok(()) => published += 1,
+                Err(e) => {
+                    log::error!("Load test update of {} failed: {:#}", symbol, e);
+                    failed += 1;
+                }
+            }
+        }
+        let elapsed = started.elapsed();
+        log::info!("Load test cycle {} took {:?}", n, elapsed);
+        cycle_times.push(elapsed);
+    }
+    let rpc_requests = publisher.rpc_requests() - rpc_start;
+
+    let total: Duration = cycle_times.iter().sum();
+    cycle_times.sort();
+    let interval = Duration::from_secs(settings.general.fetch_interval_seconds);
+    let over_interval = cycle_times.iter().filter(|t| **t > interval).count();
+    let (rss, peak) = memory_kb(&fs::read_to_string("/proc/self/status").unwrap_or_default());
+
+    println!(
+        "Load test: {} symbols x {} cycles, {:?} target, seed {}",
+        options.symbols, options.cycles, options.target, seed
+    );
+    println!(
+        "Cycle time: min {:?}, mean {:?}, p95 {:?}, max {:?}",
+        cycle_times[0],
+        total / cycle_times.len() as u32,
+        percentile(&cycle_times, 95.0),
+        cycle_times[cycle_times.len() - 1]
+    );
+    println!(
+        "Cycles over the {}s fetch interval: {}/{}",
+        settings.general.fetch_interval_seconds,
+        over_interval,
+        cycle_times.len()
+    );
+    println!("Updates: {} published, {} failed", published, failed);
+    println!(
+        "RPC requests: {} total, {:.1} per cycle, {:.1} per update",
+        rpc_requests,
+        rpc_requests as f64 / options.cycles as f64,
+        rpc_requests as f64 / (published + failed).max(1) as f64
+    );
+    println!(
+        "Memory: {} resident, {} peak",
+        format_kb(rss),
+        format_kb(peak)
+    );
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} updates failed",
+            failed,
+            published + failed
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_percentile_and_memory_parsing() {
+        let times: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&times, 95.0), Duration::from_millis(19));
+        assert_eq!(percentile(&times, 100.0), Duration::from_millis(20));
+        assert_eq!(percentile(&times[..1], 95.0), Duration::from_millis(1));
+
+        let status = "Name:\tneo_oracle_mvp\nVmHWM:\t   20480 kB\nVmRSS:\t   10240 kB\n";
+        assert_eq!(memory_kb(status), (Some(10240), Some(20480)));
+        assert_eq!(memory_kb(""), (None, None));
+        assert_eq!(format_kb(Some(10240)), "10.0 MiB");
+    }
+
+    #[tokio::test]
+    async fn test_mock_publisher_counts_rpc_requests() {
+        let mut publisher = Publisher::Mock {
+            latency: Duration::ZERO,
+            rpc_requests: 0,
+        };
+        let updates = || {
+            (1..=3)
+                .map(|i| PriceInfo {
+                    symbol: format!("SYN000{}/USD", i),
+                    price: Decimal::ONE,
+                    timestamp_ms: 0,
+                    manual_override: false,
+                    confidence: None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // Two transactions of up to two updates each, then one per update.
+        let results = publisher
+            .publish(updates(), 2, &mut CycleRng::from_seed(1))
+            .await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(publisher.rpc_requests(), 2 * MOCK_RPCS_PER_TRANSACTION);
+        assert_eq!(
+            publisher
+                .publish(updates(), 1, &mut CycleRng::from_seed(1))
+                .await
+                .len(),
+            3
+        );
+        assert_eq!(publisher.rpc_requests(), 5 * MOCK_RPCS_PER_TRANSACTION);
+    }
+}
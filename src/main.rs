@@ -1,162 +1,2325 @@
-use anyhow::Result;
-use tokio::time::{Duration, sleep};
+use anyhow::{Context, Result};
+use chain::ChainBackend;
+use clap::Parser;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant, sleep};
+use tracing::Instrument;
 
 mod aggregator;
+mod alerts;
+mod assets;
+mod backup;
 mod binance_client;
+mod bindings;
+mod bybit_client;
+mod calendar;
+mod chain;
+mod cli;
+mod clock_drift;
 mod coinbase_client;
+mod coingecko_client;
 mod config;
+mod config_history;
+mod consumers;
+mod cross_rate;
+mod cycle;
+mod debug_bundle;
+mod digest;
+mod endpoints;
+mod fees;
+mod health;
+#[cfg(feature = "http-api")]
+mod http_api;
+mod index;
+mod keystore;
+mod loadtest;
+mod metrics;
+mod move_schema;
+mod object_map;
+mod okx_client;
+mod outbox;
+mod overrides;
+mod publish_limiter;
+mod publish_policy;
+#[cfg(feature = "http-api")]
+mod push;
+mod quarantine;
+mod rate_limit;
+#[cfg(feature = "http-api")]
+mod reconcile;
+mod redaction;
+#[cfg(feature = "http-api")]
+mod response_cache;
+mod rpc_failover;
+mod runtime_info;
+mod schema;
+mod service;
+mod shadow;
+mod sla;
+mod source_recovery;
+mod sources;
+mod state;
+#[cfg(feature = "storage-sql")]
+mod storage;
 mod sui_publisher;
+mod symbol;
+mod symbol_registry;
+mod synthetic_source;
+mod transport;
+#[cfg(feature = "ws-sources")]
+mod ws_stream;
 
-// Helper function to parse price string to Option<f64>
-fn parse_price(price_str_opt: Option<&String>) -> Option<f64> {
-    price_str_opt.and_then(|price_str| price_str.parse::<f64>().ok())
+/// Long-lived state shared by every publishing cycle.
+struct Monitoring {
+    calendars: calendar::CalendarRegistry,
+    overrides: Arc<overrides::OverrideStore>,
+    metrics: Arc<metrics::Metrics>,
+    sla: sla::SlaTracker,
+    alerter: alerts::Alerter,
+    /// The daily summary of outcomes, gas and SLA compliance, when `digest.enabled`.
+    digest: Option<digest::Digest>,
+    /// What `/healthz` and `/readyz` report.
+    health: Arc<health::Health>,
+    /// Prices gas in USD and records it in the gas ledger.
+    fees: fees::FeeTracker,
+    /// Every fetched sample and aggregated price, when `storage.enabled`.
+    #[cfg(feature = "storage-sql")]
+    history: Option<storage::HistoryStore>,
+    /// Divergence of the `[shadow]` aggregates from the live ones.
+    shadow: shadow::ShadowTracker,
+    /// Symbols currently withheld because their sources disagree; alerts fire on entry only.
+    divergent: Mutex<HashSet<String>>,
+    /// Symbols currently withheld because their aggregate is far from the reference price.
+    off_reference: Mutex<HashSet<String>>,
+    /// Symbols currently withheld because their aggregate is outside its sanity bounds.
+    out_of_bounds: Mutex<HashSet<String>>,
+    /// Symbols currently withheld because their price does not scale to a `u64` above 0.
+    unscalable: Mutex<HashSet<String>>,
+    /// Symbols flagged stale on-chain through `sui.stale_function` and not updated since.
+    marked_stale: Mutex<HashSet<String>>,
+    publish_limiter: publish_limiter::PublishLimiter<QueuedUpdate>,
+    /// The last published price of each symbol, for the deviation and heartbeat gate.
+    publish_policy: Arc<publish_policy::PublishPolicy>,
+    /// Held while publishing, so queued updates, a cycle and a symbol registration never
+    /// pick the same gas coin.
+    publishing: Arc<tokio::sync::Mutex<()>>,
+    /// Signalled when an update expired before it landed, so the next cycle starts at once
+    /// instead of after `general.fetch_interval_seconds`.
+    refetch: tokio::sync::Notify,
+    /// Publishes to the package, checked against its on-chain module at startup.
+    publisher: Arc<dyn ChainBackend>,
+    /// What `publisher` supports, probed when it connected.
+    capabilities: chain::Capabilities,
+    /// The same for the previous package, during a migration window.
+    previous_publisher: Option<Box<dyn ChainBackend>>,
+    /// Compares the PriceObjects with what was last published to them. It reads them back
+    /// from Sui, so unlike the pipeline it holds the `SuiPublisher` itself.
+    #[cfg(feature = "http-api")]
+    reconciler: Arc<reconcile::Reconciler>,
+    /// Estimated clock offset of each source, for the age of its samples.
+    clock_drift: Arc<clock_drift::ClockDriftTracker>,
+    /// Sources back from an outage, weighted down until they have been up for a while.
+    recovery: source_recovery::RecoveryTracker,
+    /// Sources and feeds taken out of the cycle after repeated failures.
+    quarantine: quarantine::QuarantineStore,
+    /// Whether each streaming source is read from its stream or polled over REST.
+    transports: transport::TransportSelector,
+    /// Updates not yet on chain, published after a restart while still fresh.
+    outbox: outbox::Outbox,
+    /// The settings each cycle runs with, including feeds registered at runtime.
+    symbols: Arc<symbol_registry::SymbolRegistry>,
+    /// Published prices for the HTTP API's `/ws` subscribers.
+    #[cfg(feature = "http-api")]
+    push: Arc<push::PushHub>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
-    log::info!("Neo Oracle MVP starting");
+/// An update held back by `min_update_interval_ms`, published once the spacing elapses.
+struct QueuedUpdate {
+    price_info: chain::PriceInfo,
+    aggregation: aggregator::AggregateResult,
+}
 
-    let settings = match config::Settings::load() {
-        Ok(s) => s,
+/// The reported price, if it is positive. A venue quoting `0` or a negative price has no
+/// usable price.
+fn parse_price(sample: Option<&sources::PriceSample>) -> Option<Decimal> {
+    sample
+        .map(|sample| sample.price)
+        .filter(|price| *price > Decimal::ZERO)
+}
+
+/// Exchange tickers whose order book must be checked against a liquidity minimum.
+fn liquidity_tickers(settings: &config::Settings, source: &str) -> Vec<String> {
+    settings
+        .symbols
+        .values()
+        .filter(|s| s.min_liquidity_notional.is_some())
+        .filter_map(|s| s.tickers.get(source).cloned())
+        .collect()
+}
+
+/// Exchange tickers of volume-weighted feeds, whose 24h volume must be fetched.
+fn volume_tickers(settings: &config::Settings, source: &str) -> Vec<String> {
+    settings
+        .feed_symbols()
+        .into_iter()
+        .filter(|symbol| settings.aggregation_method(symbol) == config::AggregationMethod::Vwap)
+        .filter_map(|symbol| settings.ticker(symbol, source).cloned())
+        .collect()
+}
+
+/// Exchange tickers of feeds publishing their bid and ask, whose quotes must be fetched.
+fn quote_tickers(settings: &config::Settings, source: &str) -> Vec<String> {
+    settings
+        .feed_symbols()
+        .into_iter()
+        .filter(|symbol| settings.symbols[*symbol].publish_bid_ask)
+        .filter_map(|symbol| settings.ticker(symbol, source).cloned())
+        .collect()
+}
+
+fn source_input(
+    data: &sources::SourceData,
+    exchange_symbol: Option<&String>,
+    min_liquidity_notional: Option<f64>,
+) -> aggregator::AggregationInput {
+    let sample = exchange_symbol.and_then(|sym| data.prices.as_ref()?.get(sym));
+    let price = parse_price(sample);
+    let excluded = match (price, exchange_symbol, min_liquidity_notional) {
+        (None, _, _) => sample.map(|sample| format!("unusable price '{}'", sample.price)),
+        (Some(_), Some(sym), Some(min)) => aggregator::liquidity_exclusion(
+            data.depth.as_ref().and_then(|d| d.get(sym)).copied(),
+            min,
+        ),
+        _ => None,
+    };
+    aggregator::AggregationInput {
+        excluded,
+        volume: exchange_symbol.and_then(|sym| data.volume.as_ref()?.get(sym).copied()),
+        quote: exchange_symbol.and_then(|sym| data.quotes.as_ref()?.get(sym).copied()),
+        observed_at_ms: sample.and_then(|sample| sample.timestamp_ms),
+        ..aggregator::AggregationInput::new(data.source.name(), exchange_symbol.cloned(), price)
+    }
+}
+
+/// Effective per-feed configuration, as recorded in the config history.
+fn feed_configs(
+    settings: &config::Settings,
+    sources: &[Box<dyn sources::PriceSource>],
+) -> Vec<(String, config_history::FeedConfig)> {
+    settings
+        .feed_symbols()
+        .into_iter()
+        .map(|symbol| {
+            let symbol_settings = settings.symbols.get(symbol).cloned().unwrap_or_default();
+            let config = config_history::FeedConfig {
+                sources: sources
+                    .iter()
+                    .map(|source| {
+                        (
+                            source.name().to_string(),
+                            settings.ticker(symbol, source.name()).cloned(),
+                        )
+                    })
+                    .collect(),
+                max_source_age_ms: settings.general.max_source_age_ms,
+                max_source_spread_pct: symbol_settings.max_source_spread_pct,
+                max_reference_deviation_pct: symbol_settings.max_reference_deviation_pct,
+                min_liquidity_notional: symbol_settings.min_liquidity_notional,
+                outlier_filter: settings.outlier_filter(symbol),
+                aggregation_method: settings.aggregation_method(symbol),
+                publish_bid_ask: symbol_settings.publish_bid_ask,
+                publish_inverse: symbol_settings.publish_inverse,
+                price_adjustments_bps: sources
+                    .iter()
+                    .map(|source| {
+                        let bps = settings.price_adjustment_bps(symbol, source.name());
+                        (source.name().to_string(), bps)
+                    })
+                    .filter(|(_, bps)| *bps != 0.0)
+                    .collect(),
+                sla: symbol_settings.sla,
+                calendar: symbol_settings
+                    .calendar
+                    .unwrap_or_else(|| calendar::DEFAULT_CALENDAR.to_string()),
+                priority: symbol_settings.priority,
+            };
+            (symbol.to_string(), config)
+        })
+        .collect()
+}
+
+/// Withholds a symbol whose sources disagree by more than its configured tolerance, rather
+/// than averaging numbers that point at a venue outage or a symbol mismatch.
+fn check_source_spread(
+    monitoring: &Monitoring,
+    symbol: &str,
+    spread_pct: Option<f64>,
+    max_spread_pct: Option<f64>,
+) -> Option<cycle::SymbolOutcome> {
+    let (Some(spread_pct), Some(max_spread_pct)) = (spread_pct, max_spread_pct) else {
+        return None;
+    };
+    if spread_pct <= max_spread_pct {
+        if monitoring.divergent.lock().unwrap().remove(symbol) {
+            log::info!(
+                "{} sources back within tolerance ({:.3}% <= {}%)",
+                symbol,
+                spread_pct,
+                max_spread_pct
+            );
+        }
+        return None;
+    }
+
+    let reason = format!(
+        "source spread {:.3}% exceeds max {}%",
+        spread_pct, max_spread_pct
+    );
+    log::warn!("Withholding {} update: {}", symbol, reason);
+    let newly_divergent = monitoring
+        .divergent
+        .lock()
+        .unwrap()
+        .insert(symbol.to_string());
+    if newly_divergent {
+        let alert = alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind: "source_spread",
+            symbol: symbol.to_string(),
+            message: format!("{}; possible venue outage or symbol mismatch", reason),
+            timestamp_ms: clock_drift::local_now_ms() as u64,
+        };
+        monitoring.alerter.send(&alert);
+    }
+    Some(cycle::SymbolOutcome::Withheld { reason })
+}
+
+/// The price of `symbol` from the first reference source that has one recent enough, with
+/// the source's name.
+fn reference_price(
+    settings: &config::Settings,
+    symbol: &str,
+    sources: &[sources::SourceData],
+    now_ms: i64,
+) -> Option<(&'static str, Decimal)> {
+    sources.iter().find_map(|data| {
+        let max_age = data.source.reference_max_age()?;
+        let sample = data
+            .prices
+            .as_ref()?
+            .get(settings.ticker(symbol, data.source.name())?)?;
+        if let Some(timestamp_ms) = sample.timestamp_ms
+            && now_ms - timestamp_ms > max_age.as_millis() as i64
+        {
+            log::debug!(
+                "{} reference price of {} is stale ({} ms old)",
+                data.source.name(),
+                symbol,
+                now_ms - timestamp_ms
+            );
+            return None;
+        }
+        Some((data.source.name(), parse_price(Some(sample))?))
+    })
+}
+
+/// Withholds a symbol whose aggregate is further from the reference price than it may be,
+/// which points at the venues sharing a bad print or a symbol mapped to the wrong asset.
+fn check_reference(
+    monitoring: &Monitoring,
+    symbol: &str,
+    price: Decimal,
+    reference: Option<(&str, Decimal)>,
+    max_deviation_pct: Option<f64>,
+) -> Option<cycle::SymbolOutcome> {
+    let (source, reference_price) = reference?;
+    let (price, reference_price) = (
+        aggregator::as_f64(price),
+        aggregator::as_f64(reference_price),
+    );
+    let deviation_pct = (price - reference_price).abs() / reference_price * 100.0;
+    monitoring.metrics.set_gauge(
+        "oracle_reference_deviation_pct",
+        "Distance of the last aggregate from the reference price",
+        &[("symbol", symbol), ("source", source)],
+        deviation_pct,
+    );
+    let max_deviation_pct = max_deviation_pct?;
+    if deviation_pct <= max_deviation_pct {
+        if monitoring.off_reference.lock().unwrap().remove(symbol) {
+            log::info!(
+                "{} back within {}% of the {} reference price",
+                symbol,
+                max_deviation_pct,
+                source
+            );
+        }
+        return None;
+    }
+
+    let reason = format!(
+        "aggregate {:.2} is {:.3}% from the {} reference price {:.2} (max {}%)",
+        price, deviation_pct, source, reference_price, max_deviation_pct
+    );
+    log::warn!("Withholding {} update: {}", symbol, reason);
+    let newly_off = monitoring
+        .off_reference
+        .lock()
+        .unwrap()
+        .insert(symbol.to_string());
+    if newly_off {
+        let alert = alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind: "reference_deviation",
+            symbol: symbol.to_string(),
+            message: format!("{}; possible bad print or symbol mismatch", reason),
+            timestamp_ms: clock_drift::local_now_ms() as u64,
+        };
+        monitoring.alerter.send(&alert);
+    }
+    Some(cycle::SymbolOutcome::Withheld { reason })
+}
+
+/// Withholds a symbol whose aggregate is outside its sanity bounds, rather than writing an
+/// obviously wrong price on-chain.
+fn check_bounds(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    symbol: &str,
+    price: Decimal,
+) -> Option<cycle::SymbolOutcome> {
+    let violation = monitoring.publish_policy.bounds_violation(
+        symbol,
+        aggregator::as_f64(price),
+        sanity_bounds(settings, symbol),
+        Instant::now(),
+    );
+    let Some(reason) = violation else {
+        if monitoring.out_of_bounds.lock().unwrap().remove(symbol) {
+            log::info!("{} back within its sanity bounds", symbol);
+        }
+        return None;
+    };
+    log::error!("Withholding {} update: {}", symbol, reason);
+    let newly_out = monitoring
+        .out_of_bounds
+        .lock()
+        .unwrap()
+        .insert(symbol.to_string());
+    if newly_out {
+        let alert = alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind: "sanity_bounds",
+            symbol: symbol.to_string(),
+            message: format!("{}; possible bad print or misconfigured feed", reason),
+            timestamp_ms: clock_drift::local_now_ms() as u64,
+        };
+        monitoring.alerter.send(&alert);
+    }
+    Some(cycle::SymbolOutcome::Withheld { reason })
+}
+
+/// Withholds an update whose price does not fit a `u64` at its feed's decimals, or rounds
+/// to 0, rather than writing a price consumers would read as real.
+fn check_scalable(
+    monitoring: &Monitoring,
+    price_info: &chain::PriceInfo,
+) -> Option<cycle::SymbolOutcome> {
+    let symbol = price_info.symbol.as_str();
+    let decimals = monitoring.publisher.decimals(symbol);
+    let reason = match sui_publisher::scale_price(
+        price_info.price,
+        decimals,
+        monitoring.publisher.price_rounding(),
+    ) {
+        Ok(0) => format!(
+            "price {} rounds to 0 at {} decimals",
+            price_info.price, decimals
+        ),
+        Ok(_) => {
+            if monitoring.unscalable.lock().unwrap().remove(symbol) {
+                log::info!("{} price scales again at {} decimals", symbol, decimals);
+            }
+            return None;
+        }
+        Err(e) => e.to_string(),
+    };
+    log::error!("Withholding {} update: {}", symbol, reason);
+    let newly_unscalable = monitoring
+        .unscalable
+        .lock()
+        .unwrap()
+        .insert(symbol.to_string());
+    if newly_unscalable {
+        let alert = alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind: "unscalable_price",
+            symbol: symbol.to_string(),
+            message: format!("{}; check the feed's decimals", reason),
+            timestamp_ms: clock_drift::local_now_ms() as u64,
+        };
+        monitoring.alerter.send(&alert);
+    }
+    Some(cycle::SymbolOutcome::Withheld { reason })
+}
+
+fn market_closed(monitoring: &Monitoring, symbol: &str) -> Option<cycle::SymbolOutcome> {
+    if monitoring
+        .calendars
+        .for_symbol(symbol)
+        .is_open(chrono::Utc::now())
+    {
+        return None;
+    }
+    log::info!("Market for {} is closed; not publishing.", symbol);
+    Some(cycle::SymbolOutcome::Skipped {
+        reason: "market closed".to_string(),
+    })
+}
+
+/// `price_sources` without the quarantined ones. Skipping a source is only logged at debug
+/// level: the quarantine was alerted on, and its end is logged.
+fn admitted_sources<'a>(
+    price_sources: &'a [Box<dyn sources::PriceSource>],
+    quarantined: &quarantine::QuarantineState,
+    now_ms: u64,
+) -> Vec<&'a dyn sources::PriceSource> {
+    price_sources
+        .iter()
+        .map(|source| source.as_ref())
+        .filter(|source| {
+            let subject = quarantine::Subject::source(source.name());
+            match quarantined.active(&subject, now_ms) {
+                Some(entry) => {
+                    log::debug!(
+                        "Not fetching {}: quarantined until {} ({})",
+                        source.name(),
+                        entry.until(),
+                        entry.reason
+                    );
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect()
+}
+
+/// The outcome of a feed whose circuit breaker is open.
+fn breaker_open(
+    quarantined: &quarantine::QuarantineState,
+    symbol: &str,
+    now_ms: u64,
+) -> Option<cycle::SymbolOutcome> {
+    let entry = quarantined.active(&quarantine::Subject::feed(symbol), now_ms)?;
+    Some(cycle::SymbolOutcome::Skipped {
+        reason: format!(
+            "circuit breaker open until {} ({})",
+            entry.until(),
+            entry.reason
+        ),
+    })
+}
+
+/// Counts this cycle's source and feed failures towards quarantine, and alerts on every
+/// source quarantined and feed breaker tripped.
+fn record_failures(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    fetched: &[sources::SourceData<'_>],
+    outcomes: &[(String, cycle::SymbolOutcome)],
+) {
+    let limits = &settings.quarantine;
+    let ttl_ms = limits.ttl_seconds.saturating_mul(1000);
+    let max_ttl_ms = limits.max_ttl_seconds.saturating_mul(1000);
+    let now_ms = clock_drift::local_now_ms() as u64;
+    let tripped = monitoring.quarantine.update(|state| {
+        let mut tripped = Vec::new();
+        for data in fetched {
+            let subject = quarantine::Subject::source(data.source.name());
+            let result = data.error.as_deref().map_or(Ok(()), Err);
+            let threshold = limits.source_failure_threshold;
+            if let Some(entry) =
+                state.record(&subject, result, threshold, ttl_ms, max_ttl_ms, now_ms)
+            {
+                tripped.push(entry.clone());
+            }
+        }
+        for (symbol, outcome) in outcomes {
+            let result = match outcome {
+                cycle::SymbolOutcome::Published { .. } => Ok(()),
+                cycle::SymbolOutcome::Failed { error } => Err(error.as_str()),
+                _ => continue,
+            };
+            let subject = quarantine::Subject::feed(symbol);
+            let threshold = limits.feed_failure_threshold;
+            if let Some(entry) =
+                state.record(&subject, result, threshold, ttl_ms, max_ttl_ms, now_ms)
+            {
+                tripped.push(entry.clone());
+            }
+        }
+        tripped
+    });
+    let tripped = match tripped {
+        Ok(tripped) => tripped,
         Err(e) => {
-            log::error!("Failed to load configuration: {}", e);
-            return Err(anyhow::anyhow!("Configuration loading failed: {}", e));
+            log::error!("Failed to update the quarantine: {:?}", e);
+            return;
         }
     };
-    log::info!("Configuration loaded successfully. Starting main loop...");
+    for entry in tripped {
+        let (kind, what) = match entry.kind {
+            quarantine::Kind::Source => ("source_quarantined", "not fetched"),
+            quarantine::Kind::Feed => ("feed_circuit_open", "not published"),
+        };
+        monitoring.alerter.send(&alerts::Alert {
+            severity: alerts::Severity::Critical,
+            kind,
+            symbol: entry.name.clone(),
+            message: format!("{} until {} after {}", what, entry.until(), entry.reason),
+            timestamp_ms: now_ms,
+        });
+    }
+}
 
-    loop {
-        log::info!("--- Fetching new prices ---");
-
-        let binance_prices_map =
-            match binance_client::get_binance_prices(&settings.apis.binance).await {
-                Ok(prices) => {
-                    log::info!("Successfully fetched prices from Binance:");
-                    for (symbol, price) in &prices {
-                        log::debug!("Binance - {}: {}", symbol, price);
-                    }
-                    Some(prices)
+type SourcePrices<'a> = BTreeMap<&'static str, Option<&'a HashMap<String, sources::PriceSample>>>;
+
+/// An update to submit, with what its debug bundle needs.
+struct PreparedUpdate<'a> {
+    price_info: chain::PriceInfo,
+    aggregation: aggregator::AggregateResult,
+    sources: SourcePrices<'a>,
+    /// The manual override it publishes; its use is recorded once submitted.
+    manual_override: Option<overrides::ManualOverride>,
+}
+
+/// A feed's share of this cycle: an outcome reached without publishing, or an update to
+/// submit with the rest of the cycle's updates.
+enum Pending<'a> {
+    Settled(cycle::SymbolOutcome),
+    Update(Box<PreparedUpdate<'a>>),
+}
+
+impl From<cycle::SymbolOutcome> for Pending<'_> {
+    fn from(outcome: cycle::SymbolOutcome) -> Self {
+        Pending::Settled(outcome)
+    }
+}
+
+/// What preparing a feed produced this cycle.
+struct FeedOutcome<'a> {
+    outcome: Pending<'a>,
+    /// The price to submit, an input of index feeds.
+    price: Option<Decimal>,
+    /// The feed's bid, ask and inverse feeds, if it publishes them.
+    derived: Vec<(String, Pending<'a>)>,
+}
+
+impl From<cycle::SymbolOutcome> for FeedOutcome<'_> {
+    fn from(outcome: cycle::SymbolOutcome) -> Self {
+        Self {
+            outcome: outcome.into(),
+            price: None,
+            derived: Vec::new(),
+        }
+    }
+}
+
+/// Combines a feed's weighted, filtered source prices with `method`; a volume-weighted
+/// feed missing a volume falls back to the mean.
+fn aggregate_inputs(
+    symbol: &str,
+    mut inputs: Vec<aggregator::AggregationInput>,
+    method: config::AggregationMethod,
+    aggregation: &config::AggregationSettings,
+    outlier_filter: Option<config::OutlierFilter>,
+) -> aggregator::AggregateResult {
+    let strategy = aggregator::for_method(method, aggregation);
+    let vwap = method == config::AggregationMethod::Vwap;
+    let volume_weighted = vwap && {
+        let prices: Vec<Option<Decimal>> = inputs.iter().map(|i| i.usable_price()).collect();
+        let volumes: Vec<Option<f64>> = inputs.iter().map(|i| i.volume).collect();
+        match aggregator::volume_weights(&prices, &volumes) {
+            Some(volume_weights) => {
+                for (input, volume) in inputs.iter_mut().zip(volume_weights) {
+                    input.weight *= volume;
                 }
-                Err(e) => {
-                    log::error!("Failed to fetch prices from Binance: {}", e);
-                    None
+                true
+            }
+            None => {
+                log::warn!(
+                    "Missing 24h volume for a {} source; falling back to the mean",
+                    symbol
+                );
+                false
+            }
+        }
+    };
+    let method_label = format!(
+        "{}{}",
+        if volume_weighted {
+            "vwap"
+        } else {
+            strategy.name()
+        },
+        match outlier_filter {
+            None => "",
+            Some(config::OutlierFilter::Mad { .. }) => "_within_mad",
+            Some(config::OutlierFilter::Percent { .. }) => "_within_pct",
+        }
+    );
+    aggregator::aggregate_prices(
+        strategy.as_ref(),
+        method_label,
+        inputs,
+        aggregation.min_sources,
+    )
+}
+
+/// Compares the `[shadow]` aggregate of `symbol` with the live one, logging the divergence.
+fn compare_shadow(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    symbol: &str,
+    live: &aggregator::AggregateResult,
+    shadow: &aggregator::AggregateResult,
+) {
+    let Some(bps) = monitoring.shadow.compare(
+        symbol,
+        live.result.map(aggregator::as_f64),
+        shadow.result.map(aggregator::as_f64),
+    ) else {
+        match (live.result, shadow.result) {
+            (Some(_), None) => log::info!(
+                "Shadow {} ({}): not enough source data ({} sources)",
+                symbol,
+                shadow.method,
+                shadow.contributing()
+            ),
+            (None, Some(price)) => log::info!(
+                "Shadow {} ({}): {:.2}, while the live configuration has no price",
+                symbol,
+                shadow.method,
+                price
+            ),
+            _ => {}
+        }
+        return;
+    };
+    let shadow_price = shadow.result.unwrap_or_default();
+    if bps.abs() >= settings.shadow.warn_bps {
+        log::warn!(
+            "Shadow {} ({}): {:.2}, {:+.1} bps from live (warn_bps {})",
+            symbol,
+            shadow.method,
+            shadow_price,
+            bps,
+            settings.shadow.warn_bps
+        );
+    } else {
+        log::info!(
+            "Shadow {} ({}): {:.2}, {:+.1} bps from live",
+            symbol,
+            shadow.method,
+            shadow_price,
+            bps
+        );
+    }
+}
+
+/// Prepares one feed's update from the exchange prices, with its bid, ask and inverse
+/// feeds. Also returns the price to submit, for index feeds built on top of it.
+async fn prepare_feed<'a>(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    symbol: &str,
+    sources: &'a [sources::SourceData<'_>],
+    quarantined: &quarantine::QuarantineState,
+) -> FeedOutcome<'a> {
+    if let Some(outcome) = market_closed(monitoring, symbol) {
+        return outcome.into();
+    }
+
+    let min_liquidity_notional = settings
+        .symbols
+        .get(symbol)
+        .and_then(|s| s.min_liquidity_notional);
+    // Reference sources only check the aggregate of the others.
+    let mut inputs: Vec<aggregator::AggregationInput> = sources
+        .iter()
+        .filter(|data| data.source.reference_max_age().is_none())
+        .map(|data| {
+            let source = data.source.name();
+            let mut input = source_input(
+                data,
+                settings.ticker(symbol, source),
+                min_liquidity_notional,
+            );
+            input.adjust(settings.price_adjustment_bps(symbol, source));
+            input
+        })
+        .collect();
+    // A stale price is left out rather than averaged with fresh ones.
+    let now_ms = clock_drift::local_now_ms();
+    for input in inputs.iter_mut().filter(|i| i.price.is_some()) {
+        let age_ms = input.observed_at_ms.map(|observed_at_ms| {
+            monitoring
+                .clock_drift
+                .corrected_age_ms(&input.source, observed_at_ms, now_ms)
+        });
+        if let Some(reason) =
+            aggregator::staleness_exclusion(age_ms, settings.general.max_source_age_ms)
+        {
+            monitoring.metrics.inc_counter(
+                "oracle_source_stale_samples_total",
+                "Source prices left out of an aggregate for their age",
+                &[("symbol", symbol), ("source", &input.source)],
+                1.0,
+            );
+            input.excluded = Some(reason);
+        }
+    }
+    let ramp_cycles = settings.aggregation.recovery_ramp_cycles;
+    for input in inputs.iter_mut().filter(|i| i.price.is_some()) {
+        input.weight = monitoring.recovery.weight(&input.source, ramp_cycles);
+    }
+    let outlier_filter = settings.outlier_filter(symbol);
+    if let Some(filter) = outlier_filter {
+        let prices: Vec<Option<Decimal>> = inputs.iter().map(|i| i.usable_price()).collect();
+        let rejections = aggregator::outlier_rejections(&prices, filter);
+        for (input, rejection) in inputs.iter_mut().zip(rejections) {
+            if rejection.is_some() {
+                monitoring.metrics.inc_counter(
+                    "oracle_source_outliers_total",
+                    "Source prices rejected as outliers",
+                    &[("symbol", symbol), ("source", &input.source)],
+                    1.0,
+                );
+                input.excluded = rejection;
+            }
+        }
+    }
+    for input in &inputs {
+        if let Some(reason) = &input.excluded {
+            log::warn!(
+                "Excluding {} from {} aggregate: {}",
+                input.source,
+                symbol,
+                reason
+            );
+        }
+    }
+    // The shadow configuration sees the same prices and exclusions, with its own weights.
+    let shadow_inputs = settings.shadow.enabled.then(|| {
+        let mut shadow_inputs = inputs.clone();
+        for input in shadow_inputs.iter_mut().filter(|i| i.price.is_some()) {
+            input.weight *= settings.shadow_source_weight(&input.source);
+        }
+        shadow_inputs
+    });
+    for input in inputs.iter_mut().filter(|i| i.price.is_some()) {
+        input.weight *= settings.source_weight(&input.source);
+    }
+    let aggregation = aggregate_inputs(
+        symbol,
+        inputs,
+        settings.aggregation_method(symbol),
+        &settings.aggregation,
+        outlier_filter,
+    );
+    if let Some(shadow_inputs) = shadow_inputs {
+        let (method, shadow_aggregation) = settings.shadow_aggregation(symbol);
+        let shadow = aggregate_inputs(
+            symbol,
+            shadow_inputs,
+            method,
+            &shadow_aggregation,
+            outlier_filter,
+        );
+        compare_shadow(settings, monitoring, symbol, &aggregation, &shadow);
+    }
+    monitoring.metrics.set_gauge(
+        "oracle_aggregate_sources",
+        "Sources that contributed to the last aggregate",
+        &[("symbol", symbol)],
+        aggregation.contributing() as f64,
+    );
+    let manual_override = monitoring
+        .overrides
+        .active(symbol, clock_drift::local_now_ms() as u64);
+    let price = match &manual_override {
+        Some(entry) => {
+            log::warn!(
+                "Publishing manual override for {}: {} (set by {}, expires at {})",
+                symbol,
+                entry.price,
+                entry.operator,
+                entry.expires_at_ms
+            );
+            entry.price
+        }
+        None => {
+            let Some(aggregated_price) = aggregation.result else {
+                let contributing = aggregation.contributing();
+                log::warn!(
+                    "Could not aggregate {} price: {} of {} required sources contributed.",
+                    symbol,
+                    contributing,
+                    settings.aggregation.min_sources
+                );
+                return cycle::SymbolOutcome::Skipped {
+                    reason: format!(
+                        "not enough source data ({} of {} required sources)",
+                        contributing, settings.aggregation.min_sources
+                    ),
                 }
+                .into();
             };
 
-        let coinbase_prices_map =
-            match coinbase_client::get_coinbase_prices(&settings.apis.coinbase).await {
-                Ok(prices) => {
-                    log::info!("Successfully fetched prices from Coinbase:");
-                    for (symbol, price) in &prices {
-                        log::debug!("Coinbase - {}: {}", symbol, price);
-                    }
-                    Some(prices)
+            let max_spread_pct = settings
+                .symbols
+                .get(symbol)
+                .and_then(|s| s.max_source_spread_pct);
+            if let Some(outcome) =
+                check_source_spread(monitoring, symbol, aggregation.spread_pct, max_spread_pct)
+            {
+                return outcome.into();
+            }
+            let reference = reference_price(settings, symbol, sources, clock_drift::local_now_ms());
+            let max_deviation_pct = settings
+                .symbols
+                .get(symbol)
+                .and_then(|s| s.max_reference_deviation_pct);
+            if let Some(outcome) = check_reference(
+                monitoring,
+                symbol,
+                aggregated_price,
+                reference,
+                max_deviation_pct,
+            ) {
+                return outcome.into();
+            }
+            if let Some(outcome) = check_bounds(settings, monitoring, symbol, aggregated_price) {
+                return outcome.into();
+            }
+
+            log::info!("Aggregated {} Price: {:.2}", symbol, aggregated_price);
+            aggregated_price
+        }
+    };
+
+    let price_info = chain::PriceInfo {
+        symbol: symbol.to_string(), // Standardized symbol for on-chain
+        price,
+        timestamp_ms: clock_drift::publish_timestamp_ms(settings.general.timestamp_granularity_ms),
+        manual_override: manual_override.is_some(),
+        // An override replaces the sources the confidence describes.
+        confidence: aggregation.confidence.filter(|_| manual_override.is_none()),
+    };
+    let timestamp_ms = price_info.timestamp_ms;
+    let sources: SourcePrices = sources
+        .iter()
+        .map(|data| (data.source.name(), data.prices.as_ref()))
+        .collect();
+    let publishes_quotes = manual_override.is_none() && settings.symbols[symbol].publish_bid_ask;
+    let is_override = manual_override.is_some();
+    let outcome = Pending::Update(Box::new(PreparedUpdate {
+        price_info,
+        aggregation: aggregation.clone(),
+        sources: sources.clone(),
+        manual_override,
+    }));
+
+    // An override sets the price only, so the venues' quotes no longer apply.
+    let mut derived = Vec::new();
+    let now_ms = clock_drift::local_now_ms() as u64;
+    if publishes_quotes {
+        let strategy =
+            aggregator::for_method(settings.aggregation_method(symbol), &settings.aggregation);
+        let quote = aggregator::aggregate_quotes(
+            strategy.as_ref(),
+            &aggregation,
+            settings.aggregation.min_sources,
+        )
+        .map(|quote| [quote.bid, quote.ask]);
+        if let Err(reason) = &quote {
+            log::warn!("Not publishing {} bid and ask: {}", symbol, reason);
+        }
+        for (i, side) in config::QUOTE_SIDES.into_iter().enumerate() {
+            let feed = config::quote_feed(symbol, side);
+            let outcome = match (&quote, breaker_open(quarantined, &feed, now_ms)) {
+                (_, Some(outcome)) => outcome.into(),
+                (Err(reason), None) => cycle::SymbolOutcome::Skipped {
+                    reason: reason.clone(),
                 }
-                Err(e) => {
-                    log::error!("Failed to fetch prices from Coinbase: {}", e);
+                .into(),
+                (Ok(prices), None) => Pending::Update(Box::new(PreparedUpdate {
+                    price_info: chain::PriceInfo {
+                        symbol: feed.clone(),
+                        price: prices[i],
+                        timestamp_ms,
+                        manual_override: false,
+                        confidence: None,
+                    },
+                    aggregation: aggregation.clone(),
+                    sources: sources.clone(),
+                    manual_override: None,
+                })),
+            };
+            derived.push((feed, outcome));
+        }
+    }
+    // The inverse of an override is as manual as the override itself.
+    if let Some(feed) = settings.inverse_feed(symbol) {
+        let inverse = sui_publisher::inverse_price(
+            price,
+            settings.decimals(&feed),
+            settings.sui.price_rounding,
+        );
+        let outcome = match (inverse, breaker_open(quarantined, &feed, now_ms)) {
+            (_, Some(outcome)) => outcome.into(),
+            (Err(reason), None) => {
+                log::warn!("Not publishing {}: {}", feed, reason);
+                cycle::SymbolOutcome::Skipped { reason }.into()
+            }
+            (Ok(inverse), None) => Pending::Update(Box::new(PreparedUpdate {
+                price_info: chain::PriceInfo {
+                    symbol: feed.clone(),
+                    price: inverse,
+                    timestamp_ms,
+                    manual_override: is_override,
+                    confidence: None,
+                },
+                aggregation: aggregation.clone(),
+                sources: sources.clone(),
+                manual_override: None,
+            })),
+        };
+        derived.push((feed, outcome));
+    }
+    FeedOutcome {
+        outcome,
+        price: Some(price),
+        derived,
+    }
+}
+
+/// Prepares an index update from this cycle's component prices.
+fn prepare_index(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    name: &str,
+    index: &config::IndexSettings,
+    component_prices: &HashMap<String, Decimal>,
+) -> Pending<'static> {
+    if let Some(outcome) = market_closed(monitoring, name) {
+        return outcome.into();
+    }
+    let (level, inputs) = match index::compute(index, component_prices) {
+        Ok(computed) => computed,
+        Err(e) => {
+            log::warn!("Could not compute index {}: {}", name, e);
+            return cycle::SymbolOutcome::Skipped {
+                reason: e.to_string(),
+            }
+            .into();
+        }
+    };
+    log::info!("Computed {} index: {:.2}", name, level);
+    let aggregation = aggregator::AggregateResult {
+        method: "weighted_basket".to_string(),
+        inputs,
+        spread_pct: None,
+        confidence: None,
+        result: Some(level),
+    };
+    let price_info = chain::PriceInfo {
+        symbol: name.to_string(),
+        price: level,
+        timestamp_ms: clock_drift::publish_timestamp_ms(settings.general.timestamp_granularity_ms),
+        manual_override: false,
+        confidence: None,
+    };
+    Pending::Update(Box::new(PreparedUpdate {
+        price_info,
+        aggregation,
+        sources: BTreeMap::new(),
+        manual_override: None,
+    }))
+}
+
+/// Prepares a cross-rate update from this cycle's prices of its two legs.
+fn prepare_cross(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    name: &str,
+    cross: &config::CrossSettings,
+    feed_prices: &HashMap<String, Decimal>,
+) -> Pending<'static> {
+    if let Some(outcome) = market_closed(monitoring, name) {
+        return outcome.into();
+    }
+    let (rate, inputs) = match cross_rate::compute(cross, feed_prices) {
+        Ok(computed) => computed,
+        Err(e) => {
+            log::warn!("Could not compute cross rate {}: {}", name, e);
+            return cycle::SymbolOutcome::Skipped {
+                reason: e.to_string(),
+            }
+            .into();
+        }
+    };
+    log::info!("Computed {} cross rate: {}", name, rate);
+    let aggregation = aggregator::AggregateResult {
+        method: "cross_rate".to_string(),
+        inputs,
+        spread_pct: None,
+        confidence: None,
+        result: Some(rate),
+    };
+    let price_info = chain::PriceInfo {
+        symbol: name.to_string(),
+        price: rate,
+        timestamp_ms: clock_drift::publish_timestamp_ms(settings.general.timestamp_granularity_ms),
+        manual_override: false,
+        confidence: None,
+    };
+    Pending::Update(Box::new(PreparedUpdate {
+        price_info,
+        aggregation,
+        sources: BTreeMap::new(),
+        manual_override: None,
+    }))
+}
+
+/// Bid, ask and inverse feeds are spaced like the feed they belong to.
+fn min_update_interval(settings: &config::Settings, symbol: &str) -> Duration {
+    let ms = settings
+        .symbols
+        .get(settings.derived_feed_parent(symbol).unwrap_or(symbol))
+        .and_then(|s| s.min_update_interval_ms)
+        .unwrap_or(settings.sui.min_update_interval_ms);
+    Duration::from_millis(ms)
+}
+
+/// The sanity bounds of `symbol`'s aggregate.
+fn sanity_bounds(settings: &config::Settings, symbol: &str) -> publish_policy::Bounds {
+    let symbol_settings = settings.symbols.get(symbol);
+    publish_policy::Bounds {
+        min_price: symbol_settings.and_then(|s| s.min_price),
+        max_price: symbol_settings.and_then(|s| s.max_price),
+        max_change_pct: symbol_settings.and_then(|s| s.max_change_pct),
+        max_change_interval: Duration::from_secs(
+            symbol_settings
+                .and_then(|s| s.max_change_interval_seconds)
+                .unwrap_or(config::DEFAULT_MAX_CHANGE_INTERVAL_SECONDS),
+        ),
+    }
+}
+
+/// The deviation and heartbeat gate of `symbol`; derived feeds share their feed's.
+fn publish_policy(settings: &config::Settings, symbol: &str) -> publish_policy::Policy {
+    let symbol_settings = settings
+        .symbols
+        .get(settings.derived_feed_parent(symbol).unwrap_or(symbol));
+    publish_policy::Policy {
+        deviation_bps: symbol_settings
+            .and_then(|s| s.deviation_threshold_bps)
+            .unwrap_or(settings.sui.deviation_threshold_bps),
+        heartbeat: Duration::from_secs(
+            symbol_settings
+                .and_then(|s| s.heartbeat_seconds)
+                .unwrap_or(settings.sui.heartbeat_seconds),
+        ),
+    }
+}
+
+/// Settles an update whose price has barely moved since the last publish, unless its
+/// heartbeat is due.
+fn gate<'a>(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    update: Box<PreparedUpdate<'a>>,
+) -> Pending<'a> {
+    let price_info = &update.price_info;
+    let decision = monitoring.publish_policy.decide(
+        &price_info.symbol,
+        aggregator::as_f64(price_info.price),
+        price_info.manual_override,
+        publish_policy(settings, &price_info.symbol),
+        Instant::now(),
+    );
+    match decision {
+        publish_policy::Decision::Publish => Pending::Update(update),
+        publish_policy::Decision::Hold {
+            deviation_bps,
+            due_in,
+        } => {
+            let outcome = cycle::SymbolOutcome::Skipped {
+                reason: format!(
+                    "moved {:.1} bps, below deviation_threshold_bps; heartbeat in {} s",
+                    deviation_bps,
+                    due_in.as_secs()
+                ),
+            };
+            record_price_history(monitoring, &update, &outcome);
+            outcome.into()
+        }
+    }
+}
+
+/// Submits this cycle's updates together. An update whose price does not scale is withheld,
+/// and one within its deviation threshold is settled as skipped until its heartbeat; one
+/// whose symbol was updated less than `min_update_interval_ms` ago is queued for
+/// `publish_queued_updates` instead.
+async fn submit_updates(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    pending: Vec<(String, Pending<'_>)>,
+    rng: &mut cycle::CycleRng,
+) -> Vec<(String, cycle::SymbolOutcome)> {
+    let pending: Vec<(String, Pending)> = pending
+        .into_iter()
+        .map(|(symbol, pending)| match pending {
+            Pending::Update(mut update) => {
+                monitoring.capabilities.adapt(&mut update.price_info);
+                match check_scalable(monitoring, &update.price_info) {
+                    Some(outcome) => (symbol, outcome.into()),
+                    None => (symbol, gate(settings, monitoring, update)),
+                }
+            }
+            settled => (symbol, settled),
+        })
+        .collect();
+    let updates: Vec<chain::PriceInfo> = pending
+        .iter()
+        .filter_map(|(_, pending)| match pending {
+            Pending::Update(update) => Some(update.price_info.clone()),
+            Pending::Settled(_) => None,
+        })
+        .collect();
+    if let Err(e) = monitoring.outbox.push(&updates) {
+        log::error!("Failed to record pending updates in the outbox: {:?}", e);
+    }
+    let mut outcomes = Vec::with_capacity(pending.len());
+    let (mut positions, mut ready) = (Vec::new(), Vec::new());
+    for (symbol, pending) in pending {
+        let outcome = match pending {
+            Pending::Settled(outcome) => Some(outcome),
+            Pending::Update(update) => match admit(settings, monitoring, *update) {
+                Ok(update) => {
+                    positions.push(outcomes.len());
+                    ready.push(update);
                     None
                 }
+                Err(outcome) => Some(outcome),
+            },
+        };
+        outcomes.push((symbol, outcome));
+    }
+    let submitted = submit_now(settings, monitoring, ready, rng).await;
+    for (i, outcome) in positions.into_iter().zip(submitted) {
+        outcomes[i].1 = Some(outcome);
+    }
+    outcomes
+        .into_iter()
+        .map(|(symbol, outcome)| (symbol, outcome.expect("every update was submitted")))
+        .collect()
+}
+
+/// Hands back an update to submit now, unless its symbol was updated less than
+/// `min_update_interval_ms` ago; then it is queued and its outcome returned.
+fn admit<'a>(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    update: PreparedUpdate<'a>,
+) -> Result<PreparedUpdate<'a>, cycle::SymbolOutcome> {
+    let min_spacing = min_update_interval(settings, &update.price_info.symbol);
+    if min_spacing.is_zero() {
+        return Ok(update);
+    }
+    let symbol = update.price_info.symbol.clone();
+    let queued = QueuedUpdate {
+        price_info: update.price_info.clone(),
+        aggregation: update.aggregation.clone(),
+    };
+    match monitoring
+        .publish_limiter
+        .admit(&symbol, min_spacing, Instant::now(), queued)
+    {
+        publish_limiter::Admission::Publish(_) => Ok(update),
+        publish_limiter::Admission::Queued { due_in } => {
+            log::info!(
+                "Queued {} update: last update was less than {} ms ago; publishing in {} ms",
+                symbol,
+                min_spacing.as_millis(),
+                due_in.as_millis()
+            );
+            let outcome = cycle::SymbolOutcome::Withheld {
+                reason: format!(
+                    "queued by min_update_interval_ms, due in {} ms",
+                    due_in.as_millis()
+                ),
             };
+            record_price_history(monitoring, &update, &outcome);
+            Err(outcome)
+        }
+    }
+}
 
-        let btc_binance_symbol = settings
-            .apis
-            .binance
-            .symbols
-            .iter()
-            .find(|s| s.contains("BTC"))
-            .map(|s| s.as_str());
-        let btc_coinbase_symbol = settings
-            .apis
-            .coinbase
-            .symbols
+/// Publishes queued updates as soon as their symbols' spacing elapses, independently of
+/// the fetch cycle.
+async fn publish_queued_updates(
+    settings: Arc<config::Settings>,
+    monitoring: Arc<Monitoring>,
+    mut rng: cycle::CycleRng,
+) {
+    loop {
+        match monitoring.publish_limiter.next_due() {
+            Some(due) => {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(due) => {}
+                    // A newer update may be due earlier.
+                    _ = monitoring.publish_limiter.queue_changed() => continue,
+                }
+            }
+            None => {
+                monitoring.publish_limiter.queue_changed().await;
+                continue;
+            }
+        }
+        let _publishing = monitoring.publishing.lock().await;
+        monitoring.publisher.begin_round();
+        let updates: Vec<PreparedUpdate> = monitoring
+            .publish_limiter
+            .take_due(Instant::now())
+            .into_iter()
+            .map(|(_, update)| PreparedUpdate {
+                price_info: update.price_info,
+                aggregation: update.aggregation,
+                sources: BTreeMap::new(),
+                manual_override: None,
+            })
+            .collect();
+        let symbols: Vec<String> = updates
             .iter()
-            .find(|s| s.contains("BTC"))
-            .map(|s| s.as_str());
-        let btc_price_binance = btc_binance_symbol.and_then(|sym| {
-            binance_prices_map
-                .as_ref()
-                .and_then(|m| parse_price(m.get(sym)))
-        });
-        let btc_price_coinbase = btc_coinbase_symbol.and_then(|sym| {
-            coinbase_prices_map
-                .as_ref()
-                .and_then(|m| parse_price(m.get(sym)))
-        });
+            .map(|u| u.price_info.symbol.clone())
+            .collect();
+        let outcomes = submit_now(&settings, &monitoring, updates, &mut rng).await;
+        for (symbol, outcome) in symbols.iter().zip(outcomes) {
+            log::info!("Queued {} update: {}", symbol, outcome.label());
+            if let Some(digest) = &monitoring.digest {
+                digest.record(symbol, &outcome);
+            }
+        }
+    }
+}
 
-        let btc_prices_to_aggregate = [btc_price_binance, btc_price_coinbase];
-        if let Some(aggregated_btc_price) = aggregator::aggregate_prices(&btc_prices_to_aggregate) {
-            log::info!("Aggregated BTC/USD Price: {:.2}", aggregated_btc_price);
-            let btc_price_info = sui_publisher::PriceInfo {
-                symbol: "BTC/USD".to_string(), // Standardized symbol for on-chain
-                price: aggregated_btc_price,
-                timestamp_ms: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis() as u64,
-            };
-            match sui_publisher::submit_price_update(btc_price_info).await {
-                Ok(digest) => log::info!(
-                    "Successfully submitted BTC/USD price update to Sui. Digest: {}",
-                    digest
-                ),
-                Err(e) => log::error!("Failed to submit BTC/USD price update to Sui: {:?}", e),
+/// Publishes the updates the previous run left pending that are still fresh, before the
+/// first cycle.
+async fn publish_outbox(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    rng: &mut cycle::CycleRng,
+) {
+    let (fresh, expired) = match monitoring.outbox.drain(clock_drift::local_now_ms() as u64) {
+        Ok(drained) => drained,
+        Err(e) => {
+            log::error!("Failed to read the outbox: {:?}", e);
+            return;
+        }
+    };
+    for entry in &expired {
+        log::warn!(
+            "Dropping pending {} update from the outbox: older than {} s",
+            entry.price_info.symbol,
+            settings.outbox.max_age_seconds
+        );
+    }
+    if fresh.is_empty() {
+        return;
+    }
+    log::info!(
+        "Publishing {} pending updates left by the previous run",
+        fresh.len()
+    );
+    let updates: Vec<PreparedUpdate> = fresh
+        .into_iter()
+        .map(|price_info| PreparedUpdate {
+            aggregation: aggregator::AggregateResult {
+                method: "outbox".to_string(),
+                inputs: Vec::new(),
+                spread_pct: None,
+                confidence: None,
+                result: Some(price_info.price),
+            },
+            price_info,
+            sources: BTreeMap::new(),
+            manual_override: None,
+        })
+        .collect();
+    let symbols: Vec<String> = updates
+        .iter()
+        .map(|u| u.price_info.symbol.clone())
+        .collect();
+    let _publishing = monitoring.publishing.lock().await;
+    monitoring.publisher.begin_round();
+    let outcomes = submit_now(settings, monitoring, updates, rng).await;
+    for (symbol, outcome) in symbols.iter().zip(outcomes) {
+        log::info!("Pending {} update: {}", symbol, outcome.label());
+    }
+}
+
+/// Submits updates in programmable transaction blocks of up to `sui.max_batch_size` Move
+/// calls, records them for SLA tracking, captures a debug bundle for each failed one and
+/// mirrors them to the previous package during a migration window. Outcomes are in the
+/// order of `updates`.
+async fn submit_now(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    updates: Vec<PreparedUpdate<'_>>,
+    rng: &mut cycle::CycleRng,
+) -> Vec<cycle::SymbolOutcome> {
+    if updates.is_empty() {
+        return Vec::new();
+    }
+    let price_infos: Vec<chain::PriceInfo> = updates.iter().map(|u| u.price_info.clone()).collect();
+    let mut trace = chain::PublishTrace::new();
+    let results = monitoring
+        .publisher
+        .publish(price_infos.clone(), rng, &mut trace)
+        .await;
+    let mut outcomes = Vec::with_capacity(updates.len());
+    for (update, result) in updates.iter().zip(results) {
+        // An expired update is settled too: it is never to be published.
+        if result.is_ok()
+            && let Err(e) = monitoring.outbox.settle(&update.price_info)
+        {
+            log::error!(
+                "Failed to remove a published update from the outbox: {:?}",
+                e
+            );
+        }
+        outcomes.push(record_submission(
+            settings, monitoring, update, result, &trace,
+        ));
+    }
+    if let Some(previous) = settings.sui.previous_package(chrono::Utc::now()) {
+        publish_to_previous_package(&previous, monitoring, price_infos, rng).await;
+    }
+    outcomes
+}
+
+/// Turns the result of submitting `update` into its cycle outcome, recording a published
+/// update for SLA tracking and spacing and capturing a debug bundle for a failed one.
+fn record_submission(
+    settings: &config::Settings,
+    monitoring: &Monitoring,
+    update: &PreparedUpdate,
+    result: Result<chain::Submission>,
+    trace: &chain::PublishTrace,
+) -> cycle::SymbolOutcome {
+    let symbol = update.price_info.symbol.as_str();
+    let _feed = tracing::error_span!("feed", symbol).entered();
+    let outcome = match result {
+        Ok(chain::Submission::Duplicate) => cycle::SymbolOutcome::Skipped {
+            reason: "same payload as the last submitted update".to_string(),
+        },
+        Ok(chain::Submission::Expired { age_ms }) => {
+            log::warn!(
+                "Abandoned {} price update {} ms old; fetching fresh prices",
+                symbol,
+                age_ms
+            );
+            monitoring.refetch.notify_one();
+            cycle::SymbolOutcome::Withheld {
+                reason: format!("expired after {} ms without landing", age_ms),
             }
-        } else {
-            log::warn!("Could not aggregate BTC/USD price. Not enough data.");
         }
+        Ok(chain::Submission::Published(receipt)) => {
+            let _publish =
+                tracing::error_span!("publish", digest = receipt.digest.as_str()).entered();
+            log::info!(
+                "Successfully submitted {} price update to Sui. Digest: {}",
+                symbol,
+                receipt.digest
+            );
+            monitoring
+                .sla
+                .record_update(symbol, clock_drift::local_now_ms() as u64);
+            monitoring
+                .publish_limiter
+                .record_published(symbol, Instant::now());
+            monitoring.publish_policy.record_published(
+                symbol,
+                aggregator::as_f64(update.price_info.price),
+                update.price_info.manual_override,
+                Instant::now(),
+            );
+            monitoring
+                .fees
+                .observe_published(symbol, aggregator::as_f64(update.price_info.price));
+            let gas_usd = monitoring.fees.record(
+                symbol,
+                &receipt.digest,
+                receipt.gas_used_mist,
+                clock_drift::local_now_ms() as u64,
+            );
+            sla::record_publish_metrics(
+                &monitoring.metrics,
+                symbol,
+                receipt.gas_used_mist,
+                gas_usd,
+            );
+            push_update(monitoring, update, &receipt.digest);
+            cycle::SymbolOutcome::Published {
+                digest: receipt.digest,
+                gas_used_mist: receipt.gas_used_mist,
+                gas_usd,
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to submit {} price update to Sui: {:?}", symbol, e);
+            if settings.debug_bundles.enabled {
+                let bundle = debug_bundle::DebugBundle {
+                    reason: "publish_failed",
+                    symbol,
+                    captured_at_ms: clock_drift::local_now_ms() as u64,
+                    error: format!("{:?}", e),
+                    sources: update.sources.clone(),
+                    aggregation: &update.aggregation,
+                    publish_trace: trace,
+                };
+                match debug_bundle::capture(&settings.debug_bundles, &bundle) {
+                    Ok(dir) => log::warn!("Captured debug bundle at {}", dir.display()),
+                    Err(e) => log::warn!("Failed to capture debug bundle: {:?}", e),
+                }
+            }
+            cycle::SymbolOutcome::Failed {
+                error: format!("{:#}", e),
+            }
+        }
+    };
+    if let Some(entry) = &update.manual_override {
+        monitoring
+            .overrides
+            .record_use(entry, clock_drift::local_now_ms() as u64, outcome.label());
+    }
+    record_price_history(monitoring, update, &outcome);
+    outcome
+}
 
-        let eth_binance_symbol = settings
-            .apis
-            .binance
-            .symbols
-            .iter()
-            .find(|s| s.contains("ETH"))
-            .map(|s| s.as_str());
-        let eth_coinbase_symbol = settings
-            .apis
-            .coinbase
-            .symbols
+/// Sends a published price to the `/ws` subscribers.
+#[cfg(feature = "http-api")]
+fn push_update(monitoring: &Monitoring, update: &PreparedUpdate, digest: &str) {
+    let price_info = &update.price_info;
+    let sources = update
+        .aggregation
+        .inputs
+        .iter()
+        .filter(|input| input.included)
+        .filter_map(|input| {
+            let observed_at_ms = update
+                .sources
+                .get(input.source.as_str())
+                .copied()
+                .flatten()
+                .zip(input.exchange_symbol.as_ref())
+                .and_then(|(prices, ticker)| prices.get(ticker)?.timestamp_ms)
+                .map(|ms| ms as u64);
+            Some(schema::SourcePrice {
+                source: input.source.clone(),
+                price: aggregator::as_f64(input.price?),
+                observed_at_ms,
+            })
+        })
+        .collect();
+    let decimals = monitoring.publisher.decimals(&price_info.symbol);
+    let scaled_price = match sui_publisher::scale_price(
+        price_info.price,
+        decimals,
+        monitoring.publisher.price_rounding(),
+    ) {
+        Ok(scaled_price) => scaled_price,
+        Err(e) => {
+            log::error!("Not pushing {} update: {:?}", price_info.symbol, e);
+            return;
+        }
+    };
+    let object_id = monitoring
+        .publisher
+        .known_object(&price_info.symbol)
+        .ok()
+        .flatten();
+    monitoring
+        .push
+        .publish(schema::WsMessage::PriceUpdate(schema::PriceUpdate {
+            schema_version: schema::SCHEMA_VERSION.to_string(),
+            symbol: price_info.symbol.clone(),
+            price: aggregator::as_f64(price_info.price),
+            scaled_price,
+            decimals,
+            timestamp_ms: price_info.timestamp_ms,
+            sources,
+            object_id,
+            tx_digest: Some(digest.to_string()),
+        }));
+}
+
+#[cfg(not(feature = "http-api"))]
+fn push_update(_monitoring: &Monitoring, _update: &PreparedUpdate, _digest: &str) {}
+
+/// Records an aggregated price and its outcome in the price history.
+#[cfg(feature = "storage-sql")]
+fn record_price_history(
+    monitoring: &Monitoring,
+    update: &PreparedUpdate,
+    outcome: &cycle::SymbolOutcome,
+) {
+    let Some(history) = &monitoring.history else {
+        return;
+    };
+    if let Err(e) = history.record_price(
+        clock_drift::local_now_ms() as u64,
+        &update.price_info,
+        update.aggregation.contributing(),
+        outcome,
+    ) {
+        log::error!("Failed to record price history: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "storage-sql"))]
+fn record_price_history(
+    _monitoring: &Monitoring,
+    _update: &PreparedUpdate,
+    _outcome: &cycle::SymbolOutcome,
+) {
+}
+
+/// Records the samples of a fetch in the price history and drops those past retention.
+#[cfg(feature = "storage-sql")]
+fn record_sample_history(monitoring: &Monitoring, fetched: &[sources::SourceData], now_ms: u64) {
+    let Some(history) = &monitoring.history else {
+        return;
+    };
+    for data in fetched {
+        if let Some(prices) = &data.prices
+            && let Err(e) = history.record_samples(now_ms, data.source.name(), prices)
+        {
+            log::error!("Failed to record sample history: {:?}", e);
+        }
+    }
+    match history.prune(now_ms) {
+        Ok(0) => {}
+        Ok(rows) => log::info!("Pruned {} price history rows past retention", rows),
+        Err(e) => log::error!("Failed to prune price history: {:?}", e),
+    }
+}
+
+#[cfg(not(feature = "storage-sql"))]
+fn record_sample_history(_monitoring: &Monitoring, _fetched: &[sources::SourceData], _now_ms: u64) {
+}
+
+/// Flags feeds past their SLA's `max_staleness_ms` stale on-chain, once per stale period:
+/// a feed back within it is forgotten, so its next stale period is flagged again. The
+/// feed's next update is published whatever its deviation, so that it clears the flag.
+async fn mark_stale_feeds(monitoring: &Monitoring, statuses: &[sla::SlaStatus]) {
+    let due: Vec<&str> = {
+        let mut marked = monitoring.marked_stale.lock().unwrap();
+        statuses
             .iter()
-            .find(|s| s.contains("ETH"))
-            .map(|s| s.as_str());
-        let eth_price_binance = eth_binance_symbol.and_then(|sym| {
-            binance_prices_map
-                .as_ref()
-                .and_then(|m| parse_price(m.get(sym)))
-        });
-        let eth_price_coinbase = eth_coinbase_symbol.and_then(|sym| {
-            coinbase_prices_map
-                .as_ref()
-                .and_then(|m| parse_price(m.get(sym)))
-        });
+            .filter(|status| {
+                if !status.is_stale() {
+                    marked.remove(&status.symbol);
+                }
+                status.is_stale() && !marked.contains(&status.symbol)
+            })
+            .map(|status| status.symbol.as_str())
+            .collect()
+    };
+    if due.is_empty() {
+        return;
+    }
+    let _publishing = monitoring.publishing.lock().await;
+    monitoring.publisher.begin_round();
+    for symbol in due {
+        let mut trace = chain::PublishTrace::new();
+        let marked = monitoring
+            .publisher
+            .mark_stale(symbol, &mut trace)
+            .instrument(tracing::error_span!("feed", symbol))
+            .await;
+        match marked {
+            Ok(receipt) => {
+                if let Some(receipt) = receipt {
+                    log::warn!(
+                        "Marked {} stale on-chain. Digest: {}",
+                        symbol,
+                        receipt.digest
+                    );
+                    monitoring.fees.record(
+                        symbol,
+                        &receipt.digest,
+                        receipt.gas_used_mist,
+                        clock_drift::local_now_ms() as u64,
+                    );
+                    monitoring.metrics.inc_counter(
+                        "oracle_stale_markers_total",
+                        "Stale flags published on-chain",
+                        &[("symbol", symbol)],
+                        1.0,
+                    );
+                }
+                monitoring.publish_policy.forget(symbol);
+                monitoring
+                    .marked_stale
+                    .lock()
+                    .unwrap()
+                    .insert(symbol.to_string());
+            }
+            Err(e) => log::error!("Failed to mark {} stale on-chain: {:#}", symbol, e),
+        }
+    }
+}
 
-        let eth_prices_to_aggregate = [eth_price_binance, eth_price_coinbase];
-        if let Some(aggregated_eth_price) = aggregator::aggregate_prices(&eth_prices_to_aggregate) {
-            log::info!("Aggregated ETH/USD Price: {:.2}", aggregated_eth_price);
-            let eth_price_info = sui_publisher::PriceInfo {
-                symbol: "ETH/USD".to_string(), // Standardized symbol for on-chain
-                price: aggregated_eth_price,
-                timestamp_ms: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis() as u64,
-            };
-            match sui_publisher::submit_price_update(eth_price_info).await {
-                Ok(digest) => log::info!(
-                    "Successfully submitted ETH/USD price update to Sui. Digest: {}",
-                    digest
-                ),
-                Err(e) => log::error!("Failed to submit ETH/USD price update to Sui: {:?}", e),
+/// Mirrors updates to the previous package during a migration window. Results are logged
+/// and counted but do not affect the symbols' cycle outcomes.
+async fn publish_to_previous_package(
+    previous: &config::SuiSettings,
+    monitoring: &Monitoring,
+    price_infos: Vec<chain::PriceInfo>,
+    rng: &mut cycle::CycleRng,
+) {
+    let Some(publisher) = &monitoring.previous_publisher else {
+        return;
+    };
+    // Never create objects in the old package; only feeds consumers already read are kept.
+    let price_infos: Vec<chain::PriceInfo> = price_infos
+        .into_iter()
+        .filter(
+            |price_info| match publisher.known_object(&price_info.symbol) {
+                Ok(known) => known.is_some(),
+                Err(e) => {
+                    log::warn!("Skipping dual-write for {}: {:?}", price_info.symbol, e);
+                    false
+                }
+            },
+        )
+        .collect();
+    if price_infos.is_empty() {
+        return;
+    }
+    let symbols: Vec<String> = price_infos.iter().map(|p| p.symbol.clone()).collect();
+    let mut trace = chain::PublishTrace::new();
+    let results = publisher.publish(price_infos, rng, &mut trace).await;
+    for (symbol, result) in symbols.iter().zip(results) {
+        let outcome = match result {
+            Ok(chain::Submission::Duplicate) => "duplicate",
+            Ok(chain::Submission::Expired { .. }) => "expired",
+            Ok(chain::Submission::Published(receipt)) => {
+                log::info!(
+                    "Dual-wrote {} to previous package {}. Digest: {}",
+                    symbol,
+                    previous.package_id,
+                    receipt.digest
+                );
+                "published"
             }
-        } else {
-            log::warn!("Could not aggregate ETH/USD price. Not enough data.");
+            Err(e) => {
+                log::warn!(
+                    "Dual-write of {} to previous package {} failed: {:#}",
+                    symbol,
+                    previous.package_id,
+                    e
+                );
+                "failed"
+            }
+        };
+        monitoring.metrics.inc_counter(
+            "oracle_migration_publish_total",
+            "Updates mirrored to the previous package during a migration window",
+            &[("symbol", symbol), ("outcome", outcome)],
+            1.0,
+        );
+    }
+}
+
+#[cfg(feature = "http-api")]
+fn spawn_http_api(settings: &config::Settings, monitoring: &Monitoring, info: schema::RuntimeInfo) {
+    let state = Arc::new(http_api::ApiState {
+        info,
+        metrics: monitoring.metrics.clone(),
+        overrides: monitoring.overrides.clone(),
+        symbols: monitoring.symbols.clone(),
+        health: monitoring.health.clone(),
+        push: monitoring.push.clone(),
+        reconciler: monitoring.reconciler.clone(),
+        prices: response_cache::ResponseCache::new(Duration::from_secs(
+            settings.general.fetch_interval_seconds,
+        )),
+        admin_tokens: http_api::admin_tokens(&settings.admin),
+        keystore_file: settings.sui.keystore_file.clone(),
+        fix_keystore_permissions: settings.sui.fix_keystore_permissions,
+        max_connections: settings.limits.max_http_connections,
+    });
+    let bind_addr = settings.http.bind_addr.clone();
+    tokio::spawn(async move {
+        if let Err(e) = http_api::serve(&bind_addr, state).await {
+            log::error!("{:?}", e);
+        }
+    });
+}
+
+#[cfg(not(feature = "http-api"))]
+fn spawn_http_api(
+    _settings: &config::Settings,
+    _monitoring: &Monitoring,
+    _info: schema::RuntimeInfo,
+) {
+    log::warn!("http.enabled is set, but this build has no HTTP API (feature `http-api`)");
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let settings = config::Settings::load();
+    redaction::init_logger(
+        settings.as_ref().ok().map(|s| &s.logging),
+        settings
+            .as_ref()
+            .map_or(chrono_tz::Tz::UTC, |s| s.reporting_timezone()),
+        settings
+            .as_ref()
+            .map(|s| s.instance.labels.clone())
+            .unwrap_or_default(),
+    )?;
+    let args = cli::Cli::parse();
+    // Every command reads the state files, so an upgraded deployment is migrated before any
+    // of them runs. A configuration error is reported by the command itself.
+    if let Ok(settings) = settings {
+        let steps = state::migrate(
+            &state::StateFiles::from_settings(&settings),
+            std::path::Path::new("."),
+            chrono::Utc::now(),
+        )
+        .context("Failed to migrate legacy state files")?;
+        for step in steps {
+            log::info!("State migration: {}", step);
         }
+    }
+
+    match args.command.unwrap_or(cli::Command::Run) {
+        cli::Command::Run => run_oracle().await,
+        cli::Command::ChainStatus { json } => cli::chain_status(json).await,
+        cli::Command::Objects { command } => cli::objects(command).await,
+        cli::Command::Consumers { command } => cli::consumers(command).await,
+        cli::Command::Assets { command } => cli::assets(command).await,
+        cli::Command::Bootstrap { continue_on_error } => cli::bootstrap(continue_on_error).await,
+        cli::Command::Accounts { json } => cli::accounts(json).await,
+        cli::Command::Loadtest {
+            symbols,
+            cycles,
+            target,
+            rpc_latency_ms,
+            seed,
+        } => {
+            loadtest::run(loadtest::Options {
+                symbols,
+                cycles,
+                target,
+                rpc_latency_ms,
+                seed,
+            })
+            .await
+        }
+        cli::Command::InstallService {
+            name,
+            user,
+            env,
+            force,
+            print,
+        } => cli::install_service(name, user, &env, force, print),
+        cli::Command::UninstallService { name } => cli::uninstall_service(&name),
+        cli::Command::Quarantine { command } => cli::quarantine(command),
+        cli::Command::VerifyPackage { json } => cli::verify_package(json).await,
+        cli::Command::GenBindings { lang, output } => cli::gen_bindings(lang, output),
+        cli::Command::State { command } => cli::state(command),
+        cli::Command::ConfigHistory { symbol, json } => cli::config_history(&symbol, json),
+    }
+}
 
+async fn run_oracle() -> Result<()> {
+    log::info!("Neo Oracle MVP starting");
+
+    let settings = match config::Settings::load() {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("Failed to load configuration: {}", e);
+            return Err(anyhow::anyhow!("Configuration loading failed: {}", e));
+        }
+    };
+    let settings = symbol_registry::with_registered(settings)?;
+    log::info!("Configuration loaded successfully.");
+
+    // Fails startup on an unprotected keystore rather than on the first publish.
+    let publisher_address = sui_publisher::publisher_address(&settings.sui)
+        .context("Failed to load publisher key")?
+        .to_string();
+    let started_at_ms = clock_drift::local_now_ms() as u64;
+    let runtime_info = runtime_info::collect(&settings, &publisher_address, started_at_ms)?;
+    runtime_info::log_startup_banner(&runtime_info);
+    let clock_drift = Arc::new(clock_drift::ClockDriftTracker::new());
+    let source_endpoints = Arc::new(endpoints::EndpointSelector::new());
+    let http_client = sources::http_client(&settings.http_client)?;
+    let mut price_sources =
+        sources::from_settings(&settings, &http_client, &clock_drift, &source_endpoints);
+    match config_history::record(
+        &settings.general.config_history_file,
+        started_at_ms,
+        &feed_configs(&settings, &price_sources),
+    ) {
+        Ok(changed) if !changed.is_empty() => {
+            log::info!("Feed configuration changed for: {}", changed.join(", "))
+        }
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to record feed config history: {:?}", e),
+    }
+
+    index::validate(&settings.indices, &settings.feed_symbols())?;
+    let indices: Vec<&str> = settings.indices.keys().map(String::as_str).collect();
+    cross_rate::validate(&settings.crosses, &settings.feed_symbols(), &indices)?;
+    let usd_feed = &settings.fees.usd_feed;
+    if !usd_feed.is_empty() && !settings.feed_symbols().contains(&usd_feed.as_str()) {
+        log::warn!(
+            "fees.usd_feed {} is not a feed; gas is reported in MIST only until it is registered",
+            usd_feed
+        );
+    }
+    let calendars = calendar::CalendarRegistry::from_settings(
+        &settings.calendars,
+        &settings.symbols,
+        settings.reporting_timezone(),
+    )?;
+    if let Some(expected) = &settings.sui.expected_package_digest {
+        let verified = sui_publisher::package_digests(&settings.sui)
+            .await
+            .and_then(|digests| digests.verify(expected));
+        if let Err(e) = verified {
+            let alerter =
+                alerts::Alerter::new(&settings.alerts, settings.limits.max_pending_alerts);
+            alerter.send(&alerts::Alert {
+                severity: alerts::Severity::Critical,
+                kind: "package_mismatch",
+                symbol: settings.sui.package_id.clone(),
+                message: format!("refusing to publish: {:#}", e),
+                timestamp_ms: clock_drift::local_now_ms() as u64,
+            });
+            alerter.flush().await;
+            return Err(e.context("Package verification failed"));
+        }
         log::info!(
-            "--- Waiting for next fetch cycle ({} seconds) ---",
-            settings.general.fetch_interval_seconds
+            "Package {} matches sui.expected_package_digest",
+            settings.sui.package_id
+        );
+    }
+    // Fails startup on a package whose functions the oracle cannot call.
+    let feed_decimals = settings.feed_decimals();
+    let publisher = sui_publisher::SuiPublisher::connect(settings.sui.clone())
+        .await?
+        .with_decimals(feed_decimals.clone());
+    publisher.check_decimals().await?;
+    let capabilities = publisher.capabilities();
+    log::info!("Publishing to {}", capabilities);
+    let publisher = Arc::new(publisher);
+    let previous_publisher = match settings.sui.previous_package(chrono::Utc::now()) {
+        Some(previous) => Some(Box::new(
+            sui_publisher::SuiPublisher::connect(previous)
+                .await?
+                .with_decimals(feed_decimals)
+                .sharing_gas_with(&publisher),
+        ) as Box<dyn ChainBackend>),
+        None => None,
+    };
+    let settings = Arc::new(settings);
+    let publishing = Arc::new(tokio::sync::Mutex::new(()));
+    let symbols = Arc::new(symbol_registry::SymbolRegistry::new(
+        settings.clone(),
+        publisher.clone(),
+        publishing.clone(),
+        http_client.clone(),
+        clock_drift.clone(),
+        source_endpoints.clone(),
+    )?);
+    let metrics = Arc::new(
+        metrics::Metrics::with_settings(settings.metrics.clone())
+            .with_instance_labels(&settings.instance.labels),
+    );
+    for (name, exchange) in settings.apis.exchanges() {
+        if exchange.stream_url.is_some() && !cfg!(feature = "ws-sources") {
+            log::warn!(
+                "apis.{}.stream_url is set, but this build has no WebSocket streams (feature `ws-sources`); polling REST",
+                name
+            );
+        }
+    }
+    if settings.storage.enabled && !cfg!(feature = "storage-sql") {
+        log::warn!(
+            "storage.enabled is set, but this build has no price history (feature `storage-sql`)"
         );
-        sleep(Duration::from_secs(settings.general.fetch_interval_seconds)).await;
     }
+    let policy = Arc::new(publish_policy::PublishPolicy::new());
+    #[cfg(feature = "http-api")]
+    let reconciler = Arc::new(reconcile::Reconciler {
+        publisher: publisher.clone(),
+        policy: policy.clone(),
+        symbols: symbols.clone(),
+        publishing: publishing.clone(),
+    });
+    let monitoring = Monitoring {
+        sla: sla::SlaTracker::new(&settings.symbols, &calendars, started_at_ms)
+            .with_max_history(settings.limits.max_history_per_symbol),
+        calendars,
+        overrides: Arc::new(overrides::OverrideStore::new(&settings.admin)),
+        metrics: metrics.clone(),
+        alerter: alerts::Alerter::new(&settings.alerts, settings.limits.max_pending_alerts),
+        digest: settings
+            .digest
+            .enabled
+            .then(|| {
+                digest::Digest::new(
+                    &settings.digest,
+                    settings.reporting_timezone(),
+                    chrono::Utc::now(),
+                )
+            })
+            .transpose()
+            .context("Invalid digest send time")?,
+        fees: fees::FeeTracker::new(settings.fees.clone()),
+        #[cfg(feature = "storage-sql")]
+        history: settings
+            .storage
+            .enabled
+            .then(|| storage::HistoryStore::open(&settings.storage))
+            .transpose()?,
+        health: Arc::new(health::Health::new(settings.health.clone(), started_at_ms)),
+        shadow: shadow::ShadowTracker::new(),
+        divergent: Mutex::new(HashSet::new()),
+        off_reference: Mutex::new(HashSet::new()),
+        out_of_bounds: Mutex::new(HashSet::new()),
+        unscalable: Mutex::new(HashSet::new()),
+        marked_stale: Mutex::new(HashSet::new()),
+        publish_limiter: publish_limiter::PublishLimiter::new(),
+        publish_policy: policy,
+        publishing,
+        refetch: tokio::sync::Notify::new(),
+        publisher,
+        capabilities,
+        previous_publisher,
+        #[cfg(feature = "http-api")]
+        reconciler,
+        clock_drift: clock_drift.clone(),
+        recovery: source_recovery::RecoveryTracker::new(),
+        quarantine: quarantine::QuarantineStore::new(&settings.quarantine.file),
+        transports: transport::TransportSelector::new(settings.streams.clone(), metrics.clone()),
+        outbox: outbox::Outbox::open(
+            &settings.outbox.file,
+            settings.outbox.max_age_seconds * 1000,
+        )?,
+        symbols,
+        #[cfg(feature = "http-api")]
+        push: Arc::new(push::PushHub::new(settings.limits.max_ws_queue, metrics)),
+    };
+    let monitoring = Arc::new(monitoring);
+    let base_seed = settings
+        .general
+        .random_seed
+        .unwrap_or_else(cycle::random_base_seed);
+    log::info!(
+        "Random seed: {} (set general.random_seed to reproduce this run)",
+        base_seed
+    );
+    let mut background_rng = cycle::CycleRng::for_background(base_seed);
+    log::info!(
+        "Seed for the outbox and queued updates: {}",
+        background_rng.seed()
+    );
+    publish_outbox(&settings, &monitoring, &mut background_rng).await;
+    tokio::spawn(publish_queued_updates(
+        settings.clone(),
+        monitoring.clone(),
+        background_rng,
+    ));
+
+    if settings.http.enabled {
+        spawn_http_api(&settings, &monitoring, runtime_info);
+    }
+
+    log::info!("Starting main loop...");
+    let mut migration_ended = false;
+
+    if settings.general.startup_burst_fetches > 0 {
+        let quarantined = monitoring.quarantine.load().unwrap_or_else(|e| {
+            log::error!("Failed to load the quarantine: {:?}", e);
+            quarantine::QuarantineState::default()
+        });
+        let admitted = admitted_sources(
+            &price_sources,
+            &quarantined,
+            clock_drift::local_now_ms() as u64,
+        );
+        log::info!(
+            "Seeding source estimates with {} fetches {} ms apart before the first publish",
+            settings.general.startup_burst_fetches,
+            settings.general.startup_burst_spacing_ms
+        );
+        let answered = sources::burst_fetch(
+            &admitted,
+            settings.general.startup_burst_fetches,
+            Duration::from_millis(settings.general.startup_burst_spacing_ms),
+            Duration::from_millis(settings.general.source_timeout_ms),
+        )
+        .await;
+        for (source, rounds) in answered {
+            log::info!(
+                "Startup burst: {} answered {} of {} fetches",
+                source,
+                rounds,
+                settings.general.startup_burst_fetches
+            );
+        }
+    }
+
+    let mut sources_settings = settings.clone();
+    for cycle_number in 0u64.. {
+        let settings = monitoring.symbols.settings();
+        if !Arc::ptr_eq(&settings, &sources_settings) {
+            // A feed was registered: fetch its tickers from this cycle on.
+            price_sources =
+                sources::from_settings(&settings, &http_client, &clock_drift, &source_endpoints);
+            if let Err(e) = config_history::record(
+                &settings.general.config_history_file,
+                clock_drift::local_now_ms() as u64,
+                &feed_configs(&settings, &price_sources),
+            ) {
+                log::error!("Failed to record feed config history: {:?}", e);
+            }
+            sources_settings = settings.clone();
+        }
+        // At error level, so that its field is on every line logged at all.
+        async {
+            let mut rng = cycle::CycleRng::for_cycle(base_seed, cycle_number);
+            log::info!(
+                "--- Fetching new prices (cycle {}, seed {}) ---",
+                cycle_number,
+                rng.seed()
+            );
+
+            let now_ms = clock_drift::local_now_ms() as u64;
+            let quarantined = monitoring
+                .quarantine
+                .update(|state| {
+                    for entry in state.expire(now_ms) {
+                        log::info!(
+                            "{} re-admitted on probation after quarantine ({})",
+                            entry.subject(),
+                            entry.reason
+                        );
+                    }
+                    state.clone()
+                })
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to load the quarantine: {:?}", e);
+                    quarantine::QuarantineState::default()
+                });
+            for source in &price_sources {
+                let subject = quarantine::Subject::source(source.name());
+                monitoring.metrics.set_gauge(
+                    "oracle_source_circuit_open",
+                    "1 while the source is quarantined and not fetched",
+                    &[("source", source.name())],
+                    quarantined.active(&subject, now_ms).map_or(0.0, |_| 1.0),
+                );
+            }
+            let admitted = admitted_sources(&price_sources, &quarantined, now_ms);
+            let fetch_started = std::time::Instant::now();
+            let fetched = sources::fetch_all(
+                &admitted,
+                |source| monitoring.transports.choose(source, fetch_started),
+                |source| liquidity_tickers(&settings, source.name()),
+                |source| volume_tickers(&settings, source.name()),
+                |source| quote_tickers(&settings, source.name()),
+                Duration::from_millis(settings.general.source_timeout_ms),
+            )
+            .await;
+            for data in &fetched {
+                monitoring.recovery.observe(
+                    data.source.name(),
+                    data.prices.is_some(),
+                    settings.aggregation.recovery_ramp_cycles,
+                );
+            }
+            record_sample_history(&monitoring, &fetched, clock_drift::local_now_ms() as u64);
+
+            if let Some(migration) = &settings.sui.migration
+                && !migration_ended
+                && chrono::Utc::now() >= migration.ends_at
+            {
+                log::info!(
+                    "Migration window ended at {}; publishing to package {} only",
+                    migration.ends_at,
+                    settings.sui.package_id
+                );
+                migration_ended = true;
+            }
+
+            let mut summary = cycle::CycleSummary::new();
+            let feed_symbols = settings.feed_symbols();
+            let symbols: Vec<&str> = feed_symbols
+                .iter()
+                .copied()
+                .chain(settings.computed_feeds())
+                .collect();
+            let paused = cycle::paused_symbols(
+                &symbols,
+                &settings.symbols,
+                settings.limits.max_symbols_per_cycle,
+            );
+            if !paused.is_empty() {
+                log::warn!(
+                    "{} symbols configured but limits.max_symbols_per_cycle is {}; pausing {} low-priority symbols",
+                    symbols.len(),
+                    settings.limits.max_symbols_per_cycle,
+                    paused.len()
+                );
+            }
+            let paused_outcome = || cycle::SymbolOutcome::Skipped {
+                reason: "paused: max_symbols_per_cycle reached".to_string(),
+            };
+            let mut pending: Vec<(String, Pending)> = Vec::new();
+            // Prices submitted this cycle, the inputs of index and cross-rate feeds.
+            let mut published_prices = HashMap::new();
+            for &symbol in &feed_symbols {
+                let outcome = if paused.contains(symbol) {
+                    paused_outcome().into()
+                } else if let Some(outcome) = breaker_open(&quarantined, symbol, now_ms) {
+                    outcome.into()
+                } else {
+                    let prepared =
+                        prepare_feed(&settings, &monitoring, symbol, &fetched, &quarantined)
+                            .instrument(tracing::error_span!("feed", symbol))
+                            .await;
+                    if let Some(price) = prepared.price {
+                        published_prices.insert(symbol.to_string(), price);
+                    }
+                    pending.extend(prepared.derived);
+                    prepared.outcome
+                };
+                pending.push((symbol.to_string(), outcome));
+            }
+            for (name, index) in &settings.indices {
+                let outcome = if paused.contains(name.as_str()) {
+                    paused_outcome().into()
+                } else if let Some(outcome) = breaker_open(&quarantined, name, now_ms) {
+                    outcome.into()
+                } else {
+                    tracing::error_span!("feed", symbol = name.as_str()).in_scope(|| {
+                        prepare_index(&settings, &monitoring, name, index, &published_prices)
+                    })
+                };
+                pending.push((name.clone(), outcome));
+            }
+            for (name, cross) in &settings.crosses {
+                let outcome = if paused.contains(name.as_str()) {
+                    paused_outcome().into()
+                } else if let Some(outcome) = breaker_open(&quarantined, name, now_ms) {
+                    outcome.into()
+                } else {
+                    tracing::error_span!("feed", symbol = name.as_str()).in_scope(|| {
+                        prepare_cross(&settings, &monitoring, name, cross, &published_prices)
+                    })
+                };
+                pending.push((name.clone(), outcome));
+            }
+            let publishing = monitoring.publishing.lock().await;
+            // A round per cycle: each transaction reserves its own gas coin, so a failed one
+            // cannot hand a stale coin to the next.
+            monitoring.publisher.begin_round();
+            let outcomes = submit_updates(&settings, &monitoring, pending, &mut rng).await;
+            drop(publishing);
+            record_failures(&settings, &monitoring, &fetched, &outcomes);
+            for (symbol, outcome) in outcomes {
+                monitoring.metrics.inc_counter(
+                    "oracle_cycle_symbol_outcomes_total",
+                    "Per-symbol cycle outcomes",
+                    &[("symbol", &symbol), ("outcome", outcome.label())],
+                    1.0,
+                );
+                if let Some(digest) = &monitoring.digest {
+                    digest.record(&symbol, &outcome);
+                }
+                summary.record(&symbol, outcome);
+            }
+            summary.log();
+
+            let now_ms = clock_drift::local_now_ms() as u64;
+            let cycle_result = if fetched.iter().all(|data| data.prices.is_none()) {
+                Err("no source delivered prices".to_string())
+            } else {
+                summary.failure().map_or(Ok(()), Err)
+            };
+            monitoring.health.record_cycle(now_ms, cycle_result);
+            if monitoring.health.chain_check_due(now_ms) {
+                let balance = monitoring.publisher.gas_balance().await;
+                if let Err(e) = &balance {
+                    log::warn!("Sui RPC check failed: {:#}", e);
+                }
+                monitoring
+                    .health
+                    .record_balance(now_ms, balance.map_err(|e| format!("{:#}", e)));
+            }
+            let statuses = monitoring.sla.evaluate(now_ms);
+            sla::export_metrics(&monitoring.metrics, &statuses);
+            source_endpoints.export_metrics(&monitoring.metrics);
+            monitoring.publisher.export_metrics(&monitoring.metrics);
+            monitoring.fees.export_metrics(&monitoring.metrics);
+            monitoring.shadow.export_metrics(&monitoring.metrics);
+            for alert in statuses.iter().filter_map(|s| s.alert(now_ms)) {
+                monitoring.alerter.send(&alert);
+            }
+            if monitoring.capabilities.stale_marking {
+                mark_stale_feeds(&monitoring, &statuses).await;
+            }
+            if let Some(digest) = &monitoring.digest {
+                digest.record_sla(&statuses);
+                if let Some(report) = digest.take_due(chrono::Utc::now()) {
+                    let incidents = monitoring.alerter.take_incidents();
+                    monitoring
+                        .alerter
+                        .send(&report.alert(&settings.sui.package_id, &incidents));
+                }
+            }
+
+            let jitter_ms = rng.jitter_ms(settings.general.fetch_jitter_ms);
+            log::info!(
+                "--- Waiting for next fetch cycle ({} seconds + {} ms jitter) ---",
+                settings.general.fetch_interval_seconds,
+                jitter_ms
+            );
+            tokio::select! {
+                _ = sleep(
+                    Duration::from_secs(settings.general.fetch_interval_seconds)
+                        + Duration::from_millis(jitter_ms),
+                ) => {}
+                _ = monitoring.refetch.notified() => {
+                    log::info!("An update expired before landing; starting the next cycle now");
+                }
+            }
+        }
+        .instrument(tracing::error_span!("cycle", cycle = cycle_number))
+        .await;
+    }
+    Ok(())
 }
@@ -0,0 +1,309 @@
+// Rendering is only reachable through the HTTP API's /metrics route.
+#![cfg_attr(not(feature = "http-api"), allow(dead_code))]
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::config::MetricsSettings;
+
+/// Label value the rolled-up values of a capped label are counted under.
+const ROLLUP_VALUE: &str = "other";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        }
+    }
+}
+
+struct Family {
+    help: &'static str,
+    kind: Kind,
+    samples: BTreeMap<Vec<(String, String)>, f64>,
+    /// Values of each capped label that have a series of their own.
+    admitted: HashMap<String, BTreeSet<String>>,
+    rolling_up: bool,
+}
+
+/// Minimal in-process metrics registry rendered in the Prometheus text exposition format.
+/// Families are created on first use; there is no up-front registration.
+#[derive(Default)]
+pub struct Metrics {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+    settings: Option<MetricsSettings>,
+    /// `instance.labels`, rendered on every series.
+    instance_labels: Vec<(String, String)>,
+}
+
+fn label_key(labels: &[(&str, &str)]) -> Vec<(String, String)> {
+    labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// The series `labels` are recorded under, with the capped labels `settings` does not admit
+/// rolled up; `None` for a gauge, whose values cannot be combined.
+fn series_key(
+    settings: &MetricsSettings,
+    family: &mut Family,
+    labels: &[(&str, &str)],
+) -> Option<Vec<(String, String)>> {
+    let mut key = label_key(labels);
+    for (label, value) in key.iter_mut() {
+        if !settings.rollup_labels.contains(label) {
+            continue;
+        }
+        let admitted = family.admitted.entry(label.clone()).or_default();
+        let admit = if settings.labelled_values.is_empty() {
+            admitted.contains(value.as_str())
+                || settings.max_label_values == 0
+                || admitted.len() < settings.max_label_values
+        } else {
+            settings.labelled_values.contains(value)
+        };
+        if admit {
+            admitted.insert(value.clone());
+            continue;
+        }
+        if family.kind == Kind::Gauge {
+            return None;
+        }
+        *value = ROLLUP_VALUE.to_string();
+    }
+    Some(key)
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    /// A registry without label caps.
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry whose capped labels keep at most the values `settings` admits.
+    pub fn with_settings(settings: MetricsSettings) -> Self {
+        Self {
+            families: Mutex::default(),
+            settings: Some(settings),
+            instance_labels: Vec::new(),
+        }
+    }
+
+    /// The registry with `labels` on every series. A series' own label of the same name
+    /// takes precedence.
+    pub fn with_instance_labels(self, labels: &BTreeMap<String, String>) -> Self {
+        Self {
+            instance_labels: label_key(
+                &labels
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            ..self
+        }
+    }
+
+    fn update(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        kind: Kind,
+        labels: &[(&str, &str)],
+        apply: impl FnOnce(&mut f64),
+    ) {
+        if !cfg!(feature = "metrics") {
+            return;
+        }
+        let mut families = self.families.lock().unwrap();
+        let family = families.entry(name).or_insert_with(|| Family {
+            help,
+            kind,
+            samples: BTreeMap::new(),
+            admitted: HashMap::new(),
+            rolling_up: false,
+        });
+        debug_assert_eq!(family.kind, kind, "metric {} registered twice", name);
+        let key = match &self.settings {
+            Some(settings) => {
+                let key = series_key(settings, family, labels);
+                if !family.rolling_up && key.as_ref().is_none_or(|k| *k != label_key(labels)) {
+                    family.rolling_up = true;
+                    log::warn!(
+                        "Metric {} reached its cap of {} values; further ones are {}",
+                        name,
+                        settings.rollup_labels.join(", "),
+                        if kind == Kind::Gauge {
+                            "not exported"
+                        } else {
+                            "counted as \"other\""
+                        }
+                    );
+                }
+                key
+            }
+            None => Some(label_key(labels)),
+        };
+        if let Some(key) = key {
+            apply(family.samples.entry(key).or_insert(0.0));
+        }
+    }
+
+    pub fn set_gauge(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        self.update(name, help, Kind::Gauge, labels, |v| *v = value);
+    }
+
+    pub fn inc_counter(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        delta: f64,
+    ) {
+        self.update(name, help, Kind::Counter, labels, |v| *v += delta);
+    }
+
+    /// Renders every family in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind.as_str());
+            for (labels, value) in &family.samples {
+                let instance_labels = self
+                    .instance_labels
+                    .iter()
+                    .filter(|(k, _)| !labels.iter().any(|(own, _)| own == k));
+                let labels: Vec<_> = instance_labels.chain(labels).collect();
+                if labels.is_empty() {
+                    let _ = writeln!(out, "{} {}", name, value);
+                } else {
+                    let rendered: Vec<String> = labels
+                        .iter()
+                        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+                        .collect();
+                    let _ = writeln!(out, "{}{{{}}} {}", name, rendered.join(","), value);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_gauges_and_counters() {
+        let metrics = Metrics::new();
+        metrics.set_gauge("oracle_up", "Process is running", &[], 1.0);
+        metrics.inc_counter(
+            "oracle_updates_total",
+            "Updates",
+            &[("symbol", "BTC/USD")],
+            1.0,
+        );
+        metrics.inc_counter(
+            "oracle_updates_total",
+            "Updates",
+            &[("symbol", "BTC/USD")],
+            2.0,
+        );
+        metrics.set_gauge("oracle_up", "Process is running", &[], 0.0);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE oracle_up gauge\noracle_up 0\n"));
+        assert!(text.contains("# TYPE oracle_updates_total counter\n"));
+        assert!(text.contains("oracle_updates_total{symbol=\"BTC/USD\"} 3\n"));
+    }
+
+    #[test]
+    fn test_capped_labels_roll_up() {
+        let metrics = Metrics::with_settings(MetricsSettings {
+            max_label_values: 2,
+            ..Default::default()
+        });
+        for symbol in ["BTC/USD", "ETH/USD", "SOL/USD", "SUI/USD", "BTC/USD"] {
+            metrics.inc_counter("updates_total", "Updates", &[("symbol", symbol)], 1.0);
+            metrics.set_gauge(
+                "price",
+                "Price",
+                &[("symbol", symbol), ("source", "x")],
+                1.0,
+            );
+        }
+        let text = metrics.render();
+        assert!(text.contains("updates_total{symbol=\"BTC/USD\"} 2\n"));
+        assert!(text.contains("updates_total{symbol=\"ETH/USD\"} 1\n"));
+        assert!(text.contains("updates_total{symbol=\"other\"} 2\n"));
+        assert!(text.contains("price{symbol=\"ETH/USD\",source=\"x\"} 1\n"));
+        assert!(!text.contains("SOL/USD"));
+
+        let metrics = Metrics::with_settings(MetricsSettings {
+            labelled_values: vec!["SUI/USD".to_string()],
+            ..Default::default()
+        });
+        metrics.inc_counter("updates_total", "Updates", &[("symbol", "BTC/USD")], 1.0);
+        metrics.inc_counter("updates_total", "Updates", &[("symbol", "SUI/USD")], 1.0);
+        let text = metrics.render();
+        assert!(text.contains("updates_total{symbol=\"SUI/USD\"} 1\n"));
+        assert!(text.contains("updates_total{symbol=\"other\"} 1\n"));
+    }
+
+    #[test]
+    fn test_instance_labels_on_every_series() {
+        let labels = BTreeMap::from([
+            ("instance".to_string(), "oracle-a".to_string()),
+            ("source".to_string(), "ignored".to_string()),
+        ]);
+        let metrics = Metrics::default().with_instance_labels(&labels);
+        metrics.set_gauge("oracle_up", "Process is running", &[], 1.0);
+        metrics.set_gauge("price", "Price", &[("source", "binance")], 2.0);
+        let text = metrics.render();
+        assert!(
+            text.contains("oracle_up{instance=\"oracle-a\",source=\"ignored\"} 1\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("price{instance=\"oracle-a\",source=\"binance\"} 2\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        let metrics = Metrics::new();
+        metrics.set_gauge("m", "help", &[("reason", "a \"b\"\n")], 1.0);
+        assert!(
+            metrics
+                .render()
+                .contains("m{reason=\"a \\\"b\\\"\\n\"} 1\n")
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use anyhow::{Result, anyhow};
+use sui_sdk::rpc_types::{
+    SuiMoveNormalizedFunction, SuiMoveNormalizedModule, SuiMoveNormalizedType, SuiMoveVisibility,
+};
+
+/// A value the oracle can pass to one parameter of a Move call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgRole {
+    /// The feed's `PriceObject`, by reference.
+    PriceObject,
+    /// The feed symbol, as `vector<u8>` or `String`.
+    Symbol,
+    /// The scaled price (`u64`).
+    Price,
+    /// The price's timestamp in ms (`u64`).
+    TimestampMs,
+    /// The price scale (`u8`).
+    Decimals,
+    /// The scaled confidence of the price (`u64`), after its timestamp.
+    Confidence,
+    /// The shared `0x2::clock::Clock`.
+    Clock,
+}
+
+/// The arguments of one entry function, in parameter order, derived from its signature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallTemplate {
+    pub function: String,
+    pub args: Vec<ArgRole>,
+}
+
+/// How to call each function the oracle uses in the deployed package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleCalls {
+    pub create: CallTemplate,
+    pub update: CallTemplate,
+    /// `None` if the package has no `sui.manual_update_function`; overrides then fail.
+    pub manual_update: Option<CallTemplate>,
+    /// `None` unless `sui.stale_function` is set.
+    pub mark_stale: Option<CallTemplate>,
+    /// `None` unless `sui.confidence_function` is set.
+    pub update_with_confidence: Option<CallTemplate>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CallKind {
+    Create,
+    Update,
+    UpdateWithConfidence,
+    MarkStale,
+}
+
+impl CallKind {
+    /// The arguments the original package takes, which the oracle was written against; for
+    /// `MarkStale`, the signature the README documents.
+    fn default_args(self) -> &'static [ArgRole] {
+        match self {
+            CallKind::Create => &[
+                ArgRole::Symbol,
+                ArgRole::Price,
+                ArgRole::TimestampMs,
+                ArgRole::Decimals,
+            ],
+            CallKind::Update => &[ArgRole::PriceObject, ArgRole::Price, ArgRole::TimestampMs],
+            CallKind::UpdateWithConfidence => &[
+                ArgRole::PriceObject,
+                ArgRole::Price,
+                ArgRole::TimestampMs,
+                ArgRole::Confidence,
+            ],
+            CallKind::MarkStale => &[ArgRole::PriceObject, ArgRole::Clock],
+        }
+    }
+
+    fn accepts(self, role: ArgRole) -> bool {
+        match self {
+            CallKind::Create => role != ArgRole::PriceObject,
+            CallKind::Update | CallKind::UpdateWithConfidence => {
+                !matches!(role, ArgRole::Symbol | ArgRole::Decimals)
+            }
+            CallKind::MarkStale => matches!(role, ArgRole::PriceObject | ArgRole::Clock),
+        }
+    }
+}
+
+fn is_struct(t: &SuiMoveNormalizedType, struct_module: &str, struct_name: &str) -> bool {
+    matches!(t, SuiMoveNormalizedType::Struct { module, name, .. }
+        if module == struct_module && name == struct_name)
+}
+
+/// Move source syntax for a parameter type, for diagnostics.
+fn type_name(t: &SuiMoveNormalizedType) -> String {
+    match t {
+        SuiMoveNormalizedType::Bool => "bool".to_string(),
+        SuiMoveNormalizedType::U8 => "u8".to_string(),
+        SuiMoveNormalizedType::U16 => "u16".to_string(),
+        SuiMoveNormalizedType::U32 => "u32".to_string(),
+        SuiMoveNormalizedType::U64 => "u64".to_string(),
+        SuiMoveNormalizedType::U128 => "u128".to_string(),
+        SuiMoveNormalizedType::U256 => "u256".to_string(),
+        SuiMoveNormalizedType::Address => "address".to_string(),
+        SuiMoveNormalizedType::Signer => "signer".to_string(),
+        SuiMoveNormalizedType::Struct {
+            module,
+            name,
+            type_arguments,
+            ..
+        } if type_arguments.is_empty() => format!("{}::{}", module, name),
+        SuiMoveNormalizedType::Struct {
+            module,
+            name,
+            type_arguments,
+            ..
+        } => format!(
+            "{}::{}<{}>",
+            module,
+            name,
+            type_arguments
+                .iter()
+                .map(type_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        SuiMoveNormalizedType::Vector(inner) => format!("vector<{}>", type_name(inner)),
+        SuiMoveNormalizedType::TypeParameter(i) => format!("T{}", i),
+        SuiMoveNormalizedType::Reference(inner) => format!("&{}", type_name(inner)),
+        SuiMoveNormalizedType::MutableReference(inner) => format!("&mut {}", type_name(inner)),
+    }
+}
+
+fn signature(module: &str, name: &str, function: &SuiMoveNormalizedFunction) -> String {
+    format!(
+        "{}::{}({})",
+        module,
+        name,
+        function
+            .parameters
+            .iter()
+            .map(type_name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// What the oracle passes for one parameter.
+enum Param {
+    Value(ArgRole),
+    /// The `TxContext`, supplied by the runtime.
+    Implicit,
+    Unsupported,
+}
+
+/// `u64s` counts the `u64` parameters before this one: the first is the price, the second
+/// its timestamp and the third its confidence.
+fn param(module: &str, t: &SuiMoveNormalizedType, u64s: usize) -> Param {
+    use SuiMoveNormalizedType as T;
+    let role = match t {
+        T::Reference(inner) | T::MutableReference(inner)
+            if is_struct(inner, module, "PriceObject") =>
+        {
+            ArgRole::PriceObject
+        }
+        T::Reference(inner) if is_struct(inner, "clock", "Clock") => ArgRole::Clock,
+        T::Reference(inner) | T::MutableReference(inner)
+            if is_struct(inner, "tx_context", "TxContext") =>
+        {
+            return Param::Implicit;
+        }
+        T::Vector(inner) if matches!(**inner, T::U8) => ArgRole::Symbol,
+        t if is_struct(t, "string", "String") || is_struct(t, "ascii", "String") => ArgRole::Symbol,
+        T::U8 => ArgRole::Decimals,
+        T::U64 if u64s == 0 => ArgRole::Price,
+        T::U64 if u64s == 1 => ArgRole::TimestampMs,
+        T::U64 if u64s == 2 => ArgRole::Confidence,
+        _ => return Param::Unsupported,
+    };
+    Param::Value(role)
+}
+
+fn resolve(module: &SuiMoveNormalizedModule, name: &str, kind: CallKind) -> Result<CallTemplate> {
+    let function = module.exposed_functions.get(name).ok_or_else(|| {
+        anyhow!(
+            "{}::{} not found; the module exposes: {}",
+            module.name,
+            name,
+            module
+                .exposed_functions
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let signature = signature(&module.name, name, function);
+    if !function.is_entry && !matches!(function.visibility, SuiMoveVisibility::Public) {
+        return Err(anyhow!(
+            "{} is neither public nor entry and cannot be called",
+            signature
+        ));
+    }
+
+    let mut args = Vec::new();
+    let last = function.parameters.len().saturating_sub(1);
+    for (i, t) in function.parameters.iter().enumerate() {
+        let u64s = args
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r,
+                    ArgRole::Price | ArgRole::TimestampMs | ArgRole::Confidence
+                )
+            })
+            .count();
+        match param(&module.name, t, u64s) {
+            Param::Value(role) if kind.accepts(role) && !args.contains(&role) => args.push(role),
+            Param::Implicit if i == last => {}
+            _ => {
+                return Err(anyhow!(
+                    "{}: cannot supply parameter {} ({})",
+                    signature,
+                    i + 1,
+                    type_name(t)
+                ));
+            }
+        }
+    }
+
+    let missing = match kind {
+        CallKind::Create if !args.contains(&ArgRole::Symbol) => Some("the symbol"),
+        CallKind::Update | CallKind::UpdateWithConfidence | CallKind::MarkStale
+            if !args.contains(&ArgRole::PriceObject) =>
+        {
+            Some("the PriceObject")
+        }
+        CallKind::Update | CallKind::UpdateWithConfidence if !args.contains(&ArgRole::Price) => {
+            Some("a u64 price")
+        }
+        CallKind::Update | CallKind::UpdateWithConfidence
+            if !args.contains(&ArgRole::TimestampMs) && !args.contains(&ArgRole::Clock) =>
+        {
+            Some("a u64 timestamp or the Clock")
+        }
+        CallKind::UpdateWithConfidence if !args.contains(&ArgRole::Confidence) => {
+            Some("a u64 confidence after the price and timestamp")
+        }
+        _ => None,
+    };
+    if let Some(missing) = missing {
+        return Err(anyhow!("{} does not take {}", signature, missing));
+    }
+    if args != kind.default_args() {
+        log::warn!("Adapting calls to {} ({:?})", signature, args);
+    }
+    Ok(CallTemplate {
+        function: name.to_string(),
+        args,
+    })
+}
+
+/// Checks the oracle's functions against the package's normalized module and derives how
+/// to call each of them, so a changed signature fails at startup with the offending
+/// parameter named instead of aborting every transaction.
+pub fn resolve_calls(
+    module: &SuiMoveNormalizedModule,
+    create_function: &str,
+    update_function: &str,
+    manual_update_function: &str,
+    stale_function: Option<&str>,
+    confidence_function: Option<&str>,
+) -> Result<ModuleCalls> {
+    let manual_update = if module
+        .exposed_functions
+        .contains_key(manual_update_function)
+    {
+        Some(resolve(module, manual_update_function, CallKind::Update)?)
+    } else {
+        log::warn!(
+            "{}::{} not found; manual overrides cannot be published",
+            module.name,
+            manual_update_function
+        );
+        None
+    };
+    Ok(ModuleCalls {
+        create: resolve(module, create_function, CallKind::Create)?,
+        update: resolve(module, update_function, CallKind::Update)?,
+        manual_update,
+        mark_stale: stale_function
+            .map(|name| resolve(module, name, CallKind::MarkStale))
+            .transpose()?,
+        update_with_confidence: confidence_function
+            .map(|name| resolve(module, name, CallKind::UpdateWithConfidence))
+            .transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SuiMoveNormalizedType as T;
+    use std::collections::BTreeMap;
+
+    fn object(module: &str, name: &str) -> T {
+        T::Struct {
+            address: "0x2".to_string(),
+            module: module.to_string(),
+            name: name.to_string(),
+            type_arguments: vec![],
+        }
+    }
+
+    fn module(functions: Vec<(&str, Vec<T>)>) -> SuiMoveNormalizedModule {
+        SuiMoveNormalizedModule {
+            file_format_version: 6,
+            address: "0x1234".to_string(),
+            name: "price_oracle".to_string(),
+            exposed_functions: functions
+                .into_iter()
+                .map(|(name, parameters)| {
+                    let function = SuiMoveNormalizedFunction {
+                        visibility: SuiMoveVisibility::Public,
+                        is_entry: true,
+                        parameters,
+                        return_: vec![],
+                    };
+                    (name.to_string(), function)
+                })
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    fn price_object() -> T {
+        T::MutableReference(Box::new(object("price_oracle", "PriceObject")))
+    }
+
+    fn tx_context() -> T {
+        T::MutableReference(Box::new(object("tx_context", "TxContext")))
+    }
+
+    fn create() -> (&'static str, Vec<T>) {
+        (
+            "create_price_object",
+            vec![
+                T::Vector(Box::new(T::U8)),
+                T::U64,
+                T::U64,
+                T::U8,
+                tx_context(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_resolves_and_adapts_signatures() {
+        let module = module(vec![
+            create(),
+            (
+                "update_price",
+                vec![price_object(), T::U64, T::U64, tx_context()],
+            ),
+            (
+                "update_price_manual",
+                vec![
+                    price_object(),
+                    T::U64,
+                    T::Reference(Box::new(object("clock", "Clock"))),
+                ],
+            ),
+            (
+                "mark_stale",
+                vec![
+                    price_object(),
+                    T::Reference(Box::new(object("clock", "Clock"))),
+                ],
+            ),
+            (
+                "update_price_with_confidence",
+                vec![price_object(), T::U64, T::U64, T::U64, tx_context()],
+            ),
+            (
+                "update_price_clock_confidence",
+                vec![
+                    price_object(),
+                    T::U64,
+                    T::Reference(Box::new(object("clock", "Clock"))),
+                    T::U64,
+                ],
+            ),
+        ]);
+        let calls = resolve_calls(
+            &module,
+            "create_price_object",
+            "update_price",
+            "update_price_manual",
+            Some("mark_stale"),
+            Some("update_price_with_confidence"),
+        )
+        .unwrap();
+        assert_eq!(calls.create.args, CallKind::Create.default_args());
+        assert_eq!(calls.update.args, CallKind::Update.default_args());
+        assert_eq!(
+            calls.manual_update.unwrap().args,
+            vec![ArgRole::PriceObject, ArgRole::Price, ArgRole::Clock]
+        );
+        assert_eq!(
+            calls.mark_stale.unwrap().args,
+            CallKind::MarkStale.default_args()
+        );
+        assert_eq!(
+            calls.update_with_confidence.unwrap().args,
+            CallKind::UpdateWithConfidence.default_args()
+        );
+        // With the Clock for a timestamp, the second u64 is taken as the timestamp.
+        let err = resolve_calls(
+            &module,
+            "create_price_object",
+            "update_price",
+            "update_price_manual",
+            None,
+            Some("update_price_clock_confidence"),
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("does not take a u64 confidence after the price and timestamp"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_rejects_incompatible_signatures() {
+        let resolve_update = |parameters| {
+            let module = module(vec![create(), ("update_price", parameters)]);
+            resolve_calls(
+                &module,
+                "create_price_object",
+                "update_price",
+                "missing",
+                None,
+                None,
+            )
+            .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            resolve_update(vec![price_object(), T::U64, T::Bool]).unwrap_err(),
+            "price_oracle::update_price(&mut price_oracle::PriceObject, u64, bool): \
+             cannot supply parameter 3 (bool)"
+        );
+        assert!(
+            resolve_update(vec![price_object(), T::U64])
+                .unwrap_err()
+                .ends_with("does not take a u64 timestamp or the Clock")
+        );
+        // The TxContext is only implicit as the last parameter.
+        assert!(resolve_update(vec![price_object(), tx_context(), T::U64, T::U64]).is_err());
+
+        let module = module(vec![create()]);
+        assert!(
+            resolve_calls(
+                &module,
+                "create_price_object",
+                "update_price",
+                "missing",
+                None,
+                None
+            )
+            .unwrap_err()
+            .to_string()
+            .starts_with("price_oracle::update_price not found")
+        );
+    }
+}
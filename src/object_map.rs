@@ -0,0 +1,182 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use sui_sdk::types::base_types::ObjectID;
+
+const CSV_HEADER: &str = "symbol,object_id";
+
+/// Interchange formats for the feed -> PriceObject ID map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ObjectMapFormat {
+    /// `symbol,object_id` rows with hex object IDs.
+    Csv,
+    /// A JSON object of symbol to hex object ID, the same shape as the known-objects file.
+    Json,
+}
+
+pub fn render(objects: &BTreeMap<String, ObjectID>, format: ObjectMapFormat) -> Result<String> {
+    match format {
+        ObjectMapFormat::Csv => {
+            let mut out = format!("{}\n", CSV_HEADER);
+            for (symbol, id) in objects {
+                out.push_str(&format!("{},{}\n", symbol, id));
+            }
+            Ok(out)
+        }
+        ObjectMapFormat::Json => {
+            let hex: BTreeMap<&String, String> =
+                objects.iter().map(|(s, id)| (s, id.to_string())).collect();
+            Ok(serde_json::to_string_pretty(&hex)? + "\n")
+        }
+    }
+}
+
+fn parse_object_id(symbol: &str, value: &str) -> Result<ObjectID> {
+    if symbol.is_empty() {
+        return Err(anyhow!("empty symbol"));
+    }
+    ObjectID::from_str(value)
+        .map_err(|e| anyhow!("{}: invalid object ID '{}': {}", symbol, value, e))
+}
+
+/// Parses an exported map. Every symbol and every object ID must appear at most once.
+pub fn parse(content: &str, format: ObjectMapFormat) -> Result<BTreeMap<String, ObjectID>> {
+    let mut entries = Vec::new();
+    match format {
+        ObjectMapFormat::Csv => {
+            for (i, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') || (i == 0 && line == CSV_HEADER) {
+                    continue;
+                }
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let [symbol, id] = fields[..] else {
+                    return Err(anyhow!(
+                        "line {}: expected 'symbol,object_id', got '{}'",
+                        i + 1,
+                        line
+                    ));
+                };
+                let id = parse_object_id(symbol, id).context(format!("line {}", i + 1))?;
+                entries.push((symbol.to_string(), id));
+            }
+        }
+        ObjectMapFormat::Json => {
+            let raw: BTreeMap<String, String> =
+                serde_json::from_str(content).context("Expected a JSON object of symbol to ID")?;
+            for (symbol, id) in raw {
+                let id = parse_object_id(&symbol, &id)?;
+                entries.push((symbol, id));
+            }
+        }
+    }
+
+    let mut objects = BTreeMap::new();
+    let mut owners: HashMap<ObjectID, String> = HashMap::new();
+    for (symbol, id) in entries {
+        if let Some(other) = owners.insert(id, symbol.clone()) {
+            return Err(anyhow!(
+                "{} is listed for both {} and {}",
+                id,
+                other,
+                symbol
+            ));
+        }
+        if objects.insert(symbol.clone(), id).is_some() {
+            return Err(anyhow!("{} is listed more than once", symbol));
+        }
+    }
+    Ok(objects)
+}
+
+/// Merges `imported` into `existing`. Remapping a symbol that already points at a different
+/// object is refused unless `force` is set. Returns the number of entries added or changed.
+pub fn merge(
+    existing: &mut HashMap<String, ObjectID>,
+    imported: &BTreeMap<String, ObjectID>,
+    force: bool,
+) -> Result<usize> {
+    let conflicts: Vec<String> = imported
+        .iter()
+        .filter_map(|(symbol, id)| {
+            let current = existing.get(symbol)?;
+            (current != id).then(|| format!("{} ({} -> {})", symbol, current, id))
+        })
+        .collect();
+    if !conflicts.is_empty() && !force {
+        return Err(anyhow!(
+            "Import would remap {}; pass --force to overwrite",
+            conflicts.join(", ")
+        ));
+    }
+
+    let mut changed = 0;
+    for (symbol, id) in imported {
+        if existing.insert(symbol.clone(), *id) != Some(*id) {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(b: u8) -> ObjectID {
+        ObjectID::from_single_byte(b)
+    }
+
+    #[test]
+    fn test_round_trip_both_formats() {
+        let objects = BTreeMap::from([
+            ("BTC/USD".to_string(), id(1)),
+            ("ETH/USD".to_string(), id(2)),
+        ]);
+        for format in [ObjectMapFormat::Csv, ObjectMapFormat::Json] {
+            let rendered = render(&objects, format).unwrap();
+            assert_eq!(parse(&rendered, format).unwrap(), objects);
+        }
+        assert!(
+            render(&objects, ObjectMapFormat::Csv)
+                .unwrap()
+                .starts_with("symbol,object_id\nBTC/USD,0x")
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_rows() {
+        let ok = format!("# comment\nBTC/USD, {}\n\n", id(1));
+        assert_eq!(parse(&ok, ObjectMapFormat::Csv).unwrap()["BTC/USD"], id(1));
+
+        for bad in [
+            "BTC/USD\n".to_string(),
+            "BTC/USD,not-hex\n".to_string(),
+            format!(",{}\n", id(1)),
+            format!("BTC/USD,{}\nBTC/USD,{}\n", id(1), id(2)),
+            format!("BTC/USD,{}\nETH/USD,{}\n", id(1), id(1)),
+        ] {
+            assert!(
+                parse(&bad, ObjectMapFormat::Csv).is_err(),
+                "accepted {:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_refuses_remap_without_force() {
+        let mut existing = HashMap::from([("BTC/USD".to_string(), id(1))]);
+        let imported = BTreeMap::from([
+            ("BTC/USD".to_string(), id(9)),
+            ("ETH/USD".to_string(), id(2)),
+        ]);
+        assert!(merge(&mut existing, &imported, false).is_err());
+        assert_eq!(existing.len(), 1);
+
+        assert_eq!(merge(&mut existing, &imported, true).unwrap(), 2);
+        assert_eq!(existing["BTC/USD"], id(9));
+        assert_eq!(merge(&mut existing, &imported, false).unwrap(), 0);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::aggregator::{self, Quote};
+use crate::clock_drift::{self, ClockDriftTracker};
+use crate::config::{ExchangeConfig, RateLimitSettings};
+use crate::endpoints::{self, EndpointSelector};
+use crate::rate_limit;
+use crate::sources::{PriceSample, PriceSource, TradeFilter};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The envelope of every OKX v5 response. A `code` other than `"0"` is an error, even
+/// with a `200` status.
+#[derive(Deserialize, Debug)]
+pub struct OkxResponse<T> {
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+    #[serde(default = "Vec::new")]
+    pub data: Vec<T>,
+}
+
+impl<T> OkxResponse<T> {
+    fn into_data(self) -> Result<Vec<T>> {
+        if self.code != "0" {
+            return Err(anyhow!("OKX error {}: {}", self.code, self.msg));
+        }
+        Ok(self.data)
+    }
+}
+
+/// One instrument's ticker. Sizes and volume are in base currency for spot instruments.
+/// Fields OKX has no value for are empty strings.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxTicker {
+    pub inst_id: String,
+    pub last: String,
+    #[serde(default)]
+    pub last_sz: String,
+    #[serde(default)]
+    pub bid_px: String,
+    #[serde(default)]
+    pub bid_sz: String,
+    #[serde(default)]
+    pub ask_px: String,
+    #[serde(default)]
+    pub ask_sz: String,
+    #[serde(default)]
+    pub vol24h: String,
+    /// When OKX generated the ticker, in milliseconds since the epoch.
+    pub ts: String,
+}
+
+impl OkxTicker {
+    fn parse(&self, field: &str, value: &str) -> Result<f64> {
+        value.parse().context(format!(
+            "Invalid {} '{}' for {}",
+            field, value, self.inst_id
+        ))
+    }
+
+    fn parse_price(&self, field: &str, value: &str) -> Result<Decimal> {
+        value.parse().context(format!(
+            "Invalid {} '{}' for {}",
+            field, value, self.inst_id
+        ))
+    }
+
+    fn price(&self) -> Result<Decimal> {
+        self.parse_price("last", &self.last)
+    }
+
+    fn timestamp_ms(&self) -> Option<i64> {
+        self.ts.parse().ok()
+    }
+
+    fn notional(&self) -> Result<f64> {
+        Ok(aggregator::top_of_book_notional(
+            self.parse("bidPx", &self.bid_px)?,
+            self.parse("bidSz", &self.bid_sz)?,
+            self.parse("askPx", &self.ask_px)?,
+            self.parse("askSz", &self.ask_sz)?,
+        ))
+    }
+
+    fn quote(&self) -> Result<Quote> {
+        Ok(Quote {
+            bid: self.parse_price("bidPx", &self.bid_px)?,
+            ask: self.parse_price("askPx", &self.ask_px)?,
+        })
+    }
+
+    fn volume(&self) -> Result<f64> {
+        self.parse("vol24h", &self.vol24h)
+    }
+}
+
+const EXCHANGE_NAME: &str = "okx";
+
+/// OKX allows 20 ticker requests per 2 seconds per IP.
+pub const RATE_LIMIT: RateLimitSettings = RateLimitSettings {
+    weight_per_minute: 600,
+    burst: Some(20),
+    request_weight: 1,
+};
+
+fn ticker_url(base_url: &str, inst_id: &str) -> String {
+    format!(
+        "{}/market/ticker?instId={}",
+        base_url.trim_end_matches('/'),
+        inst_id
+    )
+}
+
+async fn get_okx_ticker(
+    client: &Client,
+    name: &str,
+    base_url: &str,
+    inst_id: &str,
+    clock_drift: &ClockDriftTracker,
+) -> Result<OkxTicker> {
+    let url = ticker_url(base_url, inst_id);
+    log::debug!("Fetching ticker for {} from OKX: {}", inst_id, url);
+    let request_sent_ms = clock_drift::local_now_ms();
+    let response = rate_limit::check(client.get(&url).send().await?)?;
+    let response_received_ms = clock_drift::local_now_ms();
+    let ticker = response
+        .json::<OkxResponse<OkxTicker>>()
+        .await?
+        .into_data()?
+        .into_iter()
+        .find(|t| t.inst_id == inst_id)
+        .ok_or_else(|| anyhow!("OKX returned no ticker for {}", inst_id))?;
+    if let Some(ts) = ticker.timestamp_ms() {
+        clock_drift.observe(name, ts, request_sent_ms, response_received_ms);
+    }
+    Ok(ticker)
+}
+
+async fn get_okx_prices(
+    source: &OkxSource,
+    inst_ids: &[String],
+) -> Result<HashMap<String, PriceSample>> {
+    let OkxSource {
+        name,
+        config,
+        client,
+        clock_drift,
+        endpoints: selector,
+        trades,
+    } = source;
+    let mut prices = HashMap::new();
+    let endpoints = endpoints::endpoints(config);
+    let endpoint = selector.select(name, &endpoints);
+
+    for inst_id in inst_ids {
+        let request = get_okx_ticker(client, name, &endpoint.base_url, inst_id, clock_drift);
+        let ticker = selector
+            .timed(name, &endpoint, request)
+            .await
+            .and_then(|ticker| Ok((ticker.price()?, ticker)));
+        match ticker {
+            Ok((price, ticker)) => {
+                log::info!("Fetched price for {}: {}", inst_id, price);
+                let size = ticker.last_sz.parse().ok();
+                let trade = PriceSample {
+                    timestamp_ms: ticker.timestamp_ms(),
+                    price,
+                };
+                match trades.observe(inst_id, trade, size) {
+                    Some(sample) => {
+                        prices.insert(inst_id.clone(), sample);
+                    }
+                    None => {
+                        log::warn!("No OKX trade of {} above the minimum notional yet", inst_id)
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to fetch price for {} from OKX: {}", inst_id, e),
+        }
+    }
+
+    // Keep the other endpoints' latency current with one request per cycle.
+    if let (Some(probe), Some(inst_id)) = (
+        selector.probe_candidate(name, &endpoints, &endpoint),
+        inst_ids.first(),
+    ) {
+        let request = get_okx_ticker(client, name, &probe.base_url, inst_id, clock_drift);
+        if let Err(e) = selector.timed(name, &probe, request).await {
+            log::debug!("OKX endpoint probe of {} failed: {}", probe.base_url, e);
+        }
+    }
+    Ok(prices)
+}
+
+/// `what` of each of `inst_ids`, read from its ticker by `extract`. Instruments whose
+/// ticker could not be fetched or lacks the value are missing from the map.
+async fn get_okx_ticker_values<T>(
+    source: &OkxSource,
+    inst_ids: &[String],
+    what: &str,
+    extract: fn(&OkxTicker) -> Result<T>,
+) -> Result<HashMap<String, T>> {
+    let mut values = HashMap::new();
+    let endpoint = source
+        .endpoints
+        .select(source.name, &endpoints::endpoints(&source.config));
+    for inst_id in inst_ids {
+        let request = get_okx_ticker(
+            &source.client,
+            source.name,
+            &endpoint.base_url,
+            inst_id,
+            &source.clock_drift,
+        );
+        match source
+            .endpoints
+            .timed(source.name, &endpoint, request)
+            .await
+            .and_then(|ticker| extract(&ticker))
+        {
+            Ok(value) => {
+                values.insert(inst_id.clone(), value);
+            }
+            Err(e) => log::error!("Failed to fetch {} of {} from OKX: {}", what, inst_id, e),
+        }
+    }
+    Ok(values)
+}
+
+/// OKX spot tickers (`/api/v5/market/ticker`), keyed by instrument ID such as `BTC-USDT`.
+/// Depth, quotes and 24h volume come from the same ticker, and trades below
+/// `min_trade_notional` leave the price at the previous trade.
+pub struct OkxSource {
+    name: &'static str,
+    config: ExchangeConfig,
+    client: Client,
+    clock_drift: Arc<ClockDriftTracker>,
+    endpoints: Arc<EndpointSelector>,
+    trades: TradeFilter,
+}
+
+impl OkxSource {
+    pub fn new(
+        config: ExchangeConfig,
+        client: Client,
+        clock_drift: Arc<ClockDriftTracker>,
+        endpoints: Arc<EndpointSelector>,
+    ) -> Self {
+        if config.stream_url.is_some() {
+            log::warn!("OKX sources have no WebSocket stream yet; stream_url is ignored");
+        }
+        Self {
+            name: EXCHANGE_NAME,
+            trades: TradeFilter::new(config.min_trade_notional),
+            config,
+            client,
+            clock_drift,
+            endpoints,
+        }
+    }
+
+    /// The source under another name, for a venue that speaks OKX's API. Clock drift and
+    /// endpoint latency are tracked by name, so they are kept apart from OKX's.
+    pub fn with_name(self, name: &'static str) -> Self {
+        Self { name, ..self }
+    }
+}
+
+#[async_trait]
+impl PriceSource for OkxSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.config.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        get_okx_prices(self, symbols).await
+    }
+
+    async fn fetch_depth(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_okx_ticker_values(self, symbols, "order-book depth", OkxTicker::notional).await
+    }
+
+    async fn fetch_volume(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        get_okx_ticker_values(self, symbols, "24h volume", OkxTicker::volume).await
+    }
+
+    async fn fetch_quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        get_okx_ticker_values(self, symbols, "bid and ask", OkxTicker::quote).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_okx_ticker() {
+        let json_data = r#"{"code":"0","msg":"","data":[{"instType":"SPOT","instId":"BTC-USDT","last":"65000.1","lastSz":"0.002","askPx":"65000.2","askSz":"1.5","bidPx":"65000.0","bidSz":"0.5","open24h":"64000","high24h":"65500","low24h":"63800","volCcy24h":"1173557484.5","vol24h":"18151.52","ts":"1672515782136","sodUtc0":"64500","sodUtc8":"64600"}]}"#;
+        let response: OkxResponse<OkxTicker> = serde_json::from_str(json_data).unwrap();
+        let ticker = response.into_data().unwrap().pop().unwrap();
+        assert_eq!(ticker.inst_id, "BTC-USDT");
+        assert_eq!(ticker.price().unwrap(), Decimal::new(650001, 1));
+        assert_eq!(ticker.timestamp_ms(), Some(1672515782136));
+        assert_eq!(ticker.notional().unwrap(), 32_500.0);
+        assert_eq!(
+            ticker.quote().unwrap(),
+            Quote {
+                bid: Decimal::from(65000),
+                ask: Decimal::new(650002, 1)
+            }
+        );
+        assert_eq!(ticker.volume().unwrap(), 18151.52);
+        assert_eq!(
+            ticker_url("https://www.okx.com/api/v5/", "BTC-USDT"),
+            "https://www.okx.com/api/v5/market/ticker?instId=BTC-USDT"
+        );
+    }
+
+    #[test]
+    fn test_okx_error_envelope() {
+        let json_data = r#"{"code":"51001","msg":"Instrument ID does not exist","data":[]}"#;
+        let response: OkxResponse<OkxTicker> = serde_json::from_str(json_data).unwrap();
+        let err = response.into_data().unwrap_err().to_string();
+        assert_eq!(err, "OKX error 51001: Instrument ID does not exist");
+
+        // A ticker without a side of the book has no depth or quote.
+        let json_data = r#"{"code":"0","data":[{"instId":"SUI-USDT","last":"1.5","bidPx":"","bidSz":"","askPx":"1.51","askSz":"10","ts":"1"}]}"#;
+        let response: OkxResponse<OkxTicker> = serde_json::from_str(json_data).unwrap();
+        let ticker = response.into_data().unwrap().pop().unwrap();
+        assert!(ticker.quote().is_err());
+        assert!(ticker.volume().is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::chain::PriceInfo;
+
+const OUTBOX_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub price_info: PriceInfo,
+    /// The update is dropped instead of published after this.
+    pub valid_until_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutboxFile {
+    schema_version: u32,
+    entries: BTreeMap<String, Entry>,
+}
+
+/// Updates aggregated but not yet on chain, by symbol, persisted in `outbox.file`. An update
+/// stays until it is published, replaced by a newer update of its symbol or no longer fresh,
+/// so one that was still pending when the process died is published after the restart.
+pub struct Outbox {
+    path: String,
+    max_age_ms: u64,
+    entries: Mutex<BTreeMap<String, Entry>>,
+}
+
+impl Outbox {
+    /// Loads the updates left pending by the previous run. A `max_age_ms` of `0` keeps
+    /// nothing.
+    pub fn open(path: &str, max_age_ms: u64) -> Result<Self> {
+        let entries = if max_age_ms > 0 && Path::new(path).exists() {
+            let content = fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+            let file: OutboxFile =
+                serde_json::from_str(&content).context(format!("Failed to parse {}", path))?;
+            file.entries
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: path.to_string(),
+            max_age_ms,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Records updates before they are submitted, each valid for `max_age_ms` from its
+    /// timestamp.
+    pub fn push(&self, updates: &[PriceInfo]) -> Result<()> {
+        if self.max_age_ms == 0 || updates.is_empty() {
+            return Ok(());
+        }
+        let mut entries = self.entries.lock().unwrap();
+        for price_info in updates {
+            entries.insert(
+                price_info.symbol.clone(),
+                Entry {
+                    price_info: price_info.clone(),
+                    valid_until_ms: price_info.timestamp_ms.saturating_add(self.max_age_ms),
+                },
+            );
+        }
+        self.save(&entries)
+    }
+
+    /// Removes `price_info` once it has landed, unless a newer update of its symbol has
+    /// replaced it in the meantime.
+    pub fn settle(&self, price_info: &PriceInfo) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let settled = entries
+            .get(&price_info.symbol)
+            .is_some_and(|entry| entry.price_info.timestamp_ms == price_info.timestamp_ms);
+        if !settled {
+            return Ok(());
+        }
+        entries.remove(&price_info.symbol);
+        self.save(&entries)
+    }
+
+    /// The pending updates still fresh at `now_ms`, for publishing at startup, and those
+    /// that are not, which are dropped.
+    pub fn drain(&self, now_ms: u64) -> Result<(Vec<PriceInfo>, Vec<Entry>)> {
+        let mut entries = self.entries.lock().unwrap();
+        let (fresh, expired): (Vec<Entry>, Vec<Entry>) = entries
+            .values()
+            .cloned()
+            .partition(|entry| now_ms <= entry.valid_until_ms);
+        if !expired.is_empty() {
+            entries.retain(|_, entry| now_ms <= entry.valid_until_ms);
+            self.save(&entries)?;
+        }
+        Ok((fresh.into_iter().map(|e| e.price_info).collect(), expired))
+    }
+
+    /// Written to a temporary file first, so a crash mid-write leaves the previous outbox.
+    fn save(&self, entries: &BTreeMap<String, Entry>) -> Result<()> {
+        if let Some(parent) = Path::new(&self.path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OutboxFile {
+            schema_version: OUTBOX_SCHEMA_VERSION,
+            entries: entries.clone(),
+        };
+        let tmp = format!("{}.tmp", self.path);
+        fs::write(&tmp, serde_json::to_string_pretty(&file)? + "\n")
+            .context(format!("Failed to write {}", tmp))?;
+        fs::rename(&tmp, &self.path).context(format!("Failed to replace {}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn update(symbol: &str, timestamp_ms: u64) -> PriceInfo {
+        PriceInfo {
+            symbol: symbol.to_string(),
+            price: Decimal::from(100),
+            timestamp_ms,
+            manual_override: false,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_pending_updates_survive_restart_while_fresh() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("outbox-test-{}", std::process::id()));
+        let path = dir.join("outbox.json");
+        let path = path.to_str().unwrap();
+        let outbox = Outbox::open(path, 1000)?;
+        outbox.push(&[update("BTC/USD", 100), update("ETH/USD", 100)])?;
+        outbox.push(&[update("BTC/USD", 200)])?;
+        // The first BTC update landing does not settle the newer one.
+        outbox.settle(&update("BTC/USD", 100))?;
+        outbox.settle(&update("ETH/USD", 100))?;
+        outbox.push(&[update("SOL/USD", 10)])?;
+
+        // The process dies here; BTC and SOL never landed.
+        let (fresh, expired) = Outbox::open(path, 1000)?.drain(1100)?;
+        assert_eq!(fresh, [update("BTC/USD", 200)]);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].price_info.symbol, "SOL/USD");
+        assert!(Outbox::open(path, 1000)?.drain(1100)?.1.is_empty());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_max_age_keeps_nothing() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("outbox-off-{}.json", std::process::id()));
+        let outbox = Outbox::open(path.to_str().unwrap(), 0)?;
+        outbox.push(&[update("BTC/USD", 100)])?;
+        assert!(!path.exists());
+        assert!(outbox.drain(100)?.0.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+// Overrides are set and cleared only through the HTTP API's admin routes.
+#![cfg_attr(not(feature = "http-api"), allow(dead_code))]
+
+use anyhow::{Context, Result, anyhow};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::aggregator;
+use crate::config::AdminSettings;
+
+/// An operator-set price that replaces the source aggregate until it expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualOverride {
+    pub symbol: String,
+    /// A JSON number, as the operator sent it.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    pub set_at_ms: u64,
+    pub expires_at_ms: u64,
+    pub reason: String,
+    pub operator: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OverrideRequest {
+    pub symbol: String,
+    pub price: Decimal,
+    /// Mandatory lifetime; capped by `admin.max_override_ttl_seconds`.
+    pub ttl_seconds: u64,
+    pub reason: String,
+    pub operator: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp_ms: u64,
+    action: &'a str,
+    symbol: &'a str,
+    operator: &'a str,
+    details: serde_json::Value,
+}
+
+/// Append-only JSON-lines record of every override action and every publish that used one.
+pub struct AuditLog {
+    path: String,
+}
+
+impl AuditLog {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn append(
+        &self,
+        timestamp_ms: u64,
+        action: &str,
+        symbol: &str,
+        operator: &str,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        let record = AuditRecord {
+            timestamp_ms,
+            action,
+            symbol,
+            operator,
+            details,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!("Failed to open audit log {}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .context(format!("Failed to write audit log {}", self.path))?;
+        Ok(())
+    }
+}
+
+pub struct OverrideStore {
+    active: Mutex<HashMap<String, ManualOverride>>,
+    audit: AuditLog,
+    max_ttl_seconds: u64,
+}
+
+impl OverrideStore {
+    pub fn new(settings: &AdminSettings) -> Self {
+        Self {
+            active: Mutex::new(HashMap::new()),
+            audit: AuditLog::new(&settings.audit_log_file),
+            max_ttl_seconds: settings.max_override_ttl_seconds,
+        }
+    }
+
+    /// Validates and installs an override. The audit record is written before the override
+    /// takes effect, so an override that could not be audited is never applied.
+    pub fn set(&self, request: OverrideRequest, now_ms: u64) -> Result<ManualOverride> {
+        if request.price <= Decimal::ZERO {
+            return Err(anyhow!("price must be a positive number"));
+        }
+        if request.ttl_seconds == 0 || request.ttl_seconds > self.max_ttl_seconds {
+            return Err(anyhow!(
+                "ttl_seconds must be between 1 and {}",
+                self.max_ttl_seconds
+            ));
+        }
+        if request.reason.trim().is_empty() || request.operator.trim().is_empty() {
+            return Err(anyhow!("reason and operator are required"));
+        }
+
+        let entry = ManualOverride {
+            symbol: request.symbol,
+            price: request.price,
+            set_at_ms: now_ms,
+            expires_at_ms: now_ms + request.ttl_seconds * 1000,
+            reason: request.reason,
+            operator: request.operator,
+        };
+        self.audit.append(
+            now_ms,
+            "set",
+            &entry.symbol,
+            &entry.operator,
+            serde_json::to_value(&entry)?,
+        )?;
+        log::warn!(
+            "Manual override set for {} at {} by {} until {} ({})",
+            entry.symbol,
+            entry.price,
+            entry.operator,
+            entry.expires_at_ms,
+            entry.reason
+        );
+        self.active
+            .lock()
+            .unwrap()
+            .insert(entry.symbol.clone(), entry.clone());
+        Ok(entry)
+    }
+
+    pub fn clear(
+        &self,
+        symbol: &str,
+        operator: &str,
+        now_ms: u64,
+    ) -> Result<Option<ManualOverride>> {
+        let removed = self.active.lock().unwrap().remove(symbol);
+        if let Some(entry) = &removed {
+            self.audit.append(
+                now_ms,
+                "clear",
+                symbol,
+                operator,
+                serde_json::to_value(entry)?,
+            )?;
+            log::warn!("Manual override for {} cleared by {}", symbol, operator);
+        }
+        Ok(removed)
+    }
+
+    /// Returns the live override for `symbol`, retiring it if it has expired.
+    pub fn active(&self, symbol: &str, now_ms: u64) -> Option<ManualOverride> {
+        let mut active = self.active.lock().unwrap();
+        let entry = active.get(symbol)?;
+        if now_ms < entry.expires_at_ms {
+            return Some(entry.clone());
+        }
+        let expired = active.remove(symbol)?;
+        drop(active);
+        log::warn!("Manual override for {} expired", symbol);
+        if let Err(e) = self.audit.append(
+            now_ms,
+            "expire",
+            symbol,
+            &expired.operator,
+            serde_json::to_value(&expired).unwrap_or_default(),
+        ) {
+            log::error!("{:?}", e);
+        }
+        None
+    }
+
+    pub fn list(&self, now_ms: u64) -> Vec<ManualOverride> {
+        let symbols: Vec<String> = self.active.lock().unwrap().keys().cloned().collect();
+        let mut live: Vec<ManualOverride> = symbols
+            .iter()
+            .filter_map(|s| self.active(s, now_ms))
+            .collect();
+        live.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        live
+    }
+
+    /// Audits one publish attempt that used `entry`.
+    pub fn record_use(&self, entry: &ManualOverride, now_ms: u64, outcome: &str) {
+        let details = serde_json::json!({
+            "price": aggregator::as_f64(entry.price),
+            "expires_at_ms": entry.expires_at_ms,
+            "outcome": outcome,
+        });
+        if let Err(e) =
+            self.audit
+                .append(now_ms, "publish", &entry.symbol, &entry.operator, details)
+        {
+            log::error!("{:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn store(audit_log_file: &str) -> OverrideStore {
+        let _ = fs::remove_file(audit_log_file);
+        OverrideStore::new(&AdminSettings {
+            audit_log_file: audit_log_file.to_string(),
+            max_override_ttl_seconds: 600,
+            ..Default::default()
+        })
+    }
+
+    fn request(ttl_seconds: u64) -> OverrideRequest {
+        OverrideRequest {
+            symbol: "BTC/USD".to_string(),
+            price: Decimal::from(65000),
+            ttl_seconds,
+            reason: "all venues down".to_string(),
+            operator: "alice".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_override_lifecycle_is_audited() {
+        let path = "./test_overrides_lifecycle.jsonl";
+        let store = store(path);
+        store.set(request(60), 1_000).unwrap();
+
+        assert_eq!(
+            store.active("BTC/USD", 30_000).unwrap().price,
+            Decimal::from(65000)
+        );
+        store.record_use(
+            &store.active("BTC/USD", 30_000).unwrap(),
+            30_000,
+            "published",
+        );
+        assert!(store.active("BTC/USD", 61_000).is_none());
+        assert!(store.list(61_000).is_empty());
+
+        let actions: Vec<String> = fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["action"].to_string())
+            .collect();
+        assert_eq!(actions, vec!["\"set\"", "\"publish\"", "\"expire\""]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_override_requires_bounded_expiry() {
+        let path = "./test_overrides_validation.jsonl";
+        let store = store(path);
+        assert!(store.set(request(0), 0).is_err());
+        assert!(store.set(request(601), 0).is_err());
+        let mut bad_price = request(60);
+        bad_price.price = Decimal::ZERO;
+        assert!(store.set(bad_price, 0).is_err());
+        let mut no_reason = request(60);
+        no_reason.reason = " ".to_string();
+        assert!(store.set(no_reason, 0).is_err());
+        assert!(store.list(0).is_empty());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_clear_override() {
+        let path = "./test_overrides_clear.jsonl";
+        let store = store(path);
+        store.set(request(60), 0).unwrap();
+        assert!(store.clear("BTC/USD", "bob", 10).unwrap().is_some());
+        assert!(store.clear("BTC/USD", "bob", 10).unwrap().is_none());
+        assert!(store.active("BTC/USD", 20).is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
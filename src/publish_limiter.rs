@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant};
+
+/// What to do with an update offered to the limiter.
+#[derive(Debug, PartialEq)]
+pub enum Admission<T> {
+    /// The target's spacing has elapsed: publish the update now.
+    Publish(T),
+    /// Too soon after the target's last update. The update is held, replacing any older held
+    /// one, and handed out by `take_due` once the spacing has elapsed.
+    Queued { due_in: Duration },
+}
+
+struct Target<T> {
+    last_published: Option<Instant>,
+    min_spacing: Duration,
+    queued: Option<T>,
+}
+
+impl<T> Target<T> {
+    fn due_at(&self) -> Option<Instant> {
+        self.last_published.map(|at| at + self.min_spacing)
+    }
+}
+
+/// Enforces a minimum spacing between successive updates of each publish target, for
+/// consumer contracts that reject more frequent updates. Updates that arrive too early are
+/// not dropped: the latest one is kept and released as soon as the target may be updated.
+pub struct PublishLimiter<T> {
+    targets: Mutex<HashMap<String, Target<T>>>,
+    queue_changed: Notify,
+}
+
+impl<T> Default for PublishLimiter<T> {
+    fn default() -> Self {
+        Self {
+            targets: Mutex::new(HashMap::new()),
+            queue_changed: Notify::new(),
+        }
+    }
+}
+
+impl<T> PublishLimiter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn admit(
+        &self,
+        target: &str,
+        min_spacing: Duration,
+        now: Instant,
+        update: T,
+    ) -> Admission<T> {
+        let mut targets = self.targets.lock().unwrap();
+        let state = targets.entry(target.to_string()).or_insert(Target {
+            last_published: None,
+            min_spacing,
+            queued: None,
+        });
+        state.min_spacing = min_spacing;
+        match state.due_at().filter(|due| *due > now) {
+            Some(due) => {
+                state.queued = Some(update);
+                drop(targets);
+                self.queue_changed.notify_one();
+                Admission::Queued { due_in: due - now }
+            }
+            None => {
+                // A fresher value supersedes anything still held.
+                state.queued = None;
+                Admission::Publish(update)
+            }
+        }
+    }
+
+    /// Starts the target's spacing; only updates that landed count.
+    pub fn record_published(&self, target: &str, at: Instant) {
+        if let Some(state) = self.targets.lock().unwrap().get_mut(target) {
+            state.last_published = Some(at);
+        }
+    }
+
+    /// When the earliest held update may be published.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.targets
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|t| t.queued.is_some())
+            .filter_map(Target::due_at)
+            .min()
+    }
+
+    /// Removes and returns the held updates whose targets may be updated at `now`.
+    pub fn take_due(&self, now: Instant) -> Vec<(String, T)> {
+        let mut targets = self.targets.lock().unwrap();
+        let mut due: Vec<(String, T)> = targets
+            .iter_mut()
+            .filter(|(_, t)| t.due_at().is_none_or(|at| at <= now))
+            .filter_map(|(name, t)| t.queued.take().map(|u| (name.clone(), u)))
+            .collect();
+        due.sort_by(|a, b| a.0.cmp(&b.0));
+        due
+    }
+
+    /// Resolves when an update has been queued since the last call.
+    pub async fn queue_changed(&self) {
+        self.queue_changed.notified().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_within_spacing_are_queued_latest_wins() {
+        let limiter = PublishLimiter::new();
+        let spacing = Duration::from_secs(30);
+        let t0 = Instant::now();
+
+        assert_eq!(
+            limiter.admit("BTC/USD", spacing, t0, 1),
+            Admission::Publish(1)
+        );
+        limiter.record_published("BTC/USD", t0);
+        let at = |s| t0 + Duration::from_secs(s);
+        assert_eq!(
+            limiter.admit("BTC/USD", spacing, at(10), 2),
+            Admission::Queued {
+                due_in: Duration::from_secs(20)
+            }
+        );
+        limiter.admit("BTC/USD", spacing, at(20), 3);
+        // Other targets are independent.
+        assert_eq!(
+            limiter.admit("ETH/USD", spacing, at(20), 9),
+            Admission::Publish(9)
+        );
+
+        assert_eq!(limiter.next_due(), Some(at(30)));
+        assert!(limiter.take_due(at(29)).is_empty());
+        assert_eq!(limiter.take_due(at(30)), vec![("BTC/USD".to_string(), 3)]);
+        assert_eq!(limiter.next_due(), None);
+    }
+
+    #[test]
+    fn test_fresh_publish_supersedes_queued_value() {
+        let limiter = PublishLimiter::new();
+        let spacing = Duration::from_secs(30);
+        let t0 = Instant::now();
+        limiter.admit("BTC/USD", spacing, t0, 1);
+        limiter.record_published("BTC/USD", t0);
+        limiter.admit("BTC/USD", spacing, t0 + Duration::from_secs(5), 2);
+
+        let later = t0 + Duration::from_secs(31);
+        assert_eq!(
+            limiter.admit("BTC/USD", spacing, later, 3),
+            Admission::Publish(3)
+        );
+        assert!(limiter.take_due(later).is_empty());
+        // A failed publish doesn't start the spacing.
+        assert_eq!(
+            limiter.admit("BTC/USD", spacing, later, 4),
+            Admission::Publish(4)
+        );
+    }
+}
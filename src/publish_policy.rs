@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// When an update is worth a transaction: its price moved at least `deviation_bps` from the
+/// last published one, or the last publish is `heartbeat` old. A `deviation_bps` of `0`
+/// publishes every update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Policy {
+    pub deviation_bps: f64,
+    pub heartbeat: Duration,
+}
+
+/// The range an aggregate must fall in to be published, and how far it may move from a
+/// price published less than `max_change_interval` ago.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    pub max_change_pct: Option<f64>,
+    pub max_change_interval: Duration,
+}
+
+/// Why an update is published, or, for `Hold`, why it is not.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Publish,
+    Hold {
+        deviation_bps: f64,
+        due_in: Duration,
+    },
+}
+
+struct Published {
+    price: f64,
+    manual_override: bool,
+    at: Instant,
+}
+
+/// The last published price of each symbol, which new updates are gated against. A symbol
+/// without one, e.g. after a restart, publishes its first update.
+#[derive(Default)]
+pub struct PublishPolicy {
+    last: Mutex<HashMap<String, Published>>,
+}
+
+/// Change from `last` to `price` in basis points of `last`.
+fn deviation_bps(last: f64, price: f64) -> f64 {
+    if last == 0.0 {
+        return if price == 0.0 { 0.0 } else { f64::INFINITY };
+    }
+    ((price - last) / last).abs() * 10_000.0
+}
+
+impl PublishPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to publish `price` for `symbol` at `now`. Switching between a manual
+    /// override and the aggregate always publishes, so the on-chain flag stays accurate.
+    pub fn decide(
+        &self,
+        symbol: &str,
+        price: f64,
+        manual_override: bool,
+        policy: Policy,
+        now: Instant,
+    ) -> Decision {
+        if policy.deviation_bps <= 0.0 {
+            return Decision::Publish;
+        }
+        let last = self.last.lock().unwrap();
+        let Some(last) = last.get(symbol) else {
+            return Decision::Publish;
+        };
+        let deviation = deviation_bps(last.price, price);
+        let age = now.saturating_duration_since(last.at);
+        if deviation >= policy.deviation_bps
+            || age >= policy.heartbeat
+            || manual_override != last.manual_override
+        {
+            return Decision::Publish;
+        }
+        Decision::Hold {
+            deviation_bps: deviation,
+            due_in: policy.heartbeat - age,
+        }
+    }
+
+    /// Why `price` for `symbol` is outside `bounds` at `now`, if it is. A manually set last
+    /// price is not compared against.
+    pub fn bounds_violation(
+        &self,
+        symbol: &str,
+        price: f64,
+        bounds: Bounds,
+        now: Instant,
+    ) -> Option<String> {
+        if let Some(min) = bounds.min_price.filter(|min| price < *min) {
+            return Some(format!("aggregate {} below min_price {}", price, min));
+        }
+        if let Some(max) = bounds.max_price.filter(|max| price > *max) {
+            return Some(format!("aggregate {} above max_price {}", price, max));
+        }
+        let max_change_pct = bounds.max_change_pct?;
+        let last = self.last.lock().unwrap();
+        let last = last.get(symbol).filter(|last| !last.manual_override)?;
+        let age = now.saturating_duration_since(last.at);
+        let change_pct = deviation_bps(last.price, price) / 100.0;
+        (age < bounds.max_change_interval && change_pct > max_change_pct).then(|| {
+            format!(
+                "aggregate {} moved {:.2}% from {} published {} s ago (max_change_pct {})",
+                price,
+                change_pct,
+                last.price,
+                age.as_secs(),
+                max_change_pct
+            )
+        })
+    }
+
+    /// Records an update that landed, which later ones are gated against.
+    pub fn record_published(&self, symbol: &str, price: f64, manual_override: bool, at: Instant) {
+        self.last.lock().unwrap().insert(
+            symbol.to_string(),
+            Published {
+                price,
+                manual_override,
+                at,
+            },
+        );
+    }
+
+    /// Drops the last published price of `symbol`, so its next update is published whatever
+    /// its deviation, e.g. to clear an on-chain stale flag.
+    pub fn forget(&self, symbol: &str) {
+        self.last.lock().unwrap().remove(symbol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publishes_on_deviation_or_heartbeat() {
+        let policy = Policy {
+            deviation_bps: 50.0,
+            heartbeat: Duration::from_secs(3600),
+        };
+        let gate = PublishPolicy::new();
+        let t0 = Instant::now();
+        let at = |s| t0 + Duration::from_secs(s);
+        assert_eq!(
+            gate.decide("BTC/USD", 100.0, false, policy, t0),
+            Decision::Publish
+        );
+        gate.record_published("BTC/USD", 100.0, false, t0);
+
+        match gate.decide("BTC/USD", 100.4, false, policy, at(60)) {
+            Decision::Hold {
+                deviation_bps,
+                due_in,
+            } => {
+                assert!((deviation_bps - 40.0).abs() < 1e-6);
+                assert_eq!(due_in, Duration::from_secs(3540));
+            }
+            decision => panic!("expected a hold, got {:?}", decision),
+        }
+        // Either direction counts, and the threshold itself publishes.
+        assert_eq!(
+            gate.decide("BTC/USD", 99.5, false, policy, at(60)),
+            Decision::Publish
+        );
+        assert_eq!(
+            gate.decide("BTC/USD", 100.0, false, policy, at(3600)),
+            Decision::Publish
+        );
+        assert_eq!(
+            gate.decide("BTC/USD", 100.0, true, policy, at(60)),
+            Decision::Publish
+        );
+        let every_update = Policy {
+            deviation_bps: 0.0,
+            ..policy
+        };
+        assert_eq!(
+            gate.decide("BTC/USD", 100.0, false, every_update, at(60)),
+            Decision::Publish
+        );
+        gate.forget("BTC/USD");
+        assert_eq!(
+            gate.decide("BTC/USD", 100.0, false, policy, at(60)),
+            Decision::Publish
+        );
+    }
+
+    #[test]
+    fn test_bounds_violation() {
+        let bounds = Bounds {
+            min_price: Some(10.0),
+            max_price: Some(1_000.0),
+            max_change_pct: Some(20.0),
+            max_change_interval: Duration::from_secs(300),
+        };
+        let gate = PublishPolicy::new();
+        let t0 = Instant::now();
+        let at = |s| t0 + Duration::from_secs(s);
+        assert_eq!(
+            gate.bounds_violation("BTC/USD", 5.0, bounds, t0).as_deref(),
+            Some("aggregate 5 below min_price 10")
+        );
+        assert!(
+            gate.bounds_violation("BTC/USD", 1_500.0, bounds, t0)
+                .is_some()
+        );
+        // Nothing published yet to compare against.
+        assert_eq!(gate.bounds_violation("BTC/USD", 100.0, bounds, t0), None);
+
+        gate.record_published("BTC/USD", 100.0, false, t0);
+        assert_eq!(
+            gate.bounds_violation("BTC/USD", 119.0, bounds, at(60)),
+            None
+        );
+        assert_eq!(
+            gate.bounds_violation("BTC/USD", 75.0, bounds, at(60))
+                .as_deref(),
+            Some("aggregate 75 moved 25.00% from 100 published 60 s ago (max_change_pct 20)")
+        );
+        // Outside the interval, or after a manual price, the move is not checked.
+        assert_eq!(
+            gate.bounds_violation("BTC/USD", 75.0, bounds, at(300)),
+            None
+        );
+        gate.record_published("BTC/USD", 100.0, true, t0);
+        assert_eq!(gate.bounds_violation("BTC/USD", 75.0, bounds, at(60)), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::metrics::Metrics;
+use crate::schema::{PriceUpdate, WsMessage};
+
+/// Fans published prices out to the `/ws` subscribers. Each subscriber has its own bounded
+/// backlog of up to `queue_len` messages: one that falls further behind, e.g. a stalled
+/// dashboard, loses the oldest messages and is told how many with a `lagged` message,
+/// while the others are unaffected and memory stays bounded.
+pub struct PushHub {
+    sender: broadcast::Sender<WsMessage>,
+    metrics: Arc<Metrics>,
+    /// The last price published for each symbol, for `GET /price`.
+    latest: Mutex<HashMap<String, PriceUpdate>>,
+}
+
+impl PushHub {
+    pub fn new(queue_len: usize, metrics: Arc<Metrics>) -> Self {
+        let (sender, _) = broadcast::channel(queue_len.max(1));
+        Self {
+            sender,
+            metrics,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queues `message` for every subscriber; without subscribers it is dropped.
+    pub fn publish(&self, message: WsMessage) {
+        if let WsMessage::PriceUpdate(update) = &message {
+            self.latest
+                .lock()
+                .unwrap()
+                .insert(update.symbol.clone(), update.clone());
+        }
+        let _ = self.sender.send(message);
+    }
+
+    /// The last price published for `symbol`.
+    pub fn latest(&self, symbol: &str) -> Option<PriceUpdate> {
+        self.latest.lock().unwrap().get(symbol).cloned()
+    }
+
+    pub fn subscribe(self: &Arc<Self>) -> Subscriber {
+        let subscriber = Subscriber {
+            receiver: self.sender.subscribe(),
+            hub: self.clone(),
+        };
+        self.export_subscribers(self.sender.receiver_count());
+        subscriber
+    }
+
+    fn export_subscribers(&self, subscribers: usize) {
+        self.metrics.set_gauge(
+            "oracle_ws_subscribers",
+            "Connected WebSocket subscribers",
+            &[],
+            subscribers as f64,
+        );
+    }
+}
+
+/// One subscriber's position in the hub's messages.
+pub struct Subscriber {
+    receiver: broadcast::Receiver<WsMessage>,
+    hub: Arc<PushHub>,
+}
+
+impl Subscriber {
+    /// The next message to send. After falling behind, a `Lagged` with the number of
+    /// messages dropped, then the oldest one still queued.
+    pub async fn next(&mut self) -> Option<WsMessage> {
+        match self.receiver.recv().await {
+            Ok(message) => Some(message),
+            Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                self.hub.metrics.inc_counter(
+                    "oracle_ws_dropped_messages_total",
+                    "Messages dropped for WebSocket subscribers that fell behind",
+                    &[],
+                    dropped as f64,
+                );
+                Some(WsMessage::Lagged { dropped })
+            }
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        // The receiver is only released after this.
+        let remaining = self.hub.sender.receiver_count().saturating_sub(1);
+        self.hub.export_subscribers(remaining);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(timestamp_ms: u64) -> WsMessage {
+        WsMessage::Heartbeat { timestamp_ms }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_drops_oldest_and_is_told() {
+        let metrics = Arc::new(Metrics::new());
+        let hub = Arc::new(PushHub::new(2, metrics.clone()));
+        let mut slow = hub.subscribe();
+        let mut fast = hub.subscribe();
+
+        hub.publish(heartbeat(1));
+        assert_eq!(fast.next().await, Some(heartbeat(1)));
+        for timestamp_ms in 2..=4 {
+            hub.publish(heartbeat(timestamp_ms));
+            assert_eq!(fast.next().await, Some(heartbeat(timestamp_ms)));
+        }
+        // Four messages into a backlog of two: the two oldest are gone.
+        assert_eq!(slow.next().await, Some(WsMessage::Lagged { dropped: 2 }));
+        assert_eq!(slow.next().await, Some(heartbeat(3)));
+        assert_eq!(slow.next().await, Some(heartbeat(4)));
+
+        let rendered = metrics.render();
+        assert!(
+            rendered.contains("oracle_ws_dropped_messages_total 2"),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains("oracle_ws_subscribers 2"), "{}", rendered);
+        drop(slow);
+        assert!(metrics.render().contains("oracle_ws_subscribers 1"));
+    }
+}
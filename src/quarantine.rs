@@ -0,0 +1,366 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const QUARANTINE_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// An exchange source, by its name in `symbols.<symbol>.tickers`; it is not fetched.
+    Source,
+    /// A feed or index whose circuit breaker tripped; it is not published.
+    Feed,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Source => "source",
+            Kind::Feed => "feed",
+        }
+    }
+}
+
+/// A quarantined source or tripped feed, e.g. `source:binance` or `feed:BTC/USD`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Subject {
+    pub kind: Kind,
+    pub name: String,
+}
+
+impl Subject {
+    pub fn source(name: &str) -> Self {
+        Self {
+            kind: Kind::Source,
+            name: name.to_string(),
+        }
+    }
+
+    pub fn feed(symbol: &str) -> Self {
+        Self {
+            kind: Kind::Feed,
+            name: symbol.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind.label(), self.name)
+    }
+}
+
+impl std::str::FromStr for Subject {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("source", name)) if !name.is_empty() => Ok(Subject::source(name)),
+            Some(("feed", name)) if !name.is_empty() => Ok(Subject::feed(name)),
+            _ => Err(anyhow!(
+                "'{}' is not source:<name> or feed:<symbol>, e.g. source:binance",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub kind: Kind,
+    pub name: String,
+    /// The last failure before the subject was quarantined.
+    pub reason: String,
+    pub since_ms: u64,
+    /// The subject is re-admitted on probation after this; one more failure quarantines it
+    /// again.
+    pub until_ms: u64,
+}
+
+impl Entry {
+    pub fn subject(&self) -> Subject {
+        Subject {
+            kind: self.kind,
+            name: self.name.clone(),
+        }
+    }
+
+    /// When the subject is re-admitted, as RFC 3339.
+    pub fn until(&self) -> String {
+        chrono::DateTime::from_timestamp_millis(self.until_ms as i64)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+            .unwrap_or_else(|| format!("{} ms", self.until_ms))
+    }
+}
+
+/// Quarantined subjects and consecutive failure counts, keyed by `Subject` display form.
+/// Failure counts are kept too, so a crash-restart loop cannot reset them before the
+/// threshold is reached.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineState {
+    #[serde(default)]
+    pub entries: BTreeMap<String, Entry>,
+    #[serde(default)]
+    pub failures: BTreeMap<String, u32>,
+    /// Quarantines since the subject's last success; each one lasts twice as long as the
+    /// one before, up to the maximum.
+    #[serde(default)]
+    pub trips: BTreeMap<String, u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QuarantineFile {
+    schema_version: u32,
+    #[serde(flatten)]
+    state: QuarantineState,
+}
+
+impl QuarantineState {
+    /// The entry keeping `subject` out at `now_ms`, if any.
+    pub fn active(&self, subject: &Subject, now_ms: u64) -> Option<&Entry> {
+        self.entries
+            .get(&subject.to_string())
+            .filter(|entry| now_ms < entry.until_ms)
+    }
+
+    /// Removes and returns entries whose TTL has passed by `now_ms`. Their failure counts
+    /// stay, so the first failure after re-admission quarantines them again.
+    pub fn expire(&mut self, now_ms: u64) -> Vec<Entry> {
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| now_ms >= entry.until_ms)
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired
+            .iter()
+            .filter_map(|key| self.entries.remove(key))
+            .collect()
+    }
+
+    /// Records one success (`Ok`) or failure of `subject`. Returns the new entry when this
+    /// failure brings it to `threshold` consecutive failures; a `threshold` of `0` never
+    /// quarantines. The first quarantine lasts `ttl_ms`, and each further one before a
+    /// success twice as long as the last, up to `max_ttl_ms`.
+    pub fn record(
+        &mut self,
+        subject: &Subject,
+        result: Result<(), &str>,
+        threshold: u32,
+        ttl_ms: u64,
+        max_ttl_ms: u64,
+        now_ms: u64,
+    ) -> Option<&Entry> {
+        let key = subject.to_string();
+        let error = match result {
+            Ok(()) => {
+                self.failures.remove(&key);
+                self.trips.remove(&key);
+                return None;
+            }
+            Err(error) => error,
+        };
+        let failures = {
+            let count = self.failures.entry(key.clone()).or_default();
+            *count += 1;
+            *count
+        };
+        if threshold == 0 || failures < threshold || self.active(subject, now_ms).is_some() {
+            return None;
+        }
+        let trips = {
+            let count = self.trips.entry(key.clone()).or_default();
+            *count += 1;
+            *count
+        };
+        let ttl_ms = ttl_ms
+            .saturating_mul(1 << (trips - 1).min(32))
+            .min(max_ttl_ms.max(ttl_ms));
+        let entry = Entry {
+            kind: subject.kind,
+            name: subject.name.clone(),
+            reason: format!(
+                "{} consecutive failure{}, last: {}",
+                failures,
+                if failures == 1 { "" } else { "s" },
+                error
+            ),
+            since_ms: now_ms,
+            until_ms: now_ms.saturating_add(ttl_ms),
+        };
+        self.entries.insert(key.clone(), entry);
+        self.entries.get(&key)
+    }
+
+    /// Re-admits `subject` with a clean failure count. Returns whether it was quarantined.
+    pub fn clear(&mut self, subject: &Subject) -> bool {
+        let key = subject.to_string();
+        self.failures.remove(&key);
+        self.trips.remove(&key);
+        self.entries.remove(&key).is_some()
+    }
+}
+
+/// The quarantine persisted in `quarantine.file`, so restarts keep known-bad sources and
+/// tripped feeds out until their TTL passes.
+pub struct QuarantineStore {
+    path: String,
+}
+
+impl QuarantineStore {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn load(&self) -> Result<QuarantineState> {
+        if !Path::new(&self.path).exists() {
+            return Ok(QuarantineState::default());
+        }
+        let content =
+            fs::read_to_string(&self.path).context(format!("Failed to read {}", self.path))?;
+        let file: QuarantineFile =
+            serde_json::from_str(&content).context(format!("Failed to parse {}", self.path))?;
+        Ok(file.state)
+    }
+
+    fn save(&self, state: &QuarantineState) -> Result<()> {
+        if let Some(parent) = Path::new(&self.path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+        }
+        let file = QuarantineFile {
+            schema_version: QUARANTINE_SCHEMA_VERSION,
+            state: state.clone(),
+        };
+        fs::write(&self.path, serde_json::to_string_pretty(&file)? + "\n")
+            .context(format!("Failed to write {}", self.path))
+    }
+
+    /// Applies `change` to the state on disk and saves it if anything changed. Reloading
+    /// first keeps changes made with the `quarantine` command in the meantime.
+    pub fn update<T>(&self, change: impl FnOnce(&mut QuarantineState) -> T) -> Result<T> {
+        let mut state = self.load()?;
+        let before = state.clone();
+        let result = change(&mut state);
+        if state != before {
+            self.save(&state)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarantine_after_threshold_and_probation() {
+        let binance = Subject::source("binance");
+        let mut state = QuarantineState::default();
+        assert!(
+            state
+                .record(&binance, Err("timeout"), 3, 1000, 1000, 0)
+                .is_none()
+        );
+        assert!(
+            state
+                .record(&binance, Err("timeout"), 3, 1000, 1000, 1)
+                .is_none()
+        );
+        let entry = state
+            .record(&binance, Err("502"), 3, 1000, 1000, 2)
+            .unwrap();
+        assert_eq!(entry.until_ms, 1002);
+        assert_eq!(entry.reason, "3 consecutive failures, last: 502");
+        assert!(state.active(&binance, 1001).is_some());
+
+        // Re-admitted on probation: the next failure quarantines it again.
+        assert_eq!(state.expire(1002).len(), 1);
+        assert!(state.active(&binance, 1002).is_none());
+        assert!(
+            state
+                .record(&binance, Err("502"), 3, 1000, 1000, 1003)
+                .is_some()
+        );
+
+        // A success resets the count.
+        state.clear(&binance);
+        state.record(&binance, Err("502"), 3, 1000, 1000, 0);
+        state.record(&binance, Ok(()), 3, 1000, 1000, 0);
+        state.record(&binance, Err("502"), 3, 1000, 1000, 0);
+        assert!(
+            state
+                .record(&binance, Err("502"), 3, 1000, 1000, 0)
+                .is_none()
+        );
+
+        let coinbase = Subject::source("coinbase");
+        for _ in 0..10 {
+            assert!(
+                state
+                    .record(&coinbase, Err("502"), 0, 1000, 1000, 0)
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn test_cooldown_doubles_until_a_success() {
+        let okx = Subject::source("okx");
+        let mut state = QuarantineState::default();
+        let mut trip = |now_ms| {
+            state
+                .record(&okx, Err("503"), 1, 1000, 3000, now_ms)
+                .map(|entry| entry.until_ms - now_ms)
+        };
+        assert_eq!(trip(0), Some(1000));
+        // Failing again on probation, the circuit stays open longer each time.
+        assert_eq!(trip(1000), Some(2000));
+        assert_eq!(trip(3000), Some(3000));
+        assert_eq!(trip(6000), Some(3000));
+        state.record(&okx, Ok(()), 1, 1000, 3000, 9000);
+        assert!(state.trips.is_empty());
+        let entry = state.record(&okx, Err("503"), 1, 1000, 3000, 9000).unwrap();
+        assert_eq!(entry.until_ms, 10_000);
+    }
+
+    #[test]
+    fn test_store_survives_restart() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("quarantine-test-{}", std::process::id()));
+        let path = dir.join("quarantine.json");
+        let store = QuarantineStore::new(path.to_str().unwrap());
+        let feed = Subject::feed("BTC/USD");
+        store.update(|state| {
+            state
+                .record(&feed, Err("gas"), 1, 60_000, 60_000, 10)
+                .cloned()
+        })?;
+
+        let reloaded = QuarantineStore::new(path.to_str().unwrap()).load()?;
+        assert_eq!(
+            reloaded.active(&feed, 20).unwrap().reason,
+            "1 consecutive failure, last: gas"
+        );
+        assert!(store.update(|state| state.clear(&feed))?);
+        assert!(store.load()?.entries.is_empty());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_subject() {
+        let subject: Subject = "feed:BTC/USD".parse().unwrap();
+        assert_eq!(subject, Subject::feed("BTC/USD"));
+        assert_eq!(subject.to_string(), "feed:BTC/USD");
+        assert!("binance".parse::<Subject>().is_err());
+        assert!("source:".parse::<Subject>().is_err());
+    }
+}
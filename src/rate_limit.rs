@@ -0,0 +1,302 @@
+//! Request budgets of the exchange sources. Each source spends its requests' weight from a
+//! token bucket refilled at the rate its exchange allows one IP, so adding symbols spaces
+//! requests out instead of getting the operator's IP banned. An exchange that answers `429`
+//! (or Binance's `418`, an IP ban) puts the source on hold for its `Retry-After`.
+
+use anyhow::{Result, anyhow};
+use reqwest::{Response, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+use crate::clock_drift::{self, parse_http_date_ms};
+use crate::config::RateLimitSettings;
+
+/// How long a source is held after a `429` without a usable `Retry-After`.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The exchange refused a request because the source exceeded its limit.
+#[derive(Debug)]
+pub struct RateLimited {
+    pub status: StatusCode,
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rate limited (HTTP {})", self.status.as_u16())?;
+        if let Some(retry_after) = self.retry_after {
+            write!(f, ", retry after {} s", retry_after.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// A `Retry-After` value: delay seconds, or an HTTP date compared with `now_ms`.
+pub fn parse_retry_after(value: &str, now_ms: i64) -> Option<Duration> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => parse_http_date_ms(value)
+            .map(|at_ms| Duration::from_millis(at_ms.saturating_sub(now_ms).max(0) as u64)),
+    }
+}
+
+/// `response` if it succeeded. A `429` or `418` is a `RateLimited` error, any other error
+/// status the usual error of `error_for_status`.
+pub fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, clock_drift::local_now_ms()));
+        return Err(RateLimited {
+            status,
+            retry_after,
+        }
+        .into());
+    }
+    Ok(response.error_for_status()?)
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimitSettings,
+    /// Negative once requests are waiting for weight that has not been refilled yet.
+    tokens: f64,
+    refilled_at: Instant,
+    held_until: Option<Instant>,
+}
+
+impl Bucket {
+    /// The bucket's size: `burst`, or a second's worth of weight.
+    fn capacity(&self) -> f64 {
+        let burst = self
+            .limit
+            .burst
+            .unwrap_or(self.limit.weight_per_minute / 60);
+        burst.max(self.limit.request_weight) as f64
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed_ms = now.saturating_duration_since(self.refilled_at).as_millis() as f64;
+        let per_ms = self.limit.weight_per_minute as f64 / 60_000.0;
+        self.tokens = (self.tokens + elapsed_ms * per_ms).min(self.capacity());
+        self.refilled_at = now;
+    }
+}
+
+/// The token buckets of all sources, by source name. Sources without a configured limit
+/// are not limited.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Sets `source`'s limit. Rebuilt sources keep the weight they spent and any hold.
+    pub fn configure(&self, source: &str, limit: RateLimitSettings, now: Instant) {
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(source) {
+            Some(bucket) => {
+                bucket.refill(now);
+                bucket.limit = limit;
+                bucket.tokens = bucket.tokens.min(bucket.capacity());
+            }
+            None => {
+                let mut bucket = Bucket {
+                    limit,
+                    tokens: 0.0,
+                    refilled_at: now,
+                    held_until: None,
+                };
+                bucket.tokens = bucket.capacity();
+                buckets.insert(source.to_string(), bucket);
+            }
+        }
+    }
+
+    /// Spends one request's weight and returns how long to wait before sending it. Fails
+    /// without spending anything while the source is on hold.
+    pub fn reserve(&self, source: &str, now: Instant) -> Result<Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Some(bucket) = buckets.get_mut(source) else {
+            return Ok(Duration::ZERO);
+        };
+        if let Some(until) = bucket.held_until.filter(|until| *until > now) {
+            return Err(anyhow!(
+                "{} is rate limited for another {} ms",
+                source,
+                (until - now).as_millis()
+            ));
+        }
+        bucket.refill(now);
+        bucket.tokens -= bucket.limit.request_weight as f64;
+        if bucket.tokens >= 0.0 {
+            return Ok(Duration::ZERO);
+        }
+        let per_ms = bucket.limit.weight_per_minute as f64 / 60_000.0;
+        Ok(Duration::from_millis(
+            (-bucket.tokens / per_ms).ceil() as u64
+        ))
+    }
+
+    /// Waits until `source` may send a request.
+    pub async fn acquire(&self, source: &str) -> Result<()> {
+        let wait = self.reserve(source, Instant::now())?;
+        if !wait.is_zero() {
+            log::debug!(
+                "Spacing out {} requests: waiting {} ms",
+                source,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// Holds `source`'s requests for `retry_after` after the exchange refused one, and
+    /// empties its bucket.
+    pub fn hold(&self, source: &str, retry_after: Option<Duration>, now: Instant) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(bucket) = buckets.get_mut(source) {
+            bucket.held_until = Some(now + retry_after.unwrap_or(DEFAULT_RETRY_AFTER));
+            bucket.tokens = bucket.tokens.min(0.0);
+            bucket.refilled_at = now;
+        }
+    }
+
+    /// How much longer each source is on hold, zero if it isn't.
+    pub fn holds(&self, now: Instant) -> Vec<(String, Duration)> {
+        let buckets = self.buckets.lock().unwrap();
+        buckets
+            .iter()
+            .map(|(source, bucket)| {
+                let remaining = bucket
+                    .held_until
+                    .map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+                (source.clone(), remaining)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(weight_per_minute: u32, burst: Option<u32>, request_weight: u32) -> RateLimitSettings {
+        RateLimitSettings {
+            weight_per_minute,
+            burst,
+            request_weight,
+        }
+    }
+
+    #[test]
+    fn test_requests_beyond_the_burst_are_spaced_out() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        // 10 requests a second, bursts of up to 3.
+        limiter.configure("coinbase", limit(600, Some(3), 1), start);
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve("coinbase", start).unwrap(), Duration::ZERO);
+        }
+        assert_eq!(
+            limiter.reserve("coinbase", start).unwrap(),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            limiter.reserve("coinbase", start).unwrap(),
+            Duration::from_millis(200)
+        );
+
+        // Refilled, but never beyond the burst.
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve("coinbase", later).unwrap(), Duration::ZERO);
+        }
+        assert!(limiter.reserve("coinbase", later).unwrap() > Duration::ZERO);
+
+        // Weighted requests; the default burst is a second's worth.
+        limiter.configure("binance", limit(6000, None, 2), start);
+        let sent = (0..60)
+            .take_while(|_| limiter.reserve("binance", start).unwrap().is_zero())
+            .count();
+        assert_eq!(sent, 50);
+        assert_eq!(limiter.reserve("unlimited", start).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_limited_source_is_held() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        limiter.configure("binance", limit(6000, None, 2), start);
+        limiter.hold("binance", Some(Duration::from_secs(10)), start);
+        let err = limiter
+            .reserve("binance", start + Duration::from_secs(4))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "binance is rate limited for another 6000 ms"
+        );
+        assert_eq!(
+            limiter.holds(start + Duration::from_secs(4)),
+            vec![("binance".to_string(), Duration::from_secs(6))]
+        );
+
+        // Reconfiguring, as when sources are rebuilt, keeps the hold.
+        limiter.configure("binance", limit(1200, None, 2), start);
+        assert!(
+            limiter
+                .reserve("binance", start + Duration::from_secs(9))
+                .is_err()
+        );
+        let after = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve("binance", after).unwrap(), Duration::ZERO);
+
+        limiter.hold("binance", None, after);
+        assert!(
+            limiter
+                .reserve("binance", after + Duration::from_secs(29))
+                .is_err()
+        );
+        assert!(
+            limiter
+                .reserve("binance", after + DEFAULT_RETRY_AFTER)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now_ms = parse_http_date_ms("Wed, 21 Oct 2026 07:28:00 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after(" 120 ", now_ms),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2026 07:28:30 GMT", now_ms),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2026 07:27:00 GMT", now_ms),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now_ms), None);
+
+        let limited = RateLimited {
+            status: StatusCode::IM_A_TEAPOT,
+            retry_after: Some(Duration::from_secs(120)),
+        };
+        assert_eq!(
+            limited.to_string(),
+            "rate limited (HTTP 418), retry after 120 s"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! On-demand comparison of every feed's PriceObject with what this process last published
+//! to it, for `POST /admin/reconcile`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use sui_sdk::types::base_types::ObjectID;
+
+use crate::clock_drift::local_now_ms;
+use crate::publish_policy::PublishPolicy;
+use crate::sui_publisher::{FeedState, KnownObjectsMap, SuiPublisher};
+use crate::symbol_registry::SymbolRegistry;
+
+/// How a feed's PriceObject compares with the local state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedStatus {
+    /// The object holds the last price published to it in this run.
+    InSync,
+    /// Nothing was published to the object in this run yet, e.g. just after a restart.
+    NotPublished,
+    /// The object holds another price than the last one published to it, e.g. one written by
+    /// another publisher. The local records of the feed are dropped, so its next update is
+    /// published whatever its deviation.
+    Diverged,
+    /// A configured feed without a PriceObject in `sui.known_objects_file`.
+    NoObject,
+    /// The object could not be read or its fields could not be decoded.
+    Unreadable,
+    /// A PriceObject in `sui.known_objects_file` of no configured feed.
+    Unconfigured,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedReconciliation {
+    pub symbol: String,
+    pub status: FeedStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// The price on-chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_timestamp_ms: Option<u64>,
+    /// The last price published to the object in this run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    pub reconciled_at_ms: u64,
+    /// How many feeds have each status.
+    pub summary: BTreeMap<FeedStatus, usize>,
+    pub feeds: Vec<FeedReconciliation>,
+}
+
+impl ReconcileReport {
+    fn new(feeds: Vec<FeedReconciliation>, reconciled_at_ms: u64) -> Self {
+        let mut summary = BTreeMap::new();
+        for feed in &feeds {
+            *summary.entry(feed.status).or_insert(0) += 1;
+        }
+        Self {
+            reconciled_at_ms,
+            summary,
+            feeds,
+        }
+    }
+}
+
+fn reconciliation(
+    symbol: &str,
+    state: &FeedState,
+    local_price: Option<f64>,
+    configured: bool,
+) -> FeedReconciliation {
+    let status = if !configured {
+        FeedStatus::Unconfigured
+    } else if state.error.is_some() {
+        FeedStatus::Unreadable
+    } else {
+        match (state.price, local_price) {
+            (_, None) => FeedStatus::NotPublished,
+            // Both were read back from scaled prices, so they compare exactly as published.
+            (Some(chain), Some(local)) if chain == local => FeedStatus::InSync,
+            _ => FeedStatus::Diverged,
+        }
+    };
+    FeedReconciliation {
+        symbol: symbol.to_string(),
+        status,
+        object_id: Some(state.object_id.to_string()),
+        version: state.version,
+        chain_price: state.price,
+        chain_timestamp_ms: state.timestamp_ms,
+        local_price,
+        error: state.error.clone(),
+    }
+}
+
+/// Compares `states`, as read from the chain for the `known` objects, with the price last
+/// published to each object. Every one of `feeds` is reported, then the known objects of
+/// no configured feed.
+pub fn compare(
+    feeds: &[String],
+    known: &KnownObjectsMap,
+    states: &[FeedState],
+    last_published: impl Fn(ObjectID) -> Option<f64>,
+) -> Vec<FeedReconciliation> {
+    let states: HashMap<&str, &FeedState> = states.iter().map(|s| (s.symbol.as_str(), s)).collect();
+    let mut report: Vec<FeedReconciliation> = feeds
+        .iter()
+        .map(|symbol| match states.get(symbol.as_str()) {
+            Some(state) => reconciliation(symbol, state, last_published(state.object_id), true),
+            None => FeedReconciliation {
+                symbol: symbol.clone(),
+                status: FeedStatus::NoObject,
+                object_id: known.get(symbol).map(ObjectID::to_string),
+                version: None,
+                chain_price: None,
+                chain_timestamp_ms: None,
+                local_price: None,
+                error: None,
+            },
+        })
+        .collect();
+    let mut unconfigured: Vec<&FeedState> = states
+        .values()
+        .filter(|state| !feeds.contains(&state.symbol))
+        .copied()
+        .collect();
+    unconfigured.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    report.extend(
+        unconfigured.into_iter().map(|state| {
+            reconciliation(&state.symbol, state, last_published(state.object_id), false)
+        }),
+    );
+    report
+}
+
+/// Runs a reconciliation, so the HTTP API can be tested without a node.
+#[async_trait]
+pub trait Reconcile: Send + Sync {
+    async fn reconcile(&self) -> Result<ReconcileReport>;
+}
+
+/// Reconciles the pipeline's publisher and publish gate with the chain.
+pub struct Reconciler {
+    pub publisher: Arc<SuiPublisher>,
+    pub policy: Arc<PublishPolicy>,
+    /// The configured and registered feeds.
+    pub symbols: Arc<SymbolRegistry>,
+    /// The pipeline's publishing lock, held so no update lands between the read and the
+    /// comparison.
+    pub publishing: Arc<tokio::sync::Mutex<()>>,
+}
+
+#[async_trait]
+impl Reconcile for Reconciler {
+    async fn reconcile(&self) -> Result<ReconcileReport> {
+        let settings = self.symbols.settings();
+        let mut feeds: Vec<String> = settings
+            .feed_symbols()
+            .into_iter()
+            .map(str::to_string)
+            .chain(settings.derived_feeds())
+            .chain(settings.computed_feeds().map(str::to_string))
+            .collect();
+        feeds.sort();
+
+        let _publishing = self.publishing.lock().await;
+        let known = self.publisher.known_objects()?;
+        let states = self.publisher.read_feed_states(&known).await?;
+        let feeds = compare(&feeds, &known, &states, |object_id| {
+            self.publisher.last_submitted_price(object_id)
+        });
+        for feed in feeds.iter().filter(|f| f.status == FeedStatus::Diverged) {
+            log::warn!(
+                "{} diverged from its PriceObject: {:?} on-chain, {:?} last published; its next \
+                 update is published",
+                feed.symbol,
+                feed.chain_price,
+                feed.local_price
+            );
+            self.policy.forget(&feed.symbol);
+            if let Some(object_id) = known.get(&feed.symbol) {
+                self.publisher.forget_submitted(*object_id);
+            }
+        }
+        let report = ReconcileReport::new(feeds, local_now_ms() as u64);
+        log::info!(
+            "Reconciled {} feeds: {:?}",
+            report.feeds.len(),
+            report.summary
+        );
+        Ok(report)
+    }
+}
+
+/// Stands in for the chain in tests.
+#[cfg(test)]
+pub struct NoChain;
+
+#[cfg(test)]
+#[async_trait]
+impl Reconcile for NoChain {
+    async fn reconcile(&self) -> Result<ReconcileReport> {
+        Err(anyhow::anyhow!("no Sui node to read PriceObjects from"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(symbol: &str, byte: u8, price: Option<f64>, error: Option<&str>) -> FeedState {
+        FeedState {
+            symbol: symbol.to_string(),
+            object_id: ObjectID::from_single_byte(byte),
+            version: Some(7),
+            price,
+            timestamp_ms: Some(1_000),
+            decimals: Some(crate::sui_publisher::DECIMALS),
+            staleness_ms: Some(5),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_compare_with_chain() {
+        let feeds: Vec<String> = ["BTC/USD", "ETH/USD", "SOL/USD", "SUI/USD", "XRP/USD"]
+            .map(str::to_string)
+            .to_vec();
+        let states = [
+            state("BTC/USD", 1, Some(65_000.0), None),
+            state("ETH/USD", 2, Some(3_100.0), None),
+            state("SOL/USD", 3, Some(150.0), None),
+            state("SUI/USD", 4, None, Some("missing field price")),
+            state("DOGE/USD", 5, Some(0.1), None),
+        ];
+        let known: KnownObjectsMap = states
+            .iter()
+            .map(|s| (s.symbol.clone(), s.object_id))
+            .collect();
+        let published = HashMap::from([
+            (ObjectID::from_single_byte(1), 65_000.0),
+            (ObjectID::from_single_byte(2), 3_000.0),
+        ]);
+        let report = compare(&feeds, &known, &states, |id| published.get(&id).copied());
+        let statuses: Vec<(&str, FeedStatus)> = report
+            .iter()
+            .map(|f| (f.symbol.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("BTC/USD", FeedStatus::InSync),
+                ("ETH/USD", FeedStatus::Diverged),
+                ("SOL/USD", FeedStatus::NotPublished),
+                ("SUI/USD", FeedStatus::Unreadable),
+                ("XRP/USD", FeedStatus::NoObject),
+                ("DOGE/USD", FeedStatus::Unconfigured),
+            ]
+        );
+        assert_eq!(report[1].chain_price, Some(3_100.0));
+        assert_eq!(report[1].local_price, Some(3_000.0));
+
+        let report = ReconcileReport::new(report, 1);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["summary"]["in_sync"], 1);
+        assert_eq!(
+            json["feeds"][4],
+            serde_json::json!({"symbol": "XRP/USD", "status": "no_object"})
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::{LogFormat, LoggingSettings, RedactionSettings};
+
+const MASK: &str = "[REDACTED]";
+
+enum Replacement {
+    Fixed(&'static str),
+    /// Keeps the first characters of the match.
+    Prefix(usize),
+    /// Keeps the capture groups around the secret: `$1$2[REDACTED]`.
+    Credential,
+    /// Keeps what precedes the path and its file name.
+    FileName,
+}
+
+/// Masks deployment details in log lines according to `[logging.redaction]`.
+pub struct Redactor {
+    rules: Vec<(Regex, Replacement)>,
+}
+
+impl Redactor {
+    pub fn new(settings: &RedactionSettings) -> Result<Self> {
+        let mut rules = Vec::new();
+        // Credentials first, so a token is never left half-masked by a later rule.
+        if settings.api_keys {
+            rules.push((
+                Regex::new(r"(?i)\b(bearer)(\s+)[A-Za-z0-9._~+/=-]+")?,
+                Replacement::Credential,
+            ));
+            rules.push((
+                Regex::new(
+                    r#"(?i)\b(api[_-]?key|apikey|access[_-]?key|token|secret|password|passphrase)(["']?\s*[:=]\s*["']?)[^\s"'&,;]+"#,
+                )?,
+                Replacement::Credential,
+            ));
+        }
+        if settings.addresses {
+            rules.push((
+                Regex::new(r"\b0x[0-9a-fA-F]{64}\b")?,
+                Replacement::Fixed("0x[address]"),
+            ));
+        }
+        if settings.digests {
+            rules.push((
+                Regex::new(r"\b[1-9A-HJ-NP-Za-km-z]{43,44}\b")?,
+                Replacement::Prefix(settings.digest_prefix_len),
+            ));
+        }
+        if settings.paths {
+            // The regex crate has no look-behind: the preceding character is captured so
+            // that the path part of a URL is left alone.
+            rules.push((
+                Regex::new(r#"(^|[\s"'=(\[])(?:/[\w.-]+)+/([\w.-]+)"#)?,
+                Replacement::FileName,
+            ));
+        }
+        for pattern in &settings.patterns {
+            let regex = Regex::new(pattern)
+                .context(format!("Invalid logging.redaction pattern '{}'", pattern))?;
+            rules.push((regex, Replacement::Fixed(MASK)));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        for (regex, replacement) in &self.rules {
+            let replaced = regex.replace_all(&line, |caps: &Captures| match replacement {
+                Replacement::Fixed(mask) => mask.to_string(),
+                Replacement::Prefix(len) => {
+                    format!("{}…", caps[0].chars().take(*len).collect::<String>())
+                }
+                Replacement::Credential => format!("{}{}{}", &caps[1], &caps[2], MASK),
+                Replacement::FileName => format!("{}…/{}", &caps[1], &caps[2]),
+            });
+            if let Cow::Owned(replaced) = replaced {
+                line = Cow::Owned(replaced);
+            }
+        }
+        line
+    }
+}
+
+/// Collects an event's fields into a JSON object. Events bridged from `log` carry their
+/// target and location as `log.*` fields; the target is kept apart, the rest dropped.
+struct JsonVisitor<'a> {
+    fields: &'a mut Map<String, Value>,
+    target: Option<String>,
+}
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        match field.name() {
+            "log.target" => self.target = value.as_str().map(str::to_string),
+            name if name.starts_with("log.") => {}
+            name => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::from(format!("{:?}", value)));
+    }
+}
+
+/// One flat JSON object per line: `timestamp`, `level`, `target`, `message`, and the fields
+/// of the spans the line was logged in (`cycle`, `symbol`, `source`, `digest`), innermost
+/// winning, and `instance.labels` below those. Masked by `redactor` as a whole when set.
+struct JsonLines {
+    redactor: Option<Redactor>,
+    timezone: Tz,
+    labels: BTreeMap<String, String>,
+}
+
+/// `at` in RFC 3339 with `timezone`'s offset, `Z` for UTC.
+fn format_timestamp(at: DateTime<Utc>, timezone: Tz, precision: SecondsFormat) -> String {
+    at.with_timezone(&timezone).to_rfc3339_opts(precision, true)
+}
+
+impl<S, N> FormatEvent<S, N> for JsonLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(format_timestamp(
+                Utc::now(),
+                self.timezone,
+                SecondsFormat::Millis,
+            )),
+        );
+        line.insert(
+            "level".to_string(),
+            Value::from(event.metadata().level().as_str()),
+        );
+        for (label, value) in &self.labels {
+            line.insert(label.clone(), Value::from(value.as_str()));
+        }
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>()
+                    && let Ok(Value::Object(fields)) = serde_json::from_str(fields)
+                {
+                    line.extend(fields);
+                }
+            }
+        }
+        let mut visitor = JsonVisitor {
+            fields: &mut line,
+            target: None,
+        };
+        event.record(&mut visitor);
+        let target = visitor
+            .target
+            .unwrap_or_else(|| event.metadata().target().to_string());
+        line.insert("target".to_string(), Value::from(target));
+        let json = serde_json::to_string(&line).map_err(|_| fmt::Error)?;
+        match &self.redactor {
+            Some(redactor) => writeln!(writer, "{}", redactor.redact(&json)),
+            None => writeln!(writer, "{}", json),
+        }
+    }
+}
+
+/// Sets up logging as `settings.format` selects, configured as usual through `RUST_LOG`
+/// and redacting every line when `settings.redaction` enables it. Text lines come from
+/// `env_logger`; JSON lines from a `tracing` subscriber that `log` records are bridged to.
+/// Timestamps carry `timezone`'s offset, and every line carries `labels`.
+pub fn init_logger(
+    settings: Option<&LoggingSettings>,
+    timezone: Tz,
+    labels: BTreeMap<String, String>,
+) -> Result<()> {
+    let redactor = settings
+        .map(|s| &s.redaction)
+        .filter(|s| s.enabled)
+        .map(Redactor::new)
+        .transpose()?;
+    if settings.is_some_and(|s| s.format == LogFormat::Json) {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("error"));
+        return tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(JsonFields::new())
+                    .event_format(JsonLines {
+                        redactor,
+                        timezone,
+                        labels,
+                    })
+                    .with_writer(std::io::stderr),
+            )
+            .try_init()
+            .map_err(|e| anyhow!("Failed to set up JSON logging: {}", e));
+    }
+    let mut builder = env_logger::Builder::from_default_env();
+    if redactor.is_some() || timezone != Tz::UTC || !labels.is_empty() {
+        let labels: String = labels
+            .iter()
+            .map(|(label, value)| format!(" {}={}", label, value))
+            .collect();
+        builder.format(move |buf, record| {
+            let message = record.args().to_string();
+            writeln!(
+                buf,
+                "[{} {:<5} {}{}] {}",
+                format_timestamp(Utc::now(), timezone, SecondsFormat::Secs),
+                record.level(),
+                record.target(),
+                labels,
+                match &redactor {
+                    Some(redactor) => redactor.redact(&message),
+                    None => Cow::Borrowed(message.as_str()),
+                }
+            )
+        });
+    }
+    builder.init();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn redactor() -> Redactor {
+        Redactor::new(&RedactionSettings {
+            enabled: true,
+            patterns: vec![r"oracle-\d+\.internal".to_string()],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_masks_deployment_details() {
+        let r = redactor();
+        assert_eq!(
+            r.redact(&format!("Signer address: 0x{}", "ab".repeat(32))),
+            "Signer address: 0x[address]"
+        );
+        assert_eq!(
+            r.redact("Digest: 7sGqZc6fWtRX9FcUaZq9gQZKxjFW7ycECV1RGTrBBmuw"),
+            "Digest: 7sGqZc…"
+        );
+        assert_eq!(
+            r.redact("GET https://x.example/api?token=s3cr3t&symbol=BTC (Bearer abc.def)"),
+            "GET https://x.example/api?token=[REDACTED]&symbol=BTC (Bearer [REDACTED])"
+        );
+        assert_eq!(
+            r.redact("Failed to read /etc/neo-oracle/sui.keystore on oracle-3.internal"),
+            "Failed to read …/sui.keystore on [REDACTED]"
+        );
+        // URL paths and relative paths are not file system paths to hide.
+        assert_eq!(
+            r.redact("Fetching https://api.binance.com/api/v3/ticker/price"),
+            "Fetching https://api.binance.com/api/v3/ticker/price"
+        );
+    }
+
+    #[test]
+    fn test_rules_can_be_turned_off() {
+        let r = Redactor::new(&RedactionSettings {
+            enabled: true,
+            paths: false,
+            digests: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let line =
+            "Wrote /var/lib/oracle/state.json, digest 7sGqZc6fWtRX9FcUaZq9gQZKxjFW7ycECV1RGTrBBmuw";
+        assert_eq!(r.redact(line), line);
+
+        assert!(
+            Redactor::new(&RedactionSettings {
+                patterns: vec!["(".to_string()],
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(JsonLines {
+                    redactor: Some(redactor()),
+                    timezone: Tz::UTC,
+                    labels: BTreeMap::from([
+                        ("instance".to_string(), "oracle-a".to_string()),
+                        ("symbol".to_string(), "shadowed".to_string()),
+                    ]),
+                })
+                .with_writer(move || BufWriter(writer.clone())),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let _cycle = tracing::error_span!("cycle", cycle = 7).entered();
+            let _feed = tracing::error_span!("feed", symbol = "BTC/USD").entered();
+            tracing::warn!(digest = "D1", "Publishing from oracle-3.internal");
+        });
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(line["cycle"], 7);
+        assert_eq!(line["symbol"], "BTC/USD");
+        assert_eq!(line["instance"], "oracle-a");
+        assert_eq!(line["digest"], "D1");
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "Publishing from [REDACTED]");
+        assert_eq!(line["target"], module_path!());
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_timestamps_in_the_reporting_zone() {
+        let at = DateTime::parse_from_rfc3339("2026-07-01T12:00:00.250Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            format_timestamp(at, Tz::UTC, SecondsFormat::Millis),
+            "2026-07-01T12:00:00.250Z"
+        );
+        assert_eq!(
+            format_timestamp(at, chrono_tz::Europe::Berlin, SecondsFormat::Secs),
+            "2026-07-01T14:00:00+02:00"
+        );
+    }
+
+    struct BufWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for BufWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use axum::body::Bytes;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Rendered response bodies, each kept for `ttl` after it was rendered. Pollers of the same
+/// resource share one lookup and serialization per TTL, instead of each reading pipeline
+/// state under its locks.
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<String, (Instant, Bytes)>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The body cached under `key`, or else the one `render` returns. `None` is not cached,
+    /// so a resource that appears is served at once.
+    pub fn get_or_render(
+        &self,
+        key: &str,
+        render: impl FnOnce() -> Option<Bytes>,
+    ) -> Option<Bytes> {
+        self.get_or_render_at(key, Instant::now(), render)
+    }
+
+    fn get_or_render_at(
+        &self,
+        key: &str,
+        now: Instant,
+        render: impl FnOnce() -> Option<Bytes>,
+    ) -> Option<Bytes> {
+        if let Some((rendered_at, body)) = self.entries.read().unwrap().get(key)
+            && now.saturating_duration_since(*rendered_at) < self.ttl
+        {
+            return Some(body.clone());
+        }
+        let body = render()?;
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_string(), (now, body.clone()));
+        Some(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_is_rendered_once_per_ttl() {
+        let cache = ResponseCache::new(Duration::from_secs(5));
+        let start = Instant::now();
+        let body = |text: &'static str| move || Some(Bytes::from(text));
+
+        assert_eq!(cache.get_or_render_at("BTC/USD", start, || None), None);
+        let first = cache.get_or_render_at("BTC/USD", start, body("1"));
+        assert_eq!(first, Some(Bytes::from("1")));
+        let later = start + Duration::from_secs(4);
+        assert_eq!(
+            cache.get_or_render_at("BTC/USD", later, || unreachable!("cached")),
+            first
+        );
+        assert_eq!(
+            cache.get_or_render_at("ETH/USD", later, body("2")),
+            Some(Bytes::from("2"))
+        );
+
+        let expired = start + Duration::from_secs(5);
+        assert_eq!(
+            cache.get_or_render_at("BTC/USD", expired, body("3")),
+            Some(Bytes::from("3"))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use crate::metrics::Metrics;
+
+/// The endpoint to send the next requests to, and whether it must pass a health probe
+/// first because it is not the one currently in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Choice {
+    pub index: usize,
+    pub probe: bool,
+}
+
+/// Health of the Sui fullnodes in `sui.rpc_url` followed by `sui.fallback_rpc_urls`. An
+/// endpoint that fails cools down for `rpc_cooldown_seconds`; requests go to the first one
+/// in config order that is not cooling down, so the primary is used again as soon as its
+/// cooldown ends and it passes a probe.
+#[derive(Debug)]
+pub struct RpcHealth {
+    urls: Vec<String>,
+    cooldown: Duration,
+    cooling_until: Vec<Option<Instant>>,
+    active: Option<usize>,
+}
+
+impl RpcHealth {
+    pub fn new(urls: Vec<String>, cooldown: Duration) -> Self {
+        Self {
+            cooling_until: vec![None; urls.len()],
+            urls,
+            cooldown,
+            active: None,
+        }
+    }
+
+    pub fn url(&self, index: usize) -> &str {
+        &self.urls[index]
+    }
+
+    /// The first endpoint not cooling down at `now`, or the one whose cooldown ends first
+    /// when all of them are.
+    pub fn choose(&self, now: Instant) -> Choice {
+        let index = self
+            .cooling_until
+            .iter()
+            .position(|until| until.is_none_or(|until| now >= until))
+            .unwrap_or_else(|| {
+                (0..self.urls.len())
+                    .min_by_key(|i| self.cooling_until[*i])
+                    .expect("sui.rpc_url is always configured")
+            });
+        Choice {
+            index,
+            probe: self.active != Some(index),
+        }
+    }
+
+    /// Makes `index` the endpoint in use after it connected or passed a probe.
+    pub fn record_success(&mut self, index: usize) {
+        self.cooling_until[index] = None;
+        if self.active == Some(index) {
+            return;
+        }
+        if self.active.is_some() || index > 0 {
+            log::warn!("Sui RPC switched to {}", self.urls[index]);
+        }
+        self.active = Some(index);
+    }
+
+    /// Cools `index` down after a timeout or error, so the next request fails over.
+    pub fn record_failure(&mut self, index: usize, now: Instant, error: &anyhow::Error) {
+        self.cooling_until[index] = Some(now + self.cooldown);
+        if self.active == Some(index) {
+            self.active = None;
+        }
+        log::warn!(
+            "Sui RPC {} failed; not using it for {} s: {:#}",
+            self.urls[index],
+            self.cooldown.as_secs(),
+            error
+        );
+    }
+
+    /// Per endpoint, labelled `primary` or `fallback_<n>` so URLs with API keys stay out of
+    /// the metrics.
+    pub fn export_metrics(&self, metrics: &Metrics, now: Instant) {
+        for (i, until) in self.cooling_until.iter().enumerate() {
+            let endpoint = match i {
+                0 => "primary".to_string(),
+                n => format!("fallback_{}", n),
+            };
+            let labels = [("endpoint", endpoint.as_str())];
+            metrics.set_gauge(
+                "oracle_sui_rpc_endpoint_selected",
+                "Whether the Sui RPC endpoint is the one currently in use",
+                &labels,
+                f64::from(self.active == Some(i)),
+            );
+            metrics.set_gauge(
+                "oracle_sui_rpc_endpoint_healthy",
+                "Whether the Sui RPC endpoint is usable (1) or cooling down after a failure (0)",
+                &labels,
+                f64::from(until.is_none_or(|until| now >= until)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_fails_over_and_returns_to_primary_after_cooldown() {
+        let urls = ["https://primary", "https://fallback1", "https://fallback2"];
+        let mut health = RpcHealth::new(
+            urls.iter().map(|u| u.to_string()).collect(),
+            Duration::from_secs(60),
+        );
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let choice = |index, probe| Choice { index, probe };
+
+        assert_eq!(health.choose(at(0)), choice(0, true));
+        health.record_success(0);
+        assert_eq!(health.choose(at(0)), choice(0, false));
+
+        health.record_failure(0, at(10), &anyhow!("timed out"));
+        assert_eq!(health.choose(at(10)), choice(1, true));
+        health.record_success(1);
+        assert_eq!(health.choose(at(20)), choice(1, false));
+
+        // The primary is probed again once its cooldown ends; a failed probe renews it.
+        assert_eq!(health.choose(at(70)), choice(0, true));
+        health.record_failure(0, at(70), &anyhow!("503"));
+        assert_eq!(health.choose(at(80)), choice(1, false));
+        assert_eq!(health.choose(at(130)), choice(0, true));
+        health.record_success(0);
+        assert_eq!(health.choose(at(130)), choice(0, false));
+
+        // With every endpoint cooling down, the one that recovers first is tried.
+        health.record_failure(1, at(200), &anyhow!("503"));
+        health.record_failure(0, at(210), &anyhow!("503"));
+        health.record_failure(2, at(220), &anyhow!("503"));
+        assert_eq!(health.choose(at(230)), choice(1, true));
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::config::Settings;
+use crate::schema::{self, RuntimeInfo, SourceInfo};
+
+/// Commit the binary was built from, embedded by `build.rs`.
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+/// SHA-256 over the canonical JSON of the effective settings (after local overrides and
+/// paper mode), so two instances can be compared by a single hex string. `instance.labels`
+/// tell instances apart, so they are left out.
+pub fn config_digest(settings: &Settings) -> Result<String> {
+    // Round-trip through `Value` so map keys are emitted in sorted order.
+    let mut value = serde_json::to_value(settings)?;
+    if let Some(settings) = value.as_object_mut() {
+        settings.remove("instance");
+    }
+    let canonical = serde_json::to_string(&value)?;
+    Ok(hex::encode(Sha256::digest(canonical.as_bytes())))
+}
+
+/// Best-effort name of the Sui network behind an RPC URL.
+pub fn network_name(rpc_url: &str) -> &'static str {
+    let url = rpc_url.to_ascii_lowercase();
+    if url.contains("127.0.0.1") || url.contains("localhost") {
+        "localnet"
+    } else if url.contains("testnet") {
+        "testnet"
+    } else if url.contains("devnet") {
+        "devnet"
+    } else if url.contains("mainnet") {
+        "mainnet"
+    } else {
+        "custom"
+    }
+}
+
+pub fn collect(
+    settings: &Settings,
+    publisher_address: &str,
+    started_at_ms: u64,
+) -> Result<RuntimeInfo> {
+    let mut sources = vec![
+        SourceInfo {
+            name: "binance".to_string(),
+            symbols: settings.apis.binance.symbols.clone(),
+        },
+        SourceInfo {
+            name: "coinbase".to_string(),
+            symbols: settings.apis.coinbase.symbols.clone(),
+        },
+    ];
+    if let Some(okx) = &settings.apis.okx {
+        sources.push(SourceInfo {
+            name: "okx".to_string(),
+            symbols: okx.symbols.clone(),
+        });
+    }
+    if let Some(bybit) = &settings.apis.bybit {
+        sources.push(SourceInfo {
+            name: "bybit".to_string(),
+            symbols: bybit.symbols.clone(),
+        });
+    }
+    if let Some(coingecko) = &settings.apis.coingecko {
+        sources.push(SourceInfo {
+            name: "coingecko".to_string(),
+            symbols: coingecko.exchange.symbols.clone(),
+        });
+    }
+    let strategies = BTreeMap::from([("aggregation".to_string(), "mean".to_string())]);
+
+    Ok(RuntimeInfo {
+        schema_version: schema::SCHEMA_VERSION.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: GIT_HASH.to_string(),
+        config_digest: config_digest(settings)?,
+        network: settings
+            .sui
+            .network
+            .map_or_else(|| network_name(&settings.sui.rpc_url), |n| n.name())
+            .to_string(),
+        rpc_url: settings.sui.rpc_url.clone(),
+        paper_mode: settings.general.paper_mode,
+        publisher_address: publisher_address.to_string(),
+        package_id: settings.sui.package_id.clone(),
+        sources,
+        strategies,
+        started_at_ms,
+        labels: settings.instance.labels.clone(),
+    })
+}
+
+/// Emits the runtime info as a single structured log record.
+pub fn log_startup_banner(info: &RuntimeInfo) {
+    match serde_json::to_string(info) {
+        Ok(json) => log::info!("startup {}", json),
+        Err(e) => log::warn!("Failed to serialize startup info: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    fn settings(toml: &str) -> Settings {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    const BASE: &str = r#"
+[apis.binance]
+base_url = "https://api.binance.com/api/v3/ticker/price"
+symbols = ["BTCUSDT"]
+
+[apis.coinbase]
+base_url = "https://api.exchange.coinbase.com/products"
+symbols = ["BTC-USD"]
+
+[general]
+fetch_interval_seconds = 5
+"#;
+
+    #[test]
+    fn test_config_digest_is_stable_and_sensitive() {
+        let a = config_digest(&settings(BASE)).unwrap();
+        let b = config_digest(&settings(BASE)).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 64);
+
+        let changed = BASE.replace("fetch_interval_seconds = 5", "fetch_interval_seconds = 6");
+        assert_ne!(a, config_digest(&settings(&changed)).unwrap());
+
+        let mut labelled = settings(BASE);
+        labelled
+            .instance
+            .labels
+            .insert("instance".to_string(), "oracle-b".to_string());
+        assert_eq!(a, config_digest(&labelled).unwrap());
+    }
+
+    #[test]
+    fn test_network_name() {
+        assert_eq!(
+            network_name("https://fullnode.testnet.sui.io:443"),
+            "testnet"
+        );
+        assert_eq!(
+            network_name("https://fullnode.mainnet.sui.io:443"),
+            "mainnet"
+        );
+        assert_eq!(network_name("http://127.0.0.1:9000"), "localnet");
+        assert_eq!(network_name("https://rpc.example.com"), "custom");
+    }
+
+    #[test]
+    fn test_collect_lists_sources() {
+        let info = collect(&settings(BASE), "0xabc", 7).unwrap();
+        assert_eq!(info.network, "testnet");
+        assert_eq!(info.publisher_address, "0xabc");
+        assert_eq!(info.sources.len(), 2);
+        assert_eq!(info.sources[0].symbols, vec!["BTCUSDT"]);
+        assert_eq!(info.strategies["aggregation"], "mean");
+        assert_eq!(info.started_at_ms, 7);
+    }
+}
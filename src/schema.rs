@@ -0,0 +1,461 @@
+//! Versioned wire formats for everything the oracle exposes to external consumers:
+//! HTTP API responses, WebSocket broadcasts and the gRPC service
+//! (`proto/oracle/v1/oracle.proto`). Types only the HTTP API serves are unused in builds
+//! without the `http-api` feature.
+//!
+//! Compatibility rules for `SCHEMA_VERSION`:
+//! - Adding an optional field is a minor bump; consumers must ignore unknown fields.
+//! - Renaming, removing or retyping a field is a major bump.
+//! - Field names here and in the proto file must stay in sync (enforced by tests).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Schema version carried by every top-level payload, as `MAJOR.MINOR`.
+pub const SCHEMA_VERSION: &str = "1.0";
+
+/// The proto definition of the gRPC service, kept next to the serde types it mirrors.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+pub const PROTO_DEFINITION: &str = include_str!("../proto/oracle/v1/oracle.proto");
+
+/// `u64` values are encoded as decimal strings so JavaScript consumers don't lose precision,
+/// matching how Sui JSON-RPC renders Move `u64`s.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+mod u64_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn default_schema_version() -> String {
+    SCHEMA_VERSION.to_string()
+}
+
+/// A single source's contribution to an aggregated price.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SourcePrice {
+    pub source: String,
+    pub price: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_at_ms: Option<u64>,
+}
+
+/// An aggregated price for one feed, as published (or about to be published) on-chain.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PriceUpdate {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub symbol: String,
+    pub price: f64,
+    /// `price` scaled by `10^decimals`, exactly as written on-chain.
+    #[serde(with = "u64_string")]
+    #[schemars(with = "String")]
+    pub scaled_price: u64,
+    pub decimals: u8,
+    pub timestamp_ms: u64,
+    #[serde(default)]
+    pub sources: Vec<SourcePrice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_digest: Option<String>,
+}
+
+/// On-chain status of one feed (HTTP `GET /feeds`, `chain-status --json`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FeedStatus {
+    pub symbol: String,
+    pub object_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staleness_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response body listing feed statuses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FeedStatusList {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub feeds: Vec<FeedStatus>,
+}
+
+/// A coin type and the feed that prices it (HTTP `GET /assets`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AssetInfo {
+    /// Canonical Move type name, with a full-length address.
+    pub coin_type: String,
+    pub feed: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// The feed's PriceObject, once it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<String>,
+}
+
+/// Response body listing the priced assets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AssetList {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub assets: Vec<AssetInfo>,
+}
+
+/// Messages pushed to WebSocket subscribers, tagged by `type`.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsMessage {
+    PriceUpdate(PriceUpdate),
+    Heartbeat {
+        timestamp_ms: u64,
+    },
+    /// Sent when a slow subscriber's queue overflowed and messages were dropped.
+    Lagged {
+        dropped: u64,
+    },
+}
+
+/// A configured price source and the exchange symbols requested from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SourceInfo {
+    pub name: String,
+    pub symbols: Vec<String>,
+}
+
+/// What a running instance is and how it is configured (HTTP `GET /info`, startup log).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RuntimeInfo {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub version: String,
+    pub git_hash: String,
+    /// SHA-256 of the effective configuration, for comparing deployments.
+    pub config_digest: String,
+    pub network: String,
+    pub rpc_url: String,
+    pub paper_mode: bool,
+    pub publisher_address: String,
+    pub package_id: String,
+    pub sources: Vec<SourceInfo>,
+    /// Pipeline stage -> strategy in use, e.g. `aggregation` -> `mean`.
+    pub strategies: std::collections::BTreeMap<String, String>,
+    pub started_at_ms: u64,
+    /// `instance.labels`.
+    #[serde(default)]
+    pub labels: std::collections::BTreeMap<String, String>,
+}
+
+/// One condition of a health probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HealthCheck {
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl HealthCheck {
+    pub fn ok(detail: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Body of `GET /healthz` and `GET /readyz`; served with `503` unless `healthy`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HealthReport {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    /// Whether every check passed.
+    pub healthy: bool,
+    pub checks: std::collections::BTreeMap<String, HealthCheck>,
+}
+
+impl HealthReport {
+    pub fn new(checks: std::collections::BTreeMap<String, HealthCheck>) -> Self {
+        Self {
+            schema_version: default_schema_version(),
+            healthy: checks.values().all(|c| c.ok),
+            checks,
+        }
+    }
+}
+
+/// Error body returned by every HTTP endpoint on failure.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorResponse {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub error: String,
+}
+
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>) -> Self {
+        Self {
+            schema_version: default_schema_version(),
+            error: error.into(),
+        }
+    }
+}
+
+/// Body of `GET /schema`: the wire formats of the running build, so integrators can
+/// generate clients that match the deployed version exactly.
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDocument {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: String,
+    pub version: String,
+    pub git_hash: String,
+    /// JSON Schema (draft 2020-12) of each top-level payload, by type name.
+    pub json_schemas: std::collections::BTreeMap<String, serde_json::Value>,
+    /// `proto/oracle/v1/oracle.proto`, also served as is at `GET /schema/oracle.proto`.
+    pub proto: String,
+}
+
+#[cfg_attr(not(feature = "http-api"), allow(dead_code))]
+impl SchemaDocument {
+    /// The schemas of this build's payloads, stamped with its `version` and `git_hash`.
+    pub fn new(version: &str, git_hash: &str) -> Self {
+        fn entry<T: JsonSchema>() -> (String, serde_json::Value) {
+            (T::schema_name().into_owned(), schemars::schema_for!(T).to_value())
+        }
+        Self {
+            schema_version: default_schema_version(),
+            version: version.to_string(),
+            git_hash: git_hash.to_string(),
+            json_schemas: [
+                entry::<PriceUpdate>(),
+                entry::<WsMessage>(),
+                entry::<FeedStatusList>(),
+                entry::<AssetInfo>(),
+                entry::<AssetList>(),
+                entry::<RuntimeInfo>(),
+                entry::<HealthReport>(),
+                entry::<ErrorResponse>(),
+            ]
+            .into_iter()
+            .collect(),
+            proto: PROTO_DEFINITION.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_update() -> PriceUpdate {
+        PriceUpdate {
+            schema_version: SCHEMA_VERSION.to_string(),
+            symbol: "BTC/USD".to_string(),
+            price: 68000.1,
+            scaled_price: 68_000_100_000,
+            decimals: 6,
+            timestamp_ms: 1_700_000_000_000,
+            sources: vec![SourcePrice {
+                source: "binance".to_string(),
+                price: 68000.1,
+                observed_at_ms: None,
+            }],
+            object_id: None,
+            tx_digest: Some("9xYz".to_string()),
+        }
+    }
+
+    /// Field names present in a proto message body, e.g. `string symbol = 1;` -> `symbol`.
+    fn proto_fields(message: &str) -> Vec<String> {
+        let start = PROTO_DEFINITION
+            .find(&format!("message {} {{", message))
+            .unwrap_or_else(|| panic!("message {} missing from proto", message));
+        let body = &PROTO_DEFINITION[start..];
+        let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+        let mut fields: Vec<String> = body
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.starts_with("//") || !line.contains('=') {
+                    return None;
+                }
+                let decl = line.split('=').next()?.trim();
+                decl.split_whitespace().last().map(str::to_string)
+            })
+            .collect();
+        fields.sort();
+        fields
+    }
+
+    fn json_fields(value: serde_json::Value) -> Vec<String> {
+        let mut fields: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_price_update_golden_json() {
+        let json = serde_json::to_string(&sample_update()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"schema_version":"1.0","symbol":"BTC/USD","price":68000.1,"scaled_price":"68000100000","decimals":6,"timestamp_ms":1700000000000,"sources":[{"source":"binance","price":68000.1}],"tx_digest":"9xYz"}"#
+        );
+    }
+
+    #[test]
+    fn test_price_update_accepts_minimal_and_future_payloads() {
+        // Older producers may omit optional fields; newer ones may add fields we don't know.
+        let json = r#"{"symbol":"ETH/USD","price":3000.5,"scaled_price":"3000500000","decimals":6,"timestamp_ms":1,"confidence":0.1}"#;
+        let update: PriceUpdate = serde_json::from_str(json).unwrap();
+        assert_eq!(update.schema_version, SCHEMA_VERSION);
+        assert_eq!(update.scaled_price, 3_000_500_000);
+        assert!(update.sources.is_empty());
+    }
+
+    #[test]
+    fn test_ws_message_tagging() {
+        let json = serde_json::to_value(WsMessage::Lagged { dropped: 3 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "lagged", "dropped": 3 }));
+        let json = serde_json::to_value(WsMessage::PriceUpdate(sample_update())).unwrap();
+        assert_eq!(json["type"], "price_update");
+        assert_eq!(json["symbol"], "BTC/USD");
+    }
+
+    #[test]
+    fn test_schema_document_describes_payloads() {
+        let document = SchemaDocument::new("0.1.0", "abc");
+        assert_eq!(document.git_hash, "abc");
+        assert!(document.proto.contains("message PriceUpdate"));
+        let price_update = &document.json_schemas["PriceUpdate"];
+        let properties = price_update["properties"].as_object().unwrap();
+        let mut fields: Vec<String> = properties.keys().cloned().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            json_fields({
+                let mut update = sample_update();
+                update.object_id = Some("0x1".to_string());
+                serde_json::to_value(update).unwrap()
+            })
+        );
+        // As encoded, not as stored.
+        assert_eq!(properties["scaled_price"]["type"], "string");
+        let required = price_update["required"].as_array().unwrap();
+        assert!(!required.contains(&serde_json::json!("object_id")));
+        assert!(
+            ["FeedStatusList", "HealthReport", "WsMessage"]
+                .iter()
+                .all(|name| document.json_schemas.contains_key(*name))
+        );
+    }
+
+    #[test]
+    fn test_proto_fields_match_serde_types() {
+        let mut update = sample_update();
+        update.object_id = Some("0x1".to_string());
+        assert_eq!(
+            proto_fields("PriceUpdate"),
+            json_fields(serde_json::to_value(update).unwrap())
+        );
+
+        let status = FeedStatus {
+            symbol: "BTC/USD".to_string(),
+            object_id: "0x1".to_string(),
+            version: Some(1),
+            price: Some(1.0),
+            timestamp_ms: Some(1),
+            decimals: Some(6),
+            staleness_ms: Some(1),
+            error: Some("e".to_string()),
+        };
+        assert_eq!(
+            proto_fields("FeedStatus"),
+            json_fields(serde_json::to_value(status).unwrap())
+        );
+
+        let source = SourcePrice {
+            source: "binance".to_string(),
+            price: 1.0,
+            observed_at_ms: Some(1),
+        };
+        assert_eq!(
+            proto_fields("SourcePrice"),
+            json_fields(serde_json::to_value(source).unwrap())
+        );
+
+        let asset = AssetInfo {
+            coin_type: "0x2::sui::SUI".to_string(),
+            feed: "SUI/USD".to_string(),
+            decimals: Some(9),
+            object_id: Some("0x1".to_string()),
+        };
+        assert_eq!(
+            proto_fields("AssetInfo"),
+            json_fields(serde_json::to_value(&asset).unwrap())
+        );
+        let list = AssetList {
+            schema_version: SCHEMA_VERSION.to_string(),
+            assets: vec![asset],
+        };
+        assert_eq!(
+            proto_fields("AssetList"),
+            json_fields(serde_json::to_value(list).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_runtime_info_matches_proto() {
+        let info = RuntimeInfo {
+            schema_version: SCHEMA_VERSION.to_string(),
+            version: "0.1.0".to_string(),
+            git_hash: "abc".to_string(),
+            config_digest: "00".to_string(),
+            network: "testnet".to_string(),
+            rpc_url: "https://fullnode.testnet.sui.io:443".to_string(),
+            paper_mode: false,
+            publisher_address: "0x1".to_string(),
+            package_id: "0x2".to_string(),
+            sources: vec![SourceInfo {
+                name: "binance".to_string(),
+                symbols: vec!["BTCUSDT".to_string()],
+            }],
+            strategies: [("aggregation".to_string(), "mean".to_string())].into(),
+            started_at_ms: 1,
+            labels: Default::default(),
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["strategies"]["aggregation"], "mean");
+        assert_eq!(proto_fields("RuntimeInfo"), json_fields(json));
+    }
+
+    #[test]
+    fn test_proto_declares_schema_version() {
+        assert!(PROTO_DEFINITION.contains(&format!("// schema_version: {}", SCHEMA_VERSION)));
+    }
+}
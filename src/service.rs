@@ -0,0 +1,342 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const DEFAULT_NAME: &str = "neo-oracle";
+
+/// What the service manager needs to run the oracle.
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    pub name: String,
+    pub executable: PathBuf,
+    /// `config/` is read relative to it, as are relative state paths.
+    pub working_dir: PathBuf,
+    pub user: Option<String>,
+    pub env: Vec<(String, String)>,
+    /// Directories the oracle writes to besides `working_dir`, e.g. an absolute state dir.
+    pub writable_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Systemd,
+    Launchd,
+}
+
+impl Platform {
+    pub fn current() -> Result<Self> {
+        match std::env::consts::OS {
+            "linux" => Ok(Platform::Systemd),
+            "macos" => Ok(Platform::Launchd),
+            os => Err(anyhow!(
+                "Service installation is supported on Linux (systemd) and macOS (launchd), not {}",
+                os
+            )),
+        }
+    }
+
+    pub fn unit_path(self, name: &str) -> PathBuf {
+        match self {
+            Platform::Systemd => PathBuf::from(format!("/etc/systemd/system/{}.service", name)),
+            Platform::Launchd => PathBuf::from(format!("/Library/LaunchDaemons/{}.plist", name)),
+        }
+    }
+
+    pub fn render(self, spec: &ServiceSpec) -> String {
+        match self {
+            Platform::Systemd => systemd_unit(spec),
+            Platform::Launchd => launchd_plist(spec),
+        }
+    }
+}
+
+/// Parses a `--env KEY=VALUE` argument.
+pub fn parse_env(arg: &str) -> Result<(String, String)> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow!("--env '{}' is not KEY=VALUE", arg))?;
+    let valid = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow!("--env '{}': invalid variable name '{}'", arg, key));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// A value in a systemd `Environment="..."` assignment, where `%` starts a specifier.
+fn systemd_quote(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+}
+
+fn systemd_unit(spec: &ServiceSpec) -> String {
+    let working_dir = spec.working_dir.display().to_string();
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=Neo Oracle MVP price publisher\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart=\"{}\" run\n\
+         WorkingDirectory={}\n",
+        systemd_quote(&spec.executable.display().to_string()),
+        working_dir
+    );
+    if let Some(user) = &spec.user {
+        unit.push_str(&format!("User={}\n", user));
+    }
+    for (key, value) in &spec.env {
+        unit.push_str(&format!(
+            "Environment=\"{}={}\"\n",
+            key,
+            systemd_quote(value)
+        ));
+    }
+    // Secrets such as the admin token belong here rather than in the world-readable unit.
+    unit.push_str(&format!("EnvironmentFile=-/etc/{}/env\n", spec.name));
+    unit.push_str("Restart=on-failure\nRestartSec=5\n");
+
+    // Hardening: a read-only system and no privileges, writable only where state lives.
+    // A checkout under a home directory stays reachable, read-only apart from those paths.
+    let under_home = ["/home", "/root"]
+        .iter()
+        .any(|home| spec.working_dir.starts_with(home));
+    unit.push_str(&format!(
+        "UMask=0077\n\
+         NoNewPrivileges=true\n\
+         CapabilityBoundingSet=\n\
+         ProtectSystem=strict\n\
+         ProtectHome={}\n\
+         PrivateTmp=true\n\
+         PrivateDevices=true\n\
+         ProtectKernelTunables=true\n\
+         ProtectKernelModules=true\n\
+         ProtectControlGroups=true\n\
+         RestrictNamespaces=true\n\
+         RestrictRealtime=true\n\
+         RestrictSUIDSGID=true\n\
+         LockPersonality=true\n\
+         MemoryDenyWriteExecute=true\n\
+         SystemCallArchitectures=native\n",
+        if under_home { "read-only" } else { "true" }
+    ));
+    let writable: Vec<String> = std::iter::once(working_dir)
+        .chain(spec.writable_paths.iter().map(|p| p.display().to_string()))
+        .collect();
+    unit.push_str(&format!("ReadWritePaths={}\n", writable.join(" ")));
+    unit.push_str("\n[Install]\nWantedBy=multi-user.target\n");
+    unit
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn plist_string(value: &str) -> String {
+    format!("<string>{}</string>", xml_escape(value))
+}
+
+fn launchd_plist(spec: &ServiceSpec) -> String {
+    let log = spec.working_dir.join(format!("{}.log", spec.name));
+    let log = log.display().to_string();
+    let mut plist = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n    {}\n\
+         \x20   <key>ProgramArguments</key>\n    <array>\n        {}\n        <string>run</string>\n    </array>\n\
+         \x20   <key>WorkingDirectory</key>\n    {}\n",
+        plist_string(&spec.name),
+        plist_string(&spec.executable.display().to_string()),
+        plist_string(&spec.working_dir.display().to_string())
+    );
+    if let Some(user) = &spec.user {
+        plist.push_str(&format!(
+            "    <key>UserName</key>\n    {}\n",
+            plist_string(user)
+        ));
+    }
+    if !spec.env.is_empty() {
+        plist.push_str("    <key>EnvironmentVariables</key>\n    <dict>\n");
+        for (key, value) in &spec.env {
+            plist.push_str(&format!(
+                "        <key>{}</key>\n        {}\n",
+                xml_escape(key),
+                plist_string(value)
+            ));
+        }
+        plist.push_str("    </dict>\n");
+    }
+    plist.push_str(&format!(
+        "    <key>RunAtLoad</key>\n    <true/>\n\
+         \x20   <key>KeepAlive</key>\n    <dict>\n        <key>SuccessfulExit</key>\n        <false/>\n    </dict>\n\
+         \x20   <key>ThrottleInterval</key>\n    <integer>5</integer>\n\
+         \x20   <key>ProcessType</key>\n    <string>Background</string>\n\
+         \x20   <key>Umask</key>\n    <integer>63</integer>\n\
+         \x20   <key>StandardOutPath</key>\n    {}\n\
+         \x20   <key>StandardErrorPath</key>\n    {}\n\
+         </dict>\n\
+         </plist>\n",
+        plist_string(&log),
+        plist_string(&log)
+    ));
+    plist
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .context(format!("Failed to run {}", program))?;
+    if !status.success() {
+        return Err(anyhow!("{} {} failed: {}", program, args.join(" "), status));
+    }
+    Ok(())
+}
+
+/// Writes the unit for `spec`, then enables and starts it. Refuses to replace an existing
+/// unit unless `force` is set.
+pub fn install(platform: Platform, spec: &ServiceSpec, force: bool) -> Result<PathBuf> {
+    let path = platform.unit_path(&spec.name);
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        ));
+    }
+    write_unit(&path, &platform.render(spec))?;
+    match platform {
+        Platform::Systemd => {
+            run("systemctl", &["daemon-reload"])?;
+            run(
+                "systemctl",
+                &["enable", "--now", &format!("{}.service", spec.name)],
+            )?;
+        }
+        Platform::Launchd => {
+            if force {
+                // Unload the old definition first; it fails harmlessly when none is loaded.
+                let _ = run("launchctl", &["bootout", &format!("system/{}", spec.name)]);
+            }
+            run(
+                "launchctl",
+                &["bootstrap", "system", &path.display().to_string()],
+            )?;
+        }
+    }
+    Ok(path)
+}
+
+fn write_unit(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).context(format!(
+        "Failed to write {} (installing a service needs root)",
+        path.display()
+    ))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o644))?;
+    }
+    Ok(())
+}
+
+/// Stops and disables the service and removes its unit.
+pub fn uninstall(platform: Platform, name: &str) -> Result<PathBuf> {
+    let path = platform.unit_path(name);
+    if !path.exists() {
+        return Err(anyhow!("{} does not exist", path.display()));
+    }
+    match platform {
+        Platform::Systemd => {
+            run(
+                "systemctl",
+                &["disable", "--now", &format!("{}.service", name)],
+            )?;
+            fs::remove_file(&path).context(format!("Failed to remove {}", path.display()))?;
+            run("systemctl", &["daemon-reload"])?;
+        }
+        Platform::Launchd => {
+            run("launchctl", &["bootout", &format!("system/{}", name)])?;
+            fs::remove_file(&path).context(format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(working_dir: &str) -> ServiceSpec {
+        ServiceSpec {
+            name: DEFAULT_NAME.to_string(),
+            executable: PathBuf::from("/opt/neo-oracle/bin/neo_oracle_mvp"),
+            working_dir: PathBuf::from(working_dir),
+            user: Some("oracle".to_string()),
+            env: vec![
+                ("RUST_LOG".to_string(), "info".to_string()),
+                ("LABEL".to_string(), "a \"b\" <c> 100%".to_string()),
+            ],
+            writable_paths: vec![PathBuf::from("/var/lib/neo-oracle")],
+        }
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = Platform::Systemd.render(&spec("/opt/neo-oracle"));
+        for line in [
+            "ExecStart=\"/opt/neo-oracle/bin/neo_oracle_mvp\" run",
+            "WorkingDirectory=/opt/neo-oracle",
+            "User=oracle",
+            "Environment=\"RUST_LOG=info\"",
+            "Environment=\"LABEL=a \\\"b\\\" <c> 100%%\"",
+            "EnvironmentFile=-/etc/neo-oracle/env",
+            "ProtectSystem=strict",
+            "ProtectHome=true",
+            "NoNewPrivileges=true",
+            "ReadWritePaths=/opt/neo-oracle /var/lib/neo-oracle",
+            "WantedBy=multi-user.target",
+        ] {
+            assert!(unit.lines().any(|l| l == line), "missing {:?}", line);
+        }
+        let unit = Platform::Systemd.render(&spec("/home/op/neo-oracle"));
+        assert!(unit.lines().any(|l| l == "ProtectHome=read-only"));
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = Platform::Launchd.render(&spec("/opt/neo-oracle"));
+        assert!(plist.contains("<key>Label</key>\n    <string>neo-oracle</string>"));
+        assert!(plist.contains("<string>/opt/neo-oracle/bin/neo_oracle_mvp</string>"));
+        assert!(plist.contains("<key>UserName</key>\n    <string>oracle</string>"));
+        assert!(
+            plist.contains(
+                "<key>LABEL</key>\n        <string>a &quot;b&quot; &lt;c&gt; 100%</string>"
+            )
+        );
+        assert!(plist.contains("<string>/opt/neo-oracle/neo-oracle.log</string>"));
+        assert!(plist.trim_end().ends_with("</plist>"));
+    }
+
+    #[test]
+    fn test_parse_env() {
+        assert_eq!(
+            parse_env("RUST_LOG=info,neo=debug").unwrap(),
+            ("RUST_LOG".to_string(), "info,neo=debug".to_string())
+        );
+        assert!(parse_env("RUST_LOG").is_err());
+        assert!(parse_env("1ABC=x").is_err());
+        assert!(parse_env("A-B=x").is_err());
+    }
+}
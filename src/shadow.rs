@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::metrics::Metrics;
+
+/// How far one feed's shadow prices have been from its live ones since startup.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Divergence {
+    /// Cycles in which both configurations produced a price.
+    pub compared: u64,
+    /// Signed, in basis points of the live price, of the last comparison.
+    pub last_bps: f64,
+    pub mean_abs_bps: f64,
+    pub max_abs_bps: f64,
+    /// Cycles in which only the live, or only the shadow configuration produced a price.
+    pub only_live: u64,
+    pub only_shadow: u64,
+}
+
+/// Divergence of the `[shadow]` aggregates from the live ones, per feed.
+#[derive(Default)]
+pub struct ShadowTracker {
+    feeds: Mutex<BTreeMap<String, Divergence>>,
+}
+
+impl ShadowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one cycle's live and shadow aggregate of `symbol`. Returns the divergence in
+    /// basis points of the live price when both configurations produced one.
+    pub fn compare(&self, symbol: &str, live: Option<f64>, shadow: Option<f64>) -> Option<f64> {
+        let mut feeds = self.feeds.lock().unwrap();
+        let divergence = feeds.entry(symbol.to_string()).or_default();
+        let (live, shadow) = match (live, shadow) {
+            (Some(live), Some(shadow)) => (live, shadow),
+            (Some(_), None) => {
+                divergence.only_live += 1;
+                return None;
+            }
+            (None, Some(_)) => {
+                divergence.only_shadow += 1;
+                return None;
+            }
+            (None, None) => return None,
+        };
+        let bps = if live == 0.0 {
+            0.0
+        } else {
+            (shadow - live) / live * 10_000.0
+        };
+        divergence.compared += 1;
+        divergence.last_bps = bps;
+        divergence.mean_abs_bps +=
+            (bps.abs() - divergence.mean_abs_bps) / divergence.compared as f64;
+        divergence.max_abs_bps = divergence.max_abs_bps.max(bps.abs());
+        Some(bps)
+    }
+
+    pub fn export_metrics(&self, metrics: &Metrics) {
+        for (symbol, divergence) in self.feeds.lock().unwrap().iter() {
+            let labels = [("symbol", symbol.as_str())];
+            metrics.set_gauge(
+                "oracle_shadow_divergence_bps",
+                "Last shadow aggregate minus the live one, in basis points of the live one",
+                &labels,
+                divergence.last_bps,
+            );
+            metrics.set_gauge(
+                "oracle_shadow_divergence_mean_abs_bps",
+                "Mean absolute divergence of shadow aggregates from live ones since startup",
+                &labels,
+                divergence.mean_abs_bps,
+            );
+            metrics.set_gauge(
+                "oracle_shadow_divergence_max_abs_bps",
+                "Largest absolute divergence of a shadow aggregate from the live one",
+                &labels,
+                divergence.max_abs_bps,
+            );
+            for (priced_by, cycles) in [
+                ("live", divergence.only_live),
+                ("shadow", divergence.only_shadow),
+            ] {
+                metrics.set_gauge(
+                    "oracle_shadow_priced_alone_cycles",
+                    "Cycles in which only one configuration produced a price",
+                    &[("symbol", symbol.as_str()), ("priced_by", priced_by)],
+                    cycles as f64,
+                );
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn divergence(&self, symbol: &str) -> Option<Divergence> {
+        self.feeds.lock().unwrap().get(symbol).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_divergence_per_feed() {
+        let tracker = ShadowTracker::new();
+        assert_eq!(
+            tracker.compare("BTC/USD", Some(100.0), Some(100.5)),
+            Some(50.0)
+        );
+        let bps = tracker.compare("BTC/USD", Some(100.0), Some(99.9)).unwrap();
+        assert!((bps + 10.0).abs() < 1e-9);
+        assert_eq!(tracker.compare("BTC/USD", Some(100.0), None), None);
+        assert_eq!(tracker.compare("BTC/USD", None, None), None);
+
+        let btc = tracker.divergence("BTC/USD").unwrap();
+        assert_eq!((btc.compared, btc.only_live, btc.only_shadow), (2, 1, 0));
+        assert!((btc.mean_abs_bps - 30.0).abs() < 1e-9);
+        assert!((btc.max_abs_bps - 50.0).abs() < 1e-9);
+        assert!((btc.last_bps + 10.0).abs() < 1e-9);
+
+        tracker.compare("ETH/USD", None, Some(2000.0));
+        assert_eq!(tracker.divergence("ETH/USD").unwrap().only_shadow, 1);
+    }
+}
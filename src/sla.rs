@@ -0,0 +1,430 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::alerts::{Alert, Severity};
+use crate::calendar::{CalendarRegistry, MarketCalendar};
+use crate::config::{SlaSettings, SymbolSettings};
+use crate::metrics::Metrics;
+
+/// Rolling window over which `min_updates_per_hour` is measured.
+pub const UPDATE_RATE_WINDOW_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    Stale {
+        staleness_ms: u64,
+        max_staleness_ms: u64,
+    },
+    LowUpdateRate {
+        updates: u32,
+        min_updates: u32,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Stale {
+                staleness_ms,
+                max_staleness_ms,
+            } => write!(
+                f,
+                "no update for {}ms (max {}ms)",
+                staleness_ms, max_staleness_ms
+            ),
+            Violation::LowUpdateRate {
+                updates,
+                min_updates,
+            } => write!(
+                f,
+                "{} updates in the last hour (min {})",
+                updates, min_updates
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Breached,
+    Recovered,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlaStatus {
+    pub symbol: String,
+    pub staleness_ms: u64,
+    pub updates_in_window: u32,
+    pub violations: Vec<Violation>,
+    /// Share of evaluations so far in which the feed met its SLA.
+    pub attainment: f64,
+    pub transition: Option<Transition>,
+}
+
+impl SlaStatus {
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Whether the feed has gone longer than `max_staleness_ms` without an update.
+    pub fn is_stale(&self) -> bool {
+        self.violations
+            .iter()
+            .any(|v| matches!(v, Violation::Stale { .. }))
+    }
+
+    pub fn alert(&self, now_ms: u64) -> Option<Alert> {
+        let (severity, kind, message) = match self.transition? {
+            Transition::Breached => (
+                Severity::Critical,
+                "sla_breach",
+                self.violations
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+            Transition::Recovered => (
+                Severity::Info,
+                "sla_recovered",
+                format!(
+                    "feed back within SLA ({:.2}% attainment)",
+                    self.attainment * 100.0
+                ),
+            ),
+        };
+        Some(Alert {
+            severity,
+            kind,
+            symbol: self.symbol.clone(),
+            message,
+            timestamp_ms: now_ms,
+        })
+    }
+}
+
+#[derive(Default)]
+struct FeedRecord {
+    last_update_ms: Option<u64>,
+    updates: VecDeque<u64>,
+    evaluations: u64,
+    compliant_evaluations: u64,
+    in_breach: bool,
+}
+
+/// Tracks each symbol's published updates against the SLOs in `[symbols."<symbol>".sla]`.
+/// Time only counts while the symbol's market is open, so a closed market is never stale.
+pub struct SlaTracker {
+    slos: BTreeMap<String, (SlaSettings, MarketCalendar)>,
+    started_at_ms: u64,
+    max_history: usize,
+    feeds: Mutex<HashMap<String, FeedRecord>>,
+}
+
+impl SlaTracker {
+    pub fn new(
+        symbols: &BTreeMap<String, SymbolSettings>,
+        calendars: &CalendarRegistry,
+        started_at_ms: u64,
+    ) -> Self {
+        let slos = symbols
+            .iter()
+            .filter_map(|(symbol, s)| {
+                let calendar = calendars.for_symbol(symbol).clone();
+                s.sla.clone().map(|sla| (symbol.clone(), (sla, calendar)))
+            })
+            .collect();
+        Self {
+            slos,
+            started_at_ms,
+            max_history: usize::MAX,
+            feeds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Caps the update timestamps kept per symbol; the oldest are dropped first.
+    pub fn with_max_history(mut self, max_history: usize) -> Self {
+        self.max_history = max_history.max(1);
+        self
+    }
+
+    pub fn record_update(&self, symbol: &str, at_ms: u64) {
+        let mut feeds = self.feeds.lock().unwrap();
+        let record = feeds.entry(symbol.to_string()).or_default();
+        record.last_update_ms = Some(record.last_update_ms.map_or(at_ms, |t| t.max(at_ms)));
+        if record.updates.len() >= self.max_history {
+            record.updates.pop_front();
+        }
+        record.updates.push_back(at_ms);
+    }
+
+    /// Checks every symbol with an SLO whose market is open. A feed that has never updated
+    /// is stale from process start; the update-rate objective is only enforced once the
+    /// market has been open for a full window since start.
+    pub fn evaluate(&self, now_ms: u64) -> Vec<SlaStatus> {
+        let mut feeds = self.feeds.lock().unwrap();
+        let window_start = now_ms.saturating_sub(UPDATE_RATE_WINDOW_MS);
+
+        self.slos
+            .iter()
+            .filter(|(_, (_, calendar))| calendar.is_open(ms_to_datetime(now_ms)))
+            .map(|(symbol, (slo, calendar))| {
+                let record = feeds.entry(symbol.clone()).or_default();
+                while record.updates.front().is_some_and(|&t| t < window_start) {
+                    record.updates.pop_front();
+                }
+
+                let staleness_ms = calendar
+                    .open_ms_between(record.last_update_ms.unwrap_or(self.started_at_ms), now_ms);
+                let updates_in_window = record.updates.len() as u32;
+                let window_elapsed = window_start >= self.started_at_ms
+                    && calendar.open_ms_between(window_start, now_ms) >= UPDATE_RATE_WINDOW_MS;
+
+                let mut violations = Vec::new();
+                if staleness_ms > slo.max_staleness_ms {
+                    violations.push(Violation::Stale {
+                        staleness_ms,
+                        max_staleness_ms: slo.max_staleness_ms,
+                    });
+                }
+                if window_elapsed && updates_in_window < slo.min_updates_per_hour {
+                    violations.push(Violation::LowUpdateRate {
+                        updates: updates_in_window,
+                        min_updates: slo.min_updates_per_hour,
+                    });
+                }
+
+                let compliant = violations.is_empty();
+                record.evaluations += 1;
+                if compliant {
+                    record.compliant_evaluations += 1;
+                }
+                let transition = match (record.in_breach, compliant) {
+                    (false, false) => Some(Transition::Breached),
+                    (true, true) => Some(Transition::Recovered),
+                    _ => None,
+                };
+                record.in_breach = !compliant;
+
+                SlaStatus {
+                    symbol: symbol.clone(),
+                    staleness_ms,
+                    updates_in_window,
+                    violations,
+                    attainment: record.compliant_evaluations as f64 / record.evaluations as f64,
+                    transition,
+                }
+            })
+            .collect()
+    }
+}
+
+fn ms_to_datetime(ms: u64) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp_millis(ms as i64).unwrap_or_default()
+}
+
+pub fn export_metrics(metrics: &Metrics, statuses: &[SlaStatus]) {
+    for status in statuses {
+        let labels = [("symbol", status.symbol.as_str())];
+        metrics.set_gauge(
+            "oracle_sla_attainment_ratio",
+            "Share of SLA evaluations in which the feed met its objectives",
+            &labels,
+            status.attainment,
+        );
+        metrics.set_gauge(
+            "oracle_sla_breached",
+            "1 while the feed violates its SLA",
+            &labels,
+            if status.is_compliant() { 0.0 } else { 1.0 },
+        );
+        metrics.set_gauge(
+            "oracle_feed_staleness_ms",
+            "Milliseconds since the last successful on-chain update",
+            &labels,
+            status.staleness_ms as f64,
+        );
+        metrics.set_gauge(
+            "oracle_feed_updates_last_hour",
+            "Successful on-chain updates in the trailing hour",
+            &labels,
+            status.updates_in_window as f64,
+        );
+    }
+}
+
+/// Records per-symbol publish cost so gas spend can be compared against update frequency,
+/// in USD too once the USD feed has a price.
+pub fn record_publish_metrics(
+    metrics: &Metrics,
+    symbol: &str,
+    gas_used_mist: u64,
+    gas_usd: Option<f64>,
+) {
+    let labels = [("symbol", symbol)];
+    metrics.inc_counter(
+        "oracle_publish_updates_total",
+        "Successful on-chain price updates",
+        &labels,
+        1.0,
+    );
+    metrics.inc_counter(
+        "oracle_publish_gas_mist_total",
+        "Net gas charged for price updates, in MIST",
+        &labels,
+        gas_used_mist as f64,
+    );
+    if let Some(usd) = gas_usd {
+        metrics.inc_counter(
+            "oracle_publish_gas_usd_total",
+            "Net gas charged for price updates, in USD at the SUI/USD rate of each publish",
+            &labels,
+            usd,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_000_000;
+
+    fn tracker(max_staleness_ms: u64, min_updates_per_hour: u32) -> SlaTracker {
+        let symbols = BTreeMap::from([(
+            "BTC/USD".to_string(),
+            SymbolSettings {
+                sla: Some(SlaSettings {
+                    max_staleness_ms,
+                    min_updates_per_hour,
+                }),
+                ..Default::default()
+            },
+        )]);
+        let calendars =
+            CalendarRegistry::from_settings(&BTreeMap::new(), &symbols, chrono_tz::Tz::UTC)
+                .unwrap();
+        SlaTracker::new(&symbols, &calendars, START)
+    }
+
+    #[test]
+    fn test_staleness_breach_and_recovery() {
+        let tracker = tracker(10_000, 0);
+        tracker.record_update("BTC/USD", START + 1_000);
+
+        let status = &tracker.evaluate(START + 5_000)[0];
+        assert!(status.is_compliant());
+        assert_eq!(status.transition, None);
+
+        let status = &tracker.evaluate(START + 20_000)[0];
+        assert_eq!(
+            status.violations,
+            vec![Violation::Stale {
+                staleness_ms: 19_000,
+                max_staleness_ms: 10_000
+            }]
+        );
+        assert_eq!(status.transition, Some(Transition::Breached));
+        assert!(status.is_stale());
+        assert_eq!(status.alert(START + 20_000).unwrap().kind, "sla_breach");
+
+        // Still breached: no repeated alert.
+        assert_eq!(tracker.evaluate(START + 21_000)[0].transition, None);
+
+        tracker.record_update("BTC/USD", START + 22_000);
+        let status = &tracker.evaluate(START + 23_000)[0];
+        assert_eq!(status.transition, Some(Transition::Recovered));
+        assert!(!status.is_stale());
+        assert!((status.attainment - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_never_updated_feed_is_stale_from_start() {
+        let tracker = tracker(10_000, 0);
+        assert!(tracker.evaluate(START + 5_000)[0].is_compliant());
+        assert!(!tracker.evaluate(START + 15_000)[0].is_compliant());
+    }
+
+    #[test]
+    fn test_update_rate_enforced_after_full_window() {
+        let tracker = tracker(u64::MAX, 3);
+        tracker.record_update("BTC/USD", START + 10);
+        // Less than an hour since start: rate is not judged yet.
+        assert!(tracker.evaluate(START + 60_000)[0].is_compliant());
+
+        let now = START + UPDATE_RATE_WINDOW_MS + 20;
+        tracker.record_update("BTC/USD", now - 10);
+        let status = &tracker.evaluate(now)[0];
+        // The first update has aged out of the window.
+        assert_eq!(status.updates_in_window, 1);
+        assert_eq!(
+            status.violations,
+            vec![Violation::LowUpdateRate {
+                updates: 1,
+                min_updates: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let tracker = tracker(u64::MAX, 0).with_max_history(2);
+        for i in 0..5 {
+            tracker.record_update("BTC/USD", START + i);
+        }
+        assert_eq!(tracker.evaluate(START + 10)[0].updates_in_window, 2);
+    }
+
+    #[test]
+    fn test_symbols_without_sla_are_ignored() {
+        let tracker = tracker(10_000, 0);
+        tracker.record_update("ETH/USD", START);
+        let statuses = tracker.evaluate(START + 1);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].symbol, "BTC/USD");
+    }
+
+    #[test]
+    fn test_closed_market_time_does_not_count() {
+        // Friday 20:00 UTC; FX closes at 21:00 UTC and reopens Sunday 21:00 UTC.
+        const FRIDAY: u64 = 1_792_180_800_000;
+        let symbols = BTreeMap::from([(
+            "EUR/USD".to_string(),
+            SymbolSettings {
+                sla: Some(SlaSettings {
+                    max_staleness_ms: 2 * 3_600_000,
+                    min_updates_per_hour: 0,
+                }),
+                calendar: Some("fx".to_string()),
+                ..Default::default()
+            },
+        )]);
+        let calendars =
+            CalendarRegistry::from_settings(&BTreeMap::new(), &symbols, chrono_tz::Tz::UTC)
+                .unwrap();
+        let tracker = SlaTracker::new(&symbols, &calendars, FRIDAY - 1_000);
+        tracker.record_update("EUR/USD", FRIDAY);
+
+        // Saturday: market closed, nothing is evaluated.
+        assert!(tracker.evaluate(FRIDAY + 24 * 3_600_000).is_empty());
+
+        // Sunday 22:00 UTC: one open hour before the close plus one after the reopen.
+        let status = &tracker.evaluate(FRIDAY + 50 * 3_600_000)[0];
+        assert_eq!(status.staleness_ms, 2 * 3_600_000);
+        assert!(status.is_compliant());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_export_metrics() {
+        let tracker = tracker(10_000, 0);
+        let metrics = Metrics::new();
+        export_metrics(&metrics, &tracker.evaluate(START + 20_000));
+        record_publish_metrics(&metrics, "BTC/USD", 1_500, Some(0.25));
+        let text = metrics.render();
+        assert!(text.contains("oracle_sla_breached{symbol=\"BTC/USD\"} 1\n"));
+        assert!(text.contains("oracle_sla_attainment_ratio{symbol=\"BTC/USD\"} 0\n"));
+        assert!(text.contains("oracle_publish_gas_mist_total{symbol=\"BTC/USD\"} 1500\n"));
+        assert!(text.contains("oracle_publish_gas_usd_total{symbol=\"BTC/USD\"} 0.25\n"));
+    }
+}
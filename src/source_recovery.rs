@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Remembers which sources were recently down, so their first prices after recovery count
+/// for less in the aggregate: those readings are frequently stale or erratic.
+///
+/// A source recovering from an outage gets weight `k / (ramp_cycles + 1)` in its `k`th cycle
+/// back, reaching full weight after `ramp_cycles` cycles. Sources never seen down have full
+/// weight.
+#[derive(Default)]
+pub struct RecoveryTracker {
+    /// Cycles each recovering source has been back up; `0` while it is down.
+    recovering: Mutex<HashMap<String, u32>>,
+}
+
+impl RecoveryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records whether `source` delivered prices this cycle.
+    pub fn observe(&self, source: &str, up: bool, ramp_cycles: u32) {
+        let mut recovering = self.recovering.lock().unwrap();
+        if !up {
+            if recovering.insert(source.to_string(), 0).is_none() {
+                log::warn!("Source {} is down", source);
+            }
+            return;
+        }
+        let Some(cycles_up) = recovering.get_mut(source) else {
+            return;
+        };
+        *cycles_up += 1;
+        if *cycles_up > ramp_cycles {
+            recovering.remove(source);
+            log::info!("Source {} recovered; back at full weight", source);
+        } else {
+            log::info!(
+                "Source {} recovering: weight {:.2} ({} of {} cycles)",
+                source,
+                *cycles_up as f64 / (ramp_cycles + 1) as f64,
+                cycles_up,
+                ramp_cycles
+            );
+        }
+    }
+
+    /// Weight of `source` in this cycle's aggregates, in `[0, 1]`.
+    pub fn weight(&self, source: &str, ramp_cycles: u32) -> f64 {
+        match self.recovering.lock().unwrap().get(source) {
+            Some(&cycles_up) => (cycles_up as f64 / (ramp_cycles + 1) as f64).min(1.0),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight_ramps_back_after_outage() {
+        let tracker = RecoveryTracker::new();
+        tracker.observe("binance", true, 3);
+        assert_eq!(tracker.weight("binance", 3), 1.0);
+
+        tracker.observe("binance", false, 3);
+        tracker.observe("binance", false, 3);
+        assert_eq!(tracker.weight("binance", 3), 0.0);
+        let mut weights = Vec::new();
+        for _ in 0..5 {
+            tracker.observe("binance", true, 3);
+            weights.push(tracker.weight("binance", 3));
+        }
+        assert_eq!(weights, [0.25, 0.5, 0.75, 1.0, 1.0]);
+        assert_eq!(tracker.weight("coinbase", 3), 1.0);
+    }
+
+    #[test]
+    fn test_no_ramp_readmits_at_full_weight() {
+        let tracker = RecoveryTracker::new();
+        tracker.observe("coinbase", false, 0);
+        tracker.observe("coinbase", true, 0);
+        assert_eq!(tracker.weight("coinbase", 0), 1.0);
+    }
+}
@@ -0,0 +1,888 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::Instrument;
+
+use crate::aggregator::Quote;
+use crate::binance_client::{self, BinanceSource};
+use crate::bybit_client::{self, BybitSource};
+use crate::clock_drift::ClockDriftTracker;
+use crate::coinbase_client::{self, CoinbaseSource};
+use crate::coingecko_client::{self, CoinGeckoSource};
+use crate::config::{
+    ExchangeConfig, HttpClientSettings, RateLimitSettings, RequestRetrySettings, Settings,
+    SourceKind,
+};
+use crate::endpoints::EndpointSelector;
+use crate::okx_client::{self, OkxSource};
+use crate::transport::Transport;
+
+/// One price reported by a source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceSample {
+    /// The price as reported, to its last digit.
+    pub price: Decimal,
+    /// When the price was set (e.g. the last trade), by the source's own clock.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_ms: Option<i64>,
+}
+
+/// Latest trade price per ticker of a trade-based source, ignoring trades below a minimum
+/// notional so dust trades at off-market prices on thin pairs don't move it.
+pub struct TradeFilter {
+    min_notional: Option<f64>,
+    latest: Mutex<HashMap<String, PriceSample>>,
+}
+
+impl TradeFilter {
+    pub fn new(min_notional: Option<f64>) -> Self {
+        Self {
+            min_notional,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a trade of `size` at `trade.price` and returns the ticker's price from its
+    /// latest trade of at least the minimum notional, or `None` until one has been seen.
+    /// Trades of unknown size are taken as they are.
+    pub fn observe(
+        &self,
+        ticker: &str,
+        trade: PriceSample,
+        size: Option<f64>,
+    ) -> Option<PriceSample> {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        let notional = size.zip(trade.price.to_f64()).map(|(s, p)| s * p);
+        match (self.min_notional, notional) {
+            (Some(min), Some(notional)) if notional < min => {
+                log::debug!(
+                    "Ignoring {} trade of {} notional at {} (below {})",
+                    ticker,
+                    notional,
+                    trade.price,
+                    min
+                );
+                latest.get(ticker).cloned()
+            }
+            _ => {
+                latest.insert(ticker.to_string(), trade.clone());
+                Some(trade)
+            }
+        }
+    }
+
+    /// The ticker's price from its latest trade of at least the minimum notional.
+    #[cfg(feature = "ws-sources")]
+    pub fn latest(&self, ticker: &str) -> Option<PriceSample> {
+        let latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        latest.get(ticker).cloned()
+    }
+}
+
+/// A venue prices are fetched from. Failures of individual tickers are logged and left out
+/// of the result; an error means the source delivered nothing usable.
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Name used in logs, metrics, debug bundles and the config history, e.g. `binance`.
+    fn name(&self) -> &'static str;
+
+    /// The source's configured tickers.
+    fn symbols(&self) -> &[String];
+
+    /// Latest prices of `symbols`, keyed by ticker.
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>>;
+
+    /// How many requests `fetch` sends for `symbols`, one after another. The fetch may take
+    /// `general.source_timeout_ms` for each.
+    fn requests_per_fetch(&self, _symbols: &[String]) -> u32 {
+        1
+    }
+
+    /// Displayed top-of-book notional of `symbols`, in quote currency.
+    async fn fetch_depth(&self, _symbols: &[String]) -> Result<HashMap<String, f64>> {
+        Err(anyhow!("{} does not report order-book depth", self.name()))
+    }
+
+    /// Trailing 24h traded volume of `symbols`, in base currency.
+    async fn fetch_volume(&self, _symbols: &[String]) -> Result<HashMap<String, f64>> {
+        Err(anyhow!("{} does not report trading volume", self.name()))
+    }
+
+    /// Best bid and ask of `symbols`.
+    async fn fetch_quotes(&self, _symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        Err(anyhow!("{} does not report bid and ask", self.name()))
+    }
+
+    /// The stream pushing the source's prices, if it has one. `transport::TransportSelector`
+    /// decides each cycle whether prices are read from it or fetched over REST.
+    fn stream(&self) -> Option<&dyn PriceStream> {
+        None
+    }
+
+    /// For a reference source, how old its prices may be to check aggregates against.
+    /// A reference source never contributes to an aggregate; `None` for the venues.
+    fn reference_max_age(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Prices a source receives over a long-lived connection.
+pub trait PriceStream: Send + Sync {
+    /// The latest price received for each of `symbols` that had one.
+    fn latest(&self, symbols: &[String]) -> HashMap<String, PriceSample>;
+
+    /// Time since the last message; `None` before the first one.
+    fn last_message_age(&self) -> Option<Duration>;
+
+    /// Reconnects since the stream started.
+    fn reconnects(&self) -> u64;
+}
+
+/// The HTTP client of all exchange sources. Clones share its connection pool.
+pub fn http_client(settings: &HttpClientSettings) -> Result<Client> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_millis(settings.connect_timeout_ms))
+        .timeout(Duration::from_millis(settings.request_timeout_ms))
+        .pool_idle_timeout(Duration::from_millis(settings.pool_idle_timeout_ms))
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host)
+        .tcp_keepalive(
+            (settings.tcp_keepalive_ms > 0)
+                .then(|| Duration::from_millis(settings.tcp_keepalive_ms)),
+        );
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(
+            reqwest::Proxy::all(proxy).map_err(|e| anyhow!("Invalid http_client.proxy: {}", e))?,
+        );
+    }
+    builder
+        .build()
+        .map_err(|e| anyhow!("Failed to build the HTTP client: {}", e))
+}
+
+/// Whether a failed exchange request may succeed when sent again: the exchange answered
+/// with a server error, or the request timed out.
+fn is_transient(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout() || e.status().is_some_and(|s| s.is_server_error()))
+}
+
+/// Sends `request` again, with exponential backoff, while it fails transiently and
+/// `policy.max_attempts` allows. Other failures, e.g. `4xx` responses, an unparsable body or
+/// a rate limit, are returned at once. `what` names the request in logs.
+pub async fn with_retry<T, F>(
+    policy: &RequestRetrySettings,
+    what: &str,
+    mut request: impl FnMut() -> F,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Err(e) if attempt < policy.max_attempts && is_transient(&e) => {
+                let delay = Duration::from_millis(policy.delay_ms(attempt));
+                log::debug!(
+                    "Retrying {} in {} ms after attempt {} failed: {}",
+                    what,
+                    delay.as_millis(),
+                    attempt,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `name` with a static lifetime, which `PriceSource::name` requires of registered
+/// sources too. Each distinct name is allocated once, however often sources are rebuilt.
+fn intern(name: &str) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut names = NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    match names.get(name) {
+        Some(interned) => interned,
+        None => {
+            let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(interned);
+            interned
+        }
+    }
+}
+
+/// The documented rate limit of the exchanges speaking `kind`'s API.
+fn documented_rate_limit(kind: SourceKind) -> RateLimitSettings {
+    match kind {
+        SourceKind::Binance => binance_client::RATE_LIMIT,
+        SourceKind::Coinbase => coinbase_client::RATE_LIMIT,
+        SourceKind::Okx => okx_client::RATE_LIMIT,
+        SourceKind::Bybit => bybit_client::RATE_LIMIT,
+    }
+}
+
+/// The configured sources, then the registered ones by name, in the order they are
+/// fetched and aggregated. They all send their requests through `http`, within the rate
+/// limits set on `endpoints`.
+pub fn from_settings(
+    settings: &Settings,
+    http: &Client,
+    clock_drift: &Arc<ClockDriftTracker>,
+    endpoints: &Arc<EndpointSelector>,
+) -> Vec<Box<dyn PriceSource>> {
+    let limit = |name: &str, exchange: &ExchangeConfig, documented: RateLimitSettings| {
+        endpoints.set_rate_limit(name, exchange.rate_limit.clone().unwrap_or(documented));
+    };
+    let retry = &settings.http_client.retry;
+    let registered = settings.apis.registered.iter().map(|(name, source)| {
+        limit(name, &source.exchange, documented_rate_limit(source.kind));
+        let exchange = source.exchange.clone();
+        let source: Box<dyn PriceSource> = match source.kind {
+            SourceKind::Binance => Box::new(
+                BinanceSource::new(
+                    exchange,
+                    http.clone(),
+                    retry.clone(),
+                    clock_drift.clone(),
+                    endpoints.clone(),
+                )
+                .with_name(intern(name)),
+            ),
+            SourceKind::Coinbase => Box::new(
+                CoinbaseSource::new(
+                    exchange,
+                    settings.general.max_source_age_ms,
+                    http.clone(),
+                    retry.clone(),
+                    clock_drift.clone(),
+                    endpoints.clone(),
+                )
+                .with_name(intern(name)),
+            ),
+            SourceKind::Okx => Box::new(
+                OkxSource::new(
+                    exchange,
+                    http.clone(),
+                    clock_drift.clone(),
+                    endpoints.clone(),
+                )
+                .with_name(intern(name)),
+            ),
+            SourceKind::Bybit => Box::new(
+                BybitSource::new(
+                    exchange,
+                    http.clone(),
+                    clock_drift.clone(),
+                    endpoints.clone(),
+                )
+                .with_name(intern(name)),
+            ),
+        };
+        source
+    });
+    limit(
+        "binance",
+        &settings.apis.binance,
+        binance_client::RATE_LIMIT,
+    );
+    limit(
+        "coinbase",
+        &settings.apis.coinbase,
+        coinbase_client::RATE_LIMIT,
+    );
+    let mut sources: Vec<Box<dyn PriceSource>> = vec![
+        Box::new(BinanceSource::new(
+            settings.apis.binance.clone(),
+            http.clone(),
+            retry.clone(),
+            clock_drift.clone(),
+            endpoints.clone(),
+        )),
+        Box::new(CoinbaseSource::new(
+            settings.apis.coinbase.clone(),
+            settings.general.max_source_age_ms,
+            http.clone(),
+            retry.clone(),
+            clock_drift.clone(),
+            endpoints.clone(),
+        )),
+    ];
+    if let Some(okx) = &settings.apis.okx {
+        limit("okx", okx, okx_client::RATE_LIMIT);
+        sources.push(Box::new(OkxSource::new(
+            okx.clone(),
+            http.clone(),
+            clock_drift.clone(),
+            endpoints.clone(),
+        )));
+    }
+    if let Some(bybit) = &settings.apis.bybit {
+        limit("bybit", bybit, bybit_client::RATE_LIMIT);
+        sources.push(Box::new(BybitSource::new(
+            bybit.clone(),
+            http.clone(),
+            clock_drift.clone(),
+            endpoints.clone(),
+        )));
+    }
+    if let Some(coingecko) = &settings.apis.coingecko {
+        limit(
+            "coingecko",
+            &coingecko.exchange,
+            coingecko_client::RATE_LIMIT,
+        );
+        sources.push(Box::new(CoinGeckoSource::new(
+            coingecko.clone(),
+            http.clone(),
+            endpoints.clone(),
+        )));
+    }
+    sources
+        .into_iter()
+        .chain(registered)
+        .filter(|s| !s.symbols().is_empty())
+        .collect()
+}
+
+/// What one source returned this cycle.
+pub struct SourceData<'a> {
+    pub source: &'a dyn PriceSource,
+    /// `None` when the fetch failed.
+    pub prices: Option<HashMap<String, PriceSample>>,
+    /// Why the source priced none of its tickers this cycle, if it didn't.
+    pub error: Option<String>,
+    /// Fetched only for the tickers `fetch_all` was asked to check.
+    pub depth: Option<HashMap<String, f64>>,
+    /// Fetched only for the tickers `fetch_all` was asked to weight by volume.
+    pub volume: Option<HashMap<String, f64>>,
+    /// Fetched only for the tickers `fetch_all` was asked to quote.
+    pub quotes: Option<HashMap<String, Quote>>,
+}
+
+/// `request`'s result, or an error once `timeout` has passed.
+async fn within<T>(timeout: Duration, request: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(timeout, request)
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("timed out after {} ms", timeout.as_millis())))
+}
+
+/// The source's prices from its stream, when `transport` says to read it, else over REST,
+/// allowing `timeout` for each request the fetch sends. A source that prices its tickers one
+/// by one thus keeps the others' prices when one of them is slow.
+async fn fetch_prices(
+    source: &dyn PriceSource,
+    transport: Transport,
+    timeout: Duration,
+) -> Result<HashMap<String, PriceSample>> {
+    let symbols = source.symbols();
+    match source.stream() {
+        Some(stream) if transport == Transport::Stream => Ok(stream.latest(symbols)),
+        _ => {
+            let requests = source.requests_per_fetch(symbols).max(1);
+            within(timeout * requests, source.fetch(symbols)).await
+        }
+    }
+}
+
+async fn fetch_source<'a>(
+    source: &'a dyn PriceSource,
+    transport: Transport,
+    depth_tickers: Vec<String>,
+    volume_tickers: Vec<String>,
+    quote_tickers: Vec<String>,
+    timeout: Duration,
+) -> SourceData<'a> {
+    let (prices, error) = match fetch_prices(source, transport, timeout).await {
+        Ok(prices) => {
+            log::info!("Successfully fetched prices from {}:", source.name());
+            for (symbol, sample) in &prices {
+                log::debug!("{} - {}: {}", source.name(), symbol, sample.price);
+            }
+            let error = (prices.is_empty() && !source.symbols().is_empty())
+                .then(|| "no ticker could be priced".to_string());
+            (Some(prices), error)
+        }
+        Err(e) => {
+            log::error!("Failed to fetch prices from {}: {}", source.name(), e);
+            (None, Some(format!("{:#}", e)))
+        }
+    };
+    let depth = if depth_tickers.is_empty() {
+        None
+    } else {
+        within(timeout, source.fetch_depth(&depth_tickers))
+            .await
+            .map_err(|e| log::error!("Failed to fetch order books from {}: {}", source.name(), e))
+            .ok()
+    };
+    let volume = if volume_tickers.is_empty() {
+        None
+    } else {
+        within(timeout, source.fetch_volume(&volume_tickers))
+            .await
+            .map_err(|e| log::error!("Failed to fetch volumes from {}: {}", source.name(), e))
+            .ok()
+    };
+    let quotes = if quote_tickers.is_empty() {
+        None
+    } else {
+        within(timeout, source.fetch_quotes(&quote_tickers))
+            .await
+            .map_err(|e| log::error!("Failed to fetch quotes from {}: {}", source.name(), e))
+            .ok()
+    };
+    SourceData {
+        source,
+        prices,
+        error,
+        depth,
+        volume,
+        quotes,
+    }
+}
+
+/// Fetches every source concurrently over the transport `transport` returns for it, plus the
+/// order-book depth of the tickers `depth_tickers` returns for it, the 24h volume of those
+/// `volume_tickers` returns and the best bid and ask of those `quote_tickers` returns. Each
+/// fetch of a source is abandoned after `timeout` per request it sends, so a slow exchange
+/// can't hold up the others. Results are in the order of `sources`.
+pub async fn fetch_all<'a>(
+    sources: &[&'a dyn PriceSource],
+    transport: impl Fn(&dyn PriceSource) -> Transport,
+    depth_tickers: impl Fn(&dyn PriceSource) -> Vec<String>,
+    volume_tickers: impl Fn(&dyn PriceSource) -> Vec<String>,
+    quote_tickers: impl Fn(&dyn PriceSource) -> Vec<String>,
+    timeout: Duration,
+) -> Vec<SourceData<'a>> {
+    join_all(sources.iter().map(|&source| {
+        fetch_source(
+            source,
+            transport(source),
+            depth_tickers(source),
+            volume_tickers(source),
+            quote_tickers(source),
+            timeout,
+        )
+        .instrument(tracing::error_span!("source", source = source.name()))
+    }))
+    .await
+}
+
+/// Fetches `sources` `rounds` times, waiting `spacing` after each round, and discards the
+/// prices. The requests seed what sources learn across fetches, such as clock drift and
+/// endpoint latency estimates and the last trade above `min_trade_notional`, so the first
+/// published values don't rest on a single reading. Returns how many rounds each source
+/// priced at least one ticker in.
+pub async fn burst_fetch(
+    sources: &[&dyn PriceSource],
+    rounds: u32,
+    spacing: Duration,
+    timeout: Duration,
+) -> Vec<(&'static str, u32)> {
+    let mut answered = vec![0; sources.len()];
+    for _ in 0..rounds {
+        let no_tickers = |_: &dyn PriceSource| Vec::new();
+        let fetched = fetch_all(
+            sources,
+            |_| Transport::Rest,
+            no_tickers,
+            no_tickers,
+            no_tickers,
+            timeout,
+        )
+        .await;
+        for (count, data) in answered.iter_mut().zip(&fetched) {
+            if data.error.is_none() {
+                *count += 1;
+            }
+        }
+        tokio::time::sleep(spacing).await;
+    }
+    sources.iter().map(|s| s.name()).zip(answered).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSource {
+        name: &'static str,
+        symbols: Vec<String>,
+        price: Option<Decimal>,
+        delay: Duration,
+        requests: u32,
+    }
+
+    impl MockSource {
+        fn new(name: &'static str, price: Option<Decimal>) -> Self {
+            Self {
+                name,
+                symbols: vec!["BTCUSD".to_string()],
+                price,
+                delay: Duration::ZERO,
+                requests: 1,
+            }
+        }
+
+        fn slow(name: &'static str, delay: Duration) -> Self {
+            Self {
+                delay,
+                ..Self::new(name, Some(Decimal::new(1005, 1)))
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PriceSource for MockSource {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn symbols(&self) -> &[String] {
+            &self.symbols
+        }
+
+        async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+            tokio::time::sleep(self.delay).await;
+            let price = self.price.ok_or_else(|| anyhow!("venue down"))?;
+            Ok(symbols
+                .iter()
+                .map(|s| {
+                    let sample = PriceSample {
+                        price,
+                        timestamp_ms: None,
+                    };
+                    (s.clone(), sample)
+                })
+                .collect())
+        }
+
+        fn requests_per_fetch(&self, _symbols: &[String]) -> u32 {
+            self.requests
+        }
+
+        async fn fetch_depth(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+            Ok(symbols.iter().map(|s| (s.clone(), 1_000.0)).collect())
+        }
+
+        async fn fetch_volume(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+            Ok(symbols.iter().map(|s| (s.clone(), 25.0)).collect())
+        }
+
+        async fn fetch_quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+            let quote = Quote {
+                bid: Decimal::from(100),
+                ask: Decimal::from(101),
+            };
+            Ok(symbols.iter().map(|s| (s.clone(), quote)).collect())
+        }
+    }
+
+    fn trade(price: &str, timestamp_ms: i64) -> PriceSample {
+        PriceSample {
+            price: price.parse().unwrap(),
+            timestamp_ms: Some(timestamp_ms),
+        }
+    }
+
+    #[test]
+    fn test_http_client_rejects_invalid_proxy() {
+        let mut settings = HttpClientSettings::default();
+        assert!(http_client(&settings).is_ok());
+        settings.tcp_keepalive_ms = 0;
+        settings.proxy = Some("http://proxy.internal:3128".to_string());
+        assert!(http_client(&settings).is_ok());
+        settings.proxy = Some("http://[::1".to_string());
+        let err = http_client(&settings).unwrap_err().to_string();
+        assert!(err.starts_with("Invalid http_client.proxy"), "{}", err);
+    }
+
+    #[test]
+    fn test_dust_trades_do_not_move_price() {
+        let filter = TradeFilter::new(Some(100.0));
+        // A dust print before any real trade yields no price.
+        assert_eq!(filter.observe("XYZ-USD", trade("9.0", 1), Some(1.0)), None);
+        assert_eq!(
+            filter.observe("XYZ-USD", trade("10.0", 2), Some(20.0)),
+            Some(trade("10.0", 2))
+        );
+        assert_eq!(
+            filter.observe("XYZ-USD", trade("4.0", 3), Some(0.5)),
+            Some(trade("10.0", 2))
+        );
+        // Sizes that aren't reported can't be judged.
+        assert_eq!(
+            filter.observe("XYZ-USD", trade("10.5", 4), None),
+            Some(trade("10.5", 4))
+        );
+
+        let unfiltered = TradeFilter::new(None);
+        assert_eq!(
+            unfiltered.observe("XYZ-USD", trade("4.0", 3), Some(0.5)),
+            Some(trade("4.0", 3))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_isolates_failing_sources() {
+        let sources: [&dyn PriceSource; 2] = [
+            &MockSource::new("up", Some(Decimal::new(1005, 1))),
+            &MockSource::new("down", None),
+        ];
+        let up_only = |s: &dyn PriceSource| {
+            if s.name() == "up" {
+                s.symbols().to_vec()
+            } else {
+                Vec::new()
+            }
+        };
+        let timeout = Duration::from_secs(5);
+        let rest = |_: &dyn PriceSource| Transport::Rest;
+        let none = |_: &dyn PriceSource| Vec::new();
+        let fetched = fetch_all(&sources, rest, up_only, none, none, timeout).await;
+
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(fetched[0].source.name(), "up");
+        assert_eq!(
+            fetched[0].prices.as_ref().unwrap()["BTCUSD"].price,
+            Decimal::new(1005, 1)
+        );
+        assert_eq!(fetched[0].depth.as_ref().unwrap()["BTCUSD"], 1_000.0);
+        assert!(fetched[0].volume.is_none());
+        assert!(fetched[0].error.is_none());
+        assert!(fetched[1].prices.is_none());
+        assert!(fetched[1].error.is_some());
+        assert!(fetched[1].depth.is_none());
+        assert!(fetched[0].quotes.is_none());
+
+        let fetched = fetch_all(
+            &sources,
+            rest,
+            none,
+            |s| s.symbols().to_vec(),
+            none,
+            timeout,
+        )
+        .await;
+        assert_eq!(fetched[0].volume.as_ref().unwrap()["BTCUSD"], 25.0);
+        assert!(fetched[0].depth.is_none());
+
+        let fetched = fetch_all(
+            &sources,
+            rest,
+            none,
+            none,
+            |s| s.symbols().to_vec(),
+            timeout,
+        )
+        .await;
+        assert_eq!(
+            fetched[0].quotes.as_ref().unwrap()["BTCUSD"].ask,
+            Decimal::from(101)
+        );
+        assert!(fetched[0].volume.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sources_are_fetched_concurrently_with_timeout() {
+        let sources: [&dyn PriceSource; 3] = [
+            &MockSource::slow("hung", Duration::from_secs(30)),
+            &MockSource::slow("a", Duration::from_millis(300)),
+            &MockSource::slow("b", Duration::from_millis(300)),
+        ];
+        let started = std::time::Instant::now();
+        let fetched = fetch_all(
+            &sources,
+            |_| Transport::Rest,
+            |_| Vec::new(),
+            |_| Vec::new(),
+            |_| Vec::new(),
+            Duration::from_millis(500),
+        )
+        .await;
+        // Sequentially the two answering sources alone would take 600 ms.
+        assert!(started.elapsed() < Duration::from_millis(580));
+        assert!(fetched[0].prices.is_none());
+        assert_eq!(fetched[1].source.name(), "a");
+        assert!(fetched[1].prices.is_some());
+        assert!(fetched[2].prices.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_is_per_request() {
+        let one_request = MockSource::slow("one", Duration::from_millis(300));
+        let two_requests = MockSource {
+            requests: 2,
+            ..MockSource::slow("two", Duration::from_millis(300))
+        };
+        let sources: [&dyn PriceSource; 2] = [&one_request, &two_requests];
+        let fetched = fetch_all(
+            &sources,
+            |_| Transport::Rest,
+            |_| Vec::new(),
+            |_| Vec::new(),
+            |_| Vec::new(),
+            Duration::from_millis(200),
+        )
+        .await;
+        assert!(fetched[0].prices.is_none());
+        assert!(fetched[1].prices.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_burst_fetch_counts_answered_rounds() {
+        let sources: [&dyn PriceSource; 2] = [
+            &MockSource::new("up", Some(Decimal::new(1005, 1))),
+            &MockSource::new("down", None),
+        ];
+        let started = std::time::Instant::now();
+        let answered = burst_fetch(
+            &sources,
+            3,
+            Duration::from_millis(20),
+            Duration::from_secs(5),
+        )
+        .await;
+        assert_eq!(answered, [("up", 3), ("down", 0)]);
+        assert!(started.elapsed() >= Duration::from_millis(60));
+    }
+
+    /// Answers the `n`th request with the `n`th of `statuses`, then with `200`s, and counts
+    /// the requests.
+    async fn scripted_server(statuses: Vec<u16>) -> (String, Arc<Mutex<usize>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(Mutex::new(0));
+        let count = served.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let n = {
+                    let mut count = count.lock().unwrap();
+                    *count += 1;
+                    *count
+                };
+                let status = statuses.get(n - 1).copied().unwrap_or(200);
+                let response = format!(
+                    "HTTP/1.1 {} Scripted\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    status
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, served)
+    }
+
+    /// Leaves the first `silent` requests unanswered, then answers `200`s, and counts the
+    /// requests.
+    async fn silent_server(silent: usize) -> (String, Arc<Mutex<usize>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(Mutex::new(0));
+        let count = served.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let n = {
+                    let mut count = count.lock().unwrap();
+                    *count += 1;
+                    *count
+                };
+                if n <= silent {
+                    // Held open past the client's timeout.
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        drop(socket);
+                    });
+                    continue;
+                }
+                let response =
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, served)
+    }
+
+    #[tokio::test]
+    async fn test_only_transient_failures_are_retried() {
+        let policy = RequestRetrySettings {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 2,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay_ms(1), 1);
+        assert_eq!(policy.delay_ms(5), 2);
+        assert_eq!(RequestRetrySettings::default().delay_ms(40), 1000);
+
+        let client = http_client(&HttpClientSettings::default()).unwrap();
+        let get = |client: &Client, url: String| {
+            let client = client.clone();
+            move || {
+                let (client, url) = (client.clone(), url.clone());
+                async move {
+                    let response = crate::rate_limit::check(client.get(&url).send().await?)?;
+                    Ok(response.text().await?)
+                }
+            }
+        };
+
+        let (url, served) = scripted_server(vec![503, 502]).await;
+        let body = with_retry(&policy, "price", get(&client, url))
+            .await
+            .unwrap();
+        assert_eq!((body.as_str(), *served.lock().unwrap()), ("ok", 3));
+
+        let (url, served) = scripted_server(vec![500, 500, 500]).await;
+        let err = with_retry(&policy, "price", get(&client, url))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("500"), "{}", err);
+        assert_eq!(*served.lock().unwrap(), 3);
+
+        // Neither a client error nor a rate limit gets better by asking again.
+        for status in [404, 429] {
+            let (url, served) = scripted_server(vec![status]).await;
+            assert!(
+                with_retry(&policy, "price", get(&client, url))
+                    .await
+                    .is_err()
+            );
+            assert_eq!(*served.lock().unwrap(), 1);
+        }
+
+        // An exchange that never answers is asked again once the request times out.
+        let impatient = http_client(&HttpClientSettings {
+            request_timeout_ms: 100,
+            ..Default::default()
+        })
+        .unwrap();
+        let (url, served) = silent_server(2).await;
+        let body = with_retry(&policy, "price", get(&impatient, url))
+            .await
+            .unwrap();
+        assert_eq!((body.as_str(), *served.lock().unwrap()), ("ok", 3));
+
+        let (url, served) = silent_server(3).await;
+        let err = with_retry(&policy, "price", get(&impatient, url))
+            .await
+            .unwrap_err();
+        assert!(is_transient(&err), "{:?}", err);
+        assert_eq!(*served.lock().unwrap(), 3);
+    }
+}
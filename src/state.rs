@@ -0,0 +1,238 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sui_sdk::types::crypto::{EncodeDecodeBase64, SuiKeyPair};
+
+use crate::config::Settings;
+use crate::keystore;
+use crate::sui_publisher;
+
+/// Copies of every file a migration touched, under `<state_dir>/backup/<timestamp>/`.
+const BACKUP_DIR: &str = "backup";
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// The state files of a deployment, with paths already resolved against `state_dir`.
+#[derive(Debug)]
+pub struct StateFiles<'a> {
+    pub state_dir: &'a str,
+    pub known_objects_files: Vec<&'a str>,
+    pub keystore_file: Option<&'a str>,
+}
+
+impl<'a> StateFiles<'a> {
+    pub fn from_settings(settings: &'a Settings) -> Self {
+        let mut known_objects_files = vec![settings.sui.known_objects_file.as_str()];
+        if let Some(migration) = &settings.sui.migration {
+            known_objects_files.push(migration.previous_known_objects_file.as_str());
+        }
+        Self {
+            state_dir: &settings.general.state_dir,
+            known_objects_files,
+            keystore_file: settings.sui.keystore_file.as_deref(),
+        }
+    }
+}
+
+struct Migration<'a> {
+    files: &'a StateFiles<'a>,
+    legacy_root: &'a Path,
+    backup_dir: PathBuf,
+    steps: Vec<String>,
+}
+
+impl Migration<'_> {
+    fn backup(&self, file: &Path) -> Result<()> {
+        fs::create_dir_all(&self.backup_dir)
+            .context(format!("Failed to create {}", self.backup_dir.display()))?;
+        let name = file
+            .file_name()
+            .ok_or_else(|| anyhow!("{} has no file name", file.display()))?;
+        let backup = self.backup_dir.join(name);
+        fs::copy(file, &backup).context(format!(
+            "Failed to back up {} to {}",
+            file.display(),
+            backup.display()
+        ))?;
+        Ok(())
+    }
+
+    /// Moves `path` from its pre-state-directory location, if it is only found there.
+    fn relocate(&mut self, path: &str) -> Result<()> {
+        let target = Path::new(path);
+        if target.exists() {
+            return Ok(());
+        }
+        let Ok(relative) = target.strip_prefix(self.files.state_dir) else {
+            return Ok(());
+        };
+        let legacy = self.legacy_root.join(relative);
+        if !legacy.exists() {
+            return Ok(());
+        }
+        self.backup(&legacy)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+        }
+        // A rename cannot cross file systems; fall back to copy and delete.
+        if fs::rename(&legacy, target).is_err() {
+            fs::copy(&legacy, target).context(format!(
+                "Failed to copy {} to {}",
+                legacy.display(),
+                path
+            ))?;
+            fs::remove_file(&legacy).context(format!("Failed to remove {}", legacy.display()))?;
+        }
+        self.steps
+            .push(format!("moved {} to {}", legacy.display(), path));
+        Ok(())
+    }
+
+    fn migrate_known_objects(&mut self, path: &str) -> Result<()> {
+        self.relocate(path)?;
+        self.relocate(&sui_publisher::pending_creations_path(path))?;
+        if !sui_publisher::is_legacy_known_objects(path)? {
+            return Ok(());
+        }
+        self.backup(Path::new(path))?;
+        let objects = sui_publisher::load_known_objects(path)?;
+        sui_publisher::save_known_objects(path, &objects)?;
+        self.steps
+            .push(format!("rewrote {} in the versioned schema", path));
+        Ok(())
+    }
+
+    fn migrate_keystore(&mut self, path: &str) -> Result<()> {
+        self.relocate(path)?;
+        let path = Path::new(path);
+        if !path.exists() {
+            return Ok(());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        let Some(key) = keystore::legacy_key_line(&content) else {
+            return Ok(());
+        };
+        SuiKeyPair::decode_base64(key).map_err(|e| {
+            anyhow!(
+                "{} is neither a keystore nor a single base64 key: {}",
+                path.display(),
+                e
+            )
+        })?;
+        self.backup(path)?;
+        keystore::write_keystore(path, &[key.to_string()])?;
+        self.steps
+            .push(format!("converted {} to a Sui keystore", path.display()));
+        Ok(())
+    }
+}
+
+/// Brings state files from the pre-state-directory layout into `state_dir`: files found
+/// only at their old location, relative to `legacy_root`, are moved in, unversioned
+/// known-objects files are rewritten in the current schema, and a single-line key file
+/// becomes a Sui keystore. Every touched file is backed up first. Returns what was done.
+pub fn migrate(files: &StateFiles, legacy_root: &Path, now: DateTime<Utc>) -> Result<Vec<String>> {
+    fs::create_dir_all(files.state_dir).context(format!(
+        "Failed to create state directory {}",
+        files.state_dir
+    ))?;
+    let mut migration = Migration {
+        files,
+        legacy_root,
+        backup_dir: Path::new(files.state_dir)
+            .join(BACKUP_DIR)
+            .join(now.format(BACKUP_TIMESTAMP_FORMAT).to_string()),
+        steps: Vec::new(),
+    };
+    for path in &files.known_objects_files {
+        migration.migrate_known_objects(path)?;
+    }
+    if let Some(path) = files.keystore_file {
+        migration.migrate_keystore(path)?;
+    }
+    if !migration.steps.is_empty() {
+        #[cfg(unix)]
+        {
+            // Backups may hold a key; keep them as private as the keystore.
+            use std::os::unix::fs::PermissionsExt;
+            for entry in fs::read_dir(&migration.backup_dir)? {
+                fs::set_permissions(entry?.path(), fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        migration
+            .steps
+            .push(format!("backups in {}", migration.backup_dir.display()));
+    }
+    Ok(migration.steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sui_sdk::types::base_types::ObjectID;
+
+    const TEST_KEY_B64: &str = "ALiJ7ig1JDkCMh4/TL914LABL4HVntuoSXtf414NmW9K";
+
+    #[test]
+    fn test_migrates_legacy_layout() -> Result<()> {
+        let root = "./test_state_migration";
+        let _ = fs::remove_dir_all(root);
+        fs::create_dir_all(root)?;
+        let id = ObjectID::from_single_byte(7);
+        fs::write(
+            format!("{}/known_price_objects.json", root),
+            format!("{{\"BTC/USD\": \"{}\"}}", id),
+        )?;
+        fs::write(format!("{}/sui.key", root), format!("{}\n", TEST_KEY_B64))?;
+
+        let state_dir = format!("{}/state", root);
+        let known = format!("{}/known_price_objects.json", state_dir);
+        let key = format!("{}/sui.key", state_dir);
+        let files = StateFiles {
+            state_dir: &state_dir,
+            known_objects_files: vec![&known],
+            keystore_file: Some(&key),
+        };
+        let now: DateTime<Utc> = "2026-10-14T12:00:00Z".parse().unwrap();
+        let steps = migrate(&files, Path::new(root), now)?;
+        assert_eq!(steps.len(), 5);
+
+        assert!(!Path::new(&format!("{}/known_price_objects.json", root)).exists());
+        assert!(!sui_publisher::is_legacy_known_objects(&known)?);
+        assert_eq!(
+            sui_publisher::load_known_objects(&known)?.get("BTC/USD"),
+            Some(&id)
+        );
+        assert!(keystore::load_keypair(&key, None, false).is_ok());
+        let backups = format!("{}/backup/20261014T120000Z", state_dir);
+        assert_eq!(
+            fs::read_to_string(format!("{}/sui.key", backups))?,
+            format!("{}\n", TEST_KEY_B64)
+        );
+        assert!(Path::new(&format!("{}/known_price_objects.json", backups)).exists());
+
+        // Already migrated: nothing left to do.
+        assert!(migrate(&files, Path::new(root), now)?.is_empty());
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unrecognised_key_file_is_refused() -> Result<()> {
+        let state_dir = "./test_state_bad_key";
+        fs::create_dir_all(state_dir)?;
+        let key = format!("{}/sui.key", state_dir);
+        fs::write(&key, "not-a-key")?;
+        let files = StateFiles {
+            state_dir,
+            known_objects_files: Vec::new(),
+            keystore_file: Some(&key),
+        };
+        assert!(migrate(&files, Path::new("."), Utc::now()).is_err());
+        assert_eq!(fs::read_to_string(&key)?, "not-a-key");
+        fs::remove_dir_all(state_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::sync::Mutex;
+
+use crate::aggregator;
+use crate::chain::PriceInfo;
+use crate::config::StorageSettings;
+use crate::cycle::SymbolOutcome;
+use crate::sources::PriceSample;
+
+/// Version stored in the database's `user_version`.
+const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS samples (
+    fetched_at_ms INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    ticker TEXT NOT NULL,
+    price TEXT NOT NULL,
+    source_timestamp_ms INTEGER
+);
+CREATE INDEX IF NOT EXISTS samples_fetched_at ON samples (fetched_at_ms);
+CREATE TABLE IF NOT EXISTS prices (
+    recorded_at_ms INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    price REAL NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    manual_override INTEGER NOT NULL,
+    sources INTEGER NOT NULL,
+    outcome TEXT NOT NULL,
+    detail TEXT,
+    digest TEXT,
+    gas_used_mist INTEGER
+);
+CREATE INDEX IF NOT EXISTS prices_recorded_at ON prices (recorded_at_ms);
+CREATE INDEX IF NOT EXISTS prices_symbol ON prices (symbol, recorded_at_ms);
+";
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Every raw sample the sources returned (`samples`, price as reported) and every
+/// aggregated price with what became of it (`prices`: outcome, reason or error, and the
+/// digest and gas of a published one), in a local SQLite database.
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+    retention_ms: u64,
+}
+
+impl HistoryStore {
+    pub fn open(settings: &StorageSettings) -> Result<Self> {
+        let conn = Connection::open(&settings.file)
+            .context(format!("Failed to open price history {}", settings.file))?;
+        Self::init(conn, settings.retention_days * DAY_MS)
+    }
+
+    fn init(conn: Connection, retention_ms: u64) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create the price history tables")?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            retention_ms,
+        })
+    }
+
+    /// Records the samples `source` returned at `fetched_at_ms`; prices are kept as
+    /// reported, unparsed.
+    pub fn record_samples<'a>(
+        &self,
+        fetched_at_ms: u64,
+        source: &str,
+        samples: impl IntoIterator<Item = (&'a String, &'a PriceSample)>,
+    ) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO samples (fetched_at_ms, source, ticker, price, source_timestamp_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (ticker, sample) in samples {
+                insert.execute(params![
+                    fetched_at_ms,
+                    source,
+                    ticker,
+                    sample.price.to_string(),
+                    sample.timestamp_ms
+                ])?;
+            }
+        }
+        tx.commit().context("Failed to record samples")
+    }
+
+    /// Records an aggregated price of `sources` sources and its outcome.
+    pub fn record_price(
+        &self,
+        recorded_at_ms: u64,
+        price_info: &PriceInfo,
+        sources: usize,
+        outcome: &SymbolOutcome,
+    ) -> Result<()> {
+        let (detail, digest, gas_used_mist) = match outcome {
+            SymbolOutcome::Published {
+                digest,
+                gas_used_mist,
+                ..
+            } => (None, Some(digest.as_str()), Some(*gas_used_mist)),
+            SymbolOutcome::Skipped { reason } | SymbolOutcome::Withheld { reason } => {
+                (Some(reason.as_str()), None, None)
+            }
+            SymbolOutcome::Failed { error } => (Some(error.as_str()), None, None),
+        };
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO prices (recorded_at_ms, symbol, price, timestamp_ms, manual_override,
+                     sources, outcome, detail, digest, gas_used_mist)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    recorded_at_ms,
+                    price_info.symbol,
+                    aggregator::as_f64(price_info.price),
+                    price_info.timestamp_ms,
+                    price_info.manual_override,
+                    sources,
+                    outcome.label(),
+                    detail,
+                    digest,
+                    gas_used_mist
+                ],
+            )
+            .context(format!("Failed to record the {} price", price_info.symbol))?;
+        Ok(())
+    }
+
+    /// Deletes rows older than `retention_days` at `now_ms`. Returns how many.
+    pub fn prune(&self, now_ms: u64) -> Result<usize> {
+        if self.retention_ms == 0 {
+            return Ok(0);
+        }
+        let cutoff = now_ms.saturating_sub(self.retention_ms);
+        let conn = self.conn.lock().unwrap();
+        let samples = conn.execute("DELETE FROM samples WHERE fetched_at_ms < ?1", [cutoff])?;
+        let prices = conn.execute("DELETE FROM prices WHERE recorded_at_ms < ?1", [cutoff])?;
+        Ok(samples + prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_records_samples_and_prices_and_prunes() {
+        let store = HistoryStore::init(Connection::open_in_memory().unwrap(), DAY_MS).unwrap();
+        let samples = HashMap::from([(
+            "BTCUSDT".to_string(),
+            PriceSample {
+                price: Decimal::new(6500010, 2),
+                timestamp_ms: Some(900),
+            },
+        )]);
+        store.record_samples(1_000, "binance", &samples).unwrap();
+        let price_info = PriceInfo {
+            symbol: "BTC/USD".to_string(),
+            price: Decimal::new(650001, 1),
+            timestamp_ms: 1_000,
+            manual_override: false,
+            confidence: None,
+        };
+        let published = SymbolOutcome::Published {
+            digest: "D1".to_string(),
+            gas_used_mist: 2_000,
+            gas_usd: None,
+        };
+        store
+            .record_price(1_000, &price_info, 2, &published)
+            .unwrap();
+        let skipped = SymbolOutcome::Skipped {
+            reason: "moved 1.0 bps".to_string(),
+        };
+        store
+            .record_price(DAY_MS + 2_000, &price_info, 2, &skipped)
+            .unwrap();
+
+        {
+            let conn = store.conn.lock().unwrap();
+            let (price, source_ts): (String, Option<i64>) = conn
+                .query_row("SELECT price, source_timestamp_ms FROM samples", [], |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                })
+                .unwrap();
+            assert_eq!((price.as_str(), source_ts), ("65000.10", Some(900)));
+            let published: (String, Option<String>, Option<u64>) = conn
+                .query_row(
+                    "SELECT outcome, digest, gas_used_mist FROM prices ORDER BY recorded_at_ms",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                )
+                .unwrap();
+            assert_eq!(
+                published,
+                ("published".to_string(), Some("D1".to_string()), Some(2_000))
+            );
+        }
+
+        // Only the rows of the first day are past the retention window.
+        assert_eq!(store.prune(DAY_MS + 1_500).unwrap(), 2);
+        let remaining: (String, Option<String>) = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT outcome, detail FROM prices", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(
+            remaining,
+            ("skipped".to_string(), Some("moved 1.0 bps".to_string()))
+        );
+    }
+}
@@ -1,477 +1,2565 @@
 use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use move_core_types::identifier::Identifier;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
 use shared_crypto::intent::{Intent, IntentMessage};
 use sui_sdk::SuiClient;
 use sui_sdk::SuiClientBuilder;
 use sui_sdk::rpc_types::{
-    SuiExecutionStatus, SuiObjectDataOptions, SuiTransactionBlockEffectsAPI,
-    SuiTransactionBlockResponseOptions,
+    SuiExecutionStatus, SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectResponse,
+    SuiObjectResponseQuery, SuiParsedData, SuiRawData, SuiTransactionBlockEffects,
+    SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponseOptions,
 };
-use sui_sdk::types::base_types::{ObjectID, SuiAddress};
-use sui_sdk::types::crypto::{EncodeDecodeBase64, Signature as SuiSdkSignature, SuiKeyPair};
+use sui_sdk::types::base_types::{ObjectID, ObjectRef, SuiAddress};
+use sui_sdk::types::crypto::{Signature as SuiSdkSignature, SuiKeyPair};
+use sui_sdk::types::digests::TransactionDigest;
+use sui_sdk::types::parse_sui_struct_tag;
 use sui_sdk::types::programmable_transaction_builder::ProgrammableTransactionBuilder;
-use sui_sdk::types::transaction::{CallArg, ObjectArg, Transaction, TransactionData};
+use sui_sdk::types::transaction::{
+    CallArg, ObjectArg, ProgrammableTransaction, Transaction, TransactionData,
+};
 use sui_types::object::Owner;
+use sui_types::{SUI_CLOCK_OBJECT_ID, SUI_CLOCK_OBJECT_SHARED_VERSION};
+
+use crate::chain::{
+    Capabilities, ChainBackend, ChainKind, PriceInfo, PriceObjects, PublishReceipt, PublishTrace,
+    Submission,
+};
+use crate::clock_drift;
+use crate::config::{PriceRounding, RetrySettings, SuiSettings};
+use crate::cycle::CycleRng;
+use crate::keystore;
+use crate::metrics::Metrics;
+use crate::move_schema;
+use crate::move_schema::{ArgRole, CallTemplate, ModuleCalls};
+use crate::rpc_failover::RpcHealth;
+use crate::schema;
 
 // Constants
-const PACKAGE_ID_STR: &str = "0xe99f0a2f17480d0859a5eb3c565a9f6ea3cbe4a7dec819dbacdb37f5ee33f482";
-const MODULE_NAME: &str = "price_oracle";
+pub const MODULE_NAME: &str = "price_oracle";
 const CREATE_PRICE_OBJECT_FUNC_NAME: &str = "create_price_object";
 const UPDATE_PRICE_FUNC_NAME: &str = "update_price";
 
-const PUBLISHER_PRIVATE_KEY_B64: &str = "ALiJ7ig1JDkCMh4/TL914LABL4HVntuoSXtf414NmW9K";
-const PUBLISHER_ADDRESS_STR: &str =
-    "0x267eb37d0b256d86f5fea3a86c895de51b23aa4d6abf13fc144b850fed4b7167";
-
-const KNOWN_OBJECTS_FILENAME: &str = "known_price_objects.json";
-const SUI_TESTNET_RPC_URL: &str = "https://fullnode.testnet.sui.io:443";
-const DECIMALS: u8 = 6;
+/// Scale of published prices, `price * 10^DECIMALS` on-chain, unless the feed sets
+/// `symbols."<feed>".decimals`.
+pub const DECIMALS: u8 = 6;
+/// Upper bound of every transaction's gas budget, and the balance a gas coin needs.
 const GAS_BUDGET: u64 = 100_000_000;
 const DEFAULT_GAS_PRICE: u64 = 1000;
+/// Upper bound on object IDs accepted by a single `sui_multiGetObjects` call.
+const MULTI_GET_OBJECTS_BATCH_SIZE: usize = 50;
+/// Gas coins considered when picking one for a transaction.
+const GAS_COIN_PAGE_SIZE: usize = 50;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PriceInfo {
+pub type KnownObjectsMap = HashMap<String, ObjectID>;
+
+/// Version written to the known-objects file.
+const KNOWN_OBJECTS_SCHEMA_VERSION: u32 = 1;
+
+/// On-disk layout of the known-objects file.
+#[derive(Debug, Serialize, Deserialize)]
+struct KnownObjectsFile {
+    schema_version: u32,
+    objects: KnownObjectsMap,
+}
+
+/// Files written before the schema was versioned hold a bare symbol -> ID map. They are
+/// still read, and are rewritten in the current layout on the next save.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StoredKnownObjects {
+    Versioned(KnownObjectsFile),
+    Legacy(KnownObjectsMap),
+}
+
+/// On-chain state of one of our PriceObjects, as read back from the network.
+#[derive(Debug, Clone)]
+pub struct FeedState {
     pub symbol: String,
-    pub price: f64,
-    pub timestamp_ms: u64,
+    pub object_id: ObjectID,
+    pub version: Option<u64>,
+    pub price: Option<f64>,
+    pub timestamp_ms: Option<u64>,
+    pub decimals: Option<u8>,
+    /// Milliseconds between the on-chain timestamp and the time of the read.
+    pub staleness_ms: Option<u64>,
+    /// Set when the object could not be read or its fields could not be decoded.
+    pub error: Option<String>,
+}
+
+impl From<FeedState> for schema::FeedStatus {
+    fn from(state: FeedState) -> Self {
+        schema::FeedStatus {
+            symbol: state.symbol,
+            object_id: state.object_id.to_string(),
+            version: state.version,
+            price: state.price,
+            timestamp_ms: state.timestamp_ms,
+            decimals: state.decimals,
+            staleness_ms: state.staleness_ms,
+            error: state.error,
+        }
+    }
+}
+
+/// Gas coins handed out during one publishing cycle. Each symbol pays from its own coin, so a
+/// failed or in-flight transaction for one symbol cannot leave a stale coin version behind
+/// for the next one.
+#[derive(Default)]
+struct GasReservations {
+    reserved: Mutex<HashSet<ObjectID>>,
+}
+
+impl GasReservations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Releases every coin, for a new round of transactions.
+    fn clear(&self) {
+        self.reserved.lock().unwrap().clear();
+    }
+
+    /// Reserves the first candidate that is not yet taken and can cover `GAS_BUDGET`.
+    fn reserve(&self, candidates: impl IntoIterator<Item = (ObjectID, u64)>) -> Option<ObjectID> {
+        let mut reserved = self.reserved.lock().unwrap();
+        let (object_id, _) = candidates
+            .into_iter()
+            .find(|(id, balance)| *balance >= GAS_BUDGET && !reserved.contains(id))?;
+        reserved.insert(object_id);
+        Some(object_id)
+    }
+}
+
+async fn gas_coins(sui_client: &SuiClient, owner: SuiAddress) -> Result<Vec<(ObjectRef, u64)>> {
+    Ok(counted(sui_client)
+        .coin_read_api()
+        .get_coins(owner, None, None, Some(GAS_COIN_PAGE_SIZE))
+        .await?
+        .data
+        .iter()
+        .map(|c| (c.object_ref(), c.balance))
+        .collect())
+}
+
+/// Reserves one of `coins`, the gas coins of `signer_address` and their balances, in `gas`.
+fn reserve_gas_coin(
+    coins: &[(ObjectRef, u64)],
+    signer_address: SuiAddress,
+    gas: &GasReservations,
+    purpose: &str,
+) -> Result<ObjectRef> {
+    let object_id = gas
+        .reserve(coins.iter().map(|(c, balance)| (c.0, *balance)))
+        .ok_or_else(|| {
+            Permanent(format!(
+                "No unreserved gas coin with at least {} MIST for address {} to {}; \
+                 split the gas coin so each transaction can pay independently",
+                GAS_BUDGET, signer_address, purpose
+            ))
+        })?;
+    coins
+        .iter()
+        .find(|(c, _)| c.0 == object_id)
+        .map(|(c, _)| *c)
+        .ok_or_else(|| anyhow!("Reserved gas coin {} vanished from page", object_id))
+}
+
+async fn select_gas_coin(
+    sui_client: &SuiClient,
+    signer_address: SuiAddress,
+    gas: &GasReservations,
+    purpose: &str,
+) -> Result<ObjectRef> {
+    let coins = gas_coins(sui_client, signer_address)
+        .await
+        .context(format!("Failed to fetch gas coins for {}", purpose))?;
+    reserve_gas_coin(&coins, signer_address, gas, purpose)
+}
+
+/// Budget for `pt` paid from `gas_object_ref`: the gas its dry run used plus
+/// `sui.gas_budget_margin_pct`, capped at `GAS_BUDGET`. A dry run that aborts fails without
+/// spending gas; one that cannot be run falls back to `GAS_BUDGET`.
+async fn gas_budget(
+    sui_client: &SuiClient,
+    sui_settings: &SuiSettings,
+    sender: SuiAddress,
+    gas_object_ref: ObjectRef,
+    pt: &ProgrammableTransaction,
+    gas_price: u64,
+) -> Result<u64> {
+    if !sui_settings.dry_run_gas_budget {
+        return Ok(GAS_BUDGET);
+    }
+    let tx_data = TransactionData::new_programmable(
+        sender,
+        vec![gas_object_ref],
+        pt.clone(),
+        GAS_BUDGET,
+        gas_price,
+    );
+    let response = match counted(sui_client)
+        .read_api()
+        .dry_run_transaction_block(tx_data)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!(
+                "Dry run failed, using the maximum gas budget of {} MIST: {}",
+                GAS_BUDGET,
+                e
+            );
+            return Ok(GAS_BUDGET);
+        }
+    };
+    if response.effects.status() != &SuiExecutionStatus::Success {
+        return Err(Permanent(format!("dry run failed: {:?}", response.effects.status())).into());
+    }
+    let cost = response.effects.gas_cost_summary();
+    Ok(budget_with_margin(
+        cost.computation_cost.saturating_add(cost.storage_cost),
+        sui_settings.gas_budget_margin_pct,
+    ))
+}
+
+/// `cost` plus `margin_pct` percent, at most `GAS_BUDGET`. The storage rebate is paid back
+/// only after execution, so the budget must cover the storage cost in full.
+fn budget_with_margin(cost: u64, margin_pct: f64) -> u64 {
+    let budget = (cost as f64 * (1.0 + margin_pct.max(0.0) / 100.0)).ceil();
+    (budget as u64).min(GAS_BUDGET)
+}
+
+/// A failure no retry can fix, such as a transaction that executed and aborted.
+#[derive(Debug)]
+struct Permanent(String);
+
+impl std::fmt::Display for Permanent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Permanent {}
+
+/// An update older than `sui.retry.max_payload_age_ms` before an attempt to publish it.
+#[derive(Debug)]
+struct Expired {
+    age_ms: u64,
+}
+
+impl std::fmt::Display for Expired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Price update expired: {} ms old", self.age_ms)
+    }
+}
+
+impl std::error::Error for Expired {}
+
+/// Fragments of the errors validators return for an input object whose version was
+/// consumed in the meantime.
+const STALE_OBJECT_ERRORS: [&str; 2] = [
+    "unavailable for consumption",
+    "not available for consumption",
+];
+
+fn is_stale_object(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error);
+    STALE_OBJECT_ERRORS.iter().any(|s| message.contains(s))
+}
+
+/// Fragments of the errors validators return for an owned object, such as a gas coin,
+/// locked by another transaction.
+const LOCKED_OBJECT_ERRORS: [&str; 2] = ["ObjectLockConflict", "equivocated"];
+
+fn is_locked_object(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error);
+    LOCKED_OBJECT_ERRORS.iter().any(|s| message.contains(s))
+}
+
+/// Whether `error` points at the endpoint rather than the transaction, so another
+/// endpoint may succeed.
+fn is_endpoint_failure(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Permanent>().is_none()
+        && !is_stale_object(error)
+        && !is_locked_object(error)
+}
+
+/// The abort code of a Move abort in an execution status error such as
+/// `MoveAbort(MoveLocation { .. }, 3) in command 0`.
+fn move_abort_code(error: &str) -> Option<u64> {
+    let (_, abort) = error.split_once("MoveAbort(")?;
+    let location_and_code = &abort[..abort.rfind(") in command")?];
+    location_and_code.rsplit_once(", ")?.1.parse().ok()
+}
+
+/// How long to wait before retry number `retry` after `error`, or `None` if it cannot help.
+/// A stale object version is retried at once, since a retry reads the current version. A
+/// locked gas coin is retried after the usual backoff; it stays reserved, so the retry pays
+/// from another coin.
+fn retry_delay(
+    error: &anyhow::Error,
+    policy: &RetrySettings,
+    retry: u32,
+    jitter_ms: u64,
+) -> Option<Duration> {
+    if error.downcast_ref::<Permanent>().is_some() {
+        return None;
+    }
+    if is_stale_object(error) {
+        return Some(Duration::ZERO);
+    }
+    let backoff_ms = policy
+        .base_delay_ms
+        .saturating_mul(1u64 << retry.saturating_sub(1).min(20));
+    Some(Duration::from_millis(backoff_ms.saturating_add(jitter_ms)))
+}
+
+/// What a fullnode reported for an executed transaction.
+struct Executed {
+    digest: String,
+    /// `None` when the response carried no effects.
+    status: Option<SuiExecutionStatus>,
+    /// Net gas charged (computation + storage - rebate), in MIST.
+    gas_used_mist: u64,
+}
+
+/// The receipt of `executed`. A transaction that did not succeed is a `Permanent` failure,
+/// naming the abort code if it aborted.
+fn receipt(label: &str, executed: Executed) -> Result<PublishReceipt> {
+    match &executed.status {
+        Some(SuiExecutionStatus::Success) => Ok(PublishReceipt {
+            digest: executed.digest,
+            gas_used_mist: executed.gas_used_mist,
+        }),
+        status => {
+            let abort_code = match status {
+                Some(SuiExecutionStatus::Failure { error }) => move_abort_code(error),
+                _ => None,
+            };
+            Err(Permanent(format!(
+                "{} transaction failed: {:?}{}. Digest: {}",
+                label,
+                status,
+                abort_code.map_or(String::new(), |code| format!(" (Move abort code {})", code)),
+                executed.digest
+            ))
+            .into())
+        }
+    }
+}
+
+/// One update of a block: its price, the PriceObject it goes to and its payload.
+struct BlockUpdate<'a> {
+    price_info: &'a PriceInfo,
+    object_id: ObjectID,
+    payload: Payload,
+}
+
+/// The Sui RPC calls of an attempt to publish a block. `PooledRpc` makes them through the
+/// publisher's fullnodes; tests script them with `mock_rpc::MockRpc`.
+#[async_trait]
+trait BlockRpc: Send + Sync {
+    /// The current reference of each of `ids`, in order; `None` for an object not found.
+    async fn object_refs(&self, ids: &[ObjectID]) -> Result<Vec<Option<ObjectRef>>>;
+
+    /// Gas coins of `owner` and their balances in MIST.
+    async fn gas_coins(&self, owner: SuiAddress) -> Result<Vec<(ObjectRef, u64)>>;
+
+    /// Signs and executes the block writing each of `updates` to its object in `objects`,
+    /// paid from `gas_coin`.
+    async fn execute_block(
+        &self,
+        updates: &[BlockUpdate<'_>],
+        objects: &[ObjectRef],
+        gas_coin: ObjectRef,
+        trace: &mut PublishTrace,
+    ) -> Result<Executed>;
+}
+
+const BATCH_LABEL: &str = "update_price_batch";
+
+/// Publishes `updates` as one block through `rpc`, retrying failed attempts per `policy`.
+/// Every attempt reads the PriceObjects' current versions and reserves a gas coin of its
+/// own, so an attempt rejected for a stale object version is retried at once, and one whose
+/// coin another transaction locked pays from another coin. Once the oldest update is older
+/// than `policy.max_payload_age_ms`, no further attempt is made and the block fails as
+/// `Expired`. The retries' jitter is drawn from `rng`.
+async fn submit_block(
+    rpc: &dyn BlockRpc,
+    owner: SuiAddress,
+    policy: &RetrySettings,
+    updates: &[BlockUpdate<'_>],
+    gas: &GasReservations,
+    rng: &mut CycleRng,
+    trace: &mut PublishTrace,
+) -> Result<PublishReceipt> {
+    let mut attempt = 1;
+    loop {
+        if let Some(max_age_ms) = policy.max_payload_age_ms {
+            let oldest_ms = updates.iter().map(|u| u.price_info.timestamp_ms).min();
+            let age_ms = oldest_ms.map_or(0, |oldest_ms| {
+                (clock_drift::local_now_ms() as u64).saturating_sub(oldest_ms)
+            });
+            if age_ms > max_age_ms {
+                return Err(Expired { age_ms }.into());
+            }
+        }
+        let error = match attempt_block(rpc, owner, updates, gas, trace).await {
+            Ok(receipt) => return Ok(receipt),
+            Err(e) => e,
+        };
+        let delay = (attempt < policy.max_attempts)
+            .then(|| retry_delay(&error, policy, attempt, rng.jitter_ms(policy.jitter_ms)))
+            .flatten();
+        let Some(delay) = delay else {
+            return Err(error);
+        };
+        log::warn!(
+            "Attempt {} of {} to submit {} price updates failed; retrying in {} ms: {:#}",
+            attempt,
+            policy.max_attempts,
+            updates.len(),
+            delay.as_millis(),
+            error
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn attempt_block(
+    rpc: &dyn BlockRpc,
+    owner: SuiAddress,
+    updates: &[BlockUpdate<'_>],
+    gas: &GasReservations,
+    trace: &mut PublishTrace,
+) -> Result<PublishReceipt> {
+    let object_ids: Vec<ObjectID> = updates.iter().map(|u| u.object_id).collect();
+    let mut objects = Vec::with_capacity(object_ids.len());
+    for ids in object_ids.chunks(MULTI_GET_OBJECTS_BATCH_SIZE) {
+        let refs = rpc
+            .object_refs(ids)
+            .await
+            .context("Failed to fetch PriceObjects for update")?;
+        // Responses come back in request order.
+        for (object_id, object_ref) in ids.iter().zip(refs) {
+            objects.push(
+                object_ref.ok_or_else(|| {
+                    anyhow!("PriceObject {} data not found for update", object_id)
+                })?,
+            );
+        }
+    }
+    let coins = rpc
+        .gas_coins(owner)
+        .await
+        .context(format!("Failed to fetch gas coins for {}", BATCH_LABEL))?;
+    let gas_coin = reserve_gas_coin(&coins, owner, gas, BATCH_LABEL)?;
+    log::info!(
+        "Submitting {} price updates in one transaction: {}",
+        updates.len(),
+        updates
+            .iter()
+            .map(|u| u.price_info.symbol.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let executed = rpc
+        .execute_block(updates, &objects, gas_coin, trace)
+        .await?;
+    let receipt = receipt(BATCH_LABEL, executed)?;
+    log::info!(
+        "Successfully submitted {} price updates. Transaction Digest: {}, gas used: {} MIST",
+        updates.len(),
+        receipt.digest,
+        receipt.gas_used_mist
+    );
+    Ok(receipt)
+}
+
+/// `BlockRpc` through the publisher's RPC pool. Each call goes to the endpoint in use, and
+/// one failing for reasons of the endpoint cools it down, so a retry goes to a fallback.
+struct PooledRpc<'a> {
+    publisher: &'a SuiPublisher,
+}
+
+impl PooledRpc<'_> {
+    async fn call<T, F>(&self, request: impl FnOnce(SuiClient) -> F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        let (index, client) = self.publisher.rpc.client().await?;
+        let result = request(client).await;
+        if let Err(e) = &result {
+            self.publisher.rpc.record_failure(index, e);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl BlockRpc for PooledRpc<'_> {
+    async fn object_refs(&self, ids: &[ObjectID]) -> Result<Vec<Option<ObjectRef>>> {
+        self.call(|client| async move {
+            let responses = counted(&client)
+                .read_api()
+                .multi_get_object_with_options(ids.to_vec(), SuiObjectDataOptions::new())
+                .await?;
+            Ok(responses
+                .into_iter()
+                .map(|r| r.data.map(|data| data.object_ref()))
+                .collect())
+        })
+        .await
+    }
+
+    async fn gas_coins(&self, owner: SuiAddress) -> Result<Vec<(ObjectRef, u64)>> {
+        self.call(|client| async move { gas_coins(&client, owner).await })
+            .await
+    }
+
+    async fn execute_block(
+        &self,
+        updates: &[BlockUpdate<'_>],
+        objects: &[ObjectRef],
+        gas_coin: ObjectRef,
+        trace: &mut PublishTrace,
+    ) -> Result<Executed> {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        for (update, object_ref) in updates.iter().zip(objects) {
+            self.publisher.add_update(
+                &mut builder,
+                update.price_info,
+                *object_ref,
+                update.payload,
+            )?;
+        }
+        let pt = builder.finish();
+        self.call(|client| async move {
+            self.publisher
+                .execute_paid(&client, pt, BATCH_LABEL, gas_coin, trace)
+                .await
+        })
+        .await
+    }
 }
 
-type KnownObjectsMap = HashMap<String, ObjectID>;
+/// Address the oracle signs and pays gas from.
+pub fn publisher_address(settings: &SuiSettings) -> Result<SuiAddress> {
+    let keypair = keystore::publisher_keypair(settings)?;
+    Ok(SuiAddress::from(&keypair.public()))
+}
 
-fn get_publisher_keypair() -> Result<SuiKeyPair> {
-    SuiKeyPair::decode_base64(PUBLISHER_PRIVATE_KEY_B64)
-        .map_err(|e| anyhow!("Failed to decode base64 private key: {}", e))
+/// Total SUI balance (in MIST) of each address, or why it could not be read.
+pub async fn sui_balances(
+    sui_settings: &SuiSettings,
+    addresses: &[SuiAddress],
+) -> Result<Vec<Result<u128>>> {
+    let sui_client = connect_client(sui_settings).await?;
+    let mut balances = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        balances.push(
+            counted(&sui_client)
+                .coin_read_api()
+                .get_balance(*address, None)
+                .await
+                .map(|b| b.total_balance)
+                .context(format!("Failed to read balance of {}", address)),
+        );
+    }
+    Ok(balances)
 }
 
-fn load_known_objects() -> Result<KnownObjectsMap> {
-    let path = Path::new(KNOWN_OBJECTS_FILENAME);
+fn load_json_map<T: DeserializeOwned>(filename: &str) -> Result<HashMap<String, T>> {
+    let path = Path::new(filename);
     if !path.exists() {
         return Ok(HashMap::new());
     }
-    let file = File::open(path).context(format!("Failed to open {}", KNOWN_OBJECTS_FILENAME))?;
+    let file = File::open(path).context(format!("Failed to open {}", filename))?;
     let reader = BufReader::new(file);
-    let objects: KnownObjectsMap = serde_json::from_reader(reader).context(format!(
-        "Failed to parse JSON from {}",
-        KNOWN_OBJECTS_FILENAME
-    ))?;
-    Ok(objects)
+    let map = serde_json::from_reader(reader)
+        .context(format!("Failed to parse JSON from {}", filename))?;
+    Ok(map)
 }
 
-fn save_known_objects(objects: &KnownObjectsMap) -> Result<()> {
-    let path = Path::new(KNOWN_OBJECTS_FILENAME);
+fn save_json<T: Serialize + ?Sized>(filename: &str, value: &T) -> Result<()> {
+    let path = Path::new(filename);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+    }
     let file = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
         .open(path)
-        .context(format!(
-            "Failed to open or create {} for writing",
-            KNOWN_OBJECTS_FILENAME
-        ))?;
+        .context(format!("Failed to open or create {} for writing", filename))?;
     let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, objects).context(format!(
-        "Failed to write JSON to {}",
-        KNOWN_OBJECTS_FILENAME
-    ))?;
+    serde_json::to_writer_pretty(writer, value)
+        .context(format!("Failed to write JSON to {}", filename))?;
     Ok(())
 }
 
-fn scale_price(price_f64: f64) -> u64 {
-    (price_f64 * 10f64.powi(DECIMALS as i32)).round() as u64
-}
+fn save_json_map<T: Serialize>(filename: &str, map: &HashMap<String, T>) -> Result<()> {
+    save_json(filename, map)
+}
+
+fn load_stored_known_objects(filename: &str) -> Result<Option<StoredKnownObjects>> {
+    let path = Path::new(filename);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(path).context(format!("Failed to open {}", filename))?;
+    let stored = serde_json::from_reader(BufReader::new(file))
+        .context(format!("Failed to parse JSON from {}", filename))?;
+    Ok(Some(stored))
+}
+
+pub fn load_known_objects(filename: &str) -> Result<KnownObjectsMap> {
+    Ok(match load_stored_known_objects(filename)? {
+        Some(StoredKnownObjects::Versioned(file)) => file.objects,
+        Some(StoredKnownObjects::Legacy(objects)) => objects,
+        None => KnownObjectsMap::new(),
+    })
+}
+
+/// Whether `filename` exists and predates the versioned schema.
+pub fn is_legacy_known_objects(filename: &str) -> Result<bool> {
+    Ok(matches!(
+        load_stored_known_objects(filename)?,
+        Some(StoredKnownObjects::Legacy(_))
+    ))
+}
+
+pub fn save_known_objects(filename: &str, objects: &KnownObjectsMap) -> Result<()> {
+    save_json(
+        filename,
+        &KnownObjectsFile {
+            schema_version: KNOWN_OBJECTS_SCHEMA_VERSION,
+            objects: objects.clone(),
+        },
+    )
+}
+
+/// `price` at `decimals` decimals, as written on-chain, rounded from its decimal digits.
+/// Fails for a negative price or one that does not fit a `u64` at `decimals` decimals.
+pub fn scale_price(price: Decimal, decimals: u8, rounding: PriceRounding) -> Result<u64> {
+    let strategy = match rounding {
+        PriceRounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+        PriceRounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+        PriceRounding::Down => RoundingStrategy::ToZero,
+        PriceRounding::Up => RoundingStrategy::AwayFromZero,
+    };
+    if price.is_sign_negative() && !price.is_zero() {
+        return Err(anyhow!("price {} is negative", price));
+    }
+    10u64
+        .checked_pow(decimals.into())
+        .and_then(|unit| price.checked_mul(Decimal::from(unit)))
+        .and_then(|scaled| scaled.round_dp_with_strategy(0, strategy).to_u64())
+        .ok_or_else(|| {
+            anyhow!(
+                "price {} does not fit a u64 at {} decimals",
+                price,
+                decimals
+            )
+        })
+}
+
+/// The price of the inverse pair, e.g. USD/BTC for a BTC/USD `price`. Fails when the
+/// inverse would round to zero at `decimals` decimals or is too large to scale, rather
+/// than publishing a wrong value.
+pub fn inverse_price(
+    price: Decimal,
+    decimals: u8,
+    rounding: PriceRounding,
+) -> Result<Decimal, String> {
+    let inverse = Decimal::ONE
+        .checked_div(price)
+        .filter(|_| price > Decimal::ZERO)
+        .ok_or_else(|| format!("{} has no inverse", price))?;
+    match scale_price(inverse, decimals, rounding) {
+        Ok(0) => Err(format!(
+            "inverse of {} rounds to 0 at {} decimals",
+            price, decimals
+        )),
+        Ok(_) => Ok(inverse),
+        Err(_) => Err(format!(
+            "inverse of {} is too large to publish at {} decimals",
+            price, decimals
+        )),
+    }
+}
+
+fn unscale_price(scaled: u64, decimals: u8) -> f64 {
+    scaled as f64 / 10f64.powi(decimals as i32)
+}
+
+/// The objects among `states` whose on-chain decimals differ from `decimals` of their feed,
+/// as `<feed> (<on-chain> on-chain, <configured> configured)`.
+fn decimals_mismatches(states: &[FeedState], decimals: impl Fn(&str) -> u8) -> Vec<String> {
+    states
+        .iter()
+        .filter_map(|state| {
+            let on_chain = state.decimals?;
+            let configured = decimals(&state.symbol);
+            (on_chain != configured).then(|| {
+                format!(
+                    "{} ({} on-chain, {} configured)",
+                    state.symbol, on_chain, configured
+                )
+            })
+        })
+        .collect()
+}
+
+/// Sui RPC requests made by this process, for `loadtest` reports.
+static RPC_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+pub fn rpc_requests() -> u64 {
+    RPC_REQUESTS.load(Ordering::Relaxed)
+}
+
+/// Counts one RPC request about to be made through `client`.
+fn counted(client: &SuiClient) -> &SuiClient {
+    RPC_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    client
+}
+
+/// A client per configured fullnode, each connected when first needed, and their health.
+struct RpcPool {
+    clients: Vec<tokio::sync::OnceCell<SuiClient>>,
+    health: Mutex<RpcHealth>,
+}
+
+impl RpcPool {
+    fn new(settings: &SuiSettings) -> Self {
+        let urls: Vec<String> = std::iter::once(settings.rpc_url.clone())
+            .chain(settings.fallback_rpc_urls.iter().cloned())
+            .collect();
+        Self {
+            clients: urls.iter().map(|_| tokio::sync::OnceCell::new()).collect(),
+            health: Mutex::new(RpcHealth::new(
+                urls,
+                Duration::from_secs(settings.rpc_cooldown_seconds),
+            )),
+        }
+    }
+
+    /// The client of the endpoint in use and its index. Fails over to the next endpoint
+    /// when one cannot connect or fails its health probe; errors only if all of them do.
+    async fn client(&self) -> Result<(usize, SuiClient)> {
+        let mut last_error = None;
+        for _ in 0..self.clients.len() {
+            let (choice, url) = {
+                let health = self.health.lock().unwrap();
+                let choice = health.choose(std::time::Instant::now());
+                (choice, health.url(choice.index).to_string())
+            };
+            let cell = &self.clients[choice.index];
+            // Connecting checks the node, so a new client needs no probe.
+            let probe = choice.probe && cell.initialized();
+            let result = async {
+                let client = cell.get_or_try_init(|| build_sui_client(&url)).await?;
+                if probe {
+                    counted(client)
+                        .governance_api()
+                        .get_reference_gas_price()
+                        .await
+                        .context(format!("Health probe of {} failed", url))?;
+                }
+                anyhow::Ok(client.clone())
+            }
+            .await;
+            match result {
+                Ok(client) => {
+                    self.health.lock().unwrap().record_success(choice.index);
+                    return Ok((choice.index, client));
+                }
+                Err(e) => {
+                    self.record_failure(choice.index, &e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error
+            .expect("sui.rpc_url is always configured")
+            .context("No Sui RPC endpoint is reachable"))
+    }
+
+    /// Cools endpoint `index` down if `error` came from it rather than the transaction.
+    fn record_failure(&self, index: usize, error: &anyhow::Error) {
+        if is_endpoint_failure(error) {
+            self.health
+                .lock()
+                .unwrap()
+                .record_failure(index, std::time::Instant::now(), error);
+        }
+    }
+}
+
+/// A client of the first configured fullnode that is reachable.
+async fn connect_client(sui_settings: &SuiSettings) -> Result<SuiClient> {
+    Ok(RpcPool::new(sui_settings).client().await?.1)
+}
+
+async fn build_sui_client(rpc_url: &str) -> Result<SuiClient> {
+    // Connecting checks the node's API version.
+    RPC_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let sui_client = SuiClientBuilder::default()
+        .request_timeout(Duration::from_secs(30))
+        .build(rpc_url)
+        .await
+        .context(format!("Failed to build Sui client for URL: {}", rpc_url))?;
+    log::info!("Sui client connected to: {}", rpc_url);
+    Ok(sui_client)
+}
+
+/// Move `u64` fields are rendered as JSON strings, smaller integers as JSON numbers.
+fn json_u64(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::String(s) => s.parse().ok(),
+        serde_json::Value::Number(n) => n.as_u64(),
+        _ => None,
+    }
+}
+
+/// Extracts `(price, timestamp_ms, decimals)` from the JSON fields of a PriceObject.
+fn parse_price_object_fields(fields: &serde_json::Value) -> Option<(u64, u64, u8)> {
+    let price = json_u64(fields.get("price")?)?;
+    let timestamp_ms = json_u64(fields.get("timestamp_ms")?)?;
+    let decimals = u8::try_from(json_u64(fields.get("decimals")?)?).ok()?;
+    Some((price, timestamp_ms, decimals))
+}
+
+fn feed_state_from_response(
+    symbol: &str,
+    object_id: ObjectID,
+    response: SuiObjectResponse,
+    now_ms: u64,
+) -> FeedState {
+    let mut state = FeedState {
+        symbol: symbol.to_string(),
+        object_id,
+        version: None,
+        price: None,
+        timestamp_ms: None,
+        decimals: None,
+        staleness_ms: None,
+        error: None,
+    };
+
+    let Some(data) = response.data else {
+        state.error = Some(
+            response
+                .error
+                .map_or_else(|| "Object not found".to_string(), |e| e.to_string()),
+        );
+        return state;
+    };
+    state.version = Some(data.version.value());
+
+    let fields = match data.content {
+        Some(SuiParsedData::MoveObject(obj)) => obj.fields.to_json_value(),
+        _ => {
+            state.error = Some("Object has no Move content".to_string());
+            return state;
+        }
+    };
+    match parse_price_object_fields(&fields) {
+        Some((price, timestamp_ms, decimals)) => {
+            state.price = Some(unscale_price(price, decimals));
+            state.timestamp_ms = Some(timestamp_ms);
+            state.decimals = Some(decimals);
+            state.staleness_ms = Some(now_ms.saturating_sub(timestamp_ms));
+        }
+        None => state.error = Some(format!("Unexpected PriceObject fields: {}", fields)),
+    }
+    state
+}
+
+/// Reads the on-chain state of every PriceObject in `known_objects`, batching the reads
+/// through `sui_multiGetObjects` instead of issuing one request per feed.
+async fn fetch_feed_states(
+    sui_client: &SuiClient,
+    known_objects: &KnownObjectsMap,
+) -> Result<Vec<FeedState>> {
+    let mut feeds: Vec<(&String, &ObjectID)> = known_objects.iter().collect();
+    feeds.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut states = Vec::with_capacity(feeds.len());
+    for chunk in feeds.chunks(MULTI_GET_OBJECTS_BATCH_SIZE) {
+        let object_ids: Vec<ObjectID> = chunk.iter().map(|(_, id)| **id).collect();
+        let responses = counted(sui_client)
+            .read_api()
+            .multi_get_object_with_options(object_ids, SuiObjectDataOptions::new().with_content())
+            .await
+            .context("Failed to batch-read PriceObjects")?;
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        // Responses come back in request order.
+        for ((symbol, object_id), response) in chunk.iter().zip(responses) {
+            states.push(feed_state_from_response(
+                symbol,
+                **object_id,
+                response,
+                now_ms,
+            ));
+        }
+    }
+    Ok(states)
+}
+
+/// Batch-reads the on-chain state of all known PriceObjects.
+pub async fn chain_status(sui_settings: &SuiSettings) -> Result<Vec<FeedState>> {
+    let known_objects = load_known_objects(&sui_settings.known_objects_file)?;
+    read_feed_states(sui_settings, &known_objects).await
+}
+
+/// Batch-reads the on-chain state of the given PriceObjects.
+pub async fn read_feed_states(
+    sui_settings: &SuiSettings,
+    objects: &KnownObjectsMap,
+) -> Result<Vec<FeedState>> {
+    if objects.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sui_client = connect_client(sui_settings).await?;
+    fetch_feed_states(&sui_client, objects).await
+}
+
+/// Write-ahead record of a `create_price_object` transaction, saved before it is executed.
+/// If the process dies after the object was created but before the known-objects map was
+/// saved, the digest lets the next run find that object instead of creating a duplicate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingCreation {
+    tx_digest: String,
+    recorded_at_ms: u64,
+}
+
+/// Pending creations live next to the known-objects file they belong to, so each network
+/// keeps its own.
+pub fn pending_creations_path(known_objects_file: &str) -> String {
+    match known_objects_file.strip_suffix(".json") {
+        Some(stem) => format!("{}.pending.json", stem),
+        None => format!("{}.pending", known_objects_file),
+    }
+}
+
+fn price_object_type(sui_settings: &SuiSettings) -> String {
+    format!("{}::{}::PriceObject", sui_settings.package_id, MODULE_NAME)
+}
+
+/// Decodes the `symbol: vector<u8>` field, which JSON-RPC renders as an array of bytes.
+fn parse_symbol_field(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(items) => {
+            let bytes = items
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()?;
+            String::from_utf8(bytes).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Returns the PriceObject among the objects created by `effects`, if any.
+async fn find_created_price_object(
+    sui_client: &SuiClient,
+    sui_settings: &SuiSettings,
+    signer_address: SuiAddress,
+    effects: &SuiTransactionBlockEffects,
+) -> Result<Option<ObjectID>> {
+    let price_object_type_tag_str_pattern = price_object_type(sui_settings);
+
+    for created_obj_ref in effects.created() {
+        let owner_address = match created_obj_ref.owner {
+            Owner::AddressOwner(addr) => Some(addr),
+            _ => None, // Other owner types are not relevant here
+        };
+
+        if owner_address == Some(signer_address) {
+            let object_id_to_check = created_obj_ref.reference.object_id;
+            let obj_response = counted(sui_client)
+                .read_api()
+                .get_object_with_options(
+                    object_id_to_check,
+                    SuiObjectDataOptions::new().with_type(),
+                )
+                .await
+                .context(format!(
+                    "Failed to fetch created object {} to verify type",
+                    object_id_to_check
+                ))?;
+
+            if let Some(obj_type) = obj_response.data.and_then(|d| d.type_)
+                && obj_type
+                    .to_string()
+                    .contains(&price_object_type_tag_str_pattern)
+            {
+                log::info!("Found created PriceObject with ID: {}", object_id_to_check);
+                return Ok(Some(object_id_to_check));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Checks whether a previously recorded creation transaction actually executed.
+async fn recover_pending_creation(
+    sui_client: &SuiClient,
+    sui_settings: &SuiSettings,
+    signer_address: SuiAddress,
+    pending: &PendingCreation,
+) -> Result<Option<ObjectID>> {
+    let digest = TransactionDigest::from_str(&pending.tx_digest)
+        .map_err(|e| anyhow!("Invalid pending tx digest {}: {}", pending.tx_digest, e))?;
+    let response = match counted(sui_client)
+        .read_api()
+        .get_transaction_with_options(
+            digest,
+            SuiTransactionBlockResponseOptions::new().with_effects(),
+        )
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::info!(
+                "Pending creation tx {} not found on-chain ({}); assuming it never executed",
+                pending.tx_digest,
+                e
+            );
+            return Ok(None);
+        }
+    };
+    match response.effects {
+        Some(effects) if effects.status() == &SuiExecutionStatus::Success => {
+            find_created_price_object(sui_client, sui_settings, signer_address, &effects).await
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Looks for a PriceObject for `symbol` already owned by `owner`, e.g. one created by a run
+/// that crashed before recording it.
+async fn find_owned_price_object(
+    sui_client: &SuiClient,
+    sui_settings: &SuiSettings,
+    owner: SuiAddress,
+    symbol: &str,
+) -> Result<Option<ObjectID>> {
+    let struct_tag = parse_sui_struct_tag(&price_object_type(sui_settings))
+        .context("Invalid PriceObject type tag")?;
+    let query = SuiObjectResponseQuery::new(
+        Some(SuiObjectDataFilter::StructType(struct_tag)),
+        Some(SuiObjectDataOptions::new().with_content()),
+    );
+
+    let mut cursor = None;
+    loop {
+        let page = counted(sui_client)
+            .read_api()
+            .get_owned_objects(owner, Some(query.clone()), cursor, None)
+            .await
+            .context("Failed to list owned PriceObjects")?;
+        for response in page.data {
+            let Some(data) = response.data else { continue };
+            let object_id = data.object_id;
+            if let Some(SuiParsedData::MoveObject(obj)) = data.content {
+                let fields = obj.fields.to_json_value();
+                if fields.get("symbol").and_then(parse_symbol_field).as_deref() == Some(symbol) {
+                    return Ok(Some(object_id));
+                }
+            }
+        }
+        if !page.has_next_page {
+            return Ok(None);
+        }
+        cursor = page.next_cursor;
+    }
+}
+
+/// Publishes to one package: the Sui client, publisher key and PriceObject IDs are set up
+/// once and reused by every cycle.
+pub struct SuiPublisher {
+    settings: SuiSettings,
+    calls: ModuleCalls,
+    rpc: RpcPool,
+    keypair: SuiKeyPair,
+    address: SuiAddress,
+    /// PriceObject of each symbol, as saved in `known_objects_file`.
+    known_objects: Mutex<KnownObjectsMap>,
+    submitted: SubmittedPayloads,
+    /// Decimals of the feeds not published at `DECIMALS`.
+    decimals: HashMap<String, u8>,
+    /// Gas coins reserved in the current round, shared with other publishers of the address.
+    gas: Arc<GasReservations>,
+}
+
+impl SuiPublisher {
+    /// Loads the publisher key, connects to `settings.rpc_url` (or the first reachable
+    /// fallback) and reads how to call the package. Fails on a package whose functions the
+    /// oracle cannot call.
+    pub async fn connect(settings: SuiSettings) -> Result<Self> {
+        let keypair =
+            keystore::publisher_keypair(&settings).context("Failed to get publisher keypair")?;
+        let address = SuiAddress::from(&keypair.public());
+        let rpc = RpcPool::new(&settings);
+        let (_, client) = rpc.client().await?;
+        let calls = module_calls(&client, &settings).await?;
+        let known_objects = load_known_objects(&settings.known_objects_file)?;
+        log::info!(
+            "Publishing to package {} as {}",
+            settings.package_id,
+            address
+        );
+        Ok(Self {
+            settings,
+            calls,
+            rpc,
+            keypair,
+            address,
+            known_objects: Mutex::new(known_objects),
+            submitted: SubmittedPayloads::new(),
+            decimals: HashMap::new(),
+            gas: Arc::new(GasReservations::new()),
+        })
+    }
+
+    /// Publishes the feeds in `decimals` at their decimals, as from
+    /// `Settings::feed_decimals`.
+    pub fn with_decimals(self, decimals: HashMap<String, u8>) -> Self {
+        Self { decimals, ..self }
+    }
+
+    /// Reserves gas coins together with `other`, e.g. the previous package's publisher
+    /// with the current one's, since both pay from the same address. Rounds begun on either
+    /// are begun on both.
+    pub fn sharing_gas_with(self, other: &SuiPublisher) -> Self {
+        Self {
+            gas: other.gas.clone(),
+            ..self
+        }
+    }
+
+    /// The PriceObject of `symbol`, if it has one. A miss rereads `known_objects_file`, so
+    /// objects imported while the oracle runs are picked up.
+    fn price_object(&self, symbol: &str) -> Result<Option<ObjectID>> {
+        let mut known_objects = self.known_objects.lock().unwrap();
+        if let Some(object_id) = known_objects.get(symbol) {
+            return Ok(Some(*object_id));
+        }
+        *known_objects = load_known_objects(&self.settings.known_objects_file)?;
+        Ok(known_objects.get(symbol).copied())
+    }
+
+    /// Fails if a known PriceObject was created with other decimals than its feed is
+    /// configured with, since every update would then be scaled wrongly. Objects that
+    /// cannot be read are not checked.
+    pub async fn check_decimals(&self) -> Result<()> {
+        let known = self.known_objects()?;
+        let states = match self.read_feed_states(&known).await {
+            Ok(states) => states,
+            Err(e) => {
+                log::warn!("Could not check the decimals of the PriceObjects: {:#}", e);
+                return Ok(());
+            }
+        };
+        let mismatches = decimals_mismatches(&states, |symbol| self.decimals(symbol));
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "PriceObjects with other decimals than configured: {}; set \
+             symbols.\"<feed>\".decimals to match or create new PriceObjects",
+            mismatches.join(", ")
+        ))
+    }
+
+    /// Persists `object_id` as the PriceObject for `symbol` and clears any pending creation.
+    fn remember_price_object(&self, symbol: &str, object_id: ObjectID) -> Result<ObjectID> {
+        let sui_settings = &self.settings;
+        {
+            let mut known_objects = self.known_objects.lock().unwrap();
+            known_objects.insert(symbol.to_string(), object_id);
+            save_known_objects(&sui_settings.known_objects_file, &known_objects)?;
+        }
+
+        let pending_path = pending_creations_path(&sui_settings.known_objects_file);
+        let mut pending: HashMap<String, PendingCreation> = load_json_map(&pending_path)?;
+        if pending.remove(symbol).is_some() {
+            save_json_map(&pending_path, &pending)?;
+        }
+        log::info!("PriceObject ID {} for symbol {} saved.", object_id, symbol);
+        Ok(object_id)
+    }
+
+    /// Every known PriceObject, rereading `known_objects_file` so objects imported while the
+    /// oracle runs are included.
+    pub fn known_objects(&self) -> Result<KnownObjectsMap> {
+        let mut known_objects = self.known_objects.lock().unwrap();
+        *known_objects = load_known_objects(&self.settings.known_objects_file)?;
+        Ok(known_objects.clone())
+    }
+
+    /// Batch-reads the on-chain state of `objects` from the endpoint in use.
+    pub async fn read_feed_states(&self, objects: &KnownObjectsMap) -> Result<Vec<FeedState>> {
+        if objects.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (index, client) = self.rpc.client().await?;
+        let states = fetch_feed_states(&client, objects).await;
+        if let Err(e) = &states {
+            self.rpc.record_failure(index, e);
+        }
+        states
+    }
+
+    /// The price of the last payload that landed on `object_id` in this run.
+    #[cfg(feature = "http-api")]
+    pub fn last_submitted_price(&self, object_id: ObjectID) -> Option<f64> {
+        self.submitted
+            .last(object_id)
+            .map(|payload| unscale_price(payload.scaled_price, payload.decimals))
+    }
+
+    /// Forgets the last payload that landed on `object_id`, so the next update is submitted
+    /// even if it repeats it.
+    #[cfg(feature = "http-api")]
+    pub fn forget_submitted(&self, object_id: ObjectID) {
+        self.submitted.forget(object_id);
+    }
+
+    async fn get_or_create_price_object_id(
+        &self,
+        gas: &GasReservations,
+        trace: &mut PublishTrace,
+        symbol: &str,
+    ) -> Result<ObjectID> {
+        if let Some(object_id) = self.price_object(symbol)? {
+            log::info!(
+                "Found existing ObjectID {} for symbol {}",
+                object_id,
+                symbol
+            );
+            return Ok(object_id);
+        }
+        let (rpc, client) = self.rpc.client().await?;
+        let result = self
+            .find_or_create_price_object(&client, gas, trace, symbol)
+            .await;
+        if let Err(e) = &result {
+            self.rpc.record_failure(rpc, e);
+        }
+        result
+    }
+
+    /// The PriceObject of `symbol` that is not in `known_objects_file`: recovered from an
+    /// unfinished creation, found among the publisher's objects or newly created.
+    async fn find_or_create_price_object(
+        &self,
+        sui_client: &SuiClient,
+        gas: &GasReservations,
+        trace: &mut PublishTrace,
+        symbol: &str,
+    ) -> Result<ObjectID> {
+        let sui_settings = &self.settings;
+        let (keypair, signer_address) = (&self.keypair, self.address);
+        let create = &self.calls.create;
+
+        let pending_path = pending_creations_path(&sui_settings.known_objects_file);
+        let mut pending: HashMap<String, PendingCreation> = load_json_map(&pending_path)?;
+        if let Some(intent) = pending.get(symbol) {
+            log::warn!(
+                "Found unfinished PriceObject creation for {} (tx {}). Checking whether it executed...",
+                symbol,
+                intent.tx_digest
+            );
+            if let Some(object_id) =
+                recover_pending_creation(sui_client, sui_settings, signer_address, intent).await?
+            {
+                log::warn!(
+                    "Recovered PriceObject {} for {} from pending creation",
+                    object_id,
+                    symbol
+                );
+                return self.remember_price_object(symbol, object_id);
+            }
+        }
+
+        if let Some(object_id) =
+            find_owned_price_object(sui_client, sui_settings, signer_address, symbol).await?
+        {
+            log::warn!(
+                "PriceObject {} for {} already exists on-chain but was missing from {}; reusing it",
+                object_id,
+                symbol,
+                sui_settings.known_objects_file
+            );
+            return self.remember_price_object(symbol, object_id);
+        }
+
+        log::info!(
+            "No ObjectID found for symbol {}. Creating new PriceObject...",
+            symbol
+        );
+
+        let package_id = ObjectID::from_str(&sui_settings.package_id)?;
+        let module_ident = Identifier::from_str(MODULE_NAME).context("Invalid module name")?;
+        let function_ident =
+            Identifier::from_str(&create.function).context("Invalid function name")?;
+        let args = call_args(
+            create,
+            &CallValues {
+                price_object: None,
+                symbol,
+                price: 0,
+                timestamp_ms: 0,
+                decimals: self.decimals(symbol),
+                confidence: 0,
+            },
+        )?;
+
+        let pt = {
+            let mut builder = ProgrammableTransactionBuilder::new();
+            builder
+                .move_call(
+                    package_id,
+                    module_ident.clone(),
+                    function_ident.clone(),
+                    vec![],
+                    args,
+                )
+                .context("Move call construction failed")?;
+            builder.finish()
+        };
+
+        let gas_price = counted(sui_client)
+            .governance_api()
+            .get_reference_gas_price()
+            .await
+            .unwrap_or(DEFAULT_GAS_PRICE);
+
+        let gas_object_ref =
+            select_gas_coin(sui_client, signer_address, gas, "create PriceObject").await?;
+        let budget = gas_budget(
+            sui_client,
+            sui_settings,
+            signer_address,
+            gas_object_ref,
+            &pt,
+            gas_price,
+        )
+        .await?;
+
+        let tx_data = TransactionData::new_programmable(
+            signer_address,
+            vec![gas_object_ref],
+            pt,
+            budget,
+            gas_price,
+        );
+
+        pending.insert(
+            symbol.to_string(),
+            PendingCreation {
+                tx_digest: tx_data.digest().to_string(),
+                recorded_at_ms: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+            },
+        );
+        save_json_map(&pending_path, &pending)
+            .context("Failed to record pending PriceObject creation")?;
+        trace.record_tx(
+            "create_price_object",
+            &bcs::to_bytes(&tx_data).unwrap_or_default(),
+        );
+
+        let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+        let fastcrypto_signature = SuiSdkSignature::new_secure(&intent_msg, keypair);
+
+        let transaction_envelope = Transaction::from_generic_sig_data(
+            tx_data.clone(),
+            vec![fastcrypto_signature.clone().into()],
+        );
+
+        // An RPC error here leaves the pending record in place: the transaction may still land.
+        let response = counted(sui_client)
+            .quorum_driver_api()
+            .execute_transaction_block(
+                transaction_envelope,
+                SuiTransactionBlockResponseOptions::new()
+                    .with_effects()
+                    .with_events(),
+                None,
+            )
+            .await
+            .context("Failed to execute create_price_object transaction")?;
+        trace.record_response(&response);
+
+        if response
+            .effects
+            .as_ref()
+            .is_none_or(|e| e.status() != &SuiExecutionStatus::Success)
+        {
+            // The transaction executed and failed, so it can't have created anything.
+            pending.remove(symbol);
+            save_json_map(&pending_path, &pending)?;
+            return Err(anyhow!(
+                "create_price_object transaction failed: {:?}",
+                response.effects.as_ref().map(|e| e.status())
+            ));
+        }
+
+        let effects: &SuiTransactionBlockEffects = response
+            .effects
+            .as_ref()
+            .ok_or_else(|| anyhow!("Transaction effects are missing"))?;
+
+        let new_object_id = find_created_price_object(sui_client, sui_settings, signer_address, effects)
+            .await?
+            .ok_or_else(|| {
+                let events_str = response.events.as_ref().map_or_else(
+                    || "No events".to_string(),
+                    |evts| format!("{:?}", evts.data.iter().map(|e| e.type_.to_string()).collect::<Vec<_>>()),
+                );
+                anyhow!(
+                    "Could not find created PriceObject ID in transaction effects. Effects: {:?}, Events: {}",
+                    effects, events_str
+                )
+            })?;
+
+        self.remember_price_object(symbol, new_object_id)
+    }
+}
+
+#[async_trait]
+impl PriceObjects for SuiPublisher {
+    async fn ensure_price_object(&self, symbol: &str) -> Result<String> {
+        let object_id = self
+            .get_or_create_price_object_id(
+                &GasReservations::new(),
+                &mut PublishTrace::new(),
+                symbol,
+            )
+            .await?;
+        Ok(object_id.to_string())
+    }
+}
+
+/// What the oracle supplies to a `CallTemplate`.
+struct CallValues<'a> {
+    /// Only for updates.
+    price_object: Option<ObjectRef>,
+    symbol: &'a str,
+    price: u64,
+    timestamp_ms: u64,
+    decimals: u8,
+    /// Only for updates through `sui.confidence_function`.
+    confidence: u64,
+}
+
+fn call_args(template: &CallTemplate, values: &CallValues) -> Result<Vec<CallArg>> {
+    template
+        .args
+        .iter()
+        .map(|role| {
+            Ok(match role {
+                ArgRole::PriceObject => CallArg::Object(ObjectArg::ImmOrOwnedObject(
+                    values
+                        .price_object
+                        .ok_or_else(|| anyhow!("{} needs a PriceObject", template.function))?,
+                )),
+                ArgRole::Symbol => CallArg::Pure(
+                    bcs::to_bytes(values.symbol.as_bytes()).context("BCS failed for symbol")?,
+                ),
+                ArgRole::Price => {
+                    CallArg::Pure(bcs::to_bytes(&values.price).context("BCS failed for price")?)
+                }
+                ArgRole::TimestampMs => CallArg::Pure(
+                    bcs::to_bytes(&values.timestamp_ms).context("BCS failed for timestamp_ms")?,
+                ),
+                ArgRole::Confidence => CallArg::Pure(
+                    bcs::to_bytes(&values.confidence).context("BCS failed for confidence")?,
+                ),
+                ArgRole::Decimals => CallArg::Pure(
+                    bcs::to_bytes(&values.decimals).context("BCS failed for decimals")?,
+                ),
+                ArgRole::Clock => CallArg::Object(ObjectArg::SharedObject {
+                    id: SUI_CLOCK_OBJECT_ID,
+                    initial_shared_version: SUI_CLOCK_OBJECT_SHARED_VERSION,
+                    mutable: false,
+                }),
+            })
+        })
+        .collect()
+}
+
+/// Bytecode digests of a package as deployed.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageDigests {
+    pub package_id: String,
+    pub version: u64,
+    /// SHA-256 of each module's bytecode, hex-encoded.
+    pub modules: BTreeMap<String, String>,
+    /// The digest `sui.expected_package_digest` pins; see `package_digest`.
+    pub digest: String,
+}
+
+impl PackageDigests {
+    /// Fails unless the package digest is `expected`, ignoring case and a `0x` prefix.
+    pub fn verify(&self, expected: &str) -> Result<()> {
+        let expected = expected
+            .trim()
+            .trim_start_matches("0x")
+            .to_ascii_lowercase();
+        if self.digest == expected {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Package {} (version {}) has digest {}, not the pinned {}; its bytecode changed",
+            self.package_id,
+            self.version,
+            self.digest,
+            expected
+        ))
+    }
+}
+
+/// SHA-256 over the modules in name order, each as its name and bytecode preceded by their
+/// lengths, hex-encoded. Any added, removed, renamed or changed module changes it.
+fn package_digest(modules: &BTreeMap<String, Vec<u8>>) -> String {
+    let mut hasher = Sha256::new();
+    for (name, bytecode) in modules {
+        hasher.update((name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update((bytecode.len() as u64).to_le_bytes());
+        hasher.update(bytecode);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Reads the bytecode of `sui.package_id` and digests it.
+pub async fn package_digests(sui_settings: &SuiSettings) -> Result<PackageDigests> {
+    let sui_client = connect_client(sui_settings).await?;
+    let package_id = ObjectID::from_str(&sui_settings.package_id)?;
+    let response = counted(&sui_client)
+        .read_api()
+        .get_object_with_options(package_id, SuiObjectDataOptions::new().with_bcs())
+        .await
+        .context(format!("Failed to read package {}", package_id))?;
+    let data = response
+        .data
+        .ok_or_else(|| anyhow!("Package {} not found", package_id))?;
+    let Some(SuiRawData::Package(package)) = data.bcs else {
+        return Err(anyhow!("Object {} is not a Move package", package_id));
+    };
+    Ok(PackageDigests {
+        package_id: package_id.to_string(),
+        version: package.version.value(),
+        modules: package
+            .module_map
+            .iter()
+            .map(|(name, bytecode)| (name.clone(), hex::encode(Sha256::digest(bytecode))))
+            .collect(),
+        digest: package_digest(&package.module_map),
+    })
+}
+
+/// Reads the package's `price_oracle` module and derives how to call each function the
+/// oracle uses. Fails if a signature cannot be adapted to.
+async fn module_calls(sui_client: &SuiClient, sui_settings: &SuiSettings) -> Result<ModuleCalls> {
+    let package_id = ObjectID::from_str(&sui_settings.package_id)?;
+    let modules = counted(sui_client)
+        .read_api()
+        .get_normalized_move_modules_by_package(package_id)
+        .await
+        .context(format!(
+            "Failed to read the modules of package {}",
+            package_id
+        ))?;
+    let module = modules.get(MODULE_NAME).ok_or_else(|| {
+        anyhow!(
+            "Package {} has no {} module; it has: {}",
+            package_id,
+            MODULE_NAME,
+            modules.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    move_schema::resolve_calls(
+        module,
+        CREATE_PRICE_OBJECT_FUNC_NAME,
+        UPDATE_PRICE_FUNC_NAME,
+        &sui_settings.manual_update_function,
+        sui_settings.stale_function.as_deref(),
+        sui_settings.confidence_function.as_deref(),
+    )
+    .context(format!(
+        "Package {} does not match what the oracle calls",
+        package_id
+    ))
+}
+
+/// An argument of an operator-initiated Move call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveArg {
+    /// Passed by reference: shared objects mutably, anything else as an owned object.
+    Object(ObjectID),
+    Address(SuiAddress),
+    U8(u8),
+    U64(u64),
+    /// A Move `std::string::String`.
+    String(String),
+}
+
+async fn resolve_move_arg(sui_client: &SuiClient, arg: &MoveArg) -> Result<CallArg> {
+    let object_id = match arg {
+        MoveArg::Address(address) => return Ok(CallArg::Pure(bcs::to_bytes(address)?)),
+        MoveArg::U8(value) => return Ok(CallArg::Pure(bcs::to_bytes(value)?)),
+        MoveArg::U64(value) => return Ok(CallArg::Pure(bcs::to_bytes(value)?)),
+        MoveArg::String(value) => return Ok(CallArg::Pure(bcs::to_bytes(value)?)),
+        MoveArg::Object(object_id) => *object_id,
+    };
+    let data = counted(sui_client)
+        .read_api()
+        .get_object_with_options(object_id, SuiObjectDataOptions::new().with_owner())
+        .await
+        .context(format!("Failed to fetch object {}", object_id))?
+        .data
+        .ok_or_else(|| anyhow!("Object {} not found", object_id))?;
+    Ok(match data.owner {
+        Some(Owner::Shared {
+            initial_shared_version,
+        }) => CallArg::Object(ObjectArg::SharedObject {
+            id: object_id,
+            initial_shared_version,
+            mutable: true,
+        }),
+        _ => CallArg::Object(ObjectArg::ImmOrOwnedObject(data.object_ref())),
+    })
+}
+
+/// Signs and executes a single `package::module::function(args)` call from the publisher
+/// address. Returns the transaction digest.
+pub async fn execute_move_call(
+    sui_settings: &SuiSettings,
+    module: &str,
+    function: &str,
+    args: &[MoveArg],
+) -> Result<String> {
+    let keypair =
+        keystore::publisher_keypair(sui_settings).context("Failed to get publisher keypair")?;
+    let signer_address = SuiAddress::from(&keypair.public());
+    let sui_client = connect_client(sui_settings).await?;
 
-async fn get_or_create_price_object_id(
-    sui_client: &SuiClient,
-    signer_address: SuiAddress,
-    keypair: &SuiKeyPair,
-    symbol: &str,
-) -> Result<ObjectID> {
-    let mut known_objects = load_known_objects()?;
-    if let Some(object_id) = known_objects.get(symbol) {
-        log::info!(
-            "Found existing ObjectID {} for symbol {}",
-            object_id,
-            symbol
-        );
-        return Ok(*object_id);
+    let mut call_args = Vec::with_capacity(args.len());
+    for arg in args {
+        call_args.push(resolve_move_arg(&sui_client, arg).await?);
     }
-
-    log::info!(
-        "No ObjectID found for symbol {}. Creating new PriceObject...",
-        symbol
-    );
-
-    let package_id = ObjectID::from_str(PACKAGE_ID_STR)?;
-    let module_ident = Identifier::from_str(MODULE_NAME).context("Invalid module name")?;
-    let function_ident =
-        Identifier::from_str(CREATE_PRICE_OBJECT_FUNC_NAME).context("Invalid function name")?;
-
+    let package_id = ObjectID::from_str(&sui_settings.package_id)?;
     let pt = {
         let mut builder = ProgrammableTransactionBuilder::new();
-        let symbol_bytes = symbol.as_bytes().to_vec();
         builder
             .move_call(
                 package_id,
-                module_ident.clone(),
-                function_ident.clone(),
+                Identifier::from_str(module).context("Invalid module name")?,
+                Identifier::from_str(function).context("Invalid function name")?,
                 vec![],
-                vec![
-                    CallArg::Pure(
-                        bcs::to_bytes(&symbol_bytes).context("BCS failed for symbol_bytes")?,
-                    ),
-                    CallArg::Pure(bcs::to_bytes(&0u64).context("BCS failed for initial_price")?),
-                    CallArg::Pure(
-                        bcs::to_bytes(&0u64).context("BCS failed for initial_timestamp_ms")?,
-                    ),
-                    CallArg::Pure(bcs::to_bytes(&DECIMALS).context("BCS failed for DECIMALS")?),
-                ],
+                call_args,
             )
             .context("Move call construction failed")?;
         builder.finish()
     };
 
-    let gas_price = sui_client
+    let gas_price = counted(&sui_client)
         .governance_api()
         .get_reference_gas_price()
         .await
         .unwrap_or(DEFAULT_GAS_PRICE);
-
-    let gas_coins_response = sui_client
-        .coin_read_api()
-        .get_coins(signer_address, None, None, Some(1))
-        .await
-        .context("Failed to fetch gas coins for create_price_object")?;
-
-    let gas_object_ref = gas_coins_response
-        .data
-        .get(0)
-        .ok_or_else(|| {
-            anyhow!(
-                "No gas coins found for address {} to create object",
-                signer_address
-            )
-        })?
-        .object_ref();
-
+    let gas_object_ref = select_gas_coin(
+        &sui_client,
+        signer_address,
+        &GasReservations::new(),
+        function,
+    )
+    .await?;
+    let budget = gas_budget(
+        &sui_client,
+        sui_settings,
+        signer_address,
+        gas_object_ref,
+        &pt,
+        gas_price,
+    )
+    .await?;
     let tx_data = TransactionData::new_programmable(
         signer_address,
         vec![gas_object_ref],
         pt,
-        GAS_BUDGET,
+        budget,
         gas_price,
     );
-
     let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
-    let fastcrypto_signature = SuiSdkSignature::new_secure(&intent_msg, keypair);
-
-    let transaction_envelope = Transaction::from_generic_sig_data(
-        tx_data.clone(),
-        vec![fastcrypto_signature.clone().into()],
-    );
+    let signature = SuiSdkSignature::new_secure(&intent_msg, &keypair);
+    let transaction = Transaction::from_generic_sig_data(tx_data, vec![signature.into()]);
 
-    let response = sui_client
+    let response = counted(&sui_client)
         .quorum_driver_api()
         .execute_transaction_block(
-            transaction_envelope,
-            SuiTransactionBlockResponseOptions::new()
-                .with_effects()
-                .with_events(),
+            transaction,
+            SuiTransactionBlockResponseOptions::new().with_effects(),
             None,
         )
         .await
-        .context("Failed to execute create_price_object transaction")?;
-
+        .context(format!("Failed to execute {}::{}", module, function))?;
     if response
         .effects
         .as_ref()
-        .map_or(true, |e| e.status() != &SuiExecutionStatus::Success)
+        .is_none_or(|e| e.status() != &SuiExecutionStatus::Success)
     {
         return Err(anyhow!(
-            "create_price_object transaction failed: {:?}",
-            response.effects.as_ref().map(|e| e.status())
+            "{}::{} failed: {:?}. Digest: {}",
+            module,
+            function,
+            response.effects.as_ref().map(|e| e.status()),
+            response.digest
         ));
     }
+    Ok(response.digest.to_string())
+}
 
-    let effects: &sui_sdk::rpc_types::SuiTransactionBlockEffects = response
-        .effects
-        .as_ref()
-        .ok_or_else(|| anyhow!("Transaction effects are missing"))?;
-    let mut new_object_id: Option<ObjectID> = None;
-    let price_object_type_tag_str_pattern =
-        format!("{}::{}::PriceObject", PACKAGE_ID_STR, MODULE_NAME);
+/// What an update would write to its PriceObject, as far as deduplication is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Payload {
+    manual_override: bool,
+    scaled_price: u64,
+    /// Scaled like the price.
+    scaled_confidence: Option<u64>,
+    decimals: u8,
+    timestamp_bucket: u64,
+}
 
-    for created_obj_ref in effects.created() {
-        let owner_address = match created_obj_ref.owner {
-            Owner::AddressOwner(addr) => Some(addr),
-            _ => None, // Other owner types are not relevant here
-        };
+impl Payload {
+    fn new(
+        price_info: &PriceInfo,
+        bucket_ms: u64,
+        decimals: u8,
+        rounding: PriceRounding,
+    ) -> Result<Self> {
+        let scaled_confidence = price_info
+            .confidence
+            .map(|confidence| {
+                let confidence = Decimal::from_f64(confidence)
+                    .ok_or_else(|| anyhow!("confidence {} has no decimal value", confidence))?;
+                scale_price(confidence, decimals, rounding)
+            })
+            .transpose()
+            .context(format!("Failed to scale {} confidence", price_info.symbol))?;
+        Ok(Self {
+            manual_override: price_info.manual_override,
+            scaled_price: scale_price(price_info.price, decimals, rounding)
+                .context(format!("Failed to scale {} price", price_info.symbol))?,
+            scaled_confidence,
+            decimals,
+            timestamp_bucket: price_info.timestamp_ms / bucket_ms.max(1),
+        })
+    }
+}
 
-        if owner_address == Some(signer_address) {
-            let object_id_to_check = created_obj_ref.reference.object_id;
-            let obj_response = sui_client
-                .read_api()
-                .get_object_with_options(
-                    object_id_to_check,
-                    SuiObjectDataOptions::new().with_type(),
-                )
-                .await
-                .context(format!(
-                    "Failed to fetch created object {} to verify type",
-                    object_id_to_check
-                ))?;
+/// The last payload that landed on each PriceObject, so that an identical one, e.g. from a
+/// value rounding to the same scaled price, is skipped before any gas is spent.
+#[derive(Debug, Default)]
+pub struct SubmittedPayloads {
+    last: Mutex<HashMap<ObjectID, Payload>>,
+}
 
-            if let Some(obj_data) = obj_response.data {
-                if let Some(obj_type) = obj_data.type_ {
-                    if obj_type
-                        .to_string()
-                        .contains(&price_object_type_tag_str_pattern)
-                    {
-                        new_object_id = Some(object_id_to_check);
-                        log::info!(
-                            "Found created PriceObject with ID: {}",
-                            new_object_id.unwrap()
-                        );
-                        break;
-                    }
-                }
-            }
-        }
+impl SubmittedPayloads {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    let new_object_id = new_object_id.ok_or_else(|| {
-        let events_str = response.events.map_or_else(
-            || "No events".to_string(),
-            |evts| format!("{:?}", evts.data.iter().map(|e| e.type_.to_string()).collect::<Vec<_>>()),
-        );
-        anyhow!(
-            "Could not find created PriceObject ID in transaction effects. Effects: {:?}, Events: {}",
-            effects, events_str
-        )
-    })?;
+    fn is_duplicate(&self, object_id: ObjectID, payload: Payload) -> bool {
+        self.last.lock().unwrap().get(&object_id) == Some(&payload)
+    }
 
-    known_objects.insert(symbol.to_string(), new_object_id);
-    save_known_objects(&known_objects)?;
-    log::info!(
-        "New PriceObject ID {} for symbol {} saved.",
-        new_object_id,
-        symbol
-    );
+    #[cfg(feature = "http-api")]
+    fn last(&self, object_id: ObjectID) -> Option<Payload> {
+        self.last.lock().unwrap().get(&object_id).copied()
+    }
+
+    fn record(&self, object_id: ObjectID, payload: Payload) {
+        self.last.lock().unwrap().insert(object_id, payload);
+    }
 
-    Ok(new_object_id)
+    fn forget(&self, object_id: ObjectID) {
+        self.last.lock().unwrap().remove(&object_id);
+    }
 }
 
-pub async fn submit_price_update(price_info: PriceInfo) -> Result<String> {
-    log::info!("Attempting to submit price update for: {:?}", price_info);
+/// Confidence and stale marking depend on the functions found in the package.
+#[async_trait]
+impl ChainBackend for SuiPublisher {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            chain: ChainKind::Sui,
+            max_batch_size: self.settings.max_batch_size.max(1),
+            confidence: self.calls.update_with_confidence.is_some(),
+            stale_marking: self.calls.mark_stale.is_some(),
+        }
+    }
 
-    let keypair = get_publisher_keypair().context("Failed to get publisher keypair")?;
+    /// Decimals `symbol` is published at.
+    fn decimals(&self, symbol: &str) -> u8 {
+        self.decimals.get(symbol).copied().unwrap_or(DECIMALS)
+    }
 
-    let public_key = keypair.public();
-    let signer_address = SuiAddress::from(&public_key);
+    /// How prices are rounded for publishing.
+    fn price_rounding(&self) -> PriceRounding {
+        self.settings.price_rounding
+    }
 
-    let expected_signer_address = SuiAddress::from_str(PUBLISHER_ADDRESS_STR)?;
-    if signer_address != expected_signer_address {
-        return Err(anyhow!(
-            "Derived signer address {} does not match expected address {}",
-            signer_address,
-            expected_signer_address
-        ));
+    /// The PriceObject of `symbol`, if it has one.
+    fn known_object(&self, symbol: &str) -> Result<Option<String>> {
+        Ok(self.price_object(symbol)?.map(|id| id.to_string()))
     }
-    log::info!("Signer address: {}", signer_address);
 
-    let sui_client = SuiClientBuilder::default()
-        .request_timeout(Duration::from_secs(30))
-        .build(SUI_TESTNET_RPC_URL)
-        .await
-        .context(format!(
-            "Failed to build Sui client for URL: {}",
-            SUI_TESTNET_RPC_URL
-        ))?;
+    /// Releases the gas coins reserved in the last round.
+    fn begin_round(&self) {
+        self.gas.clear();
+    }
+
+    /// Publishes price updates with one Move call each, packed into one programmable
+    /// transaction block per `sui.max_batch_size` updates, so gas is selected and paid once
+    /// per block. An update repeating the last payload submitted to its PriceObject is left
+    /// out. A block succeeds or fails as a whole; its updates share its digest and split its
+    /// gas evenly. Each transaction of a round pays from a different coin; `trace` collects
+    /// what was sent and received. Results are in the order of `updates`.
+    async fn publish(
+        &self,
+        updates: Vec<PriceInfo>,
+        rng: &mut CycleRng,
+        trace: &mut PublishTrace,
+    ) -> Vec<Result<Submission>> {
+        let mut results = Vec::with_capacity(updates.len());
+        for chunk in updates.chunks(self.capabilities().max_batch_size) {
+            results.extend(self.publish_block(chunk, &self.gas, rng, trace).await);
+        }
+        results
+    }
+
+    /// Flags the PriceObject of `symbol` stale through `sui.stale_function`, in a
+    /// transaction of its own. Not retried: a failed marker is attempted again next cycle.
+    /// The next update is submitted even if it repeats the last payload, so that it clears
+    /// the flag. `None` if the feed has no PriceObject yet, so there is nothing to flag.
+    async fn mark_stale(
+        &self,
+        symbol: &str,
+        trace: &mut PublishTrace,
+    ) -> Result<Option<PublishReceipt>> {
+        let template = self
+            .calls
+            .mark_stale
+            .as_ref()
+            .ok_or_else(|| anyhow!("sui.stale_function is not set"))?;
+        let Some(object_id) = self.price_object(symbol)? else {
+            return Ok(None);
+        };
+        let (rpc, client) = self.rpc.client().await?;
+        let result = async {
+            let object_ref = counted(&client)
+                .read_api()
+                .get_object_with_options(object_id, SuiObjectDataOptions::new())
+                .await
+                .context(format!("Failed to fetch PriceObject {}", object_id))?
+                .data
+                .ok_or_else(|| anyhow!("PriceObject {} not found", object_id))?
+                .object_ref();
+            let args = call_args(
+                template,
+                &CallValues {
+                    price_object: Some(object_ref),
+                    symbol,
+                    price: 0,
+                    timestamp_ms: 0,
+                    decimals: self.decimals(symbol),
+                    confidence: 0,
+                },
+            )?;
+            let mut builder = ProgrammableTransactionBuilder::new();
+            builder
+                .move_call(
+                    ObjectID::from_str(&self.settings.package_id)?,
+                    Identifier::from_str(MODULE_NAME).context("Invalid module name")?,
+                    Identifier::from_str(&template.function)
+                        .context("Invalid function name for stale marker")?,
+                    vec![],
+                    args,
+                )
+                .context("Move call construction failed for stale marker")?;
+            self.execute(
+                &client,
+                builder.finish(),
+                &template.function,
+                &self.gas,
+                trace,
+            )
+            .await
+        }
+        .await;
+        match &result {
+            Ok(_) => self.submitted.forget(object_id),
+            Err(e) => self.rpc.record_failure(rpc, e),
+        }
+        result.map(Some)
+    }
+
+    /// The publisher's total SUI balance in MIST, read from the endpoint in use.
+    async fn gas_balance(&self) -> Result<u128> {
+        let (index, client) = self.rpc.client().await?;
+        let balance = counted(&client)
+            .coin_read_api()
+            .get_balance(self.address, None)
+            .await
+            .map(|b| b.total_balance)
+            .context(format!("Failed to read balance of {}", self.address));
+        if let Err(e) = &balance {
+            self.rpc.record_failure(index, e);
+        }
+        balance
+    }
+
+    /// Exports the health of the Sui RPC endpoints.
+    fn export_metrics(&self, metrics: &Metrics) {
+        self.rpc
+            .health
+            .lock()
+            .unwrap()
+            .export_metrics(metrics, std::time::Instant::now());
+    }
+}
+
+impl SuiPublisher {
+    async fn publish_block(
+        &self,
+        updates: &[PriceInfo],
+        gas: &GasReservations,
+        rng: &mut CycleRng,
+        trace: &mut PublishTrace,
+    ) -> Vec<Result<Submission>> {
+        let mut results: Vec<Option<Result<Submission>>> = Vec::with_capacity(updates.len());
+        // Position in `updates`, object and payload of each update going into the block.
+        let mut batched = Vec::new();
+        for (i, price_info) in updates.iter().enumerate() {
+            match self.prepare(price_info, gas, trace).await {
+                Ok(Some((object_id, payload))) => {
+                    batched.push((i, object_id, payload));
+                    results.push(None);
+                }
+                Ok(None) => results.push(Some(Ok(Submission::Duplicate))),
+                Err(e) => results.push(Some(Err(e))),
+            }
+        }
+        if !batched.is_empty() {
+            let outcome = self.execute_block(updates, &batched, gas, rng, trace).await;
+            for (i, object_id, payload) in &batched {
+                results[*i] = Some(match &outcome {
+                    Ok(receipt) => {
+                        self.submitted.record(*object_id, *payload);
+                        Ok(Submission::Published(PublishReceipt {
+                            digest: receipt.digest.clone(),
+                            gas_used_mist: receipt.gas_used_mist / batched.len() as u64,
+                        }))
+                    }
+                    Err(e) => match e.downcast_ref::<Expired>() {
+                        Some(expired) => Ok(Submission::Expired {
+                            age_ms: expired.age_ms,
+                        }),
+                        None => Err(anyhow!("{:#}", e)),
+                    },
+                });
+            }
+        }
+        results.into_iter().flatten().collect()
+    }
 
-    log::info!("Sui client connected to: {}", SUI_TESTNET_RPC_URL);
+    /// Executes the block of `batched` updates, retrying failed attempts per `sui.retry`.
+    /// Every attempt goes to the RPC endpoint in use, so one that timed out or errored is
+    /// retried on a fallback.
+    async fn execute_block(
+        &self,
+        updates: &[PriceInfo],
+        batched: &[(usize, ObjectID, Payload)],
+        gas: &GasReservations,
+        rng: &mut CycleRng,
+        trace: &mut PublishTrace,
+    ) -> Result<PublishReceipt> {
+        let block: Vec<BlockUpdate> = batched
+            .iter()
+            .map(|(i, object_id, payload)| BlockUpdate {
+                price_info: &updates[*i],
+                object_id: *object_id,
+                payload: *payload,
+            })
+            .collect();
+        let rpc = PooledRpc { publisher: self };
+        let policy = &self.settings.retry;
+        submit_block(&rpc, self.address, policy, &block, gas, rng, trace).await
+    }
 
-    let price_object_id =
-        get_or_create_price_object_id(&sui_client, signer_address, &keypair, &price_info.symbol)
+    /// The PriceObject `price_info` updates, created if needed, and its payload; `None` when
+    /// the payload repeats the last one submitted to the object.
+    async fn prepare(
+        &self,
+        price_info: &PriceInfo,
+        gas: &GasReservations,
+        trace: &mut PublishTrace,
+    ) -> Result<Option<(ObjectID, Payload)>> {
+        let price_object_id = self
+            .get_or_create_price_object_id(gas, trace, &price_info.symbol)
             .await
             .context(format!(
                 "Failed to get or create PriceObject ID for symbol {}",
                 price_info.symbol
             ))?;
-
-    log::info!(
-        "Using PriceObject ID {} for symbol {}",
-        price_object_id,
-        price_info.symbol
-    );
-
-    let object_to_update_response = sui_client
-        .read_api()
-        .get_object_with_options(
+        log::info!(
+            "Using PriceObject ID {} for symbol {}",
             price_object_id,
-            SuiObjectDataOptions::new()
-                .with_owner()
-                .with_previous_transaction(),
-        )
-        .await
-        .context(format!(
-            "Failed to fetch PriceObject {} for update",
-            price_object_id
-        ))?;
-
-    let object_data = object_to_update_response
-        .data
-        .ok_or_else(|| anyhow!("PriceObject {} data not found for update", price_object_id))?;
-    let object_to_update_ref = object_data.object_ref();
+            price_info.symbol
+        );
 
-    let scaled_price_val = scale_price(price_info.price);
-    log::info!(
-        "Scaled price for {}: {} (original: {}, decimals: {})",
-        price_info.symbol,
-        scaled_price_val,
-        price_info.price,
-        DECIMALS
-    );
+        let mut payload = Payload::new(
+            price_info,
+            self.settings.dedup_timestamp_bucket_ms,
+            self.decimals(&price_info.symbol),
+            self.settings.price_rounding,
+        )?;
+        // Only written, and so only told apart, through `sui.confidence_function`.
+        if !self.capabilities().confidence {
+            payload.scaled_confidence = None;
+        }
+        if self.submitted.is_duplicate(price_object_id, payload) {
+            log::info!(
+                "Skipping {} update: same payload as the last one submitted to {}",
+                price_info.symbol,
+                price_object_id
+            );
+            return Ok(None);
+        }
+        Ok(Some((price_object_id, payload)))
+    }
 
-    let package_id = ObjectID::from_str(PACKAGE_ID_STR)?;
-    let module_ident =
-        Identifier::from_str(MODULE_NAME).context("Invalid module name for update")?;
-    let function_ident =
-        Identifier::from_str(UPDATE_PRICE_FUNC_NAME).context("Invalid function name for update")?;
+    /// Adds the Move call writing `payload` to the PriceObject at `object_ref`. Returns the
+    /// function called.
+    fn add_update(
+        &self,
+        builder: &mut ProgrammableTransactionBuilder,
+        price_info: &PriceInfo,
+        object_ref: ObjectRef,
+        payload: Payload,
+    ) -> Result<&str> {
+        let (sui_settings, calls) = (&self.settings, &self.calls);
+        log::info!(
+            "Scaled price for {}: {} (original: {}, decimals: {})",
+            price_info.symbol,
+            payload.scaled_price,
+            price_info.price,
+            payload.decimals
+        );
 
-    let pt = {
-        let mut builder = ProgrammableTransactionBuilder::new();
+        let package_id = ObjectID::from_str(&sui_settings.package_id)?;
+        let module_ident =
+            Identifier::from_str(MODULE_NAME).context("Invalid module name for update")?;
+        let update = if price_info.manual_override {
+            calls.manual_update.as_ref().ok_or_else(|| {
+                anyhow!(
+                    "Package {} has no {} function for manual overrides",
+                    sui_settings.package_id,
+                    sui_settings.manual_update_function
+                )
+            })?
+        } else if let (Some(with_confidence), Some(_)) =
+            (&calls.update_with_confidence, payload.scaled_confidence)
+        {
+            with_confidence
+        } else {
+            &calls.update
+        };
+        let function_ident =
+            Identifier::from_str(&update.function).context("Invalid function name for update")?;
+        let args = call_args(
+            update,
+            &CallValues {
+                price_object: Some(object_ref),
+                symbol: &price_info.symbol,
+                price: payload.scaled_price,
+                timestamp_ms: price_info.timestamp_ms,
+                decimals: payload.decimals,
+                confidence: payload.scaled_confidence.unwrap_or(0),
+            },
+        )?;
         builder
-            .move_call(
-                package_id,
-                module_ident.clone(),
-                function_ident.clone(),
-                vec![],
-                vec![
-                    CallArg::Object(ObjectArg::ImmOrOwnedObject(object_to_update_ref)),
-                    CallArg::Pure(
-                        bcs::to_bytes(&scaled_price_val)
-                            .context("BCS failed for scaled_price_val")?,
-                    ),
-                    CallArg::Pure(
-                        bcs::to_bytes(&price_info.timestamp_ms)
-                            .context("BCS failed for timestamp_ms")?,
-                    ),
-                ],
-            )
+            .move_call(package_id, module_ident, function_ident, vec![], args)
             .context("Move call construction failed for update")?;
-        builder.finish()
-    };
+        Ok(&update.function)
+    }
 
-    let gas_price = sui_client
-        .governance_api()
-        .get_reference_gas_price()
-        .await
-        .unwrap_or(DEFAULT_GAS_PRICE);
+    /// Signs and executes `pt` through `sui_client`, paying from a coin reserved in `gas`. `label` names the
+    /// transaction in errors and the trace.
+    async fn execute(
+        &self,
+        sui_client: &SuiClient,
+        pt: ProgrammableTransaction,
+        label: &str,
+        gas: &GasReservations,
+        trace: &mut PublishTrace,
+    ) -> Result<PublishReceipt> {
+        // A coin of each attempt, so a retry never reuses one a timed-out attempt may spend.
+        let gas_object_ref = select_gas_coin(sui_client, self.address, gas, label).await?;
+        let executed = self
+            .execute_paid(sui_client, pt, label, gas_object_ref, trace)
+            .await?;
+        receipt(label, executed)
+    }
 
-    let gas_coins_response = sui_client
-        .coin_read_api()
-        .get_coins(signer_address, None, None, Some(1))
-        .await
-        .context("Failed to fetch gas coins for update_price")?;
+    /// Signs and executes `pt` through `sui_client`, paying from `gas_object_ref`.
+    async fn execute_paid(
+        &self,
+        sui_client: &SuiClient,
+        pt: ProgrammableTransaction,
+        label: &str,
+        gas_object_ref: ObjectRef,
+        trace: &mut PublishTrace,
+    ) -> Result<Executed> {
+        let gas_price = counted(sui_client)
+            .governance_api()
+            .get_reference_gas_price()
+            .await
+            .unwrap_or(DEFAULT_GAS_PRICE);
+        let budget = gas_budget(
+            sui_client,
+            &self.settings,
+            self.address,
+            gas_object_ref,
+            &pt,
+            gas_price,
+        )
+        .await?;
 
-    let gas_object_ref = gas_coins_response
-        .data
-        .get(0)
-        .ok_or_else(|| {
-            anyhow!(
-                "No gas coins found for address {} to update price",
-                signer_address
+        let tx_data = TransactionData::new_programmable(
+            self.address,
+            vec![gas_object_ref],
+            pt,
+            budget,
+            gas_price,
+        );
+        trace.record_tx(label, &bcs::to_bytes(&tx_data).unwrap_or_default());
+        let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+        let fastcrypto_signature = SuiSdkSignature::new_secure(&intent_msg, &self.keypair);
+        let transaction_envelope =
+            Transaction::from_generic_sig_data(tx_data, vec![fastcrypto_signature.into()]);
+
+        let response = counted(sui_client)
+            .quorum_driver_api()
+            .execute_transaction_block(
+                transaction_envelope,
+                SuiTransactionBlockResponseOptions::new().with_effects(),
+                None,
             )
-        })?
-        .object_ref();
+            .await
+            .context(format!("Failed to execute {} transaction", label))?;
+        trace.record_response(&response);
+        Ok(Executed {
+            digest: response.digest.to_string(),
+            status: response.effects.as_ref().map(|e| e.status().clone()),
+            gas_used_mist: response
+                .effects
+                .as_ref()
+                .map(|e| e.gas_cost_summary().net_gas_usage().max(0) as u64)
+                .unwrap_or_default(),
+        })
+    }
+}
 
-    let tx_data = TransactionData::new_programmable(
-        signer_address,
-        vec![gas_object_ref],
-        pt,
-        GAS_BUDGET,
-        gas_price,
-    );
+#[cfg(test)]
+mod mock_rpc;
 
-    let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
-    let fastcrypto_signature = SuiSdkSignature::new_secure(&intent_msg, &keypair);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let transaction_envelope =
-        Transaction::from_generic_sig_data(tx_data, vec![fastcrypto_signature.clone().into()]);
+    #[test]
+    fn test_parse_price_object_fields() {
+        let fields = serde_json::json!({
+            "id": { "id": "0x1" },
+            "symbol": [66, 84, 67],
+            "price": "68000100000",
+            "timestamp_ms": "1700000000000",
+            "decimals": 6
+        });
+        assert_eq!(
+            parse_price_object_fields(&fields),
+            Some((68_000_100_000, 1_700_000_000_000, 6))
+        );
+        assert!((unscale_price(68_000_100_000, 6) - 68000.1).abs() < 1e-9);
+    }
 
-    log::info!(
-        "Submitting update_price transaction for symbol {}...",
-        price_info.symbol
-    );
-    let response = sui_client
-        .quorum_driver_api()
-        .execute_transaction_block(
-            transaction_envelope,
-            SuiTransactionBlockResponseOptions::new().with_effects(),
-            None,
-        )
-        .await
-        .context("Failed to execute update_price transaction")?;
+    #[test]
+    fn test_pending_creations_path() {
+        assert_eq!(
+            pending_creations_path("known_price_objects.json"),
+            "known_price_objects.pending.json"
+        );
+        assert_eq!(pending_creations_path("objects"), "objects.pending");
+    }
 
-    if response
-        .effects
-        .as_ref()
-        .map_or(true, |e| e.status() != &SuiExecutionStatus::Success)
-    {
-        return Err(anyhow!(
-            "update_price transaction failed: {:?}. Digest: {}",
-            response.effects.as_ref().map(|e| e.status()),
-            response.digest
+    #[test]
+    fn test_known_objects_schema() -> Result<()> {
+        let path = "./test_known_objects_schema.json";
+        let id = ObjectID::from_single_byte(7);
+        std::fs::write(path, format!("{{\"BTC/USD\": \"{}\"}}", id))?;
+        assert!(is_legacy_known_objects(path)?);
+        let objects = load_known_objects(path)?;
+        assert_eq!(objects.get("BTC/USD"), Some(&id));
+
+        save_known_objects(path, &objects)?;
+        assert!(!is_legacy_known_objects(path)?);
+        assert_eq!(load_known_objects(path)?, objects);
+        std::fs::remove_file(path)?;
+        assert!(!is_legacy_known_objects(path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_symbol_field() {
+        let bytes = serde_json::json!([66, 84, 67, 47, 85, 83, 68]);
+        assert_eq!(parse_symbol_field(&bytes).as_deref(), Some("BTC/USD"));
+        let text = serde_json::json!("ETH/USD");
+        assert_eq!(parse_symbol_field(&text).as_deref(), Some("ETH/USD"));
+        assert_eq!(parse_symbol_field(&serde_json::json!([256])), None);
+    }
+
+    #[test]
+    fn test_pending_creations_round_trip() -> Result<()> {
+        let path = "./test_pending_creations.pending.json";
+        let mut pending = HashMap::new();
+        pending.insert(
+            "BTC/USD".to_string(),
+            PendingCreation {
+                tx_digest: "5Ht6".to_string(),
+                recorded_at_ms: 1,
+            },
+        );
+        save_json_map(path, &pending)?;
+        let loaded: HashMap<String, PendingCreation> = load_json_map(path)?;
+        std::fs::remove_file(path)?;
+        assert_eq!(loaded, pending);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gas_reservations_hand_out_distinct_coins() {
+        let gas = GasReservations::new();
+        let poor = ObjectID::from_single_byte(1);
+        let a = ObjectID::from_single_byte(2);
+        let b = ObjectID::from_single_byte(3);
+        let coins = [(poor, GAS_BUDGET - 1), (a, GAS_BUDGET), (b, GAS_BUDGET * 2)];
+
+        assert_eq!(gas.reserve(coins), Some(a));
+        assert_eq!(gas.reserve(coins), Some(b));
+        assert_eq!(gas.reserve(coins), None);
+        // A new round starts with every coin available again.
+        gas.clear();
+        assert_eq!(gas.reserve(coins), Some(a));
+    }
+
+    #[test]
+    fn test_identical_payloads_are_duplicates() {
+        let submitted = SubmittedPayloads::new();
+        let object_id = ObjectID::from_single_byte(1);
+        let price = |price: &str, timestamp_ms| PriceInfo {
+            symbol: "BTC/USD".to_string(),
+            price: price.parse().unwrap(),
+            timestamp_ms,
+            manual_override: false,
+            confidence: None,
+        };
+        let payload = |price_info: &PriceInfo, bucket_ms| {
+            Payload::new(price_info, bucket_ms, DECIMALS, Default::default()).unwrap()
+        };
+        submitted.record(object_id, payload(&price("100", 10_000), 0));
+        assert!(submitted.is_duplicate(object_id, payload(&price("100", 10_000), 0)));
+        // Rounds to the same scaled price.
+        assert!(submitted.is_duplicate(object_id, payload(&price("100.0000001", 10_000), 0)));
+        let finer = Payload::new(&price("100.0000001", 10_000), 0, 9, Default::default()).unwrap();
+        assert!(!submitted.is_duplicate(object_id, finer));
+        assert!(!submitted.is_duplicate(object_id, payload(&price("100", 10_001), 0)));
+        assert!(!submitted.is_duplicate(
+            ObjectID::from_single_byte(2),
+            payload(&price("100", 10_000), 0)
         ));
+
+        submitted.record(object_id, payload(&price("100", 10_000), 5_000));
+        assert!(submitted.is_duplicate(object_id, payload(&price("100", 14_999), 5_000)));
+        assert!(!submitted.is_duplicate(object_id, payload(&price("100", 15_000), 5_000)));
+        let mut manual = price("100", 10_000);
+        manual.manual_override = true;
+        assert!(!submitted.is_duplicate(object_id, payload(&manual, 5_000)));
+        let confident = PriceInfo {
+            confidence: Some(0.25),
+            ..price("100", 10_000)
+        };
+        assert_eq!(payload(&confident, 5_000).scaled_confidence, Some(250_000));
+        assert!(!submitted.is_duplicate(object_id, payload(&confident, 5_000)));
     }
 
-    log::info!(
-        "Successfully submitted price update for {}. Transaction Digest: {}",
-        price_info.symbol,
-        response.digest
-    );
+    #[test]
+    fn test_parse_price_object_fields_rejects_missing_fields() {
+        let fields = serde_json::json!({ "price": "1", "decimals": 6 });
+        assert_eq!(parse_price_object_fields(&fields), None);
+        let fields = serde_json::json!({ "price": "1", "timestamp_ms": "2", "decimals": 300 });
+        assert_eq!(parse_price_object_fields(&fields), None);
+    }
 
-    Ok(response.digest.to_string())
-}
+    #[test]
+    fn test_package_digest_covers_every_module() {
+        let modules = BTreeMap::from([
+            ("price_oracle".to_string(), vec![1, 2, 3]),
+            ("fees".to_string(), vec![4]),
+        ]);
+        let digests = PackageDigests {
+            package_id: "0x42".to_string(),
+            version: 1,
+            modules: BTreeMap::new(),
+            digest: package_digest(&modules),
+        };
+        assert!(digests.verify(&digests.digest.to_uppercase()).is_ok());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut changed = modules.clone();
+        changed.get_mut("fees").unwrap().push(5);
+        assert!(digests.verify(&package_digest(&changed)).is_err());
+        // Moving a byte between modules is a change too.
+        let moved = BTreeMap::from([
+            ("price_oracle".to_string(), vec![1, 2]),
+            ("fees".to_string(), vec![3, 4]),
+        ]);
+        assert_ne!(package_digest(&moved), digests.digest);
+    }
+
+    #[test]
+    fn test_inverse_price() {
+        let half_up = PriceRounding::HalfUp;
+        let dec = |price: &str| price.parse::<Decimal>().unwrap();
+        let scaled_inverse = |price| {
+            scale_price(
+                inverse_price(dec(price), DECIMALS, half_up).unwrap(),
+                DECIMALS,
+                half_up,
+            )
+            .unwrap()
+        };
+        assert_eq!(inverse_price(dec("4"), DECIMALS, half_up), Ok(dec("0.25")));
+        assert_eq!(scaled_inverse("3"), 333_333);
+        assert_eq!(scaled_inverse("1500000"), 1);
+        assert!(inverse_price(dec("1500000"), DECIMALS, PriceRounding::Down).is_err());
+        // Far from 1 either way: nothing to publish rather than 0 or a saturated u64.
+        assert!(
+            inverse_price(dec("3000000"), DECIMALS, half_up)
+                .unwrap_err()
+                .contains("rounds to 0")
+        );
+        // Unless the feed has the decimals for it.
+        assert!(inverse_price(dec("3000000"), 12, half_up).is_ok());
+        assert_eq!(scaled_inverse("0.000000001"), 1_000_000_000_000_000);
+        assert!(
+            inverse_price(dec("0.00000000000001"), DECIMALS, half_up)
+                .unwrap_err()
+                .contains("too large")
+        );
+        assert!(inverse_price(Decimal::ZERO, DECIMALS, half_up).is_err());
+        assert!(inverse_price(dec("-2"), DECIMALS, half_up).is_err());
+    }
+
+    #[test]
+    fn test_scale_price_rounds_decimal_digits() {
+        let scale = |price: &str, decimals, rounding| {
+            scale_price(price.parse().unwrap(), decimals, rounding)
+        };
+        assert_eq!(
+            scale("1.0000005", DECIMALS, PriceRounding::HalfUp).unwrap(),
+            1_000_001
+        );
+        assert_eq!(
+            scale("1.0000005", DECIMALS, PriceRounding::HalfEven).unwrap(),
+            1_000_000
+        );
+        assert_eq!(
+            scale("1.0000015", DECIMALS, PriceRounding::HalfEven).unwrap(),
+            1_000_002
+        );
+        assert_eq!(
+            scale("1.0000009", DECIMALS, PriceRounding::Down).unwrap(),
+            1_000_000
+        );
+        assert_eq!(
+            scale("1.0000001", DECIMALS, PriceRounding::Up).unwrap(),
+            1_000_001
+        );
+        assert_eq!(
+            scale("65000.1", DECIMALS, PriceRounding::Down).unwrap(),
+            65_000_100_000
+        );
+        // SHIB/USD and an FX pair.
+        assert_eq!(
+            scale("0.00002451", 12, PriceRounding::HalfUp).unwrap(),
+            24_510_000
+        );
+        assert_eq!(scale("1.08456", 4, PriceRounding::HalfUp).unwrap(), 10_846);
+        assert_eq!(
+            scale("1.5", 18, PriceRounding::HalfUp).unwrap(),
+            1_500_000_000_000_000_000
+        );
+        // An error rather than a 0 that looks like a price.
+        assert!(
+            scale("-1", DECIMALS, PriceRounding::HalfUp)
+                .unwrap_err()
+                .to_string()
+                .contains("negative")
+        );
+        assert!(
+            scale("65000", 18, PriceRounding::HalfUp)
+                .unwrap_err()
+                .to_string()
+                .contains("does not fit a u64")
+        );
+        assert_eq!(scale("0", DECIMALS, PriceRounding::HalfUp).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_decimals_mismatches() {
+        let state = |symbol: &str, decimals| FeedState {
+            symbol: symbol.to_string(),
+            object_id: ObjectID::from_single_byte(1),
+            version: Some(1),
+            price: None,
+            timestamp_ms: None,
+            decimals,
+            staleness_ms: None,
+            error: None,
+        };
+        let states = [
+            state("BTC/USD", Some(6)),
+            state("SHIB/USD", Some(6)),
+            state("EUR/USD", Some(4)),
+            state("SUI/USD", None),
+        ];
+        let configured = HashMap::from([("SHIB/USD", 12), ("EUR/USD", 4)]);
+        let decimals = |symbol: &str| configured.get(symbol).copied().unwrap_or(DECIMALS);
+        assert_eq!(
+            decimals_mismatches(&states, decimals),
+            ["SHIB/USD (6 on-chain, 12 configured)"]
+        );
+    }
+
+    #[test]
+    fn test_budget_with_margin() {
+        assert_eq!(budget_with_margin(2_000_000, 20.0), 2_400_000);
+        assert_eq!(budget_with_margin(2_000_000, 0.0), 2_000_000);
+        assert_eq!(budget_with_margin(2_000_000, -5.0), 2_000_000);
+        assert_eq!(budget_with_margin(GAS_BUDGET, 50.0), GAS_BUDGET);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_and_skips_permanent_failures() {
+        let policy = RetrySettings {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            jitter_ms: 50,
+            max_payload_age_ms: None,
+        };
+        let timeout = anyhow!("request timed out");
+        let delays: Vec<_> = (1..=3)
+            .map(|retry| {
+                retry_delay(&timeout, &policy, retry, 7)
+                    .unwrap()
+                    .as_millis()
+            })
+            .collect();
+        assert_eq!(delays, [107, 207, 407]);
+
+        let stale = anyhow!(
+            "Transaction needs to be rebuilt because object 0x1 version 0x5 is unavailable for consumption"
+        )
+        .context("Failed to execute update_price_batch transaction");
+        assert_eq!(retry_delay(&stale, &policy, 1, 7), Some(Duration::ZERO));
+
+        let aborted = anyhow::Error::from(Permanent("MoveAbort".to_string()))
+            .context("Failed to submit price updates");
+        assert_eq!(retry_delay(&aborted, &policy, 1, 7), None);
+    }
 
     #[tokio::test]
     async fn test_publish_flow() {
+        let sui_settings = SuiSettings::default();
+        let known_objects_file = sui_settings.known_objects_file.clone();
+        let known_objects_file = known_objects_file.as_str();
+        let publisher = SuiPublisher::connect(sui_settings)
+            .await
+            .expect("Failed to read the package's module");
         let btc_price_info_1 = PriceInfo {
             symbol: "BTC/USD_TEST_RUST_FIX_V2".to_string(),
-            price: 68000.10,
+            price: Decimal::new(6800010, 2),
             timestamp_ms: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            manual_override: false,
+            confidence: None,
         };
 
         log::debug!(
             "Test 1: Submitting first price for {}",
             btc_price_info_1.symbol
         );
-        match submit_price_update(btc_price_info_1.clone()).await {
-            Ok(digest) => log::debug!("Test 1 Succeeded. Digest: {}", digest),
+        match publisher
+            .publish(
+                vec![btc_price_info_1.clone()],
+                &mut CycleRng::from_seed(1),
+                &mut PublishTrace::new(),
+            )
+            .await
+            .remove(0)
+        {
+            Ok(submission) => log::debug!("Test 1 Succeeded: {:?}", submission),
             Err(e) => {
-                let mut known = load_known_objects().unwrap_or_default();
+                let mut known = load_known_objects(known_objects_file).unwrap_or_default();
                 if known.remove(&btc_price_info_1.symbol).is_some() {
-                    save_known_objects(&known).expect(
+                    save_known_objects(known_objects_file, &known).expect(
                         "Failed to cleanup test symbol from known_objects after Test 1 fail",
                     );
                     log::debug!(
                         "Cleaned up test symbol {} from {} after Test 1 fail",
                         btc_price_info_1.symbol,
-                        KNOWN_OBJECTS_FILENAME
+                        known_objects_file
                     );
                 }
                 panic!("Test 1 Failed: {:?}", e);
@@ -482,29 +2570,40 @@ mod tests {
 
         let btc_price_info_2 = PriceInfo {
             symbol: btc_price_info_1.symbol.clone(),
-            price: 68002.25,
+            price: Decimal::new(6800225, 2),
             timestamp_ms: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64
                 + 1000,
+            manual_override: false,
+            confidence: None,
         };
         log::debug!(
             "\nTest 2: Submitting second price for {}",
             btc_price_info_2.symbol
         );
-        match submit_price_update(btc_price_info_2).await {
-            Ok(digest) => log::debug!("Test 2 Succeeded. Digest: {}", digest),
+        match publisher
+            .publish(
+                vec![btc_price_info_2],
+                &mut CycleRng::from_seed(1),
+                &mut PublishTrace::new(),
+            )
+            .await
+            .remove(0)
+        {
+            Ok(submission) => log::debug!("Test 2 Succeeded: {:?}", submission),
             Err(e) => panic!("Test 2 Failed: {:?}", e),
         }
 
-        let mut known = load_known_objects().unwrap_or_default();
+        let mut known = load_known_objects(known_objects_file).unwrap_or_default();
         if known.remove(&btc_price_info_1.symbol).is_some() {
-            save_known_objects(&known).expect("Failed to cleanup test symbol from known_objects");
+            save_known_objects(known_objects_file, &known)
+                .expect("Failed to cleanup test symbol from known_objects");
             log::debug!(
                 "Cleaned up test symbol {} from {}",
                 btc_price_info_1.symbol,
-                KNOWN_OBJECTS_FILENAME
+                known_objects_file
             );
         }
     }
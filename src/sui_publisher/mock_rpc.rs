@@ -0,0 +1,296 @@
+//! A scripted stand-in for the fullnode behind a publish attempt. It reproduces what a busy
+//! network does to the publisher without one: versions consumed by a competing
+//! transaction, gas coins locked by another transaction, and Move aborts.
+
+use super::*;
+use std::collections::VecDeque;
+use sui_sdk::types::base_types::{ObjectDigest, SequenceNumber};
+
+/// How the next executed block ends.
+pub enum Outcome {
+    Success,
+    /// A competing transaction writes the object first, consuming the version the block was
+    /// built on.
+    Preempted(ObjectID),
+    /// The block's gas coin is locked by another transaction.
+    GasLocked,
+    /// The block executes and aborts with this code.
+    MoveAbort(u64),
+    /// The fullnode doesn't answer.
+    Timeout,
+}
+
+/// A block as executed: the object references it was built on and the coin it paid with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutedBlock {
+    pub objects: Vec<ObjectRef>,
+    pub gas_coin: ObjectRef,
+}
+
+pub struct MockRpc {
+    versions: Mutex<HashMap<ObjectID, u64>>,
+    coins: Vec<(ObjectRef, u64)>,
+    script: Mutex<VecDeque<Outcome>>,
+    executed: Mutex<Vec<ExecutedBlock>>,
+}
+
+fn object_ref(object_id: ObjectID, version: u64) -> ObjectRef {
+    (
+        object_id,
+        SequenceNumber::from_u64(version),
+        ObjectDigest::new([version as u8; 32]),
+    )
+}
+
+impl MockRpc {
+    /// `objects` at version 1 and `coins` gas coins of ample balance, executing blocks as
+    /// `script` says, then successfully.
+    pub fn new(objects: &[ObjectID], coins: u8, script: Vec<Outcome>) -> Self {
+        Self {
+            versions: Mutex::new(objects.iter().map(|id| (*id, 1)).collect()),
+            coins: (0..coins)
+                .map(|i| {
+                    let coin = object_ref(ObjectID::from_single_byte(0xc0 + i), 1);
+                    (coin, GAS_BUDGET * 10)
+                })
+                .collect(),
+            script: Mutex::new(script.into()),
+            executed: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn executed(&self) -> Vec<ExecutedBlock> {
+        self.executed.lock().unwrap().clone()
+    }
+
+    pub fn version(&self, object_id: ObjectID) -> u64 {
+        self.versions.lock().unwrap()[&object_id]
+    }
+}
+
+#[async_trait]
+impl BlockRpc for MockRpc {
+    async fn object_refs(&self, ids: &[ObjectID]) -> Result<Vec<Option<ObjectRef>>> {
+        let versions = self.versions.lock().unwrap();
+        Ok(ids
+            .iter()
+            .map(|id| versions.get(id).map(|version| object_ref(*id, *version)))
+            .collect())
+    }
+
+    async fn gas_coins(&self, _owner: SuiAddress) -> Result<Vec<(ObjectRef, u64)>> {
+        Ok(self.coins.clone())
+    }
+
+    async fn execute_block(
+        &self,
+        _updates: &[BlockUpdate<'_>],
+        objects: &[ObjectRef],
+        gas_coin: ObjectRef,
+        _trace: &mut PublishTrace,
+    ) -> Result<Executed> {
+        self.executed.lock().unwrap().push(ExecutedBlock {
+            objects: objects.to_vec(),
+            gas_coin,
+        });
+        let outcome = self
+            .script
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(Outcome::Success);
+        let mut versions = self.versions.lock().unwrap();
+        match outcome {
+            Outcome::Preempted(object_id) => {
+                *versions.get_mut(&object_id).expect("a scripted object") += 1;
+            }
+            Outcome::GasLocked => {
+                return Err(anyhow!(
+                    "Transaction is rejected as invalid by more than 1/3 of validators by \
+                     stake (non-retryable). Non-retryable errors: [ObjectLockConflict {{ \
+                     obj_ref: ({}, {}, {}) }}]",
+                    gas_coin.0,
+                    gas_coin.1,
+                    gas_coin.2
+                ));
+            }
+            Outcome::Timeout => return Err(anyhow!("Request timeout")),
+            Outcome::Success | Outcome::MoveAbort(_) => {}
+        }
+        for (object_id, version, _) in objects {
+            let current = versions[object_id];
+            if version.value() != current {
+                return Err(anyhow!(
+                    "Transaction needs to be rebuilt because object {} version {} is \
+                     unavailable for consumption, current version: {}",
+                    object_id,
+                    version,
+                    current
+                ));
+            }
+        }
+        let status = match outcome {
+            Outcome::MoveAbort(code) => SuiExecutionStatus::Failure {
+                error: format!(
+                    "MoveAbort(MoveLocation {{ module: ModuleId {{ address: {}, name: \
+                     Identifier(\"{}\") }}, function: 1, instruction: 14, function_name: \
+                     Some(\"{}\") }}, {}) in command 0",
+                    ObjectID::from_single_byte(0x42),
+                    MODULE_NAME,
+                    UPDATE_PRICE_FUNC_NAME,
+                    code
+                ),
+            },
+            _ => {
+                for (object_id, _, _) in objects {
+                    *versions.get_mut(object_id).unwrap() += 1;
+                }
+                SuiExecutionStatus::Success
+            }
+        };
+        Ok(Executed {
+            digest: format!("D{}", self.executed.lock().unwrap().len()),
+            status: Some(status),
+            gas_used_mist: 1_000_000,
+        })
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn price_object() -> ObjectID {
+        ObjectID::from_single_byte(0x11)
+    }
+
+    fn policy(max_attempts: u32, base_delay_ms: u64) -> RetrySettings {
+        RetrySettings {
+            max_attempts,
+            base_delay_ms,
+            jitter_ms: 0,
+            max_payload_age_ms: None,
+        }
+    }
+
+    async fn submit(rpc: &MockRpc, policy: &RetrySettings) -> Result<PublishReceipt> {
+        let price_info = PriceInfo {
+            symbol: "BTC/USD".to_string(),
+            price: Decimal::from(65_000),
+            timestamp_ms: clock_drift::local_now_ms() as u64,
+            manual_override: false,
+            confidence: None,
+        };
+        let block = [BlockUpdate {
+            price_info: &price_info,
+            object_id: price_object(),
+            payload: Payload::new(&price_info, 0, DECIMALS, Default::default()).unwrap(),
+        }];
+        submit_block(
+            rpc,
+            SuiAddress::ZERO,
+            policy,
+            &block,
+            &GasReservations::new(),
+            &mut CycleRng::from_seed(1),
+            &mut PublishTrace::new(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_is_retried_at_once_on_the_current_version() {
+        let rpc = MockRpc::new(
+            &[price_object()],
+            3,
+            vec![Outcome::Preempted(price_object())],
+        );
+        // A backoff would outlast the timeout.
+        let receipt =
+            tokio::time::timeout(Duration::from_secs(5), submit(&rpc, &policy(3, 60_000)))
+                .await
+                .expect("stale versions are retried without backoff")
+                .unwrap();
+        assert_eq!(receipt.digest, "D2");
+
+        let executed = rpc.executed();
+        assert_eq!(executed.len(), 2);
+        assert_eq!(executed[0].objects[0].1.value(), 1);
+        assert_eq!(executed[1].objects[0].1.value(), 2);
+        assert_eq!(rpc.version(price_object()), 3);
+    }
+
+    #[tokio::test]
+    async fn test_locked_gas_coin_is_not_paid_with_again() {
+        let rpc = MockRpc::new(
+            &[price_object()],
+            3,
+            vec![Outcome::GasLocked, Outcome::Timeout],
+        );
+        submit(&rpc, &policy(3, 1)).await.unwrap();
+        let coins: Vec<ObjectID> = rpc.executed().iter().map(|b| b.gas_coin.0).collect();
+        assert_eq!(coins.len(), 3);
+        // Each attempt pays from a coin no earlier attempt may still be spending.
+        assert!(coins[0] != coins[1] && coins[1] != coins[2] && coins[0] != coins[2]);
+        let locked = anyhow!("Non-retryable errors: [ObjectLockConflict {{ .. }}]");
+        assert!(!is_endpoint_failure(&locked));
+
+        // With every coin spent on earlier attempts, the last one fails for good.
+        let rpc = MockRpc::new(
+            &[price_object()],
+            2,
+            vec![Outcome::GasLocked, Outcome::GasLocked],
+        );
+        let err = submit(&rpc, &policy(3, 1)).await.unwrap_err();
+        assert!(err.downcast_ref::<Permanent>().is_some());
+        assert!(
+            err.to_string().contains("No unreserved gas coin"),
+            "{}",
+            err
+        );
+        assert_eq!(rpc.executed().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_move_abort_is_not_retried_and_names_its_code() {
+        let rpc = MockRpc::new(&[price_object()], 3, vec![Outcome::MoveAbort(3)]);
+        let err = submit(&rpc, &policy(3, 1)).await.unwrap_err();
+        assert!(err.downcast_ref::<Permanent>().is_some());
+        assert!(err.to_string().contains("(Move abort code 3)"), "{}", err);
+        assert_eq!(rpc.executed().len(), 1);
+        // An aborted transaction writes nothing.
+        assert_eq!(rpc.version(price_object()), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_retried_up_to_max_attempts() {
+        let rpc = MockRpc::new(
+            &[price_object()],
+            5,
+            vec![Outcome::Timeout, Outcome::Timeout, Outcome::Timeout],
+        );
+        let err = submit(&rpc, &policy(3, 1)).await.unwrap_err();
+        assert!(err.to_string().contains("Request timeout"), "{}", err);
+        assert_eq!(rpc.executed().len(), 3);
+
+        let err = submit(&MockRpc::new(&[], 1, vec![]), &policy(3, 1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not found for update"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_expired_update_is_not_attempted_again() {
+        let rpc = MockRpc::new(&[price_object()], 3, vec![Outcome::Timeout]);
+        // The backoff outlasts the payload's validity.
+        let expiring = RetrySettings {
+            max_payload_age_ms: Some(100),
+            ..policy(3, 200)
+        };
+        let err = submit(&rpc, &expiring).await.unwrap_err();
+        assert!(err.downcast_ref::<Expired>().is_some(), "{}", err);
+        assert_eq!(rpc.executed().len(), 1);
+
+        let rpc = MockRpc::new(&[price_object()], 3, vec![Outcome::Timeout]);
+        submit(&rpc, &policy(3, 1)).await.unwrap();
+    }
+}
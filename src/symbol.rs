@@ -0,0 +1,117 @@
+use anyhow::{Result, anyhow};
+use std::fmt;
+
+use crate::config::SourceKind;
+
+/// Separators accepted between base and quote; `/` is the canonical one.
+const SEPARATORS: [char; 3] = ['/', '-', '_'];
+
+/// Ticker value asking for the ticker to be derived from the symbol in the source's format.
+pub const AUTO_TICKER: &str = "auto";
+
+/// A `BASE/QUOTE` pair in canonical form: ASCII uppercase letters and digits on both sides
+/// of a single `/`. Feeds fetched from the exchanges are keyed by it, so `btc-usd` and
+/// `BTC/USD` are the same feed. Index names and the derived `<symbol>:bid` feeds are other
+/// feed names, not pairs.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol {
+    canonical: String,
+    /// Byte offset of the `/`.
+    separator: usize,
+}
+
+impl Symbol {
+    /// `raw` in canonical form: trimmed, uppercased, and with `-` or `_` as its separator
+    /// turned into `/`.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let upper = raw.trim().to_ascii_uppercase();
+        let (base, quote) = upper
+            .split_once(SEPARATORS)
+            .ok_or_else(|| anyhow!("'{}' is not a BASE/QUOTE pair", raw))?;
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        };
+        if !valid(base) || !valid(quote) {
+            return Err(anyhow!(
+                "'{}' is not a BASE/QUOTE pair of letters and digits",
+                raw
+            ));
+        }
+        Ok(Self {
+            canonical: format!("{}/{}", base, quote),
+            separator: base.len(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.canonical
+    }
+
+    pub fn base(&self) -> &str {
+        &self.canonical[..self.separator]
+    }
+
+    pub fn quote(&self) -> &str {
+        &self.canonical[self.separator + 1..]
+    }
+
+    /// The pair the other way round, e.g. `USD/BTC` for `BTC/USD`.
+    pub fn inverse(&self) -> Self {
+        Self {
+            canonical: format!("{}/{}", self.quote(), self.base()),
+            separator: self.quote().len(),
+        }
+    }
+
+    /// The pair as `kind`'s API writes it: `BTCUSDT` on Binance and Bybit, `BTC-USD` on
+    /// Coinbase and OKX.
+    pub fn ticker(&self, kind: SourceKind) -> String {
+        match kind {
+            SourceKind::Binance | SourceKind::Bybit => format!("{}{}", self.base(), self.quote()),
+            SourceKind::Coinbase | SourceKind::Okx => {
+                format!("{}-{}", self.base(), self.quote())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_canonical_form() {
+        for raw in ["BTC/USD", "btc/usd", " btc-usd ", "BTC_USD"] {
+            assert_eq!(Symbol::parse(raw).unwrap().as_str(), "BTC/USD", "{}", raw);
+        }
+        let pair = Symbol::parse("1000pepe/usdt").unwrap();
+        assert_eq!((pair.base(), pair.quote()), ("1000PEPE", "USDT"));
+        assert_eq!(pair.inverse().as_str(), "USDT/1000PEPE");
+        assert_eq!(pair.inverse().base(), "USDT");
+        assert_eq!(pair.ticker(SourceKind::Binance), "1000PEPEUSDT");
+        assert_eq!(pair.ticker(SourceKind::Coinbase), "1000PEPE-USDT");
+        assert_eq!(pair.ticker(SourceKind::Okx), "1000PEPE-USDT");
+        assert_eq!(pair.ticker(SourceKind::Bybit), "1000PEPEUSDT");
+
+        // Index names, derived feeds and anything else that is not a pair.
+        for raw in [
+            "TOP2",
+            "BTC/USD:bid",
+            "BTC/USD/EUR",
+            "/USD",
+            "BTC/",
+            "BTC/US D",
+        ] {
+            assert!(Symbol::parse(raw).is_err(), "{}", raw);
+        }
+    }
+}
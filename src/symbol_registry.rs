@@ -0,0 +1,758 @@
+// Symbols and sources are registered only through the HTTP API's admin routes.
+#![cfg_attr(not(feature = "http-api"), allow(dead_code))]
+
+use anyhow::{Context, Result, anyhow};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::chain::PriceObjects;
+use crate::clock_drift::ClockDriftTracker;
+use crate::config::{
+    AggregationMethod, ExchangeConfig, OutlierFilter, RegisteredSourceSettings, Settings,
+    SourceKind, SymbolSettings,
+};
+use crate::endpoints::EndpointSelector;
+use crate::overrides::AuditLog;
+use crate::sources::{self, PriceSource};
+use crate::symbol::Symbol;
+
+/// Version written to the registered-symbols file.
+const REGISTERED_SYMBOLS_SCHEMA_VERSION: u32 = 1;
+
+/// Version written to the registered-sources file.
+const REGISTERED_SOURCES_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RegisteredSymbolsFile {
+    schema_version: u32,
+    symbols: BTreeMap<String, SymbolSettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RegisteredSourcesFile {
+    schema_version: u32,
+    sources: BTreeMap<String, SourceRequest>,
+}
+
+/// A feed to add at runtime, as posted to `/admin/symbols`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolRequest {
+    pub symbol: String,
+    /// Exchange ticker per source, as in `symbols.<symbol>.tickers`.
+    pub tickers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub max_source_spread_pct: Option<f64>,
+    #[serde(default)]
+    pub min_liquidity_notional: Option<f64>,
+    #[serde(default)]
+    pub outlier_filter: Option<OutlierFilter>,
+    #[serde(default)]
+    pub aggregation_method: Option<AggregationMethod>,
+    #[serde(default)]
+    pub min_update_interval_ms: Option<u64>,
+    #[serde(default)]
+    pub priority: i32,
+    pub operator: String,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// A source to add at runtime, as posted to `/admin/sources`: another venue speaking the
+/// API of a built-in source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceRequest {
+    /// Name used in `symbols.<symbol>.tickers`, logs and metrics, e.g. `binance_us`.
+    pub name: String,
+    pub kind: SourceKind,
+    pub base_url: String,
+    /// Used instead of `base_url` in paper mode, as `apis.<source>.sandbox_base_url`.
+    #[serde(default)]
+    pub sandbox_base_url: Option<String>,
+    /// The source's exchange ticker per configured feed.
+    pub tickers: BTreeMap<String, String>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    pub operator: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredSource {
+    pub name: String,
+    /// The price the source quoted for each feed when its ticker was checked.
+    pub probed_prices: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredSymbol {
+    pub symbol: String,
+    pub object_id: String,
+    /// The price each source quoted when its ticker was checked.
+    pub probed_prices: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum RegisterError {
+    /// The request is invalid or a ticker could not be priced; nothing was changed.
+    Rejected(anyhow::Error),
+    /// Creating the PriceObject or saving the registration failed.
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegisterError::Rejected(e) | RegisterError::Failed(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+/// Reads the feeds registered in earlier runs; a missing file registers none.
+pub fn load_registered(path: &str) -> Result<BTreeMap<String, SymbolSettings>> {
+    if !Path::new(path).exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+    let file: RegisteredSymbolsFile =
+        serde_json::from_str(&content).context(format!("Failed to parse {}", path))?;
+    Ok(file.symbols)
+}
+
+/// Reads the sources registered in earlier runs; a missing file registers none.
+pub fn load_registered_sources(path: &str) -> Result<BTreeMap<String, SourceRequest>> {
+    if !Path::new(path).exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+    let file: RegisteredSourcesFile =
+        serde_json::from_str(&content).context(format!("Failed to parse {}", path))?;
+    Ok(file.sources)
+}
+
+/// Adds `request`'s source to `settings` and its tickers to the feeds they price.
+fn add_source(settings: &mut Settings, request: &SourceRequest) -> Result<()> {
+    let base_url = if settings.general.paper_mode {
+        request.sandbox_base_url.clone().ok_or_else(|| {
+            anyhow!(
+                "paper_mode is enabled but {} has no sandbox_base_url",
+                request.name
+            )
+        })?
+    } else {
+        request.base_url.clone()
+    };
+    for (symbol, ticker) in &request.tickers {
+        settings
+            .symbols
+            .get_mut(symbol)
+            .ok_or_else(|| anyhow!("{} is not a configured feed", symbol))?
+            .tickers
+            .insert(request.name.clone(), ticker.clone());
+    }
+    let exchange = ExchangeConfig {
+        base_url,
+        symbols: Vec::new(),
+        sandbox_base_url: request.sandbox_base_url.clone(),
+        region: None,
+        mirrors: Vec::new(),
+        min_trade_notional: None,
+        price_adjustment_bps: 0.0,
+        stream_url: None,
+        sandbox_stream_url: None,
+        rate_limit: None,
+    };
+    settings.apis.registered.insert(
+        request.name.clone(),
+        RegisteredSourceSettings {
+            kind: request.kind,
+            weight: request.weight,
+            exchange,
+        },
+    );
+    settings.resolve_tickers().map_err(|e| anyhow!("{}", e))
+}
+
+/// `settings` with the feeds and sources registered in earlier runs added, so they are
+/// fetched, aggregated and published like configured ones from startup on.
+pub fn with_registered(mut settings: Settings) -> Result<Settings> {
+    let registered = load_registered(&settings.admin.registered_symbols_file)?;
+    for (symbol, symbol_settings) in registered {
+        if settings.symbols.contains_key(&symbol) {
+            log::warn!(
+                "{} is both configured and registered; the configuration takes precedence",
+                symbol
+            );
+            continue;
+        }
+        log::info!("Loaded registered feed {}", symbol);
+        settings.symbols.insert(symbol, symbol_settings);
+    }
+    settings
+        .resolve_tickers()
+        .map_err(|e| anyhow!("Invalid registered feed: {}", e))?;
+    let sources = load_registered_sources(&settings.admin.registered_sources_file)?;
+    for (name, mut request) in sources {
+        if settings.apis.has_source(&name) {
+            log::warn!(
+                "{} is both configured and registered as a source; the configuration takes precedence",
+                name
+            );
+            continue;
+        }
+        request.tickers.retain(|symbol, _| {
+            let configured = settings.symbols.contains_key(symbol);
+            if !configured {
+                log::warn!(
+                    "Registered source {} maps {}, which is no longer a feed",
+                    name,
+                    symbol
+                );
+            }
+            configured
+        });
+        add_source(&mut settings, &request)
+            .context(format!("Invalid registered source {}", name))?;
+        log::info!("Loaded registered source {}", name);
+    }
+    Ok(settings)
+}
+
+/// The effective settings, including feeds registered since startup, and the registration
+/// of new feeds: mappings are checked by fetching each ticker, the PriceObject is created,
+/// and the feed is saved and picked up by the next cycle.
+pub struct SymbolRegistry {
+    current: RwLock<Arc<Settings>>,
+    /// Serializes registrations, so two cannot claim the same symbol.
+    registering: tokio::sync::Mutex<()>,
+    /// The pipeline's publishing lock, held while the object is created.
+    publishing: Arc<tokio::sync::Mutex<()>>,
+    /// Creates the PriceObject of each registered feed.
+    objects: Arc<dyn PriceObjects>,
+    http: reqwest::Client,
+    clock_drift: Arc<ClockDriftTracker>,
+    endpoints: Arc<EndpointSelector>,
+    audit: AuditLog,
+    /// Feeds registered through this registry or in earlier runs, as saved.
+    registered: Mutex<BTreeMap<String, SymbolSettings>>,
+    /// Sources registered through this registry or in earlier runs, as saved.
+    registered_sources: Mutex<BTreeMap<String, SourceRequest>>,
+}
+
+impl SymbolRegistry {
+    pub fn new(
+        settings: Arc<Settings>,
+        objects: Arc<dyn PriceObjects>,
+        publishing: Arc<tokio::sync::Mutex<()>>,
+        http: reqwest::Client,
+        clock_drift: Arc<ClockDriftTracker>,
+        endpoints: Arc<EndpointSelector>,
+    ) -> Result<Self> {
+        let registered = load_registered(&settings.admin.registered_symbols_file)?;
+        let registered_sources = load_registered_sources(&settings.admin.registered_sources_file)?;
+        Ok(Self {
+            audit: AuditLog::new(&settings.admin.audit_log_file),
+            current: RwLock::new(settings),
+            registering: tokio::sync::Mutex::new(()),
+            publishing,
+            objects,
+            http,
+            clock_drift,
+            endpoints,
+            registered: Mutex::new(registered),
+            registered_sources: Mutex::new(registered_sources),
+        })
+    }
+
+    /// The settings to run the next cycle with.
+    pub fn settings(&self) -> Arc<Settings> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// The feeds registered at runtime, in this run or earlier ones.
+    pub fn registered(&self) -> BTreeMap<String, SymbolSettings> {
+        self.registered.lock().unwrap().clone()
+    }
+
+    /// The sources registered at runtime, in this run or earlier ones.
+    pub fn registered_sources(&self) -> BTreeMap<String, SourceRequest> {
+        self.registered_sources.lock().unwrap().clone()
+    }
+
+    /// Checks `request` against the current settings and returns them with the feed added,
+    /// under its canonical symbol, and its settings with `"auto"` tickers resolved.
+    fn candidate(&self, request: &SymbolRequest) -> Result<(Settings, String, SymbolSettings)> {
+        let symbol = Symbol::parse(&request.symbol)?.to_string();
+        let symbol = symbol.as_str();
+        if request.operator.trim().is_empty() {
+            return Err(anyhow!("operator is required"));
+        }
+        let mut settings = (*self.settings()).clone();
+        if settings.symbols.contains_key(symbol) || settings.computed_feeds().any(|c| c == symbol) {
+            return Err(anyhow!("{} is already a configured symbol", symbol));
+        }
+        if request.tickers.is_empty() || request.tickers.values().any(|t| t.trim().is_empty()) {
+            return Err(anyhow!("tickers must map at least one source to a ticker"));
+        }
+        let positive = |value: Option<f64>| value.is_none_or(|v| v.is_finite() && v > 0.0);
+        if !positive(request.max_source_spread_pct) || !positive(request.min_liquidity_notional) {
+            return Err(anyhow!(
+                "max_source_spread_pct and min_liquidity_notional must be positive"
+            ));
+        }
+        let symbol_settings = SymbolSettings {
+            tickers: request.tickers.clone(),
+            max_source_spread_pct: request.max_source_spread_pct,
+            min_liquidity_notional: request.min_liquidity_notional,
+            outlier_filter: request.outlier_filter,
+            aggregation_method: request.aggregation_method,
+            min_update_interval_ms: request.min_update_interval_ms,
+            priority: request.priority,
+            ..Default::default()
+        };
+        settings.symbols.insert(symbol.to_string(), symbol_settings);
+        settings.resolve_tickers().map_err(|e| anyhow!("{}", e))?;
+        let symbol_settings = settings.symbols[symbol].clone();
+        if request.tickers.len() < settings.aggregation.min_sources {
+            return Err(anyhow!(
+                "tickers map {} sources but aggregation.min_sources requires {}",
+                request.tickers.len(),
+                settings.aggregation.min_sources
+            ));
+        }
+        Ok((settings, symbol.to_string(), symbol_settings))
+    }
+
+    /// Fetches every ticker of the new feed once; a source that cannot price it rejects
+    /// the registration.
+    async fn probe(
+        &self,
+        settings: &Settings,
+        tickers: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, String>> {
+        let sources =
+            sources::from_settings(settings, &self.http, &self.clock_drift, &self.endpoints);
+        let mut prices = BTreeMap::new();
+        for (name, ticker) in tickers {
+            let source = sources
+                .iter()
+                .find(|s| s.name() == name)
+                .ok_or_else(|| anyhow!("source {} is not available", name))?;
+            prices.insert(name.clone(), probe_ticker(source.as_ref(), ticker).await?);
+        }
+        Ok(prices)
+    }
+
+    fn save(&self, registered: &BTreeMap<String, SymbolSettings>) -> Result<()> {
+        let file = RegisteredSymbolsFile {
+            schema_version: REGISTERED_SYMBOLS_SCHEMA_VERSION,
+            symbols: registered.clone(),
+        };
+        write_json(&self.settings().admin.registered_symbols_file, &file)
+    }
+
+    /// Validates, probes and audits the request, creates the feed's PriceObject and saves
+    /// the feed. It is published from the next cycle on.
+    pub async fn register(
+        &self,
+        request: SymbolRequest,
+        now_ms: u64,
+    ) -> Result<RegisteredSymbol, RegisterError> {
+        let _registering = self.registering.lock().await;
+        let (settings, symbol, symbol_settings) =
+            self.candidate(&request).map_err(RegisterError::Rejected)?;
+        let probed_prices = self
+            .probe(&settings, &symbol_settings.tickers)
+            .await
+            .map_err(RegisterError::Rejected)?;
+        let details =
+            serde_json::to_value(&request).map_err(|e| RegisterError::Failed(e.into()))?;
+        self.audit
+            .append(
+                now_ms,
+                "register_symbol",
+                &symbol,
+                &request.operator,
+                details,
+            )
+            .map_err(RegisterError::Failed)?;
+
+        let object_id = {
+            let _publishing = self.publishing.lock().await;
+            self.objects
+                .ensure_price_object(&symbol)
+                .await
+                .map_err(RegisterError::Failed)?
+        };
+        let mut registered = self.registered.lock().unwrap().clone();
+        registered.insert(symbol.clone(), symbol_settings);
+        self.save(&registered).map_err(RegisterError::Failed)?;
+        *self.registered.lock().unwrap() = registered;
+        *self.current.write().unwrap() = Arc::new(settings);
+        log::warn!(
+            "Registered feed {} ({:?}) by {}; PriceObject {}",
+            symbol,
+            request.tickers,
+            request.operator,
+            object_id
+        );
+        Ok(RegisteredSymbol {
+            symbol,
+            object_id,
+            probed_prices,
+        })
+    }
+
+    /// Checks `request` against the current settings and returns them with the source
+    /// added.
+    fn candidate_source(&self, request: &SourceRequest) -> Result<Settings> {
+        let name = &request.name;
+        let valid_name = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+        if name.is_empty() || !name.chars().all(valid_name) {
+            return Err(anyhow!(
+                "name must be non-empty lowercase letters, digits and underscores"
+            ));
+        }
+        if request.operator.trim().is_empty() {
+            return Err(anyhow!("operator is required"));
+        }
+        let mut settings = (*self.settings()).clone();
+        if settings.apis.has_source(name) {
+            return Err(anyhow!("{} is already a source", name));
+        }
+        if request.tickers.is_empty() || request.tickers.values().any(|t| t.trim().is_empty()) {
+            return Err(anyhow!("tickers must map at least one feed to a ticker"));
+        }
+        if !(request.weight.is_finite() && request.weight > 0.0) {
+            return Err(anyhow!("weight must be positive"));
+        }
+        add_source(&mut settings, request)?;
+        Ok(settings)
+    }
+
+    /// Fetches every ticker of the new source once; a ticker it cannot price rejects the
+    /// registration.
+    async fn probe_source(
+        &self,
+        settings: &Settings,
+        request: &SourceRequest,
+    ) -> Result<BTreeMap<String, String>> {
+        let sources =
+            sources::from_settings(settings, &self.http, &self.clock_drift, &self.endpoints);
+        let source = sources
+            .iter()
+            .find(|s| s.name() == request.name)
+            .ok_or_else(|| anyhow!("source {} is not available", request.name))?;
+        let mut prices = BTreeMap::new();
+        for (symbol, ticker) in &request.tickers {
+            prices.insert(symbol.clone(), probe_ticker(source.as_ref(), ticker).await?);
+        }
+        Ok(prices)
+    }
+
+    /// Validates, probes and audits the request and saves the source. It is fetched and
+    /// aggregated with its weight from the next cycle on.
+    pub async fn register_source(
+        &self,
+        request: SourceRequest,
+        now_ms: u64,
+    ) -> Result<RegisteredSource, RegisterError> {
+        let _registering = self.registering.lock().await;
+        let settings = self
+            .candidate_source(&request)
+            .map_err(RegisterError::Rejected)?;
+        let probed_prices = self
+            .probe_source(&settings, &request)
+            .await
+            .map_err(RegisterError::Rejected)?;
+        let details =
+            serde_json::to_value(&request).map_err(|e| RegisterError::Failed(e.into()))?;
+        self.audit
+            .append(
+                now_ms,
+                "register_source",
+                &request.name,
+                &request.operator,
+                details,
+            )
+            .map_err(RegisterError::Failed)?;
+
+        let mut registered = self.registered_sources.lock().unwrap().clone();
+        registered.insert(request.name.clone(), request.clone());
+        let file = RegisteredSourcesFile {
+            schema_version: REGISTERED_SOURCES_SCHEMA_VERSION,
+            sources: registered.clone(),
+        };
+        write_json(&settings.admin.registered_sources_file, &file)
+            .map_err(RegisterError::Failed)?;
+        *self.registered_sources.lock().unwrap() = registered;
+        *self.current.write().unwrap() = Arc::new(settings);
+        log::warn!(
+            "Registered {:?} source {} at {} (weight {}, tickers {:?}) by {}",
+            request.kind,
+            request.name,
+            request.base_url,
+            request.weight,
+            request.tickers,
+            request.operator
+        );
+        Ok(RegisteredSource {
+            name: request.name,
+            probed_prices,
+        })
+    }
+}
+
+/// `source`'s price of `ticker`, fetched once; an error if it has no usable one.
+async fn probe_ticker(source: &dyn PriceSource, ticker: &str) -> Result<String> {
+    let name = source.name();
+    let sample = source
+        .fetch(&[ticker.to_string()])
+        .await
+        .context(format!("{} could not price {}", name, ticker))?
+        .remove(ticker)
+        .ok_or_else(|| anyhow!("{} returned no price for {}", name, ticker))?;
+    if sample.price <= Decimal::ZERO {
+        return Err(anyhow!(
+            "{} quoted an unusable price '{}' for {}",
+            name,
+            sample.price,
+            ticker
+        ));
+    }
+    Ok(sample.price.to_string())
+}
+
+fn write_json(path: &str, value: &impl Serialize) -> Result<()> {
+    if let Some(parent) = Path::new(path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)? + "\n")
+        .context(format!("Failed to write {}", path))
+}
+
+/// Stands in for the chain in tests, which never get as far as creating an object.
+#[cfg(test)]
+struct NoChain;
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl PriceObjects for NoChain {
+    async fn ensure_price_object(&self, symbol: &str) -> Result<String> {
+        Err(anyhow!(
+            "no Sui node to create the PriceObject of {}",
+            symbol
+        ))
+    }
+}
+
+#[cfg(test)]
+pub fn test_registry(audit_log_file: &str) -> SymbolRegistry {
+    use config::{Config, File, FileFormat};
+
+    let settings: Settings = Config::builder()
+        .add_source(File::from_str(
+            r#"
+[apis.binance]
+base_url = "http://127.0.0.1:9/binance"
+
+[apis.coinbase]
+base_url = "http://127.0.0.1:9/coinbase"
+
+[general]
+fetch_interval_seconds = 5
+
+[symbols."BTC/USD"]
+tickers = { binance = "BTCUSDT" }
+
+[assets."0x000000000000000000000000000000000000000000000000000000000000000b::wbtc::WBTC"]
+feed = "BTC/USD"
+decimals = 8
+"#,
+            FileFormat::Toml,
+        ))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap();
+    let mut settings = settings;
+    settings.admin.audit_log_file = audit_log_file.to_string();
+    SymbolRegistry::new(
+        Arc::new(settings),
+        Arc::new(NoChain),
+        Arc::new(tokio::sync::Mutex::new(())),
+        reqwest::Client::new(),
+        Arc::new(ClockDriftTracker::new()),
+        Arc::new(EndpointSelector::new()),
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(symbol: &str, tickers: &[(&str, &str)]) -> SymbolRequest {
+        SymbolRequest {
+            symbol: symbol.to_string(),
+            tickers: tickers
+                .iter()
+                .map(|(s, t)| (s.to_string(), t.to_string()))
+                .collect(),
+            max_source_spread_pct: None,
+            min_liquidity_notional: None,
+            outlier_filter: None,
+            aggregation_method: None,
+            min_update_interval_ms: None,
+            priority: 0,
+            operator: "alice".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_candidate_adds_feed_and_tickers() {
+        let registry = test_registry("./test_symbol_registry_audit.jsonl");
+        let (settings, symbol, symbol_settings) = registry
+            .candidate(&request(
+                "sui-usd",
+                &[("binance", "SUIUSDT"), ("coinbase", "auto")],
+            ))
+            .unwrap();
+        assert_eq!(symbol, "SUI/USD");
+        assert_eq!(symbol_settings.tickers["coinbase"], "SUI-USD");
+        assert_eq!(settings.feed_symbols(), vec!["BTC/USD", "SUI/USD"]);
+        assert_eq!(settings.apis.binance.symbols, vec!["BTCUSDT", "SUIUSDT"]);
+        assert_eq!(settings.apis.coinbase.symbols, vec!["SUI-USD"]);
+        // Nothing takes effect before the registration completes.
+        assert_eq!(registry.settings().feed_symbols(), vec!["BTC/USD"]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_registrations_are_rejected() {
+        let audit = "./test_symbol_registry_rejected.jsonl";
+        let registry = test_registry(audit);
+        let rejected = |request| async {
+            match registry.register(request, 1).await {
+                Err(RegisterError::Rejected(e)) => e.to_string(),
+                other => panic!("expected a rejection, got {:?}", other.map(|r| r.symbol)),
+            }
+        };
+        assert!(
+            rejected(request("btc-usd", &[("binance", "BTCUSDT")]))
+                .await
+                .contains("BTC/USD is already a configured symbol")
+        );
+        assert!(
+            rejected(request("SUIUSD", &[("binance", "SUIUSDT")]))
+                .await
+                .contains("not a BASE/QUOTE pair")
+        );
+        assert!(
+            rejected(request("SUI/USD", &[("kraken", "SUIUSD")]))
+                .await
+                .contains("unknown source 'kraken'")
+        );
+        assert!(rejected(request("SUI/USD", &[])).await.contains("tickers"));
+        let mut no_operator = request("SUI/USD", &[("binance", "SUIUSDT")]);
+        no_operator.operator = " ".to_string();
+        assert!(rejected(no_operator).await.contains("operator"));
+        assert!(
+            rejected(request("SUI/USD", &[("binance", "SUIUSDT")]))
+                .await
+                .contains("aggregation.min_sources requires 2")
+        );
+        // The exchanges are unreachable, so the mapping cannot be confirmed.
+        assert!(
+            rejected(request(
+                "SUI/USD",
+                &[("binance", "SUIUSDT"), ("coinbase", "SUI-USD")]
+            ))
+            .await
+            .contains("SUIUSDT")
+        );
+        assert!(!Path::new(audit).exists());
+    }
+
+    fn source_request(name: &str, tickers: &[(&str, &str)]) -> SourceRequest {
+        SourceRequest {
+            name: name.to_string(),
+            kind: SourceKind::Binance,
+            base_url: "http://127.0.0.1:9/binance_us".to_string(),
+            sandbox_base_url: None,
+            tickers: tickers
+                .iter()
+                .map(|(s, t)| (s.to_string(), t.to_string()))
+                .collect(),
+            weight: 0.5,
+            operator: "alice".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_source_registration_is_validated_and_probed() {
+        let audit = "./test_symbol_registry_sources.jsonl";
+        let registry = test_registry(audit);
+        let settings = registry
+            .candidate_source(&source_request("binance_us", &[("BTC/USD", "BTCUSD")]))
+            .unwrap();
+        assert_eq!(
+            settings.ticker("BTC/USD", "binance_us").map(String::as_str),
+            Some("BTCUSD")
+        );
+        assert_eq!(
+            settings.apis.registered["binance_us"].exchange.symbols,
+            vec!["BTCUSD"]
+        );
+        assert_eq!(settings.source_weight("binance_us"), 0.5);
+        assert_eq!(settings.source_weight("binance"), 1.0);
+        let sources = sources::from_settings(
+            &settings,
+            &registry.http,
+            &registry.clock_drift,
+            &registry.endpoints,
+        );
+        let names: Vec<&str> = sources.iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["binance", "binance_us"]);
+
+        let rejected = |request| async {
+            match registry.register_source(request, 1).await {
+                Err(RegisterError::Rejected(e)) => e.to_string(),
+                other => panic!("expected a rejection, got {:?}", other.map(|r| r.name)),
+            }
+        };
+        let btc = [("BTC/USD", "BTCUSD")];
+        assert!(
+            rejected(source_request("binance", &btc))
+                .await
+                .contains("already a source")
+        );
+        assert!(
+            rejected(source_request("Binance US", &btc))
+                .await
+                .contains("lowercase")
+        );
+        assert!(
+            rejected(source_request("binance_us", &[("DOGE/USD", "DOGEUSD")]))
+                .await
+                .contains("DOGE/USD is not a configured feed")
+        );
+        let mut weightless = source_request("binance_us", &btc);
+        weightless.weight = 0.0;
+        assert!(rejected(weightless).await.contains("weight"));
+        // The venue is unreachable, so the mapping cannot be confirmed.
+        assert!(
+            rejected(source_request("binance_us", &btc))
+                .await
+                .contains("BTCUSD")
+        );
+        assert!(!Path::new(audit).exists());
+        assert!(registry.registered_sources().is_empty());
+    }
+}
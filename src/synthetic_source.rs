@@ -0,0 +1,111 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::cycle::CycleRng;
+use crate::sources::{PriceSample, PriceSource};
+
+/// Largest relative price move between two fetches.
+const MAX_STEP: f64 = 0.002;
+
+/// Generated prices for load testing: every ticker follows its own seeded random walk, so
+/// a run with the same seed sees the same prices. Never configured for production.
+pub struct SyntheticSource {
+    symbols: Vec<String>,
+    state: Mutex<Walk>,
+}
+
+struct Walk {
+    rng: CycleRng,
+    prices: HashMap<String, f64>,
+}
+
+impl Walk {
+    /// Uniform in `[0, 1)`.
+    fn next_unit(&mut self) -> f64 {
+        (self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl SyntheticSource {
+    /// `count` tickers named `SYN0001USD`, `SYN0002USD`, ...
+    pub fn new(count: usize, seed: u64) -> Self {
+        Self {
+            symbols: (1..=count).map(ticker).collect(),
+            state: Mutex::new(Walk {
+                rng: CycleRng::from_seed(seed),
+                prices: HashMap::new(),
+            }),
+        }
+    }
+}
+
+pub fn ticker(n: usize) -> String {
+    format!("SYN{:04}USD", n)
+}
+
+#[async_trait]
+impl PriceSource for SyntheticSource {
+    fn name(&self) -> &'static str {
+        "synthetic"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    async fn fetch(&self, symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+        let mut walk = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("synthetic price state poisoned"))?;
+        let mut samples = HashMap::with_capacity(symbols.len());
+        for symbol in symbols {
+            let price = match walk.prices.get(symbol).copied() {
+                Some(price) => price * (1.0 + (walk.next_unit() * 2.0 - 1.0) * MAX_STEP),
+                // Starting levels spread over 1 to 10,000.
+                None => 10f64.powf(walk.next_unit() * 4.0),
+            };
+            walk.prices.insert(symbol.clone(), price);
+            let sample = PriceSample {
+                price: Decimal::try_from(price)?.round_dp(8),
+                timestamp_ms: Some(chrono::Utc::now().timestamp_millis()),
+            };
+            samples.insert(symbol.clone(), sample);
+        }
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::prelude::ToPrimitive;
+
+    async fn prices(source: &SyntheticSource) -> Vec<f64> {
+        let samples = source.fetch(source.symbols()).await.unwrap();
+        source
+            .symbols()
+            .iter()
+            .map(|s| samples[s].price.to_f64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_walk_is_seeded_and_bounded() {
+        let a = SyntheticSource::new(3, 7);
+        let b = SyntheticSource::new(3, 7);
+        assert_eq!(a.symbols(), ["SYN0001USD", "SYN0002USD", "SYN0003USD"]);
+
+        let first = prices(&a).await;
+        assert_eq!(first, prices(&b).await);
+        assert!(first.iter().all(|p| (1.0..=10_000.0).contains(p)));
+        let second = prices(&a).await;
+        assert_eq!(second, prices(&b).await);
+        for (before, after) in first.iter().zip(&second) {
+            assert!((after / before - 1.0).abs() <= MAX_STEP + 1e-6);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::StreamSettings;
+use crate::metrics::Metrics;
+use crate::sources::PriceSource;
+
+/// How a source's prices are read this cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// From the latest messages of its stream.
+    Stream,
+    /// By polling its REST API.
+    Rest,
+}
+
+impl Transport {
+    fn label(self) -> &'static str {
+        match self {
+            Transport::Stream => "stream",
+            Transport::Rest => "rest",
+        }
+    }
+}
+
+struct State {
+    transport: Transport,
+    /// Since when the stream has been healthy without interruption, while on REST.
+    healthy_since: Option<Instant>,
+    reconnects_seen: u64,
+    /// When the reconnects within `reconnect_window_seconds` were observed.
+    reconnects: VecDeque<Instant>,
+}
+
+/// Falls back to REST polling for a source whose stream degrades, i.e. goes without a
+/// message for `max_staleness_ms` or reconnects more than `max_reconnects` times within
+/// `reconnect_window_seconds`. Switches back once the stream has been healthy for
+/// `stable_seconds`. A stream starts on REST, since it has no prices yet.
+pub struct TransportSelector {
+    settings: StreamSettings,
+    metrics: Arc<Metrics>,
+    states: Mutex<HashMap<&'static str, State>>,
+}
+
+impl TransportSelector {
+    pub fn new(settings: StreamSettings, metrics: Arc<Metrics>) -> Self {
+        Self {
+            settings,
+            metrics,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The transport to read `source` with at `now`. Sources without a stream always poll.
+    pub fn choose(&self, source: &dyn PriceSource, now: Instant) -> Transport {
+        let Some(stream) = source.stream() else {
+            return Transport::Rest;
+        };
+        let settings = &self.settings;
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(source.name()).or_insert(State {
+            transport: Transport::Rest,
+            healthy_since: None,
+            reconnects_seen: stream.reconnects(),
+            reconnects: VecDeque::new(),
+        });
+        let reconnects = stream.reconnects();
+        for _ in state.reconnects_seen..reconnects {
+            state.reconnects.push_back(now);
+        }
+        state.reconnects_seen = reconnects;
+        let window = Duration::from_secs(settings.reconnect_window_seconds);
+        while state
+            .reconnects
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > window)
+        {
+            state.reconnects.pop_front();
+        }
+
+        let degraded = match stream.last_message_age() {
+            None => Some("no message received yet".to_string()),
+            Some(age) if age > Duration::from_millis(settings.max_staleness_ms) => {
+                Some(format!("no message for {} ms", age.as_millis()))
+            }
+            _ if state.reconnects.len() > settings.max_reconnects as usize => Some(format!(
+                "{} reconnects within {} s",
+                state.reconnects.len(),
+                settings.reconnect_window_seconds
+            )),
+            _ => None,
+        };
+        let switch_to = match (state.transport, &degraded) {
+            (Transport::Stream, Some(reason)) => {
+                log::warn!(
+                    "{} stream degraded ({}); falling back to REST polling",
+                    source.name(),
+                    reason
+                );
+                Some(Transport::Rest)
+            }
+            (Transport::Rest, Some(_)) => {
+                state.healthy_since = None;
+                None
+            }
+            (Transport::Rest, None) => {
+                let since = *state.healthy_since.get_or_insert(now);
+                (now.duration_since(since) >= Duration::from_secs(settings.stable_seconds)).then(
+                    || {
+                        log::info!(
+                            "{} stream stable for {} s; reading it instead of polling REST",
+                            source.name(),
+                            settings.stable_seconds
+                        );
+                        Transport::Stream
+                    },
+                )
+            }
+            (Transport::Stream, None) => None,
+        };
+        if let Some(transport) = switch_to {
+            state.transport = transport;
+            state.healthy_since = None;
+            self.metrics.inc_counter(
+                "oracle_source_transport_switches_total",
+                "Switches of a source between its stream and REST polling",
+                &[("source", source.name()), ("to", transport.label())],
+                1.0,
+            );
+        }
+        self.metrics.set_gauge(
+            "oracle_source_streaming",
+            "Whether a source is read from its stream (1) or polled over REST (0)",
+            &[("source", source.name())],
+            f64::from(state.transport == Transport::Stream),
+        );
+        state.transport
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{PriceSample, PriceStream};
+    use anyhow::Result;
+    use async_trait::async_trait;
+
+    #[derive(Default)]
+    struct MockStream {
+        /// Milliseconds since the last message, if any.
+        age_ms: Mutex<Option<u64>>,
+        reconnects: Mutex<u64>,
+    }
+
+    impl PriceStream for MockStream {
+        fn latest(&self, _symbols: &[String]) -> HashMap<String, PriceSample> {
+            HashMap::new()
+        }
+
+        fn last_message_age(&self) -> Option<Duration> {
+            self.age_ms.lock().unwrap().map(Duration::from_millis)
+        }
+
+        fn reconnects(&self) -> u64 {
+            *self.reconnects.lock().unwrap()
+        }
+    }
+
+    struct StreamingSource {
+        stream: MockStream,
+    }
+
+    #[async_trait]
+    impl PriceSource for StreamingSource {
+        fn name(&self) -> &'static str {
+            "streaming"
+        }
+
+        fn symbols(&self) -> &[String] {
+            &[]
+        }
+
+        async fn fetch(&self, _symbols: &[String]) -> Result<HashMap<String, PriceSample>> {
+            Ok(HashMap::new())
+        }
+
+        fn stream(&self) -> Option<&dyn PriceStream> {
+            Some(&self.stream)
+        }
+    }
+
+    #[test]
+    fn test_falls_back_to_rest_and_returns_once_stable() {
+        let settings = StreamSettings {
+            max_staleness_ms: 5_000,
+            max_reconnects: 2,
+            reconnect_window_seconds: 60,
+            stable_seconds: 30,
+        };
+        let selector = TransportSelector::new(settings, Arc::new(Metrics::new()));
+        let source = StreamingSource {
+            stream: MockStream::default(),
+        };
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        assert_eq!(selector.choose(&source, at(0)), Transport::Rest);
+
+        *source.stream.age_ms.lock().unwrap() = Some(100);
+        assert_eq!(selector.choose(&source, at(10)), Transport::Rest);
+        assert_eq!(selector.choose(&source, at(40)), Transport::Stream);
+
+        // Stale stream: back to REST, and the stability period starts over.
+        *source.stream.age_ms.lock().unwrap() = Some(6_000);
+        assert_eq!(selector.choose(&source, at(50)), Transport::Rest);
+        *source.stream.age_ms.lock().unwrap() = Some(100);
+        assert_eq!(selector.choose(&source, at(60)), Transport::Rest);
+        assert_eq!(selector.choose(&source, at(90)), Transport::Stream);
+
+        // Three reconnects within the window are too many; they age out of it.
+        *source.stream.reconnects.lock().unwrap() = 3;
+        assert_eq!(selector.choose(&source, at(100)), Transport::Rest);
+        assert_eq!(selector.choose(&source, at(150)), Transport::Rest);
+        assert_eq!(selector.choose(&source, at(161)), Transport::Rest);
+        assert_eq!(selector.choose(&source, at(191)), Transport::Stream);
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::sources::{PriceSample, PriceStream, TradeFilter};
+
+/// A trade pushed by a venue's stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamTrade {
+    pub ticker: String,
+    pub sample: PriceSample,
+    /// Base-currency size, when the venue reports it.
+    pub size: Option<f64>,
+}
+
+/// Reconnect delays double from the first to the last, and start over once a connection
+/// has lasted `STABLE_CONNECTION`.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(30);
+const STABLE_CONNECTION: Duration = Duration::from_secs(60);
+/// How often an idle connection checks whether its source is still in use.
+const IDLE_CHECK: Duration = Duration::from_secs(30);
+
+/// Latest trade price per ticker received over a source's WebSocket, filtered like the
+/// source's REST trades by its `min_trade_notional`.
+pub struct StreamCache {
+    trades: TradeFilter,
+    last_message: Mutex<Option<Instant>>,
+    reconnects: AtomicU64,
+}
+
+impl StreamCache {
+    pub fn new(min_notional: Option<f64>) -> Self {
+        Self {
+            trades: TradeFilter::new(min_notional),
+            last_message: Mutex::new(None),
+            reconnects: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_message.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn observe(&self, trade: StreamTrade) {
+        self.trades.observe(&trade.ticker, trade.sample, trade.size);
+    }
+}
+
+impl PriceStream for StreamCache {
+    fn latest(&self, symbols: &[String]) -> HashMap<String, PriceSample> {
+        symbols
+            .iter()
+            .filter_map(|ticker| Some((ticker.clone(), self.trades.latest(ticker)?)))
+            .collect()
+    }
+
+    fn last_message_age(&self) -> Option<Duration> {
+        self.last_message.lock().unwrap().map(|at| at.elapsed())
+    }
+
+    fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+}
+
+/// Keeps a connection to `url` open, sending `subscribe` on each connect if set, feeding
+/// each text message `parse` turns into trades to the returned cache, and reconnecting
+/// with backoff when it drops. The connection is closed once the cache is dropped, e.g.
+/// when the sources are rebuilt.
+pub fn spawn(
+    name: &'static str,
+    url: String,
+    subscribe: Option<String>,
+    min_notional: Option<f64>,
+    parse: fn(&str) -> Result<Vec<StreamTrade>>,
+) -> Arc<StreamCache> {
+    let cache = Arc::new(StreamCache::new(min_notional));
+    let weak = Arc::downgrade(&cache);
+    tokio::spawn(async move {
+        let mut delay = RECONNECT_DELAY_MIN;
+        loop {
+            let connected_at = Instant::now();
+            match run(name, &url, subscribe.as_deref(), &weak, parse).await {
+                Ok(()) => return,
+                Err(e) => log::warn!("{} stream disconnected: {:#}", name, e),
+            }
+            let Some(cache) = weak.upgrade() else {
+                return;
+            };
+            cache.reconnects.fetch_add(1, Ordering::Relaxed);
+            drop(cache);
+            if connected_at.elapsed() >= STABLE_CONNECTION {
+                delay = RECONNECT_DELAY_MIN;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+        }
+    });
+    cache
+}
+
+/// One connection, until it fails or, with `Ok`, the cache is gone.
+async fn run(
+    name: &str,
+    url: &str,
+    subscribe: Option<&str>,
+    cache: &Weak<StreamCache>,
+    parse: fn(&str) -> Result<Vec<StreamTrade>>,
+) -> Result<()> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .context(format!("Failed to connect to {}", url))?;
+    if let Some(subscribe) = subscribe {
+        socket
+            .send(Message::text(subscribe))
+            .await
+            .context("Failed to subscribe")?;
+    }
+    log::info!("{} stream connected", name);
+    loop {
+        let message = tokio::time::timeout(IDLE_CHECK, socket.next()).await;
+        let Some(cache) = cache.upgrade() else {
+            return Ok(());
+        };
+        let message = match message {
+            Ok(Some(message)) => message?,
+            Ok(None) => anyhow::bail!("closed by the server"),
+            Err(_) => continue,
+        };
+        cache.touch();
+        match message {
+            Message::Text(text) => match parse(&text) {
+                Ok(trades) => trades.into_iter().for_each(|trade| cache.observe(trade)),
+                Err(e) => log::debug!("Ignoring {} stream message: {:#}", name, e),
+            },
+            Message::Close(frame) => anyhow::bail!("closed by the server: {:?}", frame),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_serves_latest_filtered_trades() {
+        let cache = StreamCache::new(Some(100.0));
+        assert_eq!(cache.last_message_age(), None);
+        let trade = |price: &str, size| StreamTrade {
+            ticker: "BTCUSDT".to_string(),
+            sample: PriceSample {
+                price: price.parse().unwrap(),
+                timestamp_ms: Some(1_000),
+            },
+            size: Some(size),
+        };
+        cache.touch();
+        cache.observe(trade("65000.0", 0.01));
+        // A dust trade leaves the price at the last one of at least the minimum notional.
+        cache.observe(trade("1.0", 1.0));
+        let symbols = ["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let latest = cache.latest(&symbols);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest["BTCUSDT"].price, "65000.0".parse().unwrap());
+        assert!(cache.last_message_age().is_some());
+        assert_eq!(cache.reconnects(), 0);
+    }
+}